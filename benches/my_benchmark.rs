@@ -177,6 +177,27 @@ pub fn bench_stereo_panning_automation() {
     assert_eq!(ctx.start_rendering_sync().length(), SAMPLES);
 }
 
+pub fn bench_exponential_automation() {
+    let ctx = OfflineAudioContext::new(2, black_box(SAMPLES), SAMPLE_RATE);
+    let osc = ctx.create_oscillator();
+    let gain = ctx.create_gain();
+
+    // exponential segments are rendered for every sample of the duration
+    let segment = DURATION as f64 / 4.;
+    gain.gain().set_value_at_time(0.001, 0.);
+    gain.gain().exponential_ramp_to_value_at_time(1., segment);
+    gain.gain().set_target_at_time(0.001, segment, segment / 4.);
+    gain.gain()
+        .exponential_ramp_to_value_at_time(1., DURATION as f64);
+
+    osc.connect(&gain);
+    gain.connect(&ctx.destination());
+
+    osc.start();
+
+    assert_eq!(ctx.start_rendering_sync().length(), SAMPLES);
+}
+
 iai::main!(
     bench_ctor,
     bench_sine,
@@ -188,4 +209,5 @@ iai::main!(
     bench_buffer_src_biquad,
     bench_stereo_positional,
     bench_stereo_panning_automation,
+    bench_exponential_automation,
 );
@@ -1,18 +1,17 @@
 //! Helpers for time domain and frequency analysis
 //!
-//! These are used in the [`AnalyserNode`](crate::node::AnalyserNode)
+//! These are used in the [`AnalyserNode`](crate::node::AnalyserNode) and the signal quality
+//! measurements of the [`util`](crate::util) module
 
-use crate::render::AudioRenderQuantumChannel;
-use crate::RENDER_QUANTUM_SIZE;
+use crate::render::{AudioRenderQuantum, AudioRenderQuantumChannel};
+use crate::MAX_CHANNELS;
 use easyfft::prelude::DynRealDft;
 use easyfft::prelude::*;
 
 use std::f32::consts::PI;
 
 /// FFT size is max 32768 samples, mandated in spec
-const MAX_SAMPLES: usize = 32768;
-/// Max FFT size corresponds to 256 render quanta
-const MAX_QUANTA: usize = MAX_SAMPLES / RENDER_QUANTUM_SIZE;
+pub(crate) const MAX_SAMPLES: usize = 32768;
 
 /// Blackman window values iterator with alpha = 0.16
 pub fn generate_blackman(size: usize) -> impl Iterator<Item = f32> {
@@ -27,18 +26,45 @@ pub fn generate_blackman(size: usize) -> impl Iterator<Item = f32> {
     })
 }
 
+/// Power spectrum of a signal, windowed by a Blackman window to reduce spectral leakage
+///
+/// Returns the squared magnitude of the `samples.len() / 2 + 1` bins, from DC to the Nyquist
+/// frequency.
+pub(crate) fn power_spectrum(samples: &[f32]) -> Vec<f32> {
+    let windowed: Vec<f32> = samples
+        .iter()
+        .zip(generate_blackman(samples.len()))
+        .map(|(s, w)| s * w)
+        .collect();
+
+    windowed[..]
+        .real_fft()
+        .iter()
+        .map(|c| c.norm_sqr())
+        .collect()
+}
+
 /// Ring buffer for time domain analysis
 struct TimeAnalyser {
     buffer: Vec<AudioRenderQuantumChannel>,
-    index: u8,
-    previous_cycle_index: u8,
+    render_quantum_size: usize,
+    /// Capacity of the ring buffer, the max FFT size in render quanta
+    max_quanta: usize,
+    index: usize,
+    previous_cycle_index: usize,
 }
 
 impl TimeAnalyser {
     /// Create a new TimeAnalyser
-    fn new() -> Self {
+    ///
+    /// The render quantum size is a power of two, so the ring buffer holds exactly `MAX_SAMPLES`
+    fn new(render_quantum_size: usize) -> Self {
+        let max_quanta = MAX_SAMPLES / render_quantum_size;
+
         Self {
-            buffer: Vec::with_capacity(MAX_QUANTA),
+            buffer: Vec::with_capacity(max_quanta),
+            render_quantum_size,
+            max_quanta,
             index: 0,
             previous_cycle_index: 0,
         }
@@ -46,21 +72,37 @@ impl TimeAnalyser {
 
     /// Add samples to the ring buffer
     fn add_data(&mut self, data: AudioRenderQuantumChannel) {
-        if self.buffer.len() < 256 {
+        debug_assert_eq!(data.len(), self.render_quantum_size);
+
+        if self.buffer.len() < self.max_quanta {
             self.buffer.push(data);
         } else {
-            self.buffer[self.index as usize] = data;
+            self.buffer[self.index] = data;
         }
-        self.index = self.index.wrapping_add(1);
+        self.index = (self.index + 1) % self.max_quanta;
+    }
+
+    /// Number of render quanta added since the ring buffer index was `index`, modulo the ring
+    /// buffer capacity
+    fn quanta_since(&self, index: usize) -> usize {
+        (self.index + self.max_quanta - index) % self.max_quanta
+    }
+
+    /// Drop all samples, as if no data was ever added
+    fn reset(&mut self) {
+        self.buffer.clear();
+        self.index = 0;
+        self.previous_cycle_index = 0;
     }
 
     /// Check if we have completed a full round of `fft_size` samples
     fn check_complete_cycle(&mut self, fft_size: usize) -> bool {
         // number of buffers processed since last complete cycle
-        let processed = self.index.wrapping_sub(self.previous_cycle_index);
-        let processed_samples = processed as usize * RENDER_QUANTUM_SIZE;
+        let processed = self.quanta_since(self.previous_cycle_index);
+        let processed_samples = processed * self.render_quantum_size;
 
-        // cycle is complete when divisible by fft_size
+        // cycle is complete when divisible by fft_size, the ring buffer capacity is a multiple
+        // of any fft_size so the count can wrap around it
         if processed_samples % fft_size == 0 {
             self.previous_cycle_index = self.index;
             return true;
@@ -71,6 +113,12 @@ impl TimeAnalyser {
 
     /// Read out the ring buffer (max `fft_size` samples)
     fn get_float_time(&self, buffer: &mut [f32], fft_size: usize) {
+        self.get_float_time_before(buffer, fft_size, 0);
+    }
+
+    /// Read out the ring buffer (max `fft_size` samples), ignoring the `skip` most recent
+    /// render quanta
+    fn get_float_time_before(&self, buffer: &mut [f32], fft_size: usize, skip: usize) {
         // buffer is never empty when this call is made
         debug_assert!(!self.buffer.is_empty());
 
@@ -78,20 +126,103 @@ impl TimeAnalyser {
         let silence = self.buffer[0].silence();
 
         // order the ring buffer, and pad with silence
-        let data_chunks = self.buffer[self.index as usize..]
+        let data_chunks = self.buffer[self.index..]
             .iter()
-            .chain(self.buffer[..self.index as usize].iter())
+            .chain(self.buffer[..self.index].iter())
             .rev()
+            .skip(skip)
             .chain(std::iter::repeat(&silence));
 
-        // split the output buffer in same sized chunks
+        // split the output buffer in same sized chunks, starting from the most recent samples
         let true_size = fft_size.min(buffer.len());
-        let buf_chunks = buffer[0..true_size].chunks_mut(RENDER_QUANTUM_SIZE).rev();
+        let buf_chunks = buffer[0..true_size].rchunks_mut(self.render_quantum_size);
 
-        // copy data from internal buffer to output buffer
+        // copy data from internal buffer to output buffer, a partial chunk takes the most
+        // recent samples of its render quantum
         buf_chunks
             .zip(data_chunks)
-            .for_each(|(b, d)| b.copy_from_slice(&d[..b.len()]));
+            .for_each(|(b, d)| b.copy_from_slice(&d[d.len() - b.len()..]));
+    }
+
+    /// Read out the `buffer.len()` most recent samples of the ring buffer
+    ///
+    /// The last value of the buffer is the most recent sample. The start of the buffer is padded
+    /// with silence when it is longer than the ring buffer content.
+    fn get_time_domain(&self, buffer: &mut [f32]) {
+        let samples = self.recent_samples().chain(std::iter::repeat(&0.));
+
+        buffer
+            .iter_mut()
+            .rev()
+            .zip(samples)
+            .for_each(|(b, &s)| *b = s);
+    }
+
+    /// Iterate over the content of the ring buffer, most recent samples first
+    fn recent_samples(&self) -> impl Iterator<Item = &f32> {
+        self.buffer[self.index..]
+            .iter()
+            .chain(self.buffer[..self.index].iter())
+            .rev()
+            .flat_map(|quantum| quantum.iter().rev())
+    }
+}
+
+/// Ring buffers of the time domain data of each input channel
+pub(crate) struct MultiChannelTimeAnalyser {
+    channels: Vec<TimeAnalyser>,
+    render_quantum_size: usize,
+}
+
+impl MultiChannelTimeAnalyser {
+    /// Create a new analyser, fed with render quanta of `render_quantum_size` samples
+    pub fn new(render_quantum_size: usize) -> Self {
+        Self {
+            channels: Vec::with_capacity(MAX_CHANNELS),
+            render_quantum_size,
+        }
+    }
+
+    /// Add the channels of a render quantum to the ring buffers
+    ///
+    /// The channels missing from the input, e.g. after a channel count change, are recorded as
+    /// silence so the ring buffers stay aligned.
+    pub fn add_data(&mut self, input: &AudioRenderQuantum) {
+        let number_of_channels = input.number_of_channels();
+        while self.channels.len() < number_of_channels {
+            self.channels
+                .push(TimeAnalyser::new(self.render_quantum_size));
+        }
+
+        self.channels.iter_mut().enumerate().for_each(|(i, time)| {
+            let data = if i < number_of_channels {
+                input.channel_data(i).clone()
+            } else {
+                input.channel_data(0).silence()
+            };
+            time.add_data(data);
+        });
+    }
+
+    /// Read out the `buffer.len() / number_of_channels` most recent frames, interleaved
+    ///
+    /// The last frame of the buffer is the most recent one. The channels that were never
+    /// received, and the start of the buffer when it is longer than the ring buffers content,
+    /// are filled with silence.
+    pub fn get_interleaved(&self, buffer: &mut [f32], number_of_channels: usize) {
+        buffer.fill(0.);
+
+        self.channels
+            .iter()
+            .take(number_of_channels)
+            .enumerate()
+            .for_each(|(channel, time)| {
+                buffer
+                    .chunks_exact_mut(number_of_channels)
+                    .rev()
+                    .zip(time.recent_samples())
+                    .for_each(|(frame, &s)| frame[channel] = s);
+            });
     }
 }
 
@@ -105,11 +236,13 @@ pub(crate) struct Analyser {
     current_fft_size: usize,
     previous_block: Vec<f32>,
     blackman: Vec<f32>,
+    /// ring buffer index at the last frequency calculation
+    fft_index: usize,
 }
 
 impl Analyser {
-    /// Create a new analyser kernel
-    pub fn new(initial_fft_size: usize) -> Self {
+    /// Create a new analyser kernel, fed with render quanta of `render_quantum_size` samples
+    pub fn new(initial_fft_size: usize, render_quantum_size: usize) -> Self {
         let fft_input = vec![0.; initial_fft_size];
         let fft_output = DynRealDft::default(initial_fft_size);
         let previous_block = vec![0.; fft_output.len()];
@@ -119,12 +252,13 @@ impl Analyser {
         generate_blackman(initial_fft_size).for_each(|v| blackman.push(v));
 
         Self {
-            time: TimeAnalyser::new(),
+            time: TimeAnalyser::new(render_quantum_size),
             fft_input,
             fft_output,
             current_fft_size: initial_fft_size,
             previous_block,
             blackman,
+            fft_index: 0,
         }
     }
 
@@ -142,6 +276,26 @@ impl Analyser {
         self.time.get_float_time(buffer, fft_size);
     }
 
+    /// Read out the `buffer.len()` most recent samples of the time domain ring buffer
+    pub fn get_time_domain(&self, buffer: &mut [f32]) {
+        self.time.get_time_domain(buffer);
+    }
+
+    /// Read out the time domain window the current frequency data was calculated from
+    pub fn get_analysed_float_time(&self, buffer: &mut [f32]) {
+        let skip = self.time.quanta_since(self.fft_index);
+        self.time
+            .get_float_time_before(buffer, self.current_fft_size, skip);
+    }
+
+    /// Drop the time domain data and the smoothed frequency data, e.g. when the analysed
+    /// signal changes
+    pub fn reset(&mut self) {
+        self.time.reset();
+        self.previous_block.iter_mut().for_each(|v| *v = 0.);
+        self.fft_index = 0;
+    }
+
     /// Check if we have completed a full round of `fft_size` samples
     pub fn check_complete_cycle(&mut self, fft_size: usize) -> bool {
         self.time.check_complete_cycle(fft_size)
@@ -181,6 +335,7 @@ impl Analyser {
 
         // put time domain data in fft_input
         self.time.get_float_time(input, fft_size);
+        self.fft_index = self.time.index;
 
         // blackman window
         input
@@ -192,11 +347,20 @@ impl Analyser {
         input.real_fft_using(&mut self.fft_output);
 
         // smoothing over time
+        //
+        // A sine at any bin but DC and Nyquist is split between the positive and negative
+        // frequencies, of which the real FFT only keeps the positive half. The DC and Nyquist
+        // bins have no such counterpart, halve them so all bins report the same level for
+        // signals of the same amplitude.
+        let last = previous_block.len() - 1;
         previous_block
             .iter_mut()
             .zip(self.fft_output.iter())
-            .for_each(|(p, c)| {
-                *p = smoothing_time_constant * *p + (1. - smoothing_time_constant) * c.norm()
+            .enumerate()
+            .for_each(|(i, (p, c))| {
+                let scale = if i == 0 || i == last { 0.5 } else { 1. };
+                let magnitude = scale * c.norm();
+                *p = smoothing_time_constant * *p + (1. - smoothing_time_constant) * magnitude
             });
     }
 }
@@ -208,19 +372,22 @@ mod tests {
     use super::*;
 
     use crate::render::Alloc;
+    use crate::RENDER_QUANTUM_SIZE;
 
     #[test]
-    fn assert_index_size() {
-        // silly test to remind us MAX_QUANTA should wrap around a u8,
-        // otherwise the ring buffer index breaks
-        assert_eq!(u8::MAX as usize + 1, MAX_QUANTA);
+    fn test_ring_buffer_size() {
+        // the ring buffer holds the max fft size, whatever the render quantum size
+        for render_quantum_size in [32, 64, RENDER_QUANTUM_SIZE, 256, 4096] {
+            let analyser = TimeAnalyser::new(render_quantum_size);
+            assert_eq!(analyser.max_quanta * render_quantum_size, MAX_SAMPLES);
+        }
     }
 
     #[test]
     fn test_time_domain() {
         let alloc = Alloc::with_capacity(256);
 
-        let mut analyser = TimeAnalyser::new();
+        let mut analyser = TimeAnalyser::new(RENDER_QUANTUM_SIZE);
         let mut buffer = vec![-1.; RENDER_QUANTUM_SIZE * 5];
 
         // feed single data buffer
@@ -280,10 +447,123 @@ mod tests {
         assert_float_eq!(&buffer[..], &[257.; 32][..], abs_all <= 0.);
     }
 
+    #[test]
+    fn test_time_domain_arbitrary_length() {
+        let alloc = Alloc::with_capacity(256);
+        let mut analyser = TimeAnalyser::new(RENDER_QUANTUM_SIZE);
+
+        // less data than requested, padded with silence
+        for i in 0..2 {
+            let mut signal = alloc.silence();
+            signal
+                .iter_mut()
+                .enumerate()
+                .for_each(|(j, v)| *v = (i * RENDER_QUANTUM_SIZE + j) as f32);
+            analyser.add_data(signal);
+        }
+
+        let mut buffer = vec![-1.; 300];
+        analyser.get_time_domain(&mut buffer);
+        assert_float_eq!(&buffer[..44], &[0.; 44][..], abs_all <= 0.);
+        buffer[44..]
+            .iter()
+            .enumerate()
+            .for_each(|(i, &v)| assert_float_eq!(v, i as f32, abs <= 0.));
+
+        // wrap around the ring buffer, each sample holds its index in the stream
+        for i in 2..300 {
+            let mut signal = alloc.silence();
+            signal
+                .iter_mut()
+                .enumerate()
+                .for_each(|(j, v)| *v = (i * RENDER_QUANTUM_SIZE + j) as f32);
+            analyser.add_data(signal);
+        }
+        let total = 300 * RENDER_QUANTUM_SIZE;
+
+        // lengths that are not a multiple of the render quantum size, reading across quanta
+        for length in [100, 1000, 30000] {
+            let mut buffer = vec![-1.; length];
+            analyser.get_time_domain(&mut buffer);
+            buffer
+                .iter()
+                .enumerate()
+                .for_each(|(i, &v)| assert_float_eq!(v, (total - length + i) as f32, abs <= 0.));
+        }
+
+        // more than the ring buffer holds
+        let mut buffer = vec![-1.; MAX_SAMPLES + 10];
+        analyser.get_time_domain(&mut buffer);
+        assert_float_eq!(&buffer[..10], &[0.; 10][..], abs_all <= 0.);
+        assert_float_eq!(buffer[10], (total - MAX_SAMPLES) as f32, abs <= 0.);
+        assert_float_eq!(buffer[MAX_SAMPLES + 9], (total - 1) as f32, abs <= 0.);
+    }
+
+    #[test]
+    fn test_interleaved_time() {
+        let alloc = Alloc::with_capacity(8);
+        let mut analyser = MultiChannelTimeAnalyser::new(RENDER_QUANTUM_SIZE);
+
+        // nothing received yet
+        let mut buffer = vec![-1.; 12];
+        analyser.get_interleaved(&mut buffer, 3);
+        assert_float_eq!(&buffer[..], &[0.; 12][..], abs_all <= 0.);
+
+        // stereo, each sample holds its index in the stream, negated on the right channel
+        for i in 0..2 {
+            let mut input = AudioRenderQuantum::from(alloc.silence());
+            input.set_number_of_channels(2);
+            input
+                .channels_mut()
+                .iter_mut()
+                .enumerate()
+                .for_each(|(c, channel)| {
+                    let sign = if c == 0 { 1. } else { -1. };
+                    channel
+                        .iter_mut()
+                        .enumerate()
+                        .for_each(|(j, v)| *v = sign * (i * RENDER_QUANTUM_SIZE + j) as f32);
+                });
+            analyser.add_data(&input);
+        }
+        let total = 2 * RENDER_QUANTUM_SIZE;
+
+        // across the two render quanta, the third channel is silent
+        let mut buffer = vec![-1.; 3 * 200];
+        analyser.get_interleaved(&mut buffer, 3);
+        buffer.chunks(3).enumerate().for_each(|(i, frame)| {
+            let n = (total - 200 + i) as f32;
+            assert_float_eq!(frame, &[n, -n, 0.][..], abs_all <= 0.);
+        });
+
+        // more frames than received, padded with silence at the start
+        let mut buffer = vec![-1.; 2 * (total + 10)];
+        analyser.get_interleaved(&mut buffer, 2);
+        assert_float_eq!(&buffer[..20], &[0.; 20][..], abs_all <= 0.);
+        assert_float_eq!(&buffer[20..22], &[0., -0.][..], abs_all <= 0.);
+        assert_float_eq!(
+            &buffer[buffer.len() - 2..],
+            &[(total - 1) as f32, -((total - 1) as f32)][..],
+            abs_all <= 0.
+        );
+
+        // a mono render quantum is recorded as silence on the right channel
+        let mut input = AudioRenderQuantum::from(alloc.silence());
+        input.channel_data_mut(0).fill(1.);
+        analyser.add_data(&input);
+        let mut buffer = vec![-1.; 2 * (RENDER_QUANTUM_SIZE + 1)];
+        analyser.get_interleaved(&mut buffer, 2);
+        let n = (total - 1) as f32;
+        assert_float_eq!(&buffer[..2], &[n, -n][..], abs_all <= 0.);
+        buffer[2..].chunks(2).for_each(|frame| {
+            assert_float_eq!(frame, &[1., 0.][..], abs_all <= 0.);
+        });
+    }
+
     #[test]
     fn test_complete_cycle() {
         let alloc = Alloc::with_capacity(256);
-        let mut analyser = TimeAnalyser::new();
+        let mut analyser = TimeAnalyser::new(RENDER_QUANTUM_SIZE);
 
         // check values smaller than RENDER_QUANTUM_SIZE
         analyser.add_data(alloc.silence());
@@ -302,12 +582,64 @@ mod tests {
         assert!(!analyser.check_complete_cycle(RENDER_QUANTUM_SIZE * 2));
     }
 
+    #[test]
+    fn test_complete_cycle_render_quantum_sizes() {
+        for render_quantum_size in [64, 256] {
+            let alloc = Alloc::with_quantum_size(4, render_quantum_size);
+            let mut analyser = TimeAnalyser::new(render_quantum_size);
+            let fft_size = 2048;
+            let quanta_per_cycle = fft_size / render_quantum_size;
+
+            // wrap around the ring buffer several times, each sample holds its index in the
+            // stream
+            let count = 3 * analyser.max_quanta + 5;
+            for i in 0..count {
+                let mut signal = alloc.silence();
+                signal
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(j, v)| *v = (i * render_quantum_size + j) as f32);
+                analyser.add_data(signal);
+
+                let complete = analyser.check_complete_cycle(fft_size);
+                assert_eq!(complete, (i + 1) % quanta_per_cycle == 0);
+            }
+            let total = count * render_quantum_size;
+
+            let mut buffer = vec![-1.; fft_size];
+            analyser.get_float_time(&mut buffer, fft_size);
+            buffer
+                .iter()
+                .enumerate()
+                .for_each(|(i, &v)| assert_float_eq!(v, (total - fft_size + i) as f32, abs <= 0.));
+
+            // fft size smaller than the render quantum: a cycle per render quantum, made of
+            // its most recent samples
+            analyser.add_data(alloc.silence());
+            assert!(analyser.check_complete_cycle(32));
+            analyser.add_data(alloc.silence());
+            assert!(analyser.check_complete_cycle(32));
+
+            let mut signal = alloc.silence();
+            signal
+                .iter_mut()
+                .enumerate()
+                .for_each(|(j, v)| *v = j as f32);
+            analyser.add_data(signal);
+            let mut buffer = vec![-1.; 32];
+            analyser.get_float_time(&mut buffer, 32);
+            buffer.iter().enumerate().for_each(|(i, &v)| {
+                assert_float_eq!(v, (render_quantum_size - 32 + i) as f32, abs <= 0.)
+            });
+        }
+    }
+
     #[test]
     fn test_freq_domain() {
         let alloc = Alloc::with_capacity(256);
 
         let fft_size: usize = RENDER_QUANTUM_SIZE * 4;
-        let mut analyser = Analyser::new(fft_size);
+        let mut analyser = Analyser::new(fft_size, RENDER_QUANTUM_SIZE);
         let mut buffer = vec![-1.; fft_size];
 
         // feed single data buffer
@@ -345,6 +677,128 @@ mod tests {
         );
     }
 
+    // level in dB of each bin, for `fft_size` samples of the given signal
+    fn frequency_levels(fft_size: usize, signal: impl Fn(usize) -> f32) -> Vec<f32> {
+        let alloc = Alloc::with_capacity(256);
+        let mut analyser = Analyser::new(fft_size, RENDER_QUANTUM_SIZE);
+
+        for quantum in 0..fft_size / RENDER_QUANTUM_SIZE {
+            let mut data = alloc.silence();
+            data.iter_mut()
+                .enumerate()
+                .for_each(|(i, v)| *v = signal(quantum * RENDER_QUANTUM_SIZE + i));
+            analyser.add_data(data);
+        }
+
+        let mut buffer = vec![0.; fft_size / 2 + 1];
+        analyser.calculate_float_frequency(fft_size, 0.);
+        analyser.get_float_frequency(&mut buffer);
+        buffer
+    }
+
+    #[test]
+    fn test_freq_domain_dc_and_nyquist() {
+        let fft_size = 1024;
+        let last = fft_size / 2;
+
+        // expected level of a full scale signal: half the sum of the window (the real FFT keeps
+        // one of the two halves of a sine), minus the normalization
+        let window_sum: f32 = generate_blackman(fft_size).sum();
+        let expected = 20. * (window_sum / 2.).log10() - 20. * (fft_size as f32).sqrt().log10();
+
+        // a sine right on bin 64, for reference
+        let sine = frequency_levels(fft_size, |i| {
+            (2. * PI * 64. * i as f32 / fft_size as f32).cos()
+        });
+        assert_float_eq!(sine[64], expected, abs <= 0.01);
+
+        let dc = frequency_levels(fft_size, |_| 1.);
+        assert_float_eq!(dc[0], expected, abs <= 0.01);
+        // the energy is only in the first bins, the main lobe of the window
+        assert!(dc[3..].iter().all(|&v| v < expected - 100.));
+
+        let nyquist = frequency_levels(fft_size, |i| if i % 2 == 0 { 1. } else { -1. });
+        assert_float_eq!(nyquist[last], expected, abs <= 0.01);
+        assert!(nyquist[..last - 2].iter().all(|&v| v < expected - 100.));
+    }
+
+    #[test]
+    fn test_smoothing_step_response() {
+        let fft_size = RENDER_QUANTUM_SIZE;
+        let alloc = Alloc::with_capacity(256);
+
+        // DC level (linear) after each window, once the input steps from silence to 1
+        let step_response = |smoothing_time_constant: f32| {
+            let mut analyser = Analyser::new(fft_size, RENDER_QUANTUM_SIZE);
+            analyser.add_data(alloc.silence());
+            analyser.calculate_float_frequency(fft_size, smoothing_time_constant);
+
+            let mut buffer = vec![0.; fft_size / 2 + 1];
+            (0..5)
+                .map(|_| {
+                    let mut signal = alloc.silence();
+                    signal.copy_from_slice(&[1.; RENDER_QUANTUM_SIZE]);
+                    analyser.add_data(signal);
+                    analyser.calculate_float_frequency(fft_size, smoothing_time_constant);
+                    analyser.get_float_frequency(&mut buffer);
+                    10_f32.powf(buffer[0] / 20.)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // no smoothing, the full level is reached at once
+        let immediate = step_response(0.);
+        let full = immediate[0];
+        assert!(full > 0.);
+        assert_float_eq!(immediate[..], [full; 5][..], r2nd_all <= 1e-5);
+
+        // exponential approach of the full level
+        let smoothed = step_response(0.9);
+        let expected: Vec<f32> = (1..=5).map(|n| full * (1. - 0.9_f32.powi(n))).collect();
+        assert_float_eq!(smoothed[..], expected[..], r2nd_all <= 1e-4);
+
+        // frozen
+        let frozen = step_response(1.);
+        assert!(frozen.iter().all(|&v| v == 0.));
+    }
+
+    #[test]
+    fn test_analysed_time_domain() {
+        let alloc = Alloc::with_capacity(256);
+
+        let fft_size: usize = RENDER_QUANTUM_SIZE * 2;
+        let mut analyser = Analyser::new(fft_size, RENDER_QUANTUM_SIZE);
+        let mut buffer = vec![-1.; fft_size];
+
+        let feed = |analyser: &mut Analyser, value: f32| {
+            let mut signal = alloc.silence();
+            signal.copy_from_slice(&[value; RENDER_QUANTUM_SIZE]);
+            analyser.add_data(signal);
+        };
+
+        // no frequency data yet, the analysed window is silent
+        feed(&mut analyser, 1.);
+        analyser.get_analysed_float_time(&mut buffer);
+        assert_float_eq!(&buffer[..], &vec![0.; fft_size][..], abs_all <= 0.);
+
+        feed(&mut analyser, 2.);
+        analyser.calculate_float_frequency(fft_size, 0.);
+        feed(&mut analyser, 3.);
+
+        // the window of the frequency calculation, not the most recent data
+        analyser.get_analysed_float_time(&mut buffer);
+        assert_float_eq!(
+            &buffer[..RENDER_QUANTUM_SIZE],
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            &buffer[RENDER_QUANTUM_SIZE..],
+            &[2.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
     #[test]
     fn test_blackman() {
         let values: Vec<f32> = generate_blackman(2048).collect();
@@ -1,7 +1,9 @@
 //! General purpose audio signal data structures
+use std::error::Error;
 use std::sync::Arc;
 
 use crate::render::AudioRenderQuantum;
+use crate::resampler::{ResampleQuality, SincResampler};
 use crate::{
     assert_valid_channel_number, assert_valid_number_of_channels, assert_valid_sample_rate,
 };
@@ -236,6 +238,171 @@ impl AudioBuffer {
         self.channel_data_mut(channel_number).as_mut_slice()
     }
 
+    /// Convert interleaved samples (`[l0, r0, l1, r1, ...]`) to an AudioBuffer
+    ///
+    /// The samples are copied as is, no resampling or conversion is applied.
+    ///
+    /// This function is not part of the Web Audio API specification.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the length of `data` is not a multiple of `number_of_channels`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if:
+    /// - the given sample rate is zero
+    /// - the given number of channels is outside the [1, 32] range,
+    ///   32 being defined by the MAX_CHANNELS constant.
+    pub fn from_interleaved(
+        data: &[f32],
+        number_of_channels: usize,
+        sample_rate: f32,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        assert_valid_sample_rate(sample_rate);
+        assert_valid_number_of_channels(number_of_channels);
+
+        if !data.len().is_multiple_of(number_of_channels) {
+            return Err(format!(
+                "IndexSizeError - interleaved data length ({}) is not a multiple of the number of channels ({})",
+                data.len(),
+                number_of_channels
+            )
+            .into());
+        }
+
+        let channels = (0..number_of_channels)
+            .map(|c| {
+                let samples = data
+                    .iter()
+                    .skip(c)
+                    .step_by(number_of_channels)
+                    .copied()
+                    .collect();
+                ChannelData::from(samples)
+            })
+            .collect();
+
+        Ok(Self::from_channels(channels, sample_rate))
+    }
+
+    /// Copy the samples of all channels into a single interleaved `Vec` (`[l0, r0, l1, r1, ...]`)
+    ///
+    /// This function is not part of the Web Audio API specification.
+    pub fn to_interleaved(&self) -> Vec<f32> {
+        let number_of_channels = self.number_of_channels();
+        let mut interleaved = vec![0.; self.length() * number_of_channels];
+
+        self.channels.iter().enumerate().for_each(|(c, channel)| {
+            interleaved
+                .iter_mut()
+                .skip(c)
+                .step_by(number_of_channels)
+                .zip(channel.as_slice())
+                .for_each(|(o, i)| *o = *i);
+        });
+
+        interleaved
+    }
+
+    /// Create a copy of this buffer resampled to the given sample rate
+    ///
+    /// The samples are converted with a high quality windowed-sinc resampler (see
+    /// [`ResampleQuality::High`]), which may delay the output by up to half a sample. The number
+    /// of channels is preserved, the length is scaled by the ratio of the sample rates and
+    /// rounded up. If the sample rates are equal, this is a cheap clone of the buffer.
+    ///
+    /// Resampling a buffer ahead of time to the sample rate of the context avoids resampling
+    /// it on the render thread.
+    ///
+    /// This function is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if:
+    /// - the given sample rate is zero
+    /// - the resampled buffer would be too large to fit in memory
+    #[must_use]
+    pub fn resample(&self, target_sample_rate: f32) -> Self {
+        let mut buffer = self.clone();
+        buffer.resample_in_place_with_quality(target_sample_rate, ResampleQuality::High);
+        buffer
+    }
+
+    /// Create a copy of the frames `[start_frame, end_frame)` of this buffer
+    ///
+    /// The number of channels and the sample rate are preserved.
+    ///
+    /// This function is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `start_frame` is greater than `end_frame`, or if
+    /// `end_frame` is greater than the length of the buffer
+    #[must_use]
+    pub fn slice(&self, start_frame: usize, end_frame: usize) -> Self {
+        if start_frame > end_frame || end_frame > self.length() {
+            panic!(
+                "IndexSizeError - slice [{}, {}) is out of range for a buffer of length {}",
+                start_frame,
+                end_frame,
+                self.length()
+            );
+        }
+
+        let channels = self
+            .channels
+            .iter()
+            .map(|channel| ChannelData::from(channel.as_slice()[start_frame..end_frame].to_vec()))
+            .collect();
+
+        Self::from_channels(channels, self.sample_rate)
+    }
+
+    /// Join the given buffers one after the other into a new buffer
+    ///
+    /// The samples are copied as is, without any crossfade at the junctions.
+    ///
+    /// This function is not part of the Web Audio API specification.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `buffers` is empty, or if the buffers do not all have the same
+    /// number of channels and sample rate.
+    pub fn concat(buffers: &[&AudioBuffer]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let first = match buffers.first() {
+            Some(first) => first,
+            None => return Err("NotSupportedError - no buffers to concatenate".into()),
+        };
+
+        if let Some(other) = buffers.iter().find(|b| {
+            b.number_of_channels() != first.number_of_channels()
+                || b.sample_rate() != first.sample_rate()
+        }) {
+            return Err(format!(
+                "NotSupportedError - cannot concatenate a buffer of {} channels at {}Hz with a buffer of {} channels at {}Hz",
+                first.number_of_channels(),
+                first.sample_rate(),
+                other.number_of_channels(),
+                other.sample_rate()
+            )
+            .into());
+        }
+
+        let length = buffers.iter().map(|b| b.length()).sum();
+        let channels = (0..first.number_of_channels())
+            .map(|c| {
+                let mut samples = Vec::with_capacity(length);
+                buffers
+                    .iter()
+                    .for_each(|b| samples.extend_from_slice(b.get_channel_data(c)));
+                ChannelData::from(samples)
+            })
+            .collect();
+
+        Ok(Self::from_channels(channels, first.sample_rate))
+    }
+
     /// Create a multi-channel audiobuffer directly from `ChannelData`s.
     // @todo - remove in favor of `AudioBuffer::from`
     pub(crate) fn from_channels(channels: Vec<ChannelData>, sample_rate: f32) -> Self {
@@ -322,7 +489,24 @@ impl AudioBuffer {
     ///
     /// This function will panic if:
     /// - the given sample rate is zero
-    pub(crate) fn resample(&mut self, sample_rate: f32) {
+    pub(crate) fn resample_in_place(&mut self, sample_rate: f32) {
+        self.resample_in_place_with_quality(sample_rate, ResampleQuality::Low);
+    }
+
+    /// Resample to the desired sample rate, with the given quality. See [`Self::resample_in_place`] for
+    /// the `ResampleQuality::Low` case, the other qualities use a windowed-sinc resampler
+    /// which may delay the output by up to half a sample.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if:
+    /// - the given sample rate is zero
+    /// - the resampled buffer would be too large to fit in memory
+    pub(crate) fn resample_in_place_with_quality(
+        &mut self,
+        sample_rate: f32,
+        quality: ResampleQuality,
+    ) {
         assert_valid_sample_rate(sample_rate);
 
         // if requested sample rate is very similar, do not resample
@@ -337,11 +521,18 @@ impl AudioBuffer {
             return;
         }
 
-        let source_sr = self.sample_rate as f64;
-        let target_sr = sample_rate as f64;
-        let ratio = target_sr / source_sr;
+        let target_length = resampled_length(self.length(), self.sample_rate, sample_rate);
+
+        if let Some(resampler) = SincResampler::new(self.sample_rate, sample_rate, quality) {
+            self.channels.iter_mut().for_each(|channel_data| {
+                channel_data.data = Arc::new(resampler.process(channel_data.as_slice()));
+            });
+
+            self.sample_rate = sample_rate;
+            return;
+        }
+
         let source_length = self.length();
-        let target_length = (self.length() as f64 * ratio).ceil() as usize;
 
         let num_channels = self.number_of_channels();
         let mut resampled = Vec::<Vec<f32>>::with_capacity(num_channels);
@@ -377,6 +568,31 @@ impl AudioBuffer {
     }
 }
 
+/// Number of frames of a buffer of `length` frames resampled from `source_sample_rate` to
+/// `target_sample_rate`, rounded up
+///
+/// # Panics
+///
+/// This function will panic if the resampled channels would not fit in memory
+#[track_caller]
+fn resampled_length(length: usize, source_sample_rate: f32, target_sample_rate: f32) -> usize {
+    // computed in floating point, so extreme ratios cannot overflow
+    let target_length =
+        (length as f64 * f64::from(target_sample_rate) / f64::from(source_sample_rate)).ceil();
+    // the largest possible allocation is `isize::MAX` bytes
+    let max_length = (isize::MAX as usize / std::mem::size_of::<f32>()) as f64;
+
+    assert!(
+        target_length <= max_length,
+        "RangeError - resampling {} frames from {}Hz to {}Hz exceeds the maximum buffer length",
+        length,
+        source_sample_rate,
+        target_sample_rate
+    );
+
+    target_length as usize
+}
+
 /// Single channel audio samples, basically wraps a `Arc<Vec<f32>>`
 ///
 /// ChannelData has copy-on-write semantics, so it is cheap to clone.
@@ -441,6 +657,47 @@ mod tests {
         assert_float_eq!(audio_buffer.duration(), 2., abs <= 0.);
     }
 
+    #[test]
+    fn test_interleaved_round_trip() {
+        let left: Vec<f32> = (0..100).map(|i| (i as f32 * 0.1).sin()).collect();
+        let right: Vec<f32> = (0..100).map(|i| -(i as f32) / 7.).collect();
+        let center = vec![f32::MIN_POSITIVE; 100];
+        let buffer = AudioBuffer::from(vec![left, right, center], 44100.);
+
+        let interleaved = buffer.to_interleaved();
+        assert_eq!(interleaved.len(), 300);
+        assert_eq!(interleaved[0], buffer.get_channel_data(0)[0]);
+        assert_eq!(interleaved[4], buffer.get_channel_data(1)[1]);
+        assert_eq!(interleaved[8], buffer.get_channel_data(2)[2]);
+
+        let result = AudioBuffer::from_interleaved(&interleaved, 3, 44100.).unwrap();
+        assert_eq!(result.number_of_channels(), 3);
+        assert_eq!(result.length(), 100);
+        assert_float_eq!(result.sample_rate(), 44100., abs <= 0.);
+        for c in 0..3 {
+            assert_eq!(result.get_channel_data(c), buffer.get_channel_data(c));
+        }
+    }
+
+    #[test]
+    fn test_from_interleaved() {
+        let buffer = AudioBuffer::from_interleaved(&[1., 2., 3., 4., 5., 6.], 2, 8000.).unwrap();
+        assert_eq!(buffer.get_channel_data(0), &[1., 3., 5.]);
+        assert_eq!(buffer.get_channel_data(1), &[2., 4., 6.]);
+        assert_eq!(buffer.to_interleaved(), vec![1., 2., 3., 4., 5., 6.]);
+
+        let empty = AudioBuffer::from_interleaved(&[], 2, 8000.).unwrap();
+        assert_eq!(empty.number_of_channels(), 2);
+        assert_eq!(empty.length(), 0);
+        assert!(empty.to_interleaved().is_empty());
+    }
+
+    #[test]
+    fn test_from_interleaved_invalid_length() {
+        let result = AudioBuffer::from_interleaved(&[1., 2., 3.], 2, 8000.);
+        assert!(result.is_err());
+    }
+
     #[test]
     #[should_panic]
     fn test_zero_channels() {
@@ -713,7 +970,7 @@ mod tests {
     fn test_resample_to_zero_hertz() {
         let channel = ChannelData::from(vec![1., 2., 3., 4., 5.]);
         let mut buffer = AudioBuffer::from_channels(vec![channel], 48000.);
-        buffer.resample(0.);
+        buffer.resample_in_place(0.);
     }
 
     #[test]
@@ -724,7 +981,7 @@ mod tests {
             sample_rate: 48000.,
         };
         let mut buffer = AudioBuffer::new(options);
-        buffer.resample(48000.);
+        buffer.resample_in_place(48000.);
 
         assert_eq!(buffer.length(), 0);
         assert_float_eq!(buffer.sample_rate, 48000., abs_all <= 0.);
@@ -734,7 +991,7 @@ mod tests {
     fn test_upsample() {
         let channel = ChannelData::from(vec![1., 2., 3., 4., 5.]);
         let mut buffer = AudioBuffer::from_channels(vec![channel], 48000.);
-        buffer.resample(96000.); // double
+        buffer.resample_in_place(96000.); // double
 
         let mut expected = [0.; 10];
         let incr = 4. / 9.; // (5 - 1) / (10 - 1)
@@ -756,7 +1013,7 @@ mod tests {
     fn test_downsample() {
         let channel = ChannelData::from(vec![1., 2., 3., 4., 5.]);
         let mut buffer = AudioBuffer::from_channels(vec![channel], 96000.);
-        buffer.resample(48000.); // half
+        buffer.resample_in_place(48000.); // half
 
         assert_float_eq!(
             buffer.channel_data(0).as_slice(),
@@ -786,7 +1043,7 @@ mod tests {
             let right_chan = ChannelData::from(right);
             let mut buffer =
                 AudioBuffer::from_channels(vec![left_chan, right_chan], source_sr as f32);
-            buffer.resample(target_sr as f32);
+            buffer.resample_in_place(target_sr as f32);
 
             let mut expected_left = vec![];
             let mut expected_right = vec![];
@@ -812,4 +1069,156 @@ mod tests {
             assert_float_eq!(buffer.sample_rate, target_sr as f32, abs_all <= 0.);
         });
     }
+
+    #[test]
+    fn test_resample() {
+        let source_sr = 44_100;
+        let target_sr = 48_000;
+        let frequency = 1000.;
+
+        let sine: Vec<f32> = (0..source_sr)
+            .map(|i| (2. * PI * frequency * i as f32 / source_sr as f32).sin())
+            .collect();
+        let silence = vec![0.; source_sr];
+        let buffer = AudioBuffer::from(vec![sine, silence], source_sr as f32);
+
+        let resampled = buffer.resample(target_sr as f32);
+
+        // the source buffer is left untouched
+        assert_eq!(buffer.length(), source_sr);
+        assert_float_eq!(buffer.sample_rate(), source_sr as f32, abs <= 0.);
+
+        assert_eq!(resampled.number_of_channels(), 2);
+        assert_eq!(resampled.length(), target_sr);
+        assert_float_eq!(resampled.sample_rate(), target_sr as f32, abs <= 0.);
+
+        // the pitch is preserved, 1 Hz bins
+        let spectrum = crate::analysis::power_spectrum(resampled.get_channel_data(0));
+        let peak = spectrum
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+            .unwrap();
+        assert_eq!(peak, 1000);
+
+        // away from the edges, the output matches a sine sampled at the target rate, up to the
+        // latency of half a source sample at most
+        let output = resampled.get_channel_data(0);
+        let max_error = (1000..47_000)
+            .map(|i| {
+                let expected = (2. * PI * frequency * i as f32 / target_sr as f32).sin();
+                (output[i] - expected).abs()
+            })
+            .fold(0., f32::max);
+        let latency_error = 2. * PI * frequency / (2. * source_sr as f32);
+        assert!(max_error <= latency_error, "{}", max_error);
+
+        assert_float_eq!(
+            resampled.get_channel_data(1),
+            &[0.; 48_000][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_resample_same_sample_rate() {
+        let buffer = AudioBuffer::from(vec![vec![1., 2., 3.]], 48000.);
+        let resampled = buffer.resample(48000.);
+
+        assert_eq!(resampled.length(), 3);
+        assert_float_eq!(resampled.sample_rate(), 48000., abs <= 0.);
+        // the channels are shared, not copied
+        assert!(Arc::ptr_eq(
+            &buffer.channels()[0].data,
+            &resampled.channels()[0].data
+        ));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_resample_too_large() {
+        let buffer = AudioBuffer::from(vec![vec![0.; 1000]], 48000.);
+        let _ = buffer.resample(f32::MAX);
+    }
+
+    #[test]
+    fn test_slice() {
+        let ramp: Vec<f32> = (0..100).map(|i| i as f32).collect();
+        let reversed: Vec<f32> = ramp.iter().rev().copied().collect();
+        let buffer = AudioBuffer::from(vec![ramp, reversed], 44100.);
+
+        let slice = buffer.slice(10, 30);
+        assert_eq!(slice.length(), 20);
+        assert_eq!(slice.number_of_channels(), 2);
+        assert_float_eq!(slice.sample_rate(), 44100., abs <= 0.);
+        let expected: Vec<f32> = (10..30).map(|i| i as f32).collect();
+        assert_float_eq!(slice.get_channel_data(0), &expected[..], abs_all <= 0.);
+        let expected: Vec<f32> = (70..90).rev().map(|i| i as f32).collect();
+        assert_float_eq!(slice.get_channel_data(1), &expected[..], abs_all <= 0.);
+
+        // empty and full slices
+        assert_eq!(buffer.slice(100, 100).length(), 0);
+        assert_float_eq!(
+            buffer.slice(0, 100).get_channel_data(0),
+            buffer.get_channel_data(0),
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_slice_out_of_range() {
+        let buffer = AudioBuffer::from(vec![vec![0.; 10]], 44100.);
+        let _ = buffer.slice(5, 11);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_slice_inverted() {
+        let buffer = AudioBuffer::from(vec![vec![0.; 10]], 44100.);
+        let _ = buffer.slice(6, 5);
+    }
+
+    #[test]
+    fn test_concat_buffers() {
+        let first = AudioBuffer::from(vec![vec![1., 2., 3.], vec![-1., -2., -3.]], 44100.);
+        let second = AudioBuffer::from(vec![vec![4., 5.], vec![-4., -5.]], 44100.);
+
+        let result = AudioBuffer::concat(&[&first, &second, &first]).unwrap();
+        assert_eq!(result.length(), 8);
+        assert_eq!(result.number_of_channels(), 2);
+        assert_float_eq!(result.sample_rate(), 44100., abs <= 0.);
+        assert_float_eq!(
+            result.get_channel_data(0),
+            &[1., 2., 3., 4., 5., 1., 2., 3.][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            result.get_channel_data(1),
+            &[-1., -2., -3., -4., -5., -1., -2., -3.][..],
+            abs_all <= 0.
+        );
+
+        // slices join back to the original buffer
+        let ramp: Vec<f32> = (0..100).map(|i| i as f32).collect();
+        let buffer = AudioBuffer::from(vec![ramp], 44100.);
+        let joined = AudioBuffer::concat(&[&buffer.slice(0, 40), &buffer.slice(40, 100)]).unwrap();
+        assert_float_eq!(
+            joined.get_channel_data(0),
+            buffer.get_channel_data(0),
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_concat_mismatch() {
+        let mono = AudioBuffer::from(vec![vec![0.; 10]], 44100.);
+        let stereo = AudioBuffer::from(vec![vec![0.; 10]; 2], 44100.);
+        let other_rate = AudioBuffer::from(vec![vec![0.; 10]], 48000.);
+
+        assert!(AudioBuffer::concat(&[&mono, &stereo]).is_err());
+        assert!(AudioBuffer::concat(&[&mono, &other_rate]).is_err());
+        assert!(AudioBuffer::concat(&[]).is_err());
+    }
 }
@@ -2,14 +2,15 @@
 
 use crate::buffer::{AudioBuffer, AudioBufferOptions};
 use crate::context::{
-    AudioContextRegistration, AudioContextState, AudioParamId, ConcreteBaseAudioContext,
+    AudioContextRegistration, AudioContextState, AudioParamId, ConcreteBaseAudioContext, Patch,
     DESTINATION_NODE_ID,
 };
 use crate::media::MediaDecoder;
-use crate::node::{AudioNode, ChannelConfigOptions};
+use crate::node::{AudioNode, AudioScheduledSourceNode, ChannelConfigOptions};
 use crate::param::AudioParamDescriptor;
 use crate::periodic_wave::{PeriodicWave, PeriodicWaveOptions};
 use crate::render::AudioProcessor;
+use crate::resampler::ResampleQuality;
 use crate::{node, AudioListener};
 
 /// The interface representing an audio-processing graph built from audio modules linked together,
@@ -79,19 +80,60 @@ pub trait BaseAudioContext {
         &self,
         input: R,
     ) -> Result<AudioBuffer, Box<dyn std::error::Error + Send + Sync>> {
-        // Set up a media decoder, consume the stream in full and construct a single buffer out of it
-        let mut buffer = MediaDecoder::try_new(input)?
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .reduce(|mut accum, item| {
-                accum.extend(&item);
-                accum
-            })
-            // if there are no samples decoded, return an empty buffer
-            .unwrap_or_else(|| AudioBuffer::from(vec![vec![]], self.sample_rate()));
+        self.decode_audio_data_sync_with_quality(input, ResampleQuality::default())
+    }
+
+    /// Decode an [`AudioBuffer`] from a given input stream, with the given quality for the
+    /// conversion to the sample rate of the context.
+    ///
+    /// When the sample rate of the input differs from the sample rate of the context, the decoded
+    /// data is resampled. [`decode_audio_data_sync`](Self::decode_audio_data_sync) uses the
+    /// windowed-sinc [`ResampleQuality::Medium`]. [`ResampleQuality::Low`] opts out of it for a
+    /// faster linear interpolation, which attenuates the high frequencies and lets aliasing
+    /// through.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an Error in various cases (IO, mime sniffing, decoding).
+    fn decode_audio_data_sync_with_quality<R: std::io::Read + Send + Sync + 'static>(
+        &self,
+        input: R,
+        quality: ResampleQuality,
+    ) -> Result<AudioBuffer, Box<dyn std::error::Error + Send + Sync>> {
+        let mut buffer = decode_audio_data(input, self.sample_rate())?;
 
         // resample to desired rate (no-op if already matching)
-        buffer.resample(self.sample_rate());
+        buffer.resample_in_place_with_quality(self.sample_rate(), quality);
+
+        Ok(buffer)
+    }
+
+    /// Decode an [`AudioBuffer`] from a given input stream, guaranteeing that no resampling
+    /// happened
+    ///
+    /// Contrary to [`decode_audio_data_sync`](Self::decode_audio_data_sync), the decoded data is
+    /// returned untouched, which is useful for bit-exact testing.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an Error in various cases (IO, mime sniffing, decoding), or if the
+    /// sample rate of the input differs from the sample rate of the context.
+    fn decode_audio_data_exact<R: std::io::Read + Send + Sync + 'static>(
+        &self,
+        input: R,
+    ) -> Result<AudioBuffer, Box<dyn std::error::Error + Send + Sync>> {
+        let buffer = decode_audio_data(input, self.sample_rate())?;
+
+        if buffer.sample_rate() != self.sample_rate() {
+            return Err(format!(
+                "NotSupportedError - sample rate of the input ({}) differs from the sample rate of the context ({})",
+                buffer.sample_rate(),
+                self.sample_rate()
+            )
+            .into());
+        }
 
         Ok(buffer)
     }
@@ -123,18 +165,36 @@ pub trait BaseAudioContext {
         node::AnalyserNode::new(self.base(), node::AnalyserOptions::default())
     }
 
+    /// Creates a `BalanceNode` to attenuate one side of a stereo output
+    #[must_use]
+    fn create_balance(&self) -> node::BalanceNode {
+        node::BalanceNode::new(self.base(), node::BalanceOptions::default())
+    }
+
     /// Creates an `BiquadFilterNode` which implements a second order filter
     #[must_use]
     fn create_biquad_filter(&self) -> node::BiquadFilterNode {
         node::BiquadFilterNode::new(self.base(), node::BiquadFilterOptions::default())
     }
 
+    /// Creates a `BitCrusherNode`, reducing the sample rate and the bit depth of the signal
+    #[must_use]
+    fn create_bit_crusher(&self) -> node::BitCrusherNode {
+        node::BitCrusherNode::new(self.base(), node::BitCrusherOptions::default())
+    }
+
     /// Creates an `AudioBufferSourceNode`
     #[must_use]
     fn create_buffer_source(&self) -> node::AudioBufferSourceNode {
         node::AudioBufferSourceNode::new(self.base(), node::AudioBufferSourceOptions::default())
     }
 
+    /// Creates a `CombFilterNode`, mixing its input with delayed copies of its input and output
+    #[must_use]
+    fn create_comb_filter(&self) -> node::CombFilterNode {
+        node::CombFilterNode::new(self.base(), node::CombFilterOptions::default())
+    }
+
     /// Creates an `ConstantSourceNode`, a source representing a constant value
     #[must_use]
     fn create_constant_source(&self) -> node::ConstantSourceNode {
@@ -167,6 +227,21 @@ pub trait BaseAudioContext {
         node::ChannelSplitterNode::new(self.base(), opts)
     }
 
+    /// Creates a `CrossoverNode`, splitting the audio signal into frequency bands
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `frequencies` holds less than 1 or more than 4 values, or is not strictly
+    /// increasing
+    #[must_use]
+    fn create_crossover(&self, frequencies: Vec<f32>) -> node::CrossoverNode {
+        let opts = node::CrossoverOptions {
+            frequencies,
+            ..node::CrossoverOptions::default()
+        };
+        node::CrossoverNode::new(self.base(), opts)
+    }
+
     /// Creates a `DelayNode`, delaying the audio signal
     #[must_use]
     fn create_delay(&self, max_delay_time: f64) -> node::DelayNode {
@@ -183,12 +258,50 @@ pub trait BaseAudioContext {
         node::DynamicsCompressorNode::new(self.base(), node::DynamicsCompressorOptions::default())
     }
 
+    /// Creates a `FftEqNode`, a linear-phase equalizer applying an arbitrary magnitude response
+    #[must_use]
+    fn create_fft_eq(&self) -> node::FftEqNode {
+        node::FftEqNode::new(self.base(), node::FftEqOptions::default())
+    }
+
+    /// Creates a `FilePlayerNode`, a source playing back an `AudioBuffer` with the transport
+    /// controls of a media element
+    #[must_use]
+    fn create_file_player(&self) -> node::FilePlayerNode {
+        node::FilePlayerNode::new(self.base(), node::FilePlayerOptions::default())
+    }
+
+    /// Creates a `FilteredNoiseNode`, a source generating band-pass filtered white noise
+    #[must_use]
+    fn create_filtered_noise(&self) -> node::FilteredNoiseNode {
+        node::FilteredNoiseNode::new(self.base(), node::FilteredNoiseOptions::default())
+    }
+
+    /// Creates a `FrequencyShifterNode`, adding a constant offset to all the frequencies of the
+    /// audio signal
+    #[must_use]
+    fn create_frequency_shifter(&self) -> node::FrequencyShifterNode {
+        node::FrequencyShifterNode::new(self.base(), node::FrequencyShifterOptions::default())
+    }
+
     /// Creates an `GainNode`, to control audio volume
     #[must_use]
     fn create_gain(&self) -> node::GainNode {
         node::GainNode::new(self.base(), node::GainOptions::default())
     }
 
+    /// Creates a `GateNode`, a noise gate attenuating the quiet parts of the audio signal
+    #[must_use]
+    fn create_gate(&self) -> node::GateNode {
+        node::GateNode::new(self.base(), node::GateOptions::default())
+    }
+
+    /// Creates a `HaasNode`, widening the stereo image by delaying one channel
+    #[must_use]
+    fn create_haas(&self) -> node::HaasNode {
+        node::HaasNode::new(self.base(), node::HaasOptions::default())
+    }
+
     /// Creates an `IirFilterNode`
     ///
     /// # Arguments
@@ -207,6 +320,18 @@ pub trait BaseAudioContext {
         node::IIRFilterNode::new(self.base(), options)
     }
 
+    /// Creates a `LfoNode`, a low frequency oscillator to modulate `AudioParam`s
+    #[must_use]
+    fn create_lfo(&self) -> node::LfoNode {
+        node::LfoNode::new(self.base(), node::LfoOptions::default())
+    }
+
+    /// Creates a `NoiseSourceNode`, a source generating white, pink or brown noise
+    #[must_use]
+    fn create_noise_source(&self) -> node::NoiseSourceNode {
+        node::NoiseSourceNode::new(self.base(), node::NoiseSourceOptions::default())
+    }
+
     /// Creates an `OscillatorNode`, a source representing a periodic waveform.
     #[must_use]
     fn create_oscillator(&self) -> node::OscillatorNode {
@@ -228,18 +353,97 @@ pub trait BaseAudioContext {
         PeriodicWave::new(self.base(), options)
     }
 
+    /// Creates a `PitchShiftNode` to transpose its input without changing its tempo
+    #[must_use]
+    fn create_pitch_shift(&self) -> node::PitchShiftNode {
+        node::PitchShiftNode::new(self.base(), node::PitchShiftOptions::default())
+    }
+
+    /// Creates a `QuadratureOscillatorNode`, a sine oscillator with a second output 90° ahead
+    #[must_use]
+    fn create_quadrature_oscillator(&self) -> node::QuadratureOscillatorNode {
+        node::QuadratureOscillatorNode::new(
+            self.base(),
+            node::QuadratureOscillatorOptions::default(),
+        )
+    }
+
+    /// Creates a `ReverbNode`, an algorithmic reverberation
+    #[must_use]
+    fn create_reverb(&self) -> node::ReverbNode {
+        node::ReverbNode::new(self.base(), node::ReverbOptions::default())
+    }
+
+    /// Creates a `RingModulatorNode`, multiplying the audio signal by a carrier
+    #[must_use]
+    fn create_ring_modulator(&self) -> node::RingModulatorNode {
+        node::RingModulatorNode::new(self.base(), node::RingModulatorOptions::default())
+    }
+
     /// Creates an `StereoPannerNode` to pan a stereo output
     #[must_use]
     fn create_stereo_panner(&self) -> node::StereoPannerNode {
         node::StereoPannerNode::new(self.base(), node::StereoPannerOptions::default())
     }
 
+    /// Creates a `StereoWidthNode` to widen or narrow the stereo image of a stereo output
+    #[must_use]
+    fn create_stereo_width(&self) -> node::StereoWidthNode {
+        node::StereoWidthNode::new(self.base(), node::StereoWidthOptions::default())
+    }
+
+    /// Creates a `SuperSawNode`, a stack of detuned sawtooth oscillators
+    #[must_use]
+    fn create_super_saw(&self) -> node::SuperSawNode {
+        node::SuperSawNode::new(self.base(), node::SuperSawOptions::default())
+    }
+
+    /// Creates a `TimeStretchNode` to play a buffer at a different tempo without changing its
+    /// pitch
+    #[must_use]
+    fn create_time_stretch(&self) -> node::TimeStretchNode {
+        node::TimeStretchNode::new(self.base(), node::TimeStretchOptions::default())
+    }
+
     /// Creates a `WaveShaperNode`
     #[must_use]
     fn create_wave_shaper(&self) -> node::WaveShaperNode {
         node::WaveShaperNode::new(self.base(), node::WaveShaperOptions::default())
     }
 
+    /// Register a user defined [`Worklet`](node::Worklet) under the given name
+    ///
+    /// The `factory` is called for each `AudioWorkletNode` created with
+    /// [`create_worklet`](Self::create_worklet), with the options of that node.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the name is empty or if a worklet is already registered with
+    /// this name
+    fn register_worklet<W, F>(&self, name: &str, factory: F)
+    where
+        W: node::Worklet + 'static,
+        F: Fn(&node::AudioWorkletNodeOptions) -> W + Send + Sync + 'static,
+    {
+        let definition = node::WorkletDefinition::new(factory);
+        self.base().register_worklet_definition(name, definition);
+    }
+
+    /// Creates an `AudioWorkletNode` running the worklet registered with the given name
+    ///
+    /// # Panics
+    ///
+    /// This function panics if no worklet was registered with this name, or if the options
+    /// specify neither inputs nor outputs
+    #[must_use]
+    fn create_worklet(
+        &self,
+        name: &str,
+        options: node::AudioWorkletNodeOptions,
+    ) -> node::AudioWorkletNode {
+        node::AudioWorkletNode::new(self.base(), name, options)
+    }
+
     /// Returns an `AudioDestinationNode` representing the final destination of all audio in the
     /// context. It can be thought of as the audio-rendering device.
     #[must_use]
@@ -264,6 +468,16 @@ pub trait BaseAudioContext {
         self.base().sample_rate()
     }
 
+    /// The number of sample frames the audio graph renders at once
+    ///
+    /// This is [`RENDER_QUANTUM_SIZE`](crate::RENDER_QUANTUM_SIZE) unless configured otherwise
+    /// with [`AudioContextOptions::render_quantum_size`](crate::context::AudioContextOptions::render_quantum_size)
+    /// or [`OfflineAudioContext::new_with_render_quantum_size`](crate::context::OfflineAudioContext::new_with_render_quantum_size).
+    #[must_use]
+    fn render_quantum_size(&self) -> usize {
+        self.base().render_quantum_size()
+    }
+
     /// Returns state of current context
     #[must_use]
     fn state(&self) -> AudioContextState {
@@ -277,6 +491,220 @@ pub trait BaseAudioContext {
         self.base().current_time()
     }
 
+    /// The index of the sample frame immediately following the last sample-frame in the block of
+    /// audio most recently processed by the context’s rendering graph.
+    ///
+    /// This is the frame-accurate counterpart of [`current_time`](Self::current_time), which is
+    /// derived from it. It advances in steps of exactly
+    /// [`render_quantum_size`](Self::render_quantum_size) frames, independently of the buffer
+    /// size of the audio backend, and is frozen while the context is suspended.
+    #[must_use]
+    fn current_frame(&self) -> u64 {
+        self.base().current_frame()
+    }
+
+    /// Connect the given nodes in series, each to the next, and return the last node.
+    ///
+    /// `context.chain(&[&a, &b, &c])` is equivalent to `a.connect(&b).connect(&c)`. Returns
+    /// `None` when the slice is empty. Channel count mismatches are resolved by the regular
+    /// up/down-mixing rules of each node.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when the nodes do not all belong to this context, or when one
+    /// of the nodes (except for the last) has no output or (except for the first) has no input.
+    fn chain<'a>(&self, nodes: &[&'a dyn AudioNode]) -> Option<&'a dyn AudioNode> {
+        nodes.windows(2).for_each(|pair| {
+            pair[0].connect(pair[1]);
+        });
+        nodes.last().copied()
+    }
+
+    /// Connect the `source` node to all of the `destinations`.
+    ///
+    /// An empty `destinations` slice is a no-op.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when the nodes do not all belong to this context, or when the
+    /// source has no output or one of the destinations has no input.
+    fn fan_out(&self, source: &dyn AudioNode, destinations: &[&dyn AudioNode]) {
+        destinations.iter().for_each(|dest| {
+            source.connect(*dest);
+        });
+    }
+
+    /// Connect all of the `sources` to the `destination` node, where they are summed.
+    ///
+    /// An empty `sources` slice is a no-op.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when the nodes do not all belong to this context, or when one of
+    /// the sources has no output or the destination has no input.
+    fn fan_in(&self, sources: &[&dyn AudioNode], destination: &dyn AudioNode) {
+        sources.iter().for_each(|src| {
+            src.connect(destination);
+        });
+    }
+
+    /// Combine two mono sources into a stereo stream
+    ///
+    /// Returns a 2-input [`ChannelMergerNode`](node::ChannelMergerNode) with `left` connected to
+    /// its first input and `right` to its second input. The merger can be connected like any
+    /// other node. Sources with multiple channels only contribute their first channel: the
+    /// channel interpretation of the merger is set to
+    /// [`Discrete`](node::ChannelInterpretation::Discrete).
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when the nodes do not belong to this context, or when one of
+    /// the sources has no output.
+    #[must_use]
+    fn merge_stereo(&self, left: &dyn AudioNode, right: &dyn AudioNode) -> node::ChannelMergerNode {
+        let merger = self.create_channel_merger(2);
+        merger.set_channel_interpretation(node::ChannelInterpretation::Discrete);
+        left.connect_at(&merger, 0, 0);
+        right.connect_at(&merger, 0, 1);
+        merger
+    }
+
+    /// Take a snapshot of the audio graph, to be rebuilt later with
+    /// [`load_patch`](Self::load_patch)
+    ///
+    /// See [`Patch`](crate::context::Patch) for the node types and the state that are saved.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    #[must_use]
+    fn save_patch(&self) -> Patch {
+        self.base().save_patch()
+    }
+
+    /// Rebuild the audio graph of a [`Patch`] in this context
+    ///
+    /// The nodes are created with their options, the automation of their params and their
+    /// start and stop times are replayed, and they are connected to each other and to the
+    /// destination of this context. Times are absolute, a patch is meant to be loaded in a
+    /// context that did not run yet. The nodes are returned in the order of the patch, they
+    /// are released like any other node when these handles are dropped.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, before any connection is made, when
+    /// - a node runs a worklet that is not registered in this context
+    /// - a connection refers to an unknown node, param or port
+    /// - the params, or the start and stop times, of a node do not match its type
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when the options or the automation events of a node are
+    /// invalid, like the corresponding constructor or `AudioParam` method.
+    fn load_patch(
+        &self,
+        patch: &Patch,
+    ) -> Result<Vec<Box<dyn AudioNode>>, Box<dyn std::error::Error + Send + Sync>> {
+        crate::context::load_patch(self.base(), patch)
+    }
+
+    /// Unsolo all audio nodes of this context, restoring the normal mix
+    ///
+    /// Mixer channels that are muted stay silent. See
+    /// [`AudioNode::set_soloed`](crate::node::AudioNode::set_soloed).
+    ///
+    /// This method is not part of the Web Audio API specification.
+    fn clear_solo(&self) {
+        self.base().clear_solo();
+    }
+
+    /// Replace the `old_node` by the `new_node` in the audio graph without clicks.
+    ///
+    /// The `new_node` is connected to all destinations of the `old_node`. Over the given
+    /// `duration` in seconds, the gain of the `old_node` is ramped down linearly while the gain
+    /// of the `new_node` is ramped up. Once the crossfade has completed, the `old_node` is
+    /// disconnected. Direct connections of the `new_node` to these destinations are replaced by
+    /// the crossfaded connection.
+    ///
+    /// The nodes are connected through their first output to the first input of each
+    /// destination. Swapping a node that is still fading in from a previous swap is supported,
+    /// both crossfades complete. Destinations whose handles have been dropped are not tracked
+    /// anymore and keep their connection to the `old_node`.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when:
+    /// - the nodes do not belong to this context
+    /// - one of the nodes has no output
+    /// - the `old_node` is not connected to any destination
+    /// - the `duration` is negative or not finite
+    fn crossfade_swap(&self, old_node: &dyn AudioNode, new_node: &dyn AudioNode, duration: f64) {
+        assert!(
+            duration.is_finite() && duration >= 0.,
+            "RangeError - duration should be positive and finite, received {:?}",
+            duration
+        );
+
+        let base = self.base();
+        let old_id = old_node.registration().id();
+        let new_id = new_node.registration().id();
+        let destinations = base.outgoing_connections(old_id);
+        assert!(
+            !destinations.is_empty(),
+            "InvalidStateError - the node to replace is not connected"
+        );
+
+        let fade_out = self.create_gain();
+        let fade_in = self.create_gain();
+        let fade_out_id = fade_out.registration().id();
+        let fade_in_id = fade_in.registration().id();
+
+        let start = self.current_time();
+        let end = start + duration;
+        fade_out
+            .gain()
+            .set_value_at_time(1., start)
+            .linear_ramp_to_value_at_time(0., end);
+        fade_in
+            .gain()
+            .set_value_at_time(0., start)
+            .linear_ramp_to_value_at_time(1., end);
+
+        // route both nodes through their fade, the order of the messages to the render thread
+        // minimizes the time either node is heard unfaded
+        old_node.connect(&fade_out);
+        new_node.connect(&fade_in);
+        destinations.iter().for_each(|&dest| {
+            base.connect(fade_in_id, dest, 0, 0);
+            base.disconnect_from_if_connected(new_id, dest);
+            base.disconnect_from(old_id, dest);
+            base.connect(fade_out_id, dest, 0, 0);
+        });
+
+        // Once the crossfade has completed, disconnect the old node and take the fade of the new
+        // node out of the graph, so the new node can be swapped again. The handles of the fades
+        // keep their connections tracked until then.
+        let timer = self.create_constant_source();
+        timer.start();
+        timer.stop_at(end);
+        let context = base.clone();
+        timer.onended(move || {
+            context.disconnect_from_if_connected(old_id, fade_out_id);
+            fade_out.disconnect();
+            context.splice_out(fade_in_id, &[fade_in.gain().registration().id()]);
+        });
+    }
+
     /// Create an `AudioParam`.
     ///
     /// Call this inside the `register` closure when setting up your `AudioNode`
@@ -307,3 +735,23 @@ pub trait BaseAudioContext {
         }
     }
 }
+
+/// Consume the input stream in full and construct a single buffer out of it, at the sample rate
+/// of the input
+///
+/// When no samples are decoded, an empty buffer at the given `sample_rate` is returned.
+fn decode_audio_data<R: std::io::Read + Send + Sync + 'static>(
+    input: R,
+    sample_rate: f32,
+) -> Result<AudioBuffer, Box<dyn std::error::Error + Send + Sync>> {
+    let buffer = MediaDecoder::try_new(input)?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .reduce(|mut accum, item| {
+            accum.extend(&item);
+            accum
+        })
+        .unwrap_or_else(|| AudioBuffer::from(vec![vec![]], sample_rate));
+
+    Ok(buffer)
+}
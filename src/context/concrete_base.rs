@@ -1,19 +1,27 @@
 //! The `ConcreteBaseAudioContext` type
 
 use crate::context::{
-    AudioContextRegistration, AudioContextState, AudioNodeId, BaseAudioContext,
-    DESTINATION_NODE_ID, LISTENER_NODE_ID, LISTENER_PARAM_IDS,
+    AudioContextRegistration, AudioContextState, AudioNodeId, BaseAudioContext, Patch,
+    PatchNodeType, PatchRecorder, Patchable, DESTINATION_NODE_ID, LISTENER_NODE_ID,
+    LISTENER_PARAM_IDS,
 };
-use crate::events::{Callback, Event, EventHandler, EventLoop};
+use crate::events::{Callback, Event, EventHandler, EventLoop, EventType};
 use crate::message::ControlMessage;
-use crate::node::{AudioDestinationNode, AudioNode, ChannelConfig, ChannelConfigOptions};
-use crate::param::{AudioParam, AudioParamEvent};
+use crate::node::{
+    AudioDestinationNode, AudioNode, ChannelConfig, ChannelConfigOptions, DestinationMeter, Rng,
+    WorkletDefinition,
+};
+use crate::param::{AudioParam, AudioParamEvent, AutomationHistory};
 use crate::render::AudioProcessor;
 use crate::spatial::AudioListenerParams;
 
-use crate::AudioListener;
+use crate::{AtomicF32, AudioListener};
 
 use crossbeam_channel::{Receiver, SendError, Sender};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::hash::{BuildHasher, Hasher};
 use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use std::sync::{Arc, Mutex, RwLock, RwLockWriteGuard};
 
@@ -45,12 +53,18 @@ impl PartialEq for ConcreteBaseAudioContext {
 struct ConcreteBaseAudioContextInner {
     /// sample rate in Hertz
     sample_rate: f32,
+    /// number of sample frames rendered at once
+    render_quantum_size: usize,
     /// max number of speaker output channels
     max_channel_count: usize,
     /// incrementing id to assign to audio nodes
     node_id_inc: AtomicU64,
     /// destination node's current channel count
     destination_channel_config: ChannelConfig,
+    /// destination node's soft clipping threshold, infinite when disabled
+    destination_limiter: Arc<AtomicF32>,
+    /// destination node's peak level and clipping flag, shared with its renderer
+    destination_meter: Arc<DestinationMeter>,
     /// message channel from control to render thread
     render_channel: RwLock<Sender<ControlMessage>>,
     /// control messages that cannot be sent immediately
@@ -67,6 +81,118 @@ struct ConcreteBaseAudioContextInner {
     state: AtomicU8,
     /// Stores the event handlers
     event_loop: EventLoop,
+    /// Mirror of the connections of the audio graph, to validate new connections
+    connections: Mutex<Connections>,
+    /// Worklets registered by name, to create `AudioWorkletNode`s
+    worklets: Mutex<HashMap<String, WorkletDefinition>>,
+    /// Nodes saved by `save_patch`, and the automation of the params
+    patch: Mutex<PatchRecorder>,
+    /// Context-wide random number generator seeding the stochastic nodes, `None` when unseeded
+    rng: Mutex<Option<Rng>>,
+}
+
+/// Control thread bookkeeping of the edges of the audio graph
+///
+/// The edges are stored per node in both directions, so the edges of a dropped node are
+/// removed in time proportional to its number of connections.
+#[derive(Default)]
+struct Connections {
+    /// Destinations of the connections of each node, including connections to `AudioParam`s
+    outgoing: HashMap<AudioNodeId, Vec<AudioNodeId>>,
+    /// Sources of the connections to each node
+    incoming: HashMap<AudioNodeId, Vec<AudioNodeId>>,
+    /// Destination, output and input port of the connections of each node, without duplicates
+    ports: HashMap<AudioNodeId, Vec<(AudioNodeId, usize, usize)>>,
+    /// Nodes whose outgoing connections are cut by the render thread when found in a cycle
+    cycle_breakers: HashSet<AudioNodeId>,
+}
+
+impl Connections {
+    fn add(&mut self, from: AudioNodeId, to: AudioNodeId, output: usize, input: usize) {
+        self.outgoing.entry(from).or_default().push(to);
+        self.incoming.entry(to).or_default().push(from);
+        let ports = self.ports.entry(from).or_default();
+        if !ports.contains(&(to, output, input)) {
+            ports.push((to, output, input));
+        }
+    }
+
+    fn is_connected(&self, from: AudioNodeId, to: AudioNodeId) -> bool {
+        self.outgoing
+            .get(&from)
+            .is_some_and(|dests| dests.contains(&to))
+    }
+
+    /// Remove all connections from `from` to `to`
+    fn remove(&mut self, from: AudioNodeId, to: AudioNodeId) {
+        if let Some(dests) = self.outgoing.get_mut(&from) {
+            dests.retain(|&dest| dest != to);
+        }
+        if let Some(sources) = self.incoming.get_mut(&to) {
+            sources.retain(|&source| source != from);
+        }
+        if let Some(ports) = self.ports.get_mut(&from) {
+            ports.retain(|&(dest, _, _)| dest != to);
+        }
+    }
+
+    /// Remove all outgoing connections of `from`
+    fn remove_outgoing(&mut self, from: AudioNodeId) {
+        self.ports.remove(&from);
+        for to in self.outgoing.remove(&from).unwrap_or_default() {
+            if let Some(sources) = self.incoming.get_mut(&to) {
+                sources.retain(|&source| source != from);
+            }
+        }
+    }
+
+    /// Remove all connections from and to `id`
+    fn remove_node(&mut self, id: AudioNodeId) {
+        self.remove_outgoing(id);
+        for from in self.incoming.remove(&id).unwrap_or_default() {
+            if let Some(dests) = self.outgoing.get_mut(&from) {
+                dests.retain(|&dest| dest != id);
+            }
+            if let Some(ports) = self.ports.get_mut(&from) {
+                ports.retain(|&(dest, _, _)| dest != id);
+            }
+        }
+        self.cycle_breakers.remove(&id);
+    }
+
+    /// Check if a connection from `from` to `to` would close a cycle without a cycle breaker
+    ///
+    /// This is the case when `from` can be reached from `to` without passing through a cycle
+    /// breaker (i.e. a `DelayNode`).
+    fn closes_illegal_cycle(&self, from: AudioNodeId, to: AudioNodeId) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![to];
+
+        while let Some(node) = stack.pop() {
+            if node == from {
+                return true;
+            }
+            if !visited.insert(node) || self.cycle_breakers.contains(&node) {
+                continue;
+            }
+            if let Some(dests) = self.outgoing.get(&node) {
+                stack.extend(dests);
+            }
+        }
+
+        false
+    }
+}
+
+/// The node ids in order of appearance, without duplicates
+fn unique(ids: Option<&Vec<AudioNodeId>>) -> Vec<AudioNodeId> {
+    let mut unique = vec![];
+    ids.into_iter().flatten().for_each(|&id| {
+        if !unique.contains(&id) {
+            unique.push(id);
+        }
+    });
+    unique
 }
 
 impl BaseAudioContext for ConcreteBaseAudioContext {
@@ -119,6 +245,7 @@ impl ConcreteBaseAudioContext {
     /// Creates a `BaseAudioContext` instance
     pub(super) fn new(
         sample_rate: f32,
+        render_quantum_size: usize,
         max_channel_count: usize,
         frames_played: Arc<AtomicU64>,
         render_channel: Sender<ControlMessage>,
@@ -129,17 +256,24 @@ impl ConcreteBaseAudioContext {
 
         let base_inner = ConcreteBaseAudioContextInner {
             sample_rate,
+            render_quantum_size,
             max_channel_count,
             render_channel: RwLock::new(render_channel),
             queued_messages: Mutex::new(Vec::new()),
             node_id_inc: AtomicU64::new(0),
             destination_channel_config: ChannelConfigOptions::default().into(),
+            destination_limiter: Arc::new(AtomicF32::new(f32::INFINITY)),
+            destination_meter: Arc::new(DestinationMeter::default()),
             frames_played,
             queued_audio_listener_msgs: Mutex::new(Vec::new()),
             listener_params: None,
             offline,
             state: AtomicU8::new(AudioContextState::Suspended as u8),
             event_loop: event_loop.clone(),
+            connections: Mutex::new(Connections::default()),
+            worklets: Mutex::new(HashMap::new()),
+            patch: Mutex::new(PatchRecorder::default()),
+            rng: Mutex::new(None),
         };
         let base = Self {
             inner: Arc::new(base_inner),
@@ -192,6 +326,18 @@ impl ConcreteBaseAudioContext {
             LISTENER_PARAM_IDS.end,
         );
 
+        // stop tracking the connections of the nodes once the render thread frees them, the
+        // handler does not keep the context alive
+        let inner = Arc::downgrade(&base.inner);
+        base.register_event_handler(
+            EventType::NodeDropped,
+            Callback::Multiple(Box::new(move |event| {
+                if let (Event::NodeDropped(id), Some(inner)) = (event, inner.upgrade()) {
+                    inner.connections.lock().unwrap().remove_node(id);
+                }
+            })),
+        );
+
         // (?) only for online context
         if let Some(event_channel) = event_channel {
             // init event loop
@@ -212,6 +358,27 @@ impl ConcreteBaseAudioContext {
         self.inner.render_channel.write().unwrap()
     }
 
+    /// Seed the context-wide random number generator, for the stochastic nodes created from now on
+    pub(super) fn set_random_seed(&self, seed: u64) {
+        *self.inner.rng.lock().unwrap() = Some(Rng::new(seed));
+    }
+
+    /// Seed of the random number generator of a new stochastic node
+    ///
+    /// When the context is seeded, each node gets its own stream drawn from the context-wide
+    /// generator: the streams only depend on the seed and on the order in which the stochastic
+    /// nodes are created. Otherwise the seed is random.
+    pub(crate) fn random_seed(&self, id: AudioNodeId) -> u64 {
+        match self.inner.rng.lock().unwrap().as_mut() {
+            Some(rng) => rng.next_u64(),
+            None => {
+                let mut hasher = RandomState::new().build_hasher();
+                hasher.write_u64(id.0);
+                hasher.finish()
+            }
+        }
+    }
+
     /// Inform render thread that the control thread `AudioNode` no langer has any handles
     pub(super) fn mark_node_dropped(&self, id: AudioNodeId) {
         // do not drop magic nodes
@@ -220,6 +387,10 @@ impl ConcreteBaseAudioContext {
             || LISTENER_PARAM_IDS.contains(&id.0);
 
         if !magic {
+            // The connections of the node are still tracked: the node lives on in the render
+            // graph until it is freed there, which is reported by a `NodeDropped` event
+            self.inner.patch.lock().unwrap().remove_node(id);
+
             let message = ControlMessage::FreeWhenFinished { id };
 
             // Sending the message will fail when the render thread has already shut down.
@@ -232,6 +403,12 @@ impl ConcreteBaseAudioContext {
     #[doc(hidden)]
     pub fn mark_cycle_breaker(&self, reg: &AudioContextRegistration) {
         let id = reg.id();
+        self.inner
+            .connections
+            .lock()
+            .unwrap()
+            .cycle_breakers
+            .insert(id);
         let message = ControlMessage::MarkCycleBreaker { id };
 
         // Sending the message will fail when the render thread has already shut down.
@@ -271,9 +448,9 @@ impl ConcreteBaseAudioContext {
         self.inner.state.load(Ordering::SeqCst).into()
     }
 
-    /// Updates state of current context
-    pub(super) fn set_state(&self, state: AudioContextState) {
-        self.inner.state.store(state as u8, Ordering::SeqCst);
+    /// Updates state of current context, returns the previous state
+    pub(super) fn set_state(&self, state: AudioContextState) -> AudioContextState {
+        self.inner.state.swap(state as u8, Ordering::SeqCst).into()
     }
 
     /// The sample rate (in sample-frames per second) at which the `AudioContext` handles audio.
@@ -282,6 +459,12 @@ impl ConcreteBaseAudioContext {
         self.inner.sample_rate
     }
 
+    /// Number of sample frames the audio graph renders at once
+    #[must_use]
+    pub(super) fn render_quantum_size(&self) -> usize {
+        self.inner.render_quantum_size
+    }
+
     /// This is the time in seconds of the sample frame immediately following the last sample-frame
     /// in the block of audio most recently processed by the context’s rendering graph.
     #[must_use]
@@ -290,7 +473,26 @@ impl ConcreteBaseAudioContext {
     // Currently, we have no other choice than casting an u64 into f64, with possible loss of precision
     #[allow(clippy::cast_precision_loss)]
     pub(super) fn current_time(&self) -> f64 {
-        self.inner.frames_played.load(Ordering::SeqCst) as f64 / self.inner.sample_rate as f64
+        self.current_frame() as f64 / self.inner.sample_rate as f64
+    }
+
+    /// Number of sample frames processed by the context’s rendering graph.
+    ///
+    /// The render thread advances this counter by exactly `render_quantum_size` for each render
+    /// quantum, so it never decreases and stays put while the context is suspended.
+    #[must_use]
+    pub(super) fn current_frame(&self) -> u64 {
+        self.inner.frames_played.load(Ordering::SeqCst)
+    }
+
+    /// Soft clipping threshold of the `AudioDestinationNode`, shared with its renderer
+    pub(crate) fn destination_limiter(&self) -> &Arc<AtomicF32> {
+        &self.inner.destination_limiter
+    }
+
+    /// Peak level and clipping flag of the `AudioDestinationNode`, shared with its renderer
+    pub(crate) fn destination_meter(&self) -> &Arc<DestinationMeter> {
+        &self.inner.destination_meter
     }
 
     /// Maximum available channels for the audio destination
@@ -317,6 +519,38 @@ impl ConcreteBaseAudioContext {
 
     /// Connects the output of the `from` audio node to the input of the `to` audio node
     pub(crate) fn connect(&self, from: AudioNodeId, to: AudioNodeId, output: usize, input: usize) {
+        self.inner
+            .connections
+            .lock()
+            .unwrap()
+            .add(from, to, output, input);
+        let message = ControlMessage::ConnectNode {
+            from,
+            to,
+            output,
+            input,
+        };
+        self.send_control_msg(message).unwrap();
+    }
+
+    /// Connects the output of the `from` audio node to the input of the `to` audio node, unless
+    /// this would create a cycle that is not broken by a `DelayNode`
+    pub(crate) fn try_connect(
+        &self,
+        from: AudioNodeId,
+        to: AudioNodeId,
+        output: usize,
+        input: usize,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        // hold the lock until the connection is registered, to validate against a stable graph
+        let mut connections = self.inner.connections.lock().unwrap();
+        if connections.closes_illegal_cycle(from, to) {
+            return Err(
+                "InvalidAccessError - connection would create a cycle without a DelayNode".into(),
+            );
+        }
+        connections.add(from, to, output, input);
+
         let message = ControlMessage::ConnectNode {
             from,
             to,
@@ -324,6 +558,8 @@ impl ConcreteBaseAudioContext {
             input,
         };
         self.send_control_msg(message).unwrap();
+
+        Ok(())
     }
 
     /// Schedule a connection of an `AudioParam` to the `AudioNode` it belongs to
@@ -336,21 +572,100 @@ impl ConcreteBaseAudioContext {
             output: 0,
             input: usize::MAX, // audio params connect to the 'hidden' input port
         };
+        self.inner.connections.lock().unwrap().add(
+            param.registration().id(),
+            audio_node,
+            0,
+            usize::MAX,
+        );
         self.inner.queued_messages.lock().unwrap().push(message);
     }
 
     /// Disconnects all outputs of the audio node that go to a specific destination node.
     pub(crate) fn disconnect_from(&self, from: AudioNodeId, to: AudioNodeId) {
+        self.inner.connections.lock().unwrap().remove(from, to);
         let message = ControlMessage::DisconnectNode { from, to };
         self.send_control_msg(message).unwrap();
     }
 
+    /// Disconnects the audio node from the destination node, if it is still connected
+    ///
+    /// No message is sent when the connection is gone, e.g. because the handle of one of the
+    /// nodes was dropped in the meantime and the render thread may have released it already.
+    pub(crate) fn disconnect_from_if_connected(&self, from: AudioNodeId, to: AudioNodeId) {
+        // hold the lock until the message is sent, to be ordered before a `FreeWhenFinished`
+        let mut connections = self.inner.connections.lock().unwrap();
+        if connections.is_connected(from, to) {
+            connections.remove(from, to);
+            let message = ControlMessage::DisconnectNode { from, to };
+            // Sending the message will fail when the render thread has already shut down.
+            // This is fine
+            let _r = self.send_control_msg(message);
+        }
+    }
+
+    /// Connect the sources of the audio node directly to its destinations instead
+    ///
+    /// The connections from the `params` of the node are left in place. Sources whose handles
+    /// have been dropped are not tracked anymore and stay connected to the node.
+    pub(crate) fn splice_out(&self, id: AudioNodeId, params: &[AudioNodeId]) {
+        // hold the lock until the messages are sent, to be ordered before a `FreeWhenFinished`
+        let mut connections = self.inner.connections.lock().unwrap();
+        let mut sources = unique(connections.incoming.get(&id));
+        sources.retain(|source| !params.contains(source));
+        let dests = unique(connections.outgoing.get(&id));
+
+        sources.into_iter().for_each(|from| {
+            dests.iter().for_each(|&to| {
+                connections.add(from, to, 0, 0);
+                let message = ControlMessage::ConnectNode {
+                    from,
+                    to,
+                    output: 0,
+                    input: 0,
+                };
+                let _r = self.send_control_msg(message);
+            });
+            connections.remove(from, id);
+            let _r = self.send_control_msg(ControlMessage::DisconnectNode { from, to: id });
+        });
+    }
+
+    /// Destinations of the outgoing connections of the audio node, without duplicates
+    pub(crate) fn outgoing_connections(&self, from: AudioNodeId) -> Vec<AudioNodeId> {
+        unique(self.inner.connections.lock().unwrap().outgoing.get(&from))
+    }
+
     /// Disconnects all outgoing connections from the audio node.
     pub(crate) fn disconnect(&self, from: AudioNodeId) {
+        self.inner.connections.lock().unwrap().remove_outgoing(from);
         let message = ControlMessage::DisconnectAll { from };
         self.send_control_msg(message).unwrap();
     }
 
+    /// Toggle the bypass of the audio node in the render thread
+    pub(crate) fn set_bypass(&self, id: AudioNodeId, bypassed: bool) {
+        let message = ControlMessage::SetBypass { id, bypassed };
+        self.send_control_msg(message).unwrap();
+    }
+
+    /// Toggle the mute of the audio node in the render thread
+    pub(crate) fn set_muted(&self, id: AudioNodeId, muted: bool) {
+        let message = ControlMessage::SetMuted { id, muted };
+        self.send_control_msg(message).unwrap();
+    }
+
+    /// Toggle the solo of the audio node in the render thread
+    pub(crate) fn set_soloed(&self, id: AudioNodeId, soloed: bool) {
+        let message = ControlMessage::SetSoloed { id, soloed };
+        self.send_control_msg(message).unwrap();
+    }
+
+    /// Unsolo all audio nodes in the render thread
+    pub(crate) fn clear_solo(&self) {
+        self.send_control_msg(ControlMessage::ClearSolo).unwrap();
+    }
+
     /// Pass an `AudioParam::AudioParamEvent` to the render thread
     ///
     /// This clunky setup (wrapping a Sender in a message sent by another Sender) ensures
@@ -398,9 +713,128 @@ impl ConcreteBaseAudioContext {
         self.inner.offline
     }
 
-    pub(crate) fn register_event_handler(&self, event: Event, callback: Callback) {
+    /// Register a worklet definition under the given name
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the name is empty or already in use
+    pub(crate) fn register_worklet_definition(&self, name: &str, definition: WorkletDefinition) {
+        if name.is_empty() {
+            panic!("NotSupportedError - worklet name cannot be empty");
+        }
+
+        let mut worklets = self.inner.worklets.lock().unwrap();
+        if worklets.contains_key(name) {
+            panic!(
+                "NotSupportedError - a worklet is already registered with name {:?}",
+                name
+            );
+        }
+        worklets.insert(name.to_string(), definition);
+    }
+
+    /// The worklet definition registered under the given name, if any
+    pub(crate) fn worklet_definition(&self, name: &str) -> Option<WorkletDefinition> {
+        self.inner.worklets.lock().unwrap().get(name).cloned()
+    }
+
+    /// Record a node to be saved by `save_patch`, until its handle is dropped
+    ///
+    /// The `snapshot` returns the current type and options of the node.
+    pub(crate) fn record_patch_node<N, F>(&self, node: &N, snapshot: F)
+    where
+        N: Patchable,
+        F: Fn() -> PatchNodeType + Send + Sync + 'static,
+    {
+        self.inner
+            .patch
+            .lock()
+            .unwrap()
+            .add_node(node, Box::new(snapshot));
+    }
+
+    /// Record an automation event of a param, for `save_patch` and `compute_values`
+    pub(crate) fn record_automation_event(&self, param: &AudioParam, event: &AudioParamEvent) {
+        self.inner
+            .patch
+            .lock()
+            .unwrap()
+            .record(param, event, self.current_frame());
+    }
+
+    /// Run `f` on the automation of a param, evaluated up to the current frame
+    pub(crate) fn with_automation_history<R, F>(&self, param: &AudioParam, f: F) -> R
+    where
+        F: FnOnce(&AutomationHistory) -> R,
+    {
+        self.inner
+            .patch
+            .lock()
+            .unwrap()
+            .with_automation(param, self.current_frame(), f)
+    }
+
+    /// Snapshot of the recorded nodes and of their connections
+    pub(super) fn save_patch(&self) -> Patch {
+        let mut patch = self.inner.patch.lock().unwrap();
+        let connections = self.inner.connections.lock().unwrap();
+        patch.save(self.current_frame(), |from| {
+            connections.ports.get(&from).cloned().unwrap_or_default()
+        })
+    }
+
+    pub(crate) fn register_event_handler(&self, event: EventType, callback: Callback) {
         self.inner
             .event_loop
             .add_handler(EventHandler { event, callback });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::OfflineAudioContext;
+
+    #[test]
+    fn test_connections_pruned_when_freed() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let base = context.base().clone();
+
+        let gain = context.create_gain();
+        let gain_id = gain.registration().id();
+        let param_id = gain.gain().registration().id();
+        gain.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        let src_id = src.registration().id();
+        src.connect(gain.gain());
+
+        drop(gain);
+        drop(src);
+
+        // the nodes are still alive in the render graph
+        {
+            let connections = base.inner.connections.lock().unwrap();
+            assert_eq!(connections.outgoing[&gain_id], vec![DESTINATION_NODE_ID]);
+            assert_eq!(connections.outgoing[&src_id], vec![param_id]);
+        }
+
+        // the unstarted source is freed together with the param it feeds, and then the gain
+        context.start_rendering_sync();
+
+        let start = std::time::Instant::now();
+        loop {
+            {
+                let connections = base.inner.connections.lock().unwrap();
+                if !connections.outgoing.contains_key(&gain_id) {
+                    assert!(!connections.incoming.contains_key(&gain_id));
+                    assert!(!connections.outgoing.contains_key(&param_id));
+                    assert!(!connections.incoming[&DESTINATION_NODE_ID].contains(&gain_id));
+                    break;
+                }
+            }
+            assert!(start.elapsed().as_secs() < 5, "connections were not pruned");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+}
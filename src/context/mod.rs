@@ -13,11 +13,14 @@ pub use offline::*;
 mod online;
 pub use online::*;
 
+mod patch;
+pub use patch::*;
+
 // magic node values
 /// Destination node id is always at index 0
-const DESTINATION_NODE_ID: AudioNodeId = AudioNodeId(0);
+pub(crate) const DESTINATION_NODE_ID: AudioNodeId = AudioNodeId(0);
 /// listener node id is always at index 1
-const LISTENER_NODE_ID: AudioNodeId = AudioNodeId(1);
+pub(crate) const LISTENER_NODE_ID: AudioNodeId = AudioNodeId(1);
 /// listener audio parameters ids are always at index 2 through 10
 const LISTENER_PARAM_IDS: Range<u64> = 2..11;
 /// listener audio parameters ids are always at index 2 through 10
@@ -36,7 +39,7 @@ pub(crate) const LISTENER_AUDIO_PARAM_IDS: [AudioParamId; 9] = [
 /// Unique identifier for audio nodes.
 ///
 /// Used for internal bookkeeping.
-#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub(crate) struct AudioNodeId(pub u64);
 
 /// Unique identifier for audio params.
@@ -52,7 +55,10 @@ impl From<&AudioParamId> for AudioNodeId {
 }
 
 /// Describes the current state of the `AudioContext`
+///
+/// New states may be added in the future, matches on this enum must include a wildcard arm.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum AudioContextState {
     /// This context is currently suspended (context time is not proceeding,
     /// audio hardware may be powered down/released).
@@ -62,6 +68,10 @@ pub enum AudioContextState {
     /// This context has been released, and can no longer be used to process audio.
     /// All system audio resources have been released.
     Closed,
+    /// The audio output device was lost, the context is trying to reconnect.
+    ///
+    /// Context time is not proceeding until a new output stream is available.
+    Interrupted,
 }
 
 impl From<u8> for AudioContextState {
@@ -70,6 +80,7 @@ impl From<u8> for AudioContextState {
             0 => Self::Suspended,
             1 => Self::Running,
             2 => Self::Closed,
+            3 => Self::Interrupted,
             _ => unreachable!(),
         }
     }
@@ -110,9 +121,11 @@ impl Drop for AudioContextRegistration {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::node::AudioNode;
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+    use crate::ResampleQuality;
 
     use float_eq::assert_float_eq;
+    use std::f32::consts::PI;
 
     fn require_send_sync_static<T: Send + Sync + 'static>(_: T) {}
 
@@ -156,6 +169,58 @@ mod tests {
         assert!(left_start != right_start);
     }
 
+    #[test]
+    fn test_decode_audio_data_resample_quality() {
+        let context = OfflineAudioContext::new(1, 0, 44100.);
+        let decode = |quality| {
+            let file = std::fs::File::open("samples/think-stereo-38000.wav").unwrap();
+            context
+                .decode_audio_data_sync_with_quality(file, quality)
+                .unwrap()
+        };
+
+        // the windowed-sinc resampler is used by default
+        let file = std::fs::File::open("samples/think-stereo-38000.wav").unwrap();
+        let default = context.decode_audio_data_sync(file).unwrap();
+        let medium = decode(ResampleQuality::Medium);
+        let low = decode(ResampleQuality::Low);
+        assert_eq!(default.length(), low.length());
+        assert_float_eq!(
+            default.get_channel_data(0),
+            medium.get_channel_data(0),
+            abs_all <= 0.
+        );
+        assert!(default.get_channel_data(0) != low.get_channel_data(0));
+    }
+
+    #[test]
+    fn test_decode_audio_data_exact() {
+        // matching sample rate, the data is not resampled
+        let context = OfflineAudioContext::new(1, 0, 38000.);
+        let file = std::fs::File::open("samples/think-stereo-38000.wav").unwrap();
+        let exact = context.decode_audio_data_exact(file).unwrap();
+        assert_eq!(exact.sample_rate(), 38000.);
+        assert_eq!(exact.number_of_channels(), 2);
+
+        let file = std::fs::File::open("samples/think-stereo-38000.wav").unwrap();
+        let resampled = context.decode_audio_data_sync(file).unwrap();
+        assert_eq!(exact.length(), resampled.length());
+        for channel in 0..2 {
+            assert_float_eq!(
+                exact.get_channel_data(channel),
+                resampled.get_channel_data(channel),
+                abs_all <= 0.
+            );
+        }
+
+        // mismatched sample rate
+        let file = std::fs::File::open("samples/think-stereo-44100.wav").unwrap();
+        let error = context.decode_audio_data_exact(file).unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("44100"), "{}", message);
+        assert!(message.contains("38000"), "{}", message);
+    }
+
     // #[test]
     // disabled: symphonia cannot handle empty WAV-files
     #[allow(dead_code)]
@@ -193,4 +258,349 @@ mod tests {
         let dest = context.destination();
         assert!(dest.context() == context.base());
     }
+
+    #[test]
+    fn test_chain() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+        let src = context.create_constant_source();
+        let gain1 = context.create_gain();
+        gain1.gain().set_value(2.);
+        let gain2 = context.create_gain();
+        gain2.gain().set_value(3.);
+        let dest = context.destination();
+
+        let last = context.chain(&[&src, &gain1, &gain2, &dest]).unwrap();
+        assert!(last.registration().id() == dest.registration().id());
+        src.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(output.get_channel_data(0), &[6.; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_chain_equals_connect() {
+        let render = |use_chain: bool| {
+            let context = OfflineAudioContext::new(2, 128, 48000.);
+            let src = context.create_oscillator();
+            let gain = context.create_gain();
+            gain.gain().set_value(0.5);
+            let panner = context.create_stereo_panner();
+            panner.pan().set_value(0.3);
+
+            if use_chain {
+                context.chain(&[&src, &gain, &panner, &context.destination()]);
+            } else {
+                src.connect(&gain)
+                    .connect(&panner)
+                    .connect(&context.destination());
+            }
+            src.start();
+
+            context.start_rendering_sync()
+        };
+
+        let chained = render(true);
+        let connected = render(false);
+        for c in 0..2 {
+            assert_float_eq!(
+                chained.get_channel_data(c),
+                connected.get_channel_data(c),
+                abs_all <= 0.
+            );
+        }
+    }
+
+    #[test]
+    fn test_chain_empty() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+        assert!(context.chain(&[]).is_none());
+
+        let src = context.create_constant_source();
+        let last = context.chain(&[&src]).unwrap();
+        assert!(last.registration().id() == src.registration().id());
+    }
+
+    #[test]
+    fn test_fan_out_fan_in() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+        let src = context.create_constant_source();
+        let gains: Vec<_> = [1., 2., 4.]
+            .iter()
+            .map(|&g| {
+                let gain = context.create_gain();
+                gain.gain().set_value(g);
+                gain
+            })
+            .collect();
+        let gains: Vec<&dyn AudioNode> = gains.iter().map(|g| g as &dyn AudioNode).collect();
+
+        context.fan_out(&src, &gains);
+        context.fan_in(&gains, &context.destination());
+        // empty slices are no-ops
+        context.fan_out(&src, &[]);
+        context.fan_in(&[], &context.destination());
+        src.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(output.get_channel_data(0), &[7.; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_fan_in_mixes_channels() {
+        let context = OfflineAudioContext::new(2, 128, 48000.);
+        let mono = context.create_constant_source();
+        let merger = context.create_channel_merger(2);
+        let stereo = context.create_constant_source();
+        stereo.offset().set_value(2.);
+        stereo.connect_at(&merger, 0, 1);
+
+        context.fan_in(&[&mono, &merger], &context.destination());
+        mono.start();
+        stereo.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(output.get_channel_data(0), &[1.; 128][..], abs_all <= 0.);
+        assert_float_eq!(output.get_channel_data(1), &[3.; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_merge_stereo() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(2, 128, sample_rate);
+        let left = context.create_oscillator();
+        left.frequency().set_value(440.);
+        let right = context.create_oscillator();
+        right.frequency().set_value(1000.);
+
+        let merger = context.merge_stereo(&left, &right);
+        merger.connect(&context.destination());
+        left.start();
+        right.start();
+
+        let output = context.start_rendering_sync();
+        for (channel, frequency) in [(0, 440.), (1, 1000.)] {
+            let expected: Vec<f32> = (0..128)
+                .map(|i| (2. * PI * frequency * i as f32 / sample_rate).sin())
+                .collect();
+            assert_float_eq!(
+                output.get_channel_data(channel),
+                &expected[..],
+                abs_all <= 1e-3
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_stereo_first_channel() {
+        let context = OfflineAudioContext::new(2, 128, 48000.);
+        // quad sources with a different value in each channel, the speakers down-mix to stereo
+        // would mix the surround channels into the first channel
+        let quad_source = |values: [f32; 4]| {
+            let merger = context.create_channel_merger(4);
+            values.iter().enumerate().for_each(|(i, &value)| {
+                let src = context.create_constant_source();
+                src.offset().set_value(value);
+                src.connect_at(&merger, 0, i);
+                src.start();
+            });
+            merger
+        };
+
+        let left = quad_source([1., 2., 10., 20.]);
+        let right = quad_source([3., 4., 30., 40.]);
+        context
+            .merge_stereo(&left, &right)
+            .connect(&context.destination());
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(output.get_channel_data(0), &[1.; 128][..], abs_all <= 0.);
+        assert_float_eq!(output.get_channel_data(1), &[3.; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_crossfade_swap() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 48000, sample_rate);
+        let old = context.create_oscillator();
+        old.frequency().set_value(440.);
+        old.connect(&context.destination());
+        let new = context.create_oscillator();
+        new.frequency().set_value(1000.);
+        // connecting the new node beforehand does not bypass the crossfade
+        new.connect(&context.destination());
+        old.start();
+        new.start();
+
+        context.crossfade_swap(&old, &new, 0.5);
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        // the output starts with the old node and ends with the new node
+        let expected_old: Vec<f32> = (0..128)
+            .map(|i| (2. * PI * 440. * i as f32 / sample_rate).sin())
+            .collect();
+        assert_float_eq!(channel[..128], expected_old[..], abs_all <= 1e-2);
+        let expected_new: Vec<f32> = (24000..24128)
+            .map(|i| (2. * PI * 1000. * i as f32 / sample_rate).sin())
+            .collect();
+        assert_float_eq!(channel[24000..24128], expected_new[..], abs_all <= 1e-2);
+
+        // without discontinuities, a sample never jumps further than the steepest sine allows
+        let max_step = 2. * PI * 1000. / sample_rate;
+        channel
+            .windows(2)
+            .for_each(|pair| assert!((pair[1] - pair[0]).abs() < max_step));
+    }
+
+    #[test]
+    fn test_crossfade_swap_during_active_ramp() {
+        let context = OfflineAudioContext::new(1, 48000, 48000.);
+        let first = context.create_constant_source();
+        first.connect(&context.destination());
+        let second = context.create_constant_source();
+        second.offset().set_value(2.);
+        let third = context.create_constant_source();
+        third.offset().set_value(4.);
+        first.start();
+        second.start();
+        third.start();
+
+        context.crossfade_swap(&first, &second, 0.5);
+        context.crossfade_swap(&second, &third, 0.25);
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        let expected: Vec<f32> = (0..48000)
+            .map(|i| {
+                let t = i as f32 / 48000.;
+                let first_gain = (1. - t / 0.5).max(0.);
+                let second_gain = 1. - first_gain;
+                let third_gain = (t / 0.25).min(1.);
+                first_gain + second_gain * (2. * (1. - third_gain) + 4. * third_gain)
+            })
+            .collect();
+        assert_float_eq!(channel[..], expected[..], abs_all <= 1e-3);
+        assert_float_eq!(channel[47999], 4., abs <= 0.);
+    }
+
+    #[test]
+    fn test_crossfade_swap_completion() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+        let old = context.create_constant_source();
+        old.connect(&context.destination());
+        let new = context.create_constant_source();
+
+        context.crossfade_swap(&old, &new, 0.01);
+
+        // the old node is disconnected and the new node is connected to the destination
+        let old_id = old.registration().id();
+        let new_id = new.registration().id();
+        let start = std::time::Instant::now();
+        while context.base().outgoing_connections(new_id) != vec![DESTINATION_NODE_ID] {
+            assert!(start.elapsed().as_secs() < 5, "crossfade did not complete");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(context.base().outgoing_connections(old_id).is_empty());
+
+        // so the new node can be swapped again
+        let newer = context.create_constant_source();
+        context.crossfade_swap(&new, &newer, 0.01);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_crossfade_swap_unconnected() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+        let old = context.create_constant_source();
+        let new = context.create_constant_source();
+        new.connect(&context.destination());
+
+        context.crossfade_swap(&old, &new, 0.1);
+    }
+
+    #[test]
+    fn test_mute_solo() {
+        let mut context = OfflineAudioContext::new(1, 128 * 9, 48000.);
+
+        // three mixer channels, the output is the sum of the audible ones
+        let source = |offset| {
+            let src = context.create_constant_source();
+            src.offset().set_value(offset);
+            src.connect(&context.destination());
+            src.start();
+            // take part in the mute/solo system
+            src.set_muted(false);
+            src
+        };
+        let a = source(1.);
+        let b = std::sync::Arc::new(source(2.));
+        let c = source(4.);
+
+        let b_clone = std::sync::Arc::clone(&b);
+        context.suspend_at(128, move |_| b_clone.set_muted(true));
+        context.suspend_at(128 * 3, move |_| a.set_soloed(true));
+        // solos are additive, the muted source stays silent
+        context.suspend_at(128 * 5, move |_| c.set_soloed(true));
+        context.suspend_at(128 * 7, move |context| {
+            context.clear_solo();
+            b.set_muted(false);
+        });
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+        let quantum = |i: usize| &channel[128 * i..128 * (i + 1)];
+
+        assert_float_eq!(quantum(0), &[7.; 128][..], abs_all <= 0.);
+        // the mute fades out over a single quantum
+        assert!(quantum(1).windows(2).all(|w| w[0] > w[1]));
+        assert_float_eq!(quantum(2), &[5.; 128][..], abs_all <= 0.);
+        assert_float_eq!(quantum(4), &[1.; 128][..], abs_all <= 0.);
+        assert_float_eq!(quantum(6), &[5.; 128][..], abs_all <= 0.);
+        assert!(quantum(7).windows(2).all(|w| w[0] < w[1]));
+        assert_float_eq!(quantum(8), &[7.; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_xrun_count() {
+        use crate::node::{AudioWorkletNodeOptions, Worklet, WorkletParamValues};
+        use crate::render::{AudioRenderQuantum, RenderScope};
+
+        // worklet that is way too slow to render in real time
+        struct Sleeper;
+
+        impl Worklet for Sleeper {
+            fn process(
+                &mut self,
+                _inputs: &[AudioRenderQuantum],
+                _outputs: &mut [AudioRenderQuantum],
+                _params: WorkletParamValues,
+                _scope: &RenderScope,
+            ) -> bool {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                true
+            }
+        }
+
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        context.register_worklet("sleeper", |_| Sleeper);
+        let sleeper = context.create_worklet("sleeper", AudioWorkletNodeOptions::default());
+        sleeper.connect(&context.destination());
+
+        let start = std::time::Instant::now();
+        while context.xrun_count() == 0 {
+            assert!(start.elapsed().as_secs() < 5, "no xrun was counted");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
 }
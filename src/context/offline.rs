@@ -1,11 +1,21 @@
 //! The `OfflineAudioContext` type
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 
 use crate::buffer::AudioBuffer;
 use crate::context::{BaseAudioContext, ConcreteBaseAudioContext};
+use crate::events::{Callback, Event, EventType};
+use crate::media::write_wav;
+use crate::render::graph::{Graph, DEFAULT_POOL_CAPACITY};
 use crate::render::RenderThread;
-use crate::{assert_valid_sample_rate, RENDER_QUANTUM_SIZE};
+use crate::{assert_valid_sample_rate, ProcessorErrorEvent, RENDER_QUANTUM_SIZE};
+
+/// Level below which trailing samples are trimmed by
+/// [`OfflineAudioContext::render_until_silent`] (-120 dBFS)
+const SILENCE_THRESHOLD: f32 = 1e-6;
 
 /// The `OfflineAudioContext` doesn't render the audio to the device hardware; instead, it generates
 /// it, as fast as it can, and outputs the result to an `AudioBuffer`.
@@ -17,9 +27,13 @@ pub struct OfflineAudioContext {
     /// the size of the buffer in sample-frames
     length: usize,
     /// the rendering 'thread', fully controlled by the offline context
-    renderer: SingleUseRenderThread,
+    renderer: Option<SingleUseRenderThread>,
+    /// callbacks to run when rendering reaches the given frame, see `suspend_at`
+    suspends: Vec<(usize, SuspendCallback)>,
 }
 
+type SuspendCallback = Box<dyn FnOnce(&mut OfflineAudioContext) + Send + Sync + 'static>;
+
 mod private {
     use super::*;
 
@@ -30,8 +44,21 @@ mod private {
             Self(rt)
         }
 
-        pub fn render_audiobuffer(self, buffer_size: usize) -> AudioBuffer {
-            self.0.render_audiobuffer(buffer_size)
+        pub fn render_audiobuffer<F: FnMut(usize)>(
+            self,
+            buffer_size: usize,
+            suspend: F,
+        ) -> AudioBuffer {
+            self.0.render_audiobuffer(buffer_size, suspend)
+        }
+
+        pub fn render_audiobuffer_until_inactive<F: FnMut(usize)>(
+            self,
+            max_buffer_size: usize,
+            suspend: F,
+        ) -> AudioBuffer {
+            self.0
+                .render_audiobuffer_until_inactive(max_buffer_size, suspend)
         }
     }
 
@@ -59,14 +86,44 @@ impl OfflineAudioContext {
     /// * `length` - length of the rendering audio buffer
     /// * `sample_rate` - output sample rate
     #[must_use]
-    #[allow(clippy::missing_panics_doc)]
     pub fn new(number_of_channels: usize, length: usize, sample_rate: f32) -> Self {
+        Self::new_with_render_quantum_size(
+            number_of_channels,
+            length,
+            sample_rate,
+            RENDER_QUANTUM_SIZE,
+        )
+    }
+
+    /// Creates an `OfflineAudioContext` instance rendering blocks of `render_quantum_size`
+    /// sample frames, instead of [`RENDER_QUANTUM_SIZE`]
+    ///
+    /// This constructor is not part of the Web Audio API specification.
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - number of output channels to render
+    /// * `length` - length of the rendering audio buffer
+    /// * `sample_rate` - output sample rate
+    /// * `render_quantum_size` - number of sample frames rendered at once
+    ///
+    /// # Panics
+    ///
+    /// Panics if the render quantum size is not a power of two in the range `[32, 4096]`
+    #[must_use]
+    pub fn new_with_render_quantum_size(
+        number_of_channels: usize,
+        length: usize,
+        sample_rate: f32,
+        render_quantum_size: usize,
+    ) -> Self {
         assert_valid_sample_rate(sample_rate);
+        crate::assert_valid_render_quantum_size(render_quantum_size);
 
         // communication channel to the render thread
         let (sender, receiver) = crossbeam_channel::unbounded();
 
-        let graph = crate::render::graph::Graph::new();
+        let graph = Graph::with_pool_capacity(DEFAULT_POOL_CAPACITY, render_quantum_size);
         let message = crate::message::ControlMessage::Startup { graph };
         sender.send(message).unwrap();
 
@@ -74,49 +131,248 @@ impl OfflineAudioContext {
         let frames_played = Arc::new(AtomicU64::new(0));
         let frames_played_clone = frames_played.clone();
 
+        // events from the render thread, handled by the event loop of the base context
+        let (event_send, event_recv) = crossbeam_channel::unbounded();
+
         // setup the render 'thread', which will run inside the control thread
         let renderer = RenderThread::new(
             sample_rate,
             number_of_channels,
             receiver,
             frames_played_clone,
+            // rendering is not real-time, so there are no xruns to count
+            Arc::new(AtomicU64::new(0)),
             None,
-            None,
+            Some(event_send),
         );
 
         // first, setup the base audio context
         let base = ConcreteBaseAudioContext::new(
             sample_rate,
+            render_quantum_size,
             number_of_channels,
             frames_played,
             sender,
-            None,
+            Some(event_recv),
             true,
         );
 
         Self {
             base,
             length,
-            renderer: SingleUseRenderThread::new(renderer),
+            renderer: Some(SingleUseRenderThread::new(renderer)),
+            suspends: Vec::new(),
         }
     }
 
+    /// Seed the random number generator of the context, for reproducible renders
+    ///
+    /// Each stochastic node (e.g. a `NoiseSourceNode`) created without a seed of its own after
+    /// this call draws an independent stream from this generator. The streams only depend on
+    /// the seed and on the order in which the stochastic nodes are created, other nodes do not
+    /// affect them.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    pub fn set_random_seed(&self, seed: u64) {
+        self.base.set_random_seed(seed);
+    }
+
     /// Given the current connections and scheduled changes, starts rendering audio.
     ///
     /// This function will block the current thread and returns the rendered `AudioBuffer`
     /// synchronously. An async version is currently not implemented.
-    pub fn start_rendering_sync(self) -> AudioBuffer {
-        // make buffer_size always a multiple of RENDER_QUANTUM_SIZE, so we can still render piecewise with
-        // the desired number of frames.
-        let buffer_size =
-            (self.length + RENDER_QUANTUM_SIZE - 1) / RENDER_QUANTUM_SIZE * RENDER_QUANTUM_SIZE;
+    #[allow(clippy::missing_panics_doc)]
+    pub fn start_rendering_sync(mut self) -> AudioBuffer {
+        // make buffer_size always a multiple of the render quantum size, so we can still render
+        // piecewise with the desired number of frames.
+        let render_quantum_size = self.render_quantum_size();
+        let buffer_size = self.length.div_ceil(render_quantum_size) * render_quantum_size;
 
-        let mut buf = self.renderer.render_audiobuffer(buffer_size);
+        let renderer = self.renderer.take().unwrap();
+        let mut buf = renderer.render_audiobuffer(buffer_size, |frame| self.run_suspends(frame));
         let _split = buf.split_off(self.length);
 
         buf
     }
 
+    /// Starts rendering audio and continues until all sources have stopped and all tails have
+    /// drained, ignoring the length of the context.
+    ///
+    /// `max_frames` takes precedence over the [`length`](Self::length) given at construction:
+    /// the returned buffer can be shorter or longer than `length`, but never longer than
+    /// `max_frames`.
+    ///
+    /// Rendering stops as soon as no node feeding another node reports a tail anymore (see
+    /// [`AudioProcessor::process`](crate::render::AudioProcessor::process)), or after
+    /// `max_frames` frames. The trailing samples with an amplitude below -120 dBFS are then
+    /// trimmed off the returned buffer.
+    ///
+    /// Nodes with an infinite tail keep the rendering going up to `max_frames`: a running
+    /// oscillator, or a `DelayNode` in a feedback loop, which reports a tail as long as it is
+    /// connected. In the latter case the buffer is still trimmed where the feedback has decayed
+    /// below the threshold.
+    ///
+    /// Like [`start_rendering_sync`](Self::start_rendering_sync), this function will block the
+    /// current thread and returns the rendered `AudioBuffer` synchronously.
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn render_until_silent(mut self, max_frames: usize) -> AudioBuffer {
+        let render_quantum_size = self.render_quantum_size();
+        let buffer_size = max_frames.div_ceil(render_quantum_size) * render_quantum_size;
+
+        let renderer = self.renderer.take().unwrap();
+        let mut buf = renderer
+            .render_audiobuffer_until_inactive(buffer_size, |frame| self.run_suspends(frame));
+
+        let audible_length = (0..buf.number_of_channels())
+            .map(|channel| {
+                buf.get_channel_data(channel)
+                    .iter()
+                    .rposition(|v| v.abs() >= SILENCE_THRESHOLD)
+                    .map_or(0, |i| i + 1)
+            })
+            .max()
+            .unwrap_or(0);
+        let _split = buf.split_off(audible_length.min(max_frames));
+
+        buf
+    }
+
+    /// Renders the given duration of audio, in seconds, and writes it to a WAV file at `path`
+    ///
+    /// The duration takes precedence over the [`length`](Self::length) given at construction.
+    /// The samples are written losslessly as 32-bit floats, see
+    /// [`write_wav`](crate::media::write_wav).
+    ///
+    /// The file is first written to a temporary file next to `path`, which is then renamed:
+    /// `path` is only created or replaced once the whole file has been written successfully.
+    ///
+    /// Like [`start_rendering_sync`](Self::start_rendering_sync), this function will block the
+    /// current thread until the file has been written.
+    ///
+    /// This function is not part of the Web Audio API specification.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the errors of the file system, e.g. if the parent directory of
+    /// `path` does not exist.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the duration is negative or not finite.
+    pub fn render_to_wav<P: AsRef<Path>>(mut self, path: P, duration: f64) -> io::Result<()> {
+        assert!(
+            duration.is_finite() && duration >= 0.,
+            "RangeError - Invalid duration: {:?}, should be a positive finite number",
+            duration
+        );
+
+        let path = path.as_ref();
+        let mut tmp_name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path is not a file"))?
+            .to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+
+        let length = (duration * f64::from(self.sample_rate())).round() as usize;
+        let render_quantum_size = self.render_quantum_size();
+        let buffer_size = length.div_ceil(render_quantum_size) * render_quantum_size;
+
+        let renderer = self.renderer.take().unwrap();
+        let mut buf = renderer.render_audiobuffer(buffer_size, |frame| self.run_suspends(frame));
+        let _split = buf.split_off(length);
+
+        let write = || {
+            let mut file = File::create(&tmp_path)?;
+            write_wav(&buf, &mut file)?;
+            file.sync_all()
+        };
+        let result = write().and_then(|()| fs::rename(&tmp_path, path));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+
+        result
+    }
+
+    /// Suspend the rendering when it reaches the given frame, to run the callback
+    ///
+    /// The callback can alter the audio graph through the context, e.g. change the values of
+    /// `AudioParam`s or add and connect nodes. The rendering resumes once the callback returns,
+    /// the changes take effect exactly from the suspend frame onwards.
+    ///
+    /// The graph can only be altered at render quantum boundaries: the frame is rounded up to the
+    /// next multiple of the [render quantum size](BaseAudioContext::render_quantum_size), like the
+    /// suspend time of the `OfflineAudioContext` of the Web Audio API. Multiple suspends run in
+    /// order of their frame. A callback can schedule suspends at later frames.
+    ///
+    /// This method is the synchronous counterpart of `suspend` of the Web Audio API
+    /// specification, where the promise resolves before the rendering resumes.
+    ///
+    /// # Panics
+    ///
+    /// Panics when:
+    /// - the rounded frame is not smaller than the length of the context
+    /// - the rounded frame has already been rendered
+    /// - a suspend is already scheduled at the rounded frame
+    pub fn suspend_at<F: FnOnce(&mut Self) + Send + Sync + 'static>(
+        &mut self,
+        frame: usize,
+        callback: F,
+    ) {
+        let render_quantum_size = self.render_quantum_size();
+        let frame = frame.div_ceil(render_quantum_size) * render_quantum_size;
+
+        assert!(
+            frame < self.length,
+            "InvalidStateError - cannot suspend at frame {} beyond the length {}",
+            frame,
+            self.length
+        );
+        assert!(
+            frame as u64 >= self.current_frame(),
+            "InvalidStateError - cannot suspend at frame {} which has already been rendered",
+            frame
+        );
+        assert!(
+            !self.suspends.iter().any(|(f, _)| *f == frame),
+            "InvalidStateError - a suspend is already scheduled at frame {}",
+            frame
+        );
+
+        self.suspends.push((frame, Box::new(callback)));
+    }
+
+    /// Run the callbacks of the suspends at the given frame
+    fn run_suspends(&mut self, frame: usize) {
+        // a callback may schedule another suspend at this frame
+        while let Some(index) = self.suspends.iter().position(|(f, _)| *f == frame) {
+            let (_, callback) = self.suspends.remove(index);
+            (callback)(self);
+        }
+    }
+
+    /// Register callback to run when an [`AudioProcessor`](crate::render::AudioProcessor) has
+    /// failed during rendering
+    ///
+    /// The failing node outputs silence from then on, the rest of the graph keeps rendering. The
+    /// callback runs on a dedicated event thread and may therefore still be invoked after the
+    /// rendered buffer has been returned.
+    ///
+    /// Calling this function multiple times will accumulate all event handlers. It is currently
+    /// not possible to remove an event handler.
+    pub fn onerror<F: FnMut(ProcessorErrorEvent) + Send + 'static>(&self, mut callback: F) {
+        self.base().register_event_handler(
+            EventType::ProcessorError,
+            Callback::Multiple(Box::new(move |event| {
+                if let Event::ProcessorError(error) = event {
+                    callback(error)
+                }
+            })),
+        );
+    }
+
     /// get the length of rendering audio buffer
     // false positive: OfflineAudioContext is not const
     #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
@@ -129,7 +385,9 @@ impl OfflineAudioContext {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
     use float_eq::assert_float_eq;
+    use std::f32::consts::PI;
 
     #[test]
     fn render_empty_graph() {
@@ -141,4 +399,287 @@ mod tests {
         assert_float_eq!(buffer.get_channel_data(0), &[0.; 555][..], abs_all <= 0.);
         assert_float_eq!(buffer.get_channel_data(1), &[0.; 555][..], abs_all <= 0.);
     }
+
+    #[test]
+    fn test_render_is_reproducible() {
+        fn render() -> Vec<u32> {
+            let context = OfflineAudioContext::new(1, 512, 44_100.);
+            let gain = context.create_gain();
+            gain.connect(&context.destination());
+
+            // values of very different magnitudes, their sum depends on the summing order
+            for i in 0..32 {
+                let src = context.create_oscillator();
+                src.frequency().set_value(100. * (i + 1) as f32);
+                let amp = context.create_gain();
+                amp.gain().set_value(10_f32.powi(i % 8 - 4));
+                src.connect(&amp);
+                amp.connect(&gain);
+                src.start();
+            }
+
+            let output = context.start_rendering_sync();
+            output
+                .get_channel_data(0)
+                .iter()
+                .map(|v| v.to_bits())
+                .collect()
+        }
+
+        assert_eq!(render(), render());
+    }
+
+    #[test]
+    fn test_random_seed() {
+        // two noise sources, optionally with an unrelated node created in between
+        fn render(seed: u64, extra_node: bool) -> Vec<u32> {
+            let context = OfflineAudioContext::new(1, 512, 44_100.);
+            context.set_random_seed(seed);
+
+            let noise = context.create_noise_source();
+            noise.connect(&context.destination());
+            noise.start();
+
+            if extra_node {
+                let gain = context.create_gain();
+                gain.connect(&context.destination());
+            }
+
+            let filtered = context.create_filtered_noise();
+            filtered.connect(&context.destination());
+            filtered.start();
+
+            let output = context.start_rendering_sync();
+            output
+                .get_channel_data(0)
+                .iter()
+                .map(|v| v.to_bits())
+                .collect()
+        }
+
+        // bit identical with the same seed
+        assert_eq!(render(1, false), render(1, false));
+        // other nodes do not change the random streams
+        assert_eq!(render(1, false), render(1, true));
+        // different seeds yield different output
+        assert_ne!(render(1, false), render(2, false));
+    }
+
+    #[test]
+    fn test_render_quantum_sizes() {
+        let render = |render_quantum_size| {
+            // not a multiple of any render quantum size
+            let context = OfflineAudioContext::new_with_render_quantum_size(
+                1,
+                4_800,
+                48_000.,
+                render_quantum_size,
+            );
+            assert_eq!(context.render_quantum_size(), render_quantum_size);
+
+            let osc = context.create_oscillator();
+            osc.frequency().set_value(440.);
+            osc.frequency().linear_ramp_to_value_at_time(880., 0.05);
+            osc.start_at(0.001);
+            osc.stop_at(0.09);
+
+            let filter = context.create_biquad_filter();
+            filter.frequency().set_value(1000.);
+            let delay = context.create_delay(1.);
+            delay.delay_time().set_value(0.0015);
+            let gain = context.create_gain();
+            // between two frames, so the change does not depend on rounding
+            gain.gain().set_value_at_time(0.5, 960.5 / 48_000.);
+
+            osc.connect(&filter);
+            filter.connect(&delay);
+            delay.connect(&gain);
+            gain.connect(&context.destination());
+
+            context.start_rendering_sync()
+        };
+
+        let expected = render(RENDER_QUANTUM_SIZE);
+        assert!(expected.get_channel_data(0).iter().any(|&v| v.abs() > 0.1));
+
+        for render_quantum_size in [64, 256] {
+            let output = render(render_quantum_size);
+            assert_eq!(output.length(), 4_800);
+            assert_float_eq!(
+                output.get_channel_data(0),
+                expected.get_channel_data(0),
+                abs_all <= 1e-4
+            );
+        }
+    }
+
+    #[test]
+    fn test_suspend_at_render_quantum_size() {
+        // rounded up to the next render quantum
+        for (render_quantum_size, expected) in [(64, 128), (256, 256)] {
+            let mut context = OfflineAudioContext::new_with_render_quantum_size(
+                1,
+                1024,
+                48_000.,
+                render_quantum_size,
+            );
+            context.suspend_at(100, move |context| {
+                assert_eq!(context.current_frame(), expected);
+            });
+            let _ = context.start_rendering_sync();
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_render_quantum_size() {
+        let _ = OfflineAudioContext::new_with_render_quantum_size(1, 1024, 48_000., 100);
+    }
+
+    #[test]
+    fn test_suspend_at() {
+        let sample_rate = 48_000.;
+        let render = |suspend: bool| {
+            let mut context = OfflineAudioContext::new(1, 1024, sample_rate);
+            let osc = context.create_oscillator();
+            osc.connect(&context.destination());
+            osc.start();
+            if suspend {
+                context.suspend_at(256, move |context| {
+                    assert_eq!(context.current_frame(), 256);
+                    osc.frequency().set_value(880.);
+                });
+            } else {
+                osc.frequency()
+                    .set_value_at_time(880., 256. / sample_rate as f64);
+            }
+            context.start_rendering_sync()
+        };
+
+        let output = render(true);
+        let expected = render(false);
+        assert_float_eq!(
+            output.get_channel_data(0),
+            expected.get_channel_data(0),
+            abs_all <= 0.
+        );
+
+        // the quantum starting at the suspend frame already reflects the change
+        let channel = output.get_channel_data(0);
+        let phase = |n: usize| {
+            let cycles = if n < 256 {
+                440. * n as f32
+            } else {
+                440. * 256. + 880. * (n - 256) as f32
+            };
+            (2. * PI * cycles / sample_rate).sin()
+        };
+        let expected: Vec<f32> = (0..1024).map(phase).collect();
+        assert_float_eq!(channel[..], expected[..], abs_all <= 1e-3);
+    }
+
+    #[test]
+    fn test_multiple_suspends() {
+        let mut context = OfflineAudioContext::new(1, 512, 44_100.);
+        let src = Arc::new(context.create_constant_source());
+        src.connect(&context.destination());
+        src.start();
+
+        // scheduled out of order, the frames are rounded up to the render quantum
+        let src_clone = Arc::clone(&src);
+        context.suspend_at(300, move |_| {
+            src_clone.offset().set_value(3.);
+        });
+        context.suspend_at(1, move |context| {
+            src.offset().set_value(2.);
+            // a callback can schedule a later suspend
+            let src = context.create_constant_source();
+            src.connect(&context.destination());
+            src.start();
+            context.suspend_at(256, move |_| src.stop());
+        });
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+        assert_float_eq!(channel[..128], [1.; 128][..], abs_all <= 0.);
+        assert_float_eq!(channel[128..256], [3.; 128][..], abs_all <= 0.);
+        assert_float_eq!(channel[256..384], [2.; 128][..], abs_all <= 0.);
+        assert_float_eq!(channel[384..], [3.; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_suspend_at_same_frame() {
+        let mut context = OfflineAudioContext::new(1, 512, 44_100.);
+        context.suspend_at(128, |_| ());
+        context.suspend_at(100, |_| ());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_suspend_beyond_length() {
+        let mut context = OfflineAudioContext::new(1, 512, 44_100.);
+        context.suspend_at(512, |_| ());
+    }
+
+    #[test]
+    fn test_render_to_wav() {
+        let sample_rate = 48_000;
+        let path = std::env::temp_dir().join(format!(
+            "web-audio-api-render-to-wav-{}.wav",
+            std::process::id()
+        ));
+
+        let context = OfflineAudioContext::new(1, 128, sample_rate as f32);
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(1000.);
+        osc.connect(&context.destination());
+        osc.start();
+        // the duration takes precedence over the length
+        context.render_to_wav(&path, 0.5).unwrap();
+
+        let context = OfflineAudioContext::new(1, 1, sample_rate as f32);
+        let buffer = context
+            .decode_audio_data_sync(File::open(&path).unwrap())
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(buffer.number_of_channels(), 1);
+        assert_eq!(buffer.length(), sample_rate / 2);
+        assert_float_eq!(buffer.sample_rate(), sample_rate as f32, abs <= 0.);
+
+        // 2 Hz bins
+        let spectrum = crate::analysis::power_spectrum(buffer.get_channel_data(0));
+        let peak = spectrum
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i * 2)
+            .unwrap();
+        assert_eq!(peak, 1000);
+    }
+
+    #[test]
+    fn test_render_to_wav_error() {
+        let dir = std::env::temp_dir().join(format!(
+            "web-audio-api-render-to-wav-missing-{}",
+            std::process::id()
+        ));
+        let path = dir.join("output.wav");
+
+        let context = OfflineAudioContext::new(1, 128, 48_000.);
+        let result = context.render_to_wav(&path, 0.1);
+
+        assert!(result.is_err());
+        assert!(!path.exists());
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_render_to_wav_invalid_duration() {
+        let path = std::env::temp_dir().join("web-audio-api-render-to-wav-invalid.wav");
+        let context = OfflineAudioContext::new(1, 128, 48_000.);
+        let _ = context.render_to_wav(path, -1.);
+    }
 }
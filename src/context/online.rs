@@ -6,12 +6,29 @@ use crate::io::{
 use crate::media::{MediaElement, MediaStream};
 use crate::message::ControlMessage;
 use crate::node::{self, ChannelConfigOptions};
-use crate::AudioRenderCapacity;
+use crate::render::graph::{Graph, DEFAULT_POOL_CAPACITY};
+use crate::render::{AllocStats, RenderPoolMetrics, RenderThread};
+use crate::RENDER_QUANTUM_SIZE;
+use crate::{AudioBuffer, AudioBufferOptions, AudioRenderCapacity, ProcessorErrorEvent};
 
-use crate::events::{Callback, Event};
+use crate::events::{Callback, Event, EventType};
 use crossbeam_channel::Sender;
 use std::error::Error;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[cfg(any(feature = "cpal", feature = "cubeb", test))]
+use std::{panic::AssertUnwindSafe, time::Duration};
+
+/// Delay before the first attempt to reopen a failed audio device, doubled for every failure
+#[cfg(any(feature = "cpal", feature = "cubeb", test))]
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(100);
+/// Upper bound of the delay between two attempts to reopen a failed audio device
+#[cfg(any(feature = "cpal", feature = "cubeb", test))]
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(5);
+/// Max waiting time to retrieve the audio graph from a failed render thread
+#[cfg(any(feature = "cpal", feature = "cubeb", test))]
+const GRAPH_RECOVERY_TIMEOUT: Duration = Duration::from_secs(1);
 
 /// Check if the provided sink_id is available for playback
 ///
@@ -65,7 +82,7 @@ impl Default for AudioContextLatencyCategory {
 ///     sample_rate: Some(44100.),
 ///     ..AudioContextOptions::default()
 /// };
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct AudioContextOptions {
     /// Identify the type of playback, which affects tradeoffs between audio output latency and
     /// power consumption.
@@ -79,6 +96,67 @@ pub struct AudioContextOptions {
     /// - use `"none"` to process the audio graph without playing through an audio output device.
     /// - use `"sinkId"` to use the specified audio sink id, obtained with [`enumerate_devices`]
     pub sink_id: String,
+
+    /// Number of render quantum buffers that are pre-allocated for the audio graph. Use `None`
+    /// for a default value.
+    ///
+    /// Large audio graphs may require more buffers than available in the pool. Additional
+    /// buffers are then allocated on demand and added to the pool. Use
+    /// [`AudioContext::render_pool_metrics`] to check the pool usage.
+    pub render_pool_capacity: Option<usize>,
+
+    /// Log a warning when buffers are allocated on demand because the render quantum buffer
+    /// pool is exhausted. Defaults to `true`.
+    ///
+    /// The allocations are reported by the render thread, the warning is logged on the control
+    /// side to keep the audio callback real-time safe.
+    pub render_pool_warning: bool,
+
+    /// Number of sample frames the audio graph renders at once, a power of two in the range
+    /// `[32, 4096]`. Defaults to [`RENDER_QUANTUM_SIZE`](crate::RENDER_QUANTUM_SIZE).
+    ///
+    /// Smaller sizes lower the latency at the cost of a higher processing overhead, a size
+    /// matching the block size of the host avoids buffering between the host and the graph.
+    ///
+    /// This option is not part of the Web Audio API specification.
+    pub render_quantum_size: usize,
+
+    /// Replace NaN and infinite values by zero at the output of every node, including the
+    /// computed values of the `AudioParam`s. Defaults to `true` in debug builds and `false` in
+    /// release builds.
+    ///
+    /// Such values, e.g. caused by setting a param to NaN, would otherwise spread to all
+    /// downstream nodes and reach the audio output device. A warning is logged the first time
+    /// the output of a node is sanitized. The check is a single pass over the output samples of
+    /// each node.
+    ///
+    /// This option is not part of the Web Audio API specification.
+    pub sanitize_output: bool,
+
+    /// Seed of the random number generator of the context, for reproducible renders. Use `None`
+    /// for random output.
+    ///
+    /// Each stochastic node (e.g. a `NoiseSourceNode`) created without a seed of its own draws
+    /// an independent stream from this generator. The streams only depend on the seed and on
+    /// the order in which the stochastic nodes are created, other nodes do not affect them.
+    ///
+    /// This option is not part of the Web Audio API specification.
+    pub random_seed: Option<u64>,
+}
+
+impl Default for AudioContextOptions {
+    fn default() -> Self {
+        Self {
+            latency_hint: AudioContextLatencyCategory::default(),
+            sample_rate: None,
+            sink_id: String::new(),
+            render_pool_capacity: None,
+            render_pool_warning: true,
+            render_quantum_size: RENDER_QUANTUM_SIZE,
+            sanitize_output: cfg!(debug_assertions),
+            random_seed: None,
+        }
+    }
 }
 
 /// This interface represents an audio graph whose `AudioDestinationNode` is routed to a real-time
@@ -88,14 +166,46 @@ pub struct AudioContextOptions {
 pub struct AudioContext {
     /// represents the underlying `BaseAudioContext`
     base: ConcreteBaseAudioContext,
-    /// audio backend (play/pause functionality)
-    backend_manager: Mutex<Box<dyn AudioBackendManager>>,
+    /// audio backend, shared with the device error handler
+    backend: Arc<SharedBackend>,
     /// Provider for rendering performance metrics
     render_capacity: AudioRenderCapacity,
+    /// Usage counters of the render quantum buffer pool
+    render_pool_stats: Arc<AllocStats>,
+    /// Number of render calls that were late, shared with the render thread
+    xruns: Arc<AtomicU64>,
+    /// Render thread driven by the host, for contexts created with `new_manual`
+    manual_render: Option<ManualRender>,
+}
+
+/// Render thread of a context without audio output device
+struct ManualRender {
+    render_thread: RenderThread,
+    /// shared with the backend, false when suspended or closed
+    running: Arc<AtomicBool>,
+    number_of_channels: usize,
+    /// Last render quantum written to the output, deinterleaved
+    last_output: AudioBuffer,
+}
+
+/// Audio backend of the `AudioContext`, with everything required to rebuild it
+struct SharedBackend {
+    /// represents the underlying `BaseAudioContext`
+    base: ConcreteBaseAudioContext,
+    /// audio backend (play/pause functionality)
+    backend_manager: Mutex<Box<dyn AudioBackendManager>>,
     /// Initializer for the render thread (when restart is required)
     render_thread_init: RenderThreadInit,
+    /// Requested latency, reused when the backend is rebuilt
+    latency_hint: AudioContextLatencyCategory,
     /// Sender for events that will be handled by the EventLoop
     event_send: Sender<Event>,
+    /// Number of pre-allocated buffers of the audio graph (when it must be recreated)
+    #[cfg(any(feature = "cpal", feature = "cubeb", test))]
+    render_pool_capacity: usize,
+    /// Replace non-finite output values (when the audio graph must be recreated)
+    #[cfg(any(feature = "cpal", feature = "cubeb", test))]
+    sanitize_output: bool,
 }
 
 impl BaseAudioContext for AudioContext {
@@ -133,33 +243,114 @@ impl AudioContext {
     ///
     /// # Panics
     ///
-    /// The `AudioContext` constructor will panic when an invalid `sinkId` is provided in the
-    /// `AudioContextOptions`. In a future version, a `try_new` constructor will be introduced that
-    /// never panics.
+    /// The `AudioContext` constructor will panic when an invalid `sinkId` or `render_quantum_size`
+    /// is provided in the `AudioContextOptions`. In a future version, a `try_new` constructor will
+    /// be introduced that never panics.
     #[allow(clippy::needless_pass_by_value)]
     #[must_use]
     pub fn new(options: AudioContextOptions) -> Self {
         if !is_valid_sink_id(&options.sink_id) {
             panic!("NotFoundError: invalid sinkId {:?}", options.sink_id);
         }
+        crate::assert_valid_render_quantum_size(options.render_quantum_size);
+
+        let (control_thread_init, render_thread_init) = io::thread_init();
+        let backend = io::build_output(options.clone(), render_thread_init.clone());
+
+        Self::from_backend(backend, control_thread_init, render_thread_init, &options)
+    }
+
+    /// Creates an `AudioContext` without audio output device, rendered by the host application
+    ///
+    /// The host pulls the rendered audio with [`render_into`](Self::render_into), typically
+    /// from an audio callback it owns, e.g. the one of a game engine. Time only progresses when
+    /// audio is pulled.
+    ///
+    /// This also serves as a null backend for tests: the last rendered quantum can be inspected
+    /// with [`last_output_quantum`](Self::last_output_quantum).
+    ///
+    /// ```
+    /// use web_audio_api::context::{AudioContext, BaseAudioContext};
+    /// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+    ///
+    /// let mut context = AudioContext::new_manual(48000., 2);
+    ///
+    /// let osc = context.create_oscillator();
+    /// osc.connect(&context.destination());
+    /// osc.start();
+    ///
+    /// // in the audio callback of the host, fill the interleaved output buffer
+    /// let mut output = vec![0.; 2 * 480];
+    /// context.render_into(&mut output);
+    /// ```
+    ///
+    /// This constructor is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the sample rate is outside of the supported range, or if
+    /// `number_of_channels` is zero or greater than [`MAX_CHANNELS`](crate::MAX_CHANNELS)
+    #[must_use]
+    pub fn new_manual(sample_rate: f32, number_of_channels: usize) -> Self {
+        crate::assert_valid_sample_rate(sample_rate);
+        crate::assert_valid_number_of_channels(number_of_channels);
 
         let (control_thread_init, render_thread_init) = io::thread_init();
-        let backend = io::build_output(options, render_thread_init.clone());
+        let (backend, render_thread) =
+            io::ManualBackend::build(sample_rate, number_of_channels, render_thread_init.clone());
+        let running = backend.running();
+
+        let mut context = Self::from_backend(
+            Box::new(backend),
+            control_thread_init,
+            render_thread_init,
+            &AudioContextOptions::default(),
+        );
+        let last_output = AudioBuffer::new(AudioBufferOptions {
+            number_of_channels,
+            length: RENDER_QUANTUM_SIZE,
+            sample_rate,
+        });
+        context.manual_render = Some(ManualRender {
+            render_thread,
+            running,
+            number_of_channels,
+            last_output,
+        });
+
+        context
+    }
+
+    /// Setup the context around the render thread of the given backend
+    fn from_backend(
+        backend: Box<dyn AudioBackendManager>,
+        control_thread_init: ControlThreadInit,
+        render_thread_init: RenderThreadInit,
+        options: &AudioContextOptions,
+    ) -> Self {
+        let render_pool_capacity = options
+            .render_pool_capacity
+            .unwrap_or(DEFAULT_POOL_CAPACITY);
+        let render_quantum_size = options.render_quantum_size;
 
         let ControlThreadInit {
             frames_played,
+            xruns,
             ctrl_msg_send,
             load_value_recv,
             event_send,
             event_recv,
         } = control_thread_init;
 
-        let graph = crate::render::graph::Graph::new();
+        let mut graph = Graph::with_pool_capacity(render_pool_capacity, render_quantum_size);
+        graph.set_sanitize_output(options.sanitize_output);
+        let render_pool_stats = graph.alloc_stats();
         let message = crate::message::ControlMessage::Startup { graph };
         ctrl_msg_send.send(message).unwrap();
 
         let base = ConcreteBaseAudioContext::new(
             backend.sample_rate(),
+            render_quantum_size,
             backend.number_of_channels(),
             frames_played,
             ctrl_msg_send,
@@ -167,29 +358,187 @@ impl AudioContext {
             false,
         );
         base.set_state(AudioContextState::Running);
+        if let Some(seed) = options.random_seed {
+            base.set_random_seed(seed);
+        }
 
         // setup AudioRenderCapacity for this context
         let base_clone = base.clone();
         let render_capacity = AudioRenderCapacity::new(base_clone, load_value_recv);
 
-        Self {
-            base,
+        let backend = Arc::new(SharedBackend {
+            base: base.clone(),
             backend_manager: Mutex::new(backend),
-            render_capacity,
             render_thread_init,
+            latency_hint: options.latency_hint,
             event_send,
+            #[cfg(any(feature = "cpal", feature = "cubeb", test))]
+            render_pool_capacity,
+            #[cfg(any(feature = "cpal", feature = "cubeb", test))]
+            sanitize_output: options.sanitize_output,
+        });
+
+        if options.render_pool_warning {
+            base.register_event_handler(
+                EventType::RenderPoolExhausted,
+                Callback::Multiple(Box::new(|event| {
+                    if let Event::RenderPoolExhausted(allocations) = event {
+                        log::warn!(
+                            "Render pool exhausted, {} buffers allocated on demand. Consider increasing the `render_pool_capacity`",
+                            allocations
+                        );
+                    }
+                })),
+            );
+        }
+
+        if options.sanitize_output {
+            base.register_event_handler(
+                EventType::OutputSanitized,
+                Callback::Multiple(Box::new(|event| {
+                    if let Event::OutputSanitized(id) = event {
+                        log::warn!(
+                            "NaN or infinite values in the output of node {:?} replaced by zero",
+                            id.0
+                        );
+                    }
+                })),
+            );
+        }
+
+        // try to reopen the audio device when the backend reports an error, only hold a weak
+        // reference to prevent a reference cycle via the event loop
+        #[cfg(any(feature = "cpal", feature = "cubeb", test))]
+        {
+            let weak_backend = Arc::downgrade(&backend);
+            base.register_event_handler(
+                EventType::DeviceError,
+                Callback::Multiple(Box::new(move |_| {
+                    if let Some(backend) = weak_backend.upgrade() {
+                        backend.handle_device_error();
+                    }
+                })),
+            );
+        }
+
+        Self {
+            base,
+            backend,
+            render_capacity,
+            render_pool_stats,
+            xruns,
+            manual_render: None,
+        }
+    }
+
+    /// Render the audio graph into the interleaved `output` buffer of the host application
+    ///
+    /// The buffer can have any length that is a multiple of the number of channels. Audio is
+    /// rendered by blocks of [`RENDER_QUANTUM_SIZE`](crate::RENDER_QUANTUM_SIZE) frames, the
+    /// frames that do not fit in `output` are kept for the next call. The output is silent while
+    /// the context is suspended or closed.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the context was not created with
+    /// [`new_manual`](Self::new_manual), or if the length of `output` is not a multiple of the
+    /// number of channels
+    pub fn render_into(&mut self, output: &mut [f32]) {
+        let manual_render = self.manual_render.as_mut().unwrap_or_else(|| {
+            panic!("InvalidStateError - render_into requires a context created with new_manual")
+        });
+
+        if !output
+            .len()
+            .is_multiple_of(manual_render.number_of_channels)
+        {
+            panic!(
+                "IndexSizeError - output length {:?} is not a multiple of the number of channels {:?}",
+                output.len(),
+                manual_render.number_of_channels
+            );
+        }
+
+        if manual_render.running.load(Ordering::SeqCst) {
+            manual_render.render_thread.render(output);
+        } else {
+            output.fill(0.);
+        }
+
+        // keep the last render quantum of frames around for inspection
+        let number_of_channels = manual_render.number_of_channels;
+        let frames = output.len() / number_of_channels;
+        let count = frames.min(RENDER_QUANTUM_SIZE);
+        let offset = RENDER_QUANTUM_SIZE - count;
+        for channel in 0..number_of_channels {
+            let data = manual_render.last_output.get_channel_data_mut(channel);
+            data.copy_within(count.., 0);
+            data[offset..]
+                .iter_mut()
+                .zip(output[(frames - count) * number_of_channels..].chunks(number_of_channels))
+                .for_each(|(d, frame)| *d = frame[channel]);
         }
     }
 
+    /// The last [`RENDER_QUANTUM_SIZE`](crate::RENDER_QUANTUM_SIZE) frames written to the output
+    /// by [`render_into`](Self::render_into), deinterleaved
+    ///
+    /// When the host pulls whole render quanta, this is exactly the last quantum rendered by the
+    /// `AudioDestinationNode`, which allows to test an audio graph deterministically without an
+    /// audio output device:
+    ///
+    /// ```
+    /// use web_audio_api::context::{AudioContext, BaseAudioContext};
+    /// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+    /// use web_audio_api::RENDER_QUANTUM_SIZE;
+    ///
+    /// let mut context = AudioContext::new_manual(48000., 1);
+    ///
+    /// let src = context.create_constant_source();
+    /// src.connect(&context.destination());
+    /// src.start();
+    ///
+    /// let mut output = vec![0.; RENDER_QUANTUM_SIZE];
+    /// context.render_into(&mut output);
+    /// assert!(context.last_output_quantum().get_channel_data(0).iter().all(|&v| v == 1.));
+    /// ```
+    ///
+    /// The buffer is silent before the first call to `render_into`.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the context was not created with
+    /// [`new_manual`](Self::new_manual)
+    #[must_use]
+    pub fn last_output_quantum(&self) -> &AudioBuffer {
+        let manual_render = self.manual_render.as_ref().unwrap_or_else(|| {
+            panic!(
+                "InvalidStateError - last_output_quantum requires a context created with new_manual"
+            )
+        });
+
+        &manual_render.last_output
+    }
+
     /// This represents the number of seconds of processing latency incurred by
     /// the `AudioContext` passing the audio from the `AudioDestinationNode`
     /// to the audio subsystem.
-    // We don't do any buffering between rendering the audio and sending
-    // it to the audio subsystem, so this value is zero. (see Gecko)
-    #[allow(clippy::unused_self)]
+    ///
+    /// This is the duration of the buffer that is rendered for each audio callback, as
+    /// requested with the `latency_hint` of the [`AudioContextOptions`]. When the audio device
+    /// cannot honor the requested buffer size, the closest supported size is used instead. The
+    /// value is zero when the buffer size is unknown, i.e. when the device rejected the requested
+    /// configuration or when the host renders the audio with
+    /// [`render_into`](Self::render_into).
     #[must_use]
+    #[allow(clippy::missing_panics_doc)]
     pub fn base_latency(&self) -> f64 {
-        0.
+        let buffer_size = self.backend.backend_manager.lock().unwrap().buffer_size();
+        buffer_size as f64 / f64::from(self.sample_rate())
     }
 
     /// The estimation in seconds of audio output latency, i.e., the interval
@@ -199,7 +548,11 @@ impl AudioContext {
     #[must_use]
     #[allow(clippy::missing_panics_doc)]
     pub fn output_latency(&self) -> f64 {
-        self.backend_manager.lock().unwrap().output_latency()
+        self.backend
+            .backend_manager
+            .lock()
+            .unwrap()
+            .output_latency()
     }
 
     /// Identifier or the information of the current audio output device.
@@ -207,7 +560,12 @@ impl AudioContext {
     /// The initial value is `""`, which means the default audio output device.
     #[allow(clippy::missing_panics_doc)]
     pub fn sink_id(&self) -> String {
-        self.backend_manager.lock().unwrap().sink_id().to_owned()
+        self.backend
+            .backend_manager
+            .lock()
+            .unwrap()
+            .sink_id()
+            .to_owned()
     }
 
     /// Update the current audio output device.
@@ -225,11 +583,15 @@ impl AudioContext {
             return Ok(()); // sink is already active
         }
 
+        if self.manual_render.is_some() {
+            Err("NotSupportedError: the sink of a manually rendered AudioContext cannot change")?;
+        }
+
         if !is_valid_sink_id(&sink_id) {
             Err(format!("NotFoundError: invalid sinkId {}", sink_id))?;
         };
 
-        let mut backend_manager_guard = self.backend_manager.lock().unwrap();
+        let mut backend_manager_guard = self.backend.backend_manager.lock().unwrap();
         let original_state = self.state();
         if original_state == AudioContextState::Closed {
             return Ok(());
@@ -242,7 +604,12 @@ impl AudioContext {
         let ctrl_msg_send = self.base.lock_control_msg_sender();
 
         // Flush out the ctrl msg receiver, cache
-        let mut pending_msgs: Vec<_> = self.render_thread_init.ctrl_msg_recv.try_iter().collect();
+        let mut pending_msgs: Vec<_> = self
+            .backend
+            .render_thread_init
+            .ctrl_msg_recv
+            .try_iter()
+            .collect();
 
         // Acquire the active audio graph from the current render thread, shutting it down
         let graph = if matches!(pending_msgs.get(0), Some(ControlMessage::Startup { .. })) {
@@ -269,10 +636,15 @@ impl AudioContext {
         // hotswap the backend
         let options = AudioContextOptions {
             sample_rate: Some(self.sample_rate()),
-            latency_hint: AudioContextLatencyCategory::default(), // todo reuse existing setting
+            latency_hint: self.backend.latency_hint,
             sink_id,
+            render_pool_capacity: None, // the audio graph is reused
+            render_pool_warning: false, // the audio graph is reused
+            render_quantum_size: self.render_quantum_size(),
+            sanitize_output: false, // the audio graph is reused
+            random_seed: None,      // the nodes are reused
         };
-        *backend_manager_guard = io::build_output(options, self.render_thread_init.clone());
+        *backend_manager_guard = io::build_output(options, self.backend.render_thread_init.clone());
 
         // if the previous backend state was suspend, suspend the new one before shipping the graph
         if original_state == AudioContextState::Suspended {
@@ -296,7 +668,7 @@ impl AudioContext {
         drop(backend_manager_guard);
 
         // trigger event when all the work is done
-        let _ = self.event_send.send(Event::SinkChanged);
+        let _ = self.backend.event_send.send(Event::SinkChanged);
 
         Ok(())
     }
@@ -305,10 +677,48 @@ impl AudioContext {
     ///
     /// Calling this function multiple times will accumulate all event handlers. It is currently
     /// not possible to remove an event handler.
-    pub fn onsinkchange<F: FnMut() + Send + 'static>(&self, callback: F) {
+    pub fn onsinkchange<F: FnMut() + Send + 'static>(&self, mut callback: F) {
         self.base().register_event_handler(
-            crate::events::Event::SinkChanged,
-            Callback::Multiple(Box::new(callback)),
+            EventType::SinkChanged,
+            Callback::Multiple(Box::new(move |_| callback())),
+        );
+    }
+
+    /// Register callback to run when the state of the `AudioContext` has changed
+    ///
+    /// The callback receives the new [`AudioContextState`] and runs on a dedicated event
+    /// thread, never on the render thread. It is only invoked on actual transitions, e.g.
+    /// suspending an already suspended context will not trigger it.
+    ///
+    /// Calling this function multiple times will accumulate all event handlers. It is currently
+    /// not possible to remove an event handler.
+    pub fn onstatechange<F: FnMut(AudioContextState) + Send + 'static>(&self, mut callback: F) {
+        self.base().register_event_handler(
+            EventType::StateChange,
+            Callback::Multiple(Box::new(move |event| {
+                if let Event::StateChange(state) = event {
+                    callback(state)
+                }
+            })),
+        );
+    }
+
+    /// Register callback to run when an [`AudioProcessor`](crate::render::AudioProcessor) has
+    /// failed on the render thread
+    ///
+    /// The failing node outputs silence from then on, the rest of the graph keeps playing. The
+    /// callback runs on a dedicated event thread, never on the render thread.
+    ///
+    /// Calling this function multiple times will accumulate all event handlers. It is currently
+    /// not possible to remove an event handler.
+    pub fn onerror<F: FnMut(ProcessorErrorEvent) + Send + 'static>(&self, mut callback: F) {
+        self.base().register_event_handler(
+            EventType::ProcessorError,
+            Callback::Multiple(Box::new(move |event| {
+                if let Event::ProcessorError(error) = event {
+                    callback(error)
+                }
+            })),
         );
     }
 
@@ -328,8 +738,8 @@ impl AudioContext {
     /// * For a `BackendSpecificError`
     #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
     pub fn suspend_sync(&self) {
-        if self.backend_manager.lock().unwrap().suspend() {
-            self.base().set_state(AudioContextState::Suspended);
+        if self.backend.backend_manager.lock().unwrap().suspend() {
+            self.backend.set_state(AudioContextState::Suspended);
         }
     }
 
@@ -347,8 +757,8 @@ impl AudioContext {
     /// * For a `BackendSpecificError`
     #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
     pub fn resume_sync(&self) {
-        if self.backend_manager.lock().unwrap().resume() {
-            self.base().set_state(AudioContextState::Running);
+        if self.backend.backend_manager.lock().unwrap().resume() {
+            self.backend.set_state(AudioContextState::Running);
         }
     }
 
@@ -365,9 +775,10 @@ impl AudioContext {
     /// Will panic when this function is called multiple times
     #[allow(clippy::missing_const_for_fn, clippy::unused_self)]
     pub fn close_sync(&self) {
-        self.backend_manager.lock().unwrap().close();
+        let backend_manager_guard = self.backend.backend_manager.lock().unwrap();
+        backend_manager_guard.close();
 
-        self.base().set_state(AudioContextState::Closed);
+        self.backend.set_state(AudioContextState::Closed);
     }
 
     /// Creates a [`MediaStreamAudioSourceNode`](node::MediaStreamAudioSourceNode) from a
@@ -406,4 +817,497 @@ impl AudioContext {
     pub fn render_capacity(&self) -> &AudioRenderCapacity {
         &self.render_capacity
     }
+
+    /// Usage metrics of the pool of render quantum buffers, for debugging purposes
+    #[must_use]
+    pub fn render_pool_metrics(&self) -> RenderPoolMetrics {
+        self.render_pool_stats.metrics()
+    }
+
+    /// Number of buffer underruns (xruns) since the creation of the context
+    ///
+    /// An underrun is counted when the render thread took longer to render the audio of a
+    /// system-level audio callback than the duration of that audio, so the audio device ran out
+    /// of samples to play. The render thread only increments an atomic counter, it is safe to
+    /// poll this value periodically, e.g. to log it.
+    ///
+    /// With the `tracing` feature enabled, the render thread records `trace` level spans for
+    /// every audio callback (`render`, with the current frame, the number of frames and the
+    /// number of xruns so far) and every render quantum (`render_quantum`, with the current frame
+    /// and the number of nodes in the graph).
+    ///
+    /// This method is not part of the Web Audio API specification.
+    #[must_use]
+    pub fn xrun_count(&self) -> u64 {
+        self.xruns.load(Ordering::Relaxed)
+    }
+}
+
+impl SharedBackend {
+    /// Update the state of the context and notify the `onstatechange` handlers
+    fn set_state(&self, state: AudioContextState) {
+        let previous = self.base.set_state(state);
+        if previous != state {
+            let _ = self.event_send.send(Event::StateChange(state));
+        }
+    }
+}
+
+// Device errors are only reported by the cpal and cubeb backends
+#[cfg(any(feature = "cpal", feature = "cubeb", test))]
+impl SharedBackend {
+    /// Mark the context as interrupted and keep trying to reopen an audio device
+    ///
+    /// Reconnection attempts are made on a dedicated thread, with an exponential back off between
+    /// failed attempts. The progression of `current_time` is paused until a device is available.
+    fn handle_device_error(self: &Arc<Self>) {
+        let previous_state = self.base.state();
+        if matches!(
+            previous_state,
+            AudioContextState::Closed | AudioContextState::Interrupted
+        ) {
+            return; // nothing to revive, or already reconnecting
+        }
+
+        log::warn!("Audio device error, trying to reconnect");
+        self.set_state(AudioContextState::Interrupted);
+
+        let weak_backend = Arc::downgrade(self);
+        std::thread::spawn(move || {
+            let mut attempt = 0;
+            loop {
+                // stop trying when the AudioContext has been dropped
+                let backend = match weak_backend.upgrade() {
+                    Some(backend) => backend,
+                    None => return,
+                };
+
+                if backend.try_reconnect(previous_state) {
+                    return;
+                }
+
+                drop(backend);
+                std::thread::sleep(reconnect_delay(attempt));
+                attempt += 1;
+            }
+        });
+    }
+
+    /// Single attempt to move the audio graph to a new output stream
+    ///
+    /// Returns false when the stream could not be built and another attempt is required.
+    fn try_reconnect(&self, previous_state: AudioContextState) -> bool {
+        let mut backend_manager_guard = self.backend_manager.lock().unwrap();
+
+        // restore the state from before the interruption, unless it was changed in the meantime
+        let target_state = match self.base.state() {
+            AudioContextState::Closed => return true,
+            AudioContextState::Interrupted => previous_state,
+            state => state,
+        };
+
+        // Acquire exclusive lock on ctrl msg sender
+        let ctrl_msg_send = self.base.lock_control_msg_sender();
+
+        // Flush out the ctrl msg receiver, cache
+        let mut pending_msgs: Vec<_> = self.render_thread_init.ctrl_msg_recv.try_iter().collect();
+
+        let graph = if matches!(pending_msgs.first(), Some(ControlMessage::Startup { .. })) {
+            // The graph was never picked up by the failed render thread, or is left over from a
+            // previous failed attempt
+            match pending_msgs.remove(0) {
+                ControlMessage::Startup { graph } => graph,
+                _ => unreachable!(),
+            }
+        } else {
+            let (graph_send, graph_recv) = crossbeam_channel::bounded(1);
+            let message = ControlMessage::Shutdown { sender: graph_send };
+            ctrl_msg_send.send(message).unwrap();
+
+            // Tear down the failed stream. The render thread hands over the graph, either by
+            // handling the shutdown message or when it is dropped.
+            backend_manager_guard.close();
+
+            graph_recv
+                .recv_timeout(GRAPH_RECOVERY_TIMEOUT)
+                .unwrap_or_else(|_| {
+                    log::error!("Unable to recover the audio graph from the failed render thread");
+                    let mut graph = Graph::with_pool_capacity(
+                        self.render_pool_capacity,
+                        self.base.render_quantum_size(),
+                    );
+                    graph.set_sanitize_output(self.sanitize_output);
+                    graph
+                })
+        };
+
+        // reopen the default device (or keep processing without output for the "none" sink)
+        let sink_id = if backend_manager_guard.sink_id() == "none" {
+            "none"
+        } else {
+            ""
+        };
+        let options = AudioContextOptions {
+            sample_rate: Some(self.base.sample_rate()),
+            latency_hint: self.latency_hint,
+            sink_id: sink_id.into(),
+            render_pool_capacity: None, // the audio graph is reused
+            render_pool_warning: false, // the audio graph is reused
+            render_quantum_size: self.base.render_quantum_size(),
+            sanitize_output: false, // the audio graph is reused
+            random_seed: None,      // the nodes are reused
+        };
+
+        // building the stream panics when no device is available
+        let render_thread_init = self.render_thread_init.clone();
+        let new_backend = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            io::build_output(options, render_thread_init)
+        }));
+
+        let new_backend = match new_backend {
+            Ok(new_backend) => new_backend,
+            Err(_) => {
+                log::warn!("Failed to reopen the audio device");
+                // keep the graph and pending messages around for the next attempt
+                let message = ControlMessage::Startup { graph };
+                ctrl_msg_send.send(message).unwrap();
+                pending_msgs
+                    .into_iter()
+                    .for_each(|m| ctrl_msg_send.send(m).unwrap());
+                return false;
+            }
+        };
+
+        if target_state == AudioContextState::Suspended {
+            new_backend.suspend();
+        }
+        *backend_manager_guard = new_backend;
+
+        // send the audio graph to the new render thread, and flush the cached msgs
+        let message = ControlMessage::Startup { graph };
+        ctrl_msg_send.send(message).unwrap();
+        pending_msgs
+            .into_iter()
+            .for_each(|m| ctrl_msg_send.send(m).unwrap());
+
+        drop(ctrl_msg_send);
+        drop(backend_manager_guard);
+
+        log::info!("Audio device reconnected");
+        self.set_state(target_state);
+
+        true
+    }
+}
+
+/// Delay before the next attempt to reopen the audio device, after `attempt` failures
+#[cfg(any(feature = "cpal", feature = "cubeb", test))]
+fn reconnect_delay(attempt: u32) -> Duration {
+    RECONNECT_BASE_DELAY
+        .saturating_mul(2_u32.saturating_pow(attempt))
+        .min(RECONNECT_MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::OfflineAudioContext;
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    use float_eq::assert_float_eq;
+
+    #[test]
+    fn test_reconnect_delay() {
+        assert_eq!(reconnect_delay(0), RECONNECT_BASE_DELAY);
+        assert_eq!(reconnect_delay(1), RECONNECT_BASE_DELAY * 2);
+        assert_eq!(reconnect_delay(3), RECONNECT_BASE_DELAY * 8);
+        // back off is capped
+        assert_eq!(reconnect_delay(10), RECONNECT_MAX_DELAY);
+        assert_eq!(reconnect_delay(u32::MAX), RECONNECT_MAX_DELAY);
+    }
+
+    #[test]
+    fn test_device_error_reconnects() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        context.onstatechange(move |state| {
+            let _ = sender.send(state);
+        });
+
+        // simulate the backend reporting a stream error
+        context.backend.event_send.send(Event::DeviceError).unwrap();
+
+        let timeout = Duration::from_secs(2);
+        assert_eq!(
+            receiver.recv_timeout(timeout).unwrap(),
+            AudioContextState::Interrupted
+        );
+        assert_eq!(
+            receiver.recv_timeout(timeout).unwrap(),
+            AudioContextState::Running
+        );
+        assert_eq!(context.state(), AudioContextState::Running);
+
+        // the graph is rendered again by the new backend
+        let time = context.current_time();
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(context.current_time() > time);
+
+        // a device error on a closed context is ignored
+        context.close_sync();
+        assert_eq!(
+            receiver.recv_timeout(timeout).unwrap(),
+            AudioContextState::Closed
+        );
+        context.backend.event_send.send(Event::DeviceError).unwrap();
+        assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn test_device_error_keeps_suspended_state() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        context.onstatechange(move |state| {
+            let _ = sender.send(state);
+        });
+
+        context.suspend_sync();
+        context.backend.event_send.send(Event::DeviceError).unwrap();
+
+        let timeout = Duration::from_secs(2);
+        assert_eq!(
+            receiver.recv_timeout(timeout).unwrap(),
+            AudioContextState::Suspended
+        );
+        assert_eq!(
+            receiver.recv_timeout(timeout).unwrap(),
+            AudioContextState::Interrupted
+        );
+        assert_eq!(
+            receiver.recv_timeout(timeout).unwrap(),
+            AudioContextState::Suspended
+        );
+
+        // time does not progress while suspended
+        let time = context.current_time();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(context.current_time(), time);
+
+        context.resume_sync();
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(context.current_time() > time);
+    }
+
+    #[test]
+    fn test_manual_render_odd_block_sizes() {
+        let mut context = AudioContext::new_manual(48000., 2);
+
+        // ramp of one step per frame, identical on both channels
+        let src = context.create_constant_source();
+        src.offset().set_value_at_time(0., 0.);
+        src.offset().linear_ramp_to_value_at_time(48000., 1.);
+        src.connect(&context.destination());
+        src.start();
+
+        let mut rendered = vec![];
+        let mut frames = 0;
+        for block_frames in [1, 127, 128, 129, 300, 5, 1000] {
+            let mut output = vec![-1.; 2 * block_frames];
+            context.render_into(&mut output);
+            rendered.extend_from_slice(&output);
+            frames += block_frames;
+
+            // time advances by whole render quanta, covering the frames pulled so far
+            let current_frame = context.current_frame() as usize;
+            assert_eq!(
+                current_frame,
+                frames.div_ceil(RENDER_QUANTUM_SIZE) * RENDER_QUANTUM_SIZE
+            );
+        }
+
+        // continuous across the block boundaries
+        rendered.chunks(2).enumerate().for_each(|(i, frame)| {
+            assert_float_eq!(frame[0], i as f32, abs <= 1e-3);
+            assert_float_eq!(frame[1], i as f32, abs <= 1e-3);
+        });
+    }
+
+    #[test]
+    fn test_manual_render_suspended() {
+        let mut context = AudioContext::new_manual(48000., 1);
+        let src = context.create_constant_source();
+        src.connect(&context.destination());
+        src.start();
+
+        let mut output = vec![0.; RENDER_QUANTUM_SIZE];
+        context.render_into(&mut output);
+        assert!(output.iter().all(|&v| v == 1.));
+
+        context.suspend_sync();
+        context.render_into(&mut output);
+        assert!(output.iter().all(|&v| v == 0.));
+        assert_eq!(context.current_frame(), RENDER_QUANTUM_SIZE as u64);
+
+        context.resume_sync();
+        context.render_into(&mut output);
+        assert!(output.iter().all(|&v| v == 1.));
+        assert_eq!(context.current_frame(), 2 * RENDER_QUANTUM_SIZE as u64);
+
+        assert!(context.set_sink_id_sync("none".into()).is_err());
+        assert_eq!(context.sink_id(), "manual");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_manual_render_partial_frame() {
+        let mut context = AudioContext::new_manual(48000., 2);
+        let mut output = vec![0.; 3];
+        context.render_into(&mut output);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_render_into_requires_manual_context() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let mut context = AudioContext::new(options);
+        let mut output = vec![0.; 2 * RENDER_QUANTUM_SIZE];
+        context.render_into(&mut output);
+    }
+
+    #[test]
+    fn test_last_output_quantum() {
+        let sample_rate = 48000.;
+        let quanta = 3;
+
+        // reference rendering of the oscillator
+        let offline = OfflineAudioContext::new(2, quanta * RENDER_QUANTUM_SIZE, sample_rate);
+        let osc = offline.create_oscillator();
+        osc.frequency().set_value(440.);
+        osc.connect(&offline.destination());
+        osc.start();
+        let expected = offline.start_rendering_sync();
+
+        let mut context = AudioContext::new_manual(sample_rate, 2);
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(440.);
+        osc.connect(&context.destination());
+        osc.start();
+
+        // silent before rendering
+        assert!(context
+            .last_output_quantum()
+            .get_channel_data(0)
+            .iter()
+            .all(|&v| v == 0.));
+
+        let mut output = vec![0.; 2 * RENDER_QUANTUM_SIZE];
+        for quantum in 0..quanta {
+            context.render_into(&mut output);
+            assert_eq!(
+                context.current_frame(),
+                ((quantum + 1) * RENDER_QUANTUM_SIZE) as u64
+            );
+
+            let range = quantum * RENDER_QUANTUM_SIZE..(quantum + 1) * RENDER_QUANTUM_SIZE;
+            for channel in 0..2 {
+                assert_float_eq!(
+                    context.last_output_quantum().get_channel_data(channel),
+                    &expected.get_channel_data(channel)[range.clone()],
+                    abs_all <= 0.
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_last_output_quantum_small_blocks() {
+        let mut context = AudioContext::new_manual(48000., 1);
+
+        // one step per frame
+        let src = context.create_constant_source();
+        src.offset().set_value_at_time(0., 0.);
+        src.offset().linear_ramp_to_value_at_time(48000., 1.);
+        src.connect(&context.destination());
+        src.start();
+
+        // the last 128 frames of the output, across the block boundaries
+        let mut output = vec![0.; 100];
+        context.render_into(&mut output);
+        context.render_into(&mut output);
+        let expected: Vec<f32> = (72..200).map(|v| v as f32).collect();
+        assert_float_eq!(
+            context.last_output_quantum().get_channel_data(0),
+            &expected[..],
+            abs_all <= 1e-3
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_last_output_quantum_requires_manual_context() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+        let _ = context.last_output_quantum();
+    }
+
+    #[test]
+    fn test_latency_hint() {
+        let sample_rate = 48000.;
+        let hints = [
+            (AudioContextLatencyCategory::Interactive, 128),
+            (AudioContextLatencyCategory::Balanced, 512),
+            (AudioContextLatencyCategory::Playback, 1024),
+            // 2400 frames, rounded up to a power of two
+            (AudioContextLatencyCategory::Custom(0.05), 4096),
+        ];
+
+        for (latency_hint, buffer_size) in hints {
+            let options = AudioContextOptions {
+                sink_id: "none".into(),
+                sample_rate: Some(sample_rate),
+                latency_hint,
+                ..AudioContextOptions::default()
+            };
+            let context = AudioContext::new(options);
+            assert_float_eq!(
+                context.base_latency(),
+                buffer_size as f64 / sample_rate as f64,
+                abs <= 0.
+            );
+
+            // the hint is retained when the backend is rebuilt after a device error
+            let (sender, receiver) = crossbeam_channel::unbounded();
+            context.onstatechange(move |state| {
+                let _ = sender.send(state);
+            });
+            context.backend.event_send.send(Event::DeviceError).unwrap();
+            while receiver.recv_timeout(Duration::from_secs(2)).unwrap()
+                != AudioContextState::Running
+            {}
+            assert_float_eq!(
+                context.base_latency(),
+                buffer_size as f64 / sample_rate as f64,
+                abs <= 0.
+            );
+        }
+
+        let context = AudioContext::new_manual(sample_rate, 2);
+        assert_float_eq!(context.base_latency(), 0., abs <= 0.);
+    }
 }
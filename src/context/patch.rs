@@ -0,0 +1,748 @@
+//! Serialization of the audio graph, see [`Patch`]
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+
+use crate::context::{
+    AudioNodeId, BaseAudioContext, ConcreteBaseAudioContext, DESTINATION_NODE_ID,
+};
+use crate::control::Scheduler;
+use crate::node::{
+    AudioNode, AudioWorkletNode, AudioWorkletNodeOptions, BiquadFilterNode, BiquadFilterOptions,
+    ChannelMergerNode, ChannelMergerOptions, ChannelSplitterNode, ChannelSplitterOptions,
+    ConstantSourceNode, ConstantSourceOptions, DelayNode, DelayOptions, GainNode, GainOptions,
+    OscillatorNode, OscillatorOptions, StereoPannerNode, StereoPannerOptions,
+};
+use crate::param::{AudioParam, AudioParamEvent, AutomationEvent, AutomationHistory};
+
+/// Snapshot of an audio graph, to save it and to rebuild it later
+///
+/// A patch is taken with [`BaseAudioContext::save_patch`] and rebuilt, in the same or in another
+/// context, with [`BaseAudioContext::load_patch`]. It holds the nodes of the graph with the
+/// options they were created with, the automation calls made on their params, the start and
+/// stop times of the source nodes and the connections between the nodes. With the `serde`
+/// feature, a patch can be stored in any format supported by serde, e.g. JSON.
+///
+/// Automation events that have elapsed when the patch is saved are not kept: they are replaced
+/// by a [`SetValue`](AutomationEvent::SetValue) to the value they left the param at, followed
+/// by the events still in progress or to come.
+///
+/// Only the node types listed in [`PatchNodeType`] are part of a patch. Nodes of other types are
+/// left out, together with their connections. Likewise, a node is left out as soon as all its
+/// handles are dropped, even if it is still playing.
+///
+/// This type is not part of the Web Audio API specification.
+///
+/// # Example
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, OfflineAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = OfflineAudioContext::new(1, 48_000, 48_000.);
+/// let osc = context.create_oscillator();
+/// let gain = context.create_gain();
+/// gain.gain().set_value(0.5);
+/// osc.connect(&gain).connect(&context.destination());
+/// osc.start();
+///
+/// let patch = context.save_patch();
+///
+/// let other = OfflineAudioContext::new(1, 48_000, 48_000.);
+/// // keep the nodes alive while the graph is in use
+/// let _nodes = other.load_patch(&patch).unwrap();
+/// let buffer = other.start_rendering_sync();
+/// ```
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Patch {
+    /// The nodes of the graph, in order of creation
+    pub nodes: Vec<PatchNode>,
+    /// The connections between the nodes
+    pub connections: Vec<PatchConnection>,
+}
+
+impl Patch {
+    /// Id of the destination of the context, as the target of a [`PatchConnection`]
+    pub const DESTINATION: u64 = DESTINATION_NODE_ID.0;
+}
+
+/// A node of a [`Patch`]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatchNode {
+    /// Identifier of the node within the patch
+    pub id: u64,
+    /// Type of the node and the options it was created with
+    pub node: PatchNodeType,
+    /// The params of the node, in the order of [`PatchNodeType`]
+    pub params: Vec<PatchParam>,
+    /// Start time of a source node, if it was started
+    pub start: Option<f64>,
+    /// Stop time of a source node, if it was stopped
+    pub stop: Option<f64>,
+}
+
+/// Type of a [`PatchNode`], with the options to create it
+///
+/// The options hold the values the params were created with, later changes are recorded as
+/// automation events of the [`PatchParam`]s. The channel configuration is the current one.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum PatchNodeType {
+    /// An [`AudioWorkletNode`] running the worklet registered as `name`, with an
+    /// [`AudioParam`] per parameter of the worklet, sorted by name
+    AudioWorklet {
+        name: String,
+        options: AudioWorkletNodeOptions,
+    },
+    /// A [`BiquadFilterNode`], with the `frequency`, `detune`, `Q` and `gain` params
+    BiquadFilter(BiquadFilterOptions),
+    /// A [`ChannelMergerNode`], without params
+    ChannelMerger(ChannelMergerOptions),
+    /// A [`ChannelSplitterNode`], without params
+    ChannelSplitter(ChannelSplitterOptions),
+    /// A [`ConstantSourceNode`], with the `offset` param
+    ConstantSource(ConstantSourceOptions),
+    /// A [`DelayNode`], with the `delay_time` param
+    Delay(DelayOptions),
+    /// A [`GainNode`], with the `gain` param
+    Gain(GainOptions),
+    /// An [`OscillatorNode`], with the `frequency` and `detune` params
+    Oscillator(OscillatorOptions),
+    /// A [`StereoPannerNode`], with the `pan` param
+    StereoPanner(StereoPannerOptions),
+}
+
+/// An [`AudioParam`] of a [`PatchNode`]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatchParam {
+    /// Identifier of the param within the patch
+    pub id: u64,
+    /// The automation calls that restore the param, in order
+    pub automation: Vec<AutomationEvent>,
+}
+
+/// A connection of a [`Patch`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PatchConnection {
+    /// Id of the source node
+    pub from: u64,
+    /// Output port of the source node
+    pub output: usize,
+    /// Id of the destination node or param, or [`Patch::DESTINATION`]
+    pub to: u64,
+    /// Input port of the destination, always zero for a param
+    pub input: usize,
+}
+
+/// Node types that can be part of a [`Patch`]
+pub(crate) trait Patchable: AudioNode {
+    /// The params of the node, in the order of [`PatchNodeType`]
+    fn patch_params(&self) -> Vec<&AudioParam>;
+
+    /// The scheduler of a source node
+    fn patch_scheduler(&self) -> Option<&Scheduler> {
+        None
+    }
+
+    /// Id the outgoing connections of the node are made from
+    fn patch_output_id(&self) -> AudioNodeId {
+        self.registration().id()
+    }
+}
+
+/// Current state of a node, with the live channel configuration and type
+type Snapshot = Box<dyn Fn() -> PatchNodeType + Send + Sync>;
+
+struct RecordedNode {
+    snapshot: Snapshot,
+    output: AudioNodeId,
+    params: Vec<AudioNodeId>,
+    scheduler: Option<Scheduler>,
+}
+
+/// Control thread bookkeeping of the nodes that can be saved in a [`Patch`]
+#[derive(Default)]
+pub(super) struct PatchRecorder {
+    /// The nodes that still have a handle, by id
+    nodes: BTreeMap<AudioNodeId, RecordedNode>,
+    /// Automation of the params, recorded nodes or not, by param id
+    automation: HashMap<AudioNodeId, AutomationHistory>,
+}
+
+impl PatchRecorder {
+    pub(super) fn add_node<N: Patchable>(&mut self, node: &N, snapshot: Snapshot) {
+        let params: Vec<_> = node
+            .patch_params()
+            .into_iter()
+            .map(|param| param.registration().id())
+            .collect();
+
+        let record = RecordedNode {
+            snapshot,
+            output: node.patch_output_id(),
+            params,
+            scheduler: node.patch_scheduler().cloned(),
+        };
+        self.nodes.insert(node.registration().id(), record);
+    }
+
+    pub(super) fn remove_node(&mut self, id: AudioNodeId) {
+        self.nodes.remove(&id);
+        // the node may be a param
+        self.automation.remove(&id);
+    }
+
+    /// Record an automation event sent at `frame`, elapsed events are only dropped when the
+    /// automation is read
+    pub(super) fn record(&mut self, param: &AudioParam, event: &AudioParamEvent, frame: u64) {
+        self.automation
+            .entry(param.registration().id())
+            .or_insert_with(|| AutomationHistory::new(param))
+            .record(event, frame);
+    }
+
+    /// Run `f` on the automation of the param, evaluated up to `frame`
+    pub(super) fn with_automation<R, F>(&mut self, param: &AudioParam, frame: u64, f: F) -> R
+    where
+        F: FnOnce(&AutomationHistory) -> R,
+    {
+        match self.automation.get_mut(&param.registration().id()) {
+            Some(automation) => {
+                automation.advance(frame);
+                f(automation)
+            }
+            None => f(&AutomationHistory::new(param)),
+        }
+    }
+
+    /// Take a snapshot at `frame`, given the `(to, output, input)` connections going out of a
+    /// node
+    pub(super) fn save<F>(&mut self, frame: u64, outgoing: F) -> Patch
+    where
+        F: Fn(AudioNodeId) -> Vec<(AudioNodeId, usize, usize)>,
+    {
+        let params: HashSet<_> = self
+            .nodes
+            .values()
+            .flat_map(|record| record.params.iter().copied())
+            .collect();
+        params.iter().for_each(|param| {
+            if let Some(automation) = self.automation.get_mut(param) {
+                automation.advance(frame);
+            }
+        });
+
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|(id, record)| {
+                let scheduled = |time: f64| Some(time).filter(|&time| time < f64::MAX);
+                let (start, stop) = match &record.scheduler {
+                    Some(scheduler) => (
+                        scheduled(scheduler.get_start_at()),
+                        scheduled(scheduler.get_stop_at()),
+                    ),
+                    None => (None, None),
+                };
+
+                PatchNode {
+                    id: id.0,
+                    node: (record.snapshot)(),
+                    params: record
+                        .params
+                        .iter()
+                        .map(|param| PatchParam {
+                            id: param.0,
+                            automation: self
+                                .automation
+                                .get(param)
+                                .map(AutomationHistory::automation_events)
+                                .unwrap_or_default(),
+                        })
+                        .collect(),
+                    start,
+                    stop,
+                }
+            })
+            .collect();
+
+        let connections = self
+            .nodes
+            .iter()
+            .flat_map(|(id, record)| {
+                outgoing(record.output)
+                    .into_iter()
+                    .filter(|(to, _, _)| {
+                        *to == DESTINATION_NODE_ID
+                            || self.nodes.contains_key(to)
+                            || params.contains(to)
+                    })
+                    .map(move |(to, output, input)| PatchConnection {
+                        from: id.0,
+                        output,
+                        to: to.0,
+                        input,
+                    })
+            })
+            .collect();
+
+        Patch { nodes, connections }
+    }
+}
+
+/// Where the connections of a loaded node or param are made
+struct Endpoint {
+    input: AudioNodeId,
+    output: AudioNodeId,
+    number_of_inputs: usize,
+    number_of_outputs: usize,
+}
+
+impl Endpoint {
+    fn param(param: &AudioParam) -> Self {
+        Self {
+            input: param.registration().id(),
+            output: param.registration().id(),
+            number_of_inputs: 1,
+            number_of_outputs: 0,
+        }
+    }
+}
+
+/// Replay the automation and the scheduling of a loaded node
+fn restore<N: Patchable + 'static>(
+    node: N,
+    patch_node: &PatchNode,
+    endpoints: &mut HashMap<u64, Endpoint>,
+) -> Result<Box<dyn AudioNode>, Box<dyn Error + Send + Sync>> {
+    let params = node.patch_params();
+    if params.len() != patch_node.params.len() {
+        return Err(format!(
+            "InvalidStateError - node {} has {} params, expected {}",
+            patch_node.id,
+            patch_node.params.len(),
+            params.len()
+        )
+        .into());
+    }
+
+    params
+        .iter()
+        .zip(&patch_node.params)
+        .for_each(|(param, patch_param)| {
+            patch_param
+                .automation
+                .iter()
+                .for_each(|event| event.apply(param));
+            endpoints.insert(patch_param.id, Endpoint::param(param));
+        });
+
+    match (node.patch_scheduler(), patch_node.start) {
+        (Some(scheduler), Some(start)) => {
+            scheduler.try_start_at(start)?;
+            if let Some(stop) = patch_node.stop {
+                scheduler.try_stop_at(stop)?;
+            }
+        }
+        (None, Some(_)) => {
+            return Err(format!(
+                "InvalidStateError - node {} is not a source node and cannot be started",
+                patch_node.id
+            )
+            .into())
+        }
+        (_, None) => {
+            if patch_node.stop.is_some() {
+                return Err(format!(
+                    "InvalidStateError - node {} is stopped without being started",
+                    patch_node.id
+                )
+                .into());
+            }
+        }
+    }
+
+    let endpoint = Endpoint {
+        input: node.registration().id(),
+        output: node.patch_output_id(),
+        number_of_inputs: node.number_of_inputs(),
+        number_of_outputs: node.number_of_outputs(),
+    };
+    endpoints.insert(patch_node.id, endpoint);
+
+    Ok(Box::new(node))
+}
+
+/// Check the ids of the patch before creating any node
+fn validate(
+    context: &ConcreteBaseAudioContext,
+    patch: &Patch,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut ids = HashSet::new();
+    ids.insert(Patch::DESTINATION);
+    let mut node_ids = HashSet::new();
+
+    for patch_node in &patch.nodes {
+        if let PatchNodeType::AudioWorklet { name, .. } = &patch_node.node {
+            if context.worklet_definition(name).is_none() {
+                return Err(format!(
+                    "InvalidStateError - no worklet registered with name {:?}",
+                    name
+                )
+                .into());
+            }
+        }
+
+        node_ids.insert(patch_node.id);
+        let params = patch_node.params.iter().map(|param| param.id);
+        for id in std::iter::once(patch_node.id).chain(params) {
+            if !ids.insert(id) {
+                return Err(format!("InvalidStateError - duplicate id {} in patch", id).into());
+            }
+        }
+    }
+
+    for connection in &patch.connections {
+        if !node_ids.contains(&connection.from) || !ids.contains(&connection.to) {
+            return Err(format!(
+                "InvalidStateError - connection from {} to {} refers to an unknown id",
+                connection.from, connection.to
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Rebuild the graph of the patch in the context, see [`BaseAudioContext::load_patch`]
+pub(super) fn load_patch(
+    context: &ConcreteBaseAudioContext,
+    patch: &Patch,
+) -> Result<Vec<Box<dyn AudioNode>>, Box<dyn Error + Send + Sync>> {
+    validate(context, patch)?;
+
+    let mut endpoints = HashMap::new();
+    let destination = context.destination();
+    endpoints.insert(
+        Patch::DESTINATION,
+        Endpoint {
+            input: DESTINATION_NODE_ID,
+            output: DESTINATION_NODE_ID,
+            number_of_inputs: destination.number_of_inputs(),
+            number_of_outputs: 0,
+        },
+    );
+
+    let nodes = patch
+        .nodes
+        .iter()
+        .map(|patch_node| {
+            let endpoints = &mut endpoints;
+            match &patch_node.node {
+                PatchNodeType::AudioWorklet { name, options } => restore(
+                    AudioWorkletNode::new(context, name, options.clone()),
+                    patch_node,
+                    endpoints,
+                ),
+                PatchNodeType::BiquadFilter(options) => restore(
+                    BiquadFilterNode::new(context, options.clone()),
+                    patch_node,
+                    endpoints,
+                ),
+                PatchNodeType::ChannelMerger(options) => restore(
+                    ChannelMergerNode::new(context, options.clone()),
+                    patch_node,
+                    endpoints,
+                ),
+                PatchNodeType::ChannelSplitter(options) => restore(
+                    ChannelSplitterNode::new(context, options.clone()),
+                    patch_node,
+                    endpoints,
+                ),
+                PatchNodeType::ConstantSource(options) => restore(
+                    ConstantSourceNode::new(context, options.clone()),
+                    patch_node,
+                    endpoints,
+                ),
+                PatchNodeType::Delay(options) => restore(
+                    DelayNode::new(context, options.clone()),
+                    patch_node,
+                    endpoints,
+                ),
+                PatchNodeType::Gain(options) => restore(
+                    GainNode::new(context, options.clone()),
+                    patch_node,
+                    endpoints,
+                ),
+                PatchNodeType::Oscillator(options) => restore(
+                    OscillatorNode::new(context, options.clone()),
+                    patch_node,
+                    endpoints,
+                ),
+                PatchNodeType::StereoPanner(options) => restore(
+                    StereoPannerNode::new(context, options.clone()),
+                    patch_node,
+                    endpoints,
+                ),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // check all ports before making any connection
+    for connection in &patch.connections {
+        let from = &endpoints[&connection.from];
+        let to = &endpoints[&connection.to];
+        if connection.output >= from.number_of_outputs || connection.input >= to.number_of_inputs {
+            return Err(format!(
+                "IndexSizeError - connection from output {} of {} to input {} of {} is out of bounds",
+                connection.output, connection.from, connection.input, connection.to
+            )
+            .into());
+        }
+    }
+
+    patch.connections.iter().for_each(|connection| {
+        context.connect(
+            endpoints[&connection.from].output,
+            endpoints[&connection.to].input,
+            connection.output,
+            connection.input,
+        );
+    });
+
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::{AudioScheduledSourceNode, OscillatorType};
+
+    use super::*;
+
+    fn render_patch(patch: &Patch, length: usize) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, length, 48_000.);
+        let _nodes = context.load_patch(patch).unwrap();
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_save_load() {
+        let context = OfflineAudioContext::new(1, 4800, 48_000.);
+
+        let osc = context.create_oscillator();
+        osc.set_type(OscillatorType::Square);
+        osc.frequency().set_value(220.);
+        osc.frequency().linear_ramp_to_value_at_time(880., 0.05);
+        osc.start_at(0.01);
+        osc.stop_at(0.08);
+
+        let gain = GainNode::new(
+            &context,
+            GainOptions {
+                gain: 0.5,
+                ..GainOptions::default()
+            },
+        );
+        gain.gain().set_value_at_time(0.25, 0.03);
+
+        osc.connect(&gain).connect(&context.destination());
+
+        let patch = context.save_patch();
+        assert_eq!(patch.nodes.len(), 2);
+        assert_eq!(patch.nodes[0].start, Some(0.01));
+        assert_eq!(patch.nodes[0].stop, Some(0.08));
+        assert_eq!(
+            patch.nodes[0].params[0].automation,
+            vec![
+                AutomationEvent::SetValue { value: 220. },
+                AutomationEvent::LinearRamp {
+                    value: 880.,
+                    end_time: 0.05
+                },
+            ]
+        );
+        assert_eq!(patch.connections.len(), 2);
+
+        let expected = context.start_rendering_sync();
+        let result = render_patch(&patch, 4800);
+        assert_float_eq!(&result[..], expected.get_channel_data(0), abs_all <= 0.);
+        assert!(result.iter().any(|&v| v != 0.));
+    }
+
+    #[test]
+    fn test_save_elapsed_automation() {
+        let sample_rate = 48_000.;
+        let mut context = OfflineAudioContext::new(1, 2048, sample_rate);
+
+        let src = context.create_constant_source();
+        src.offset().set_value_at_time(0.2, 0.);
+        src.offset()
+            .linear_ramp_to_value_at_time(0.8, 256. / f64::from(sample_rate));
+        src.offset()
+            .set_value_at_time(0.5, 512. / f64::from(sample_rate));
+        src.connect(&context.destination());
+        src.start();
+
+        let patch = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let saved = patch.clone();
+        context.suspend_at(1024, move |context| {
+            // the events have elapsed, only the value they left the param at is kept
+            let patch = context.save_patch();
+            assert_eq!(
+                patch.nodes[0].params[0].automation,
+                vec![AutomationEvent::SetValue { value: 0.5 }]
+            );
+
+            src.offset()
+                .set_value_at_time(0.3, 1536. / f64::from(sample_rate));
+            *saved.lock().unwrap() = Some(context.save_patch());
+        });
+
+        let expected = context.start_rendering_sync();
+        let patch = patch.lock().unwrap().take().unwrap();
+        assert_eq!(
+            patch.nodes[0].params[0].automation,
+            vec![
+                AutomationEvent::SetValue { value: 0.5 },
+                AutomationEvent::SetValueAtTime {
+                    value: 0.3,
+                    start_time: 1536. / f64::from(sample_rate)
+                },
+            ]
+        );
+
+        // the patch restores the graph as it was when saved
+        let result = render_patch(&patch, 2048);
+        assert_float_eq!(
+            &result[1024..],
+            &expected.get_channel_data(0)[1024..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_save_connections() {
+        let context = OfflineAudioContext::new(1, 128, 48_000.);
+
+        let lfo = context.create_constant_source();
+        let splitter = context.create_channel_splitter(2);
+        let gain = context.create_gain();
+        let delay = context.create_delay(1.);
+        let analyser = context.create_analyser();
+
+        lfo.connect(gain.gain());
+        splitter.connect_at(&gain, 1, 0);
+        gain.connect(&delay);
+        delay.connect(&context.destination());
+        // not part of the patch
+        delay.connect(&analyser);
+        analyser.connect(&gain);
+
+        let patch = context.save_patch();
+        let id = |node: &dyn AudioNode| node.registration().id().0;
+        let connection = |from, output, to, input| PatchConnection {
+            from,
+            output,
+            to,
+            input,
+        };
+
+        assert_eq!(patch.nodes.len(), 4);
+        assert_eq!(
+            patch.connections,
+            vec![
+                connection(id(&lfo), 0, id(gain.gain()), 0),
+                connection(id(&splitter), 1, id(&gain), 0),
+                connection(id(&gain), 0, id(&delay), 0),
+                connection(id(&delay), 0, Patch::DESTINATION, 0),
+            ]
+        );
+
+        gain.disconnect();
+        drop(lfo);
+        let patch = context.save_patch();
+        assert_eq!(patch.nodes.len(), 3);
+        assert_eq!(
+            patch.connections,
+            vec![
+                connection(id(&splitter), 1, id(&gain), 0),
+                connection(id(&delay), 0, Patch::DESTINATION, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let context = OfflineAudioContext::new(1, 128, 48_000.);
+        let gain = context.create_gain();
+        gain.connect(&context.destination());
+        let patch = context.save_patch();
+
+        let mut worklet = patch.clone();
+        worklet.nodes[0].node = PatchNodeType::AudioWorklet {
+            name: String::from("unknown"),
+            options: AudioWorkletNodeOptions::default(),
+        };
+        assert!(context.load_patch(&worklet).is_err());
+
+        let mut port = patch.clone();
+        port.connections[0].input = 1;
+        assert!(context.load_patch(&port).is_err());
+
+        let mut params = patch.clone();
+        params.nodes[0].params.clear();
+        assert!(context.load_patch(&params).is_err());
+
+        let mut start = patch;
+        start.nodes[0].start = Some(0.);
+        assert!(context.load_patch(&start).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let context = OfflineAudioContext::new(1, 4800, 48_000.);
+
+        let src = context.create_constant_source();
+        src.offset()
+            .set_value_curve_at_time(&[0., 1., -0.5], 0., 0.05);
+        src.start();
+
+        let delay = context.create_delay(1.);
+        delay.delay_time().set_value(0.01);
+        let filter = context.create_biquad_filter();
+        filter.frequency().set_target_at_time(1000., 0.02, 0.01);
+        let panner = context.create_stereo_panner();
+        panner.pan().set_value(-0.5);
+        let merger = context.merge_stereo(&panner, &filter);
+
+        src.connect(&delay).connect(&filter);
+        src.connect(&panner);
+        merger.connect(&context.destination());
+
+        let patch = context.save_patch();
+        let json = serde_json::to_string(&patch).unwrap();
+        let patch: Patch = serde_json::from_str(&json).unwrap();
+
+        let expected = context.start_rendering_sync();
+        let result = render_patch(&patch, 4800);
+        assert_float_eq!(&result[..], expected.get_channel_data(0), abs_all <= 0.);
+        assert!(result.iter().any(|&v| v != 0.));
+    }
+}
@@ -1,15 +1,50 @@
-//! Scheduler and Controller for precise timings
+//! Scheduler, Controller and TempoClock for precise timings
 
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 
 use crate::AtomicF64;
 
+/// Calls made on a [`Scheduler`] so far, to validate the next ones
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum ScheduledState {
+    Unscheduled,
+    Started,
+    Stopped,
+}
+
+impl From<u8> for ScheduledState {
+    fn from(i: u8) -> Self {
+        match i {
+            0 => Self::Unscheduled,
+            1 => Self::Started,
+            2 => Self::Stopped,
+            _ => unreachable!(),
+        }
+    }
+}
+
 /// Helper struct to start and stop audio streams
 #[derive(Clone, Debug)]
 pub(crate) struct Scheduler {
     start: Arc<AtomicF64>,
     stop: Arc<AtomicF64>,
+    state: Arc<AtomicU8>,
+}
+
+/// Check the `when` argument of `start` and `stop`
+fn assert_valid_time(when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if when.is_finite() && when >= 0. {
+        Ok(())
+    } else {
+        Err(format!(
+            "RangeError - scheduling time should be a positive number, got {:?}",
+            when
+        )
+        .into())
+    }
 }
 
 impl Scheduler {
@@ -18,6 +53,7 @@ impl Scheduler {
         Self {
             start: Arc::new(AtomicF64::new(f64::MAX)),
             stop: Arc::new(AtomicF64::new(f64::MAX)),
+            state: Arc::new(AtomicU8::new(ScheduledState::Unscheduled as u8)),
         }
     }
 
@@ -27,9 +63,34 @@ impl Scheduler {
     }
 
     /// Schedule playback start at this timestamp
-    pub fn start_at(&self, start: f64) {
-        // todo panic on invalid values, or when already called
+    ///
+    /// Fails if the time is negative or if start was already called.
+    pub fn try_start_at(&self, start: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.try_start_at_with(start, || ())
+    }
+
+    /// Schedule playback start at this timestamp, after running `setup` if the call is valid
+    ///
+    /// The render thread only picks up the start time after `setup` completes.
+    pub fn try_start_at_with<F: FnOnce()>(
+        &self,
+        start: f64,
+        setup: F,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_valid_time(start)?;
+
+        self.state
+            .compare_exchange(
+                ScheduledState::Unscheduled as u8,
+                ScheduledState::Started as u8,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .map_err(|_| "InvalidStateError - cannot call start more than once")?;
+
+        setup();
         self.start.store(start);
+        Ok(())
     }
 
     /// Retrieve playback stop value
@@ -38,9 +99,20 @@ impl Scheduler {
     }
 
     /// Stop playback at this timestamp
-    pub fn stop_at(&self, stop: f64) {
-        // todo panic on invalid values, or when already called
+    ///
+    /// Fails if the time is negative or if start was not called yet. When called multiple
+    /// times, the last call wins.
+    pub fn try_stop_at(&self, stop: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        assert_valid_time(stop)?;
+
+        if ScheduledState::from(self.state.load(Ordering::SeqCst)) == ScheduledState::Unscheduled {
+            return Err("InvalidStateError - cannot call stop before start".into());
+        }
+
+        self.state
+            .store(ScheduledState::Stopped as u8, Ordering::SeqCst);
         self.stop.store(stop);
+        Ok(())
     }
 }
 
@@ -125,10 +197,364 @@ impl Default for Controller {
     }
 }
 
+/// Options for constructing a [`TempoClock`]
+#[derive(Clone, Debug)]
+pub struct TempoClockOptions {
+    /// Initial tempo in beats per minute
+    pub bpm: f64,
+    /// Number of beats in a bar (the numerator of the time signature)
+    pub beats_per_bar: u32,
+    /// Context time (in seconds) of beat zero
+    pub start_time: f64,
+    /// Amount of swing applied to the off-beat eighth notes, in the range [0, 1)
+    pub swing: f64,
+}
+
+impl Default for TempoClockOptions {
+    fn default() -> Self {
+        Self {
+            bpm: 120.,
+            beats_per_bar: 4,
+            start_time: 0.,
+            swing: 0.,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct TempoSegment {
+    beat: f64,
+    time: f64,
+    bpm: f64,
+}
+
+/// Converts musical positions (bars and beats) to context time in seconds
+///
+/// The returned timestamps can be used to schedule source nodes (e.g. via
+/// [`start_at`](crate::node::AudioScheduledSourceNode::start_at)) or automation events on an
+/// [`AudioParam`](crate::AudioParam).
+///
+/// ```
+/// use web_audio_api::{TempoClock, TempoClockOptions};
+///
+/// let mut clock = TempoClock::new(TempoClockOptions::default()); // 120 BPM, 4/4
+/// assert_eq!(clock.beat_time(2.), 1.);
+/// assert_eq!(clock.bar_time(1.), 2.);
+///
+/// // slow down to 60 BPM from the second bar onwards
+/// clock.set_tempo_at_beat(4., 60.);
+/// assert_eq!(clock.beat_time(5.), 3.);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TempoClock {
+    segments: Vec<TempoSegment>,
+    beats_per_bar: u32,
+    swing: f64,
+}
+
+impl TempoClock {
+    /// Create a new TempoClock
+    ///
+    /// # Panics
+    ///
+    /// Will panic if:
+    /// - the tempo is not strictly positive and finite
+    /// - `beats_per_bar` is zero
+    /// - `swing` is outside the [0, 1) range
+    pub fn new(options: TempoClockOptions) -> Self {
+        assert_valid_bpm(options.bpm);
+        assert_valid_swing(options.swing);
+        assert!(
+            options.beats_per_bar > 0,
+            "RangeError - beats_per_bar should be greater than zero"
+        );
+
+        Self {
+            segments: vec![TempoSegment {
+                beat: 0.,
+                time: options.start_time,
+                bpm: options.bpm,
+            }],
+            beats_per_bar: options.beats_per_bar,
+            swing: options.swing,
+        }
+    }
+
+    /// Number of beats in a bar
+    pub fn beats_per_bar(&self) -> u32 {
+        self.beats_per_bar
+    }
+
+    /// Context time (in seconds) of beat zero
+    pub fn start_time(&self) -> f64 {
+        self.segments[0].time
+    }
+
+    /// Tempo (in beats per minute) in effect at the given beat
+    pub fn bpm_at_beat(&self, beat: f64) -> f64 {
+        self.segment_for_beat(beat).bpm
+    }
+
+    /// Amount of swing applied to the off-beat eighth notes
+    pub fn swing(&self) -> f64 {
+        self.swing
+    }
+
+    /// Set the amount of swing applied to the off-beat eighth notes
+    ///
+    /// A value of 0 means straight eighths, 1/3 results in a triplet feel.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the value is outside the [0, 1) range
+    pub fn set_swing(&mut self, swing: f64) {
+        assert_valid_swing(swing);
+        self.swing = swing;
+    }
+
+    /// Change the tempo from the given beat onwards
+    ///
+    /// Tempo changes accumulate: the time of all subsequent beats is computed relative to the
+    /// previous changes. Setting a tempo at a beat which already holds a tempo change replaces it.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if:
+    /// - the tempo is not strictly positive and finite
+    /// - the beat is negative or not finite
+    pub fn set_tempo_at_beat(&mut self, beat: f64, bpm: f64) {
+        assert_valid_bpm(bpm);
+        assert!(
+            beat.is_finite() && beat >= 0.,
+            "RangeError - Invalid beat: {:?}, should be positive",
+            beat
+        );
+
+        // insert or replace, keeping segments sorted by beat
+        let index = self.segments.partition_point(|s| s.beat < beat);
+        let segment = TempoSegment {
+            beat,
+            time: 0.,
+            bpm,
+        };
+        match self.segments.get(index) {
+            Some(s) if s.beat == beat => self.segments[index] = segment,
+            _ => self.segments.insert(index, segment),
+        }
+
+        // recompute the start time of all segments
+        for i in 1..self.segments.len() {
+            let prev = self.segments[i - 1];
+            self.segments[i].time =
+                prev.time + (self.segments[i].beat - prev.beat) * 60. / prev.bpm;
+        }
+    }
+
+    /// Context time (in seconds) of the given beat, zero based
+    pub fn beat_time(&self, beat: f64) -> f64 {
+        let beat = self.apply_swing(beat);
+        let segment = self.segment_for_beat(beat);
+        segment.time + (beat - segment.beat) * 60. / segment.bpm
+    }
+
+    /// Context time (in seconds) of the given bar, zero based
+    pub fn bar_time(&self, bar: f64) -> f64 {
+        self.beat_time(bar * f64::from(self.beats_per_bar))
+    }
+
+    /// Context time (in seconds) of the given beat within the given bar, both zero based
+    pub fn bar_beat_time(&self, bar: f64, beat: f64) -> f64 {
+        self.beat_time(bar * f64::from(self.beats_per_bar) + beat)
+    }
+
+    /// Beat position at the given context time (in seconds)
+    ///
+    /// This is the inverse of [`beat_time`](Self::beat_time). Use it together with
+    /// [`BaseAudioContext::current_time`](crate::context::BaseAudioContext::current_time) to
+    /// find the current position in the sequence.
+    pub fn beat_at_time(&self, time: f64) -> f64 {
+        let index = self
+            .segments
+            .partition_point(|s| s.time <= time)
+            .saturating_sub(1);
+        let segment = self.segments[index];
+        let beat = segment.beat + (time - segment.time) * segment.bpm / 60.;
+        self.remove_swing(beat)
+    }
+
+    fn segment_for_beat(&self, beat: f64) -> &TempoSegment {
+        let index = self
+            .segments
+            .partition_point(|s| s.beat <= beat)
+            .saturating_sub(1);
+        &self.segments[index]
+    }
+
+    // Delay the off-beat eighth note, by warping the first and second half of each beat
+    fn apply_swing(&self, beat: f64) -> f64 {
+        if self.swing == 0. {
+            return beat;
+        }
+
+        let whole = beat.floor();
+        let frac = beat - whole;
+        let split = 0.5 * (1. + self.swing);
+
+        if frac < 0.5 {
+            whole + frac * 2. * split
+        } else {
+            whole + split + (frac - 0.5) * 2. * (1. - split)
+        }
+    }
+
+    fn remove_swing(&self, beat: f64) -> f64 {
+        if self.swing == 0. {
+            return beat;
+        }
+
+        let whole = beat.floor();
+        let frac = beat - whole;
+        let split = 0.5 * (1. + self.swing);
+
+        if frac < split {
+            whole + frac / (2. * split)
+        } else {
+            whole + 0.5 + (frac - split) / (2. * (1. - split))
+        }
+    }
+}
+
+impl Default for TempoClock {
+    fn default() -> Self {
+        Self::new(TempoClockOptions::default())
+    }
+}
+
+/// Length of a note relative to a whole note, see [`NoteDivision`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NoteValue {
+    Whole,
+    Half,
+    Quarter,
+    Eighth,
+    Sixteenth,
+    ThirtySecond,
+}
+
+impl NoteValue {
+    /// Number of quarter notes (beats) in this note value
+    fn beats(self) -> f64 {
+        match self {
+            Self::Whole => 4.,
+            Self::Half => 2.,
+            Self::Quarter => 1.,
+            Self::Eighth => 0.5,
+            Self::Sixteenth => 0.25,
+            Self::ThirtySecond => 0.125,
+        }
+    }
+}
+
+/// Musical duration of a note, e.g. to set a tempo-synced delay time
+///
+/// ```
+/// use web_audio_api::{NoteDivision, NoteValue};
+///
+/// // an eighth note lasts a quarter of a second at 120 BPM
+/// assert_eq!(NoteDivision::Straight(NoteValue::Eighth).duration(120.), 0.25);
+/// // a dotted eighth note lasts three sixteenth notes
+/// assert_eq!(NoteDivision::Dotted(NoteValue::Eighth).duration(120.), 0.375);
+/// ```
+///
+/// - see also: [`DelayNode::set_delay_note`](crate::node::DelayNode::set_delay_note)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NoteDivision {
+    /// The plain note value
+    Straight(NoteValue),
+    /// One and a half times the note value
+    Dotted(NoteValue),
+    /// Two thirds of the note value, three triplets fill the time of two straight notes
+    Triplet(NoteValue),
+}
+
+impl NoteDivision {
+    /// Number of quarter notes (beats) in this division
+    #[must_use]
+    pub fn beats(&self) -> f64 {
+        match *self {
+            Self::Straight(value) => value.beats(),
+            Self::Dotted(value) => value.beats() * 1.5,
+            Self::Triplet(value) => value.beats() * 2. / 3.,
+        }
+    }
+
+    /// Duration (in seconds) of this division at the given tempo, in beats per minute
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the tempo is not strictly positive and finite
+    #[must_use]
+    pub fn duration(&self, bpm: f64) -> f64 {
+        assert_valid_bpm(bpm);
+        self.beats() * 60. / bpm
+    }
+}
+
+#[track_caller]
+fn assert_valid_bpm(bpm: f64) {
+    if !(bpm.is_finite() && bpm > 0.) {
+        panic!(
+            "RangeError - Invalid tempo: {:?}, should be strictly positive",
+            bpm
+        );
+    }
+}
+
+#[track_caller]
+fn assert_valid_swing(swing: f64) {
+    if !(0. ..1.).contains(&swing) {
+        panic!(
+            "RangeError - Invalid swing: {:?}, should be in the range [0, 1)",
+            swing
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use float_eq::assert_float_eq;
+
     use super::*;
 
+    #[test]
+    fn test_scheduler() {
+        let scheduler = Scheduler::new();
+
+        assert!(scheduler.try_stop_at(1.).is_err()); // stop before start
+        assert!(scheduler.try_start_at(-1.).is_err()); // negative time
+        assert!(scheduler.try_start_at(f64::NAN).is_err());
+        assert_eq!(scheduler.get_start_at(), f64::MAX);
+
+        assert!(scheduler.try_start_at(1.).is_ok());
+        assert!(scheduler.try_start_at(2.).is_err()); // double start
+        assert_eq!(scheduler.get_start_at(), 1.);
+
+        assert!(scheduler.try_stop_at(-1.).is_err());
+        assert!(scheduler.try_stop_at(3.).is_ok());
+        assert!(scheduler.try_stop_at(2.).is_ok()); // last call wins
+        assert_eq!(scheduler.get_stop_at(), 2.);
+    }
+
+    #[test]
+    fn test_scheduler_setup_only_when_valid() {
+        let scheduler = Scheduler::new();
+        let mut calls = 0;
+
+        scheduler.try_start_at_with(0., || calls += 1).unwrap();
+        assert!(scheduler.try_start_at_with(0., || calls += 1).is_err());
+        assert_eq!(calls, 1);
+    }
+
     #[test]
     fn test_controller() {
         let controller = Controller::new();
@@ -137,4 +563,131 @@ mod tests {
         assert!(controller.loop_start() == 0.);
         assert!(controller.loop_end() == f64::MAX);
     }
+
+    #[test]
+    fn test_tempo_clock() {
+        let clock = TempoClock::new(TempoClockOptions::default());
+
+        // 120 BPM: a beat every 0.5 seconds
+        assert_float_eq!(clock.beat_time(0.), 0., abs <= 0.);
+        assert_float_eq!(clock.beat_time(1.), 0.5, abs <= 0.);
+        assert_float_eq!(clock.beat_time(2.5), 1.25, abs <= 0.);
+        assert_float_eq!(clock.bar_time(1.), 2., abs <= 0.);
+        assert_float_eq!(clock.bar_beat_time(2., 1.), 4.5, abs <= 0.);
+        assert_float_eq!(clock.beat_at_time(1.25), 2.5, abs <= 0.);
+    }
+
+    #[test]
+    fn test_tempo_clock_start_time() {
+        let clock = TempoClock::new(TempoClockOptions {
+            bpm: 60.,
+            beats_per_bar: 3,
+            start_time: 10.,
+            ..TempoClockOptions::default()
+        });
+
+        assert_float_eq!(clock.beat_time(0.), 10., abs <= 0.);
+        assert_float_eq!(clock.bar_time(1.), 13., abs <= 0.);
+        assert_float_eq!(clock.beat_at_time(13.), 3., abs <= 0.);
+    }
+
+    #[test]
+    fn test_tempo_clock_tempo_change() {
+        let mut clock = TempoClock::new(TempoClockOptions::default());
+
+        // 120 BPM for 4 beats (2 seconds), then 60 BPM
+        clock.set_tempo_at_beat(4., 60.);
+        assert_float_eq!(clock.beat_time(3.), 1.5, abs <= 0.);
+        assert_float_eq!(clock.beat_time(4.), 2., abs <= 0.);
+        assert_float_eq!(clock.beat_time(6.), 4., abs <= 0.);
+        assert_float_eq!(clock.bpm_at_beat(3.), 120., abs <= 0.);
+        assert_float_eq!(clock.bpm_at_beat(4.), 60., abs <= 0.);
+
+        // then 240 BPM from beat 8 (at 6 seconds)
+        clock.set_tempo_at_beat(8., 240.);
+        assert_float_eq!(clock.beat_time(8.), 6., abs <= 0.);
+        assert_float_eq!(clock.beat_time(12.), 7., abs <= 0.);
+
+        // inserting an earlier change shifts all subsequent beats
+        clock.set_tempo_at_beat(2., 60.);
+        assert_float_eq!(clock.beat_time(2.), 1., abs <= 0.);
+        assert_float_eq!(clock.beat_time(8.), 7., abs <= 0.);
+        assert_float_eq!(clock.beat_time(12.), 8., abs <= 0.);
+
+        // replacing a change
+        clock.set_tempo_at_beat(2., 120.);
+        assert_float_eq!(clock.beat_time(4.), 2., abs <= 0.);
+
+        assert_float_eq!(clock.beat_at_time(7.), 12., abs <= 0.);
+        assert_float_eq!(clock.beat_at_time(2.5), 4.5, abs <= 0.);
+    }
+
+    #[test]
+    fn test_tempo_clock_swing() {
+        let mut clock = TempoClock::new(TempoClockOptions {
+            bpm: 60.,
+            swing: 1. / 3.,
+            ..TempoClockOptions::default()
+        });
+
+        // on-beats are not affected
+        assert_float_eq!(clock.beat_time(0.), 0., abs <= 1e-12);
+        assert_float_eq!(clock.beat_time(1.), 1., abs <= 1e-12);
+        // off-beat eighth gets a triplet feel
+        assert_float_eq!(clock.beat_time(0.5), 2. / 3., abs <= 1e-12);
+        assert_float_eq!(clock.beat_time(1.5), 1. + 2. / 3., abs <= 1e-12);
+        assert_float_eq!(clock.beat_at_time(2. / 3.), 0.5, abs <= 1e-12);
+
+        clock.set_swing(0.);
+        assert_float_eq!(clock.beat_time(0.5), 0.5, abs <= 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tempo_clock_invalid_bpm() {
+        let mut clock = TempoClock::default();
+        clock.set_tempo_at_beat(1., 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tempo_clock_invalid_swing() {
+        let mut clock = TempoClock::default();
+        clock.set_swing(1.);
+    }
+
+    #[test]
+    fn test_note_division() {
+        assert_float_eq!(
+            NoteDivision::Straight(NoteValue::Quarter).duration(120.),
+            0.5,
+            abs <= 0.
+        );
+        assert_float_eq!(
+            NoteDivision::Straight(NoteValue::Eighth).duration(120.),
+            0.25,
+            abs <= 0.
+        );
+        assert_float_eq!(
+            NoteDivision::Dotted(NoteValue::Quarter).duration(60.),
+            1.5,
+            abs <= 0.
+        );
+        assert_float_eq!(
+            NoteDivision::Triplet(NoteValue::Quarter).duration(60.),
+            2. / 3.,
+            abs <= 1e-12
+        );
+        assert_float_eq!(
+            NoteDivision::Straight(NoteValue::Whole).beats(),
+            4.,
+            abs <= 0.
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_note_division_invalid_bpm() {
+        let _ = NoteDivision::Straight(NoteValue::Quarter).duration(0.);
+    }
 }
@@ -0,0 +1,475 @@
+//! ADSR envelope generator and glide helpers scheduling automation events on an `AudioParam`
+
+use crate::param::AudioParam;
+
+/// Attack-decay-sustain-release envelope, scheduled on an [`AudioParam`]
+///
+/// On [`trigger`](Self::trigger) the param rises to `1` during `attack` seconds, then falls to the
+/// `sustain` level during `decay` seconds, and holds this level until [`release`](Self::release)
+/// brings it down to `0` during `release` seconds.
+///
+/// Each phase is an exponential approach (see [`AudioParam::set_target_at_time`]) reaching its
+/// target within 1% at the end of the phase, like the envelope of an analog synthesizer. A phase
+/// with a duration of zero jumps to its target.
+///
+/// The approach is asymptotic: the attack peaks at `1 - e^-5` (about `0.993`) rather than exactly
+/// `1`, and the decay starts from that level. Likewise the release only reaches `0` exactly once
+/// the value underflows.
+///
+/// Both methods first cancel the automation events scheduled from their start time onwards and
+/// hold the value the param has at that time. Hence retriggering, or releasing during the attack
+/// or decay phase, continues smoothly from the current level.
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::AdsrEnvelope;
+///
+/// let context = AudioContext::default();
+///
+/// let amp = context.create_gain();
+/// amp.gain().set_value(0.);
+/// amp.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&amp);
+/// osc.start();
+///
+/// let envelope = AdsrEnvelope {
+///     attack: 0.01,
+///     decay: 0.2,
+///     sustain: 0.5,
+///     release: 1.,
+/// };
+///
+/// let now = context.current_time();
+/// envelope.trigger(amp.gain(), now);
+/// envelope.release(amp.gain(), now + 1.);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdsrEnvelope {
+    /// Duration in seconds of the ramp from the current value to `1`
+    pub attack: f64,
+    /// Duration in seconds of the ramp from `1` to the sustain level
+    pub decay: f64,
+    /// Level held after the decay phase, in the range `[0, 1]`
+    pub sustain: f32,
+    /// Duration in seconds of the ramp from the current value to `0`
+    pub release: f64,
+}
+
+impl Default for AdsrEnvelope {
+    fn default() -> Self {
+        Self {
+            attack: 0.,
+            decay: 0.,
+            sustain: 1.,
+            release: 0.,
+        }
+    }
+}
+
+impl AdsrEnvelope {
+    /// Schedule the attack, decay and sustain phases on `param`, starting at `when`
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `when` or any of the durations is negative, or if the sustain level is not
+    /// in the range `[0, 1]`
+    pub fn trigger(&self, param: &AudioParam, when: f64) {
+        self.trigger_scaled(param, when, 1.);
+    }
+
+    /// Same as [`trigger`](Self::trigger) with the envelope levels multiplied by `scale`
+    pub(crate) fn trigger_scaled(&self, param: &AudioParam, when: f64, scale: f32) {
+        self.assert_valid();
+
+        param.cancel_and_hold_at_time(when);
+        param.set_target_at_time(scale, when, time_constant(self.attack));
+        param.set_target_at_time(
+            self.sustain * scale,
+            when + self.attack,
+            time_constant(self.decay),
+        );
+    }
+
+    /// Schedule the release phase on `param`, starting at `when`
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `when` or any of the durations is negative, or if the sustain level is not
+    /// in the range `[0, 1]`
+    pub fn release(&self, param: &AudioParam, when: f64) {
+        self.assert_valid();
+
+        param.cancel_and_hold_at_time(when);
+        param.set_target_at_time(0., when, time_constant(self.release));
+    }
+
+    #[track_caller]
+    fn assert_valid(&self) {
+        for (name, duration) in [
+            ("attack", self.attack),
+            ("decay", self.decay),
+            ("release", self.release),
+        ] {
+            assert!(
+                duration >= 0. && duration.is_finite(),
+                "RangeError - {} duration ({:?}) should be a non-negative finite number",
+                name,
+                duration
+            );
+        }
+
+        assert!(
+            (0. ..=1.).contains(&self.sustain),
+            "RangeError - sustain level ({:?}) should be in the range [0, 1]",
+            self.sustain
+        );
+    }
+}
+
+/// Glide the value of `param` to `target`, reaching it within 1% after `glide_time` seconds
+///
+/// The glide is an exponential approach (see [`AudioParam::set_target_at_time`]) starting at
+/// `now` from the value the param has at that time, like the portamento of an analog
+/// synthesizer. The automation events scheduled from `now` onwards are cancelled first, so a
+/// glide can be interrupted by another one. A `glide_time` of zero jumps to the target.
+///
+/// Gliding the frequency of an oscillator in Hz approaches the target linearly in frequency,
+/// hence much faster in pitch for a downward glide than for an upward one. Use
+/// [`glide_detune_to`] to glide in cents for a musically even portamento.
+///
+/// This function is not part of the Web Audio API specification.
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::glide_to;
+///
+/// let context = AudioContext::default();
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&context.destination());
+/// osc.start();
+///
+/// let now = context.current_time();
+/// glide_to(osc.frequency(), 660., 0.1, now + 0.5);
+/// ```
+///
+/// # Panics
+///
+/// Will panic if `now` is negative, or if `glide_time` is negative or not finite
+pub fn glide_to(param: &AudioParam, target: f32, glide_time: f64, now: f64) {
+    assert!(
+        glide_time >= 0. && glide_time.is_finite(),
+        "RangeError - glide time ({:?}) should be a non-negative finite number",
+        glide_time
+    );
+
+    param.cancel_and_hold_at_time(now);
+    param.set_target_at_time(target, now, time_constant(glide_time));
+}
+
+/// Glide the pitch of an oscillator to `target` Hz by automating its `detune` param in cents
+///
+/// The frequency of the oscillator is `base_frequency * 2^(detune / 1200)`, where
+/// `base_frequency` is the value of its `frequency` param, which should not be automated during
+/// the glide. The detune approaches `1200 * log2(target / base_frequency)` cents like
+/// [`glide_to`], so the pitch glides at the same rate upwards and downwards, and reaches the
+/// target within 1% of the interval after `glide_time` seconds.
+///
+/// This function is not part of the Web Audio API specification.
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+/// use web_audio_api::glide_detune_to;
+///
+/// let context = AudioContext::default();
+///
+/// let osc = context.create_oscillator();
+/// osc.frequency().set_value(440.);
+/// osc.connect(&context.destination());
+/// osc.start();
+///
+/// let now = context.current_time();
+/// // an octave up
+/// glide_detune_to(osc.detune(), 440., 880., 0.1, now + 0.5);
+/// ```
+///
+/// # Panics
+///
+/// Will panic if `now` is negative, if `glide_time` is negative or not finite, or if
+/// `base_frequency` or `target` is not strictly positive
+pub fn glide_detune_to(
+    detune: &AudioParam,
+    base_frequency: f32,
+    target: f32,
+    glide_time: f64,
+    now: f64,
+) {
+    assert!(
+        base_frequency > 0. && target > 0.,
+        "RangeError - base frequency ({:?}) and target ({:?}) should be strictly positive",
+        base_frequency,
+        target
+    );
+
+    let cents = 1200. * (target / base_frequency).log2();
+    glide_to(detune, cents, glide_time, now);
+}
+
+/// Number of time constants in a phase, the remaining distance to the target is `e^-5` (< 1%)
+const TIME_CONSTANTS_PER_PHASE: f64 = 5.;
+
+fn time_constant(duration: f64) -> f64 {
+    duration / TIME_CONSTANTS_PER_PHASE
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 8000.;
+
+    // render the envelope applied to a constant signal of 1
+    fn render(schedule: impl FnOnce(&AudioParam)) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, 8000, SAMPLE_RATE);
+
+        let amp = context.create_gain();
+        amp.gain().set_value(0.);
+        amp.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&amp);
+        src.start();
+
+        schedule(amp.gain());
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    fn sample(output: &[f32], time: f64) -> f32 {
+        output[(time * SAMPLE_RATE as f64).round() as usize]
+    }
+
+    // value of an exponential approach from `from` to `to`, `elapsed` seconds in a phase
+    fn approach(from: f32, to: f32, elapsed: f64, duration: f64) -> f32 {
+        let decay = (-elapsed * TIME_CONSTANTS_PER_PHASE / duration).exp() as f32;
+        to + (from - to) * decay
+    }
+
+    const ENVELOPE: AdsrEnvelope = AdsrEnvelope {
+        attack: 0.1,
+        decay: 0.2,
+        sustain: 0.5,
+        release: 0.2,
+    };
+
+    #[test]
+    fn test_envelope_shape() {
+        let output = render(|param| {
+            ENVELOPE.trigger(param, 0.1);
+            ENVELOPE.release(param, 0.6);
+        });
+
+        assert_float_eq!(sample(&output, 0.05), 0., abs <= 0.);
+        // attack
+        let peak = approach(0., 1., 0.1, 0.1);
+        assert_float_eq!(peak, 1. - (-5_f32).exp(), abs <= 1e-6);
+        assert_float_eq!(
+            sample(&output, 0.15),
+            approach(0., 1., 0.05, 0.1),
+            abs <= 1e-4
+        );
+        assert_float_eq!(sample(&output, 0.2), peak, abs <= 1e-4);
+        // decay
+        assert_float_eq!(
+            sample(&output, 0.3),
+            approach(peak, 0.5, 0.1, 0.2),
+            abs <= 1e-4
+        );
+        assert_float_eq!(
+            sample(&output, 0.4),
+            approach(peak, 0.5, 0.2, 0.2),
+            abs <= 1e-4
+        );
+        // sustain
+        let sustain = approach(peak, 0.5, 0.4, 0.2);
+        assert_float_eq!(sustain, 0.5, abs <= 1e-4);
+        assert_float_eq!(sample(&output, 0.6), sustain, abs <= 1e-4);
+        // release
+        assert_float_eq!(
+            sample(&output, 0.7),
+            approach(sustain, 0., 0.1, 0.2),
+            abs <= 1e-4
+        );
+        assert_float_eq!(
+            sample(&output, 0.8),
+            approach(sustain, 0., 0.2, 0.2),
+            abs <= 1e-4
+        );
+        assert!(sample(&output, 0.8) < 0.005);
+        assert_float_eq!(
+            sample(&output, 0.9),
+            approach(sustain, 0., 0.3, 0.2),
+            abs <= 1e-4
+        );
+    }
+
+    #[test]
+    fn test_release_during_decay() {
+        let output = render(|param| {
+            ENVELOPE.trigger(param, 0.1);
+            ENVELOPE.release(param, 0.3);
+        });
+
+        let peak = approach(0., 1., 0.1, 0.1);
+        let level = approach(peak, 0.5, 0.1, 0.2);
+
+        // the release phase starts from the level reached in the decay phase
+        assert_float_eq!(sample(&output, 0.3), level, abs <= 1e-4);
+        assert_float_eq!(
+            sample(&output, 0.4),
+            approach(level, 0., 0.1, 0.2),
+            abs <= 1e-4
+        );
+        assert_float_eq!(
+            sample(&output, 0.5),
+            approach(level, 0., 0.2, 0.2),
+            abs <= 1e-4
+        );
+    }
+
+    #[test]
+    fn test_retrigger() {
+        let output = render(|param| {
+            ENVELOPE.trigger(param, 0.1);
+            ENVELOPE.release(param, 0.6);
+            // retrigger halfway the release phase
+            ENVELOPE.trigger(param, 0.7);
+        });
+
+        let sustain = approach(approach(0., 1., 0.1, 0.1), 0.5, 0.4, 0.2);
+        let level = approach(sustain, 0., 0.1, 0.2);
+
+        // the attack phase starts from the level reached in the release phase
+        assert_float_eq!(sample(&output, 0.7), level, abs <= 1e-4);
+        assert_float_eq!(
+            sample(&output, 0.75),
+            approach(level, 1., 0.05, 0.1),
+            abs <= 1e-4
+        );
+
+        let peak = approach(level, 1., 0.1, 0.1);
+        assert_float_eq!(sample(&output, 0.8), peak, abs <= 1e-4);
+        assert_float_eq!(
+            sample(&output, 0.9),
+            approach(peak, 0.5, 0.1, 0.2),
+            abs <= 1e-4
+        );
+    }
+
+    #[test]
+    fn test_zero_durations() {
+        let envelope = AdsrEnvelope {
+            sustain: 0.5,
+            ..AdsrEnvelope::default()
+        };
+
+        let output = render(|param| {
+            envelope.trigger(param, 0.2);
+            envelope.release(param, 0.4);
+        });
+
+        assert_float_eq!(sample(&output, 0.1), 0., abs <= 0.);
+        assert_float_eq!(sample(&output, 0.3), 0.5, abs <= 0.);
+        assert_float_eq!(sample(&output, 0.5), 0., abs <= 0.);
+    }
+
+    #[test]
+    fn test_glide_to() {
+        let output = render(|param| {
+            param.set_value_at_time(440., 0.);
+            glide_to(param, 880., 0.2, 0.1);
+        });
+
+        assert_float_eq!(sample(&output, 0.1), 440., abs <= 0.);
+        assert_float_eq!(
+            sample(&output, 0.2),
+            approach(440., 880., 0.1, 0.2),
+            abs <= 1e-2
+        );
+        // within 1% of the distance at the end of the glide
+        assert!((880. - sample(&output, 0.3)) < 4.4);
+        assert!((880. - sample(&output, 0.5)) < 0.1);
+    }
+
+    #[test]
+    fn test_glide_to_interrupted() {
+        let output = render(|param| {
+            param.set_value_at_time(440., 0.);
+            glide_to(param, 880., 0.2, 0.1);
+            glide_to(param, 220., 0.1, 0.2);
+        });
+
+        let level = approach(440., 880., 0.1, 0.2);
+        assert_float_eq!(sample(&output, 0.2), level, abs <= 1e-2);
+        assert_float_eq!(
+            sample(&output, 0.25),
+            approach(level, 220., 0.05, 0.1),
+            abs <= 1e-2
+        );
+        // within 1% of the distance at the end of the second glide
+        assert!((sample(&output, 0.3) - 220.) < 0.01 * (level - 220.));
+    }
+
+    #[test]
+    fn test_glide_to_zero_time() {
+        let output = render(|param| {
+            param.set_value_at_time(440., 0.);
+            glide_to(param, 880., 0., 0.1);
+        });
+
+        assert_float_eq!(sample(&output, 0.05), 440., abs <= 0.);
+        assert_float_eq!(sample(&output, 0.1), 880., abs <= 0.);
+    }
+
+    #[test]
+    fn test_glide_detune_to() {
+        // the constant signal stands in for the detune param of an oscillator
+        let output = render(|detune| glide_detune_to(detune, 440., 880., 0.2, 0.1));
+        let frequency = |time| 440. * 2_f32.powf(sample(&output, time) / 1200.);
+
+        assert_float_eq!(frequency(0.1), 440., abs <= 1e-3);
+        // halfway in pitch after one time constant ln(2) of the glide
+        let half = 0.1 + 0.2 / TIME_CONSTANTS_PER_PHASE * std::f64::consts::LN_2;
+        assert_float_eq!(frequency(half), 440. * 2_f32.sqrt(), abs <= 0.5);
+        // within 1% of the octave at the end of the glide
+        assert!(sample(&output, 0.3) > 1188.);
+        assert!(frequency(0.5) > 879.5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_glide_time() {
+        render(|param| glide_to(param, 1., -1., 0.));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_sustain() {
+        let envelope = AdsrEnvelope {
+            sustain: 2.,
+            ..AdsrEnvelope::default()
+        };
+
+        render(|param| envelope.trigger(param, 0.));
+    }
+}
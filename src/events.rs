@@ -1,29 +1,97 @@
-use crate::context::AudioNodeId;
+use crate::context::{AudioContextState, AudioNodeId};
 use crossbeam_channel::Receiver;
 use std::sync::{Arc, Mutex};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Failure of an [`AudioProcessor`](crate::render::AudioProcessor) on the render thread
+///
+/// See [`AudioContext::onerror`](crate::context::AudioContext::onerror) and
+/// [`OfflineAudioContext::onerror`](crate::context::OfflineAudioContext::onerror)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessorErrorEvent {
+    message: String,
+    current_time: f64,
+}
+
+impl ProcessorErrorEvent {
+    pub(crate) fn new(message: String, current_time: f64) -> Self {
+        Self {
+            message,
+            current_time,
+        }
+    }
+
+    /// Description of the failure, i.e. the panic message of the processor
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Time, in seconds, of the render quantum in which the failure occurred
+    #[must_use]
+    pub fn current_time(&self) -> f64 {
+        self.current_time
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Event {
     Ended(AudioNodeId),
     SinkChanged,
+    StateChange(AudioContextState),
+    #[cfg(any(feature = "cpal", feature = "cubeb", test))]
+    DeviceError,
+    ProcessorError(ProcessorErrorEvent),
+    RenderPoolExhausted(u64),
+    OutputSanitized(AudioNodeId),
+    NodeDropped(AudioNodeId),
+}
+
+impl Event {
+    fn type_(&self) -> EventType {
+        match self {
+            Self::Ended(id) => EventType::Ended(*id),
+            Self::SinkChanged => EventType::SinkChanged,
+            Self::StateChange(_) => EventType::StateChange,
+            #[cfg(any(feature = "cpal", feature = "cubeb", test))]
+            Self::DeviceError => EventType::DeviceError,
+            Self::ProcessorError(_) => EventType::ProcessorError,
+            Self::RenderPoolExhausted(_) => EventType::RenderPoolExhausted,
+            Self::OutputSanitized(_) => EventType::OutputSanitized,
+            Self::NodeDropped(_) => EventType::NodeDropped,
+        }
+    }
+}
+
+/// The kind of event an [`EventHandler`] is listening to, regardless of the event payload
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum EventType {
+    Ended(AudioNodeId),
+    SinkChanged,
+    StateChange,
+    #[cfg(any(feature = "cpal", feature = "cubeb", test))]
+    DeviceError,
+    ProcessorError,
+    RenderPoolExhausted,
+    OutputSanitized,
+    NodeDropped,
 }
 
 pub(crate) enum Callback {
-    Once(Box<dyn FnOnce() + Send + 'static>),
-    Multiple(Box<dyn FnMut() + Send + 'static>),
+    Once(Box<dyn FnOnce(Event) + Send + 'static>),
+    Multiple(Box<dyn FnMut(Event) + Send + 'static>),
 }
 
 impl Callback {
-    fn run(self) {
+    fn run(self, event: Event) {
         match self {
-            Self::Once(f) => (f)(),
-            Self::Multiple(mut f) => (f)(),
+            Self::Once(f) => (f)(event),
+            Self::Multiple(mut f) => (f)(event),
         }
     }
 }
 
 pub(crate) struct EventHandler {
-    pub event: Event,
+    pub event: EventType,
     pub callback: Callback,
 }
 
@@ -40,8 +108,9 @@ impl EventLoop {
     pub fn run(&self, event_channel: Receiver<Event>) {
         let self_clone = self.clone();
 
-        std::thread::spawn(move || loop {
-            // this thread is dedicated to event handling so we can block
+        std::thread::spawn(move || {
+            // this thread is dedicated to event handling so we can block, it exits once all
+            // senders (i.e. the render thread and the context) are dropped
             for message in event_channel.iter() {
                 let mut handlers = self_clone.callbacks.lock().unwrap();
                 // find EventHandlerInfos that matches messsage and execute callback
@@ -49,16 +118,16 @@ impl EventLoop {
                 let mut i = 0;
                 while i < handlers.len() {
                     let handler = &mut handlers[i];
-                    if handler.event != message {
+                    if handler.event != message.type_() {
                         i += 1;
                         continue;
                     }
                     if let Callback::Multiple(f) = &mut handler.callback {
-                        (f)();
+                        (f)(message.clone());
                         i += 1;
                     } else {
                         let handler = handlers.remove(i);
-                        handler.callback.run();
+                        handler.callback.run(message.clone());
                     }
                 }
             }
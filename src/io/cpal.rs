@@ -14,10 +14,11 @@ use cpal::{
 use super::{AudioBackendManager, MediaDeviceInfo, MediaDeviceInfoKind, RenderThreadInit};
 use crate::buffer::AudioBuffer;
 use crate::context::AudioContextOptions;
+use crate::events::Event;
 use crate::media::MicrophoneRender;
 use crate::render::RenderThread;
 
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, Sender};
 
 mod private {
     use super::*;
@@ -73,6 +74,7 @@ pub struct CpalBackend {
     output_latency: Arc<AtomicF64>,
     sample_rate: f32,
     number_of_channels: usize,
+    buffer_size: usize,
     sink_id: String,
 }
 
@@ -86,6 +88,7 @@ impl AudioBackendManager for CpalBackend {
 
         let RenderThreadInit {
             frames_played,
+            xruns,
             ctrl_msg_recv,
             load_value_send,
             event_send,
@@ -119,6 +122,7 @@ impl AudioBackendManager for CpalBackend {
         let buffer_size = super::buffer_size_for_latency_category(
             options.latency_hint,
             prefered.sample_rate.0 as f32,
+            options.render_quantum_size,
         ) as u32;
 
         let clamped_buffer_size: u32 = match supported.buffer_size() {
@@ -131,12 +135,14 @@ impl AudioBackendManager for CpalBackend {
         let output_latency = Arc::new(AtomicF64::new(0.));
         let number_of_channels = usize::from(prefered.channels);
         let mut sample_rate = prefered.sample_rate.0 as f32;
+        let mut buffer_size = clamped_buffer_size as usize;
 
         let renderer = RenderThread::new(
             sample_rate,
             prefered.channels as usize,
             ctrl_msg_recv.clone(),
             frames_played.clone(),
+            xruns.clone(),
             Some(load_value_send.clone()),
             Some(event_send.clone()),
         );
@@ -151,6 +157,7 @@ impl AudioBackendManager for CpalBackend {
             &prefered,
             renderer,
             output_latency.clone(),
+            event_send.clone(),
         );
 
         let stream = match spawned {
@@ -161,6 +168,8 @@ impl AudioBackendManager for CpalBackend {
             Err(e) => {
                 log::warn!("Output stream build failed with prefered config: {}", e);
                 sample_rate = supported.sample_rate().0 as f32;
+                // the default buffer size of the device is not known
+                buffer_size = 0;
                 let supported_config: StreamConfig = supported.clone().into();
                 log::debug!(
                     "Attempt output stream with fallback config: {:?}",
@@ -172,8 +181,9 @@ impl AudioBackendManager for CpalBackend {
                     supported_config.channels as usize,
                     ctrl_msg_recv,
                     frames_played,
+                    xruns,
                     Some(load_value_send),
-                    Some(event_send),
+                    Some(event_send.clone()),
                 );
 
                 let spawned = spawn_output_stream(
@@ -182,6 +192,7 @@ impl AudioBackendManager for CpalBackend {
                     &supported_config,
                     renderer,
                     output_latency.clone(),
+                    event_send,
                 );
                 spawned.expect("OutputStream build failed with default config")
             }
@@ -194,6 +205,7 @@ impl AudioBackendManager for CpalBackend {
             output_latency,
             sample_rate,
             number_of_channels,
+            buffer_size,
             sink_id: options.sink_id,
         }
     }
@@ -277,6 +289,7 @@ impl AudioBackendManager for CpalBackend {
             output_latency: Arc::new(AtomicF64::new(0.)),
             sample_rate,
             number_of_channels,
+            buffer_size: input_buffer_size as usize,
             sink_id: "".into(),
         };
 
@@ -303,6 +316,10 @@ impl AudioBackendManager for CpalBackend {
         self.number_of_channels
     }
 
+    fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
     fn output_latency(&self) -> f64 {
         self.output_latency.load()
     }
@@ -354,14 +371,20 @@ fn latency_in_seconds(infos: &OutputCallbackInfo) -> f64 {
 /// * `sample_format` - audio sample format of the stream
 /// * `config` - stream configuration
 /// * `render` - the render thread which process the audio data
+/// * `output_latency` - updated with the output latency of the stream
+/// * `error_sender` - notifies the control thread of stream errors (e.g. device unplugged)
 fn spawn_output_stream(
     device: &Device,
     sample_format: SampleFormat,
     config: &StreamConfig,
     mut render: RenderThread,
     output_latency: Arc<AtomicF64>,
+    error_sender: Sender<Event>,
 ) -> Result<Stream, BuildStreamError> {
-    let err_fn = |err| log::error!("an error occurred on the output audio stream: {}", err);
+    let err_fn = move |err| {
+        log::error!("an error occurred on the output audio stream: {}", err);
+        let _ = error_sender.send(Event::DeviceError);
+    };
 
     match sample_format {
         SampleFormat::F32 => device.build_output_stream(
@@ -4,13 +4,14 @@ use super::{AudioBackendManager, MediaDeviceInfo, MediaDeviceInfoKind, RenderThr
 
 use crate::buffer::AudioBuffer;
 use crate::context::AudioContextOptions;
+use crate::events::Event;
 use crate::media::MicrophoneRender;
 use crate::render::RenderThread;
 use crate::RENDER_QUANTUM_SIZE;
 
 use cubeb::{Context, DeviceId, DeviceType, StereoFrame, Stream, StreamParams};
 
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, Sender};
 
 // erase type of `Frame` in cubeb `Stream<Frame>`
 pub struct BoxedStream(Box<dyn CubebStream>);
@@ -99,6 +100,7 @@ fn init_output_backend<const N: usize>(
     buffer_size: u32,
     device: Option<DeviceId>,
     mut renderer: RenderThread,
+    error_sender: Sender<Event>,
 ) -> ThreadSafeClosableStream {
     let mut builder = cubeb::StreamBuilder::<[f32; N]>::new();
 
@@ -117,14 +119,17 @@ fn init_output_backend<const N: usize>(
             {
                 let output: &mut [f32] =
                     // SAFETY: `[T]` is layout-identical to `[T; N]`
-                    unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr().cast(), RENDER_QUANTUM_SIZE * N) };
+                    unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr().cast(), output.len() * N) };
                 renderer.render(output);
             }
 
             output.len() as isize
         })
-        .state_callback(|state| {
+        .state_callback(move |state| {
             println!("stream state changed: {:?}", state);
+            if matches!(state, cubeb::State::Error) {
+                let _ = error_sender.send(Event::DeviceError);
+            }
         });
 
     let stream = builder.init(ctx).expect("Failed to create cubeb stream");
@@ -137,6 +142,7 @@ pub struct CubebBackend {
     stream: ThreadSafeClosableStream,
     sample_rate: f32,
     number_of_channels: usize,
+    buffer_size: usize,
     sink_id: String,
 }
 
@@ -147,6 +153,7 @@ impl AudioBackendManager for CubebBackend {
     {
         let RenderThreadInit {
             frames_played,
+            xruns,
             ctrl_msg_recv,
             load_value_send,
             event_send,
@@ -178,8 +185,9 @@ impl AudioBackendManager for CubebBackend {
             number_of_channels,
             ctrl_msg_recv,
             frames_played,
+            xruns,
             Some(load_value_send),
-            Some(event_send),
+            Some(event_send.clone()),
         );
 
         let params = cubeb::StreamParamsBuilder::new()
@@ -190,8 +198,11 @@ impl AudioBackendManager for CubebBackend {
             .take();
 
         // Calculate ideal latency
-        let buffer_size_req =
-            super::buffer_size_for_latency_category(options.latency_hint, sample_rate) as u32;
+        let buffer_size_req = super::buffer_size_for_latency_category(
+            options.latency_hint,
+            sample_rate,
+            options.render_quantum_size,
+        ) as u32;
         let min_latency = ctx
             .min_latency(&params)
             .ok()
@@ -207,47 +218,32 @@ impl AudioBackendManager for CubebBackend {
                 .map(|e| *e.device().downcast::<DeviceId>().unwrap())
         };
 
-        let stream = match number_of_channels {
-            // so sorry, but I need to constify the non-const `number_of_channels`
-            1 => init_output_backend::<1>(&ctx, params, buffer_size, device_id, renderer),
-            2 => init_output_backend::<2>(&ctx, params, buffer_size, device_id, renderer),
-            3 => init_output_backend::<3>(&ctx, params, buffer_size, device_id, renderer),
-            4 => init_output_backend::<4>(&ctx, params, buffer_size, device_id, renderer),
-            5 => init_output_backend::<5>(&ctx, params, buffer_size, device_id, renderer),
-            6 => init_output_backend::<6>(&ctx, params, buffer_size, device_id, renderer),
-            7 => init_output_backend::<7>(&ctx, params, buffer_size, device_id, renderer),
-            8 => init_output_backend::<8>(&ctx, params, buffer_size, device_id, renderer),
-            9 => init_output_backend::<9>(&ctx, params, buffer_size, device_id, renderer),
-            10 => init_output_backend::<10>(&ctx, params, buffer_size, device_id, renderer),
-            11 => init_output_backend::<11>(&ctx, params, buffer_size, device_id, renderer),
-            12 => init_output_backend::<12>(&ctx, params, buffer_size, device_id, renderer),
-            13 => init_output_backend::<13>(&ctx, params, buffer_size, device_id, renderer),
-            14 => init_output_backend::<14>(&ctx, params, buffer_size, device_id, renderer),
-            15 => init_output_backend::<15>(&ctx, params, buffer_size, device_id, renderer),
-            16 => init_output_backend::<16>(&ctx, params, buffer_size, device_id, renderer),
-            17 => init_output_backend::<17>(&ctx, params, buffer_size, device_id, renderer),
-            18 => init_output_backend::<18>(&ctx, params, buffer_size, device_id, renderer),
-            19 => init_output_backend::<19>(&ctx, params, buffer_size, device_id, renderer),
-            20 => init_output_backend::<20>(&ctx, params, buffer_size, device_id, renderer),
-            21 => init_output_backend::<21>(&ctx, params, buffer_size, device_id, renderer),
-            22 => init_output_backend::<22>(&ctx, params, buffer_size, device_id, renderer),
-            23 => init_output_backend::<23>(&ctx, params, buffer_size, device_id, renderer),
-            24 => init_output_backend::<24>(&ctx, params, buffer_size, device_id, renderer),
-            25 => init_output_backend::<25>(&ctx, params, buffer_size, device_id, renderer),
-            26 => init_output_backend::<26>(&ctx, params, buffer_size, device_id, renderer),
-            27 => init_output_backend::<27>(&ctx, params, buffer_size, device_id, renderer),
-            28 => init_output_backend::<28>(&ctx, params, buffer_size, device_id, renderer),
-            29 => init_output_backend::<29>(&ctx, params, buffer_size, device_id, renderer),
-            30 => init_output_backend::<30>(&ctx, params, buffer_size, device_id, renderer),
-            31 => init_output_backend::<31>(&ctx, params, buffer_size, device_id, renderer),
-            32 => init_output_backend::<32>(&ctx, params, buffer_size, device_id, renderer),
-            _ => unreachable!(),
-        };
+        // so sorry, but I need to constify the non-const `number_of_channels`
+        macro_rules! init_output_backend_for {
+            ($($n:literal)*) => {
+                match number_of_channels {
+                    $($n => init_output_backend::<$n>(
+                        &ctx,
+                        params,
+                        buffer_size,
+                        device_id,
+                        renderer,
+                        event_send,
+                    ),)*
+                    _ => unreachable!(),
+                }
+            };
+        }
+
+        let stream = init_output_backend_for!(
+            1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+        );
 
         let backend = CubebBackend {
             stream,
             number_of_channels,
             sample_rate,
+            buffer_size: buffer_size as usize,
             sink_id: options.sink_id,
         };
 
@@ -287,8 +283,11 @@ impl AudioBackendManager for CubebBackend {
             .take();
 
         // Calculate ideal latency
-        let buffer_size_req =
-            super::buffer_size_for_latency_category(options.latency_hint, sample_rate) as u32;
+        let buffer_size_req = super::buffer_size_for_latency_category(
+            options.latency_hint,
+            sample_rate,
+            options.render_quantum_size,
+        ) as u32;
         let min_latency = ctx
             .min_latency(&params)
             .ok()
@@ -328,6 +327,7 @@ impl AudioBackendManager for CubebBackend {
             stream: ThreadSafeClosableStream::new(stream),
             number_of_channels,
             sample_rate,
+            buffer_size: buffer_size as usize,
             sink_id: "".into(),
         };
 
@@ -354,6 +354,10 @@ impl AudioBackendManager for CubebBackend {
         self.number_of_channels
     }
 
+    fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
     fn output_latency(&self) -> f64 {
         self.stream.output_latency(self.sample_rate)
     }
@@ -0,0 +1,149 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::{AudioBackendManager, MediaDeviceInfo, RenderThreadInit};
+use crate::buffer::AudioBuffer;
+use crate::context::AudioContextOptions;
+use crate::render::RenderThread;
+
+use crossbeam_channel::Receiver;
+
+/// Backend without an audio stream, the host application pulls the rendered audio
+///
+/// The `RenderThread` is handed over to the `AudioContext`, the backend only keeps track of
+/// whether the stream is running.
+#[derive(Clone)]
+pub struct ManualBackend {
+    running: Arc<AtomicBool>,
+    sample_rate: f32,
+    number_of_channels: usize,
+}
+
+impl ManualBackend {
+    /// Setup the backend and the render thread that will be driven by the host
+    pub fn build(
+        sample_rate: f32,
+        number_of_channels: usize,
+        render_thread_init: RenderThreadInit,
+    ) -> (Self, RenderThread) {
+        let RenderThreadInit {
+            frames_played,
+            xruns,
+            ctrl_msg_recv,
+            load_value_send,
+            event_send,
+        } = render_thread_init;
+
+        let render_thread = RenderThread::new(
+            sample_rate,
+            number_of_channels,
+            ctrl_msg_recv,
+            frames_played,
+            xruns,
+            Some(load_value_send),
+            Some(event_send),
+        );
+
+        let backend = Self {
+            running: Arc::new(AtomicBool::new(true)),
+            sample_rate,
+            number_of_channels,
+        };
+
+        (backend, render_thread)
+    }
+
+    /// Shared flag indicating if the host should render audio or output silence
+    pub fn running(&self) -> Arc<AtomicBool> {
+        self.running.clone()
+    }
+}
+
+impl AudioBackendManager for ManualBackend {
+    /// The render thread is driven by the host, use `ManualBackend::build` instead
+    ///
+    /// # Panics
+    ///
+    /// Always, the `AudioContext` only creates this backend with `ManualBackend::build`
+    fn build_output(_options: AudioContextOptions, _render_thread_init: RenderThreadInit) -> Self
+    where
+        Self: Sized,
+    {
+        panic!("the manual backend hands its render thread to the host, use ManualBackend::build")
+    }
+
+    /// The manual backend has no input stream
+    ///
+    /// # Panics
+    ///
+    /// Always, microphone capture goes through the backend selected by the cargo features
+    fn build_input(_options: AudioContextOptions) -> (Self, Receiver<AudioBuffer>)
+    where
+        Self: Sized,
+    {
+        panic!("the manual backend does not capture input, it only renders the output for the host")
+    }
+
+    /// Resume or start the stream
+    fn resume(&self) -> bool {
+        self.running.store(true, Ordering::SeqCst);
+        true
+    }
+
+    /// Suspend the stream
+    fn suspend(&self) -> bool {
+        self.running.store(false, Ordering::SeqCst);
+        true
+    }
+
+    /// Close the stream, freeing all resources. It cannot be started again after closing.
+    fn close(&self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    /// Sample rate of the stream
+    fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Number of channels of the stream
+    fn number_of_channels(&self) -> usize {
+        self.number_of_channels
+    }
+
+    /// Number of frames rendered per audio callback
+    ///
+    /// The host decides how many frames are pulled at once.
+    fn buffer_size(&self) -> usize {
+        0
+    }
+
+    /// Output latency of the stream in seconds
+    ///
+    /// The latency of the host audio callback is not known.
+    fn output_latency(&self) -> f64 {
+        0.
+    }
+
+    /// The audio output device
+    fn sink_id(&self) -> &str {
+        "manual"
+    }
+
+    /// Clone the stream reference
+    fn boxed_clone(&self) -> Box<dyn AudioBackendManager> {
+        Box::new(self.clone())
+    }
+
+    /// The manual backend has no devices
+    ///
+    /// # Panics
+    ///
+    /// Always, devices are listed by the backend selected by the cargo features
+    fn enumerate_devices() -> Vec<MediaDeviceInfo>
+    where
+        Self: Sized,
+    {
+        panic!("the manual backend has no devices, use the backend selected by the cargo features")
+    }
+}
@@ -11,6 +11,9 @@ use crate::events::Event;
 use crate::message::ControlMessage;
 use crate::{AudioRenderCapacityLoad, RENDER_QUANTUM_SIZE};
 
+mod manual;
+pub(crate) use manual::ManualBackend;
+
 mod none;
 
 #[cfg(feature = "cpal")]
@@ -50,6 +53,7 @@ pub fn enumerate_devices() -> Vec<MediaDeviceInfo> {
 #[derive(Debug)]
 pub(crate) struct ControlThreadInit {
     pub frames_played: Arc<AtomicU64>,
+    pub xruns: Arc<AtomicU64>,
     pub ctrl_msg_send: Sender<ControlMessage>,
     pub load_value_recv: Receiver<AudioRenderCapacityLoad>,
     pub event_send: Sender<Event>,
@@ -59,6 +63,7 @@ pub(crate) struct ControlThreadInit {
 #[derive(Clone, Debug)]
 pub(crate) struct RenderThreadInit {
     pub frames_played: Arc<AtomicU64>,
+    pub xruns: Arc<AtomicU64>,
     pub ctrl_msg_recv: Receiver<ControlMessage>,
     pub load_value_send: Sender<AudioRenderCapacityLoad>,
     pub event_send: Sender<Event>,
@@ -67,6 +72,8 @@ pub(crate) struct RenderThreadInit {
 pub(crate) fn thread_init() -> (ControlThreadInit, RenderThreadInit) {
     // track number of frames - synced from render thread to control thread
     let frames_played = Arc::new(AtomicU64::new(0));
+    // count render callbacks that took longer than the audio they rendered
+    let xruns = Arc::new(AtomicU64::new(0));
     // communication channel for ctrl msgs to the render thread
     let (ctrl_msg_send, ctrl_msg_recv) = crossbeam_channel::unbounded();
     // communication channel for render load values
@@ -76,6 +83,7 @@ pub(crate) fn thread_init() -> (ControlThreadInit, RenderThreadInit) {
 
     let control_thread_init = ControlThreadInit {
         frames_played: frames_played.clone(),
+        xruns: xruns.clone(),
         ctrl_msg_send,
         load_value_recv,
         event_send: event_send.clone(),
@@ -84,6 +92,7 @@ pub(crate) fn thread_init() -> (ControlThreadInit, RenderThreadInit) {
 
     let render_thread_init = RenderThreadInit {
         frames_played,
+        xruns,
         ctrl_msg_recv,
         load_value_send,
         event_send,
@@ -166,6 +175,9 @@ pub(crate) trait AudioBackendManager: Send + Sync + 'static {
     /// Number of channels of the stream
     fn number_of_channels(&self) -> usize;
 
+    /// Number of frames rendered per audio callback, `0` when unknown
+    fn buffer_size(&self) -> usize;
+
     /// Output latency of the stream in seconds
     ///
     /// This is the difference between the time the backend acquires the data in the callback and
@@ -187,15 +199,17 @@ pub(crate) trait AudioBackendManager: Send + Sync + 'static {
 fn buffer_size_for_latency_category(
     latency_cat: AudioContextLatencyCategory,
     sample_rate: f32,
+    render_quantum_size: usize,
 ) -> usize {
     // at 44100Hz sample rate (this could be even more relaxed):
-    // Interactive: 128 samples is 2,9ms
+    // Interactive: 128 samples is 2,9ms (or a single render quantum)
     // Balanced:    512 samples is 11,6ms
     // Playback:    1024 samples is 23,2ms
+    // the render quantum size is a power of two, so all sizes are a multiple of it
     match latency_cat {
-        AudioContextLatencyCategory::Interactive => RENDER_QUANTUM_SIZE,
-        AudioContextLatencyCategory::Balanced => RENDER_QUANTUM_SIZE * 4,
-        AudioContextLatencyCategory::Playback => RENDER_QUANTUM_SIZE * 8,
+        AudioContextLatencyCategory::Interactive => render_quantum_size,
+        AudioContextLatencyCategory::Balanced => (RENDER_QUANTUM_SIZE * 4).max(render_quantum_size),
+        AudioContextLatencyCategory::Playback => (RENDER_QUANTUM_SIZE * 8).max(render_quantum_size),
         // buffer_size is always positive and truncation is the desired behavior
         #[allow(clippy::cast_sign_loss)]
         #[allow(clippy::cast_possible_truncation)]
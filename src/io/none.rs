@@ -5,9 +5,8 @@ use super::{AudioBackendManager, MediaDeviceInfo, RenderThreadInit};
 use crate::buffer::AudioBuffer;
 use crate::context::AudioContextOptions;
 use crate::render::RenderThread;
-use crate::RENDER_QUANTUM_SIZE;
 
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
 
 const NUMBER_OF_CHANNELS: usize = 2;
 
@@ -21,18 +20,20 @@ enum NoneBackendMessage {
 pub struct NoneBackend {
     sender: Sender<NoneBackendMessage>,
     sample_rate: f32,
+    buffer_size: usize,
 }
 
 struct Callback {
     receiver: Receiver<NoneBackendMessage>,
     render_thread: RenderThread,
     sample_rate: f32,
+    buffer_size: usize,
     running: bool,
 }
 
 impl Callback {
     fn run(mut self) {
-        let buffer_size = RENDER_QUANTUM_SIZE; // TODO Latency Category
+        let buffer_size = self.buffer_size;
         let mut buffer = vec![0.; buffer_size * NUMBER_OF_CHANNELS];
         let interval = Duration::from_secs_f32(buffer_size as f32 / self.sample_rate);
 
@@ -41,15 +42,18 @@ impl Callback {
 
         loop {
             // poll the receiver as long as the deadline is in the future
-            while let Ok(msg) = self.receiver.recv_deadline(deadline) {
-                match msg {
-                    NoneBackendMessage::Close => return,
-                    NoneBackendMessage::Resume => {
+            loop {
+                match self.receiver.recv_deadline(deadline) {
+                    Ok(NoneBackendMessage::Close) => return,
+                    Ok(NoneBackendMessage::Resume) => {
                         self.running = true;
                         deadline = Instant::now().checked_add(interval).unwrap();
                         break; // start processing right away
                     }
-                    NoneBackendMessage::Suspend => self.running = false,
+                    Ok(NoneBackendMessage::Suspend) => self.running = false,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    // the backend was dropped without closing it, e.g. with its context
+                    Err(RecvTimeoutError::Disconnected) => return,
                 }
             }
 
@@ -70,9 +74,15 @@ impl AudioBackendManager for NoneBackend {
     {
         let sample_rate = options.sample_rate.unwrap_or(48000.);
         let channels = NUMBER_OF_CHANNELS;
+        let buffer_size = super::buffer_size_for_latency_category(
+            options.latency_hint,
+            sample_rate,
+            options.render_quantum_size,
+        );
 
         let RenderThreadInit {
             frames_played,
+            xruns,
             ctrl_msg_recv,
             load_value_send,
             event_send,
@@ -83,17 +93,18 @@ impl AudioBackendManager for NoneBackend {
             channels,
             ctrl_msg_recv,
             frames_played,
+            xruns,
             Some(load_value_send),
             Some(event_send),
         );
 
         let (sender, receiver) = crossbeam_channel::unbounded();
 
-        // todo: pass buffer size and sample rate
         let callback = Callback {
             render_thread,
             receiver,
             sample_rate,
+            buffer_size,
             running: true,
         };
 
@@ -102,6 +113,7 @@ impl AudioBackendManager for NoneBackend {
         Self {
             sender,
             sample_rate,
+            buffer_size,
         }
     }
 
@@ -140,6 +152,11 @@ impl AudioBackendManager for NoneBackend {
         NUMBER_OF_CHANNELS
     }
 
+    /// Number of frames rendered per audio callback
+    fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
     /// Output latency of the stream in seconds
     ///
     /// This is the difference between the time the backend acquires the data in the callback and
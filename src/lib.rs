@@ -41,8 +41,16 @@ use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
 /// Render quantum size, the audio graph is rendered in blocks of RENDER_QUANTUM_SIZE samples
 /// see. <https://webaudio.github.io/web-audio-api/#render-quantum>
+///
+/// This is the default size, a context can be configured with another size, see
+/// [`BaseAudioContext::render_quantum_size`](crate::context::BaseAudioContext::render_quantum_size)
 pub const RENDER_QUANTUM_SIZE: usize = 128;
 
+/// Smallest supported render quantum size
+pub(crate) const MIN_RENDER_QUANTUM_SIZE: usize = 32;
+/// Largest supported render quantum size
+pub(crate) const MAX_RENDER_QUANTUM_SIZE: usize = 4096;
+
 /// Maximum number of channels for audio processing
 pub const MAX_CHANNELS: usize = 32;
 
@@ -54,10 +62,16 @@ pub use capacity::*;
 
 pub mod context;
 pub(crate) mod control;
+pub use control::{NoteDivision, NoteValue, TempoClock, TempoClockOptions};
+
+mod envelope;
+pub use envelope::*;
+
 pub mod media;
 pub mod node;
 
 mod events;
+pub use events::ProcessorErrorEvent;
 
 mod param;
 pub use param::*;
@@ -67,25 +81,33 @@ pub use periodic_wave::*;
 
 pub mod render;
 
+mod resampler;
+pub use resampler::ResampleQuality;
+
 mod sample;
 pub(crate) use sample::Sample;
 
 mod spatial;
 pub use spatial::AudioListener;
 
+pub mod util;
+
+mod voice;
+pub use voice::*;
+
 mod io;
 pub use io::{enumerate_devices, MediaDeviceInfo, MediaDeviceInfoKind};
 
 mod analysis;
 mod message;
+mod triple_buffer;
 
-/// Atomic float 32, only `load` and `store` are supported, no arithmetics
+/// Atomic float 32, only `load`, `store` and `swap` are supported, no arithmetics
 #[derive(Debug)]
 pub(crate) struct AtomicF32 {
     inner: AtomicU32,
 }
 
-// `swap()` is not implemented as `AtomicF32` is only used in `param.rs` for now
 impl AtomicF32 {
     pub fn new(v: f32) -> Self {
         Self {
@@ -101,6 +123,13 @@ impl AtomicF32 {
         self.inner
             .store(u32::from_ne_bytes(v.to_ne_bytes()), Ordering::SeqCst)
     }
+
+    pub fn swap(&self, v: f32) -> f32 {
+        let prev = self
+            .inner
+            .swap(u32::from_ne_bytes(v.to_ne_bytes()), Ordering::SeqCst);
+        f32::from_ne_bytes(prev.to_ne_bytes())
+    }
 }
 
 /// Atomic float 64, only `load` and `store` are supported, no arithmetics
@@ -152,6 +181,26 @@ pub(crate) fn assert_valid_sample_rate(sample_rate: f32) {
     }
 }
 
+/// Assert that the given render quantum size is valid.
+///
+/// # Panics
+///
+/// This function will panic if:
+/// - the given size is not a power of two in the [32, 4096] range
+///
+#[track_caller]
+#[inline(always)]
+pub(crate) fn assert_valid_render_quantum_size(render_quantum_size: usize) {
+    if !render_quantum_size.is_power_of_two()
+        || !(MIN_RENDER_QUANTUM_SIZE..=MAX_RENDER_QUANTUM_SIZE).contains(&render_quantum_size)
+    {
+        panic!(
+            "NotSupportedError - Invalid render quantum size: {:?}, should be a power of two in range [{:?}, {:?}]",
+            render_quantum_size, MIN_RENDER_QUANTUM_SIZE, MAX_RENDER_QUANTUM_SIZE
+        );
+    }
+}
+
 /// Assert that the given number of channels is valid.
 ///
 /// # Panics
@@ -190,6 +239,36 @@ pub(crate) fn assert_valid_channel_number(channel_number: usize, number_of_chann
     }
 }
 
+/// Magnitude below which the state of recursive processors is flushed to zero
+///
+/// About -300dB, far below the resolution of any audio output, but far above the range of
+/// subnormal numbers (`f32::MIN_POSITIVE` is about 1e-38) which are very slow to process on some
+/// CPUs. Flushing also lets the tail time of the processors end once the state has decayed.
+pub(crate) const DENORMAL_THRESHOLD: f64 = 1e-15;
+
+/// Flush the value to zero if its magnitude is below [`DENORMAL_THRESHOLD`]
+///
+/// Recursive processors apply it to their feedback state once per render quantum, so a decaying
+/// state never reaches the subnormal range.
+#[inline(always)]
+pub(crate) fn flush_denormal(value: f64) -> f64 {
+    if value.abs() < DENORMAL_THRESHOLD {
+        0.
+    } else {
+        value
+    }
+}
+
+/// Same as [`flush_denormal`] for single precision state
+#[inline(always)]
+pub(crate) fn flush_denormal_f32(value: f32) -> f32 {
+    if f64::from(value.abs()) < DENORMAL_THRESHOLD {
+        0.
+    } else {
+        value
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use float_eq::assert_float_eq;
@@ -205,6 +284,19 @@ mod tests {
         assert_float_eq!(f.load(), 3.0, abs <= 0.);
     }
 
+    #[test]
+    fn test_flush_denormal() {
+        assert_float_eq!(flush_denormal(1e-16), 0., abs <= 0.);
+        assert_float_eq!(flush_denormal(-1e-16), 0., abs <= 0.);
+        assert_float_eq!(flush_denormal(f64::MIN_POSITIVE / 2.), 0., abs <= 0.);
+        assert_float_eq!(flush_denormal(1e-14), 1e-14, abs <= 0.);
+        assert_float_eq!(flush_denormal(-0.5), -0.5, abs <= 0.);
+
+        assert_float_eq!(flush_denormal_f32(f32::MIN_POSITIVE / 2.), 0., abs <= 0.);
+        assert_float_eq!(flush_denormal_f32(-1e-16), 0., abs <= 0.);
+        assert_float_eq!(flush_denormal_f32(1e-6), 1e-6, abs <= 0.);
+    }
+
     #[test]
     #[should_panic]
     fn test_invalid_sample_rate_zero() {
@@ -228,6 +320,25 @@ mod tests {
         assert_valid_sample_rate(48000.);
     }
 
+    #[test]
+    #[should_panic]
+    fn test_invalid_render_quantum_size_not_power_of_two() {
+        assert_valid_render_quantum_size(100);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_render_quantum_size_too_small() {
+        assert_valid_render_quantum_size(16);
+    }
+
+    #[test]
+    fn test_valid_render_quantum_size() {
+        assert_valid_render_quantum_size(32);
+        assert_valid_render_quantum_size(RENDER_QUANTUM_SIZE);
+        assert_valid_render_quantum_size(4096);
+    }
+
     #[test]
     #[should_panic]
     fn test_invalid_number_of_channels_min() {
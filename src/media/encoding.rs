@@ -0,0 +1,145 @@
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+use crate::buffer::AudioBuffer;
+
+/// `WAVE_FORMAT_IEEE_FLOAT` format tag
+const FORMAT_IEEE_FLOAT: u16 = 3;
+/// Size of a sample, in bytes
+const BYTES_PER_SAMPLE: u16 = 4;
+/// Size of the header up to the data, excluding the first 8 bytes of the RIFF chunk
+const HEADER_SIZE: u32 = 4 + (8 + 18) + (8 + 4) + 8;
+
+/// Write an [`AudioBuffer`] as a 32-bit float WAV file
+///
+/// The samples are interleaved and written without any conversion, the encoding is lossless. The
+/// file can be decoded back with
+/// [`BaseAudioContext::decode_audio_data_sync`](crate::context::BaseAudioContext::decode_audio_data_sync).
+///
+/// This function is not part of the Web Audio API specification.
+///
+/// # Errors
+///
+/// This function returns the errors of the writer, or an error of kind
+/// [`InvalidInput`](io::ErrorKind::InvalidInput) when the buffer exceeds the 4GB size limit of
+/// the format.
+///
+/// # Example
+///
+/// ```
+/// use web_audio_api::AudioBuffer;
+/// use web_audio_api::media::write_wav;
+///
+/// let buffer = AudioBuffer::from(vec![vec![0.; 128], vec![1.; 128]], 48_000.);
+///
+/// let mut bytes = vec![];
+/// write_wav(&buffer, &mut bytes).unwrap();
+/// assert_eq!(bytes.len(), 58 + 2 * 128 * 4);
+/// ```
+pub fn write_wav<W: Write>(buffer: &AudioBuffer, writer: W) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(writer);
+
+    let number_of_channels = buffer.number_of_channels();
+    let length = buffer.length();
+    let too_large = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "audio buffer too large for the WAV format",
+        )
+    };
+    let frames = u32::try_from(length).map_err(|_| too_large())?;
+    let data_size = (number_of_channels * length)
+        .checked_mul(BYTES_PER_SAMPLE as usize)
+        .and_then(|size| u32::try_from(size).ok())
+        .filter(|size| size.checked_add(HEADER_SIZE).is_some())
+        .ok_or_else(too_large)?;
+
+    let channels = number_of_channels as u16;
+    let sample_rate = buffer.sample_rate() as u32;
+    let block_align = channels * BYTES_PER_SAMPLE;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(HEADER_SIZE + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&18_u32.to_le_bytes())?;
+    writer.write_all(&FORMAT_IEEE_FLOAT.to_le_bytes())?;
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * u32::from(block_align)).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&(BYTES_PER_SAMPLE * 8).to_le_bytes())?;
+    // no extension
+    writer.write_all(&0_u16.to_le_bytes())?;
+
+    // required for formats other than PCM
+    writer.write_all(b"fact")?;
+    writer.write_all(&4_u32.to_le_bytes())?;
+    writer.write_all(&frames.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    let channels: Vec<&[f32]> = (0..number_of_channels)
+        .map(|channel| buffer.get_channel_data(channel))
+        .collect();
+    for i in 0..length {
+        for channel in &channels {
+            writer.write_all(&channel[i].to_le_bytes())?;
+        }
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+
+    use super::*;
+
+    #[test]
+    fn test_header() {
+        let buffer = AudioBuffer::from(vec![vec![0.; 3], vec![0.; 3]], 44_100.);
+        let mut bytes = vec![];
+        write_wav(&buffer, &mut bytes).unwrap();
+
+        assert_eq!(bytes.len(), 58 + 2 * 3 * 4);
+        assert_eq!(&bytes[..4], b"RIFF");
+        assert_eq!(&bytes[4..8], &(bytes.len() as u32 - 8).to_le_bytes());
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        // float, stereo, 44.1kHz, 8 bytes per frame, 32 bits
+        assert_eq!(&bytes[20..22], &3_u16.to_le_bytes());
+        assert_eq!(&bytes[22..24], &2_u16.to_le_bytes());
+        assert_eq!(&bytes[24..28], &44_100_u32.to_le_bytes());
+        assert_eq!(&bytes[28..32], &(44_100_u32 * 8).to_le_bytes());
+        assert_eq!(&bytes[32..34], &8_u16.to_le_bytes());
+        assert_eq!(&bytes[34..36], &32_u16.to_le_bytes());
+        assert_eq!(&bytes[38..42], b"fact");
+        assert_eq!(&bytes[46..50], &3_u32.to_le_bytes());
+        assert_eq!(&bytes[50..54], b"data");
+        assert_eq!(&bytes[54..58], &24_u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let left: Vec<f32> = (0..1000).map(|i| (i as f32 / 1000.).sin()).collect();
+        let right: Vec<f32> = left.iter().map(|v| -0.5 * v).collect();
+        let buffer = AudioBuffer::from(vec![left.clone(), right.clone()], 48_000.);
+
+        let mut bytes = vec![];
+        write_wav(&buffer, &mut bytes).unwrap();
+
+        let context = OfflineAudioContext::new(1, 1, 48_000.);
+        let decoded = context.decode_audio_data_sync(Cursor::new(bytes)).unwrap();
+        assert_eq!(decoded.number_of_channels(), 2);
+        assert_eq!(decoded.length(), 1000);
+        assert_float_eq!(decoded.sample_rate(), 48_000., abs <= 0.);
+        assert_float_eq!(decoded.get_channel_data(0), &left[..], abs_all <= 0.);
+        assert_float_eq!(decoded.get_channel_data(1), &right[..], abs_all <= 0.);
+    }
+}
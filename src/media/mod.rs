@@ -1,8 +1,11 @@
-//! Convenience abstractions that are not part of the WebAudio API (media decoding, microphone)
+//! Convenience abstractions that are not part of the WebAudio API (media decoding and encoding, microphone)
 
 mod decoding;
 pub use decoding::MediaDecoder;
 
+mod encoding;
+pub use encoding::write_wav;
+
 mod element;
 pub use element::MediaElement;
 
@@ -70,7 +70,7 @@ impl<M: MediaStream> Iterator for Resampler<M> {
                 None => return None,
                 Some(Err(e)) => return Some(Err(e)),
                 Some(Ok(mut data)) => {
-                    data.resample(self.sample_rate);
+                    data.resample_in_place(self.sample_rate);
                     data
                 }
             },
@@ -94,7 +94,7 @@ impl<M: MediaStream> Iterator for Resampler<M> {
                 }
                 Some(Err(e)) => return Some(Err(e)),
                 Some(Ok(mut data)) => {
-                    data.resample(self.sample_rate);
+                    data.resample_in_place(self.sample_rate);
                     buffer.extend(&data)
                 }
             }
@@ -45,6 +45,18 @@ pub(crate) enum ControlMessage {
     /// Mark node as a cycle breaker (DelayNode only)
     MarkCycleBreaker { id: AudioNodeId },
 
+    /// Pass the input of this node straight to its output, skipping its processor
+    SetBypass { id: AudioNodeId, bypassed: bool },
+
+    /// Silence the outputs of this node
+    SetMuted { id: AudioNodeId, muted: bool },
+
+    /// Solo this node, silencing the other mixer channels
+    SetSoloed { id: AudioNodeId, soloed: bool },
+
+    /// Unsolo all nodes
+    ClearSolo,
+
     /// Shut down and recycle the audio graph
     Shutdown { sender: Sender<Graph> },
 
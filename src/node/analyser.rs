@@ -1,9 +1,10 @@
-use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use crate::analysis::Analyser;
+use crate::analysis::{Analyser, MultiChannelTimeAnalyser, MAX_SAMPLES};
 use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::AtomicF64;
 
 use super::{AudioNode, ChannelConfig, ChannelConfigOptions, ChannelInterpretation};
 
@@ -24,6 +25,10 @@ pub struct AnalyserOptions {
     #[allow(dead_code)]
     pub min_decibels: f64,
     pub smoothing_time_constant: f64,
+    /// The input channel to analyse
+    pub channel: AnalyserChannel,
+    /// The weighting curve applied to the frequency data
+    pub frequency_weighting: FrequencyWeighting,
     pub channel_config: ChannelConfigOptions,
 }
 
@@ -34,11 +39,98 @@ impl Default for AnalyserOptions {
             max_decibels: -30.,
             min_decibels: 100.,
             smoothing_time_constant: 0.8,
+            channel: AnalyserChannel::default(),
+            frequency_weighting: FrequencyWeighting::default(),
             channel_config: ChannelConfigOptions::default(),
         }
     }
 }
 
+/// Input channel analysed by an [`AnalyserNode`]
+///
+/// This enum is not part of the Web Audio API specification, which always analyses the down-mix.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum AnalyserChannel {
+    /// Down-mix of all channels to mono, according to the speakers interpretation
+    #[default]
+    Downmix,
+    /// A single channel, e.g. `Channel(0)` for the left channel of a stereo signal
+    ///
+    /// Falls back to the down-mix when the input has fewer channels.
+    Channel(usize),
+}
+
+impl From<usize> for AnalyserChannel {
+    fn from(i: usize) -> Self {
+        match i {
+            0 => AnalyserChannel::Downmix,
+            i => AnalyserChannel::Channel(i - 1),
+        }
+    }
+}
+
+impl From<AnalyserChannel> for usize {
+    fn from(channel: AnalyserChannel) -> Self {
+        match channel {
+            AnalyserChannel::Downmix => 0,
+            AnalyserChannel::Channel(i) => i + 1,
+        }
+    }
+}
+
+/// Weighting curve applied to the frequency data of an [`AnalyserNode`]
+///
+/// The curves are defined in IEC 61672-1, they model the sensitivity of the human ear to get
+/// readings related to the perceived loudness, e.g. for sound level measurements.
+///
+/// This enum is not part of the Web Audio API specification.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum FrequencyWeighting {
+    /// Unweighted frequency data
+    #[default]
+    None,
+    /// A-weighting, for sound levels at low volume: the low and the high frequencies are
+    /// attenuated strongly, e.g. -19.1dB at 100Hz
+    A,
+    /// C-weighting, for sound levels at high volume: nearly flat, the extreme low and high
+    /// frequencies are attenuated, e.g. -3dB at 31.5Hz and 8kHz
+    C,
+}
+
+impl From<usize> for FrequencyWeighting {
+    fn from(i: usize) -> Self {
+        match i {
+            0 => FrequencyWeighting::None,
+            1 => FrequencyWeighting::A,
+            2 => FrequencyWeighting::C,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Gain in dB of the weighting curve at the given frequency
+fn weighting_db(weighting: FrequencyWeighting, frequency: f64) -> f64 {
+    // poles of the curves (IEC 61672-1), the constants normalize the gain at 1kHz to 0dB
+    const F1: f64 = 20.598_997;
+    const F2: f64 = 107.652_65;
+    const F3: f64 = 737.862_23;
+    const F4: f64 = 12_194.217;
+
+    let f2 = frequency * frequency;
+    match weighting {
+        FrequencyWeighting::None => 0.,
+        FrequencyWeighting::A => {
+            let gain = F4 * F4 * f2 * f2
+                / ((f2 + F1 * F1) * ((f2 + F2 * F2) * (f2 + F3 * F3)).sqrt() * (f2 + F4 * F4));
+            20. * gain.log10() + 2.
+        }
+        FrequencyWeighting::C => {
+            let gain = F4 * F4 * f2 / ((f2 + F1 * F1) * (f2 + F4 * F4));
+            20. * gain.log10() + 0.062
+        }
+    }
+}
+
 enum AnalyserRequest {
     FloatTime {
         sender: Sender<Vec<f32>>,
@@ -48,6 +140,233 @@ enum AnalyserRequest {
         sender: Sender<Vec<f32>>,
         buffer: Vec<f32>,
     },
+    TimeDomain {
+        sender: Sender<Vec<f32>>,
+        buffer: Vec<f32>,
+    },
+    InterleavedTime {
+        sender: Sender<Vec<f32>>,
+        buffer: Vec<f32>,
+        number_of_channels: usize,
+    },
+    Snapshot {
+        sender: Sender<AnalyserSnapshot>,
+        snapshot: AnalyserSnapshot,
+    },
+}
+
+/// Time domain and frequency data of an [`AnalyserNode`], captured at the same instant
+///
+/// The time domain data is the window the frequency data was calculated from, so both always
+/// describe the same input. Only this window (`fft_size` samples) and the frequency bins are
+/// copied out of the render thread, the data can then be read any number of times.
+///
+/// This struct is not part of the Web Audio API specification.
+///
+/// - see also: [`AnalyserNode::snapshot`]
+#[derive(Clone, Debug)]
+pub struct AnalyserSnapshot {
+    time: Vec<f32>,
+    frequency: Vec<f32>,
+}
+
+impl AnalyserSnapshot {
+    /// The size of the FFT the frequency data was calculated with (in sample-frames)
+    #[must_use]
+    pub fn fft_size(&self) -> usize {
+        self.time.len()
+    }
+
+    /// Number of frequency bins, half the FFT size
+    #[must_use]
+    pub fn frequency_bin_count(&self) -> usize {
+        self.frequency.len()
+    }
+
+    /// The analysed window of time domain data, the last value is the most recent sample
+    #[must_use]
+    pub fn time_domain(&self) -> &[f32] {
+        &self.time
+    }
+
+    /// The frequency data, in dB
+    #[must_use]
+    pub fn frequency(&self) -> &[f32] {
+        &self.frequency
+    }
+
+    /// Copies the time domain data into the provided buffer
+    ///
+    /// At most `fft_size` values are copied, the rest of the buffer is left unaltered.
+    pub fn get_float_time(&self, buffer: &mut [f32]) {
+        let len = buffer.len().min(self.time.len());
+        buffer[..len].copy_from_slice(&self.time[..len]);
+    }
+
+    /// Copies the frequency data (in dB) into the provided buffer
+    ///
+    /// At most `frequency_bin_count` values are copied, the rest of the buffer is left
+    /// unaltered.
+    pub fn get_float_frequency(&self, buffer: &mut [f32]) {
+        let len = buffer.len().min(self.frequency.len());
+        buffer[..len].copy_from_slice(&self.frequency[..len]);
+    }
+}
+
+/// History of the frequency data of an [`AnalyserNode`], e.g. to draw a spectrogram
+///
+/// Each call to [`update`](Self::update) adds a column with the current frequency data (in dB)
+/// of the analyser, but only if the analyser calculated a new FFT since the previous column was
+/// added. Calling it faster than the FFT rate thus never duplicates columns, calling it slower
+/// skips the intermediate frames. Once `history` columns are stored, the oldest one is dropped
+/// for each new column.
+///
+/// This struct is not part of the Web Audio API specification.
+#[derive(Clone, Debug)]
+pub struct SpectrogramBuffer {
+    history: usize,
+    frequency_bin_count: usize,
+    /// the columns, from oldest to newest
+    data: Vec<f32>,
+    /// frame count of the analyser when the last column was added
+    frame: usize,
+}
+
+impl SpectrogramBuffer {
+    /// Returns an empty `SpectrogramBuffer` which keeps at most `history` columns
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `history` is zero
+    #[must_use]
+    pub fn new(history: usize) -> Self {
+        assert_valid_history(history);
+
+        Self {
+            history,
+            frequency_bin_count: 0,
+            data: Vec::new(),
+            frame: 0,
+        }
+    }
+
+    /// Adds a column with the current frequency data of the `analyser`, if it calculated a
+    /// new FFT since the last column was added
+    ///
+    /// Returns `true` if a column was added. When the FFT size of the analyser changed, the
+    /// stored columns are dropped as they no longer have the same number of bins.
+    pub fn update(&mut self, analyser: &AnalyserNode) -> bool {
+        let frame = analyser.frames.load(Ordering::SeqCst);
+        if frame == self.frame {
+            return false;
+        }
+        self.frame = frame;
+
+        let column = analyser.get_float_frequency_data(vec![0.; analyser.frequency_bin_count()]);
+        if column.len() != self.frequency_bin_count {
+            self.frequency_bin_count = column.len();
+            self.data.clear();
+        }
+
+        if self.columns() == self.history {
+            self.data.drain(..self.frequency_bin_count);
+        }
+        self.data.extend_from_slice(&column);
+
+        true
+    }
+
+    /// Maximum number of columns kept
+    #[must_use]
+    pub fn history(&self) -> usize {
+        self.history
+    }
+
+    /// Update the maximum number of columns kept
+    ///
+    /// The most recent columns are preserved, the oldest ones are dropped if there are more
+    /// than `history`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `history` is zero
+    pub fn set_history(&mut self, history: usize) {
+        assert_valid_history(history);
+
+        let columns = self.columns();
+        if columns > history {
+            self.data
+                .drain(..(columns - history) * self.frequency_bin_count);
+        }
+        self.history = history;
+    }
+
+    /// Number of frequency bins of each column
+    #[must_use]
+    pub fn frequency_bin_count(&self) -> usize {
+        self.frequency_bin_count
+    }
+
+    /// Number of columns currently stored, at most [`history`](Self::history)
+    #[must_use]
+    pub fn columns(&self) -> usize {
+        self.data
+            .len()
+            .checked_div(self.frequency_bin_count)
+            .unwrap_or(0)
+    }
+
+    /// The frequency data (in dB) of a single column, `0` being the oldest one
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `index` is not smaller than [`columns`](Self::columns)
+    #[must_use]
+    pub fn column(&self, index: usize) -> &[f32] {
+        assert!(
+            index < self.columns(),
+            "IndexSizeError - column index {:?} is out of range for {:?} columns",
+            index,
+            self.columns()
+        );
+        let start = index * self.frequency_bin_count;
+        &self.data[start..start + self.frequency_bin_count]
+    }
+
+    /// All columns, from oldest to newest, each with [`frequency_bin_count`](Self::frequency_bin_count)
+    /// values (in dB)
+    #[must_use]
+    pub fn data(&self) -> &[f32] {
+        &self.data
+    }
+}
+
+/// Assert that the spectrogram history holds at least one column
+///
+/// # Panics
+///
+/// This function panics if the history is zero
+#[track_caller]
+fn assert_valid_history(history: usize) {
+    assert!(
+        history > 0,
+        "RangeError - spectrogram history ({:?}) should be at least 1",
+        history
+    );
+}
+
+/// Assert that the smoothing time constant is in the range `[0, 1]`
+///
+/// # Panics
+///
+/// This function panics if the value is out of range, or NaN
+#[track_caller]
+fn assert_valid_smoothing_time_constant(value: f64) {
+    assert!(
+        (0. ..=1.).contains(&value),
+        "IndexSizeError - smoothing time constant ({:?}) should be in the range [0, 1]",
+        value
+    );
 }
 
 /// Provides real-time frequency and time-domain analysis information
@@ -55,7 +374,11 @@ pub struct AnalyserNode {
     registration: AudioContextRegistration,
     channel_config: ChannelConfig,
     fft_size: Arc<AtomicUsize>,
-    smoothing_time_constant: Arc<AtomicU32>,
+    smoothing_time_constant: Arc<AtomicF64>,
+    channel: Arc<AtomicUsize>,
+    frequency_weighting: Arc<AtomicUsize>,
+    /// number of frequency frames calculated so far
+    frames: Arc<AtomicUsize>,
     sender: Sender<AnalyserRequest>,
 }
 
@@ -78,19 +401,39 @@ impl AudioNode for AnalyserNode {
 }
 
 impl AnalyserNode {
+    /// Returns an `AnalyserNode`
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the smoothing time constant is not in the range `[0, 1]`
     pub fn new<C: BaseAudioContext>(context: &C, options: AnalyserOptions) -> Self {
         context.register(move |registration| {
             let fft_size = Arc::new(AtomicUsize::new(options.fft_size));
-            let smoothing_time_constant = Arc::new(AtomicU32::new(
-                (options.smoothing_time_constant * 100.) as u32,
-            ));
+            assert_valid_smoothing_time_constant(options.smoothing_time_constant);
+            let smoothing_time_constant = Arc::new(AtomicF64::new(options.smoothing_time_constant));
+
+            let channel = usize::from(options.channel);
+            let current_channel = channel;
+            let channel = Arc::new(AtomicUsize::new(channel));
+            let frequency_weighting =
+                Arc::new(AtomicUsize::new(options.frequency_weighting as usize));
+            let frames = Arc::new(AtomicUsize::new(0));
 
             let (sender, receiver) = crossbeam_channel::bounded(0);
 
             let render = AnalyserRenderer {
-                analyser: Analyser::new(options.fft_size),
+                analyser: Analyser::new(options.fft_size, context.render_quantum_size()),
+                time_channels: MultiChannelTimeAnalyser::new(context.render_quantum_size()),
                 fft_size: fft_size.clone(),
                 smoothing_time_constant: smoothing_time_constant.clone(),
+                channel: channel.clone(),
+                current_channel,
+                frequency_weighting: frequency_weighting.clone(),
+                // reserve enough space for all FFT sizes
+                weights: Vec::with_capacity(MAX_SAMPLES / 2 + 1),
+                current_weights: None,
+                sample_rate: context.sample_rate(),
+                frames: frames.clone(),
                 receiver,
             };
 
@@ -99,6 +442,9 @@ impl AnalyserNode {
                 channel_config: options.channel_config.into(),
                 fft_size,
                 smoothing_time_constant,
+                channel,
+                frequency_weighting,
+                frames,
                 sender,
             };
 
@@ -124,14 +470,53 @@ impl AnalyserNode {
 
     /// Time averaging parameter with the last analysis frame.
     pub fn smoothing_time_constant(&self) -> f64 {
-        self.smoothing_time_constant.load(Ordering::SeqCst) as f64 / 100.
+        self.smoothing_time_constant.load()
     }
 
-    /// Set smoothing time constant, this MUST be a value between 0 and 1
+    /// Set the time averaging parameter with the last analysis frame
+    ///
+    /// Each frequency bin is the weighted average of its previous value (with this weight) and
+    /// the magnitude of the current analysis frame. A value of `0` disables the smoothing, a
+    /// value of `1` freezes the frequency data. The default is `0.8`.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the value is not in the range `[0, 1]`
     pub fn set_smoothing_time_constant(&self, value: f64) {
-        // todo assert range
-        self.smoothing_time_constant
-            .store((value * 100.) as u32, Ordering::SeqCst);
+        assert_valid_smoothing_time_constant(value);
+        self.smoothing_time_constant.store(value);
+    }
+
+    /// The input channel that is analysed
+    pub fn channel(&self) -> AnalyserChannel {
+        self.channel.load(Ordering::SeqCst).into()
+    }
+
+    /// Select the input channel to analyse, the down-mix of all channels by default
+    ///
+    /// When the selection changes, the time domain and frequency data are reset, so the
+    /// analysis never mixes data from different channels.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    pub fn set_channel(&self, channel: AnalyserChannel) {
+        self.channel.store(channel.into(), Ordering::SeqCst);
+    }
+
+    /// The weighting curve applied to the frequency data
+    pub fn frequency_weighting(&self) -> FrequencyWeighting {
+        self.frequency_weighting.load(Ordering::SeqCst).into()
+    }
+
+    /// Select the weighting curve applied to the frequency data, none by default
+    ///
+    /// The gain of the curve at the frequency of each bin is added to the normalized dB
+    /// values of [`get_float_frequency_data`](Self::get_float_frequency_data) and of the
+    /// [`snapshot`](Self::snapshot). The time domain data is not affected.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    pub fn set_frequency_weighting(&self, weighting: FrequencyWeighting) {
+        self.frequency_weighting
+            .store(weighting as usize, Ordering::SeqCst);
     }
 
     /// Copies the current time domain data (waveform data) into the provided buffer
@@ -144,6 +529,82 @@ impl AnalyserNode {
         receiver.recv().unwrap()
     }
 
+    /// Copies the `out.len()` most recent input samples into `out`, regardless of the FFT size
+    ///
+    /// Contrary to [`get_float_time_domain_data`](Self::get_float_time_domain_data), the length
+    /// does not need to be related to the FFT size nor to the render quantum size, e.g. to
+    /// display an oscilloscope of exactly 1000 samples. The last value of `out` is the most
+    /// recent sample. At most 32768 samples are kept, the start of longer buffers is filled
+    /// with silence.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    // we can fix this panic cf issue #101
+    #[allow(clippy::missing_panics_doc)]
+    pub fn get_time_domain(&self, out: &mut [f32]) {
+        let (sender, receiver) = crossbeam_channel::bounded(0);
+        let buffer = vec![0.; out.len()];
+        let request = AnalyserRequest::TimeDomain { sender, buffer };
+        self.sender.send(request).unwrap();
+        out.copy_from_slice(&receiver.recv().unwrap());
+    }
+
+    /// Copies the `out.len() / channels` most recent input frames into `out`, interleaved
+    ///
+    /// Unlike the other time domain methods, which read the analysed [`channel`](Self::channel),
+    /// this reads every input channel, e.g. `[left, right, left, right, ...]` for a stereo
+    /// input with `channels = 2`. The last frame of `out` is the most recent one. The channels
+    /// beyond those of the input are filled with silence, and so is the start of `out` when it
+    /// is longer than the 32768 frames which are kept.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `channels` is zero, or if the length of `out` is not a multiple of
+    /// `channels`
+    pub fn get_float_time_interleaved(&self, out: &mut [f32], channels: usize) {
+        if channels == 0 || !out.len().is_multiple_of(channels) {
+            panic!(
+                "IndexSizeError - buffer length {:?} is not a multiple of the number of channels {:?}",
+                out.len(),
+                channels
+            );
+        }
+
+        let (sender, receiver) = crossbeam_channel::bounded(0);
+        let request = AnalyserRequest::InterleavedTime {
+            sender,
+            buffer: vec![0.; out.len()],
+            number_of_channels: channels,
+        };
+        self.sender.send(request).unwrap();
+        out.copy_from_slice(&receiver.recv().unwrap());
+    }
+
+    /// Captures the current time domain and frequency data at once
+    ///
+    /// Two separate calls to [`get_float_time_domain_data`](Self::get_float_time_domain_data)
+    /// and [`get_float_frequency_data`](Self::get_float_frequency_data) can be served by
+    /// different render quanta. The snapshot is taken in a single render quantum, its time
+    /// domain data is the window its frequency data was calculated from.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    // we can fix this panic cf issue #101
+    #[allow(clippy::missing_panics_doc)]
+    pub fn snapshot(&self) -> AnalyserSnapshot {
+        // preallocate for the current fft size, the render thread only resizes if it changed
+        let fft_size = self.fft_size();
+        let snapshot = AnalyserSnapshot {
+            time: Vec::with_capacity(fft_size),
+            frequency: Vec::with_capacity(fft_size / 2),
+        };
+
+        let (sender, receiver) = crossbeam_channel::bounded(0);
+        let request = AnalyserRequest::Snapshot { sender, snapshot };
+        self.sender.send(request).unwrap();
+        receiver.recv().unwrap()
+    }
+
     /// Copies the current frequency data into the provided buffer
     // we can fix this panic cf issue #101
     #[allow(clippy::missing_panics_doc)]
@@ -157,8 +618,20 @@ impl AnalyserNode {
 
 struct AnalyserRenderer {
     pub analyser: Analyser,
+    /// time domain data of all input channels
+    pub time_channels: MultiChannelTimeAnalyser,
     pub fft_size: Arc<AtomicUsize>,
-    pub smoothing_time_constant: Arc<AtomicU32>,
+    pub smoothing_time_constant: Arc<AtomicF64>,
+    pub channel: Arc<AtomicUsize>,
+    /// channel analysed in the previous render quantum
+    pub current_channel: usize,
+    pub frequency_weighting: Arc<AtomicUsize>,
+    /// gain in dB of the weighting curve for each frequency bin
+    pub weights: Vec<f32>,
+    /// weighting and FFT size of the current `weights`
+    pub current_weights: Option<(FrequencyWeighting, usize)>,
+    pub sample_rate: f32,
+    pub frames: Arc<AtomicUsize>,
     pub receiver: Receiver<AnalyserRequest>,
 }
 
@@ -167,6 +640,35 @@ struct AnalyserRenderer {
 #[allow(clippy::non_send_fields_in_send_ty)]
 unsafe impl Send for AnalyserRenderer {}
 
+impl AnalyserRenderer {
+    /// Copy the frequency data, weighted with the selected curve
+    fn get_float_frequency(&mut self, buffer: &mut [f32]) {
+        self.analyser.get_float_frequency(buffer);
+
+        let weighting = FrequencyWeighting::from(self.frequency_weighting.load(Ordering::SeqCst));
+        if weighting == FrequencyWeighting::None {
+            return;
+        }
+
+        // precompute the weights when the weighting or the FFT size changes
+        let fft_size = self.analyser.current_fft_size();
+        if self.current_weights != Some((weighting, fft_size)) {
+            let bin_width = f64::from(self.sample_rate) / fft_size as f64;
+            self.weights.clear();
+            (0..=fft_size / 2)
+                .map(|i| weighting_db(weighting, i as f64 * bin_width) as f32)
+                .for_each(|w| self.weights.push(w));
+            self.current_weights = Some((weighting, fft_size));
+        }
+
+        // the weighting applies to the normalized dB values
+        buffer
+            .iter_mut()
+            .zip(self.weights.iter())
+            .for_each(|(b, w)| *b += w);
+    }
+}
+
 impl AudioProcessor for AnalyserRenderer {
     fn process(
         &mut self,
@@ -182,21 +684,36 @@ impl AudioProcessor for AnalyserRenderer {
         // pass through input
         *output = input.clone();
 
+        // start over when another channel is selected
+        let channel = self.channel.load(Ordering::SeqCst);
+        if channel != self.current_channel {
+            self.analyser.reset();
+            self.current_channel = channel;
+        }
+
         // add current input to ring buffer
-        let mut mono = input.clone();
-        mono.mix(1, ChannelInterpretation::Speakers);
-        let mono_data = mono.channel_data(0).clone();
-        self.analyser.add_data(mono_data);
+        let data = match AnalyserChannel::from(channel) {
+            AnalyserChannel::Channel(i) if i < input.number_of_channels() => {
+                input.channel_data(i).clone()
+            }
+            _ => {
+                let mut mono = input.clone();
+                mono.mix(1, ChannelInterpretation::Speakers);
+                mono.channel_data(0).clone()
+            }
+        };
+        self.analyser.add_data(data);
+        self.time_channels.add_data(input);
 
         // calculate frequency domain every `fft_size` samples
         let fft_size = self.fft_size.load(Ordering::Relaxed);
         let resized = self.analyser.current_fft_size() != fft_size;
         let complete_cycle = self.analyser.check_complete_cycle(fft_size);
         if resized || complete_cycle {
-            let smoothing_time_constant =
-                self.smoothing_time_constant.load(Ordering::Relaxed) as f32 / 100.;
+            let smoothing_time_constant = self.smoothing_time_constant.load() as f32;
             self.analyser
                 .calculate_float_frequency(fft_size, smoothing_time_constant);
+            self.frames.fetch_add(1, Ordering::SeqCst);
         }
 
         // check if any information was requested from the control thread
@@ -209,14 +726,537 @@ impl AudioProcessor for AnalyserRenderer {
                     let _ = sender.send(buffer);
                 }
                 AnalyserRequest::FloatFrequency { sender, mut buffer } => {
-                    self.analyser.get_float_frequency(&mut buffer[..]);
+                    self.get_float_frequency(&mut buffer[..]);
 
                     // allow to fail when receiver is disconnected
                     let _ = sender.send(buffer);
                 }
+                AnalyserRequest::TimeDomain { sender, mut buffer } => {
+                    self.analyser.get_time_domain(&mut buffer[..]);
+
+                    // allow to fail when receiver is disconnected
+                    let _ = sender.send(buffer);
+                }
+                AnalyserRequest::InterleavedTime {
+                    sender,
+                    mut buffer,
+                    number_of_channels,
+                } => {
+                    self.time_channels
+                        .get_interleaved(&mut buffer[..], number_of_channels);
+
+                    // allow to fail when receiver is disconnected
+                    let _ = sender.send(buffer);
+                }
+                AnalyserRequest::Snapshot {
+                    sender,
+                    mut snapshot,
+                } => {
+                    let fft_size = self.analyser.current_fft_size();
+
+                    snapshot.time.resize(fft_size, 0.);
+                    self.analyser.get_analysed_float_time(&mut snapshot.time);
+                    snapshot.frequency.resize(fft_size / 2, 0.);
+                    self.get_float_frequency(&mut snapshot.frequency);
+
+                    // allow to fail when receiver is disconnected
+                    let _ = sender.send(snapshot);
+                }
             }
         }
 
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::analysis::generate_blackman;
+    use crate::context::{
+        AudioContext, AudioContextOptions, BaseAudioContext, OfflineAudioContext,
+    };
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    use easyfft::prelude::*;
+
+    #[test]
+    fn test_smoothing_time_constant() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let analyser = context.create_analyser();
+        assert_float_eq!(analyser.smoothing_time_constant(), 0.8, abs <= 0.);
+
+        for value in [0., 0.35, 1.] {
+            analyser.set_smoothing_time_constant(value);
+            assert_float_eq!(analyser.smoothing_time_constant(), value, abs <= 0.);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_smoothing_time_constant_above_one() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        context.create_analyser().set_smoothing_time_constant(1.01);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_smoothing_time_constant_negative() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        context.create_analyser().set_smoothing_time_constant(-0.1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_smoothing_time_constant_nan() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        context
+            .create_analyser()
+            .set_smoothing_time_constant(f64::NAN);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_smoothing_time_constant_option() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let options = AnalyserOptions {
+            smoothing_time_constant: 2.,
+            ..AnalyserOptions::default()
+        };
+        let _ = AnalyserNode::new(&context, options);
+    }
+
+    #[test]
+    fn test_channel_selection() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        // 0.5 on the left, -0.25 on the right
+        let merger = context.create_channel_merger(2);
+        let left = context.create_constant_source();
+        left.offset().set_value(0.5);
+        left.connect_at(&merger, 0, 0);
+        left.start();
+        let right = context.create_constant_source();
+        right.offset().set_value(-0.25);
+        right.connect_at(&merger, 0, 1);
+        right.start();
+
+        let analyser = |channel| {
+            let options = AnalyserOptions {
+                fft_size: 256,
+                smoothing_time_constant: 0.,
+                channel,
+                ..AnalyserOptions::default()
+            };
+            let analyser = AnalyserNode::new(&context, options);
+            assert_eq!(analyser.channel(), channel);
+            merger.connect(&analyser);
+            analyser.connect(&context.destination());
+            analyser
+        };
+        let left = analyser(AnalyserChannel::Channel(0));
+        let right = analyser(AnalyserChannel::Channel(1));
+        let downmix = analyser(AnalyserChannel::Downmix);
+        let fallback = analyser(AnalyserChannel::Channel(2));
+
+        // wait for a full window of audio to be rendered
+        let wait_for_window = |analyser: &AnalyserNode| {
+            let start = std::time::Instant::now();
+            loop {
+                let time = analyser.get_float_time_domain_data(vec![0.; 256]);
+                if !time.contains(&0.) {
+                    return time;
+                }
+                assert!(start.elapsed().as_secs() < 5, "no audio rendered");
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+        };
+
+        assert_float_eq!(&wait_for_window(&left)[..], &[0.5; 256][..], abs_all <= 0.);
+        assert_float_eq!(
+            &wait_for_window(&right)[..],
+            &[-0.25; 256][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            &wait_for_window(&downmix)[..],
+            &[0.125; 256][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            &wait_for_window(&fallback)[..],
+            &[0.125; 256][..],
+            abs_all <= 0.
+        );
+
+        // the DC level of each analysis differs accordingly, once calculated from a full window
+        std::thread::sleep(std::time::Duration::from_millis(30));
+        let dc = |analyser: &AnalyserNode| analyser.get_float_frequency_data(vec![0.; 128])[0];
+        assert_float_eq!(dc(&left) - dc(&right), 20. * 2_f32.log10(), abs <= 0.5);
+
+        // switching the channel drops the data of the previous one
+        left.set_channel(AnalyserChannel::Channel(1));
+        assert_eq!(left.channel(), AnalyserChannel::Channel(1));
+        let time = left.get_float_time_domain_data(vec![0.; 256]);
+        assert!(time.iter().all(|&v| v == 0. || v == -0.25));
+        assert_float_eq!(
+            &wait_for_window(&left)[..],
+            &[-0.25; 256][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_weighting_curves() {
+        // reference values of IEC 61672-1, given at the nominal third octave frequencies
+        let cases = [
+            (FrequencyWeighting::A, 31.5, -39.4),
+            (FrequencyWeighting::A, 100., -19.1),
+            (FrequencyWeighting::A, 1000., 0.),
+            (FrequencyWeighting::A, 8000., -1.1),
+            (FrequencyWeighting::C, 31.5, -3.),
+            (FrequencyWeighting::C, 100., -0.3),
+            (FrequencyWeighting::C, 1000., 0.),
+            (FrequencyWeighting::C, 8000., -3.),
+            (FrequencyWeighting::None, 100., 0.),
+        ];
+        for (weighting, frequency, expected) in cases {
+            assert_float_eq!(weighting_db(weighting, frequency), expected, abs <= 0.2);
+        }
+    }
+
+    #[test]
+    fn test_frequency_weighting() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+        let sample_rate = context.sample_rate();
+
+        // equal energy tones at 100Hz and 1kHz
+        let gain = context.create_gain();
+        gain.gain().set_value(0.5);
+        for frequency in [100., 1000.] {
+            let osc = context.create_oscillator();
+            osc.frequency().set_value(frequency);
+            osc.connect(&gain);
+            osc.start();
+        }
+
+        let fft_size = 8192;
+        let analyser = |frequency_weighting| {
+            let options = AnalyserOptions {
+                fft_size,
+                smoothing_time_constant: 0.,
+                frequency_weighting,
+                ..AnalyserOptions::default()
+            };
+            let analyser = AnalyserNode::new(&context, options);
+            assert_eq!(analyser.frequency_weighting(), frequency_weighting);
+            gain.connect(&analyser);
+            analyser.connect(&context.destination());
+            analyser
+        };
+        let unweighted = analyser(FrequencyWeighting::None);
+        let weighted = analyser(FrequencyWeighting::None);
+        weighted.set_frequency_weighting(FrequencyWeighting::A);
+        assert_eq!(weighted.frequency_weighting(), FrequencyWeighting::A);
+
+        // wait for a full window of audio to be rendered
+        let start = std::time::Instant::now();
+        while weighted.snapshot().time_domain().contains(&0.) {
+            assert!(start.elapsed().as_secs() < 5, "no audio rendered");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        // level of the peak bin around the frequency
+        let level = |analyser: &AnalyserNode, frequency: f32| {
+            let bins = analyser.get_float_frequency_data(vec![0.; fft_size / 2]);
+            let bin = (frequency * fft_size as f32 / sample_rate).round() as usize;
+            bins[bin - 2..=bin + 2]
+                .iter()
+                .copied()
+                .fold(f32::NEG_INFINITY, f32::max)
+        };
+
+        // the unweighted readings are equal
+        let low = level(&unweighted, 100.);
+        let high = level(&unweighted, 1000.);
+        assert_float_eq!(low, high, abs <= 0.5);
+
+        // the 100Hz reading is attenuated by 19.1dB, the 1kHz reading is unaltered
+        let attenuation = low - level(&weighted, 100.);
+        assert_float_eq!(attenuation, 19.1, abs <= 0.5);
+        assert_float_eq!(level(&weighted, 1000.), high, abs <= 0.5);
+    }
+
+    #[test]
+    fn test_snapshot_time_and_frequency_match() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        let options = AnalyserOptions {
+            fft_size: 1024,
+            smoothing_time_constant: 0.,
+            ..AnalyserOptions::default()
+        };
+        let analyser = AnalyserNode::new(&context, options);
+        analyser.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(1000.);
+        // change the input over time, so successive windows differ
+        osc.frequency().linear_ramp_to_value_at_time(10_000., 1.);
+        osc.connect(&analyser);
+        osc.start();
+
+        // wait for a full window of audio to be rendered
+        let start = std::time::Instant::now();
+        while analyser.snapshot().time_domain().contains(&0.) {
+            assert!(start.elapsed().as_secs() < 5, "no audio rendered");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        for _ in 0..3 {
+            let snapshot = analyser.snapshot();
+            assert_eq!(snapshot.fft_size(), 1024);
+            assert_eq!(snapshot.frequency_bin_count(), 512);
+            assert!(snapshot.time_domain().iter().any(|&v| v != 0.));
+
+            // recompute the frequency data from the time domain data of the snapshot
+            let windowed: Vec<f32> = snapshot
+                .time_domain()
+                .iter()
+                .zip(generate_blackman(1024))
+                .map(|(s, w)| s * w)
+                .collect();
+            let norm = 20. * 1024_f32.sqrt().log10();
+            let expected: Vec<f32> = windowed[..]
+                .real_fft()
+                .iter()
+                .take(512)
+                .enumerate()
+                // the DC bin is halved
+                .map(|(i, c)| {
+                    let scale = if i == 0 { 0.5 } else { 1. };
+                    20. * (scale * c.norm()).log10() - norm
+                })
+                .collect();
+
+            let mut frequency = vec![0.; 512];
+            snapshot.get_float_frequency(&mut frequency);
+            frequency
+                .iter()
+                .zip(expected.iter())
+                .filter(|(_, &e)| e > -100.)
+                .for_each(|(&f, &e)| assert_float_eq!(f, e, abs <= 1e-2));
+
+            let mut time = vec![0.; 1024];
+            snapshot.get_float_time(&mut time);
+            assert_float_eq!(&time[..], snapshot.time_domain(), abs_all <= 0.);
+
+            std::thread::sleep(std::time::Duration::from_millis(30));
+        }
+    }
+
+    #[test]
+    fn test_render_quantum_sizes() {
+        let dc_level = |render_quantum_size| {
+            let options = AudioContextOptions {
+                sink_id: "none".into(),
+                render_quantum_size,
+                ..AudioContextOptions::default()
+            };
+            let context = AudioContext::new(options);
+            assert_eq!(context.render_quantum_size(), render_quantum_size);
+
+            let options = AnalyserOptions {
+                fft_size: 1024,
+                smoothing_time_constant: 0.,
+                ..AnalyserOptions::default()
+            };
+            let analyser = AnalyserNode::new(&context, options);
+            analyser.connect(&context.destination());
+
+            let src = context.create_constant_source();
+            src.offset().set_value(0.5);
+            src.connect(&analyser);
+            src.start();
+
+            // wait for a full window of audio to be rendered
+            let start = std::time::Instant::now();
+            loop {
+                let time = analyser.get_float_time_domain_data(vec![0.; 1024]);
+                if !time.contains(&0.) {
+                    assert_float_eq!(&time[..], &[0.5; 1024][..], abs_all <= 0.);
+                    break;
+                }
+                assert!(start.elapsed().as_secs() < 5, "no audio rendered");
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+
+            // the frequency data is calculated once a full window has been rendered
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            analyser.get_float_frequency_data(vec![0.; 512])[0]
+        };
+
+        let expected = dc_level(RENDER_QUANTUM_SIZE);
+        assert!(expected > -20.);
+        assert_float_eq!(dc_level(64), expected, abs <= 1e-3);
+        assert_float_eq!(dc_level(256), expected, abs <= 1e-3);
+    }
+
+    #[test]
+    fn test_float_time_interleaved() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        // 0.5 on the left, -0.25 on the right
+        let merger = context.create_channel_merger(2);
+        let left = context.create_constant_source();
+        left.offset().set_value(0.5);
+        left.connect_at(&merger, 0, 0);
+        left.start();
+        let right = context.create_constant_source();
+        right.offset().set_value(-0.25);
+        right.connect_at(&merger, 0, 1);
+        right.start();
+
+        let analyser = context.create_analyser();
+        merger.connect(&analyser);
+        analyser.connect(&context.destination());
+
+        // wait for enough frames to be rendered, the extra channel is silent
+        let mut interleaved = vec![0.; 3 * 256];
+        let start = std::time::Instant::now();
+        loop {
+            analyser.get_float_time_interleaved(&mut interleaved, 3);
+            if interleaved[0] != 0. {
+                break;
+            }
+            assert!(start.elapsed().as_secs() < 5, "no audio rendered");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        interleaved.chunks(3).for_each(|frame| {
+            assert_float_eq!(frame, &[0.5, -0.25, 0.][..], abs_all <= 0.);
+        });
+
+        // the analysed channel is not affected
+        let time = analyser.get_float_time_domain_data(vec![0.; 256]);
+        assert_float_eq!(&time[..], &[0.125; 256][..], abs_all <= 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_float_time_interleaved_invalid_length() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let analyser = context.create_analyser();
+        let mut interleaved = vec![0.; 255];
+        analyser.get_float_time_interleaved(&mut interleaved, 2);
+    }
+
+    #[test]
+    fn test_spectrogram_sweep() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        let options = AnalyserOptions {
+            fft_size: 1024,
+            smoothing_time_constant: 0.,
+            ..AnalyserOptions::default()
+        };
+        let analyser = AnalyserNode::new(&context, options);
+        analyser.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(500.);
+        osc.frequency()
+            .exponential_ramp_to_value_at_time(16_000., context.current_time() + 1.);
+        osc.connect(&analyser);
+        osc.start();
+
+        let mut spectrogram = SpectrogramBuffer::new(64);
+        let start = std::time::Instant::now();
+        while start.elapsed().as_millis() < 1200 {
+            spectrogram.update(&analyser);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        // the peak bin moves up across the columns
+        let peaks: Vec<usize> = (0..spectrogram.columns())
+            .map(|i| {
+                let column = spectrogram.column(i);
+                (0..column.len())
+                    .max_by(|&a, &b| column[a].total_cmp(&column[b]))
+                    .unwrap()
+            })
+            .filter(|&peak| peak > 0)
+            .collect();
+        assert!(peaks.len() > 10, "too few columns: {:?}", peaks);
+        assert!(peaks.windows(2).all(|w| w[0] <= w[1]), "{:?}", peaks);
+        assert!(peaks[peaks.len() - 1] > 10 * peaks[0], "{:?}", peaks);
+
+        assert_eq!(spectrogram.frequency_bin_count(), 512);
+        assert_eq!(spectrogram.data().len(), spectrogram.columns() * 512);
+    }
+
+    #[test]
+    fn test_spectrogram_history() {
+        let mut spectrogram = SpectrogramBuffer::new(4);
+        spectrogram.frequency_bin_count = 2;
+        spectrogram.data = vec![0., 0., 1., 1., 2., 2., 3., 3.];
+        assert_eq!(spectrogram.columns(), 4);
+
+        // growing keeps all columns
+        spectrogram.set_history(6);
+        assert_eq!(spectrogram.history(), 6);
+        assert_eq!(spectrogram.columns(), 4);
+
+        // shrinking keeps the most recent columns
+        spectrogram.set_history(3);
+        assert_eq!(spectrogram.history(), 3);
+        assert_eq!(spectrogram.columns(), 3);
+        assert_float_eq!(
+            spectrogram.data(),
+            &[1., 1., 2., 2., 3., 3.][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(spectrogram.column(0), &[1., 1.][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_spectrogram_no_duplicate_columns() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let analyser = context.create_analyser();
+
+        // no FFT was calculated, nothing is added
+        let mut spectrogram = SpectrogramBuffer::new(4);
+        assert!(!spectrogram.update(&analyser));
+        assert_eq!(spectrogram.columns(), 0);
+        assert!(spectrogram.data().is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_spectrogram_zero_history() {
+        let _ = SpectrogramBuffer::new(0);
+    }
+}
@@ -1,13 +1,14 @@
 use crossbeam_channel::{Receiver, Sender};
 use once_cell::sync::OnceCell;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::error::Error;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 
 use crate::buffer::AudioBuffer;
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
 use crate::control::Controller;
 use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
-use crate::RENDER_QUANTUM_SIZE;
 
 use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig};
 
@@ -43,12 +44,38 @@ impl Default for AudioBufferSourceOptions {
     }
 }
 
+/// Interpolation applied when the buffer is played at another rate than the audio context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InterpolationQuality {
+    /// Use the nearest sample, cheapest but introduces a lot of distortion
+    Nearest,
+    /// Linear interpolation between the two surrounding samples
+    #[default]
+    Linear,
+    /// Cubic (Catmull-Rom) interpolation on the four surrounding samples, reduces the
+    /// degradation of high frequencies at a higher computational cost
+    Cubic,
+}
+
+impl From<u32> for InterpolationQuality {
+    fn from(i: u32) -> Self {
+        match i {
+            0 => InterpolationQuality::Nearest,
+            1 => InterpolationQuality::Linear,
+            2 => InterpolationQuality::Cubic,
+            _ => unreachable!(),
+        }
+    }
+}
+
 struct AudioBufferMessage(AudioBuffer);
 
 #[derive(Copy, Clone)]
 struct PlaybackInfo {
     prev_frame_index: usize,
     k: f32,
+    /// Frame range of the loop, if the playhead is looping
+    loop_frames: Option<(usize, usize)>,
 }
 
 /// `AudioBufferSourceNode` represents an audio source that consists of an
@@ -91,7 +118,7 @@ pub struct AudioBufferSourceNode {
     detune: AudioParam,        // has constraints, no a-rate
     playback_rate: AudioParam, // has constraints, no a-rate
     buffer: OnceCell<AudioBuffer>,
-    source_started: AtomicBool,
+    interpolation_quality: Arc<AtomicU32>,
 }
 
 impl AudioNode for AudioBufferSourceNode {
@@ -113,26 +140,12 @@ impl AudioNode for AudioBufferSourceNode {
 }
 
 impl AudioScheduledSourceNode for AudioBufferSourceNode {
-    fn start(&self) {
-        let start = self.registration.context().current_time();
-        self.start_at_with_offset_and_duration(start, 0., f64::MAX);
+    fn try_start_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.try_start_at_with_offset_and_duration(when, 0., f64::MAX)
     }
 
-    fn start_at(&self, when: f64) {
-        self.start_at_with_offset_and_duration(when, 0., f64::MAX);
-    }
-
-    fn stop(&self) {
-        let stop = self.registration.context().current_time();
-        self.stop_at(stop);
-    }
-
-    fn stop_at(&self, when: f64) {
-        if !self.source_started.load(Ordering::SeqCst) {
-            panic!("InvalidStateError cannot stop before start");
-        }
-
-        self.controller.scheduler().stop_at(when);
+    fn try_stop_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.controller.scheduler().try_stop_at(when)
     }
 }
 
@@ -179,6 +192,8 @@ impl AudioBufferSourceNode {
             let (sender, receiver) = crossbeam_channel::bounded(1);
 
             let controller = Controller::new();
+            let interpolation_quality =
+                Arc::new(AtomicU32::new(InterpolationQuality::default() as u32));
 
             let renderer = AudioBufferSourceRenderer {
                 controller: controller.clone(),
@@ -186,8 +201,10 @@ impl AudioBufferSourceNode {
                 buffer: None,
                 detune: d_proc,
                 playback_rate: pr_proc,
+                interpolation_quality: interpolation_quality.clone(),
                 render_state: AudioBufferRendererState::default(),
                 ended_triggered: false,
+                playback_infos: vec![None; context.render_quantum_size()],
             };
 
             let node = Self {
@@ -198,7 +215,7 @@ impl AudioBufferSourceNode {
                 detune: d_param,
                 playback_rate: pr_param,
                 buffer: OnceCell::new(),
-                source_started: AtomicBool::new(false),
+                interpolation_quality,
             };
 
             node.controller.set_loop(loop_);
@@ -217,7 +234,7 @@ impl AudioBufferSourceNode {
     ///
     /// # Panics
     ///
-    /// Panics if the source was already started
+    /// Panics if the source was already started, or if `start` is negative
     pub fn start_at_with_offset(&self, start: f64, offset: f64) {
         self.start_at_with_offset_and_duration(start, offset, f64::MAX);
     }
@@ -226,15 +243,30 @@ impl AudioBufferSourceNode {
     ///
     /// # Panics
     ///
-    /// Panics if the source was already started
+    /// Panics if the source was already started, or if `start` is negative
     pub fn start_at_with_offset_and_duration(&self, start: f64, offset: f64, duration: f64) {
-        if self.source_started.swap(true, Ordering::SeqCst) {
-            panic!("InvalidStateError: Cannot call `start` twice");
-        }
+        self.try_start_at_with_offset_and_duration(start, offset, duration)
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
 
-        self.controller.set_offset(offset);
-        self.controller.set_duration(duration);
-        self.controller.scheduler().start_at(start);
+    /// Start the playback at the given time, with a given offset, for a given duration, failing
+    /// instead of panicking on invalid calls
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, and the source is not scheduled, when
+    /// - the source was already started (`InvalidStateError`)
+    /// - `start` is negative or not finite (`RangeError`)
+    pub fn try_start_at_with_offset_and_duration(
+        &self,
+        start: f64,
+        offset: f64,
+        duration: f64,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.controller.scheduler().try_start_at_with(start, || {
+            self.controller.set_offset(offset);
+            self.controller.set_duration(duration);
+        })
     }
 
     /// Current buffer value (nullable)
@@ -304,6 +336,19 @@ impl AudioBufferSourceNode {
     pub fn set_loop_end(&self, value: f64) {
         self.controller.set_loop_end(value);
     }
+
+    /// Interpolation used to compute the samples between the frames of the [`AudioBuffer`]
+    pub fn interpolation_quality(&self) -> InterpolationQuality {
+        self.interpolation_quality.load(Ordering::SeqCst).into()
+    }
+
+    /// Set the interpolation used to compute the samples between the frames of the
+    /// [`AudioBuffer`], i.e. when the playback rate or detune are changed, or when the sample
+    /// rate of the buffer differs from the one of the audio context
+    pub fn set_interpolation_quality(&self, value: InterpolationQuality) {
+        self.interpolation_quality
+            .store(value as u32, Ordering::SeqCst);
+    }
 }
 
 struct AudioBufferRendererState {
@@ -332,8 +377,11 @@ struct AudioBufferSourceRenderer {
     buffer: Option<AudioBuffer>,
     detune: AudioParamId,
     playback_rate: AudioParamId,
+    interpolation_quality: Arc<AtomicU32>,
     render_state: AudioBufferRendererState,
     ended_triggered: bool,
+    /// Position in the buffer of each sample of the render quantum
+    playback_infos: Vec<Option<PlaybackInfo>>,
 }
 
 impl AudioProcessor for AudioBufferSourceRenderer {
@@ -349,7 +397,7 @@ impl AudioProcessor for AudioBufferSourceRenderer {
 
         let sample_rate = scope.sample_rate as f64;
         let dt = 1. / sample_rate;
-        let block_duration = dt * RENDER_QUANTUM_SIZE as f64;
+        let block_duration = dt * scope.render_quantum_size as f64;
         let next_block_time = scope.current_time + block_duration;
 
         if let Ok(msg) = self.receiver.try_recv() {
@@ -461,7 +509,11 @@ impl AudioProcessor for AudioBufferSourceRenderer {
         }
 
         // by default loop_end is 0., see AudioBufferSourceOptions
-        if loop_start != 0. || loop_end != 0. || sampling_ratio != 1. {
+        if loop_start != 0.
+            || loop_end != 0.
+            || sampling_ratio != 1.
+            || computed_playback_rate != 1.
+        {
             self.render_state.is_aligned = false;
         }
 
@@ -529,14 +581,14 @@ impl AudioProcessor for AudioBufferSourceRenderer {
 
                 if let Some(loop_point_index) = loop_point_index {
                     self.render_state.buffer_time =
-                        ((RENDER_QUANTUM_SIZE - loop_point_index) as f64 / sample_rate)
+                        ((scope.render_quantum_size - loop_point_index) as f64 / sample_rate)
                             % buffer_duration;
                 } else {
                     self.render_state.buffer_time += block_duration;
                 }
             } else {
                 let start_index = (self.render_state.buffer_time * sample_rate).round() as usize;
-                let end_index = start_index + RENDER_QUANTUM_SIZE;
+                let end_index = start_index + scope.render_quantum_size;
                 // we can do memcopy
                 buffer
                     .channels()
@@ -573,7 +625,15 @@ impl AudioProcessor for AudioBufferSourceRenderer {
 
         // internal buffer used to store playback infos to compute the samples
         // according to the source buffer. (prev_sample_index, k)
-        let mut playback_infos = [None; RENDER_QUANTUM_SIZE];
+        let playback_infos = &mut self.playback_infos;
+
+        // loop boundaries in frames of the buffer, to pick the neighbour samples
+        // of the interpolation across the loop points
+        let buffer_sample_rate = buffer.sample_rate() as f64;
+        let loop_frames = (
+            (actual_loop_start * buffer_sample_rate) as usize,
+            ((actual_loop_end * buffer_sample_rate).ceil() as usize).min(buffer.length()),
+        );
 
         // compute position for each sample and store into `self.positions`
         for playback_info in playback_infos.iter_mut() {
@@ -644,6 +704,11 @@ impl AudioProcessor for AudioBufferSourceRenderer {
                 *playback_info = Some(PlaybackInfo {
                     prev_frame_index,
                     k,
+                    loop_frames: if self.render_state.entered_loop {
+                        Some(loop_frames)
+                    } else {
+                        None
+                    },
                 });
             } else {
                 *playback_info = None;
@@ -656,6 +721,8 @@ impl AudioProcessor for AudioBufferSourceRenderer {
         }
 
         // fill output according to computed positions
+        let interpolation_quality = self.interpolation_quality.load(Ordering::SeqCst).into();
+
         buffer
             .channels()
             .iter()
@@ -668,19 +735,7 @@ impl AudioProcessor for AudioBufferSourceRenderer {
                     .zip(output_channel.iter_mut())
                     .for_each(|(playhead, o)| {
                         *o = match playhead {
-                            Some(PlaybackInfo {
-                                prev_frame_index,
-                                k,
-                            }) => {
-                                // `prev_frame_index` cannot be out of bounds
-                                let prev_sample = buffer_channel[*prev_frame_index];
-                                let next_sample = match buffer_channel.get(prev_frame_index + 1) {
-                                    Some(val) => *val,
-                                    None => 0.,
-                                };
-
-                                (1. - k).mul_add(prev_sample, k * next_sample)
-                            }
+                            Some(info) => interpolate(buffer_channel, info, interpolation_quality),
                             None => 0.,
                         };
                     });
@@ -690,6 +745,61 @@ impl AudioProcessor for AudioBufferSourceRenderer {
     }
 }
 
+/// Compute the sample at the given playhead position
+fn interpolate(
+    buffer_channel: &[f32],
+    info: &PlaybackInfo,
+    interpolation_quality: InterpolationQuality,
+) -> f32 {
+    let PlaybackInfo {
+        prev_frame_index,
+        k,
+        loop_frames,
+    } = *info;
+
+    // frame at the given offset from `prev_frame_index`, wrapped around the loop
+    // points if the playhead is looping, silent outside the buffer
+    let frame = |offset: isize| {
+        let mut index = prev_frame_index as isize + offset;
+
+        if let Some((loop_start, loop_end)) = loop_frames {
+            let (loop_start, loop_end) = (loop_start as isize, loop_end as isize);
+            if index >= loop_end {
+                index -= loop_end - loop_start;
+            } else if index < loop_start {
+                index += loop_end - loop_start;
+            }
+        }
+
+        if index < 0 {
+            0.
+        } else {
+            buffer_channel.get(index as usize).copied().unwrap_or(0.)
+        }
+    };
+
+    match interpolation_quality {
+        InterpolationQuality::Nearest => {
+            if k < 0.5 {
+                frame(0)
+            } else {
+                frame(1)
+            }
+        }
+        InterpolationQuality::Linear => (1. - k).mul_add(frame(0), k * frame(1)),
+        InterpolationQuality::Cubic => {
+            let (p0, p1, p2, p3) = (frame(-1), frame(0), frame(1), frame(2));
+
+            // Catmull-Rom spline
+            let c1 = 0.5 * (p2 - p0);
+            let c2 = p0 - 2.5 * p1 + 2. * p2 - 0.5 * p3;
+            let c3 = 0.5 * (p3 - p0) + 1.5 * (p1 - p2);
+
+            ((c3 * k + c2) * k + c1).mul_add(k, p1)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use float_eq::assert_float_eq;
@@ -901,7 +1011,7 @@ mod tests {
     #[test]
     fn test_schedule_in_the_past() {
         let sample_rate = 48000.;
-        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate);
+        let mut context = OfflineAudioContext::new(1, 2 * RENDER_QUANTUM_SIZE, sample_rate);
 
         let mut dirac = context.create_buffer(1, 1, sample_rate);
         dirac.copy_to_channel(&[1.], 0);
@@ -909,13 +1019,15 @@ mod tests {
         let src = context.create_buffer_source();
         src.connect(&context.destination());
         src.set_buffer(dirac);
-        src.start_at(-1.);
+
+        // start at time 0 while rendering the second block
+        context.suspend_at(RENDER_QUANTUM_SIZE, move |_| src.start_at(0.));
 
         let result = context.start_rendering_sync();
         let channel = result.get_channel_data(0);
 
-        let mut expected = vec![0.; RENDER_QUANTUM_SIZE];
-        expected[0] = 1.;
+        let mut expected = vec![0.; 2 * RENDER_QUANTUM_SIZE];
+        expected[RENDER_QUANTUM_SIZE] = 1.;
 
         assert_float_eq!(channel[..], expected[..], abs_all <= 0.);
     }
@@ -962,6 +1074,40 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_audio_buffer_resampling_with_playback_rate() {
+        let base_sr = 44_100;
+        let buf_sr = 48_000;
+        let context = OfflineAudioContext::new(1, base_sr, base_sr as f32);
+
+        // 1 second of a 441Hz sine at 48kHz
+        let sine: Vec<f32> = (0..buf_sr)
+            .map(|i| (i as f32 / buf_sr as f32 * 441. * 2. * PI).sin())
+            .collect();
+        let mut buffer = context.create_buffer(1, buf_sr, buf_sr as f32);
+        buffer.copy_to_channel(&sine, 0);
+
+        let src = context.create_buffer_source();
+        src.connect(&context.destination());
+        src.set_buffer(buffer);
+        src.playback_rate().set_value(2.);
+        src.start();
+
+        let result = context.start_rendering_sync();
+        let channel = result.get_channel_data(0);
+
+        // played twice as fast: half a second of a 882Hz sine at the context rate
+        let half = base_sr / 2;
+        let expected: Vec<f32> = (0..half)
+            .map(|i| (i as f32 / base_sr as f32 * 882. * 2. * PI).sin())
+            .collect();
+        assert_float_eq!(channel[..half], expected[..], abs_all <= 1e-3);
+        // the buffer ends within the render quantum following the half second
+        assert!(channel[half + RENDER_QUANTUM_SIZE..]
+            .iter()
+            .all(|&v| v == 0.));
+    }
+
     #[test]
     fn test_end_of_file() {
         // fast track
@@ -1201,4 +1347,150 @@ mod tests {
             );
         }
     }
+
+    // render a sine buffer at the given playback rate, returns the relative rms error compared
+    // to the ideal resampled sine
+    fn resampling_error(quality: InterpolationQuality, loop_: bool) -> f32 {
+        let sample_rate = 44_100.;
+        let context = OfflineAudioContext::new(1, 4096, sample_rate);
+
+        // 5kHz sine, whole number of periods in the buffer so it can be looped seamlessly
+        let frequency = 5000.;
+        let length = if loop_ { 441 } else { 8192 };
+        let sine: Vec<f32> = (0..length)
+            .map(|i| (2. * PI * frequency * i as f32 / sample_rate).sin())
+            .collect();
+        let mut buffer = context.create_buffer(1, length, sample_rate);
+        buffer.copy_to_channel(&sine, 0);
+
+        let playback_rate = 1.5;
+        let src = context.create_buffer_source();
+        src.set_interpolation_quality(quality);
+        src.playback_rate().set_value(playback_rate);
+        src.set_loop(loop_);
+        src.set_buffer(buffer);
+        src.connect(&context.destination());
+        src.start();
+
+        let result = context.start_rendering_sync();
+        let channel = result.get_channel_data(0);
+
+        // skip the first frames, where the interpolation misses the neighbours before the
+        // start of the buffer
+        let (error, signal) =
+            channel
+                .iter()
+                .enumerate()
+                .skip(4)
+                .fold((0., 0.), |(error, signal), (i, v)| {
+                    let phase = 2. * PI * frequency * playback_rate * i as f32 / sample_rate;
+                    let expected = phase.sin();
+                    (error + (v - expected).powi(2), signal + expected.powi(2))
+                });
+
+        (error / signal).sqrt()
+    }
+
+    #[test]
+    fn test_interpolation_quality() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let src = context.create_buffer_source();
+        assert_eq!(src.interpolation_quality(), InterpolationQuality::Linear);
+        src.set_interpolation_quality(InterpolationQuality::Cubic);
+        assert_eq!(src.interpolation_quality(), InterpolationQuality::Cubic);
+
+        let nearest = resampling_error(InterpolationQuality::Nearest, false);
+        let linear = resampling_error(InterpolationQuality::Linear, false);
+        let cubic = resampling_error(InterpolationQuality::Cubic, false);
+
+        assert!(nearest > linear);
+        assert!(cubic < linear / 4.);
+    }
+
+    #[test]
+    fn test_cubic_interpolation_loop_wrap() {
+        // the neighbour frames are taken across the loop points
+        let linear = resampling_error(InterpolationQuality::Linear, true);
+        let cubic = resampling_error(InterpolationQuality::Cubic, true);
+        let cubic_no_loop = resampling_error(InterpolationQuality::Cubic, false);
+
+        assert!(cubic < linear / 4.);
+        assert_float_eq!(cubic, cubic_no_loop, rmax <= 0.1);
+    }
+
+    #[test]
+    fn test_playback_rate_on_aligned_start() {
+        // the buffer is aligned with the render quanta, but played at another rate
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+        let samples: Vec<f32> = (0..RENDER_QUANTUM_SIZE * 2).map(|i| i as f32).collect();
+        let mut buffer = context.create_buffer(1, samples.len(), 44_100.);
+        buffer.copy_to_channel(&samples, 0);
+
+        let src = context.create_buffer_source();
+        src.playback_rate().set_value(2.);
+        src.set_buffer(buffer);
+        src.connect(&context.destination());
+        src.start();
+
+        let result = context.start_rendering_sync();
+        let expected: Vec<f32> = (0..RENDER_QUANTUM_SIZE).map(|i| 2. * i as f32).collect();
+        assert_float_eq!(result.get_channel_data(0)[..], expected[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_linear_interpolation_loop_wrap() {
+        // power of two, so that the loop start is exactly on a frame
+        let sample_rate = 32_768.;
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate);
+
+        let mut buffer = context.create_buffer(1, 4, sample_rate);
+        buffer.copy_to_channel(&[0., 1., 2., 3.], 0);
+
+        let src = context.create_buffer_source();
+        src.set_interpolation_quality(InterpolationQuality::Linear);
+        src.playback_rate().set_value(0.5);
+        src.set_loop(true);
+        src.set_loop_start(1. / sample_rate as f64);
+        src.set_loop_end(4. / sample_rate as f64);
+        src.set_buffer(buffer);
+        src.connect(&context.destination());
+        src.start();
+
+        let result = context.start_rendering_sync();
+        let channel = result.get_channel_data(0);
+
+        // between the last frame and the loop end, the next frame is the loop start
+        let expected = [0., 0.5, 1., 1.5, 2., 2.5, 3., 2., 1., 1.5, 2., 2.5, 3., 2.];
+        assert_float_eq!(channel[..expected.len()], expected[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_interpolation_at_frames() {
+        // at whole frame positions all interpolations yield the buffer samples
+        [
+            InterpolationQuality::Nearest,
+            InterpolationQuality::Linear,
+            InterpolationQuality::Cubic,
+        ]
+        .iter()
+        .for_each(|quality| {
+            let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+            let samples: Vec<f32> = (0..RENDER_QUANTUM_SIZE * 2).map(|i| i as f32).collect();
+            let mut buffer = context.create_buffer(1, samples.len(), 44_100.);
+            buffer.copy_to_channel(&samples, 0);
+
+            let src = context.create_buffer_source();
+            src.set_interpolation_quality(*quality);
+            src.playback_rate().set_value(2.);
+            src.set_buffer(buffer);
+            src.connect(&context.destination());
+            src.start();
+
+            let result = context.start_rendering_sync();
+            let expected: Vec<f32> = samples.iter().step_by(2).copied().collect();
+            assert_float_eq!(result.get_channel_data(0), &expected[..], abs_all <= 0.);
+        });
+    }
 }
@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::context::{
+    AudioContextRegistration, AudioParamId, BaseAudioContext, PatchNodeType, Patchable,
+};
+use crate::param::{AudioParam, AudioParamDescriptor};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Declaration of an [`AudioParam`] of a [`Worklet`]
+#[derive(Clone, Debug)]
+pub struct WorkletParamDescriptor {
+    /// name of the parameter, to look it up in [`AudioWorkletNode::parameters`] and
+    /// [`WorkletParamValues::get`]
+    pub name: String,
+    /// range, default value and automation rate of the parameter
+    pub descriptor: AudioParamDescriptor,
+}
+
+/// User defined audio processing code, instantiated by name with
+/// [`BaseAudioContext::create_worklet`](crate::context::BaseAudioContext::create_worklet)
+///
+/// This is the counterpart of the `AudioWorkletProcessor` of the Web Audio API. The processor
+/// runs on the render thread, its `process` method follows the same contract as
+/// [`AudioProcessor::process`].
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::node::{AudioNode, AudioWorkletNodeOptions, Worklet, WorkletParamDescriptor, WorkletParamValues};
+/// use web_audio_api::render::{AudioRenderQuantum, RenderScope};
+/// use web_audio_api::{AudioParamDescriptor, AutomationRate};
+///
+/// struct Amplifier;
+///
+/// impl Worklet for Amplifier {
+///     fn parameter_descriptors() -> Vec<WorkletParamDescriptor> {
+///         vec![WorkletParamDescriptor {
+///             name: String::from("gain"),
+///             descriptor: AudioParamDescriptor {
+///                 min_value: 0.,
+///                 max_value: 10.,
+///                 default_value: 1.,
+///                 automation_rate: AutomationRate::K,
+///             },
+///         }]
+///     }
+///
+///     fn process(
+///         &mut self,
+///         inputs: &[AudioRenderQuantum],
+///         outputs: &mut [AudioRenderQuantum],
+///         params: WorkletParamValues,
+///         _scope: &RenderScope,
+///     ) -> bool {
+///         let gain = params.get("gain")[0];
+///         outputs[0] = inputs[0].clone();
+///         outputs[0]
+///             .channels_mut()
+///             .iter_mut()
+///             .for_each(|c| c.iter_mut().for_each(|s| *s *= gain));
+///         false
+///     }
+/// }
+///
+/// let context = AudioContext::default();
+/// context.register_worklet("amplifier", |_options| Amplifier);
+///
+/// let amplifier = context.create_worklet("amplifier", AudioWorkletNodeOptions::default());
+/// amplifier.parameters()["gain"].set_value(2.);
+/// amplifier.connect(&context.destination());
+/// ```
+pub trait Worklet: Send {
+    /// The `AudioParam`s of the worklet, they are created for each instance of the worklet
+    fn parameter_descriptors() -> Vec<WorkletParamDescriptor>
+    where
+        Self: Sized,
+    {
+        Vec::new()
+    }
+
+    /// Audio processing function
+    ///
+    /// See [`AudioProcessor::process`] for the meaning of the arguments and return value.
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: WorkletParamValues,
+        scope: &RenderScope,
+    ) -> bool;
+}
+
+type WorkletFactory = dyn Fn(&AudioWorkletNodeOptions) -> Box<dyn Worklet> + Send + Sync;
+
+/// Definition of a worklet registered on the context, to create instances by name
+#[derive(Clone)]
+pub(crate) struct WorkletDefinition {
+    parameter_descriptors: Vec<WorkletParamDescriptor>,
+    factory: Arc<WorkletFactory>,
+}
+
+impl WorkletDefinition {
+    pub(crate) fn new<W, F>(factory: F) -> Self
+    where
+        W: Worklet + 'static,
+        F: Fn(&AudioWorkletNodeOptions) -> W + Send + Sync + 'static,
+    {
+        Self {
+            parameter_descriptors: W::parameter_descriptors(),
+            factory: Arc::new(move |options| Box::new((factory)(options))),
+        }
+    }
+}
+
+/// Accessor for current [`AudioParam`] values of a [`Worklet`], by name
+///
+/// Provided to [`Worklet::process`] in the render thread
+pub struct WorkletParamValues<'a> {
+    values: AudioParamValues<'a>,
+    ids: &'a [(String, AudioParamId)],
+}
+
+impl WorkletParamValues<'_> {
+    /// Get the computed values for the parameter with the given name
+    ///
+    /// See [`AudioParamValues::get`] for the length of the returned slice.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if no parameter named `name` was declared in
+    /// [`Worklet::parameter_descriptors`]
+    pub fn get(&self, name: &str) -> impl Deref<Target = [f32]> + '_ {
+        let (_, id) = self
+            .ids
+            .iter()
+            .find(|(n, _)| n == name)
+            .unwrap_or_else(|| panic!("NotFoundError - no worklet parameter named {:?}", name));
+
+        self.values.get(id)
+    }
+}
+
+/// Options for constructing an [`AudioWorkletNode`]
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct AudioWorkletNodeOptions {
+    /// number of inputs of the node
+    pub number_of_inputs: usize,
+    /// number of outputs of the node
+    pub number_of_outputs: usize,
+    /// initial values of the parameters, by name
+    pub parameter_data: HashMap<String, f32>,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for AudioWorkletNodeOptions {
+    fn default() -> Self {
+        Self {
+            number_of_inputs: 1,
+            number_of_outputs: 1,
+            parameter_data: HashMap::new(),
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `AudioWorkletNode` runs a user defined [`Worklet`] in the audio graph
+///
+/// Worklets are registered by name with
+/// [`BaseAudioContext::register_worklet`](crate::context::BaseAudioContext::register_worklet),
+/// the parameters they declare are exposed as [`AudioParam`]s of the node.
+///
+/// - MDN documentation: <https://developer.mozilla.org/en-US/docs/Web/API/AudioWorkletNode>
+/// - specification: <https://webaudio.github.io/web-audio-api/#AudioWorkletNode>
+/// - see also: [`BaseAudioContext::create_worklet`](crate::context::BaseAudioContext::create_worklet)
+///
+/// # Usage
+///
+/// See the [`Worklet`] trait.
+pub struct AudioWorkletNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    number_of_inputs: usize,
+    number_of_outputs: usize,
+    parameters: HashMap<String, AudioParam>,
+}
+
+impl AudioNode for AudioWorkletNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        self.number_of_inputs
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        self.number_of_outputs
+    }
+}
+
+impl AudioWorkletNode {
+    /// Returns an `AudioWorkletNode` running the worklet registered as `name`
+    ///
+    /// # Arguments:
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `name` - name the worklet was registered with
+    /// * `options` - audio worklet node options
+    ///
+    /// # Panics
+    ///
+    /// Will panic if
+    /// - no worklet was registered with this name on the context
+    /// - both `options.number_of_inputs` and `options.number_of_outputs` are zero
+    pub fn new<C: BaseAudioContext>(
+        context: &C,
+        name: &str,
+        options: AudioWorkletNodeOptions,
+    ) -> Self {
+        let definition = context.base().worklet_definition(name).unwrap_or_else(|| {
+            panic!(
+                "InvalidStateError - no worklet registered with name {:?}",
+                name
+            )
+        });
+
+        if options.number_of_inputs == 0 && options.number_of_outputs == 0 {
+            panic!("NotSupportedError - AudioWorkletNode should have at least one input or output");
+        }
+
+        let patch_name = name.to_string();
+        let patch_options = options.clone();
+
+        let node = context.register(move |registration| {
+            let WorkletDefinition {
+                parameter_descriptors,
+                factory,
+            } = definition;
+
+            let mut parameters = HashMap::with_capacity(parameter_descriptors.len());
+            let mut ids = Vec::with_capacity(parameter_descriptors.len());
+
+            parameter_descriptors.into_iter().for_each(|param| {
+                let (audio_param, id) = context.create_audio_param(param.descriptor, &registration);
+                if let Some(&value) = options.parameter_data.get(&param.name) {
+                    audio_param.set_value(value);
+                }
+                parameters.insert(param.name.clone(), audio_param);
+                ids.push((param.name, id));
+            });
+
+            let renderer = AudioWorkletRenderer {
+                worklet: (factory)(&options),
+                ids,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: options.channel_config.into(),
+                number_of_inputs: options.number_of_inputs,
+                number_of_outputs: options.number_of_outputs,
+                parameters,
+            };
+
+            (node, Box::new(renderer))
+        });
+
+        let channel_config = node.channel_config.clone();
+        context
+            .base()
+            .record_patch_node(&node, move || PatchNodeType::AudioWorklet {
+                name: patch_name.clone(),
+                options: AudioWorkletNodeOptions {
+                    channel_config: (&channel_config).into(),
+                    ..patch_options.clone()
+                },
+            });
+
+        node
+    }
+
+    /// The `AudioParam`s declared by the worklet, by name
+    #[must_use]
+    pub fn parameters(&self) -> &HashMap<String, AudioParam> {
+        &self.parameters
+    }
+}
+
+impl Patchable for AudioWorkletNode {
+    fn patch_params(&self) -> Vec<&AudioParam> {
+        let mut names: Vec<_> = self.parameters.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| &self.parameters[name])
+            .collect()
+    }
+}
+
+struct AudioWorkletRenderer {
+    worklet: Box<dyn Worklet>,
+    ids: Vec<(String, AudioParamId)>,
+}
+
+impl AudioProcessor for AudioWorkletRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        let params = WorkletParamValues {
+            values: params,
+            ids: &self.ids,
+        };
+
+        self.worklet.process(inputs, outputs, params, scope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+    use crate::param::AutomationRate;
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    /// Doubles its input, scaled by the `gain` parameter
+    struct GainDoubler;
+
+    impl Worklet for GainDoubler {
+        fn parameter_descriptors() -> Vec<WorkletParamDescriptor> {
+            vec![WorkletParamDescriptor {
+                name: String::from("gain"),
+                descriptor: AudioParamDescriptor {
+                    min_value: 0.,
+                    max_value: 10.,
+                    default_value: 1.,
+                    automation_rate: AutomationRate::A,
+                },
+            }]
+        }
+
+        fn process(
+            &mut self,
+            inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            params: WorkletParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            let gain = params.get("gain");
+            outputs[0] = inputs[0].clone();
+            outputs[0].channels_mut().iter_mut().for_each(|channel| {
+                channel
+                    .iter_mut()
+                    .zip(gain.iter().cycle())
+                    .for_each(|(s, g)| *s *= 2. * g);
+            });
+
+            false
+        }
+    }
+
+    #[test]
+    fn test_gain_doubler() {
+        let context = OfflineAudioContext::new(1, 2 * RENDER_QUANTUM_SIZE, 48000.);
+        context.register_worklet("gain-doubler", |_| GainDoubler);
+
+        let src = context.create_constant_source();
+        src.offset().set_value(0.5);
+        let doubler = context.create_worklet("gain-doubler", AudioWorkletNodeOptions::default());
+        doubler.parameters()["gain"].set_value_at_time(3., RENDER_QUANTUM_SIZE as f64 / 48000.);
+
+        src.connect(&doubler);
+        doubler.connect(&context.destination());
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+        assert_float_eq!(
+            output[..RENDER_QUANTUM_SIZE],
+            [1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            output[RENDER_QUANTUM_SIZE..],
+            [3.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_parameter_data_and_modulation() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 48000.);
+        context.register_worklet("gain-doubler", |_| GainDoubler);
+
+        let options = AudioWorkletNodeOptions {
+            parameter_data: HashMap::from([(String::from("gain"), 2.)]),
+            ..AudioWorkletNodeOptions::default()
+        };
+        let doubler = context.create_worklet("gain-doubler", options);
+        assert_float_eq!(doubler.parameters()["gain"].value(), 2., abs <= 0.);
+
+        // the param values are computed by the graph, including the modulation
+        let modulation = context.create_constant_source();
+        modulation.connect(&doubler.parameters()["gain"]);
+        modulation.start();
+
+        let src = context.create_constant_source();
+        src.connect(&doubler);
+        src.start();
+        doubler.connect(&context.destination());
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[6.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_factory_receives_options() {
+        struct Splitter;
+
+        impl Worklet for Splitter {
+            fn process(
+                &mut self,
+                inputs: &[AudioRenderQuantum],
+                outputs: &mut [AudioRenderQuantum],
+                _params: WorkletParamValues,
+                _scope: &RenderScope,
+            ) -> bool {
+                outputs.iter_mut().for_each(|o| *o = inputs[0].clone());
+                false
+            }
+        }
+
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 48000.);
+        context.register_worklet("splitter", |options| {
+            assert_eq!(options.number_of_outputs, 3);
+            Splitter
+        });
+
+        let options = AudioWorkletNodeOptions {
+            number_of_outputs: 3,
+            ..AudioWorkletNodeOptions::default()
+        };
+        let splitter = context.create_worklet("splitter", options);
+        assert_eq!(splitter.number_of_outputs(), 3);
+        assert!(splitter.parameters().is_empty());
+
+        let src = context.create_constant_source();
+        src.connect(&splitter);
+        src.start();
+        splitter.connect_at(&context.destination(), 2, 0);
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0),
+            &[1.; RENDER_QUANTUM_SIZE][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unknown_name() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 48000.);
+        let _ = context.create_worklet("unknown", AudioWorkletNodeOptions::default());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_register_twice() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 48000.);
+        context.register_worklet("gain-doubler", |_| GainDoubler);
+        context.register_worklet("gain-doubler", |_| GainDoubler);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_no_inputs_nor_outputs() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 48000.);
+        context.register_worklet("gain-doubler", |_| GainDoubler);
+
+        let options = AudioWorkletNodeOptions {
+            number_of_inputs: 0,
+            number_of_outputs: 0,
+            ..AudioWorkletNodeOptions::default()
+        };
+        let _ = context.create_worklet("gain-doubler", options);
+    }
+}
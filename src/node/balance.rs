@@ -0,0 +1,369 @@
+//! The stereo balance control and renderer parts
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::{
+    AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
+};
+
+/// Options for constructing a [`BalanceNode`]
+#[derive(Clone, Debug)]
+pub struct BalanceOptions {
+    /// initial value for the balance parameter
+    pub balance: f32,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for BalanceOptions {
+    fn default() -> Self {
+        Self {
+            balance: 0.,
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                count_mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        }
+    }
+}
+
+/// Assert that the channel count is valid for the BalanceNode
+///
+/// # Panics
+///
+/// This function panics if given count is greater than 2
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count(count: usize) {
+    if count > 2 {
+        panic!("NotSupportedError: BalanceNode channel count cannot be greater than two");
+    }
+}
+
+/// Assert that the channel count mode is valid for the BalanceNode
+///
+/// # Panics
+///
+/// This function panics if given count mode is [`ChannelCountMode::Max`]
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count_mode(mode: ChannelCountMode) {
+    if mode == ChannelCountMode::Max {
+        panic!("NotSupportedError: BalanceNode channel count mode cannot be set to max");
+    }
+}
+
+/// Gains of the left and right channel for the given balance, in the range `[-1, 1]`
+fn balance_gains(balance: f32) -> (f32, f32) {
+    let balance = balance.clamp(-1., 1.);
+    (1. - balance.max(0.), 1. + balance.min(0.))
+}
+
+/// `BalanceNode` attenuates one side of a stereo stream, like the balance knob of an amplifier
+///
+/// Contrary to the [`StereoPannerNode`](super::StereoPannerNode), the channels are never mixed
+/// together and the center position leaves the signal unchanged. The side opposite to the
+/// `balance` param is attenuated linearly:
+/// - a balance of `-1` mutes the right channel
+/// - a balance of `0` leaves both channels unchanged
+/// - a balance of `1` mutes the left channel
+///
+/// A mono input is duplicated to both channels first.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_balance`](crate::context::BaseAudioContext::create_balance)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// let balance = context.create_balance();
+/// balance.balance().set_value(-0.5);
+/// balance.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&balance);
+/// osc.start();
+/// ```
+pub struct BalanceNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Position of the balance, from left (`-1`) to right (`1`)
+    balance: AudioParam,
+}
+
+impl AudioNode for BalanceNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn set_channel_count_mode(&self, mode: ChannelCountMode) {
+        assert_valid_channel_count_mode(mode);
+        self.channel_config.set_count_mode(mode);
+    }
+
+    fn set_channel_count(&self, count: usize) {
+        assert_valid_channel_count(count);
+        self.channel_config.set_count(count);
+    }
+}
+
+impl BalanceNode {
+    /// returns a `BalanceNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - balance options
+    ///
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * `options.channel_config.count` is greater than 2
+    /// * `options.channel_config.mode` is `ChannelCountMode::Max`
+    ///
+    pub fn new<C: BaseAudioContext>(context: &C, options: BalanceOptions) -> Self {
+        context.register(move |registration| {
+            assert_valid_channel_count_mode(options.channel_config.count_mode);
+            assert_valid_channel_count(options.channel_config.count);
+
+            let balance_options = AudioParamDescriptor {
+                min_value: -1.,
+                max_value: 1.,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+            let (balance_param, balance_proc) =
+                context.create_audio_param(balance_options, &registration);
+
+            balance_param.set_value(options.balance);
+
+            let renderer = BalanceRenderer {
+                balance: balance_proc,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: options.channel_config.into(),
+                balance: balance_param,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// Returns the balance audio parameter, from left (`-1`) to right (`1`)
+    #[must_use]
+    pub fn balance(&self) -> &AudioParam {
+        &self.balance
+    }
+}
+
+/// `BalanceRenderer` represents the rendering part of `BalanceNode`
+struct BalanceRenderer {
+    /// Position of the balance
+    balance: AudioParamId,
+}
+
+impl AudioProcessor for BalanceRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        if input.is_silent() {
+            output.make_silent();
+            return false;
+        }
+
+        // duplicate a mono input to both channels, the balance always outputs stereo
+        *output = input.clone();
+        if output.number_of_channels() == 1 {
+            output.mix(2, ChannelInterpretation::Speakers);
+        }
+
+        // a-rate param
+        let balance_values = params.get(&self.balance);
+        let [left, right] = output.stereo_mut();
+
+        left.iter_mut()
+            .zip(right.iter_mut())
+            .zip(balance_values.iter().cycle())
+            .for_each(|((l, r), &balance)| {
+                let (gain_left, gain_right) = balance_gains(balance);
+                *l *= gain_left;
+                *r *= gain_right;
+            });
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioScheduledSourceNode, StereoPannerNode, StereoPannerOptions};
+
+    use super::*;
+
+    // render a stereo signal of ones on the left and halves on the right
+    fn render_balance(balance: f32) -> (Vec<f32>, Vec<f32>) {
+        let sample_rate = 8000.;
+        let context = OfflineAudioContext::new(2, 128, sample_rate);
+
+        let mut buffer = context.create_buffer(2, 128, sample_rate);
+        buffer.copy_to_channel(&[1.; 128], 0);
+        buffer.copy_to_channel(&[0.5; 128], 1);
+
+        let node = BalanceNode::new(
+            &context,
+            BalanceOptions {
+                balance,
+                ..BalanceOptions::default()
+            },
+        );
+        node.connect(&context.destination());
+
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&node);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        (
+            output.get_channel_data(0).to_vec(),
+            output.get_channel_data(1).to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(2, 1, 44_100.);
+        let node = context.create_balance();
+        assert_float_eq!(node.balance().value(), 0., abs <= 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_channel_count() {
+        let context = OfflineAudioContext::new(2, 1, 44_100.);
+        let node = context.create_balance();
+        node.set_channel_count(3);
+    }
+
+    #[test]
+    fn test_balance_law() {
+        // center is unchanged, no cross-feed between the channels
+        let (left, right) = render_balance(0.);
+        assert_float_eq!(left[..], [1.; 128][..], abs_all <= 0.);
+        assert_float_eq!(right[..], [0.5; 128][..], abs_all <= 0.);
+
+        // hard left mutes the right channel and keeps the left one
+        let (left, right) = render_balance(-1.);
+        assert_float_eq!(left[..], [1.; 128][..], abs_all <= 0.);
+        assert_float_eq!(right[..], [0.; 128][..], abs_all <= 0.);
+
+        // hard right mutes the left channel and keeps the right one
+        let (left, right) = render_balance(1.);
+        assert_float_eq!(left[..], [0.; 128][..], abs_all <= 0.);
+        assert_float_eq!(right[..], [0.5; 128][..], abs_all <= 0.);
+
+        // linear attenuation in between
+        let (left, right) = render_balance(0.5);
+        assert_float_eq!(left[..], [0.5; 128][..], abs_all <= 0.);
+        assert_float_eq!(right[..], [0.5; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_distinct_from_panner() {
+        // the equal-power panner moves the left channel to the right when panned hard right
+        let sample_rate = 8000.;
+        let context = OfflineAudioContext::new(2, 128, sample_rate);
+
+        let mut buffer = context.create_buffer(2, 128, sample_rate);
+        buffer.copy_to_channel(&[1.; 128], 0);
+        buffer.copy_to_channel(&[0.5; 128], 1);
+
+        let panner = StereoPannerNode::new(
+            &context,
+            StereoPannerOptions {
+                pan: 1.,
+                ..StereoPannerOptions::default()
+            },
+        );
+        panner.connect(&context.destination());
+
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&panner);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(output.get_channel_data(1)[0], 1.5, abs <= 1e-6);
+
+        let (_, right) = render_balance(1.);
+        assert_float_eq!(right[0], 0.5, abs <= 0.);
+    }
+
+    #[test]
+    fn test_mono_input() {
+        let sample_rate = 8000.;
+        let context = OfflineAudioContext::new(2, 128, sample_rate);
+
+        let node = context.create_balance();
+        node.balance().set_value_at_time(-0.5, 0.);
+        node.balance()
+            .linear_ramp_to_value_at_time(0.5, 128. / sample_rate as f64);
+        node.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&node);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        let left = output.get_channel_data(0);
+        let right = output.get_channel_data(1);
+
+        // the mono input is duplicated at unity gain, unlike the equal-power panner
+        assert_float_eq!(left[0], 1., abs <= 0.);
+        assert_float_eq!(right[0], 0.5, abs <= 0.);
+
+        // a-rate modulation, the attenuation moves from the right to the left channel
+        assert!(left[127] < 0.55);
+        assert_float_eq!(right[127], 1., abs <= 0.);
+        assert!(left.windows(2).all(|w| w[0] >= w[1]));
+        assert!(right.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
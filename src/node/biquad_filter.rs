@@ -4,10 +4,12 @@ use std::f64::consts::{PI, SQRT_2};
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
-use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::context::{
+    AudioContextRegistration, AudioParamId, BaseAudioContext, PatchNodeType, Patchable,
+};
 use crate::param::{AudioParam, AudioParamDescriptor};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
-use crate::{MAX_CHANNELS, RENDER_QUANTUM_SIZE};
+use crate::{flush_denormal, MAX_CHANNELS};
 
 use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
 
@@ -17,17 +19,17 @@ fn get_computed_freq(freq: f32, detune: f32) -> f32 {
 
 /// Biquad filter coefficients normalized against a0
 #[derive(Clone, Copy, Debug, Default)]
-struct Coefficients {
-    b0: f64,
-    b1: f64,
-    b2: f64,
-    a1: f64,
-    a2: f64,
+pub(super) struct Coefficients {
+    pub b0: f64,
+    pub b1: f64,
+    pub b2: f64,
+    pub a1: f64,
+    pub a2: f64,
 }
 
 // allow non snake to better the variable names in the spec
 #[allow(non_snake_case)]
-fn calculate_coefs(
+pub(super) fn calculate_coefs(
     filter_type: BiquadFilterType,
     sample_rate: f64,
     f0: f64,
@@ -167,7 +169,15 @@ fn calculate_coefs(
 }
 
 /// Biquad filter types
+///
+/// With the `serde` feature, the variants are (de)serialized by their name in the Web Audio API
+/// (`"lowpass"`, `"highpass"`, ...).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum BiquadFilterType {
     /// Allows frequencies below the cutoff frequency to pass through and
     /// attenuates frequencies above the cutoff. (12dB/oct rolloff)
@@ -226,11 +236,17 @@ impl From<u32> for BiquadFilterType {
 //   float gain = 0;
 // };
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct BiquadFilterOptions {
     pub q: f32,
     pub detune: f32,
     pub frequency: f32,
     pub gain: f32,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub type_: BiquadFilterType,
     pub channel_config: ChannelConfigOptions,
 }
@@ -338,7 +354,9 @@ impl BiquadFilterNode {
     /// * `context` - audio context in which the audio node will live.
     /// * `options` - biquad filter options
     pub fn new<C: BaseAudioContext>(context: &C, options: BiquadFilterOptions) -> Self {
-        context.register(move |registration| {
+        let patch_options = options.clone();
+
+        let node = context.register(move |registration| {
             let sample_rate = context.sample_rate();
 
             let q_options = AudioParamDescriptor {
@@ -385,10 +403,8 @@ impl BiquadFilterNode {
                 frequency: f_proc,
                 q: q_proc,
                 type_: type_.clone(),
-                x1: Vec::with_capacity(MAX_CHANNELS),
-                x2: Vec::with_capacity(MAX_CHANNELS),
-                y1: Vec::with_capacity(MAX_CHANNELS),
-                y2: Vec::with_capacity(MAX_CHANNELS),
+                state: BiquadState::new(),
+                coefs_list: vec![Coefficients::default(); context.render_quantum_size()],
             };
 
             let node = Self {
@@ -402,7 +418,19 @@ impl BiquadFilterNode {
             };
 
             (node, Box::new(renderer))
-        })
+        });
+
+        let channel_config = node.channel_config.clone();
+        let type_ = node.type_.clone();
+        context.base().record_patch_node(&node, move || {
+            PatchNodeType::BiquadFilter(BiquadFilterOptions {
+                type_: type_.load(Ordering::SeqCst).into(),
+                channel_config: (&channel_config).into(),
+                ..patch_options.clone()
+            })
+        });
+
+        node
     }
 
     /// Returns the gain audio paramter
@@ -519,6 +547,12 @@ impl BiquadFilterNode {
     }
 }
 
+impl Patchable for BiquadFilterNode {
+    fn patch_params(&self) -> Vec<&AudioParam> {
+        vec![&self.frequency, &self.detune, &self.q, &self.gain]
+    }
+}
+
 /// `BiquadFilterRenderer` represents the rendering part of `BiquadFilterNode`
 struct BiquadFilterRenderer {
     /// quality factor - its impact on the frequency response of the filter
@@ -536,12 +570,91 @@ struct BiquadFilterRenderer {
     /// `BiquadFilterType` repesented as u32
     type_: Arc<AtomicU32>,
     // keep filter state for each channel
+    state: BiquadState,
+    // coefficients of each frame of the render quantum
+    coefs_list: Vec<Coefficients>,
+}
+
+/// History of the filter for each channel
+struct BiquadState {
     x1: Vec<f64>,
     x2: Vec<f64>,
     y1: Vec<f64>,
     y2: Vec<f64>,
 }
 
+impl BiquadState {
+    fn new() -> Self {
+        Self {
+            x1: Vec::with_capacity(MAX_CHANNELS),
+            x2: Vec::with_capacity(MAX_CHANNELS),
+            y1: Vec::with_capacity(MAX_CHANNELS),
+            y2: Vec::with_capacity(MAX_CHANNELS),
+        }
+    }
+
+    fn number_of_channels(&self) -> usize {
+        self.x1.len()
+    }
+
+    fn resize(&mut self, num_channels: usize) {
+        self.x1.resize(num_channels, 0.);
+        self.x2.resize(num_channels, 0.);
+        self.y1.resize(num_channels, 0.);
+        self.y2.resize(num_channels, 0.);
+    }
+
+    /// `true` when the history of all channels is zero, i.e. the filter has no tail left
+    fn is_zero(&self) -> bool {
+        [&self.x1, &self.x2, &self.y1, &self.y2]
+            .iter()
+            .all(|state| state.iter().all(|&v| v == 0.))
+    }
+
+    /// Filter each channel of the input into the output, which must have as many channels as
+    /// the state
+    fn filter(
+        &mut self,
+        input: &AudioRenderQuantum,
+        output: &mut AudioRenderQuantum,
+        coefs_list: &[Coefficients],
+    ) {
+        for (channel_number, output_channel) in output.channels_mut().iter_mut().enumerate() {
+            let input_channel = input.channel_data(channel_number);
+            // retrieve state from previous block
+            let mut x1 = self.x1[channel_number];
+            let mut x2 = self.x2[channel_number];
+            let mut y1 = self.y1[channel_number];
+            let mut y2 = self.y2[channel_number];
+
+            output_channel
+                .iter_mut()
+                .zip(input_channel.iter())
+                .zip(coefs_list.iter())
+                .for_each(|((o, &i), c)| {
+                    // 𝑎0𝑦(𝑛)+𝑎1𝑦(𝑛−1)+𝑎2𝑦(𝑛−2)=𝑏0𝑥(𝑛)+𝑏1𝑥(𝑛−1)+𝑏2𝑥(𝑛−2)
+                    // as all coefs are normalized against 𝑎0, we get
+                    // 𝑦(𝑛) = 𝑏0𝑥(𝑛) + 𝑏1𝑥(𝑛−1) + 𝑏2𝑥(𝑛−2) - 𝑎1𝑦(𝑛−1) - 𝑎2𝑦(𝑛−2)
+                    let x = f64::from(i);
+                    let y = c.b0 * x + c.b1 * x1 + c.b2 * x2 - c.a1 * y1 - c.a2 * y2;
+                    // update state
+                    x2 = x1;
+                    x1 = x;
+                    y2 = y1;
+                    y1 = y;
+                    // cast output value as f32
+                    *o = y as f32;
+                });
+
+            // store channel state for next block, flushing the decayed values to zero
+            self.x1[channel_number] = flush_denormal(x1);
+            self.x2[channel_number] = flush_denormal(x2);
+            self.y1[channel_number] = flush_denormal(y1);
+            self.y2[channel_number] = flush_denormal(y2);
+        }
+    }
+}
+
 impl AudioProcessor for BiquadFilterRenderer {
     fn process(
         &mut self,
@@ -556,22 +669,10 @@ impl AudioProcessor for BiquadFilterRenderer {
         let sample_rate = scope.sample_rate;
 
         // handle tail time
-        if input.is_silent() {
-            let mut ended = true;
-
-            if self.x1.iter().any(|&v| v.is_normal())
-                || self.x2.iter().any(|&v| v.is_normal())
-                || self.y1.iter().any(|&v| v.is_normal())
-                || self.y2.iter().any(|&v| v.is_normal())
-            {
-                ended = false;
-            }
-
+        if input.is_silent() && self.state.is_zero() {
             // input is silent and filter history is clean
-            if ended {
-                output.make_silent();
-                return false;
-            }
+            output.make_silent();
+            return false;
         }
 
         // eventually resize state according to input number of channels
@@ -582,16 +683,13 @@ impl AudioProcessor for BiquadFilterRenderer {
             // see https://webaudio.github.io/web-audio-api/#channels-tail-time
             let num_channels = input.number_of_channels();
 
-            if num_channels != self.x1.len() {
-                self.x1.resize(num_channels, 0.);
-                self.x2.resize(num_channels, 0.);
-                self.y1.resize(num_channels, 0.);
-                self.y2.resize(num_channels, 0.);
+            if num_channels != self.state.number_of_channels() {
+                self.state.resize(num_channels);
             }
 
             output.set_number_of_channels(num_channels);
         } else {
-            let num_channels = self.x1.len();
+            let num_channels = self.state.number_of_channels();
             output.set_number_of_channels(num_channels);
         }
 
@@ -612,11 +710,11 @@ impl AudioProcessor for BiquadFilterRenderer {
             f64::from(q[0]),
         );
 
-        let mut coefs_list = [coef; RENDER_QUANTUM_SIZE];
-        // if one of the params has a length of RENDER_QUANTUM_SIZE, we need
+        self.coefs_list.fill(coef);
+        // if one of the params has a length of the render quantum size, we need
         // to compute the coefs for each frame
         if frequency.len() != 1 || detune.len() != 1 || q.len() != 1 || gain.len() != 1 {
-            coefs_list
+            self.coefs_list
                 .iter_mut()
                 .zip(frequency.iter().cycle())
                 .zip(detune.iter().cycle())
@@ -635,41 +733,10 @@ impl AudioProcessor for BiquadFilterRenderer {
                 });
         };
 
-        for (channel_number, output_channel) in output.channels_mut().iter_mut().enumerate() {
-            let input_channel = input.channel_data(channel_number);
-            // retrieve state from previous block
-            let mut x1 = self.x1[channel_number];
-            let mut x2 = self.x2[channel_number];
-            let mut y1 = self.y1[channel_number];
-            let mut y2 = self.y2[channel_number];
+        self.state.filter(input, output, &self.coefs_list);
 
-            output_channel
-                .iter_mut()
-                .zip(input_channel.iter())
-                .zip(coefs_list.iter())
-                .for_each(|((o, &i), c)| {
-                    // 𝑎0𝑦(𝑛)+𝑎1𝑦(𝑛−1)+𝑎2𝑦(𝑛−2)=𝑏0𝑥(𝑛)+𝑏1𝑥(𝑛−1)+𝑏2𝑥(𝑛−2)
-                    // as all coefs are normalized against 𝑎0, we get
-                    // 𝑦(𝑛) = 𝑏0𝑥(𝑛) + 𝑏1𝑥(𝑛−1) + 𝑏2𝑥(𝑛−2) - 𝑎1𝑦(𝑛−1) - 𝑎2𝑦(𝑛−2)
-                    let x = f64::from(i);
-                    let y = c.b0 * x + c.b1 * x1 + c.b2 * x2 - c.a1 * y1 - c.a2 * y2;
-                    // update state
-                    x2 = x1;
-                    x1 = x;
-                    y2 = y1;
-                    y1 = y;
-                    // cast output value as f32
-                    *o = y as f32;
-                });
-
-            // store channel state for next block
-            self.x1[channel_number] = x1;
-            self.x2[channel_number] = x2;
-            self.y1[channel_number] = y1;
-            self.y2[channel_number] = y2;
-        }
-
-        true
+        // the tail time ends as soon as the decayed state has been flushed to zero
+        !(input.is_silent() && self.state.is_zero())
     }
 }
 
@@ -678,6 +745,9 @@ mod tests {
     use float_eq::assert_float_eq;
 
     use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+    use crate::render::Alloc;
+    use crate::{AudioBuffer, RENDER_QUANTUM_SIZE};
 
     use super::*;
 
@@ -1100,4 +1170,64 @@ mod tests {
             assert_float_eq!(phases, expected_phases, abs_all <= 1e-6);
         }
     }
+
+    #[test]
+    fn test_flush_denormals() {
+        let sample_rate = 8000.;
+        let context = OfflineAudioContext::new(1, 8000, sample_rate);
+
+        // a loud transient followed by silence
+        let mut impulse = vec![0.; 128];
+        impulse[0] = 100.;
+        let buffer = AudioBuffer::from(vec![impulse], sample_rate);
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.start();
+
+        let biquad = context.create_biquad_filter();
+        biquad.frequency().set_value(1000.);
+        src.connect(&biquad);
+        biquad.connect(&context.destination());
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        // the state decays to exactly zero instead of lingering as subnormal numbers
+        assert!(output.iter().all(|v| !v.is_subnormal()));
+        assert!(output[..128].iter().any(|v| v.abs() > 1.));
+        assert_float_eq!(output[4000..], [0.; 4000][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_flush_denormals_ends_tail() {
+        let alloc = Alloc::with_capacity(2);
+        let coefs = calculate_coefs(BiquadFilterType::Lowpass, 8000., 1000., 0., 1.);
+        let coefs_list = [coefs; RENDER_QUANTUM_SIZE];
+
+        let mut state = BiquadState::new();
+        state.resize(1);
+
+        // impulse at the end of the render quantum, so the filter is still ringing afterwards
+        let mut impulse = AudioRenderQuantum::from(alloc.silence());
+        impulse.channel_data_mut(0)[RENDER_QUANTUM_SIZE - 1] = 100.;
+        let mut output = AudioRenderQuantum::from(alloc.silence());
+        state.filter(&impulse, &mut output, &coefs_list);
+        assert!(!state.is_zero());
+
+        // the filter rings down from the impulse until its state is flushed
+        let silence = AudioRenderQuantum::from(alloc.silence());
+        let quanta = (0..100)
+            .take_while(|_| {
+                state.filter(&silence, &mut output, &coefs_list);
+                !state.is_zero()
+            })
+            .count();
+        assert!(quanta < 100);
+
+        // the history is exactly zero, so the renderer reports the end of its tail time
+        assert_float_eq!(state.x1[..], [0.][..], abs_all <= 0.);
+        assert_float_eq!(state.x2[..], [0.][..], abs_all <= 0.);
+        assert_float_eq!(state.y1[..], [0.][..], abs_all <= 0.);
+        assert_float_eq!(state.y2[..], [0.][..], abs_all <= 0.);
+    }
 }
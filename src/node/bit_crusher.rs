@@ -0,0 +1,401 @@
+//! The bit crusher control and renderer parts
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::MAX_CHANNELS;
+
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Largest sample rate reduction factor of a [`BitCrusherNode`]
+pub const MAX_SAMPLE_RATE_REDUCTION: f32 = 1024.;
+
+/// Options for constructing a [`BitCrusherNode`]
+#[derive(Clone, Debug)]
+pub struct BitCrusherOptions {
+    /// initial value for the bit depth, in the `[1, 16]` range
+    pub bits: f32,
+    /// initial value for the sample rate reduction factor, in the
+    /// `[1, MAX_SAMPLE_RATE_REDUCTION]` range
+    pub sample_rate_reduction: f32,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for BitCrusherOptions {
+    fn default() -> Self {
+        Self {
+            bits: 16.,
+            sample_rate_reduction: 1.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `BitCrusherNode` degrades its input by reducing its sample rate and its bit depth, for
+/// lo-fi, retro or chiptune effects
+///
+/// The sample rate is reduced by holding each sample for `sample_rate_reduction` samples, i.e.
+/// a sample-and-hold decimation without any anti-aliasing filter, so the high frequencies fold
+/// back into the audible range. A fractional factor holds the samples for a varying number of
+/// samples, averaging to the factor. A factor of `1` leaves the signal untouched.
+///
+/// The held samples are then rounded to the nearest multiple of `2^(1 - bits)`, i.e. to
+/// `2^(bits - 1)` levels on each side of zero, zero being a level too. The quantization is
+/// symmetric around zero, and a fractional bit depth sweeps smoothly between the depths. The
+/// samples are not clipped.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_bit_crusher`](crate::context::BaseAudioContext::create_bit_crusher)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// let crusher = context.create_bit_crusher();
+/// crusher.bits().set_value(4.);
+/// crusher.sample_rate_reduction().set_value(8.);
+/// crusher.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&crusher);
+/// osc.start();
+/// ```
+pub struct BitCrusherNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Bit depth of the output
+    bits: AudioParam,
+    /// Number of samples each sample is held for
+    sample_rate_reduction: AudioParam,
+}
+
+impl AudioNode for BitCrusherNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl BitCrusherNode {
+    /// returns a `BitCrusherNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - bit crusher options
+    pub fn new<C: BaseAudioContext>(context: &C, options: BitCrusherOptions) -> Self {
+        context.register(move |registration| {
+            let BitCrusherOptions {
+                bits,
+                sample_rate_reduction,
+                channel_config,
+            } = options;
+
+            let bits_options = AudioParamDescriptor {
+                min_value: 1.,
+                max_value: 16.,
+                default_value: 16.,
+                automation_rate: AutomationRate::A,
+            };
+            let (bits_param, bits_proc) = context.create_audio_param(bits_options, &registration);
+            bits_param.set_value(bits);
+
+            let reduction_options = AudioParamDescriptor {
+                min_value: 1.,
+                max_value: MAX_SAMPLE_RATE_REDUCTION,
+                default_value: 1.,
+                automation_rate: AutomationRate::A,
+            };
+            let (reduction_param, reduction_proc) =
+                context.create_audio_param(reduction_options, &registration);
+            reduction_param.set_value(sample_rate_reduction);
+
+            let renderer = BitCrusherRenderer {
+                bits: bits_proc,
+                sample_rate_reduction: reduction_proc,
+                held: Vec::with_capacity(MAX_CHANNELS),
+                hold_position: 0.,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                bits: bits_param,
+                sample_rate_reduction: reduction_param,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// A-rate [`AudioParam`] of the bit depth, in the `[1, 16]` range
+    #[must_use]
+    pub fn bits(&self) -> &AudioParam {
+        &self.bits
+    }
+
+    /// A-rate [`AudioParam`] of the sample rate reduction factor, i.e. the number of samples
+    /// each sample is held for, in the `[1, MAX_SAMPLE_RATE_REDUCTION]` range
+    #[must_use]
+    pub fn sample_rate_reduction(&self) -> &AudioParam {
+        &self.sample_rate_reduction
+    }
+}
+
+/// `BitCrusherRenderer` represents the rendering part of `BitCrusherNode`
+struct BitCrusherRenderer {
+    /// Bit depth of the output
+    bits: AudioParamId,
+    /// Number of samples each sample is held for
+    sample_rate_reduction: AudioParamId,
+    /// Quantized sample currently held, for each channel
+    held: Vec<f32>,
+    /// Number of samples elapsed since the last held sample was taken
+    hold_position: f64,
+}
+
+impl AudioProcessor for BitCrusherRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        if input.is_silent() {
+            self.held.iter_mut().for_each(|h| *h = 0.);
+            output.make_silent();
+            return false;
+        }
+
+        let number_of_channels = input.number_of_channels();
+        self.held.resize(number_of_channels, 0.);
+
+        *output = input.clone();
+
+        let bits = params.get(&self.bits);
+        let sample_rate_reduction = params.get(&self.sample_rate_reduction);
+        let start_position = self.hold_position;
+        let mut hold_position = start_position;
+
+        output
+            .channels_mut()
+            .iter_mut()
+            .zip(self.held.iter_mut())
+            .for_each(|(channel, held)| {
+                // all channels take their samples at the same positions
+                hold_position = start_position;
+
+                channel
+                    .iter_mut()
+                    .zip(bits.iter().cycle())
+                    .zip(sample_rate_reduction.iter().cycle())
+                    .for_each(|((o, &bits), &reduction)| {
+                        if hold_position < 1. {
+                            let levels = (bits - 1.).exp2();
+                            *held = (*o * levels).round() / levels;
+                        }
+
+                        *o = *held;
+
+                        // wrap with a modulo, the reduction may have dropped below the
+                        // position reached with a previous, larger, reduction
+                        hold_position += 1.;
+                        if hold_position >= f64::from(reduction) {
+                            hold_position %= f64::from(reduction);
+                        }
+                    });
+            });
+
+        self.hold_position = hold_position;
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.;
+
+    /// Render a 100 Hz sine through a bit crusher
+    fn render(bits: f32, sample_rate_reduction: f32) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, 4_800, SAMPLE_RATE);
+
+        let options = BitCrusherOptions {
+            bits,
+            sample_rate_reduction,
+            ..BitCrusherOptions::default()
+        };
+        let crusher = BitCrusherNode::new(&context, options);
+        crusher.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(100.);
+        osc.connect(&crusher);
+        osc.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, 1, SAMPLE_RATE);
+        let crusher = context.create_bit_crusher();
+        assert_float_eq!(crusher.bits().value(), 16., abs <= 0.);
+        assert_float_eq!(crusher.sample_rate_reduction().value(), 1., abs <= 0.);
+    }
+
+    #[test]
+    fn test_passthrough() {
+        let reference = render(16., 1.);
+        let context = OfflineAudioContext::new(1, 4_800, SAMPLE_RATE);
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(100.);
+        osc.connect(&context.destination());
+        osc.start();
+        let sine = context.start_rendering_sync();
+
+        // only the 16 bits quantization applies
+        assert_float_eq!(
+            reference[..],
+            sine.get_channel_data(0),
+            abs_all <= 0.5 / 32768.
+        );
+    }
+
+    #[test]
+    fn test_quantization() {
+        let output = render(4., 1.);
+
+        // the 17 multiples of 1/8 in [-1, 1]
+        output.iter().for_each(|&v| {
+            assert_float_eq!(v * 8., (v * 8.).round(), abs <= 0.);
+            assert!(v.abs() <= 1.);
+        });
+
+        let mut levels: Vec<i32> = output.iter().map(|&v| (v * 8.) as i32).collect();
+        levels.sort_unstable();
+        levels.dedup();
+        assert_eq!(levels, (-8..=8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_quantization_is_symmetric() {
+        let context = OfflineAudioContext::new(1, 128, SAMPLE_RATE);
+
+        let crusher = context.create_bit_crusher();
+        crusher.bits().set_value(2.);
+        crusher.connect(&context.destination());
+
+        let values = [0.2, -0.2, 0.3, -0.3, 0.7, -0.7, 0.8, -0.8];
+        let mut buffer = context.create_buffer(1, values.len(), SAMPLE_RATE);
+        buffer.copy_to_channel(&values, 0);
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&crusher);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0)[..values.len()],
+            [0., 0., 0.5, -0.5, 0.5, -0.5, 1., -1.][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_decimation() {
+        let crushed = render(16., 4.);
+        let sine = render(16., 1.);
+
+        // plateaus of 4 samples, holding the first sample of each group
+        crushed
+            .chunks(4)
+            .zip(sine.chunks(4))
+            .for_each(|(plateau, reference)| {
+                plateau
+                    .iter()
+                    .for_each(|&v| assert_float_eq!(v, reference[0], abs <= 0.));
+            });
+        assert!(crushed.windows(2).filter(|w| w[0] != w[1]).count() < 1_200);
+    }
+
+    #[test]
+    fn test_fractional_decimation() {
+        let crushed = render(16., 2.5);
+
+        // plateaus of 2 or 3 samples, alternating
+        let mut lengths = vec![];
+        let mut length = 1;
+        crushed.windows(2).for_each(|w| {
+            if w[0] == w[1] {
+                length += 1;
+            } else {
+                lengths.push(length);
+                length = 1;
+            }
+        });
+
+        // skip the plateaus around the peaks of the sine, where consecutive held samples
+        // may round to the same value
+        let counted: Vec<_> = lengths.iter().filter(|&&l| l <= 3).collect();
+        assert!(counted.iter().all(|&&l| l == 2 || l == 3));
+        let twos = counted.iter().filter(|&&&l| l == 2).count();
+        let threes = counted.iter().filter(|&&&l| l == 3).count();
+        assert!(twos > 500);
+        assert!(twos.abs_diff(threes) <= 2);
+    }
+
+    #[test]
+    fn test_decimation_automated_to_passthrough() {
+        let context = OfflineAudioContext::new(1, 4_800, SAMPLE_RATE);
+
+        let crusher = context.create_bit_crusher();
+        crusher.sample_rate_reduction().set_value(8.);
+        crusher
+            .sample_rate_reduction()
+            .set_value_at_time(1., 2_000. / f64::from(SAMPLE_RATE));
+        crusher.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(100.);
+        osc.connect(&crusher);
+        osc.start();
+
+        let crushed = context.start_rendering_sync().get_channel_data(0).to_vec();
+        let sine = render(16., 1.);
+
+        // held samples before the automation, then the output follows the input again
+        assert!(crushed[..2_000].windows(2).filter(|w| w[0] != w[1]).count() < 300);
+        assert_float_eq!(crushed[2_001..], sine[2_001..], abs_all <= 0.);
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 
-use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::context::{AudioContextRegistration, BaseAudioContext, PatchNodeType, Patchable};
+use crate::param::AudioParam;
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 
 use super::{
@@ -12,6 +13,11 @@ use super::{
 //   unsigned long numberOfInputs = 6;
 // };
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct ChannelMergerOptions {
     pub number_of_inputs: usize,
     pub channel_config: ChannelConfigOptions,
@@ -64,7 +70,7 @@ impl AudioNode for ChannelMergerNode {
 
 impl ChannelMergerNode {
     pub fn new<C: BaseAudioContext>(context: &C, mut options: ChannelMergerOptions) -> Self {
-        context.register(move |registration| {
+        let node = context.register(move |registration| {
             options.channel_config.count = options.number_of_inputs;
 
             let node = ChannelMergerNode {
@@ -75,7 +81,23 @@ impl ChannelMergerNode {
             let render = ChannelMergerRenderer {};
 
             (node, Box::new(render))
-        })
+        });
+
+        let channel_config = node.channel_config.clone();
+        context.base().record_patch_node(&node, move || {
+            PatchNodeType::ChannelMerger(ChannelMergerOptions {
+                number_of_inputs: channel_config.count(),
+                channel_config: (&channel_config).into(),
+            })
+        });
+
+        node
+    }
+}
+
+impl Patchable for ChannelMergerNode {
+    fn patch_params(&self) -> Vec<&AudioParam> {
+        vec![]
     }
 }
 
@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 
-use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::context::{AudioContextRegistration, BaseAudioContext, PatchNodeType, Patchable};
+use crate::param::AudioParam;
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 
 use super::{
@@ -12,6 +13,11 @@ use super::{
 //   unsigned long numberOfOutputs = 6;
 // };
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct ChannelSplitterOptions {
     pub number_of_outputs: usize,
     pub channel_config: ChannelConfigOptions,
@@ -68,7 +74,7 @@ impl AudioNode for ChannelSplitterNode {
 
 impl ChannelSplitterNode {
     pub fn new<C: BaseAudioContext>(context: &C, mut options: ChannelSplitterOptions) -> Self {
-        context.register(move |registration| {
+        let node = context.register(move |registration| {
             options.channel_config.count = options.number_of_outputs;
 
             let node = ChannelSplitterNode {
@@ -81,7 +87,23 @@ impl ChannelSplitterNode {
             };
 
             (node, Box::new(render))
-        })
+        });
+
+        let channel_config = node.channel_config.clone();
+        context.base().record_patch_node(&node, move || {
+            PatchNodeType::ChannelSplitter(ChannelSplitterOptions {
+                number_of_outputs: channel_config.count(),
+                channel_config: (&channel_config).into(),
+            })
+        });
+
+        node
+    }
+}
+
+impl Patchable for ChannelSplitterNode {
+    fn patch_params(&self) -> Vec<&AudioParam> {
+        vec![]
     }
 }
 
@@ -0,0 +1,541 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::{flush_denormal_f32, MAX_CHANNELS};
+
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Largest magnitude of the feedback gain, larger values are clamped so the loop always decays
+pub const MAX_COMB_FEEDBACK: f32 = 0.999;
+
+/// Options for constructing a [`CombFilterNode`]
+#[derive(Clone, Debug)]
+pub struct CombFilterOptions {
+    /// Length of the delay line, in samples
+    pub delay_samples: usize,
+    /// Largest delay that can be set on the node, in samples
+    pub max_delay_samples: usize,
+    /// initial value for the feedback parameter
+    pub feedback: f32,
+    /// initial value for the feedforward parameter
+    pub feedforward: f32,
+    /// initial value for the damping parameter
+    pub damping: f32,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for CombFilterOptions {
+    fn default() -> Self {
+        Self {
+            delay_samples: 100,
+            max_delay_samples: 4096,
+            feedback: 0.5,
+            feedforward: 0.,
+            damping: 0.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// Assert that the delay is in the `[1, max_delay_samples]` range
+///
+/// # Panics
+///
+/// This function panics if the delay is zero or greater than `max_delay_samples`
+#[track_caller]
+#[inline(always)]
+fn assert_valid_delay_samples(delay_samples: usize, max_delay_samples: usize) {
+    if delay_samples == 0 || delay_samples > max_delay_samples {
+        panic!(
+            "RangeError - delay ({:?} samples) should be in the [1, {:?}] range",
+            delay_samples, max_delay_samples
+        );
+    }
+}
+
+/// `CombFilterNode` mixes its input with a delayed copy of its input and of its output,
+/// e.g. for physical modeling synthesis
+///
+/// The output is computed as
+///
+/// `y[n] = x[n] + feedforward * x[n - D] + feedback * lowpass(y[n - D])`
+///
+/// where `D` is the delay in samples and `lowpass` is a one pole filter whose coefficient is the
+/// damping: `0.` leaves the loop unfiltered, values closer to `1.` darken the sound at each
+/// round trip. The feedback loop resonates at `sample_rate / D` Hz and its harmonics.
+///
+/// The feedback loop is computed sample by sample inside the node, so contrary to a cycle
+/// involving a [`DelayNode`](super::DelayNode), the delay can be shorter than a render quantum,
+/// down to a single sample.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_comb_filter`](crate::context::BaseAudioContext::create_comb_filter)
+///
+/// # Usage
+///
+/// Excited with a short noise burst, the comb filter yields a Karplus-Strong plucked string:
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// let string = context.create_comb_filter();
+/// // 441Hz at 44.1kHz
+/// string.set_delay_samples(100);
+/// string.feedback().set_value(0.99);
+/// string.damping().set_value(0.3);
+/// string.connect(&context.destination());
+///
+/// let noise = context.create_noise_source();
+/// noise.connect(&string);
+/// noise.start();
+/// noise.stop_at(context.current_time() + 0.002);
+/// ```
+pub struct CombFilterNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    feedback: AudioParam,
+    feedforward: AudioParam,
+    damping: AudioParam,
+    delay_samples: Arc<AtomicUsize>,
+    max_delay_samples: usize,
+}
+
+impl AudioNode for CombFilterNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl CombFilterNode {
+    /// Returns a `CombFilterNode`
+    ///
+    /// # Arguments:
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - comb filter options
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `options.delay_samples` is zero or greater than
+    /// `options.max_delay_samples`
+    pub fn new<C: BaseAudioContext>(context: &C, options: CombFilterOptions) -> Self {
+        context.register(move |registration| {
+            let CombFilterOptions {
+                delay_samples,
+                max_delay_samples,
+                feedback,
+                feedforward,
+                damping,
+                channel_config,
+            } = options;
+
+            assert_valid_delay_samples(delay_samples, max_delay_samples);
+
+            let feedback_options = AudioParamDescriptor {
+                min_value: -MAX_COMB_FEEDBACK,
+                max_value: MAX_COMB_FEEDBACK,
+                default_value: 0.5,
+                automation_rate: AutomationRate::A,
+            };
+            let (feedback_param, feedback_proc) =
+                context.create_audio_param(feedback_options, &registration);
+            feedback_param.set_value(feedback);
+
+            let feedforward_options = AudioParamDescriptor {
+                min_value: f32::MIN,
+                max_value: f32::MAX,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+            let (feedforward_param, feedforward_proc) =
+                context.create_audio_param(feedforward_options, &registration);
+            feedforward_param.set_value(feedforward);
+
+            let damping_options = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: 1.,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+            let (damping_param, damping_proc) =
+                context.create_audio_param(damping_options, &registration);
+            damping_param.set_value(damping);
+
+            let delay_samples = Arc::new(AtomicUsize::new(delay_samples));
+
+            let renderer = CombFilterRenderer {
+                feedback: feedback_proc,
+                feedforward: feedforward_proc,
+                damping: damping_proc,
+                delay_samples: delay_samples.clone(),
+                inputs: Vec::with_capacity(MAX_CHANNELS),
+                outputs: Vec::with_capacity(MAX_CHANNELS),
+                lowpass: Vec::with_capacity(MAX_CHANNELS),
+                max_delay_samples,
+                write_index: 0,
+                silent_samples: max_delay_samples,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                feedback: feedback_param,
+                feedforward: feedforward_param,
+                damping: damping_param,
+                delay_samples,
+                max_delay_samples,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// Returns the feedback audio parameter, the gain of the delayed output, clamped to
+    /// `[-MAX_COMB_FEEDBACK, MAX_COMB_FEEDBACK]`
+    #[must_use]
+    pub fn feedback(&self) -> &AudioParam {
+        &self.feedback
+    }
+
+    /// Returns the feedforward audio parameter, the gain of the delayed input
+    #[must_use]
+    pub fn feedforward(&self) -> &AudioParam {
+        &self.feedforward
+    }
+
+    /// Returns the damping audio parameter, the coefficient of the lowpass filter in the
+    /// feedback loop in the `[0, 1]` range
+    #[must_use]
+    pub fn damping(&self) -> &AudioParam {
+        &self.damping
+    }
+
+    /// Length of the delay line, in samples
+    #[must_use]
+    pub fn delay_samples(&self) -> usize {
+        self.delay_samples.load(Ordering::SeqCst)
+    }
+
+    /// Update the length of the delay line, in samples
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the delay is zero or greater than the `max_delay_samples` given at
+    /// construction
+    pub fn set_delay_samples(&self, delay_samples: usize) {
+        assert_valid_delay_samples(delay_samples, self.max_delay_samples);
+        self.delay_samples.store(delay_samples, Ordering::SeqCst);
+    }
+}
+
+struct CombFilterRenderer {
+    feedback: AudioParamId,
+    feedforward: AudioParamId,
+    damping: AudioParamId,
+    delay_samples: Arc<AtomicUsize>,
+    /// Ring buffers of the past input samples of each channel
+    inputs: Vec<Vec<f32>>,
+    /// Ring buffers of the past output samples of each channel
+    outputs: Vec<Vec<f32>>,
+    /// State of the damping filter of each channel
+    lowpass: Vec<f32>,
+    /// Length of the ring buffers
+    max_delay_samples: usize,
+    /// Position of the next sample in the ring buffers
+    write_index: usize,
+    /// Number of silent samples in a row written to the ring buffers
+    silent_samples: usize,
+}
+
+impl AudioProcessor for CombFilterRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        // the ring buffers only hold silence, nothing left to process
+        if input.is_silent() && self.silent_samples >= self.max_delay_samples {
+            output.make_silent();
+            return false;
+        }
+
+        // continue with the previous number of channels during the tail time
+        let number_of_channels = if input.is_silent() {
+            self.lowpass.len().max(1)
+        } else {
+            input.number_of_channels()
+        };
+
+        if number_of_channels != self.lowpass.len() {
+            let length = self.max_delay_samples;
+            self.inputs
+                .resize_with(number_of_channels, || vec![0.; length]);
+            self.outputs
+                .resize_with(number_of_channels, || vec![0.; length]);
+            self.lowpass.resize(number_of_channels, 0.);
+        }
+
+        output.set_number_of_channels(number_of_channels);
+
+        let feedback = params.get(&self.feedback);
+        let feedforward = params.get(&self.feedforward);
+        let damping = params.get(&self.damping);
+
+        let length = self.max_delay_samples;
+        let delay = self.delay_samples.load(Ordering::SeqCst);
+        // index of the last sample of the quantum that is not silent, on any channel
+        let mut last_sound = None;
+
+        for (channel_number, output_channel) in output.channels_mut().iter_mut().enumerate() {
+            let input_channel = if input.is_silent() {
+                input.channel_data(0)
+            } else {
+                input.channel_data(channel_number)
+            };
+
+            let past_inputs = &mut self.inputs[channel_number];
+            let past_outputs = &mut self.outputs[channel_number];
+            let mut lowpass = self.lowpass[channel_number];
+            let mut write_index = self.write_index;
+
+            output_channel
+                .iter_mut()
+                .enumerate()
+                .zip(input_channel.iter())
+                .zip(feedback.iter().cycle())
+                .zip(feedforward.iter().cycle())
+                .zip(damping.iter().cycle())
+                .for_each(|(((((i, o), &x), &fb), &ff), &damping)| {
+                    // the sample at `write_index` is the oldest one, i.e. `length` samples ago
+                    let read_index = (write_index + length - delay) % length;
+                    let delayed_input = past_inputs[read_index];
+                    let delayed_output = past_outputs[read_index];
+
+                    lowpass = (1. - damping).mul_add(delayed_output, damping * lowpass);
+                    let y = flush_denormal_f32(fb.mul_add(lowpass, ff.mul_add(delayed_input, x)));
+
+                    past_inputs[write_index] = x;
+                    past_outputs[write_index] = y;
+                    write_index = (write_index + 1) % length;
+
+                    if x != 0. || y != 0. {
+                        last_sound = last_sound.max(Some(i));
+                    }
+
+                    *o = y;
+                });
+
+            self.lowpass[channel_number] = flush_denormal_f32(lowpass);
+        }
+
+        self.write_index = (self.write_index + scope.render_quantum_size) % length;
+        self.silent_samples = match last_sound {
+            Some(i) => scope.render_quantum_size - 1 - i,
+            None => self.silent_samples + scope.render_quantum_size,
+        };
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+    use crate::AudioBuffer;
+
+    use super::*;
+
+    // render an impulse through a comb filter
+    fn render_impulse(options: CombFilterOptions, length: usize) -> Vec<f32> {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, length, sample_rate);
+
+        let comb = CombFilterNode::new(&context, options);
+        comb.connect(&context.destination());
+
+        let mut impulse = vec![0.; 128];
+        impulse[0] = 1.;
+        let src = context.create_buffer_source();
+        src.set_buffer(AudioBuffer::from(vec![impulse], sample_rate));
+        src.connect(&comb);
+        src.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_feedback_echoes() {
+        let options = CombFilterOptions {
+            delay_samples: 10,
+            feedback: 0.5,
+            ..CombFilterOptions::default()
+        };
+        let output = render_impulse(options, 128);
+
+        // echoes of halving amplitude every 10 samples, the delay is shorter than a quantum
+        output.iter().enumerate().for_each(|(i, &v)| {
+            let expected = if i % 10 == 0 {
+                0.5_f32.powi((i / 10) as i32)
+            } else {
+                0.
+            };
+            assert_float_eq!(v, expected, abs <= 1e-7);
+        });
+    }
+
+    #[test]
+    fn test_feedforward() {
+        let options = CombFilterOptions {
+            delay_samples: 3,
+            feedback: 0.,
+            feedforward: -1.,
+            ..CombFilterOptions::default()
+        };
+        let output = render_impulse(options, 128);
+
+        let mut expected = [0.; 128];
+        expected[0] = 1.;
+        expected[3] = -1.;
+        assert_float_eq!(output[..], expected[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_fundamental() {
+        let sample_rate = 48000.;
+        let delay_samples = 480;
+        let options = CombFilterOptions {
+            delay_samples,
+            feedback: 0.99,
+            damping: 0.3,
+            ..CombFilterOptions::default()
+        };
+        let output = render_impulse(options, 48000);
+
+        // the autocorrelation of the ringing peaks at the period of the fundamental
+        let ringing = &output[4800..];
+        let autocorrelation = |lag: usize| -> f32 {
+            ringing
+                .iter()
+                .zip(ringing[lag..].iter())
+                .map(|(a, b)| a * b)
+                .sum()
+        };
+        let period = (delay_samples / 2..delay_samples * 2 - delay_samples / 2)
+            .max_by(|&a, &b| autocorrelation(a).total_cmp(&autocorrelation(b)))
+            .unwrap();
+
+        // the damping filter delays the loop by a fraction of a sample
+        assert!((period as i64 - delay_samples as i64).abs() <= 1);
+        let fundamental = sample_rate / period as f32;
+        assert_float_eq!(fundamental, 100., abs <= 0.25);
+    }
+
+    #[test]
+    fn test_feedback_clamped() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+        let comb = context.create_comb_filter();
+        comb.feedback().set_value(2.);
+        assert_float_eq!(comb.feedback().value(), MAX_COMB_FEEDBACK, abs <= 0.);
+
+        let options = CombFilterOptions {
+            delay_samples: 1,
+            feedback: 1.5,
+            ..CombFilterOptions::default()
+        };
+        let output = render_impulse(options, 48000);
+
+        // the loop decays instead of running away
+        assert!(output.iter().all(|v| v.abs() <= 1.));
+        assert!(output[47000..].iter().all(|v| v.abs() < 0.01));
+    }
+
+    #[test]
+    fn test_tail_time_ends() {
+        let options = CombFilterOptions {
+            delay_samples: 100,
+            max_delay_samples: 100,
+            feedback: 0.5,
+            ..CombFilterOptions::default()
+        };
+        let output = render_impulse(options, 48000);
+        assert!(output[24000..].iter().all(|&v| v == 0.));
+    }
+
+    #[test]
+    fn test_tail_time_stereo() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(2, 4096, sample_rate);
+
+        let options = CombFilterOptions {
+            delay_samples: 100,
+            max_delay_samples: 100,
+            feedback: 0.9,
+            ..CombFilterOptions::default()
+        };
+        let comb = CombFilterNode::new(&context, options);
+        comb.connect(&context.destination());
+
+        // the left channel stays silent while the right one plays
+        let left = vec![0.; 512];
+        let right = vec![0.5; 512];
+        let src = context.create_buffer_source();
+        src.set_buffer(AudioBuffer::from(vec![left, right], sample_rate));
+        src.connect(&comb);
+        src.start();
+
+        let output = context.start_rendering_sync();
+
+        // the feedback tail of the right channel outlives the input
+        assert!(output.get_channel_data(0).iter().all(|&v| v == 0.));
+        let tail = output.get_channel_data(1)[1024..]
+            .iter()
+            .filter(|&&v| v != 0.)
+            .count();
+        assert_eq!(tail, 3072);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_delay() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+        let comb = context.create_comb_filter();
+        comb.set_delay_samples(0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_delay_above_max() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+        let comb = context.create_comb_filter();
+        comb.set_delay_samples(4097);
+    }
+}
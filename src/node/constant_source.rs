@@ -1,8 +1,11 @@
-use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use std::error::Error;
+
+use crate::context::{
+    AudioContextRegistration, AudioParamId, BaseAudioContext, PatchNodeType, Patchable,
+};
 use crate::control::Scheduler;
 use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
-use crate::RENDER_QUANTUM_SIZE;
 
 use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig};
 
@@ -11,6 +14,11 @@ use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig};
 //   float offset = 1;
 // };
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct ConstantSourceOptions {
     pub offset: f32,
 }
@@ -81,28 +89,20 @@ impl AudioNode for ConstantSourceNode {
 }
 
 impl AudioScheduledSourceNode for ConstantSourceNode {
-    fn start(&self) {
-        let when = self.registration.context().current_time();
-        self.start_at(when);
-    }
-
-    fn start_at(&self, when: f64) {
-        self.scheduler.start_at(when);
+    fn try_start_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_start_at(when)
     }
 
-    fn stop(&self) {
-        let when = self.registration.context().current_time();
-        self.stop_at(when);
-    }
-
-    fn stop_at(&self, when: f64) {
-        self.scheduler.stop_at(when);
+    fn try_stop_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_stop_at(when)
     }
 }
 
 impl ConstantSourceNode {
     pub fn new<C: BaseAudioContext>(context: &C, options: ConstantSourceOptions) -> Self {
-        context.register(move |registration| {
+        let patch_options = options.clone();
+
+        let node = context.register(move |registration| {
             let param_opts = AudioParamDescriptor {
                 min_value: f32::MIN,
                 max_value: f32::MAX,
@@ -128,7 +128,13 @@ impl ConstantSourceNode {
             };
 
             (node, Box::new(render))
-        })
+        });
+
+        context.base().record_patch_node(&node, move || {
+            PatchNodeType::ConstantSource(patch_options.clone())
+        });
+
+        node
     }
 
     pub fn offset(&self) -> &AudioParam {
@@ -136,6 +142,16 @@ impl ConstantSourceNode {
     }
 }
 
+impl Patchable for ConstantSourceNode {
+    fn patch_params(&self) -> Vec<&AudioParam> {
+        vec![&self.offset]
+    }
+
+    fn patch_scheduler(&self) -> Option<&Scheduler> {
+        Some(&self.scheduler)
+    }
+}
+
 struct ConstantSourceRenderer {
     offset: AudioParamId,
     scheduler: Scheduler,
@@ -154,7 +170,7 @@ impl AudioProcessor for ConstantSourceRenderer {
         let output = &mut outputs[0];
 
         let dt = 1. / scope.sample_rate as f64;
-        let next_block_time = scope.current_time + dt * RENDER_QUANTUM_SIZE as f64;
+        let next_block_time = scope.current_time + dt * scope.render_quantum_size as f64;
 
         let start_time = self.scheduler.get_start_at();
         let stop_time = self.scheduler.get_stop_at();
@@ -164,7 +180,7 @@ impl AudioProcessor for ConstantSourceRenderer {
             return true;
         }
 
-        output.force_mono();
+        output.ensure_mono();
 
         let offset = params.get(&self.offset);
         let output_channel = output.channel_data_mut(0);
@@ -244,16 +260,61 @@ mod tests {
 
     #[test]
     fn test_start_in_the_past() {
-        let context = OfflineAudioContext::new(1, 128, 48000.);
+        let mut context = OfflineAudioContext::new(1, 256, 48000.);
 
         let src = context.create_constant_source();
         src.connect(&context.destination());
-        src.start_at(-1.);
+
+        // start at time 0 while rendering the second block
+        context.suspend_at(128, move |_| src.start_at(0.));
 
         let buffer = context.start_rendering_sync();
         let channel = buffer.get_channel_data(0);
 
-        // 1rst block should be silence
-        assert_float_eq!(channel[0..128], vec![1.; 128][..], abs_all <= 0.);
+        // 1rst block should be silence, the source starts right away in the 2nd block
+        assert_float_eq!(channel[0..128], vec![0.; 128][..], abs_all <= 0.);
+        assert_float_eq!(channel[128..256], vec![1.; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_try_start_stop() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+        let src = context.create_constant_source();
+
+        assert!(src.try_stop().is_err()); // stop before start
+        assert!(src.try_start_at(-1.).is_err()); // negative time
+        assert!(src.try_start_at(f64::INFINITY).is_err());
+
+        assert!(src.try_start_at(0.).is_ok());
+        assert!(src.try_start().is_err()); // double start
+
+        assert!(src.try_stop_at(-1.).is_err());
+        assert!(src.try_stop_at(1.).is_ok());
+        assert!(src.try_stop().is_ok()); // the last stop wins
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_start_twice() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+        let src = context.create_constant_source();
+        src.start();
+        src.start();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_stop_before_start() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+        let src = context.create_constant_source();
+        src.stop();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_start_at_negative_time() {
+        let context = OfflineAudioContext::new(1, 128, 48000.);
+        let src = context.create_constant_source();
+        src.start_at(-1.);
     }
 }
@@ -2,16 +2,18 @@ use super::{AudioNode, ChannelConfig, ChannelConfigOptions, ChannelInterpretatio
 use crate::buffer::AudioBuffer;
 use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
-use crate::RENDER_QUANTUM_SIZE;
 use easyfft::num_complex::Complex;
 use easyfft::prelude::*;
 
 use crossbeam_channel::{Receiver, Sender};
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
     Mutex,
 };
 
+/// Maximum partition size of the convolution
+const MAX_PARTITION_SIZE: usize = 32768;
+
 /// Scale buffer by an equal-power normalization
 fn normalization(buffer: &AudioBuffer) -> f32 {
     let gain_calibration = 0.00125;
@@ -77,6 +79,13 @@ pub struct ConvolverOptions {
 /// The current implementation only handles mono-to-mono convolutions. The provided impulse
 /// response buffer and the input signal will be downmixed appropriately.
 ///
+/// The impulse response is split into partitions of equal length, which are convolved in the
+/// frequency domain. The [partition size](ConvolverNode::set_partition_size) trades latency for
+/// CPU: larger partitions need less processing for long responses but delay the output by the
+/// partition size minus the render quantum size. With the
+/// [zero latency](ConvolverNode::set_zero_latency) option, the first partition is convolved in
+/// the time domain instead, so the output is never delayed.
+///
 /// # Usage
 ///
 /// ```no_run
@@ -115,6 +124,10 @@ pub struct ConvolverNode {
     channel_config: ChannelConfig,
     /// Perform equal power normalization on response buffer
     normalize: AtomicBool,
+    /// Length of the partitions of the response buffer
+    partition_size: AtomicUsize,
+    /// Convolve the first partition in the time domain
+    zero_latency: AtomicBool,
     /// The response buffer, nullable
     buffer: Mutex<Option<AudioBuffer>>,
     /// Message bus to the renderer
@@ -169,6 +182,8 @@ impl ConvolverNode {
                 registration,
                 channel_config: channel_config.into(),
                 normalize: AtomicBool::new(!disable_normalization),
+                partition_size: AtomicUsize::new(context.render_quantum_size()),
+                zero_latency: AtomicBool::new(false),
                 sender,
                 buffer: Mutex::new(None),
             };
@@ -195,8 +210,7 @@ impl ConvolverNode {
     /// sample rate.
     pub fn set_buffer(&self, mut buffer: AudioBuffer) {
         // resample if necessary
-        buffer.resample(self.context().sample_rate());
-        let sample_rate = buffer.sample_rate();
+        buffer.resample_in_place(self.context().sample_rate());
 
         // normalize before padding because the length of the buffer affects the scale
         let scale = if self.normalize() {
@@ -205,23 +219,19 @@ impl ConvolverNode {
             1.
         };
 
-        // Pad the response buffer with zeroes so its size is a power of 2, with 2 * 128 as min size
-        let length = buffer.length();
-        let padded_length = length.next_power_of_two().max(2 * RENDER_QUANTUM_SIZE);
-        let samples: Vec<_> = (0..buffer.number_of_channels())
-            .map(|_| {
-                let mut samples = vec![0.; padded_length];
-                samples[..length]
-                    .iter_mut()
-                    .zip(buffer.get_channel_data(0))
-                    .for_each(|(o, i)| *o = *i * scale);
-                samples
-            })
+        // mono processing only for now
+        let response: Vec<f32> = buffer
+            .get_channel_data(0)
+            .iter()
+            .map(|v| v * scale)
             .collect();
 
-        let padded_buffer = AudioBuffer::from(samples, sample_rate);
-
-        let convolve = ConvolverRendererInner::new(padded_buffer);
+        let convolve = ConvolverRendererInner::new(
+            &response,
+            self.partition_size(),
+            self.context().render_quantum_size(),
+            self.zero_latency(),
+        );
         let _ = self.sender.send(convolve); // can fail when render thread shut down
 
         *self.buffer.lock().unwrap() = Some(buffer);
@@ -236,6 +246,70 @@ impl ConvolverNode {
     pub fn set_normalize(&self, value: bool) {
         self.normalize.store(value, Ordering::SeqCst);
     }
+
+    /// Length in sample-frames of the partitions of the response buffer
+    ///
+    /// This method is not part of the Web Audio API specification.
+    pub fn partition_size(&self) -> usize {
+        self.partition_size.load(Ordering::SeqCst)
+    }
+
+    /// Update the length of the partitions of the response buffer, the render quantum size by
+    /// default. This will only have an effect when `set_buffer` is called.
+    ///
+    /// Larger partitions lower the CPU usage for long responses, but delay the output by the
+    /// partition size minus the render quantum size, unless the
+    /// [zero latency](Self::set_zero_latency) option is set. The output is otherwise identical.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `frames` is not a power of two in the range from the render quantum size
+    /// to 32768
+    pub fn set_partition_size(&self, frames: usize) {
+        let render_quantum_size = self.context().render_quantum_size();
+        assert!(
+            frames.is_power_of_two()
+                && (render_quantum_size..=MAX_PARTITION_SIZE).contains(&frames),
+            "IndexSizeError - partition size ({:?}) should be a power of two in the range [{}, {}]",
+            frames,
+            render_quantum_size,
+            MAX_PARTITION_SIZE
+        );
+        self.partition_size.store(frames, Ordering::SeqCst);
+    }
+
+    /// Denotes if the first partition of the response buffer is convolved in the time domain
+    ///
+    /// This method is not part of the Web Audio API specification.
+    pub fn zero_latency(&self) -> bool {
+        self.zero_latency.load(Ordering::SeqCst)
+    }
+
+    /// Update the `zero_latency` setting, `false` by default. This will only have an effect when
+    /// `set_buffer` is called.
+    ///
+    /// The first partition of the response buffer is then convolved in the time domain and the
+    /// next ones in the frequency domain, so the output is not delayed whatever the partition
+    /// size. The direct convolution costs `partition_size` multiplications per sample, this
+    /// suits moderate partition sizes.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    pub fn set_zero_latency(&self, value: bool) {
+        self.zero_latency.store(value, Ordering::SeqCst);
+    }
+
+    /// Delay of the output in seconds, caused by the partitioned convolution
+    ///
+    /// This method is not part of the Web Audio API specification.
+    pub fn latency(&self) -> f64 {
+        if self.zero_latency() {
+            return 0.;
+        }
+        let frames = self.partition_size() - self.context().render_quantum_size();
+        frames as f64 / f64::from(self.context().sample_rate())
+    }
 }
 
 fn roll_zero<T: Default + Copy>(signal: &mut [T], n: usize) {
@@ -261,106 +335,160 @@ impl ConvolverRenderer {
 }
 
 struct ConvolverRendererInner {
-    num_ir_blocks: usize,
+    /// Length of the blocks the impulse response is split into
+    partition_size: usize,
+    /// Number of sample frames of each call to `process`
+    render_quantum_size: usize,
+    /// First partition of the response, convolved in the time domain in zero latency mode,
+    /// empty otherwise
+    direct: Vec<f32>,
+    /// Latest input samples for the direct convolution
+    history: Vec<f32>,
+    /// Spectra of the partitions convolved in the frequency domain
     h: Vec<Complex<f32>>,
+    /// Frequency domain delay line, the spectra of the upcoming output blocks
     fdl: Vec<Complex<f32>>,
+    /// Input samples of the current block
+    input_block: Vec<f32>,
+    /// Number of samples in the current block
+    fill: usize,
+    /// Output samples, the first one is the next to be rendered
     out: Vec<f32>,
+    /// Position in `out` of the output of a completed block
+    output_offset: usize,
+    /// Number of samples until the output has decayed after the input turned silent
+    tail_length: usize,
+    silent_samples: usize,
     temp_input: Vec<f32>,
     temp_output: DynRealDft<f32>,
 }
 
 impl ConvolverRendererInner {
-    fn new(response: AudioBuffer) -> Self {
-        // mono processing only for now
-        let response = response.channel_data(0).as_slice();
-
-        let p = response.len();
-
-        let num_ir_blocks = p / RENDER_QUANTUM_SIZE;
+    fn new(
+        response: &[f32],
+        partition_size: usize,
+        render_quantum_size: usize,
+        zero_latency: bool,
+    ) -> Self {
+        let num_partitions = response.len().div_ceil(partition_size).max(1);
+        let mut partitions = response.chunks(partition_size);
+
+        // the first partition is convolved in the time domain, without latency
+        let mut direct = vec![];
+        let mut history = vec![];
+        if zero_latency {
+            direct = vec![0.; partition_size];
+            let first = partitions.next().unwrap_or(&[]);
+            direct[..first.len()].copy_from_slice(first);
+            history = vec![0.; partition_size - 1 + render_quantum_size];
+        }
 
-        let mut h = vec![Complex::default(); num_ir_blocks * 2 * RENDER_QUANTUM_SIZE];
+        // the real FFT only stores the positive half of the spectrum
+        let mut temp_input = vec![0.0; 2 * partition_size];
+        let mut temp_output = DynRealDft::default(2 * partition_size);
+        let bins = temp_output.len();
 
-        let mut temp_input = vec![0.0; 2 * RENDER_QUANTUM_SIZE];
-        let mut temp_output = DynRealDft::default(2 * RENDER_QUANTUM_SIZE);
-        for (resp_fft, resp) in h
-            .chunks_mut(2 * RENDER_QUANTUM_SIZE)
-            .zip(response.chunks(RENDER_QUANTUM_SIZE))
-        {
-            // fill resp_fft with FFT of resp.zero_pad(RENDER_QUANTUM_SIZE)
-            temp_input[..RENDER_QUANTUM_SIZE].copy_from_slice(resp);
-            temp_input[RENDER_QUANTUM_SIZE..].fill(0.);
+        let mut h = Vec::with_capacity(num_partitions * bins);
+        partitions.for_each(|resp| {
+            // FFT of resp.zero_pad(2 * partition_size)
+            temp_input[..resp.len()].copy_from_slice(resp);
+            temp_input[resp.len()..].fill(0.);
             temp_input.real_fft_using(&mut temp_output);
-            resp_fft[..temp_output.len()].copy_from_slice(&temp_output);
-        }
+            h.extend_from_slice(&temp_output);
+        });
+
+        let fdl = vec![Complex::default(); h.len()];
 
-        let fdl = vec![Complex::default(); 2 * RENDER_QUANTUM_SIZE * num_ir_blocks];
-        let out = vec![0.; 2 * RENDER_QUANTUM_SIZE - 1];
+        // in zero latency mode, the frequency domain part starts one partition later, i.e. one
+        // render quantum after the block is complete
+        let output_offset = if zero_latency { render_quantum_size } else { 0 };
+        let out = vec![0.; 2 * partition_size + render_quantum_size];
 
         Self {
-            num_ir_blocks,
+            partition_size,
+            render_quantum_size,
+            direct,
+            history,
             h,
             fdl,
+            input_block: vec![0.; partition_size],
+            fill: 0,
             out,
+            output_offset,
+            tail_length: (num_partitions + 1) * partition_size,
+            silent_samples: 0,
             temp_input,
             temp_output,
         }
     }
 
     fn process(&mut self, input: &[f32], output: &mut [f32]) {
-        self.temp_input[..RENDER_QUANTUM_SIZE].copy_from_slice(input);
-        self.temp_input[RENDER_QUANTUM_SIZE..].fill(0.);
-        self.temp_input.real_fft_using(&mut self.temp_output);
-        let spectrum = &self.temp_output;
-
-        self.fdl
-            .chunks_mut(2 * RENDER_QUANTUM_SIZE)
-            .zip(self.h.chunks(2 * RENDER_QUANTUM_SIZE))
-            .for_each(|(fdl_c, h_c)| {
-                fdl_c
-                    .iter_mut()
-                    .zip(h_c)
-                    .zip(spectrum.iter())
-                    .for_each(|((f, h), s)| *f += h * s)
-            });
-
-        self.temp_output
-            .copy_from_slice(&self.fdl[..self.temp_output.len()]);
-        self.temp_output.real_ifft_using(&mut self.temp_input);
-        let inverse = &self.temp_input;
-        self.out.iter_mut().zip(inverse).for_each(|(o, i)| {
-            *o += i / (2 * RENDER_QUANTUM_SIZE) as f32;
-        });
-
-        output.copy_from_slice(&self.out[..RENDER_QUANTUM_SIZE]);
-
-        roll_zero(&mut self.fdl[..], 2 * RENDER_QUANTUM_SIZE);
-        roll_zero(&mut self.out[..], RENDER_QUANTUM_SIZE);
-    }
+        let partition_size = self.partition_size;
+        let render_quantum_size = self.render_quantum_size;
 
-    fn tail(&mut self, output: &mut AudioRenderQuantum) -> bool {
-        if self.num_ir_blocks == 0 {
-            output.make_silent();
-            return false;
+        // direct convolution of the first partition
+        if self.direct.is_empty() {
+            output.fill(0.);
+        } else {
+            let Self {
+                direct, history, ..
+            } = self;
+            history.copy_within(render_quantum_size.., 0);
+            history[partition_size - 1..].copy_from_slice(input);
+
+            output.iter_mut().enumerate().for_each(|(n, o)| {
+                *o = direct
+                    .iter()
+                    .zip(history[n..n + partition_size].iter().rev())
+                    .map(|(h, x)| h * x)
+                    .sum();
+            });
         }
 
-        self.num_ir_blocks -= 1;
+        // convolution of the other partitions, once the block is complete
+        self.input_block[self.fill..self.fill + render_quantum_size].copy_from_slice(input);
+        self.fill += render_quantum_size;
+
+        if self.fill == partition_size {
+            self.fill = 0;
+
+            if !self.fdl.is_empty() {
+                self.temp_input[..partition_size].copy_from_slice(&self.input_block);
+                self.temp_input[partition_size..].fill(0.);
+                self.temp_input.real_fft_using(&mut self.temp_output);
+                let spectrum = &self.temp_output;
+                let bins = spectrum.len();
+
+                self.fdl
+                    .chunks_mut(bins)
+                    .zip(self.h.chunks(bins))
+                    .for_each(|(fdl_c, h_c)| {
+                        fdl_c
+                            .iter_mut()
+                            .zip(h_c)
+                            .zip(spectrum.iter())
+                            .for_each(|((f, h), s)| *f += h * s)
+                    });
+
+                self.temp_output.copy_from_slice(&self.fdl[..bins]);
+                self.temp_output.real_ifft_using(&mut self.temp_input);
+                let inverse = &self.temp_input;
+                self.out[self.output_offset..]
+                    .iter_mut()
+                    .zip(inverse)
+                    .for_each(|(o, i)| {
+                        *o += i / (2 * partition_size) as f32;
+                    });
 
-        self.temp_output
-            .copy_from_slice(&self.fdl[..self.temp_output.len()]);
-        self.temp_output.real_ifft_using(&mut self.temp_input);
-        let inverse = &self.temp_input;
-        self.out.iter_mut().zip(inverse).for_each(|(o, i)| {
-            *o += i / (2 * RENDER_QUANTUM_SIZE) as f32;
-        });
+                roll_zero(&mut self.fdl[..], bins);
+            }
+        }
 
         output
-            .channel_data_mut(0)
-            .copy_from_slice(&self.out[..RENDER_QUANTUM_SIZE]);
-
-        roll_zero(&mut self.fdl[..], 2 * RENDER_QUANTUM_SIZE);
-        roll_zero(&mut self.out[..], RENDER_QUANTUM_SIZE);
-
-        self.num_ir_blocks > 0
+            .iter_mut()
+            .zip(&self.out[..render_quantum_size])
+            .for_each(|(o, v)| *o += v);
+        roll_zero(&mut self.out[..], render_quantum_size);
     }
 }
 
@@ -375,7 +503,7 @@ impl AudioProcessor for ConvolverRenderer {
         // single input/output node
         let input = &inputs[0];
         let output = &mut outputs[0];
-        output.force_mono();
+        output.ensure_mono();
 
         // handle new impulse response buffer, if any
         if let Ok(msg) = self.receiver.try_recv() {
@@ -393,7 +521,13 @@ impl AudioProcessor for ConvolverRenderer {
 
         // handle tail time
         if input.is_silent() {
-            return convolver.tail(output);
+            if convolver.silent_samples >= convolver.tail_length {
+                output.make_silent();
+                return false;
+            }
+            convolver.silent_samples += convolver.render_quantum_size;
+        } else {
+            convolver.silent_samples = 0;
         }
 
         let mut mono = input.clone();
@@ -513,6 +647,82 @@ mod tests {
         assert_float_eq!(&output[IR_LEN..], &[0.; 512 - IR_LEN][..], abs_all <= 1E-6);
     }
 
+    /// Convolve a deterministic signal with a decaying response of 3000 frames, with the given
+    /// partition size and zero latency setting
+    fn render_partitioned(partition_size: usize, zero_latency: bool) -> Vec<f32> {
+        let sample_rate = 44100.;
+        let length = 8192;
+        let context = OfflineAudioContext::new(1, length, sample_rate);
+
+        let signal: Vec<f32> = (0..4000).map(|i| ((i * i) % 17) as f32 / 8. - 1.).collect();
+        let src = context.create_buffer_source();
+        src.set_buffer(AudioBuffer::from(vec![signal], sample_rate));
+        src.start();
+
+        let ir: Vec<f32> = (0..3000)
+            .map(|i| (i as f32 * 0.1).sin() * (-(i as f32) / 500.).exp())
+            .collect();
+        let options = ConvolverOptions {
+            disable_normalization: true,
+            ..ConvolverOptions::default()
+        };
+        let conv = ConvolverNode::new(&context, options);
+        conv.set_partition_size(partition_size);
+        conv.set_zero_latency(zero_latency);
+        conv.set_buffer(AudioBuffer::from(vec![ir], sample_rate));
+
+        let latency = (conv.latency() * f64::from(sample_rate)).round() as usize;
+        assert_eq!(
+            latency,
+            if zero_latency {
+                0
+            } else {
+                partition_size - 128
+            }
+        );
+
+        src.connect(&conv);
+        conv.connect(&context.destination());
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_partition_sizes() {
+        let reference = render_partitioned(128, false);
+        assert!(reference.iter().any(|v| v.abs() > 1.));
+
+        // only the performance differs
+        for partition_size in [128, 512, 2048] {
+            let output = render_partitioned(partition_size, true);
+            assert_float_eq!(&output[..], &reference[..], abs_all <= 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_partition_latency() {
+        let reference = render_partitioned(128, false);
+
+        // the output is delayed by the partition size minus the render quantum size
+        let output = render_partitioned(512, false);
+        assert_float_eq!(&output[..384], &[0.; 384][..], abs_all <= 0.);
+        assert_float_eq!(&output[384..], &reference[..8192 - 384], abs_all <= 1e-3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_partition_size_not_power_of_two() {
+        let context = OfflineAudioContext::new(1, 128, 44100.);
+        context.create_convolver().set_partition_size(1000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_partition_size_below_render_quantum_size() {
+        let context = OfflineAudioContext::new(1, 128, 44100.);
+        context.create_convolver().set_partition_size(64);
+    }
+
     #[test]
     fn test_resample() {
         let ctx_sample_rate = 44100.;
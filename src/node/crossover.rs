@@ -0,0 +1,529 @@
+//! The crossover control and renderer parts
+use std::f64::consts::FRAC_1_SQRT_2;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::{flush_denormal, MAX_CHANNELS};
+
+use super::biquad_filter::{calculate_coefs, BiquadFilterType, Coefficients};
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Maximum number of bands of a [`CrossoverNode`]
+pub const MAX_CROSSOVER_BANDS: usize = 5;
+
+/// Q of the Butterworth sections, in dB as expected by the lowpass and highpass coefficients
+const BUTTERWORTH_Q_DB: f64 = -3.010_299_956_639_812;
+
+#[track_caller]
+fn assert_valid_frequencies(frequencies: &[f32]) {
+    assert!(
+        (1..MAX_CROSSOVER_BANDS).contains(&frequencies.len()),
+        "NotSupportedError - number of crossover frequencies ({:?}) should be between 1 and {}",
+        frequencies.len(),
+        MAX_CROSSOVER_BANDS - 1
+    );
+    assert!(
+        frequencies.windows(2).all(|w| w[0] < w[1]),
+        "NotSupportedError - crossover frequencies ({:?}) should be strictly increasing",
+        frequencies
+    );
+}
+
+/// Options for constructing a [`CrossoverNode`]
+#[derive(Clone, Debug)]
+pub struct CrossoverOptions {
+    /// Initial crossover frequencies in Hz, strictly increasing, `n` frequencies split the
+    /// signal into `n + 1` bands
+    pub frequencies: Vec<f32>,
+    /// channel config options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for CrossoverOptions {
+    fn default() -> Self {
+        Self {
+            frequencies: vec![200., 2000.],
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `CrossoverNode` splits an audio stream into frequency bands, one output per band
+///
+/// Each crossover frequency separates two bands with a 4th order Linkwitz-Riley lowpass and
+/// highpass pair (two cascaded Butterworth biquads each), i.e. a slope of 24dB per octave and
+/// both bands -6dB at the crossover frequency. The bands are split from the lowest to the highest
+/// frequency, and each band is phase compensated with the allpass response of the crossovers above
+/// it. The sum of all the outputs thus has a flat magnitude response: it is the input through
+/// an allpass filter, which makes the node suitable to build multiband processors.
+///
+/// Output `0` holds the lowest band. `n` crossover frequencies give `n + 1` bands, between 2 and
+/// [`MAX_CROSSOVER_BANDS`].
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_crossover`](crate::context::BaseAudioContext::create_crossover)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// // three bands, below 200Hz, between 200Hz and 2kHz, and above 2kHz
+/// let crossover = context.create_crossover(vec![200., 2000.]);
+///
+/// // compress the low band only
+/// let compressor = context.create_dynamics_compressor();
+/// crossover.connect_at(&compressor, 0, 0);
+/// compressor.connect(&context.destination());
+/// crossover.connect_at(&context.destination(), 1, 0);
+/// crossover.connect_at(&context.destination(), 2, 0);
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&crossover);
+/// osc.start();
+/// ```
+pub struct CrossoverNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Crossover frequencies, from the lowest to the highest
+    frequencies: Vec<AudioParam>,
+}
+
+impl AudioNode for CrossoverNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        self.frequencies.len() + 1
+    }
+}
+
+impl CrossoverNode {
+    /// returns a `CrossoverNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - crossover options
+    ///
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * `options.frequencies` holds less than 1 or more than `MAX_CROSSOVER_BANDS - 1` values
+    /// * `options.frequencies` is not strictly increasing
+    pub fn new<C: BaseAudioContext>(context: &C, options: CrossoverOptions) -> Self {
+        assert_valid_frequencies(&options.frequencies);
+
+        context.register(move |registration| {
+            let CrossoverOptions {
+                frequencies,
+                channel_config,
+            } = options;
+
+            let nyquist = context.sample_rate() / 2.;
+            let (params, procs): (Vec<_>, Vec<_>) = frequencies
+                .iter()
+                .map(|&frequency| {
+                    let descriptor = AudioParamDescriptor {
+                        min_value: 0.,
+                        max_value: nyquist,
+                        default_value: frequency,
+                        automation_rate: AutomationRate::K,
+                    };
+                    let (param, proc) = context.create_audio_param(descriptor, &registration);
+                    param.set_value(frequency);
+                    (param, proc)
+                })
+                .unzip();
+
+            let number_of_crossovers = frequencies.len();
+            let renderer = CrossoverRenderer {
+                frequencies: procs,
+                current_frequencies: vec![f32::NAN; number_of_crossovers],
+                crossovers: (0..number_of_crossovers)
+                    .map(|_| CrossoverFilters::new())
+                    .collect(),
+                allpasses: (0..number_of_crossovers)
+                    .map(|band| {
+                        (band + 1..number_of_crossovers)
+                            .map(|_| Section::new())
+                            .collect()
+                    })
+                    .collect(),
+                rest: vec![0.; context.render_quantum_size()],
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                frequencies: params,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// K-rate [`AudioParam`]s of the crossover frequencies in Hz, from the lowest to the highest
+    ///
+    /// The frequencies should stay increasing when they are automated, otherwise the bands
+    /// overlap. The sum of the outputs remains flat.
+    #[must_use]
+    pub fn frequencies(&self) -> &[AudioParam] {
+        &self.frequencies
+    }
+}
+
+/// Biquad section with the history of each channel
+struct Section {
+    x1: Vec<f64>,
+    x2: Vec<f64>,
+    y1: Vec<f64>,
+    y2: Vec<f64>,
+}
+
+impl Section {
+    fn new() -> Self {
+        Self {
+            x1: Vec::with_capacity(MAX_CHANNELS),
+            x2: Vec::with_capacity(MAX_CHANNELS),
+            y1: Vec::with_capacity(MAX_CHANNELS),
+            y2: Vec::with_capacity(MAX_CHANNELS),
+        }
+    }
+
+    fn resize(&mut self, num_channels: usize) {
+        self.x1.resize(num_channels, 0.);
+        self.x2.resize(num_channels, 0.);
+        self.y1.resize(num_channels, 0.);
+        self.y2.resize(num_channels, 0.);
+    }
+
+    /// `true` when the history of all channels is zero, i.e. the section has no tail left
+    fn is_zero(&self) -> bool {
+        [&self.x1, &self.x2, &self.y1, &self.y2]
+            .iter()
+            .all(|state| state.iter().all(|&v| v == 0.))
+    }
+
+    /// Filter a block of the given channel in place
+    fn filter(&mut self, channel_number: usize, data: &mut [f32], c: &Coefficients) {
+        let mut x1 = self.x1[channel_number];
+        let mut x2 = self.x2[channel_number];
+        let mut y1 = self.y1[channel_number];
+        let mut y2 = self.y2[channel_number];
+
+        data.iter_mut().for_each(|v| {
+            let x = f64::from(*v);
+            let y = c.b0 * x + c.b1 * x1 + c.b2 * x2 - c.a1 * y1 - c.a2 * y2;
+            x2 = x1;
+            x1 = x;
+            y2 = y1;
+            y1 = y;
+            *v = y as f32;
+        });
+
+        // store channel state for next block, flushing the decayed values to zero
+        self.x1[channel_number] = flush_denormal(x1);
+        self.x2[channel_number] = flush_denormal(x2);
+        self.y1[channel_number] = flush_denormal(y1);
+        self.y2[channel_number] = flush_denormal(y2);
+    }
+}
+
+/// Linkwitz-Riley lowpass and highpass pair of a crossover frequency
+struct CrossoverFilters {
+    lowpass: Coefficients,
+    highpass: Coefficients,
+    /// Sum of the lowpass and highpass responses, applied to the bands below
+    allpass: Coefficients,
+    lowpass_sections: [Section; 2],
+    highpass_sections: [Section; 2],
+}
+
+impl CrossoverFilters {
+    fn new() -> Self {
+        Self {
+            lowpass: Coefficients::default(),
+            highpass: Coefficients::default(),
+            allpass: Coefficients::default(),
+            lowpass_sections: [Section::new(), Section::new()],
+            highpass_sections: [Section::new(), Section::new()],
+        }
+    }
+
+    fn set_frequency(&mut self, sample_rate: f64, frequency: f64) {
+        let coefs = |type_, q| calculate_coefs(type_, sample_rate, frequency, 0., q);
+        self.lowpass = coefs(BiquadFilterType::Lowpass, BUTTERWORTH_Q_DB);
+        self.highpass = coefs(BiquadFilterType::Highpass, BUTTERWORTH_Q_DB);
+        // the sum of the squared Butterworth lowpass and highpass is a second order allpass
+        self.allpass = coefs(BiquadFilterType::Allpass, FRAC_1_SQRT_2);
+    }
+
+    fn sections_mut(&mut self) -> impl Iterator<Item = &mut Section> {
+        self.lowpass_sections
+            .iter_mut()
+            .chain(self.highpass_sections.iter_mut())
+    }
+}
+
+/// `CrossoverRenderer` represents the rendering part of `CrossoverNode`
+struct CrossoverRenderer {
+    /// Crossover frequencies, from the lowest to the highest
+    frequencies: Vec<AudioParamId>,
+    /// Frequencies of the current coefficients
+    current_frequencies: Vec<f32>,
+    /// Filters of each crossover frequency
+    crossovers: Vec<CrossoverFilters>,
+    /// Phase compensation of each band, one allpass per crossover above the band
+    allpasses: Vec<Vec<Section>>,
+    /// Part of the signal above the crossovers processed so far
+    rest: Vec<f32>,
+}
+
+impl CrossoverRenderer {
+    fn sections_mut(&mut self) -> impl Iterator<Item = &mut Section> {
+        self.crossovers
+            .iter_mut()
+            .flat_map(CrossoverFilters::sections_mut)
+            .chain(self.allpasses.iter_mut().flatten())
+    }
+}
+
+impl AudioProcessor for CrossoverRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input node
+        let input = &inputs[0];
+
+        if input.is_silent() && self.sections_mut().all(|s| s.is_zero()) {
+            outputs.iter_mut().for_each(AudioRenderQuantum::make_silent);
+            return false;
+        }
+
+        // update the coefficients when a frequency changed
+        let sample_rate = f64::from(scope.sample_rate);
+        self.frequencies
+            .iter()
+            .zip(self.current_frequencies.iter_mut())
+            .zip(self.crossovers.iter_mut())
+            .for_each(|((id, current), crossover)| {
+                let frequency = params.get(id)[0];
+                if frequency != *current {
+                    *current = frequency;
+                    crossover.set_frequency(sample_rate, f64::from(frequency));
+                }
+            });
+
+        let number_of_channels = input.number_of_channels();
+        self.sections_mut()
+            .for_each(|s| s.resize(number_of_channels));
+        outputs
+            .iter_mut()
+            .for_each(|o| o.set_number_of_channels(number_of_channels));
+
+        let Self {
+            crossovers,
+            allpasses,
+            rest,
+            ..
+        } = self;
+        let (last, bands) = outputs.split_last_mut().unwrap();
+
+        for channel_number in 0..number_of_channels {
+            rest.copy_from_slice(input.channel_data(channel_number));
+
+            for (band, output) in bands.iter_mut().enumerate() {
+                let (lower, upper) = crossovers.split_at_mut(band + 1);
+                let crossover = &mut lower[band];
+
+                // lowpass into the band, highpass to the next bands
+                let data = output.channel_data_mut(channel_number);
+                data.copy_from_slice(rest);
+                let CrossoverFilters {
+                    lowpass,
+                    highpass,
+                    lowpass_sections,
+                    highpass_sections,
+                    ..
+                } = crossover;
+                lowpass_sections
+                    .iter_mut()
+                    .for_each(|s| s.filter(channel_number, data, lowpass));
+                highpass_sections
+                    .iter_mut()
+                    .for_each(|s| s.filter(channel_number, rest, highpass));
+
+                // phase compensation of the crossovers above
+                allpasses[band]
+                    .iter_mut()
+                    .zip(upper.iter())
+                    .for_each(|(s, c)| s.filter(channel_number, data, &c.allpass));
+            }
+
+            last.channel_data_mut(channel_number).copy_from_slice(rest);
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioScheduledSourceNode, NoiseSourceNode, NoiseSourceOptions};
+
+    use super::*;
+
+    const SAMPLE_RATE: usize = 48_000;
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let crossover = context.create_crossover(vec![100., 1000., 10000.]);
+        assert_eq!(crossover.number_of_outputs(), 4);
+        assert_eq!(crossover.frequencies().len(), 3);
+        assert_float_eq!(crossover.frequencies()[1].value(), 1000., abs <= 0.);
+    }
+
+    /// Render one second of seeded white noise, through a crossover with all the bands summed
+    /// when `frequencies` is given
+    fn render_noise(frequencies: Option<Vec<f32>>) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, SAMPLE_RATE, SAMPLE_RATE as f32);
+
+        let options = NoiseSourceOptions {
+            seed: Some(42),
+            ..NoiseSourceOptions::default()
+        };
+        let noise = NoiseSourceNode::new(&context, options);
+        noise.start();
+
+        match frequencies {
+            Some(frequencies) => {
+                let crossover = context.create_crossover(frequencies);
+                noise.connect(&crossover);
+                for band in 0..crossover.number_of_outputs() {
+                    crossover.connect_at(&context.destination(), band, 0);
+                }
+            }
+            None => {
+                noise.connect(&context.destination());
+            }
+        }
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    /// Rms of one second of a sine at `frequency` in each band of a 3 band crossover
+    fn band_rms(frequency: f32) -> Vec<f32> {
+        // quad output, the last channel is unused
+        let context = OfflineAudioContext::new(4, SAMPLE_RATE, SAMPLE_RATE as f32);
+
+        let crossover = context.create_crossover(vec![200., 2000.]);
+        let merger = context.create_channel_merger(4);
+        for band in 0..3 {
+            crossover.connect_at(&merger, band, band);
+        }
+        merger.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(frequency);
+        osc.connect(&crossover);
+        osc.start();
+
+        let output = context.start_rendering_sync();
+        (0..3)
+            .map(|band| {
+                // skip the onset transient
+                let data = &output.get_channel_data(band)[SAMPLE_RATE / 2..];
+                (data.iter().map(|v| v * v).sum::<f32>() / data.len() as f32).sqrt()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_summed_bands_are_flat() {
+        let reference = crate::analysis::power_spectrum(&render_noise(None));
+        let summed = crate::analysis::power_spectrum(&render_noise(Some(vec![200., 2000.])));
+
+        // compare the energy in third octave bands over the whole spectrum
+        let mut low = 25.;
+        while low < 20_000. {
+            let high = low * 2_f32.powf(1. / 3.);
+            let energy =
+                |spectrum: &[f32]| -> f32 { spectrum[low as usize..high as usize].iter().sum() };
+            let ratio_db = 10. * (energy(&summed) / energy(&reference)).log10();
+            assert!(
+                ratio_db.abs() < 0.5,
+                "summed bands deviate by {} dB around {} Hz",
+                ratio_db,
+                low
+            );
+            low = high;
+        }
+    }
+
+    #[test]
+    fn test_band_dominance() {
+        let rms = band_rms(50.);
+        assert!(rms[0] > 0.6 && rms[1] < 0.1 * rms[0] && rms[2] < 0.01 * rms[0]);
+
+        let rms = band_rms(630.);
+        assert!(rms[1] > 0.6 && rms[0] < 0.1 * rms[1] && rms[2] < 0.1 * rms[1]);
+
+        let rms = band_rms(8000.);
+        assert!(rms[2] > 0.6 && rms[0] < 0.01 * rms[2] && rms[1] < 0.1 * rms[2]);
+    }
+
+    #[test]
+    fn test_crossover_frequency_is_half_amplitude() {
+        // both bands are -6dB at the crossover frequency, the rms of a full scale sine is 0.707
+        let rms = band_rms(2000.);
+        assert_float_eq!(rms[1], 0.5 * FRAC_1_SQRT_2 as f32, abs <= 0.02);
+        assert_float_eq!(rms[2], 0.5 * FRAC_1_SQRT_2 as f32, abs <= 0.02);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_too_many_bands() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let _ = context.create_crossover(vec![100., 200., 400., 800., 1600.]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_no_crossover() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let _ = context.create_crossover(vec![]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unsorted_frequencies() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let _ = context.create_crossover(vec![2000., 200.]);
+    }
+}
@@ -1,13 +1,23 @@
-use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::context::{
+    AudioContextRegistration, AudioNodeId, AudioParamId, BaseAudioContext, PatchNodeType, Patchable,
+};
 use crate::param::{AudioParam, AudioParamDescriptor};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
-use crate::RENDER_QUANTUM_SIZE;
+use crate::{AtomicF32, NoteDivision};
 
-use super::{AudioNode, ChannelConfig, ChannelConfigOptions, ChannelInterpretation};
+use super::{
+    assert_valid_connection, AudioNode, ChannelConfig, ChannelConfigOptions, ChannelInterpretation,
+};
 
 use std::cell::{Cell, RefCell, RefMut};
+use std::error::Error;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Duration (in seconds) of the crossfade to a delay time set with
+/// [`DelayNode::set_delay_note`]
+const NOTE_CROSSFADE_TIME: f64 = 0.05;
 
 /// Options for constructing a [`DelayNode`]
 // dictionary DelayOptions : AudioNodeOptions {
@@ -15,6 +25,11 @@ use std::sync::atomic::{AtomicU64, Ordering};
 //   double delayTime = 0;
 // };
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct DelayOptions {
     pub max_delay_time: f64,
     pub delay_time: f64,
@@ -31,6 +46,17 @@ impl Default for DelayOptions {
     }
 }
 
+/// Crossfade of the reader from a previous delay time to the current one
+#[derive(Copy, Clone, Debug)]
+struct DelayCrossfade {
+    /// delay time (in seconds) faded out
+    from: f64,
+    /// number of frames already faded
+    position: usize,
+    /// total number of frames of the crossfade
+    length: usize,
+}
+
 #[derive(Copy, Clone, Debug, Default)]
 struct PlaybackInfo {
     prev_block_index: usize,
@@ -100,6 +126,8 @@ pub struct DelayNode {
     writer_registration: AudioContextRegistration,
     delay_time: AudioParam,
     channel_config: ChannelConfig,
+    /// delay time the reader should crossfade to, NaN if none
+    crossfade_target: Arc<AtomicF32>,
 }
 
 impl AudioNode for DelayNode {
@@ -131,15 +159,7 @@ impl AudioNode for DelayNode {
         output: usize,
         input: usize,
     ) -> &'a dyn AudioNode {
-        if self.context() != dest.context() {
-            panic!("InvalidAccessError: Attempting to connect nodes from different contexts");
-        }
-        if self.number_of_outputs() <= output {
-            panic!("IndexSizeError: output port {} is out of bounds", output);
-        }
-        if dest.number_of_inputs() <= input {
-            panic!("IndexSizeError: input port {} is out of bounds", input);
-        }
+        assert_valid_connection(self, dest, output, input);
 
         self.context().connect(
             self.reader_registration.id(),
@@ -151,6 +171,26 @@ impl AudioNode for DelayNode {
         dest
     }
 
+    /// Connect a specific output of this AudioNode to a specific input of another node,
+    /// rejecting the connection if it would create a cycle that does not contain a DelayNode.
+    fn try_connect_at<'a>(
+        &self,
+        dest: &'a dyn AudioNode,
+        output: usize,
+        input: usize,
+    ) -> Result<&'a dyn AudioNode, Box<dyn Error + Send + Sync>> {
+        assert_valid_connection(self, dest, output, input);
+
+        self.context().try_connect(
+            self.reader_registration.id(),
+            dest.registration().id(),
+            output,
+            input,
+        )?;
+
+        Ok(dest)
+    }
+
     /// Disconnects all outputs of the AudioNode that go to a specific destination AudioNode.
     fn disconnect_from<'a>(&self, dest: &'a dyn AudioNode) -> &'a dyn AudioNode {
         if self.context() != dest.context() {
@@ -167,6 +207,35 @@ impl AudioNode for DelayNode {
     fn disconnect(&self) {
         self.context().disconnect(self.reader_registration.id());
     }
+
+    /// Bypass the delay line, or enable it again.
+    ///
+    /// The bypass is applied on the reader end, which then outputs the input of the node
+    /// undelayed instead of the ring buffer contents. The writer end keeps recording so the
+    /// delay line holds up to date history when it is enabled again.
+    ///
+    /// A DelayNode that is part of a cycle outputs silence while bypassed, as a sub-quantum
+    /// path through the cycle is not possible.
+    fn set_bypass(&self, bypassed: bool) {
+        self.context()
+            .set_bypass(self.reader_registration.id(), bypassed);
+    }
+
+    /// Mute the output of the delay line, or unmute it again.
+    ///
+    /// The mute is applied on the reader end, see [`AudioNode::set_muted`].
+    fn set_muted(&self, muted: bool) {
+        self.context()
+            .set_muted(self.reader_registration.id(), muted);
+    }
+
+    /// Solo the delay line, or unsolo it again.
+    ///
+    /// The solo is applied on the reader end, see [`AudioNode::set_soloed`].
+    fn set_soloed(&self, soloed: bool) {
+        self.context()
+            .set_soloed(self.reader_registration.id(), soloed);
+    }
 }
 
 impl DelayNode {
@@ -177,6 +246,7 @@ impl DelayNode {
     /// Panics when the max delay value is smaller than zero or langer than three minutes.
     pub fn new<C: BaseAudioContext>(context: &C, options: DelayOptions) -> Self {
         let sample_rate = context.sample_rate() as f64;
+        let patch_options = options.clone();
 
         // Specifies the maximum delay time in seconds allowed for the delay line.
         // If specified, this value MUST be greater than zero and less than three
@@ -192,17 +262,17 @@ impl DelayNode {
         // than quantum duration, the node, if found in a loop, will gracefully
         // fallback to the clamped behavior. (e.g. we ensure that ring buffer size
         // is always >= 2)
-        let quantum_duration = 1. / sample_rate * RENDER_QUANTUM_SIZE as f64;
+        let render_quantum_size = context.render_quantum_size();
+        let quantum_duration = 1. / sample_rate * render_quantum_size as f64;
         let max_delay_time = options.max_delay_time.max(quantum_duration);
 
         // allocate large enough buffer to store all delayed samples
         //
         // we add 1 here so that in edge cases where num_samples is a multiple of
-        // RENDER_QUANTUM_SIZE and delay_time == max_delay_time we are sure to
+        // the render quantum size and delay_time == max_delay_time we are sure to
         // enough room for history. (see. test_max_delay_multiple_of_quantum_size)
         let num_samples = max_delay_time * sample_rate + 1.;
-        let num_quanta =
-            (num_samples.ceil() as usize + RENDER_QUANTUM_SIZE - 1) / RENDER_QUANTUM_SIZE;
+        let num_quanta = (num_samples.ceil() as usize).div_ceil(render_quantum_size);
         let ring_buffer = Vec::with_capacity(num_quanta);
 
         let shared_ring_buffer = Rc::new(RefCell::new(ring_buffer));
@@ -217,6 +287,9 @@ impl DelayNode {
         let latest_frame_written = Rc::new(AtomicU64::new(u64::MAX));
         let latest_frame_written_clone = latest_frame_written.clone();
 
+        let crossfade_target = Arc::new(AtomicF32::new(f32::NAN));
+        let crossfade_target_clone = crossfade_target.clone();
+
         let node = context.register(move |writer_registration| {
             let node = context.register(move |reader_registration| {
                 let param_opts = AudioParamDescriptor {
@@ -237,6 +310,10 @@ impl DelayNode {
                     in_cycle: false,
                     last_written_index_checked: None,
                     latest_frame_written: latest_frame_written_clone,
+                    playback_infos: vec![PlaybackInfo::default(); render_quantum_size],
+                    crossfade_target: crossfade_target_clone,
+                    crossfade: None,
+                    previous_delay: None,
                 };
 
                 let node = DelayNode {
@@ -244,6 +321,7 @@ impl DelayNode {
                     writer_registration,
                     channel_config: options.channel_config.into(),
                     delay_time: param,
+                    crossfade_target,
                 };
 
                 (node, Box::new(reader_render))
@@ -267,6 +345,14 @@ impl DelayNode {
         context.base().mark_cycle_breaker(&node.writer_registration);
         context.base().connect(writer_id, reader_id, 0, 0);
 
+        let channel_config = node.channel_config.clone();
+        context.base().record_patch_node(&node, move || {
+            PatchNodeType::Delay(DelayOptions {
+                channel_config: (&channel_config).into(),
+                ..patch_options.clone()
+            })
+        });
+
         node
     }
 
@@ -274,6 +360,37 @@ impl DelayNode {
     pub fn delay_time(&self) -> &AudioParam {
         &self.delay_time
     }
+
+    /// Set the delay time to the duration of a note at the given tempo, in beats per minute
+    ///
+    /// E.g. an eighth note at 120 BPM results in a delay of `60 / 120 / 2 = 0.25` seconds. The
+    /// value is clamped to the maximum delay time of the node.
+    ///
+    /// Contrary to changing the [`delay_time`](Self::delay_time) directly, which jumps or
+    /// glides the read position through the delay line (and thus alters the pitch of the
+    /// delayed signal), the output crossfades over 50ms from the previous delay time to the new
+    /// one. This makes it possible to follow tempo changes without artifacts.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the tempo is not strictly positive and finite
+    pub fn set_delay_note(&self, division: NoteDivision, bpm: f64) {
+        let delay_time = (division.duration(bpm) as f32).min(self.delay_time.max_value());
+        self.crossfade_target.store(delay_time);
+        self.delay_time.set_value(delay_time);
+    }
+}
+
+impl Patchable for DelayNode {
+    fn patch_params(&self) -> Vec<&AudioParam> {
+        vec![&self.delay_time]
+    }
+
+    fn patch_output_id(&self) -> AudioNodeId {
+        self.reader_registration.id()
+    }
 }
 
 struct DelayWriter {
@@ -347,6 +464,10 @@ impl AudioProcessor for DelayWriter {
         // side as Reader do not access the "real" input
         self.check_ring_buffer_up_down_mix(&input);
 
+        // The writer end passes its input to the reader, which ignores it unless it is
+        // bypassed. Cloning a quantum is cheap as the channels are reference counted.
+        *output = input.clone();
+
         // populate ring buffer
         let mut buffer = self.ring_buffer.borrow_mut();
         buffer[self.index] = input;
@@ -356,10 +477,6 @@ impl AudioProcessor for DelayWriter {
         self.latest_frame_written
             .store(scope.current_frame, Ordering::SeqCst);
 
-        // The writer end does not produce output,
-        // clear the buffer so that it can be re-used
-        output.make_silent();
-
         // let the node be decommisioned if it has no input left
         false
     }
@@ -397,6 +514,13 @@ struct DelayReader {
     last_written_index: Rc<Cell<Option<usize>>>,
     // local copy of shared `last_written_index` so as to avoid render ordering issues
     last_written_index_checked: Option<usize>,
+    // playback infos of each frame of the render quantum
+    playback_infos: Vec<PlaybackInfo>,
+    // delay time set with `set_delay_note`, to crossfade to once the param reaches it
+    crossfade_target: Arc<AtomicF32>,
+    crossfade: Option<DelayCrossfade>,
+    // delay time of the last frame of the previous render quantum
+    previous_delay: Option<f32>,
 }
 
 // SAFETY:
@@ -444,24 +568,42 @@ impl AudioProcessor for DelayReader {
         // compute all playback infos for this block
         let delay = params.get(&self.delay_time);
         let sample_rate = scope.sample_rate as f64;
-        let dt = 1. / sample_rate;
-        let quantum_duration = RENDER_QUANTUM_SIZE as f64 * dt;
+
+        // start a crossfade when the delay time set with `set_delay_note` is reached
+        let crossfade_target = self.crossfade_target.load();
+        let current_delay = delay[delay.len() - 1];
+        if current_delay == crossfade_target {
+            self.crossfade_target.store(f32::NAN);
+            if let Some(previous_delay) = self.previous_delay {
+                if previous_delay != current_delay {
+                    self.crossfade = Some(DelayCrossfade {
+                        from: f64::from(previous_delay),
+                        position: 0,
+                        length: (NOTE_CROSSFADE_TIME * sample_rate) as usize,
+                    });
+                }
+            }
+        }
+        self.previous_delay = Some(current_delay);
+
+        let render_quantum_size = scope.render_quantum_size;
         let ring_size = ring_buffer.len() as i32;
         let ring_index = self.index as i32;
-        let mut playback_infos = [PlaybackInfo::default(); RENDER_QUANTUM_SIZE];
+        let in_cycle = self.in_cycle;
+        let playback_infos = &mut self.playback_infos;
 
         if delay.len() == 1 {
             playback_infos[0] = Self::get_playback_infos(
                 f64::from(delay[0]),
-                self.in_cycle,
+                in_cycle,
                 0.,
-                quantum_duration,
+                render_quantum_size,
                 sample_rate,
                 ring_size,
                 ring_index,
             );
 
-            for i in 1..RENDER_QUANTUM_SIZE {
+            for i in 1..render_quantum_size {
                 let PlaybackInfo {
                     prev_block_index,
                     prev_frame_index,
@@ -471,7 +613,7 @@ impl AudioProcessor for DelayReader {
                 let mut prev_block_index = prev_block_index;
                 let mut prev_frame_index = prev_frame_index + 1;
 
-                if prev_frame_index >= RENDER_QUANTUM_SIZE {
+                if prev_frame_index >= render_quantum_size {
                     prev_block_index = (prev_block_index + 1) % ring_buffer.len();
                     prev_frame_index = 0;
                 }
@@ -490,9 +632,9 @@ impl AudioProcessor for DelayReader {
                 .for_each(|(index, (&d, infos))| {
                     *infos = Self::get_playback_infos(
                         f64::from(d),
-                        self.in_cycle,
+                        in_cycle,
                         index as f64,
-                        quantum_duration,
+                        render_quantum_size,
                         sample_rate,
                         ring_size,
                         ring_index,
@@ -526,7 +668,7 @@ impl AudioProcessor for DelayReader {
                     let mut next_block_index = prev_block_index;
                     let mut next_frame_index = prev_frame_index + 1;
 
-                    if next_frame_index >= RENDER_QUANTUM_SIZE {
+                    if next_frame_index >= render_quantum_size {
                         next_block_index = (next_block_index + 1) % ring_buffer.len();
                         next_frame_index = 0;
                     }
@@ -559,6 +701,47 @@ impl AudioProcessor for DelayReader {
                 });
         }
 
+        // fade in the current delay time while fading out the previous one
+        if let Some(crossfade) = self.crossfade.as_mut() {
+            for (channel_number, output_channel) in output.channels_mut().iter_mut().enumerate() {
+                output_channel
+                    .iter_mut()
+                    .enumerate()
+                    .take(crossfade.length.saturating_sub(crossfade.position))
+                    .for_each(|(index, o)| {
+                        let infos = Self::get_playback_infos(
+                            crossfade.from,
+                            in_cycle,
+                            index as f64,
+                            render_quantum_size,
+                            sample_rate,
+                            ring_size,
+                            ring_index,
+                        );
+                        let previous = Self::read_sample(
+                            &ring_buffer,
+                            channel_number,
+                            infos,
+                            render_quantum_size,
+                        );
+
+                        // equal power, the delayed signals are not correlated
+                        let ratio = (crossfade.position + index) as f32 / crossfade.length as f32;
+                        let (gain, previous_gain) = (ratio * std::f32::consts::FRAC_PI_2).sin_cos();
+                        *o = (*o).mul_add(gain, previous * previous_gain);
+
+                        if o.is_normal() {
+                            is_actively_processing = true;
+                        }
+                    });
+            }
+
+            crossfade.position += render_quantum_size;
+            if crossfade.position >= crossfade.length {
+                self.crossfade = None;
+            }
+        }
+
         if !is_actively_processing {
             output.make_silent();
         }
@@ -584,12 +767,42 @@ impl AudioProcessor for DelayReader {
 }
 
 impl DelayReader {
+    /// Interpolated sample of the ring buffer at the given playback position
+    #[inline(always)]
+    fn read_sample(
+        ring_buffer: &[AudioRenderQuantum],
+        channel_number: usize,
+        infos: PlaybackInfo,
+        render_quantum_size: usize,
+    ) -> f32 {
+        let PlaybackInfo {
+            prev_block_index,
+            prev_frame_index,
+            k,
+        } = infos;
+
+        let mut next_block_index = prev_block_index;
+        let mut next_frame_index = prev_frame_index + 1;
+
+        if next_frame_index >= render_quantum_size {
+            next_block_index = (next_block_index + 1) % ring_buffer.len();
+            next_frame_index = 0;
+        }
+
+        let prev_sample =
+            ring_buffer[prev_block_index].channel_data(channel_number)[prev_frame_index];
+        let next_sample =
+            ring_buffer[next_block_index].channel_data(channel_number)[next_frame_index];
+
+        (1. - k).mul_add(prev_sample, k * next_sample)
+    }
+
     #[inline(always)]
     fn get_playback_infos(
         delay: f64,
         in_cycle: bool,
         sample_index: f64,
-        quantum_duration: f64,
+        render_quantum_size: usize,
         sample_rate: f64,
         ring_size: i32,
         ring_index: i32,
@@ -597,7 +810,7 @@ impl DelayReader {
         // param is already clamped to max_delay_time internally, so it is
         // safe to only check lower boundary
         let clamped_delay = if in_cycle {
-            delay.max(quantum_duration)
+            delay.max(render_quantum_size as f64 / sample_rate)
         } else {
             delay
         };
@@ -606,7 +819,7 @@ impl DelayReader {
         let position = sample_index - num_samples;
         let position_floored = position.floor();
         // find address of the frame in the ring buffer just before `position`
-        let num_frames = RENDER_QUANTUM_SIZE as i32;
+        let num_frames = render_quantum_size as i32;
 
         // offset of the block in which the target sample is recorded
         // we need to be `float` here so that `floor()` behaves as expected
@@ -649,6 +862,7 @@ mod tests {
 
     use crate::context::OfflineAudioContext;
     use crate::node::AudioScheduledSourceNode;
+    use crate::NoteValue;
 
     use super::*;
 
@@ -680,6 +894,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_bypass() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 256, sample_rate);
+
+        let delay = context.create_delay(2.);
+        delay.delay_time.set_value(131. / sample_rate);
+        delay.set_bypass(true);
+        delay.connect(&context.destination());
+
+        let mut dirac = context.create_buffer(1, 1, sample_rate);
+        dirac.copy_to_channel(&[1.], 0);
+
+        let src = context.create_buffer_source();
+        src.connect(&delay);
+        src.set_buffer(dirac);
+        src.start_at(0.);
+
+        let result = context.start_rendering_sync();
+        let channel = result.get_channel_data(0);
+
+        // the dirac passes through undelayed, the ring buffer contents are not played
+        let mut expected = vec![0.; 256];
+        expected[0] = 1.;
+
+        assert_float_eq!(channel[..], expected[..], abs_all <= 0.);
+    }
+
     #[test]
     fn test_sub_sample_accurate() {
         {
@@ -813,6 +1055,41 @@ mod tests {
         assert_float_eq!(channel_right[..], expected_right[..], abs_all <= 1e-5);
     }
 
+    #[test]
+    fn test_max_mode_keeps_input_channels() {
+        let delay_in_samples = 128.;
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(6, 2 * 128, sample_rate);
+
+        // default channel count is 2 in max mode, a 6 channel input is not down-mixed
+        let delay = context.create_delay(2.);
+        delay.delay_time.set_value(delay_in_samples / sample_rate);
+        delay.connect(&context.destination());
+
+        let mut six_chan_dirac = context.create_buffer(6, 256, sample_rate);
+        // different channels
+        for i in 0..6 {
+            let mut dirac = vec![0.; i + 1];
+            dirac[i] = 1.;
+            six_chan_dirac.copy_to_channel(&dirac, i);
+        }
+
+        let src = context.create_buffer_source();
+        src.connect(&delay);
+        src.set_buffer(six_chan_dirac);
+        src.start_at(0.);
+
+        let result = context.start_rendering_sync();
+        assert_eq!(result.number_of_channels(), 6);
+
+        for i in 0..6 {
+            let channel = result.get_channel_data(i);
+            let mut expected = vec![0.; 256];
+            expected[128 + i] = 1.;
+            assert_float_eq!(channel[..], expected[..], abs_all <= 1e-5);
+        }
+    }
+
     #[test]
     fn test_node_stays_alive_long_enough() {
         // make sure there are no hidden order problem
@@ -849,6 +1126,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tail_drains_after_source_stops() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 8 * 128, sample_rate);
+
+        // A feedback delay, the echoes keep coming after the source has stopped and every
+        // handle is dropped, until the tail has decayed
+        {
+            let delay = context.create_delay(1.);
+            delay.delay_time.set_value(256. / sample_rate);
+            delay.connect(&context.destination());
+
+            let feedback = context.create_gain();
+            feedback.gain().set_value(0.5);
+            delay.connect(&feedback);
+            feedback.connect(&delay);
+
+            // emit a single render quantum
+            let src = context.create_constant_source();
+            src.connect(&delay);
+            src.start_at(0.);
+            src.stop_at(128. / sample_rate as f64);
+        } // drop all nodes, trigger dynamic lifetimes
+
+        let result = context.start_rendering_sync();
+        let channel = result.get_channel_data(0);
+
+        let mut expected = vec![0.; 8 * 128];
+        expected[2 * 128..3 * 128].fill(1.);
+        expected[4 * 128..5 * 128].fill(0.5);
+        expected[6 * 128..7 * 128].fill(0.25);
+
+        assert_float_eq!(channel[..], expected[..], abs_all <= 1e-5);
+    }
+
+    #[test]
+    fn test_tail_drains_while_source_is_alive() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 4 * 128, sample_rate);
+
+        // the source is stopped but its handle is kept, the delay handle is dropped
+        let src = context.create_constant_source();
+        {
+            let delay = context.create_delay(1.);
+            delay.delay_time.set_value(256. / sample_rate);
+            delay.connect(&context.destination());
+            src.connect(&delay);
+        }
+        src.start_at(0.);
+        src.stop_at(128. / sample_rate as f64);
+
+        let result = context.start_rendering_sync();
+        let channel = result.get_channel_data(0);
+
+        let mut expected = vec![0.; 4 * 128];
+        expected[2 * 128..3 * 128].fill(1.);
+
+        assert_float_eq!(channel[..], expected[..], abs_all <= 1e-5);
+    }
+
     #[test]
     fn test_subquantum_delay() {
         for i in 0..128 {
@@ -1038,4 +1375,94 @@ mod tests {
 
         assert_float_eq!(channel[..], expected[..], abs_all <= 1e-5);
     }
+
+    #[test]
+    fn test_delay_note() {
+        let sample_rate = 8000.;
+        let context = OfflineAudioContext::new(1, 4000, sample_rate);
+
+        let delay = context.create_delay(1.);
+        delay.set_delay_note(NoteDivision::Straight(NoteValue::Eighth), 120.);
+        assert_float_eq!(delay.delay_time().value(), 0.25, abs <= 0.);
+        delay.connect(&context.destination());
+
+        let mut dirac = context.create_buffer(1, 1, sample_rate);
+        dirac.copy_to_channel(&[1.], 0);
+
+        let src = context.create_buffer_source();
+        src.connect(&delay);
+        src.set_buffer(dirac);
+        src.start();
+
+        let result = context.start_rendering_sync();
+        let mut expected = vec![0.; 4000];
+        expected[2000] = 1.;
+        assert_float_eq!(result.get_channel_data(0)[..], expected[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_delay_note_clamped() {
+        let context = OfflineAudioContext::new(1, 128, 8000.);
+        let delay = context.create_delay(1.);
+
+        // a dotted half note at 60 BPM lasts 3 seconds
+        delay.set_delay_note(NoteDivision::Dotted(NoteValue::Half), 60.);
+        assert_float_eq!(delay.delay_time().value(), 1., abs <= 0.);
+
+        delay.set_delay_note(NoteDivision::Triplet(NoteValue::Quarter), 120.);
+        assert_float_eq!(delay.delay_time().value(), 1. / 3., abs <= 1e-7);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_delay_note_invalid_bpm() {
+        let context = OfflineAudioContext::new(1, 128, 8000.);
+        let delay = context.create_delay(1.);
+        delay.set_delay_note(NoteDivision::Straight(NoteValue::Quarter), -1.);
+    }
+
+    #[test]
+    fn test_delay_note_crossfade() {
+        // delay a slow ramp and change the tempo after 1s, return the largest step between
+        // successive output samples
+        let render = |note: bool| {
+            let sample_rate = 8000.;
+            let length = 16_000;
+            let mut context = OfflineAudioContext::new(1, length, sample_rate);
+
+            let delay = context.create_delay(2.);
+            delay.set_delay_note(NoteDivision::Straight(NoteValue::Quarter), 120.);
+            delay.connect(&context.destination());
+
+            let ramp: Vec<f32> = (0..length).map(|i| i as f32 / length as f32).collect();
+            let mut buffer = context.create_buffer(1, length, sample_rate);
+            buffer.copy_to_channel(&ramp, 0);
+
+            let src = context.create_buffer_source();
+            src.connect(&delay);
+            src.set_buffer(buffer);
+            src.start();
+
+            context.suspend_at(8000, move |_| {
+                if note {
+                    delay.set_delay_note(NoteDivision::Straight(NoteValue::Quarter), 60.);
+                } else {
+                    delay.delay_time().set_value(1.);
+                }
+            });
+
+            let result = context.start_rendering_sync();
+            let output = result.get_channel_data(0);
+            // skip the start of the delayed signal
+            output[4001..]
+                .windows(2)
+                .map(|w| (w[1] - w[0]).abs())
+                .fold(0., f32::max)
+        };
+
+        // changing the param jumps back by half a second of the ramp
+        assert!(render(false) > 0.2);
+        // the crossfade spreads this over 50ms
+        assert!(render(true) < 0.01);
+    }
 }
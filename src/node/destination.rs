@@ -1,5 +1,10 @@
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
 use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::AtomicF32;
 
 use super::{
     AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
@@ -11,7 +16,127 @@ pub struct AudioDestinationNode {
     channel_config: ChannelConfig,
 }
 
-struct DestinationRenderer {}
+/// Peak level and clipping flag of the destination, written by the render thread
+#[derive(Debug)]
+pub(crate) struct DestinationMeter {
+    /// Highest absolute sample value since the last read
+    peak: AtomicF32,
+    /// Set when a sample exceeded `[-1, 1]`, until the next read
+    clipped: AtomicBool,
+    /// Measure the peak of the oversampled signal instead of the samples
+    true_peak: AtomicBool,
+}
+
+impl Default for DestinationMeter {
+    fn default() -> Self {
+        Self {
+            peak: AtomicF32::new(0.),
+            clipped: AtomicBool::new(false),
+            true_peak: AtomicBool::new(false),
+        }
+    }
+}
+
+impl DestinationMeter {
+    fn record(&self, peak: f32) {
+        if peak > self.peak.load() {
+            self.peak.store(peak);
+        }
+        if peak > 1. {
+            self.clipped.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Oversampling factor of the true peak meter
+const TRUE_PEAK_OVERSAMPLING: usize = 4;
+/// Number of taps of each phase of the true peak interpolation filter
+const TRUE_PEAK_TAPS: usize = 12;
+
+/// Peak detector of the signal reconstructed in between the samples, cf. ITU-R BS.1770 annex 2
+///
+/// The signal is 4x oversampled with a 48 taps polyphase interpolation filter, which under-reads
+/// the inter-sample peaks by less than 0.5 dB up to a quarter of the sample rate.
+struct TruePeakDetector {
+    /// Hann windowed sinc, split into its phases and reversed, each phase has unity DC gain
+    phases: [[f32; TRUE_PEAK_TAPS]; TRUE_PEAK_OVERSAMPLING],
+    /// Latest input samples of each channel, oldest first
+    history: Vec<[f32; TRUE_PEAK_TAPS - 1]>,
+}
+
+impl TruePeakDetector {
+    fn new() -> Self {
+        let len = TRUE_PEAK_OVERSAMPLING * TRUE_PEAK_TAPS;
+        let center = (len - 1) as f64 / 2.;
+        let coef = |n: usize| {
+            let x = PI * (n as f64 - center) / TRUE_PEAK_OVERSAMPLING as f64;
+            let window = 0.5 - 0.5 * (2. * PI * (n as f64 + 0.5) / len as f64).cos();
+            x.sin() / x * window
+        };
+
+        let mut phases = [[0.; TRUE_PEAK_TAPS]; TRUE_PEAK_OVERSAMPLING];
+        phases.iter_mut().enumerate().for_each(|(p, phase)| {
+            let taps: Vec<f64> = (0..TRUE_PEAK_TAPS)
+                .map(|k| coef(k * TRUE_PEAK_OVERSAMPLING + p))
+                .collect();
+            let gain: f64 = taps.iter().sum();
+            // reversed, so the taps line up with the history in chronological order
+            phase
+                .iter_mut()
+                .zip(taps.iter().rev())
+                .for_each(|(c, t)| *c = (t / gain) as f32);
+        });
+
+        Self {
+            phases,
+            history: vec![],
+        }
+    }
+
+    /// Forget the history, e.g. after silence
+    fn reset(&mut self) {
+        self.history
+            .iter_mut()
+            .for_each(|history| *history = [0.; TRUE_PEAK_TAPS - 1]);
+    }
+
+    /// Highest absolute value of the samples and of the interpolated signal
+    fn peak(&mut self, input: &AudioRenderQuantum) -> f32 {
+        let Self { phases, history } = self;
+        history.resize(input.number_of_channels(), [0.; TRUE_PEAK_TAPS - 1]);
+
+        input
+            .channels()
+            .iter()
+            .zip(history.iter_mut())
+            .fold(0., |peak: f32, (channel, history)| {
+                let mut window = [0.; TRUE_PEAK_TAPS];
+                window[..TRUE_PEAK_TAPS - 1].copy_from_slice(&history[..]);
+
+                let peak = channel.iter().fold(peak, |peak, &v| {
+                    window.rotate_left(1);
+                    window[TRUE_PEAK_TAPS - 1] = v;
+
+                    phases.iter().fold(peak.max(v.abs()), |peak, phase| {
+                        let interpolated: f32 = phase.iter().zip(&window).map(|(c, w)| c * w).sum();
+                        peak.max(interpolated.abs())
+                    })
+                });
+
+                history.copy_from_slice(&window[1..]);
+                peak
+            })
+    }
+}
+
+struct DestinationRenderer {
+    /// Soft clipping threshold, infinite when disabled
+    limiter: Arc<AtomicF32>,
+    /// Peak level and clipping flag of the input
+    meter: Arc<DestinationMeter>,
+    /// Oversampling peak detector, used in true peak mode
+    true_peak: TruePeakDetector,
+}
 
 impl AudioProcessor for DestinationRenderer {
     fn process(
@@ -28,10 +153,54 @@ impl AudioProcessor for DestinationRenderer {
         // just move input to output
         *output = input.clone();
 
+        // measure the input, before the limiter kicks in
+        let true_peak = self.meter.true_peak.load(Ordering::SeqCst);
+        if output.is_silent() || !true_peak {
+            // a render quantum is longer than the filter, silence clears its history
+            self.true_peak.reset();
+        }
+        if !output.is_silent() {
+            let peak = if true_peak {
+                self.true_peak.peak(output)
+            } else {
+                output
+                    .channels()
+                    .iter()
+                    .flat_map(|channel| channel.iter())
+                    .fold(0., |peak: f32, v| peak.max(v.abs()))
+            };
+            self.meter.record(peak);
+        }
+
+        let threshold = self.limiter.load();
+        if threshold.is_finite() && !output.is_silent() {
+            output
+                .channels_mut()
+                .iter_mut()
+                .for_each(|channel| soft_clip(&mut channel[..], threshold));
+        }
+
         true
     }
 }
 
+/// Apply a `tanh` curve to the samples above `threshold`, mapping them to the range
+/// `(threshold, 1)`
+///
+/// The curve joins the identity with the same slope at the threshold, so that no corners are
+/// introduced in the signal.
+fn soft_clip(channel: &mut [f32], threshold: f32) {
+    let headroom = 1. - threshold;
+
+    channel.iter_mut().for_each(|v| {
+        let abs = v.abs();
+        if abs > threshold {
+            let clipped = threshold + headroom * ((abs - threshold) / headroom).tanh();
+            *v = clipped.copysign(*v);
+        }
+    });
+}
+
 impl AudioNode for AudioDestinationNode {
     fn registration(&self) -> &AudioContextRegistration {
         &self.registration
@@ -48,14 +217,20 @@ impl AudioNode for AudioDestinationNode {
         1
     }
 
+    /// Update the `channel_count` attribute
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the channel count is greater than [`Self::max_channel_count`], or if it is
+    /// changed for an `OfflineAudioContext`
     fn set_channel_count(&self, v: usize) {
-        if self.registration.context().offline() && v != self.max_channels_count() {
+        if self.registration.context().offline() && v != self.max_channel_count() {
             panic!("NotSupportedError: not allowed to change OfflineAudioContext destination channel count");
         }
-        if v > self.max_channels_count() {
+        if v > self.max_channel_count() {
             panic!(
                 "IndexSizeError: channel count cannot be greater than maxChannelCount ({})",
-                self.max_channels_count()
+                self.max_channel_count()
             );
         }
         self.channel_config.set_count(v);
@@ -74,11 +249,17 @@ impl AudioDestinationNode {
                 interpretation: ChannelInterpretation::Speakers,
             }
             .into();
+            let limiter = registration.context().destination_limiter().clone();
+            let meter = registration.context().destination_meter().clone();
             let node = Self {
                 registration,
                 channel_config,
             };
-            let proc = DestinationRenderer {};
+            let proc = DestinationRenderer {
+                limiter,
+                meter,
+                true_peak: TruePeakDetector::new(),
+            };
 
             (node, Box::new(proc))
         })
@@ -99,7 +280,304 @@ impl AudioDestinationNode {
     }
     /// The maximum number of channels that the channelCount attribute can be set to (the max
     /// number of channels that the hardware is capable of supporting).
-    pub fn max_channels_count(&self) -> usize {
+    ///
+    /// This is the number of channels of the output stream, opened with the default configuration
+    /// of the audio device. When the channel count of the destination is lower, its output is
+    /// up-mixed to the stream according to its channel interpretation. For an
+    /// `OfflineAudioContext` this is the number of channels it was created with.
+    pub fn max_channel_count(&self) -> usize {
         self.registration.context().base().max_channel_count()
     }
+
+    /// The maximum number of channels that the channelCount attribute can be set to
+    #[deprecated(note = "renamed to max_channel_count")]
+    pub fn max_channels_count(&self) -> usize {
+        self.max_channel_count()
+    }
+
+    /// Threshold of the soft clipper applied to the output, `None` when disabled (the default)
+    pub fn limiter(&self) -> Option<f32> {
+        let threshold = self.registration.context().destination_limiter().load();
+        threshold.is_finite().then_some(threshold)
+    }
+
+    /// Enable or disable the soft clipper applied to the output
+    ///
+    /// When enabled, the samples with an amplitude above `threshold` are smoothly compressed
+    /// towards `1` with a `tanh` curve, each channel independently. The signal thus never
+    /// exceeds `[-1, 1]`, without the harsh distortion of hard clipping. When disabled, the
+    /// input is passed through untouched.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `threshold` is not in the range `[0, 1)`
+    pub fn set_limiter(&self, threshold: Option<f32>) {
+        let value = match threshold {
+            Some(threshold) => {
+                assert!(
+                    (0. ..1.).contains(&threshold),
+                    "RangeError - limiter threshold ({:?}) should be in the range [0, 1)",
+                    threshold
+                );
+                threshold
+            }
+            None => f32::INFINITY,
+        };
+
+        self.registration
+            .context()
+            .destination_limiter()
+            .store(value);
+    }
+
+    /// Highest absolute sample value that reached the destination since the previous call
+    ///
+    /// The level is measured before the limiter (see [`Self::set_limiter`]) so values above `1`
+    /// reveal the amount of overshoot. Reading the level resets it to zero.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    pub fn peak_level(&self) -> f32 {
+        self.registration
+            .context()
+            .destination_meter()
+            .peak
+            .swap(0.)
+    }
+
+    /// Whether a sample outside `[-1, 1]` reached the destination since the previous call
+    ///
+    /// As for [`Self::peak_level`], the input is checked before the limiter. The flag stays
+    /// raised until it is read.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    pub fn clipped_since_last_check(&self) -> bool {
+        self.registration
+            .context()
+            .destination_meter()
+            .clipped
+            .swap(false, Ordering::SeqCst)
+    }
+
+    /// Whether the meter measures the true peak, `false` by default
+    ///
+    /// This method is not part of the Web Audio API specification.
+    pub fn true_peak(&self) -> bool {
+        self.registration
+            .context()
+            .destination_meter()
+            .true_peak
+            .load(Ordering::SeqCst)
+    }
+
+    /// Measure the true peak instead of the sample peak, or switch back to sample peaks
+    ///
+    /// Between the samples, the reconstructed analog signal may exceed the highest sample value.
+    /// In true peak mode the input is 4x oversampled before measuring its peak, as specified by
+    /// ITU-R BS.1770, so [`Self::peak_level`] and [`Self::clipped_since_last_check`] also
+    /// report these inter-sample peaks.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    pub fn set_true_peak(&self, enabled: bool) {
+        self.registration
+            .context()
+            .destination_meter()
+            .true_peak
+            .store(enabled, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use std::f64::consts::PI;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    // render a linear ramp from -3 to 3, on the first channel, and its opposite on the second
+    fn render_ramp(threshold: Option<f32>) -> (Vec<f32>, Vec<f32>) {
+        let context = OfflineAudioContext::new(2, 8000, 8000.);
+        context.destination().set_limiter(threshold);
+        assert_eq!(context.destination().limiter(), threshold);
+
+        let merger = context.create_channel_merger(2);
+        merger.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.offset().set_value_at_time(-3., 0.);
+        src.offset().linear_ramp_to_value_at_time(3., 1.);
+        src.connect_at(&merger, 0, 0);
+        src.start();
+
+        let invert = context.create_gain();
+        invert.gain().set_value(-1.);
+        src.connect(&invert);
+        invert.connect_at(&merger, 0, 1);
+
+        let output = context.start_rendering_sync();
+        (
+            output.get_channel_data(0).to_vec(),
+            output.get_channel_data(1).to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_passthrough_by_default() {
+        let (left, right) = render_ramp(None);
+
+        let expected: Vec<f32> = (0..8000).map(|i| -3. + 6. * i as f32 / 8000.).collect();
+        assert_float_eq!(left[..], expected[..], abs_all <= 1e-5);
+        assert!(left.iter().any(|v| v.abs() > 1.));
+        left.iter()
+            .zip(&right)
+            .for_each(|(l, r)| assert_eq!(*l, -r));
+    }
+
+    #[test]
+    fn test_soft_clip() {
+        let (left, right) = render_ramp(Some(0.5));
+
+        // within [-1, 1], each channel independently
+        assert!(left.iter().chain(&right).all(|v| v.abs() < 1.));
+        left.iter()
+            .zip(&right)
+            .for_each(|(l, r)| assert_eq!(*l, -r));
+
+        // below the threshold, the signal is untouched
+        let center = &left[3400..4600];
+        let expected: Vec<f32> = (3400..4600).map(|i| -3. + 6. * i as f32 / 8000.).collect();
+        assert_float_eq!(center[..], expected[..], abs_all <= 1e-5);
+
+        // the output is monotonic and its slope changes smoothly (no corners at the threshold),
+        // a hard clipper would drop from a slope of 6 / 8000 to zero within a single sample
+        let slopes: Vec<f32> = left.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(slopes.iter().all(|&s| s > 0.));
+        let max_slope_change = slopes
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0., f32::max);
+        assert!(max_slope_change < 1e-5);
+    }
+
+    #[test]
+    fn test_peak_and_clipping() {
+        let context = OfflineAudioContext::new(1, 256, 8000.);
+        context.destination().set_limiter(Some(0.5));
+
+        let src = context.create_constant_source();
+        src.offset().set_value_at_time(0.5, 0.);
+        src.offset().set_value_at_time(-1.5, 128. / 8000.);
+        src.connect(&context.destination());
+        src.start();
+
+        let dest = context.destination();
+        assert!(!dest.clipped_since_last_check());
+        assert_float_eq!(dest.peak_level(), 0., abs <= 0.);
+
+        let output = context.start_rendering_sync();
+        // the limiter kept the output in range, the overshoot is still reported
+        assert!(output.get_channel_data(0).iter().all(|v| v.abs() < 1.));
+        assert!(dest.clipped_since_last_check());
+        assert_float_eq!(dest.peak_level(), 1.5, abs <= 0.);
+
+        // both are reset once read
+        assert!(!dest.clipped_since_last_check());
+        assert_float_eq!(dest.peak_level(), 0., abs <= 0.);
+    }
+
+    #[test]
+    fn test_no_clipping_below_unity() {
+        let context = OfflineAudioContext::new(1, 256, 8000.);
+
+        let src = context.create_constant_source();
+        src.offset().set_value(-0.75);
+        src.connect(&context.destination());
+        src.start();
+
+        let dest = context.destination();
+        let _ = context.start_rendering_sync();
+        assert!(!dest.clipped_since_last_check());
+        assert_float_eq!(dest.peak_level(), 0.75, abs <= 0.);
+    }
+
+    #[test]
+    fn test_true_peak() {
+        // a sine at a quarter of the sample rate, shifted by 45 degrees: all samples are at
+        // +/- 0.9 / sqrt(2) while the signal peaks at 0.9 in between
+        let render = |true_peak: bool| {
+            let context = OfflineAudioContext::new(1, 1024, 48_000.);
+            let dest = context.destination();
+            assert!(!dest.true_peak());
+            dest.set_true_peak(true_peak);
+            assert_eq!(dest.true_peak(), true_peak);
+
+            let samples: Vec<f32> = (0..1024)
+                .map(|i| 0.9 * (PI / 2. * i as f64 + PI / 4.).sin() as f32)
+                .collect();
+            let mut buffer = context.create_buffer(1, 1024, 48_000.);
+            buffer.copy_to_channel(&samples, 0);
+
+            let src = context.create_buffer_source();
+            src.set_buffer(buffer);
+            src.connect(&dest);
+            src.start();
+
+            let _ = context.start_rendering_sync();
+            (dest.peak_level(), dest.clipped_since_last_check())
+        };
+
+        let (sample_peak, _) = render(false);
+        assert_float_eq!(sample_peak, 0.9 / 2_f32.sqrt(), abs <= 1e-5);
+
+        // within 0.5 dB of the actual peak
+        let (true_peak, clipped) = render(true);
+        assert!(true_peak > 0.85 && true_peak <= 0.9);
+        assert!(!clipped);
+    }
+
+    #[test]
+    fn test_true_peak_over() {
+        // inter-sample overs are flagged as clipping, while no sample exceeds 1
+        let context = OfflineAudioContext::new(1, 1024, 48_000.);
+        let dest = context.destination();
+        dest.set_true_peak(true);
+
+        let samples: Vec<f32> = (0..1024)
+            .map(|i| 1.2 * (PI / 2. * i as f64 + PI / 4.).sin() as f32)
+            .collect();
+        assert!(samples.iter().all(|v| v.abs() < 1.));
+        let mut buffer = context.create_buffer(1, 1024, 48_000.);
+        buffer.copy_to_channel(&samples, 0);
+
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&dest);
+        src.start();
+
+        let _ = context.start_rendering_sync();
+        assert!(dest.clipped_since_last_check());
+        assert!(dest.peak_level() > 1.1);
+    }
+
+    #[test]
+    fn test_offline_max_channel_count() {
+        let context = OfflineAudioContext::new(3, 128, 8000.);
+        assert_eq!(context.destination().max_channel_count(), 3);
+        assert_eq!(context.destination().channel_count(), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_offline_channel_count_change() {
+        let context = OfflineAudioContext::new(3, 128, 8000.);
+        context.destination().set_channel_count(2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_threshold() {
+        let context = OfflineAudioContext::new(1, 128, 8000.);
+        context.destination().set_limiter(Some(1.));
+    }
 }
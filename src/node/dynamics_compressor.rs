@@ -3,25 +3,9 @@ use std::sync::Arc;
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
 use crate::param::{AudioParam, AudioParamDescriptor};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
-use crate::{AtomicF32, RENDER_QUANTUM_SIZE};
+use crate::{flush_denormal_f32, AtomicF32};
 
-use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
-
-// Converting a value 𝑣 in decibels to linear gain unit means returning 10𝑣/20.
-fn db_to_lin(val: f32) -> f32 {
-    (10.0_f32).powf(val / 20.)
-}
-
-// Converting a value 𝑣 in linear gain unit to decibel means executing the following steps:
-// If 𝑣 is equal to zero, return -1000.
-// Else, return 20log10𝑣.
-fn lin_to_db(val: f32) -> f32 {
-    if val == 0. {
-        -1000.
-    } else {
-        20. * val.log10() // 20 * log10(val);
-    }
-}
+use super::{db_to_lin, lin_to_db, AudioNode, ChannelConfig, ChannelConfigOptions};
 
 /// Options for constructing a [`DynamicsCompressorNode`]
 // https://webaudio.github.io/web-audio-api/#DynamicsCompressorOptions
@@ -63,6 +47,12 @@ impl Default for DynamicsCompressorOptions {
 /// of individual sounds are played simultaneous to control the overall signal level
 /// and help avoid clipping (distorting) the audio output to the speakers.
 ///
+/// Contrary to the specification, the node has a second input, the sidechain. When a signal is
+/// connected to the sidechain, with [`connect_at`](AudioNode::connect_at) and an input index
+/// of `1`, the gain reduction is computed from the level of the sidechain and applied to the
+/// main input, e.g. to duck a music track under a voice. Without any connection to the
+/// sidechain, the gain reduction is computed from the main input.
+///
 /// - MDN documentation: <https://developer.mozilla.org/en-US/docs/Web/API/DynamicsCompressorNode>
 /// - specification: <https://webaudio.github.io/web-audio-api/#DynamicsCompressorNode>
 /// - see also: [`BaseAudioContext::create_dynamics_compressor`](crate::context::BaseAudioContext::create_dynamics_compressor)
@@ -116,7 +106,7 @@ impl AudioNode for DynamicsCompressorNode {
     }
 
     fn number_of_inputs(&self) -> usize {
-        1
+        2
     }
 
     fn number_of_outputs(&self) -> usize {
@@ -188,8 +178,9 @@ impl DynamicsCompressorNode {
 
             // define the number of buffers we need to have a delay line of ~6ms
             // const delay = new DelayNode(context, {delayTime: 0.006});
+            let render_quantum_size = context.render_quantum_size();
             let ring_buffer_size =
-                (context.sample_rate() * 0.006 / RENDER_QUANTUM_SIZE as f32).ceil() as usize + 1;
+                (context.sample_rate() * 0.006 / render_quantum_size as f32).ceil() as usize + 1;
             let ring_buffer = Vec::<AudioRenderQuantum>::with_capacity(ring_buffer_size);
 
             let render = DynamicsCompressorRenderer {
@@ -202,6 +193,7 @@ impl DynamicsCompressorNode {
                 ring_buffer,
                 ring_index: 0,
                 prev_detector_value: 0.,
+                reduction_gains: vec![0.; render_quantum_size],
             };
 
             let node = DynamicsCompressorNode {
@@ -254,6 +246,8 @@ struct DynamicsCompressorRenderer {
     ring_buffer: Vec<AudioRenderQuantum>,
     ring_index: usize,
     prev_detector_value: f32,
+    /// Linear gain reduction of each sample of the render quantum
+    reduction_gains: Vec<f32>,
 }
 
 // SAFETY:
@@ -276,6 +270,12 @@ impl AudioProcessor for DynamicsCompressorRenderer {
         // single input/output node
         let input = inputs[0].clone();
         let output = &mut outputs[0];
+        // the level is detected on the sidechain when connected, on the main input otherwise
+        let detector_input = if scope.is_input_connected(1) {
+            &inputs[1]
+        } else {
+            &input
+        };
         let sample_rate = scope.sample_rate;
 
         let ring_size = self.ring_buffer.capacity();
@@ -329,16 +329,15 @@ impl AudioProcessor for DynamicsCompressorRenderer {
         let mut prev_detector_value = self.prev_detector_value;
 
         let mut reduction_gain = 0.; // dB
-        let mut reduction_gains = [0.; 128]; // lin
-        let mut detector_values = [0.; 128]; // lin
+        let reduction_gains = &mut self.reduction_gains; // lin
 
-        for i in 0..RENDER_QUANTUM_SIZE {
+        for i in 0..scope.render_quantum_size {
             // pick highest value for this index across all input channels
             // @tbc - this seems to be what is done in chrome
             let mut max = f32::MIN;
 
-            for channel in input.channels().iter() {
-                let sample = channel.as_slice()[i].abs();
+            for channel in detector_input.channels().iter() {
+                let sample = channel[i].abs();
                 if sample > max {
                     max = sample;
                 }
@@ -373,7 +372,6 @@ impl AudioProcessor for DynamicsCompressorRenderer {
                 release_tau * prev_detector_value + (1. - release_tau) * sample_attenuation
             };
 
-            detector_values[i] = detector_value;
             // cdB = -yL + make up gain
             reduction_gain = -1. * detector_value + makeup_gain;
             // convert to lin now, so we just to multiply samples later
@@ -382,8 +380,8 @@ impl AudioProcessor for DynamicsCompressorRenderer {
             prev_detector_value = detector_value;
         }
 
-        // update prev_detector_value for next block
-        self.prev_detector_value = prev_detector_value;
+        // update prev_detector_value for next block, flushing the decayed value to zero
+        self.prev_detector_value = flush_denormal_f32(prev_detector_value);
         // update reduction shared w/ main thread
         self.reduction.store(reduction_gain);
 
@@ -398,11 +396,11 @@ impl AudioProcessor for DynamicsCompressorRenderer {
 
         *output = delayed.clone();
 
-        // if delayed signal is silent, there is no compression to apply
-        // thus we can consider the node has reach is tail time. (TBC)
+        // if delayed signal is silent, there is no compression to apply, the node
+        // has reached its tail time once the delay line only holds silence
         if output.is_silent() {
             output.make_silent(); // truncate to 1 channel if needed
-            return false;
+            return self.ring_buffer.iter().any(|b| !b.is_silent());
         }
 
         output.channels_mut().iter_mut().for_each(|channel| {
@@ -422,6 +420,7 @@ mod tests {
 
     use crate::context::OfflineAudioContext;
     use crate::node::AudioScheduledSourceNode;
+    use crate::RENDER_QUANTUM_SIZE;
 
     use super::*;
 
@@ -500,6 +499,107 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_delay_line_drains_after_source_stops() {
+        let sample_rate = 44_100.;
+        let context = OfflineAudioContext::new(1, 128 * 8, sample_rate);
+
+        // a single block of signal, all the nodes are dropped so the compressor is only kept
+        // alive by its tail
+        {
+            let compressor = DynamicsCompressorNode::new(&context, Default::default());
+            compressor.connect(&context.destination());
+
+            let mut buffer = context.create_buffer(1, 128, sample_rate);
+            buffer.copy_to_channel(&[1.; 128], 0);
+
+            let src = context.create_buffer_source();
+            src.set_buffer(buffer);
+            src.connect(&compressor);
+            src.start();
+        }
+
+        let res = context.start_rendering_sync();
+        let chan = res.channel_data(0).as_slice();
+
+        // the block leaves the delay line after 3 blocks at this sample rate
+        assert!(chan[..128 * 3].iter().all(|&v| v == 0.));
+        assert!(chan[128 * 3..128 * 4].iter().all(|&v| v != 0.));
+        assert!(chan[128 * 4..].iter().all(|&v| v == 0.));
+    }
+
+    // render a constant main signal through a hard knee compressor with instant attack and
+    // release, with an optional sidechain signal
+    fn render_sidechain(main: f32, sidechain: Option<&[f32]>, length: usize) -> Vec<f32> {
+        let sample_rate = 44_100.;
+        let context = OfflineAudioContext::new(1, length, sample_rate);
+
+        let compressor = DynamicsCompressorNode::new(
+            &context,
+            DynamicsCompressorOptions {
+                attack: 0.,
+                knee: 0.,
+                release: 0.,
+                ..DynamicsCompressorOptions::default()
+            },
+        );
+        compressor.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.offset().set_value(main);
+        src.connect(&compressor);
+        src.start();
+
+        if let Some(sidechain) = sidechain {
+            let mut buffer = context.create_buffer(1, sidechain.len(), sample_rate);
+            buffer.copy_to_channel(sidechain, 0);
+
+            let key = context.create_buffer_source();
+            key.set_buffer(buffer);
+            key.connect_at(&compressor, 0, 1);
+            key.start();
+        }
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_sidechain_ducking() {
+        let length = 128 * 64;
+        // loud sidechain for the first half, silent afterwards
+        let mut sidechain = vec![0.; length];
+        sidechain[..length / 2].iter_mut().for_each(|s| *s = 1.);
+
+        // the quiet main signal is below the threshold, it would not be compressed by itself
+        let output = render_sidechain(0.01, Some(&sidechain), length);
+
+        // 0 dB above a -24 dB threshold with a ratio of 12 gives 22 dB of reduction, the
+        // makeup gain is applied to both halves
+        let ducked = output[length / 2 - 128];
+        let unducked = output[length - 128];
+        assert_float_eq!(ducked / unducked, db_to_lin(-22.), abs <= 1e-4);
+
+        // the reduction follows the sidechain, not the delayed main signal
+        assert_float_eq!(output[length / 2 - 1], ducked, abs <= 1e-6);
+        assert_float_eq!(output[length / 2], unducked, abs <= 1e-6);
+
+        // without sidechain, the quiet main signal is not compressed
+        let output = render_sidechain(0.01, None, length);
+        assert_float_eq!(output[length / 2 - 128], unducked, abs <= 1e-6);
+    }
+
+    #[test]
+    fn test_silent_sidechain() {
+        let length = 128 * 16;
+
+        // a loud main signal is compressed when keyed off itself
+        let compressed = render_sidechain(1., None, length);
+        // but not when a silent sidechain is connected
+        let output = render_sidechain(1., Some(&vec![0.; length]), length);
+
+        assert!(output[length - 1] > compressed[length - 1] * 10.);
+    }
+
     #[test]
     fn test_db_to_lin() {
         assert_float_eq!(db_to_lin(0.), 1., abs <= 0.);
@@ -0,0 +1,503 @@
+//! The FFT equalizer control and renderer parts
+use std::sync::Mutex;
+
+use easyfft::num_complex::Complex;
+use easyfft::prelude::*;
+
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::triple_buffer::{triple_buffer, TripleBufferReader, TripleBufferWriter};
+
+use super::stft::{hann_window, BINS, FFT_SIZE, HOP_SIZE, WINDOW_OVERLAP_GAIN};
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions, TailTimeCounter};
+
+/// Assert that the magnitude response is valid
+///
+/// # Panics
+///
+/// This function panics if a frequency or a magnitude is negative or not finite
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_response(response: &[(f32, f32)]) {
+    response.iter().for_each(|&(frequency, magnitude)| {
+        if !(frequency.is_finite() && frequency >= 0.) {
+            panic!(
+                "RangeError - response frequency should be finite and positive, got {:?}",
+                frequency
+            );
+        }
+        if !(magnitude.is_finite() && magnitude >= 0.) {
+            panic!(
+                "RangeError - response magnitude should be finite and positive, got {:?}",
+                magnitude
+            );
+        }
+    });
+}
+
+/// Linear gain of each bin, interpolated from the response points sorted by frequency
+///
+/// The magnitudes are interpolated linearly on a logarithmic frequency axis, and held constant
+/// below the lowest and above the highest frequency. An empty response is flat.
+fn bin_gains(response: &[(f32, f32)], sample_rate: f32) -> Vec<f32> {
+    (0..BINS)
+        .map(|k| {
+            let frequency = k as f32 * sample_rate / FFT_SIZE as f32;
+            // index of the first point above the bin frequency
+            let index = response.partition_point(|&(f, _)| f <= frequency);
+
+            match (
+                index.checked_sub(1).map(|i| response[i]),
+                response.get(index),
+            ) {
+                (None, None) => 1.,
+                (None, Some(&(_, magnitude))) | (Some((_, magnitude)), None) => magnitude,
+                (Some((f0, m0)), Some(&(f1, m1))) => {
+                    // the frequencies are strictly increasing here, `f1 > f0`
+                    let t = if f0 > 0. {
+                        (frequency / f0).ln() / (f1 / f0).ln()
+                    } else {
+                        frequency / f1
+                    };
+                    m0 + (m1 - m0) * t
+                }
+            }
+        })
+        .collect()
+}
+
+/// Options for constructing a [`FftEqNode`]
+#[derive(Clone, Debug, Default)]
+pub struct FftEqOptions {
+    /// magnitude response, as `(frequency, magnitude)` points, see
+    /// [`FftEqNode::set_response`]
+    pub response: Vec<(f32, f32)>,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+/// `FftEqNode` is a linear-phase equalizer applying an arbitrary magnitude response
+///
+/// The response is given as `(frequency, magnitude)` points, the frequencies in Hz and the
+/// magnitudes as linear gains, at arbitrary frequencies. It is interpolated linearly on a
+/// logarithmic frequency axis to each frequency bin, and held constant below the lowest and
+/// above the highest given frequency. An empty response leaves the signal unchanged.
+///
+/// The input is processed by a short-time Fourier transform of 2048 samples frames, 512 samples
+/// apart, the bins being multiplied by the real (zero phase) gains. The frames are Hann windowed
+/// at the analysis and at the synthesis, so they overlap-add back without discontinuities. Each
+/// channel is processed independently.
+///
+/// Contrary to a cascade of [`BiquadFilterNode`](super::BiquadFilterNode)s, the phase of the
+/// signal is preserved, at the cost of a delay of 2048 samples (the frame length) of the output
+/// relative to the input.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_fft_eq`](crate::context::BaseAudioContext::create_fft_eq)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// // cut the lows and boost the presence
+/// let eq = context.create_fft_eq();
+/// eq.set_response(vec![(30., 0.), (80., 1.), (2000., 1.), (4000., 1.5), (8000., 1.)]);
+/// eq.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&eq);
+/// osc.start();
+/// ```
+pub struct FftEqNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Sample rate of the context, to compute the frequency of the bins
+    sample_rate: f32,
+    /// Magnitude response, sorted by frequency
+    response: Mutex<Vec<(f32, f32)>>,
+    /// Gain of each bin, sent to the renderer
+    gains: Mutex<TripleBufferWriter<Vec<f32>>>,
+}
+
+impl AudioNode for FftEqNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl FftEqNode {
+    /// returns a `FftEqNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - equalizer options
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a frequency or a magnitude of `options.response` is negative or not finite
+    pub fn new<C: BaseAudioContext>(context: &C, options: FftEqOptions) -> Self {
+        context.register(move |registration| {
+            let (writer, reader) = triple_buffer();
+
+            let renderer = FftEqRenderer {
+                gains: reader,
+                window: hann_window(),
+                frame: vec![0.; FFT_SIZE],
+                spectrum: DynRealDft::default(FFT_SIZE),
+                bins: vec![Complex::default(); BINS],
+                channels: vec![],
+                position: 0,
+                tail_time: TailTimeCounter::default(),
+            };
+
+            let node = Self {
+                registration,
+                channel_config: options.channel_config.into(),
+                sample_rate: context.sample_rate(),
+                response: Mutex::new(vec![]),
+                gains: Mutex::new(writer),
+            };
+
+            node.set_response(options.response);
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// Returns the magnitude response, as `(frequency, magnitude)` points sorted by frequency
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn response(&self) -> Vec<(f32, f32)> {
+        self.response.lock().unwrap().clone()
+    }
+
+    /// Set the magnitude response, as `(frequency, magnitude)` points
+    ///
+    /// The frequencies are in Hz and the magnitudes are linear gains, the points do not need to
+    /// be sorted. The response is interpolated to the frequency bins on the control thread, the
+    /// renderer picks it up at its next frame.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a frequency or a magnitude is negative or not finite
+    pub fn set_response(&self, mut response: Vec<(f32, f32)>) {
+        assert_valid_response(&response);
+        response.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let gains = bin_gains(&response, self.sample_rate);
+        *self.response.lock().unwrap() = response;
+        self.gains.lock().unwrap().write(gains);
+    }
+}
+
+/// Analysis history and overlap-add output of a channel
+struct ChannelState {
+    /// Latest `FFT_SIZE` input samples, the last `HOP_SIZE` being filled
+    input: Vec<f32>,
+    /// Overlap-add of the synthesized frames, the first `HOP_SIZE` samples are complete
+    output: Vec<f32>,
+}
+
+impl ChannelState {
+    fn new() -> Self {
+        Self {
+            input: vec![0.; FFT_SIZE],
+            output: vec![0.; FFT_SIZE],
+        }
+    }
+}
+
+/// `FftEqRenderer` represents the rendering part of `FftEqNode`
+struct FftEqRenderer {
+    /// Gain of each bin, flat until a response is received
+    gains: TripleBufferReader<Vec<f32>>,
+    window: Vec<f32>,
+    /// Windowed analysis frame, then synthesized frame
+    frame: Vec<f32>,
+    spectrum: DynRealDft<f32>,
+    /// Filtered spectrum, copied into `spectrum` for the inverse FFT
+    bins: Vec<Complex<f32>>,
+    /// State of each channel, allocated when the channel count grows
+    channels: Vec<ChannelState>,
+    /// Number of samples of the current hop that were processed
+    position: usize,
+    /// Number of silent input samples since the latest sound, to report the tail time
+    tail_time: TailTimeCounter,
+}
+
+impl FftEqRenderer {
+    /// Filter the input frame of the channel, overlap-added to its output
+    fn process_frame(&mut self, index: usize) {
+        let Self {
+            gains,
+            window,
+            frame,
+            spectrum,
+            bins,
+            channels,
+            ..
+        } = self;
+        let channel = &mut channels[index];
+
+        frame
+            .iter_mut()
+            .zip(channel.input.iter().zip(window.iter()))
+            .for_each(|(f, (i, w))| *f = i * w);
+        frame[..].real_fft_using(spectrum);
+
+        match gains.get() {
+            Some(gains) => bins
+                .iter_mut()
+                .zip(spectrum.iter().zip(gains.iter()))
+                .for_each(|(b, (s, g))| *b = s * g),
+            None => bins.copy_from_slice(spectrum),
+        }
+        spectrum.copy_from_slice(bins);
+        spectrum.real_ifft_using(frame);
+
+        let output = &mut channel.output;
+        output.copy_within(HOP_SIZE.., 0);
+        output[FFT_SIZE - HOP_SIZE..].fill(0.);
+        let normalization = 1. / (FFT_SIZE as f32 * WINDOW_OVERLAP_GAIN);
+        output
+            .iter_mut()
+            .zip(frame.iter().zip(window.iter()))
+            .for_each(|(o, (f, w))| *o += f * w * normalization);
+
+        channel.input.copy_within(HOP_SIZE.., 0);
+    }
+}
+
+impl AudioProcessor for FftEqRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        // the latest sound has left the analysis frames and the output
+        if self
+            .tail_time
+            .tail_ended(input, FFT_SIZE + HOP_SIZE, scope.render_quantum_size)
+        {
+            output.make_silent();
+            return false;
+        }
+
+        let number_of_channels = input.number_of_channels().max(self.channels.len());
+        if self.channels.len() < number_of_channels {
+            self.channels
+                .resize_with(number_of_channels, ChannelState::new);
+        }
+        output.set_number_of_channels(number_of_channels);
+
+        // the render quantum may span several hops, or only part of one
+        let mut offset = 0;
+        while offset < scope.render_quantum_size {
+            let position = self.position;
+            let len = (HOP_SIZE - position).min(scope.render_quantum_size - offset);
+
+            self.channels
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, channel)| {
+                    let history = &mut channel.input[FFT_SIZE - HOP_SIZE + position..][..len];
+                    match input.channels().get(i) {
+                        // a silent input may have fewer channels
+                        Some(data) => history.copy_from_slice(&data[offset..][..len]),
+                        None => history.fill(0.),
+                    }
+
+                    let ready = &channel.output[position..][..len];
+                    output.channel_data_mut(i)[offset..][..len].copy_from_slice(ready);
+                });
+
+            self.position += len;
+            offset += len;
+            if self.position == HOP_SIZE {
+                // pick up the latest response before the frame
+                self.gains.update();
+                (0..self.channels.len()).for_each(|i| self.process_frame(i));
+                self.position = 0;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.;
+
+    /// Render one second of a sine at the given frequency through an equalizer
+    fn render_sine(frequency: f32, response: Vec<(f32, f32)>) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, SAMPLE_RATE as usize, SAMPLE_RATE);
+
+        let eq = FftEqNode::new(
+            &context,
+            FftEqOptions {
+                response,
+                ..FftEqOptions::default()
+            },
+        );
+        eq.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(frequency);
+        osc.connect(&eq);
+        osc.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    fn rms(signal: &[f32]) -> f32 {
+        (signal.iter().map(|v| v * v).sum::<f32>() / signal.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_bin_gains() {
+        let bin_frequency = SAMPLE_RATE / FFT_SIZE as f32;
+
+        // flat
+        let gains = bin_gains(&[], SAMPLE_RATE);
+        assert_float_eq!(gains[..], [1.; BINS][..], abs_all <= 0.);
+
+        // held outside the given frequencies, interpolated on a log scale in between
+        let response = [(10. * bin_frequency, 0.), (40. * bin_frequency, 1.)];
+        let gains = bin_gains(&response, SAMPLE_RATE);
+        assert_float_eq!(gains[..=10], [0.; 11][..], abs_all <= 0.);
+        assert_float_eq!(gains[20], 0.5, abs <= 1e-6);
+        assert_float_eq!(gains[40..], [1.; BINS - 40][..], abs_all <= 0.);
+        assert!(gains[10..=40].windows(2).all(|w| w[0] < w[1]));
+
+        // linear interpolation from DC
+        let response = [(0., 0.), (10. * bin_frequency, 1.)];
+        let gains = bin_gains(&response, SAMPLE_RATE);
+        assert_float_eq!(gains[5], 0.5, abs <= 1e-6);
+    }
+
+    #[test]
+    fn test_response() {
+        let context = OfflineAudioContext::new(1, 1, SAMPLE_RATE);
+        let eq = context.create_fft_eq();
+        assert!(eq.response().is_empty());
+
+        // the points are sorted by frequency
+        eq.set_response(vec![(1000., 0.5), (100., 1.), (10000., 2.)]);
+        assert_eq!(eq.response(), vec![(100., 1.), (1000., 0.5), (10000., 2.)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_response() {
+        let context = OfflineAudioContext::new(1, 1, SAMPLE_RATE);
+        let eq = context.create_fft_eq();
+        eq.set_response(vec![(1000., -1.)]);
+    }
+
+    #[test]
+    fn test_passthrough() {
+        let output = render_sine(440., vec![]);
+        let expected = render_sine(440., vec![(20., 1.)]);
+
+        // the input delayed by the frame length
+        let context = OfflineAudioContext::new(1, SAMPLE_RATE as usize, SAMPLE_RATE);
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(440.);
+        osc.connect(&context.destination());
+        osc.start();
+        let sine = context.start_rendering_sync().get_channel_data(0).to_vec();
+
+        assert_float_eq!(output[..FFT_SIZE], [0.; FFT_SIZE][..], abs_all <= 1e-6);
+        assert_float_eq!(
+            output[FFT_SIZE..],
+            sine[..sine.len() - FFT_SIZE],
+            abs_all <= 1e-4
+        );
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-4);
+    }
+
+    #[test]
+    fn test_notch() {
+        // notch around 1kHz
+        let notch = vec![(800., 1.), (900., 0.), (1100., 0.), (1250., 1.)];
+
+        // steady state, once the onset of the tone has left the frames
+        let level = |frequency, response| rms(&render_sine(frequency, response)[2 * FFT_SIZE..]);
+
+        // the tone in the notch is attenuated by more than 40 dB, the neighbours pass
+        let reference = level(1000., vec![]);
+        let notched = level(1000., notch.clone());
+        assert!(notched < reference * 0.01);
+
+        [500., 2000.].iter().for_each(|&frequency| {
+            let passed = level(frequency, notch.clone());
+            assert_float_eq!(passed, reference, rmax <= 0.01);
+        });
+    }
+
+    #[test]
+    fn test_render_quantum_sizes() {
+        let render = |render_quantum_size| {
+            let context = OfflineAudioContext::new_with_render_quantum_size(
+                1,
+                8192,
+                SAMPLE_RATE,
+                render_quantum_size,
+            );
+            let eq = context.create_fft_eq();
+            eq.set_response(vec![(200., 0.5), (2000., 1.)]);
+            eq.connect(&context.destination());
+
+            let osc = context.create_oscillator();
+            osc.connect(&eq);
+            osc.start();
+            context.start_rendering_sync().get_channel_data(0).to_vec()
+        };
+
+        // the hops span several render quanta, or several hops fit in a render quantum
+        let expected = render(crate::RENDER_QUANTUM_SIZE);
+        assert!(expected.iter().any(|&v| v.abs() > 0.1));
+        for render_quantum_size in [64, 256, 4096] {
+            assert_float_eq!(
+                render(render_quantum_size)[..],
+                expected[..],
+                abs_all <= 1e-5
+            );
+        }
+    }
+}
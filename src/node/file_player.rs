@@ -0,0 +1,769 @@
+use arrayvec::ArrayVec;
+use crossbeam_channel::{Receiver, Sender};
+use once_cell::sync::OnceCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::buffer::AudioBuffer;
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+use crate::AtomicF64;
+
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Duration in seconds of the fades applied on play, pause and seek
+const FADE_DURATION: f64 = 0.005;
+
+/// Maximum number of playheads being faded out at once, after seeks in quick succession
+const MAX_FADE_VOICES: usize = 4;
+
+/// Options for constructing a [`FilePlayerNode`]
+#[derive(Clone, Debug, Default)]
+pub struct FilePlayerOptions {
+    /// The decoded audio to play back
+    pub buffer: Option<AudioBuffer>,
+    /// Restart from the beginning when the end of the buffer is reached
+    pub loop_: bool,
+    /// channel config options
+    pub channel_config: ChannelConfigOptions,
+}
+
+enum FilePlayerAction {
+    SetBuffer(AudioBuffer),
+    Seek(f64),
+}
+
+/// `FilePlayerNode` plays back an [`AudioBuffer`] with the transport controls of a media
+/// element: `play`, `pause`, `seek` and looping.
+///
+/// Contrary to an [`AudioBufferSourceNode`](super::AudioBufferSourceNode), which can only be
+/// started once, the player can be paused, resumed and seeked any number of times. Playback
+/// starts paused, at the beginning of the buffer.
+///
+/// Looping is gapless: the playhead wraps around the end of the buffer within a render quantum.
+/// Pausing, resuming and seeking while playing apply short fades (a crossfade between the old
+/// and the new position in case of a seek) so no clicks are introduced.
+///
+/// The whole buffer is held in memory, use a
+/// [`MediaElement`](crate::media::MediaElement) to stream a file from disk instead.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_file_player`](crate::context::BaseAudioContext::create_file_player)
+///
+/// # Usage
+///
+/// ```no_run
+/// use std::fs::File;
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::AudioNode;
+///
+/// let context = AudioContext::default();
+/// let file = File::open("samples/sample.wav").unwrap();
+/// let audio_buffer = context.decode_audio_data_sync(file).unwrap();
+///
+/// let player = context.create_file_player();
+/// player.set_buffer(audio_buffer);
+/// player.set_loop(true);
+/// player.connect(&context.destination());
+///
+/// player.seek(1.5);
+/// player.play();
+/// ```
+pub struct FilePlayerNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    sender: Sender<FilePlayerAction>,
+    buffer: OnceCell<AudioBuffer>,
+    paused: Arc<AtomicBool>,
+    loop_: Arc<AtomicBool>,
+    current_time: Arc<AtomicF64>,
+}
+
+impl AudioNode for FilePlayerNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl FilePlayerNode {
+    /// Create a new [`FilePlayerNode`] instance
+    pub fn new<C: BaseAudioContext>(context: &C, options: FilePlayerOptions) -> Self {
+        context.register(move |registration| {
+            let FilePlayerOptions {
+                buffer,
+                loop_,
+                channel_config,
+            } = options;
+
+            let (sender, receiver) = crossbeam_channel::unbounded();
+            let paused = Arc::new(AtomicBool::new(true));
+            let loop_ = Arc::new(AtomicBool::new(loop_));
+            let current_time = Arc::new(AtomicF64::new(0.));
+
+            let fade_frames = (FADE_DURATION * context.sample_rate() as f64)
+                .round()
+                .max(1.) as u32;
+
+            let render_quantum_size = context.render_quantum_size();
+            let renderer = FilePlayerRenderer {
+                receiver,
+                buffer: None,
+                paused: paused.clone(),
+                loop_: loop_.clone(),
+                current_time: current_time.clone(),
+                position: 0.,
+                level: 0,
+                fade_frames,
+                fade_from: ArrayVec::new(),
+                crossfade: 0,
+                positions: vec![0.; render_quantum_size],
+                gains: vec![0.; render_quantum_size],
+                fade_positions: vec![[0.; MAX_FADE_VOICES]; render_quantum_size],
+                crossfades: vec![1.; render_quantum_size],
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                sender,
+                buffer: OnceCell::new(),
+                paused,
+                loop_,
+                current_time,
+            };
+
+            if let Some(buf) = buffer {
+                node.set_buffer(buf);
+            }
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// Current buffer value (nullable)
+    pub fn buffer(&self) -> Option<&AudioBuffer> {
+        self.buffer.get()
+    }
+
+    /// Provide the [`AudioBuffer`] to be played back
+    ///
+    /// # Panics
+    ///
+    /// Panics if a buffer has already been given to the player (though `new` or through
+    /// `set_buffer`)
+    pub fn set_buffer(&self, audio_buffer: AudioBuffer) {
+        let clone = audio_buffer.clone();
+
+        if self.buffer.set(audio_buffer).is_err() {
+            panic!("InvalidStateError - cannot assign buffer twice");
+        }
+
+        let _ = self.sender.send(FilePlayerAction::SetBuffer(clone));
+    }
+
+    /// Duration of the buffer in seconds, `0.` when no buffer has been set
+    #[must_use]
+    pub fn duration(&self) -> f64 {
+        self.buffer.get().map_or(0., AudioBuffer::duration)
+    }
+
+    /// Position of the playhead in seconds, in the time reference of the [`AudioBuffer`]
+    #[must_use]
+    pub fn current_time(&self) -> f64 {
+        self.current_time.load()
+    }
+
+    /// Start or resume the playback from the current position
+    ///
+    /// When the playback has reached the end of the buffer, it restarts from the beginning.
+    pub fn play(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    /// Pause the playback, the position is kept for the next call to [`play`](Self::play)
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` when the playback is paused, or has reached the end of the buffer
+    #[must_use]
+    pub fn paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Move the playhead to the given position in seconds, clamped to the duration of the buffer
+    ///
+    /// # Panics
+    ///
+    /// Panics if `seconds` is negative or not finite
+    pub fn seek(&self, seconds: f64) {
+        assert!(
+            seconds >= 0. && seconds.is_finite(),
+            "RangeError - seek position ({:?}) should be a non-negative finite number",
+            seconds
+        );
+
+        let seconds = match self.buffer.get() {
+            Some(buffer) => seconds.min(buffer.duration()),
+            None => 0.,
+        };
+
+        self.current_time.store(seconds);
+        let _ = self.sender.send(FilePlayerAction::Seek(seconds));
+    }
+
+    /// Defines if the playback should restart from the beginning at the end of the buffer
+    #[must_use]
+    pub fn loop_(&self) -> bool {
+        self.loop_.load(Ordering::SeqCst)
+    }
+
+    /// Update the looping behaviour, applied from the next render quantum
+    ///
+    /// When enabled, the playhead wraps around to the beginning of the buffer without gap when
+    /// it reaches the end. Disabling it while playing stops the playback at the end of the
+    /// buffer.
+    pub fn set_loop(&self, value: bool) {
+        self.loop_.store(value, Ordering::SeqCst);
+    }
+}
+
+struct FilePlayerRenderer {
+    receiver: Receiver<FilePlayerAction>,
+    buffer: Option<AudioBuffer>,
+    paused: Arc<AtomicBool>,
+    loop_: Arc<AtomicBool>,
+    current_time: Arc<AtomicF64>,
+    /// Playhead, in frames of the buffer
+    position: f64,
+    /// Progress of the play / pause fades, from 0 (silent) to `fade_frames` (audible)
+    level: u32,
+    /// Length of the fades, in frames of the context
+    fade_frames: u32,
+    /// Playheads faded out after a seek, with their share of the faded out signal
+    fade_from: ArrayVec<(f64, f32), MAX_FADE_VOICES>,
+    /// Progress of the crossfade after a seek, from 0 to `fade_frames`
+    crossfade: u32,
+    /// Playheads of each frame of the render quantum, shared by all channels
+    positions: Vec<f64>,
+    /// Levels of each frame of the render quantum
+    gains: Vec<f32>,
+    /// Faded out playheads of each frame of the render quantum
+    fade_positions: Vec<[f64; MAX_FADE_VOICES]>,
+    /// Crossfade progress of each frame of the render quantum
+    crossfades: Vec<f32>,
+}
+
+/// Sample at a fractional frame position, linearly interpolated
+#[inline]
+fn read_sample(data: &[f32], position: f64, loop_: bool) -> f32 {
+    let index = position as usize;
+    let k = (position - index as f64) as f32;
+
+    let current = data.get(index).copied().unwrap_or(0.);
+    let next = match data.get(index + 1) {
+        Some(&next) => next,
+        // the first sample follows the last one when looping
+        None if loop_ && index + 1 == data.len() => data[0],
+        None => 0.,
+    };
+
+    current + k * (next - current)
+}
+
+impl FilePlayerRenderer {
+    fn apply(&mut self, action: FilePlayerAction) {
+        match action {
+            FilePlayerAction::SetBuffer(buffer) => {
+                self.buffer = Some(buffer);
+                self.position = 0.;
+                self.fade_from.clear();
+            }
+            FilePlayerAction::Seek(seconds) => {
+                let buffer = match &self.buffer {
+                    Some(buffer) => buffer,
+                    None => return,
+                };
+
+                let position = seconds * buffer.sample_rate() as f64;
+                // crossfade from the old position when audible, jump otherwise
+                if self.level > 0 {
+                    // during a running crossfade, fade out the blend that is currently audible
+                    let x = if self.fade_from.is_empty() {
+                        1.
+                    } else {
+                        self.crossfade as f32 / self.fade_frames as f32
+                    };
+                    self.fade_from.iter_mut().for_each(|(_, w)| *w *= 1. - x);
+                    self.fade_from.retain(|(_, w)| *w > 0.);
+
+                    if x > 0. {
+                        // drop the least audible playhead when seeking too often
+                        if self.fade_from.is_full() {
+                            let quietest = (0..self.fade_from.len())
+                                .min_by(|&a, &b| {
+                                    self.fade_from[a].1.total_cmp(&self.fade_from[b].1)
+                                })
+                                .unwrap();
+                            self.fade_from.remove(quietest);
+                        }
+                        self.fade_from.push((self.position, x));
+                    }
+
+                    self.crossfade = 0;
+                }
+                self.position = position;
+            }
+        }
+    }
+
+    fn render(&mut self, output: &mut AudioRenderQuantum, sample_rate: f32) -> bool {
+        let buffer = match &self.buffer {
+            Some(buffer) => buffer,
+            None => {
+                output.make_silent();
+                return false;
+            }
+        };
+
+        let length = buffer.length() as f64;
+        let loop_ = self.loop_.load(Ordering::SeqCst);
+        let mut paused = self.paused.load(Ordering::SeqCst);
+
+        // restart after the end of the buffer was reached
+        if !paused && self.level == 0 && self.position >= length {
+            self.position = 0.;
+        }
+
+        if paused && self.level == 0 {
+            output.make_silent();
+            return false;
+        }
+
+        // playback rate compensating for the sample rate of the buffer
+        let step = buffer.sample_rate() as f64 / sample_rate as f64;
+
+        // playheads and levels of each frame, shared by all channels
+        self.positions.fill(0.);
+        self.gains.fill(0.);
+        // the faded out playheads are fixed for the duration of the render quantum
+        let fading = self.fade_from.clone();
+        self.crossfades.fill(1.);
+        let fade_frames = self.fade_frames as f32;
+
+        let wrap = |position: f64| {
+            if loop_ && position >= length {
+                position % length
+            } else {
+                position
+            }
+        };
+
+        for i in 0..self.positions.len() {
+            self.level = if paused {
+                self.level.saturating_sub(1)
+            } else {
+                (self.level + 1).min(self.fade_frames)
+            };
+
+            if self.level == 0 {
+                continue;
+            }
+
+            // end of the buffer, the playback stops without fade as the signal ends anyway
+            if self.position >= length && self.fade_from.is_empty() {
+                self.level = 0;
+                self.position = length;
+                self.paused.store(true, Ordering::SeqCst);
+                paused = true;
+                continue;
+            }
+
+            self.positions[i] = self.position;
+            self.gains[i] = self.level as f32 / fade_frames;
+            self.position = wrap(self.position + step);
+
+            if !self.fade_from.is_empty() {
+                self.fade_from
+                    .iter_mut()
+                    .zip(self.fade_positions[i].iter_mut())
+                    .for_each(|((from, _), p)| {
+                        *p = *from;
+                        *from = wrap(*from + step);
+                    });
+                self.crossfades[i] = self.crossfade as f32 / fade_frames;
+
+                self.crossfade += 1;
+                if self.crossfade >= self.fade_frames {
+                    self.fade_from.clear();
+                }
+            }
+        }
+
+        let Self {
+            positions,
+            gains,
+            fade_positions,
+            crossfades,
+            ..
+        } = &*self;
+        output.set_number_of_channels(buffer.number_of_channels());
+        output
+            .channels_mut()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(channel_number, channel)| {
+                let data = buffer.get_channel_data(channel_number);
+
+                channel.iter_mut().enumerate().for_each(|(i, o)| {
+                    *o = if gains[i] == 0. {
+                        0.
+                    } else if crossfades[i] < 1. {
+                        let x = crossfades[i];
+                        let current = read_sample(data, positions[i], loop_);
+                        let previous: f32 = fading
+                            .iter()
+                            .zip(fade_positions[i].iter())
+                            .map(|(&(_, w), &p)| w * read_sample(data, p, loop_))
+                            .sum();
+                        gains[i] * (x * current + (1. - x) * previous)
+                    } else {
+                        gains[i] * read_sample(data, positions[i], loop_)
+                    };
+                });
+            });
+
+        self.current_time
+            .store(self.position.min(length) / buffer.sample_rate() as f64);
+
+        !paused || self.level > 0
+    }
+}
+
+impl AudioProcessor for FilePlayerRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+
+        while let Ok(action) = self.receiver.try_recv() {
+            self.apply(action);
+        }
+
+        self.render(output, scope.sample_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::render::Alloc;
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 8000.;
+
+    // buffer of one second where each sample holds its own index
+    fn ramp() -> AudioBuffer {
+        let data = (0..SAMPLE_RATE as usize).map(|i| i as f32).collect();
+        AudioBuffer::from(vec![data], SAMPLE_RATE)
+    }
+
+    fn player(context: &OfflineAudioContext) -> FilePlayerNode {
+        let options = FilePlayerOptions {
+            buffer: Some(ramp()),
+            ..FilePlayerOptions::default()
+        };
+        let player = FilePlayerNode::new(context, options);
+        player.connect(&context.destination());
+        player
+    }
+
+    #[test]
+    fn test_duration() {
+        let context = OfflineAudioContext::new(1, 128, SAMPLE_RATE);
+        let player = context.create_file_player();
+        assert_float_eq!(player.duration(), 0., abs <= 0.);
+        assert!(player.paused());
+
+        player.set_buffer(ramp());
+        assert_float_eq!(player.duration(), 1., abs <= 0.);
+    }
+
+    #[test]
+    fn test_play_from_start() {
+        let context = OfflineAudioContext::new(1, 1024, SAMPLE_RATE);
+        let player = player(&context);
+        player.play();
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        // fade in, then the buffer is played sample by sample
+        let fade_frames = (FADE_DURATION * SAMPLE_RATE as f64) as usize;
+        assert!(output[..fade_frames]
+            .iter()
+            .enumerate()
+            .all(|(i, &v)| v <= i as f32));
+        output[fade_frames..]
+            .iter()
+            .enumerate()
+            .for_each(|(i, &v)| assert_float_eq!(v, (fade_frames + i) as f32, abs <= 0.));
+    }
+
+    #[test]
+    fn test_seek_paused() {
+        let context = OfflineAudioContext::new(1, 1024, SAMPLE_RATE);
+        let player = player(&context);
+        player.seek(0.5);
+        assert_float_eq!(player.current_time(), 0.5, abs <= 0.);
+        player.play();
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        // playback resumes at frame 4000
+        output[100..]
+            .iter()
+            .enumerate()
+            .for_each(|(i, &v)| assert_float_eq!(v, (4100 + i) as f32, abs <= 0.));
+        assert_float_eq!(player.current_time(), 0.5 + 1024. / 8000., abs <= 1e-9);
+    }
+
+    #[test]
+    fn test_seek_clamped() {
+        let context = OfflineAudioContext::new(1, 128, SAMPLE_RATE);
+        let player = player(&context);
+        player.seek(10.);
+        assert_float_eq!(player.current_time(), 1., abs <= 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_seek_negative() {
+        let context = OfflineAudioContext::new(1, 128, SAMPLE_RATE);
+        let player = player(&context);
+        player.seek(-1.);
+    }
+
+    #[test]
+    fn test_end_of_buffer() {
+        let context = OfflineAudioContext::new(1, 2048, SAMPLE_RATE);
+        let player = player(&context);
+        player.seek(0.9);
+        player.play();
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        // 800 frames remain, the output is silent afterwards
+        assert_float_eq!(output[799], 7999., abs <= 0.);
+        assert!(output[800..].iter().all(|&v| v == 0.));
+        assert!(player.paused());
+        assert_float_eq!(player.current_time(), 1., abs <= 0.);
+    }
+
+    #[test]
+    fn test_gapless_loop() {
+        let context = OfflineAudioContext::new(1, 2048, SAMPLE_RATE);
+        let player = player(&context);
+        player.set_loop(true);
+        player.seek(0.9);
+        player.play();
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        // the first frame directly follows the last one
+        assert_float_eq!(output[799], 7999., abs <= 0.);
+        output[800..]
+            .iter()
+            .enumerate()
+            .for_each(|(i, &v)| assert_float_eq!(v, i as f32, abs <= 0.));
+        assert!(!player.paused());
+    }
+
+    // renderer driven directly, so the transport can be changed between render quanta
+    fn renderer() -> FilePlayerRenderer {
+        let (_, receiver) = crossbeam_channel::unbounded();
+        let fade_frames = (FADE_DURATION * SAMPLE_RATE as f64).round();
+
+        FilePlayerRenderer {
+            receiver,
+            buffer: Some(ramp()),
+            paused: Arc::new(AtomicBool::new(true)),
+            loop_: Arc::new(AtomicBool::new(false)),
+            current_time: Arc::new(AtomicF64::new(0.)),
+            position: 0.,
+            level: 0,
+            fade_frames: fade_frames as u32,
+            fade_from: ArrayVec::new(),
+            crossfade: 0,
+            positions: vec![0.; RENDER_QUANTUM_SIZE],
+            gains: vec![0.; RENDER_QUANTUM_SIZE],
+            fade_positions: vec![[0.; MAX_FADE_VOICES]; RENDER_QUANTUM_SIZE],
+            crossfades: vec![1.; RENDER_QUANTUM_SIZE],
+        }
+    }
+
+    fn render_quanta(renderer: &mut FilePlayerRenderer, alloc: &Alloc, count: usize) -> Vec<f32> {
+        (0..count)
+            .flat_map(|_| {
+                let mut output = AudioRenderQuantum::from(alloc.silence());
+                renderer.render(&mut output, SAMPLE_RATE);
+                output.channel_data(0).to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pause_resume() {
+        let alloc = Alloc::with_capacity(1);
+        let mut renderer = renderer();
+
+        renderer.paused.store(false, Ordering::SeqCst);
+        let played = render_quanta(&mut renderer, &alloc, 4);
+        assert_float_eq!(played[511], 511., abs <= 0.);
+
+        // the fade out still advances the playhead, which is then frozen
+        renderer.paused.store(true, Ordering::SeqCst);
+        let paused = render_quanta(&mut renderer, &alloc, 4);
+        let fade_frames = (FADE_DURATION * SAMPLE_RATE as f64) as usize;
+        assert!(paused[fade_frames..].iter().all(|&v| v == 0.));
+        let position = (renderer.current_time.load() * SAMPLE_RATE as f64).round() as usize;
+        assert!((512..=512 + fade_frames).contains(&position));
+        let frozen = render_quanta(&mut renderer, &alloc, 4);
+        assert!(frozen.iter().all(|&v| v == 0.));
+        assert_float_eq!(
+            renderer.current_time.load(),
+            position as f64 / SAMPLE_RATE as f64,
+            abs <= 1e-9
+        );
+
+        // playback resumes from the position where it was paused
+        renderer.paused.store(false, Ordering::SeqCst);
+        let resumed = render_quanta(&mut renderer, &alloc, 4);
+        resumed[fade_frames..]
+            .iter()
+            .enumerate()
+            .for_each(|(i, &v)| {
+                assert_float_eq!(v, (position + fade_frames + i) as f32, abs <= 0.)
+            });
+    }
+
+    #[test]
+    fn test_seek_while_playing() {
+        let alloc = Alloc::with_capacity(1);
+        let mut renderer = renderer();
+
+        renderer.paused.store(false, Ordering::SeqCst);
+        render_quanta(&mut renderer, &alloc, 2);
+
+        renderer.apply(FilePlayerAction::Seek(0.5));
+        let output = render_quanta(&mut renderer, &alloc, 2);
+
+        // crossfade from the old playhead (frame 256) to the new one (frame 4000)
+        let fade_frames = (FADE_DURATION * SAMPLE_RATE as f64) as usize;
+        output[..fade_frames]
+            .iter()
+            .enumerate()
+            .for_each(|(i, &v)| {
+                let x = i as f32 / fade_frames as f32;
+                let expected = x * (4000 + i) as f32 + (1. - x) * (256 + i) as f32;
+                assert_float_eq!(v, expected, rmax <= 1e-5);
+            });
+        output[fade_frames..]
+            .iter()
+            .enumerate()
+            .for_each(|(i, &v)| assert_float_eq!(v, (4000 + fade_frames + i) as f32, abs <= 0.));
+
+        // no jump larger than a fraction of the distance between the playheads
+        let max_step = output
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0., f32::max);
+        assert!(max_step < 3744. / fade_frames as f32 + 2.);
+    }
+
+    #[test]
+    fn test_seek_during_crossfade() {
+        let alloc = Alloc::with_capacity(1);
+        let mut renderer = renderer();
+        // crossfade longer than a render quantum, so it is still running at the next seek
+        renderer.fade_frames = 256;
+
+        renderer.paused.store(false, Ordering::SeqCst);
+        render_quanta(&mut renderer, &alloc, 4);
+
+        renderer.apply(FilePlayerAction::Seek(0.5));
+        let mut output = render_quanta(&mut renderer, &alloc, 1);
+        renderer.apply(FilePlayerAction::Seek(0.25));
+        output.extend(render_quanta(&mut renderer, &alloc, 3));
+
+        // the second crossfade starts from the blend of both previous playheads (frames 4128
+        // and 640), which is what was audible at the time of the seek
+        let expected = 0.5 * 4128. + 0.5 * 640.;
+        assert_float_eq!(output[128], expected, rmax <= 1e-5);
+
+        // no jump larger than a fraction of the distance between the playheads
+        let max_step = output
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0., f32::max);
+        assert!(max_step < 3488. / 256. + 2.);
+
+        // the crossfade has completed to the last seek position
+        output[128 + 256..]
+            .iter()
+            .enumerate()
+            .for_each(|(i, &v)| assert_float_eq!(v, (2000 + 256 + i) as f32, abs <= 0.));
+    }
+
+    #[test]
+    fn test_sample_rate_conversion() {
+        let alloc = Alloc::with_capacity(1);
+        let mut renderer = renderer();
+        renderer.paused.store(false, Ordering::SeqCst);
+        renderer.level = renderer.fade_frames;
+
+        // the buffer is played at its own sample rate, here with half the rate of the context
+        let mut output = AudioRenderQuantum::from(alloc.silence());
+        renderer.render(&mut output, 2. * SAMPLE_RATE);
+        output
+            .channel_data(0)
+            .iter()
+            .enumerate()
+            .for_each(|(i, &v)| assert_float_eq!(v, i as f32 / 2., abs <= 0.));
+    }
+
+    #[test]
+    fn test_read_sample() {
+        let data = [1., 2., 3., 4.];
+        assert_float_eq!(read_sample(&data, 1.25, false), 2.25, abs <= 0.);
+        assert_float_eq!(read_sample(&data, 3.5, false), 2., abs <= 0.);
+        // interpolation with the first sample when looping
+        assert_float_eq!(read_sample(&data, 3.5, true), 2.5, abs <= 0.);
+        assert_float_eq!(read_sample(&data, 4., false), 0., abs <= 0.);
+    }
+}
@@ -0,0 +1,396 @@
+use std::error::Error;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::control::Scheduler;
+use crate::flush_denormal;
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::biquad_filter::{calculate_coefs, BiquadFilterType};
+use super::noise::Rng;
+use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions};
+
+/// Options for constructing a [`FilteredNoiseNode`]
+#[derive(Clone, Debug)]
+pub struct FilteredNoiseOptions {
+    /// Center frequency of the band, in Hz
+    pub frequency: f32,
+    /// Quality factor of the band, higher values give a narrower band
+    pub q: f32,
+    /// Seed of the random number generator, use `None` to draw it from the random seed of the
+    /// context, or for a random seed if the context is not seeded
+    ///
+    /// Rendering with the same seed yields identical output, which is useful for testing.
+    pub seed: Option<u64>,
+    /// channel config options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for FilteredNoiseOptions {
+    fn default() -> Self {
+        Self {
+            frequency: 1000.,
+            q: 1.,
+            seed: None,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `FilteredNoiseNode` represents an audio source generating white noise through a band-pass
+/// filter, e.g. for wind or percussion synthesis
+///
+/// The filter is the same as the `"bandpass"` type of the `BiquadFilterNode`, with a gain of
+/// 0dB at the center frequency. Using this node is equivalent to, but cheaper than, connecting
+/// a white [`NoiseSourceNode`](super::NoiseSourceNode) to a band-pass `BiquadFilterNode`.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_filtered_noise`](crate::context::BaseAudioContext::create_filtered_noise)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// // gusts of wind
+/// let wind = context.create_filtered_noise();
+/// wind.q().set_value(5.);
+/// wind.frequency().set_value(400.);
+/// wind.frequency().linear_ramp_to_value_at_time(900., 2.);
+/// wind.frequency().linear_ramp_to_value_at_time(300., 4.);
+/// wind.connect(&context.destination());
+/// wind.start();
+/// ```
+pub struct FilteredNoiseNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Center frequency of the band
+    frequency: AudioParam,
+    /// Quality factor of the band
+    q: AudioParam,
+    /// starts and stops noise audio streams
+    scheduler: Scheduler,
+}
+
+impl AudioNode for FilteredNoiseNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioScheduledSourceNode for FilteredNoiseNode {
+    fn try_start_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_start_at(when)
+    }
+
+    fn try_stop_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_stop_at(when)
+    }
+}
+
+impl FilteredNoiseNode {
+    /// Returns a `FilteredNoiseNode`
+    ///
+    /// # Arguments:
+    ///
+    /// * `context` - The `AudioContext`
+    /// * `options` - The FilteredNoiseOptions
+    pub fn new<C: BaseAudioContext>(context: &C, options: FilteredNoiseOptions) -> Self {
+        context.register(move |registration| {
+            let FilteredNoiseOptions {
+                frequency,
+                q,
+                seed,
+                channel_config,
+            } = options;
+
+            let frequency_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: context.sample_rate() / 2.,
+                default_value: 1000.,
+                automation_rate: AutomationRate::A,
+            };
+            let (f_param, f_proc) = context.create_audio_param(frequency_opts, &registration);
+            f_param.set_value(frequency);
+
+            // a zero Q would divide by zero in the filter design
+            let q_opts = AudioParamDescriptor {
+                min_value: 1e-4,
+                max_value: 1000.,
+                default_value: 1.,
+                automation_rate: AutomationRate::A,
+            };
+            let (q_param, q_proc) = context.create_audio_param(q_opts, &registration);
+            q_param.set_value(q);
+
+            let scheduler = Scheduler::new();
+
+            let seed = seed.unwrap_or_else(|| context.base().random_seed(registration.id()));
+
+            let renderer = FilteredNoiseRenderer {
+                frequency: f_proc,
+                q: q_proc,
+                scheduler: scheduler.clone(),
+                rng: Rng::new(seed),
+                state: [0.; 4],
+                ended_triggered: false,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                frequency: f_param,
+                q: q_param,
+                scheduler,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// A-rate [`AudioParam`] that defines the center frequency of the band, in Hz
+    #[must_use]
+    pub fn frequency(&self) -> &AudioParam {
+        &self.frequency
+    }
+
+    /// A-rate [`AudioParam`] that defines the quality factor of the band, higher values give a
+    /// narrower band
+    #[must_use]
+    pub fn q(&self) -> &AudioParam {
+        &self.q
+    }
+}
+
+struct FilteredNoiseRenderer {
+    /// Center frequency of the band
+    frequency: AudioParamId,
+    /// Quality factor of the band
+    q: AudioParamId,
+    /// starts and stops noise audio streams
+    scheduler: Scheduler,
+    /// white noise generator
+    rng: Rng,
+    /// filter history `[x1, x2, y1, y2]`
+    state: [f64; 4],
+    /// defines if the `ended` events was already dispatched
+    ended_triggered: bool,
+}
+
+impl AudioProcessor for FilteredNoiseRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+
+        let sample_rate = f64::from(scope.sample_rate);
+        let dt = 1. / sample_rate;
+        let next_block_time = scope.current_time + dt * scope.render_quantum_size as f64;
+
+        let start_time = self.scheduler.get_start_at();
+        let stop_time = self.scheduler.get_stop_at();
+
+        if start_time >= next_block_time {
+            output.make_silent();
+            return true;
+        }
+
+        output.ensure_mono();
+
+        let frequency = params.get(&self.frequency);
+        let q = params.get(&self.q);
+        let coefs = |f: f32, q: f32| {
+            let (f, q) = (f64::from(f), f64::from(q));
+            calculate_coefs(BiquadFilterType::Bandpass, sample_rate, f, 0., q)
+        };
+        // only recompute the coefficients for each sample when automated
+        let constant = (frequency.len() == 1 && q.len() == 1).then(|| coefs(frequency[0], q[0]));
+
+        let [mut x1, mut x2, mut y1, mut y2] = self.state;
+        let mut current_time = scope.current_time;
+
+        output
+            .channel_data_mut(0)
+            .iter_mut()
+            .zip(frequency.iter().cycle().zip(q.iter().cycle()))
+            .for_each(|(o, (&frequency, &q))| {
+                if current_time < start_time || current_time >= stop_time {
+                    *o = 0.;
+                } else {
+                    let c = constant.unwrap_or_else(|| coefs(frequency, q));
+                    let x = f64::from(self.rng.next_bipolar());
+                    let y = c.b0 * x + c.b1 * x1 + c.b2 * x2 - c.a1 * y1 - c.a2 * y2;
+                    x2 = x1;
+                    x1 = x;
+                    y2 = y1;
+                    y1 = y;
+                    *o = y as f32;
+                }
+
+                current_time += dt;
+            });
+
+        self.state = [x1, x2, y1, y2].map(flush_denormal);
+
+        // tail_time false when output has ended this quantum
+        let still_running = stop_time >= next_block_time;
+
+        if !still_running && !self.ended_triggered {
+            scope.send_ended_event();
+            self.ended_triggered = true;
+        }
+
+        still_running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{AudioContext, AudioContextOptions, OfflineAudioContext};
+    use crate::node::{AnalyserNode, AnalyserOptions};
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use super::*;
+
+    fn render_filtered_noise(options: FilteredNoiseOptions, length: usize) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, length, 48_000.);
+        let noise = FilteredNoiseNode::new(&context, options);
+        noise.connect(&context.destination());
+        noise.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_reproducible_with_seed() {
+        let options = FilteredNoiseOptions {
+            seed: Some(42),
+            ..FilteredNoiseOptions::default()
+        };
+        let a = render_filtered_noise(options.clone(), 1024);
+        let b = render_filtered_noise(options.clone(), 1024);
+        assert_float_eq!(a[..], b[..], abs_all <= 0.);
+        assert!(a.iter().any(|v| *v != 0.));
+
+        let other = FilteredNoiseOptions {
+            seed: Some(43),
+            ..options
+        };
+        let c = render_filtered_noise(other, 1024);
+        assert!(a != c);
+    }
+
+    #[test]
+    fn test_a_rate_frequency() {
+        let sample_rate = 48_000.;
+        let render = |automate: bool| {
+            let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate);
+            let options = FilteredNoiseOptions {
+                seed: Some(1),
+                ..FilteredNoiseOptions::default()
+            };
+            let noise = FilteredNoiseNode::new(&context, options);
+            if automate {
+                noise
+                    .frequency()
+                    .set_value_at_time(5000., 64. / f64::from(sample_rate));
+            }
+            noise.connect(&context.destination());
+            noise.start();
+            context.start_rendering_sync().get_channel_data(0).to_vec()
+        };
+
+        let reference = render(false);
+        let automated = render(true);
+
+        // the change applies exactly at the automation time, within the render quantum
+        assert_float_eq!(automated[..64], reference[..64], abs_all <= 0.);
+        assert!(automated[64..] != reference[64..]);
+    }
+
+    #[test]
+    fn test_energy_around_center_frequency() {
+        let options = AudioContextOptions {
+            sink_id: "none".into(),
+            sample_rate: Some(48_000.),
+            ..AudioContextOptions::default()
+        };
+        let context = AudioContext::new(options);
+
+        let options = AnalyserOptions {
+            fft_size: 2048,
+            smoothing_time_constant: 0.9,
+            ..AnalyserOptions::default()
+        };
+        let analyser = AnalyserNode::new(&context, options);
+        analyser.connect(&context.destination());
+
+        let options = FilteredNoiseOptions {
+            frequency: 3000.,
+            q: 10.,
+            seed: Some(0),
+            ..FilteredNoiseOptions::default()
+        };
+        let noise = FilteredNoiseNode::new(&context, options);
+        noise.connect(&analyser);
+        noise.start();
+
+        // let the smoothed spectrum settle over several analysis windows
+        let start = std::time::Instant::now();
+        while context.current_time() < 0.5 {
+            assert!(start.elapsed().as_secs() < 5, "no audio rendered");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        let spectrum = analyser.get_float_frequency_data(vec![0.; 1024]);
+
+        // average level (in dB) of the bins in the given frequency range
+        let bin_width = 48_000. / 2048.;
+        let level = |from: f32, to: f32| {
+            let bins = &spectrum[(from / bin_width) as usize..(to / bin_width) as usize];
+            bins.iter().sum::<f32>() / bins.len() as f32
+        };
+
+        let center = level(2900., 3100.);
+        let below = level(500., 1500.);
+        let above = level(8000., 16000.);
+
+        assert!(center - below > 15., "{} {}", center, below);
+        assert!(center - above > 15., "{} {}", center, above);
+
+        // the peak bin is close to the center frequency
+        let peak = spectrum
+            .iter()
+            .enumerate()
+            .fold(
+                (0, f32::MIN),
+                |max, (i, &v)| if v > max.1 { (i, v) } else { max },
+            )
+            .0;
+        assert_float_eq!(peak as f32 * bin_width, 3000., abs <= 200.);
+    }
+}
@@ -0,0 +1,411 @@
+//! The frequency shifter control and renderer parts
+use easyfft::num_complex::Complex;
+use easyfft::prelude::*;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::stft::{hann_window, BINS, FFT_SIZE, HOP_SIZE, WINDOW_OVERLAP_GAIN};
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions, TailTimeCounter};
+
+/// Options for constructing a [`FrequencyShifterNode`]
+#[derive(Clone, Debug)]
+pub struct FrequencyShifterOptions {
+    /// initial value for the shift parameter, in Hz
+    pub shift_hz: f32,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for FrequencyShifterOptions {
+    fn default() -> Self {
+        Self {
+            shift_hz: 0.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `FrequencyShifterNode` adds a constant offset in Hz to all the frequencies of an audio stream
+///
+/// Contrary to the [`PitchShiftNode`](super::PitchShiftNode), which multiplies the frequencies
+/// and preserves the harmonic relations, the frequency shifter moves all the partials by the
+/// same amount: a harmonic sound becomes inharmonic, a shift of a few Hz gives a slow phasing.
+/// A negative `shift_hz` moves the frequencies down, the frequencies moved below 0 Hz fold back
+/// with an inverted phase.
+///
+/// The node is a single-sideband modulator: the analytic signal of the input is computed with a
+/// Hilbert transform and multiplied by an internal quadrature oscillator at `shift_hz`, so only
+/// the upper (or lower) sideband remains. The Hilbert transform is computed by a short-time
+/// Fourier transform of 2048 samples frames, 512 samples apart, Hann windowed at the analysis and
+/// at the synthesis. This delays the output by 2048 samples relative to the input, see
+/// [`latency`](Self::latency). Each channel is processed independently.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_frequency_shifter`](crate::context::BaseAudioContext::create_frequency_shifter)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// let shifter = context.create_frequency_shifter();
+/// shifter.shift_hz().set_value(-30.);
+/// shifter.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&shifter);
+/// osc.start();
+/// ```
+pub struct FrequencyShifterNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Offset added to the frequencies
+    shift_hz: AudioParam,
+    /// Delay of the output relative to the input, in seconds
+    latency: f64,
+}
+
+impl AudioNode for FrequencyShifterNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl FrequencyShifterNode {
+    /// returns a `FrequencyShifterNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - frequency shifter options
+    pub fn new<C: BaseAudioContext>(context: &C, options: FrequencyShifterOptions) -> Self {
+        context.register(move |registration| {
+            let nyquist = context.sample_rate() / 2.;
+            let shift_options = AudioParamDescriptor {
+                min_value: -nyquist,
+                max_value: nyquist,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+            let (shift_param, shift_proc) =
+                context.create_audio_param(shift_options, &registration);
+            shift_param.set_value(options.shift_hz);
+
+            let renderer = FrequencyShifterRenderer {
+                shift_hz: shift_proc,
+                window: hann_window(),
+                frame: vec![0.; FFT_SIZE],
+                spectrum: DynRealDft::default(FFT_SIZE),
+                bins: vec![Complex::default(); BINS],
+                channels: vec![],
+                position: 0,
+                phase: 0.,
+                tail_time: TailTimeCounter::default(),
+            };
+
+            let node = Self {
+                registration,
+                channel_config: options.channel_config.into(),
+                shift_hz: shift_param,
+                latency: FFT_SIZE as f64 / f64::from(context.sample_rate()),
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// A-rate [`AudioParam`] of the offset added to the frequencies, in Hz
+    ///
+    /// The value is clamped to the range `[-nyquist, nyquist]`.
+    #[must_use]
+    pub fn shift_hz(&self) -> &AudioParam {
+        &self.shift_hz
+    }
+
+    /// Delay of the output relative to the input introduced by the Hilbert transform, in seconds
+    ///
+    /// The latency is 2048 samples at the sample rate of the context.
+    #[must_use]
+    pub fn latency(&self) -> f64 {
+        self.latency
+    }
+}
+
+/// Analysis history and overlap-add outputs of a channel
+struct ChannelState {
+    /// Latest `FFT_SIZE` input samples, the last `HOP_SIZE` being filled
+    input: Vec<f32>,
+    /// Overlap-add of the frames, the first `HOP_SIZE` samples are complete
+    in_phase: Vec<f32>,
+    /// Overlap-add of the Hilbert transformed frames, the first `HOP_SIZE` samples are complete
+    quadrature: Vec<f32>,
+}
+
+impl ChannelState {
+    fn new() -> Self {
+        Self {
+            input: vec![0.; FFT_SIZE],
+            in_phase: vec![0.; FFT_SIZE],
+            quadrature: vec![0.; FFT_SIZE],
+        }
+    }
+}
+
+/// `FrequencyShifterRenderer` represents the rendering part of `FrequencyShifterNode`
+struct FrequencyShifterRenderer {
+    /// Offset added to the frequencies
+    shift_hz: AudioParamId,
+    window: Vec<f32>,
+    /// Windowed analysis frame, then Hilbert transformed frame
+    frame: Vec<f32>,
+    spectrum: DynRealDft<f32>,
+    /// Hilbert transformed spectrum, copied into `spectrum` for the inverse FFT
+    bins: Vec<Complex<f32>>,
+    /// State of each channel, allocated when the channel count grows
+    channels: Vec<ChannelState>,
+    /// Number of samples of the current hop that were processed
+    position: usize,
+    /// Position of the quadrature oscillator in the current cycle, in the [0, 1) range
+    phase: f64,
+    /// Number of silent input samples since the latest sound, to report the tail time
+    tail_time: TailTimeCounter,
+}
+
+impl FrequencyShifterRenderer {
+    /// Hilbert transform of the input frame of the channel, overlap-added to its outputs
+    fn process_frame(&mut self, index: usize) {
+        let Self {
+            window,
+            frame,
+            spectrum,
+            bins,
+            channels,
+            ..
+        } = self;
+        let channel = &mut channels[index];
+
+        frame
+            .iter_mut()
+            .zip(channel.input.iter().zip(window.iter()))
+            .for_each(|(f, (i, w))| *f = i * w);
+
+        // the unmodified frames overlap-add back to the input
+        let in_phase = &mut channel.in_phase;
+        in_phase.copy_within(HOP_SIZE.., 0);
+        in_phase[FFT_SIZE - HOP_SIZE..].fill(0.);
+        in_phase
+            .iter_mut()
+            .zip(frame.iter().zip(window.iter()))
+            .for_each(|(o, (f, w))| *o += f * w / WINDOW_OVERLAP_GAIN);
+
+        // shift the phase of the positive frequencies by -90 degrees, the DC and Nyquist bins
+        // have no quadrature component
+        frame[..].real_fft_using(spectrum);
+        bins.iter_mut()
+            .zip(spectrum.iter())
+            .for_each(|(b, s)| *b = Complex::new(s.im, -s.re));
+        bins[0] = Complex::default();
+        bins[BINS - 1] = Complex::default();
+        spectrum.copy_from_slice(bins);
+        spectrum.real_ifft_using(frame);
+
+        let quadrature = &mut channel.quadrature;
+        quadrature.copy_within(HOP_SIZE.., 0);
+        quadrature[FFT_SIZE - HOP_SIZE..].fill(0.);
+        let normalization = 1. / (FFT_SIZE as f32 * WINDOW_OVERLAP_GAIN);
+        quadrature
+            .iter_mut()
+            .zip(frame.iter().zip(window.iter()))
+            .for_each(|(o, (f, w))| *o += f * w * normalization);
+
+        channel.input.copy_within(HOP_SIZE.., 0);
+    }
+}
+
+impl AudioProcessor for FrequencyShifterRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+        let dt = 1. / f64::from(scope.sample_rate);
+        let shift_hz = params.get(&self.shift_hz);
+
+        // the latest sound has left the analysis frames and the outputs
+        if self
+            .tail_time
+            .tail_ended(input, FFT_SIZE + HOP_SIZE, scope.render_quantum_size)
+        {
+            // keep the oscillator running
+            let mut phase = self.phase;
+            shift_hz
+                .iter()
+                .cycle()
+                .take(scope.render_quantum_size)
+                .for_each(|&f| phase = (phase + f64::from(f) * dt).rem_euclid(1.));
+            self.phase = phase;
+
+            output.make_silent();
+            return false;
+        }
+
+        let number_of_channels = input.number_of_channels().max(self.channels.len());
+        if self.channels.len() < number_of_channels {
+            self.channels
+                .resize_with(number_of_channels, ChannelState::new);
+        }
+        output.set_number_of_channels(number_of_channels);
+
+        // the render quantum may span several hops, or only part of one
+        let mut offset = 0;
+        while offset < scope.render_quantum_size {
+            let position = self.position;
+            let len = (HOP_SIZE - position).min(scope.render_quantum_size - offset);
+
+            // quadrature oscillator, shared by all channels
+            let start_phase = self.phase;
+            let mut phase = start_phase;
+            shift_hz
+                .iter()
+                .cycle()
+                .skip(offset)
+                .take(len)
+                .for_each(|&f| phase = (phase + f64::from(f) * dt).rem_euclid(1.));
+            self.phase = phase;
+
+            self.channels
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, channel)| {
+                    let history = &mut channel.input[FFT_SIZE - HOP_SIZE + position..][..len];
+                    match input.channels().get(i) {
+                        // a silent input may have fewer channels
+                        Some(data) => history.copy_from_slice(&data[offset..][..len]),
+                        None => history.fill(0.),
+                    }
+
+                    // the upper sideband of the analytic signal
+                    let mut phase = start_phase;
+                    output.channel_data_mut(i)[offset..][..len]
+                        .iter_mut()
+                        .zip(channel.in_phase[position..].iter())
+                        .zip(channel.quadrature[position..].iter())
+                        .zip(shift_hz.iter().cycle().skip(offset))
+                        .for_each(|(((o, i), q), &f)| {
+                            let (sin, cos) = (2. * std::f64::consts::PI * phase).sin_cos();
+                            *o = i * cos as f32 - q * sin as f32;
+                            phase = (phase + f64::from(f) * dt).rem_euclid(1.);
+                        });
+                });
+
+            self.position += len;
+            offset += len;
+            if self.position == HOP_SIZE {
+                (0..self.channels.len()).for_each(|i| self.process_frame(i));
+                self.position = 0;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::super::test_util::peak_frequency;
+    use super::*;
+
+    const SAMPLE_RATE: usize = 48_000;
+
+    /// Render a 1 kHz sine through a frequency shifter, the second after the latency is returned
+    fn render(shift_hz: f32) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, SAMPLE_RATE + FFT_SIZE, SAMPLE_RATE as f32);
+
+        let shifter = FrequencyShifterNode::new(
+            &context,
+            FrequencyShifterOptions {
+                shift_hz,
+                ..FrequencyShifterOptions::default()
+            },
+        );
+        shifter.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(1000.);
+        osc.connect(&shifter);
+        osc.start();
+
+        context.start_rendering_sync().get_channel_data(0)[FFT_SIZE..].to_vec()
+    }
+
+    #[test]
+    fn test_latency() {
+        let context = OfflineAudioContext::new(1, 1, SAMPLE_RATE as f32);
+        let shifter = context.create_frequency_shifter();
+        assert_float_eq!(shifter.latency(), 2048. / 48_000., abs <= 0.);
+    }
+
+    #[test]
+    fn test_shift_up() {
+        let output = render(50.);
+        assert_eq!(peak_frequency(&output, SAMPLE_RATE), 1050);
+        let spectrum = crate::analysis::power_spectrum(&output);
+
+        // the lower sideband and the original frequency are suppressed by more than 40 dB
+        assert!(spectrum[950] < spectrum[1050] * 1e-4);
+        assert!(spectrum[1000] < spectrum[1050] * 1e-4);
+    }
+
+    #[test]
+    fn test_shift_down() {
+        let output = render(-50.);
+        assert_eq!(peak_frequency(&output, SAMPLE_RATE), 950);
+        let spectrum = crate::analysis::power_spectrum(&output);
+        assert!(spectrum[1050] < spectrum[950] * 1e-4);
+    }
+
+    #[test]
+    fn test_no_shift() {
+        // the input delayed by the latency
+        let output = render(0.);
+
+        let context = OfflineAudioContext::new(1, SAMPLE_RATE, SAMPLE_RATE as f32);
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(1000.);
+        osc.connect(&context.destination());
+        osc.start();
+        let expected = context.start_rendering_sync().get_channel_data(0).to_vec();
+
+        assert_float_eq!(output[..], expected[..], abs_all <= 1e-4);
+    }
+}
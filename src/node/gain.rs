@@ -1,14 +1,21 @@
-use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::context::{
+    AudioContextRegistration, AudioParamId, BaseAudioContext, PatchNodeType, Patchable,
+};
 use crate::param::{AudioParam, AudioParamDescriptor};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 
-use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions, ChannelInterpretation};
 
 /// Options for constructing a [`GainNode`]
 // dictionary GainOptions : AudioNodeOptions {
 //   float gain = 1.0;
 // };
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct GainOptions {
     pub gain: f32,
     pub channel_config: ChannelConfigOptions,
@@ -24,6 +31,12 @@ impl Default for GainOptions {
 }
 
 /// AudioNode for volume control
+///
+/// Contrary to the specification, the signals connected to the [`gain`](Self::gain) param are
+/// not down-mixed to mono. A multi-channel control signal scales each channel of the input
+/// independently, e.g. for constant-power crossfades of stereo signals. A mono control signal
+/// applies to all channels, and a control signal with a different number of channels than the
+/// input is up- or down-mixed to the input following the speakers interpretation.
 pub struct GainNode {
     registration: AudioContextRegistration,
     channel_config: ChannelConfig,
@@ -50,7 +63,9 @@ impl AudioNode for GainNode {
 
 impl GainNode {
     pub fn new<C: BaseAudioContext>(context: &C, options: GainOptions) -> Self {
-        context.register(move |registration| {
+        let patch_options = options.clone();
+
+        let node = context.register(move |registration| {
             let param_opts = AudioParamDescriptor {
                 min_value: f32::MIN,
                 max_value: f32::MAX,
@@ -58,6 +73,7 @@ impl GainNode {
                 automation_rate: crate::param::AutomationRate::A,
             };
             let (param, proc) = context.create_audio_param(param_opts, &registration);
+            param.set_multi_channel_input();
 
             param.set_value_at_time(options.gain, 0.);
 
@@ -70,7 +86,17 @@ impl GainNode {
             };
 
             (node, Box::new(render))
-        })
+        });
+
+        let channel_config = node.channel_config.clone();
+        context.base().record_patch_node(&node, move || {
+            PatchNodeType::Gain(GainOptions {
+                channel_config: (&channel_config).into(),
+                ..patch_options.clone()
+            })
+        });
+
+        node
     }
 
     pub fn gain(&self) -> &AudioParam {
@@ -78,6 +104,12 @@ impl GainNode {
     }
 }
 
+impl Patchable for GainNode {
+    fn patch_params(&self) -> Vec<&AudioParam> {
+        vec![&self.gain]
+    }
+}
+
 struct GainRenderer {
     gain: AudioParamId,
 }
@@ -99,6 +131,28 @@ impl AudioProcessor for GainRenderer {
             return false;
         }
 
+        // multi-channel control signal, each channel scales the matching input channel
+        let gains = params.get_channels(&self.gain);
+        if gains.number_of_channels() > 1 {
+            let mut gains = gains.clone();
+            gains.mix(input.number_of_channels(), ChannelInterpretation::Speakers);
+
+            *output = input.clone();
+            output
+                .channels_mut()
+                .iter_mut()
+                .zip(gains.channels())
+                .for_each(|(channel, gain)| {
+                    channel
+                        .iter_mut()
+                        .zip(gain.iter())
+                        .for_each(|(o, g)| *o *= g);
+                });
+
+            return false;
+        }
+        drop(gains);
+
         let gain = params.get(&self.gain);
 
         // very fast track for mute or pass-through
@@ -140,3 +194,204 @@ impl AudioProcessor for GainRenderer {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::OfflineAudioContext;
+    use crate::node::{AudioScheduledSourceNode, ChannelCountMode};
+    use crate::AudioBuffer;
+
+    use super::*;
+
+    fn render_gain(bypassed: bool) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+
+        let gain = context.create_gain();
+        gain.gain().set_value(0.5);
+        gain.set_bypass(bypassed);
+        gain.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&gain);
+        src.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_bypass() {
+        assert_float_eq!(render_gain(false)[..], [0.5; 128][..], abs_all <= 0.);
+        assert_float_eq!(render_gain(true)[..], [1.; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_bypass_toggle() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+
+        let gain = context.create_gain();
+        gain.gain().set_value(0.5);
+        gain.set_bypass(true);
+        gain.set_bypass(false);
+        gain.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&gain);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0)[..],
+            [0.5; 128][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_bypass_mixes_channels() {
+        let context = OfflineAudioContext::new(2, 128, 44_100.);
+
+        // the bypassed gain down-mixes its stereo input to mono
+        let gain = context.create_gain();
+        gain.set_channel_count(1);
+        gain.set_channel_count_mode(ChannelCountMode::Explicit);
+        gain.set_bypass(true);
+        gain.connect(&context.destination());
+
+        let merger = context.create_channel_merger(2);
+        merger.connect(&gain);
+
+        let left = context.create_constant_source();
+        left.connect_at(&merger, 0, 0);
+        left.start();
+
+        let output = context.start_rendering_sync();
+        // mono output is up-mixed to both channels of the destination
+        assert_float_eq!(
+            output.get_channel_data(0)[..],
+            [0.5; 128][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            output.get_channel_data(1)[..],
+            [0.5; 128][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_bypass_source_is_muted() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+
+        let src = context.create_constant_source();
+        src.set_bypass(true);
+        src.connect(&context.destination());
+        src.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(output.get_channel_data(0)[..], [0.; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_multi_channel_gain() {
+        let context = OfflineAudioContext::new(2, 128, 44_100.);
+
+        let gain = context.create_gain();
+        gain.gain().set_value(0.);
+        gain.connect(&context.destination());
+
+        // stereo input of ones
+        let src = context.create_buffer_source();
+        src.set_buffer(AudioBuffer::from(vec![vec![1.; 128]; 2], 44_100.));
+        src.connect(&gain);
+        src.start();
+
+        // stereo control signal, 0.5 on the left and 0.25 on the right channel
+        let merger = context.create_channel_merger(2);
+        let left = context.create_constant_source();
+        left.offset().set_value(0.5);
+        left.connect_at(&merger, 0, 0);
+        left.start();
+        let right = context.create_constant_source();
+        right.offset().set_value(0.25);
+        right.connect_at(&merger, 0, 1);
+        right.start();
+        merger.connect(gain.gain());
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0)[..],
+            [0.5; 128][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            output.get_channel_data(1)[..],
+            [0.25; 128][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_mono_gain_applies_to_all_channels() {
+        let context = OfflineAudioContext::new(2, 128, 44_100.);
+
+        let gain = context.create_gain();
+        gain.gain().set_value(0.);
+        gain.connect(&context.destination());
+
+        let src = context.create_buffer_source();
+        src.set_buffer(AudioBuffer::from(vec![vec![1.; 128]; 2], 44_100.));
+        src.connect(&gain);
+        src.start();
+
+        let control = context.create_constant_source();
+        control.offset().set_value(0.5);
+        control.connect(gain.gain());
+        control.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0)[..],
+            [0.5; 128][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            output.get_channel_data(1)[..],
+            [0.5; 128][..],
+            abs_all <= 0.
+        );
+    }
+
+    #[test]
+    fn test_multi_channel_gain_down_mixed_to_mono_input() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+
+        let gain = context.create_gain();
+        gain.gain().set_value(0.);
+        gain.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&gain);
+        src.start();
+
+        // the stereo control is down-mixed to the mono input: (0.5 + 0.25) / 2
+        let merger = context.create_channel_merger(2);
+        let left = context.create_constant_source();
+        left.offset().set_value(0.5);
+        left.connect_at(&merger, 0, 0);
+        left.start();
+        let right = context.create_constant_source();
+        right.offset().set_value(0.25);
+        right.connect_at(&merger, 0, 1);
+        right.start();
+        merger.connect(gain.gain());
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0)[..],
+            [0.375; 128][..],
+            abs_all <= 0.
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! The gate/expander node and renderer parts
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::flush_denormal_f32;
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::{db_to_lin, lin_to_db, AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Options for constructing a [`GateNode`]
+#[derive(Clone, Debug)]
+pub struct GateOptions {
+    /// level in dB below which the signal is attenuated
+    pub threshold: f32,
+    /// time in seconds to open the gate
+    pub attack: f32,
+    /// time in seconds the gate stays open after the signal fell below the threshold
+    pub hold: f32,
+    /// time in seconds to close the gate
+    pub release: f32,
+    /// expansion ratio below the threshold, `f32::INFINITY` for a hard gate
+    pub ratio: f32,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for GateOptions {
+    fn default() -> Self {
+        Self {
+            threshold: -50.,      // dB
+            attack: 0.001,        // seconds
+            hold: 0.05,           // seconds
+            release: 0.1,         // seconds
+            ratio: f32::INFINITY, // unit less
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `GateNode` provides a noise gate / downward expander effect.
+///
+/// The parts of the signal whose level is below `threshold` are attenuated, e.g. to remove the
+/// background noise between the phrases of a microphone input. The gate opens as soon as the
+/// peak level across all channels reaches the threshold and stays open for `hold` seconds after
+/// the level fell below it, so that a signal hovering around the threshold does not make the gate
+/// chatter. The gain then moves to the attenuated value with the `attack` and `release` time
+/// constants.
+///
+/// Below the threshold, each dB under the threshold is turned into `ratio` dBs under it. With a
+/// `ratio` of `f32::INFINITY` (the default) the node is a hard gate and the closed gate is
+/// silent, with a `ratio` of `1` the node leaves the signal unchanged.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_gate`](crate::context::BaseAudioContext::create_gate)
+///
+/// # Usage
+///
+/// ```no_run
+/// use std::fs::File;
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+/// let file = File::open("samples/sample.wav").unwrap();
+/// let buffer = context.decode_audio_data_sync(file).unwrap();
+///
+/// let gate = context.create_gate();
+/// gate.threshold().set_value(-40.);
+/// gate.connect(&context.destination());
+///
+/// let src = context.create_buffer_source();
+/// src.connect(&gate);
+/// src.set_buffer(buffer);
+/// src.start();
+/// ```
+pub struct GateNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+    threshold: AudioParam,
+    attack: AudioParam,
+    hold: AudioParam,
+    release: AudioParam,
+    ratio: AudioParam,
+}
+
+impl AudioNode for GateNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl GateNode {
+    /// returns a `GateNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - gate options
+    pub fn new<C: BaseAudioContext>(context: &C, options: GateOptions) -> Self {
+        context.register(move |registration| {
+            let threshold_param_opts = AudioParamDescriptor {
+                min_value: -100.,
+                max_value: 0.,
+                default_value: -50.,
+                automation_rate: AutomationRate::K,
+            };
+            let (threshold_param, threshold_proc) =
+                context.create_audio_param(threshold_param_opts, &registration);
+            threshold_param.set_value(options.threshold);
+
+            let attack_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: 1.,
+                default_value: 0.001,
+                automation_rate: AutomationRate::K,
+            };
+            let (attack_param, attack_proc) =
+                context.create_audio_param(attack_param_opts, &registration);
+            attack_param.set_value(options.attack);
+
+            let hold_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: 1.,
+                default_value: 0.05,
+                automation_rate: AutomationRate::K,
+            };
+            let (hold_param, hold_proc) =
+                context.create_audio_param(hold_param_opts, &registration);
+            hold_param.set_value(options.hold);
+
+            let release_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: 1.,
+                default_value: 0.1,
+                automation_rate: AutomationRate::K,
+            };
+            let (release_param, release_proc) =
+                context.create_audio_param(release_param_opts, &registration);
+            release_param.set_value(options.release);
+
+            let ratio_param_opts = AudioParamDescriptor {
+                min_value: 1.,
+                max_value: f32::INFINITY,
+                default_value: f32::INFINITY,
+                automation_rate: AutomationRate::K,
+            };
+            let (ratio_param, ratio_proc) =
+                context.create_audio_param(ratio_param_opts, &registration);
+            ratio_param.set_value(options.ratio);
+
+            let render = GateRenderer {
+                threshold: threshold_proc,
+                attack: attack_proc,
+                hold: hold_proc,
+                release: release_proc,
+                ratio: ratio_proc,
+                hold_remaining: 0,
+                gain: 0.,
+                gains: vec![0.; context.render_quantum_size()],
+            };
+
+            let node = GateNode {
+                registration,
+                channel_config: options.channel_config.into(),
+                threshold: threshold_param,
+                attack: attack_param,
+                hold: hold_param,
+                release: release_param,
+                ratio: ratio_param,
+            };
+
+            (node, Box::new(render))
+        })
+    }
+
+    /// Level in dB below which the signal is attenuated
+    #[must_use]
+    pub fn threshold(&self) -> &AudioParam {
+        &self.threshold
+    }
+
+    /// Time in seconds to open the gate
+    #[must_use]
+    pub fn attack(&self) -> &AudioParam {
+        &self.attack
+    }
+
+    /// Time in seconds the gate stays open after the level fell below the threshold
+    #[must_use]
+    pub fn hold(&self) -> &AudioParam {
+        &self.hold
+    }
+
+    /// Time in seconds to close the gate
+    #[must_use]
+    pub fn release(&self) -> &AudioParam {
+        &self.release
+    }
+
+    /// Expansion ratio below the threshold, `f32::INFINITY` for a hard gate
+    #[must_use]
+    pub fn ratio(&self) -> &AudioParam {
+        &self.ratio
+    }
+}
+
+struct GateRenderer {
+    threshold: AudioParamId,
+    attack: AudioParamId,
+    hold: AudioParamId,
+    release: AudioParamId,
+    ratio: AudioParamId,
+    /// Number of samples the gate stays open while the level is below the threshold
+    hold_remaining: usize,
+    /// Current linear gain of the gate
+    gain: f32,
+    /// Linear gain of each sample of the render quantum
+    gains: Vec<f32>,
+}
+
+impl AudioProcessor for GateRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+        let sample_rate = scope.sample_rate;
+
+        let threshold = params.get(&self.threshold)[0];
+        let ratio = params.get(&self.ratio)[0];
+        let hold_samples = (params.get(&self.hold)[0] * sample_rate) as usize;
+
+        // one pole smoothing of the gain, same time constants as the compressor
+        let attack_tau = (-1. / (params.get(&self.attack)[0] * sample_rate)).exp();
+        let release_tau = (-1. / (params.get(&self.release)[0] * sample_rate)).exp();
+
+        let mut gain = self.gain;
+        let mut hold_remaining = self.hold_remaining;
+
+        // run the detector even on silent inputs so the gate closes
+        for (i, target_gain) in self.gains.iter_mut().enumerate() {
+            // peak level across all input channels
+            let level = input
+                .channels()
+                .iter()
+                .fold(0., |max: f32, channel| max.max(channel[i].abs()));
+            let level_db = lin_to_db(level);
+
+            let open = if level_db >= threshold {
+                hold_remaining = hold_samples;
+                true
+            } else if hold_remaining > 0 {
+                hold_remaining -= 1;
+                true
+            } else {
+                false
+            };
+
+            // an infinite ratio yields -Infinity dB, i.e. a hard gate
+            let target = if open {
+                1.
+            } else {
+                db_to_lin((level_db - threshold) * (ratio - 1.))
+            };
+
+            let tau = if target > gain {
+                attack_tau
+            } else {
+                release_tau
+            };
+            gain = tau * gain + (1. - tau) * target;
+            *target_gain = gain;
+        }
+
+        self.gain = flush_denormal_f32(gain);
+        self.hold_remaining = hold_remaining;
+
+        if input.is_silent() {
+            output.make_silent();
+            return false;
+        }
+
+        *output = input.clone();
+        output.channels_mut().iter_mut().for_each(|channel| {
+            channel
+                .iter_mut()
+                .zip(self.gains.iter())
+                .for_each(|(o, g)| *o *= g);
+        });
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 8000.;
+
+    // render the given signal through a gate with a -40 dB threshold
+    fn render_gate(signal: &[f32], hold: f32, ratio: f32) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, signal.len(), SAMPLE_RATE);
+
+        let mut buffer = context.create_buffer(1, signal.len(), SAMPLE_RATE);
+        buffer.copy_to_channel(signal, 0);
+
+        let gate = GateNode::new(
+            &context,
+            GateOptions {
+                threshold: -40.,
+                attack: 0.,
+                hold,
+                release: 0.001,
+                ratio,
+                ..GateOptions::default()
+            },
+        );
+        gate.connect(&context.destination());
+
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&gate);
+        src.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let gate = context.create_gate();
+
+        assert_float_eq!(gate.threshold().value(), -50., abs <= 0.);
+        assert_float_eq!(gate.attack().value(), 0.001, abs <= 0.);
+        assert_float_eq!(gate.hold().value(), 0.05, abs <= 0.);
+        assert_float_eq!(gate.release().value(), 0.1, abs <= 0.);
+        assert_eq!(gate.ratio().value(), f32::INFINITY);
+    }
+
+    #[test]
+    fn test_gate_after_hold() {
+        // loud for 2000 samples, then 60 dB down, i.e. 20 dB below the threshold
+        let mut signal = vec![1.; 4000];
+        signal[2000..].iter_mut().for_each(|s| *s = 0.001);
+
+        let hold = 0.05; // 400 samples
+        let output = render_gate(&signal, hold, f32::INFINITY);
+
+        // the loud part and the quiet part during the hold time are unchanged
+        assert_float_eq!(output[..2400], signal[..2400], abs_all <= 1e-6);
+        // the quiet part is muted once the hold time elapsed
+        assert!(output[2400] < 0.001);
+        assert_float_eq!(output[2600..], [0.; 1400][..], abs_all <= 1e-9);
+    }
+
+    #[test]
+    fn test_expander_ratio() {
+        let mut signal = vec![1.; 4000];
+        signal[2000..].iter_mut().for_each(|s| *s = 0.001);
+
+        // 20 dB below the threshold with a ratio of 2 gives 40 dB below the threshold
+        let output = render_gate(&signal, 0.05, 2.);
+        assert_float_eq!(output[..2400], signal[..2400], abs_all <= 1e-6);
+        assert_float_eq!(output[2500..], [0.0001; 1500][..], abs_all <= 1e-8);
+
+        // a ratio of 1 leaves the signal unchanged
+        let output = render_gate(&signal, 0., 1.);
+        assert_float_eq!(output[..], signal[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_hold_prevents_chatter() {
+        // signal hovering around the threshold, every 100 samples
+        let signal: Vec<f32> = (0..4000)
+            .map(|i| if (i / 100) % 2 == 0 { 0.02 } else { 0.005 })
+            .collect();
+
+        // the hold time is longer than the dips, the gate never closes
+        let output = render_gate(&signal, 0.05, f32::INFINITY);
+        assert_float_eq!(output[..], signal[..], abs_all <= 1e-6);
+
+        // without hold time the gate closes at each dip
+        let output = render_gate(&signal, 0., f32::INFINITY);
+        assert_float_eq!(output[..100], signal[..100], abs_all <= 1e-6);
+        assert!(output[199] < 1e-6);
+        assert_float_eq!(output[200], 0.02, abs <= 1e-6);
+    }
+}
@@ -0,0 +1,436 @@
+//! The Haas effect control and renderer parts
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::{
+    AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
+    TailTimeCounter,
+};
+
+/// Maximum delay of the delayed channel, in milliseconds
+const MAX_DELAY_MS: f32 = 50.;
+
+/// Channel delayed by a [`HaasNode`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum HaasSide {
+    /// The left channel is delayed, the image moves to the right
+    Left,
+    /// The right channel is delayed, the image moves to the left
+    #[default]
+    Right,
+}
+
+impl From<u32> for HaasSide {
+    fn from(i: u32) -> Self {
+        match i {
+            0 => HaasSide::Left,
+            1 => HaasSide::Right,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Options for constructing a [`HaasNode`]
+#[derive(Clone, Debug)]
+pub struct HaasOptions {
+    /// initial value for the delay parameter, in milliseconds
+    pub delay_ms: f32,
+    /// delayed channel
+    pub side: HaasSide,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for HaasOptions {
+    fn default() -> Self {
+        Self {
+            delay_ms: 10.,
+            side: HaasSide::default(),
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                count_mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        }
+    }
+}
+
+/// Assert that the channel count is valid for the HaasNode
+///
+/// # Panics
+///
+/// This function panics if given count is greater than 2
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count(count: usize) {
+    if count > 2 {
+        panic!("NotSupportedError: HaasNode channel count cannot be greater than two");
+    }
+}
+
+/// Assert that the channel count mode is valid for the HaasNode
+///
+/// # Panics
+///
+/// This function panics if given count mode is [`ChannelCountMode::Max`]
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count_mode(mode: ChannelCountMode) {
+    if mode == ChannelCountMode::Max {
+        panic!("NotSupportedError: HaasNode channel count mode cannot be set to max");
+    }
+}
+
+/// `HaasNode` widens a stereo image by delaying one channel by a few milliseconds
+///
+/// Below about 35 milliseconds, the delayed channel is not heard as an echo but fuses with the
+/// other one (the precedence or Haas effect): the sound is localized on the side of the
+/// undelayed channel and appears wider. The `delay_ms` param is interpolated between samples,
+/// so delays shorter than a sample are rendered too. A mono input is duplicated to both channels
+/// first.
+///
+/// As only the delay differs between the channels, the mono sum is comb filtered but does not
+/// cancel on broadband signals.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_haas`](crate::context::BaseAudioContext::create_haas)
+/// - see also: [`StereoWidthNode`](super::StereoWidthNode), a mid/side stereo width control
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode, HaasSide};
+///
+/// let context = AudioContext::default();
+///
+/// let haas = context.create_haas();
+/// haas.delay_ms().set_value(15.);
+/// haas.set_side(HaasSide::Left);
+/// haas.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&haas);
+/// osc.start();
+/// ```
+pub struct HaasNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Delay of the delayed channel, in milliseconds
+    delay_ms: AudioParam,
+    /// Delayed channel
+    side: Arc<AtomicU32>,
+}
+
+impl AudioNode for HaasNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn set_channel_count_mode(&self, mode: ChannelCountMode) {
+        assert_valid_channel_count_mode(mode);
+        self.channel_config.set_count_mode(mode);
+    }
+
+    fn set_channel_count(&self, count: usize) {
+        assert_valid_channel_count(count);
+        self.channel_config.set_count(count);
+    }
+}
+
+impl HaasNode {
+    /// returns a `HaasNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - haas options
+    ///
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * `options.channel_config.count` is greater than 2
+    /// * `options.channel_config.mode` is `ChannelCountMode::Max`
+    ///
+    pub fn new<C: BaseAudioContext>(context: &C, options: HaasOptions) -> Self {
+        context.register(move |registration| {
+            assert_valid_channel_count_mode(options.channel_config.count_mode);
+            assert_valid_channel_count(options.channel_config.count);
+
+            let delay_options = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: MAX_DELAY_MS,
+                default_value: 10.,
+                automation_rate: AutomationRate::A,
+            };
+            let (delay_param, delay_proc) =
+                context.create_audio_param(delay_options, &registration);
+            delay_param.set_value(options.delay_ms);
+
+            let side = Arc::new(AtomicU32::new(options.side as u32));
+
+            // room for the maximum delay and the next sample of the interpolation
+            let max_delay_samples = (MAX_DELAY_MS / 1000. * context.sample_rate()).ceil() as usize;
+            let ring_size = max_delay_samples + 2;
+
+            let renderer = HaasRenderer {
+                delay_ms: delay_proc,
+                side: side.clone(),
+                rings: [vec![0.; ring_size], vec![0.; ring_size]],
+                write_index: 0,
+                tail_time: TailTimeCounter::silent(ring_size),
+            };
+
+            let node = Self {
+                registration,
+                channel_config: options.channel_config.into(),
+                delay_ms: delay_param,
+                side,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// A-rate [`AudioParam`] of the delay of the delayed channel, in milliseconds
+    ///
+    /// The value is clamped to the range `[0, 50]`.
+    #[must_use]
+    pub fn delay_ms(&self) -> &AudioParam {
+        &self.delay_ms
+    }
+
+    /// Returns the delayed channel
+    #[must_use]
+    pub fn side(&self) -> HaasSide {
+        self.side.load(Ordering::SeqCst).into()
+    }
+
+    /// Set the delayed channel
+    pub fn set_side(&self, side: HaasSide) {
+        self.side.store(side as u32, Ordering::SeqCst);
+    }
+}
+
+/// `HaasRenderer` represents the rendering part of `HaasNode`
+struct HaasRenderer {
+    /// Delay of the delayed channel, in milliseconds
+    delay_ms: AudioParamId,
+    /// Delayed channel
+    side: Arc<AtomicU32>,
+    /// Latest input samples of each channel, both are recorded so the side can change
+    rings: [Vec<f32>; 2],
+    /// Index of the ring buffers where the next sample is written
+    write_index: usize,
+    /// Number of silent input samples since the latest sound, to report the tail time
+    tail_time: TailTimeCounter,
+}
+
+impl AudioProcessor for HaasRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+        let ring_size = self.rings[0].len();
+
+        // the latest sound has left the ring buffers
+        if self
+            .tail_time
+            .tail_ended(input, ring_size, scope.render_quantum_size)
+        {
+            output.make_silent();
+            return false;
+        }
+
+        // duplicate a mono input to both channels, the node always outputs stereo
+        *output = input.clone();
+        if output.number_of_channels() == 1 {
+            output.mix(2, ChannelInterpretation::Speakers);
+        }
+
+        let delayed = match HaasSide::from(self.side.load(Ordering::SeqCst)) {
+            HaasSide::Left => 0,
+            HaasSide::Right => 1,
+        };
+        let delay_values = params.get(&self.delay_ms);
+        let samples_per_ms = scope.sample_rate / 1000.;
+
+        let mut write_index = self.write_index;
+        let [left, right] = output.stereo_mut();
+        let [ring_left, ring_right] = &mut self.rings;
+
+        left.iter_mut()
+            .zip(right.iter_mut())
+            .zip(delay_values.iter().cycle())
+            .for_each(|((l, r), &delay_ms)| {
+                ring_left[write_index] = *l;
+                ring_right[write_index] = *r;
+
+                let (ring, sample) = if delayed == 0 {
+                    (&*ring_left, l)
+                } else {
+                    (&*ring_right, r)
+                };
+
+                // linear interpolation between the samples around the playback position
+                let position = delay_ms * samples_per_ms;
+                let offset = position.floor();
+                let k = position - offset;
+                let offset = offset as usize;
+                let prev = ring[(write_index + ring_size - offset) % ring_size];
+                let next = ring[(write_index + 2 * ring_size - offset - 1) % ring_size];
+                *sample = (1. - k).mul_add(prev, k * next);
+
+                write_index = (write_index + 1) % ring_size;
+            });
+
+        self.write_index = write_index;
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioScheduledSourceNode, NoiseSourceOptions};
+
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48_000.;
+
+    // render a mono dirac at frame 100 through a haas node
+    fn render_dirac(delay_ms: f32, side: HaasSide) -> (Vec<f32>, Vec<f32>) {
+        let context = OfflineAudioContext::new(2, 1024, SAMPLE_RATE);
+
+        let mut buffer = context.create_buffer(1, 1024, SAMPLE_RATE);
+        let mut dirac = [0.; 1024];
+        dirac[100] = 1.;
+        buffer.copy_to_channel(&dirac, 0);
+
+        let haas = HaasNode::new(
+            &context,
+            HaasOptions {
+                delay_ms,
+                side,
+                ..HaasOptions::default()
+            },
+        );
+        haas.connect(&context.destination());
+
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&haas);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        (
+            output.get_channel_data(0).to_vec(),
+            output.get_channel_data(1).to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(2, 1, SAMPLE_RATE);
+        let haas = context.create_haas();
+        assert_float_eq!(haas.delay_ms().value(), 10., abs <= 0.);
+        assert_eq!(haas.side(), HaasSide::Right);
+
+        haas.set_side(HaasSide::Left);
+        assert_eq!(haas.side(), HaasSide::Left);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_channel_count() {
+        let context = OfflineAudioContext::new(2, 1, SAMPLE_RATE);
+        let haas = context.create_haas();
+        haas.set_channel_count(3);
+    }
+
+    #[test]
+    fn test_delayed_channel() {
+        // 5 ms, i.e. 240 samples
+        let (left, right) = render_dirac(5., HaasSide::Right);
+        let mut expected = [0.; 1024];
+        expected[100] = 1.;
+        assert_float_eq!(left[..], expected[..], abs_all <= 0.);
+        let mut expected = [0.; 1024];
+        expected[340] = 1.;
+        assert_float_eq!(right[..], expected[..], abs_all <= 1e-6);
+
+        // the other side
+        let (left, right) = render_dirac(5., HaasSide::Left);
+        assert_float_eq!(left[340], 1., abs <= 1e-6);
+        assert_float_eq!(right[100], 1., abs <= 0.);
+    }
+
+    #[test]
+    fn test_sub_sample_delay() {
+        // half a sample
+        let (left, right) = render_dirac(0.5 / SAMPLE_RATE * 1000., HaasSide::Right);
+        assert_float_eq!(left[100], 1., abs <= 0.);
+        assert_float_eq!(right[99..103], [0., 0.5, 0.5, 0.][..], abs_all <= 1e-3);
+    }
+
+    #[test]
+    fn test_mono_sum() {
+        let length = SAMPLE_RATE as usize;
+        let context = OfflineAudioContext::new(2, length, SAMPLE_RATE);
+
+        let haas = context.create_haas();
+        haas.connect(&context.destination());
+
+        let noise = crate::node::NoiseSourceNode::new(
+            &context,
+            NoiseSourceOptions {
+                seed: Some(42),
+                ..NoiseSourceOptions::default()
+            },
+        );
+        noise.connect(&haas);
+        noise.start();
+
+        let output = context.start_rendering_sync();
+        let left = output.get_channel_data(0);
+        let right = output.get_channel_data(1);
+
+        // the delayed channel of uncorrelated noise halves the energy of the mono sum instead
+        // of cancelling it
+        let energy = |signal: &mut dyn Iterator<Item = f32>| -> f32 { signal.map(|v| v * v).sum() };
+        let input_energy = energy(&mut left.iter().copied());
+        let sum_energy = energy(&mut left.iter().zip(right).map(|(l, r)| (l + r) / 2.));
+        assert_float_eq!(sum_energy / input_energy, 0.5, abs <= 0.05);
+    }
+}
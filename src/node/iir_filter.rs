@@ -4,7 +4,7 @@ use std::f64::consts::PI;
 
 use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
-use crate::MAX_CHANNELS;
+use crate::{flush_denormal, MAX_CHANNELS};
 
 use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
 
@@ -292,6 +292,13 @@ impl IirFilterRenderer {
             states,
         }
     }
+
+    /// `true` when the states of all channels are zero, i.e. the filter has no tail left
+    fn states_are_zero(&self) -> bool {
+        self.states
+            .iter()
+            .all(|state| state.iter().all(|&v| v == 0.))
+    }
 }
 
 impl AudioProcessor for IirFilterRenderer {
@@ -307,22 +314,10 @@ impl AudioProcessor for IirFilterRenderer {
         let output = &mut outputs[0];
 
         // handle tail time
-        if input.is_silent() {
-            let mut ended = true;
-
-            // if all values in states are 0., we have nothing left to process
-            self.states.iter().all(|state| {
-                if state.iter().any(|&v| v.is_normal()) {
-                    ended = false;
-                }
-                // if ended is false, `iter().all` will stop early
-                ended
-            });
-
-            if ended {
-                output.make_silent();
-                return false;
-            }
+        // if all values in states are 0., we have nothing left to process
+        if input.is_silent() && self.states_are_zero() {
+            output.make_silent();
+            return false;
         }
 
         // eventually resize state according to input number of channels
@@ -373,17 +368,26 @@ impl AudioProcessor for IirFilterRenderer {
             }
         }
 
-        true
+        // flush the decayed values to zero
+        self.states
+            .iter_mut()
+            .flat_map(|state| state.iter_mut())
+            .for_each(|v| *v = flush_denormal(*v));
+
+        // the tail time ends as soon as the decayed states have been flushed to zero
+        !(input.is_silent() && self.states_are_zero())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use float_eq::assert_float_eq;
+    use rustc_hash::FxHashMap;
     use std::fs::File;
 
-    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::context::{AudioNodeId, BaseAudioContext, OfflineAudioContext};
     use crate::node::{AudioNode, AudioScheduledSourceNode, BiquadFilterType};
+    use crate::render::Alloc;
     use crate::AudioBuffer;
 
     use super::*;
@@ -879,4 +883,82 @@ mod tests {
         let feedforward = vec![b0, b1, b2];
         compare_frequency_response(BiquadFilterType::Highshelf, feedback, feedforward);
     }
+
+    #[test]
+    fn test_flush_denormals() {
+        let sample_rate = 8000.;
+        let context = OfflineAudioContext::new(1, 8000, sample_rate);
+
+        // a loud transient followed by silence
+        let mut impulse = vec![0.; 128];
+        impulse[0] = 100.;
+        let buffer = AudioBuffer::from(vec![impulse], sample_rate);
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.start();
+
+        // one pole lowpass, decays by a factor 0.9 at each sample
+        let iir = context.create_iir_filter(vec![0.1, 0.], vec![1., -0.9]);
+        src.connect(&iir);
+        iir.connect(&context.destination());
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        // the state decays to exactly zero instead of lingering as subnormal numbers
+        assert!(output.iter().all(|v| !v.is_subnormal()));
+        assert_float_eq!(output[0], 10., abs <= 1e-6);
+        assert_float_eq!(output[4000..], [0.; 4000][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_flush_denormals_ends_tail() {
+        let alloc = Alloc::with_capacity(2);
+        let nodes = FxHashMap::default();
+        let scope = RenderScope {
+            current_frame: 0,
+            current_time: 0.,
+            sample_rate: 8000.,
+            render_quantum_size: crate::RENDER_QUANTUM_SIZE,
+            event_sender: None,
+            node_id: std::cell::Cell::new(AudioNodeId(0)),
+            inputs_connected: std::cell::Cell::new(0),
+        };
+
+        // one pole lowpass, decays by a factor 0.9 at each sample
+        let mut renderer = IirFilterRenderer::new(vec![0.1, 0.], vec![1., -0.9]);
+
+        let mut impulse = AudioRenderQuantum::from(alloc.silence());
+        impulse.channel_data_mut(0)[0] = 100.;
+        let mut outputs = [AudioRenderQuantum::from(alloc.silence())];
+        let tail = renderer.process(
+            &[impulse],
+            &mut outputs,
+            AudioParamValues::from(&nodes),
+            &scope,
+        );
+        assert!(tail);
+        assert!(!renderer.states_are_zero());
+
+        // 10 * 0.9^n drops below the threshold after about 350 samples, i.e. in the second
+        // silent render quantum
+        let silence = [AudioRenderQuantum::from(alloc.silence())];
+        let quanta = (0..10)
+            .take_while(|_| {
+                renderer.process(
+                    &silence,
+                    &mut outputs,
+                    AudioParamValues::from(&nodes),
+                    &scope,
+                )
+            })
+            .count();
+        assert_eq!(quanta, 1);
+
+        // the tail ends in the render quantum where the states are flushed to exactly zero
+        renderer
+            .states
+            .iter()
+            .for_each(|state| assert_float_eq!(state[..], [0.][..], abs_all <= 0.));
+    }
 }
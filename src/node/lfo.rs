@@ -0,0 +1,552 @@
+use std::error::Error;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::control::Scheduler;
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::noise::Rng;
+use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig};
+
+/// Options for constructing a [`LfoNode`]
+#[derive(Clone, Debug)]
+pub struct LfoOptions {
+    /// The shape of the modulation signal
+    pub type_: LfoType,
+    /// Number of cycles per second
+    pub frequency: f32,
+    /// Peak deviation of the modulation signal from its offset
+    pub amplitude: f32,
+    /// Center value of the modulation signal
+    pub offset: f32,
+    /// Seed of the random number generator of [`LfoType::SampleAndHold`], use `None` for a
+    /// random seed
+    ///
+    /// Rendering with the same seed yields identical output, which is useful for testing.
+    pub seed: Option<u64>,
+}
+
+impl Default for LfoOptions {
+    fn default() -> Self {
+        Self {
+            type_: LfoType::default(),
+            frequency: 1.,
+            amplitude: 1.,
+            offset: 0.,
+            seed: None,
+        }
+    }
+}
+
+/// Shape of the modulation signal rendered by a `LfoNode`
+///
+/// All shapes start at the beginning of their cycle, i.e. at zero and rising except for
+/// `SampleAndHold`, and are in the [-1, 1] range before applying the amplitude and offset.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum LfoType {
+    /// Sine wave
+    #[default]
+    Sine,
+    /// Triangle wave
+    Triangle,
+    /// Square wave, positive during the first half of the cycle
+    Square,
+    /// Rising sawtooth wave, jumping from 1 to -1 halfway the cycle
+    Sawtooth,
+    /// New random value at the start of every cycle, held for the rest of the cycle
+    SampleAndHold,
+}
+
+impl From<u32> for LfoType {
+    fn from(i: u32) -> Self {
+        match i {
+            0 => LfoType::Sine,
+            1 => LfoType::Triangle,
+            2 => LfoType::Square,
+            3 => LfoType::Sawtooth,
+            4 => LfoType::SampleAndHold,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// `LfoNode` is a lightweight low frequency oscillator, generating control signals to modulate
+/// `AudioParam`s
+///
+/// Contrary to the `OscillatorNode`, the waveforms are not band-limited, which is what is
+/// expected from a modulation source. The output is `offset + amplitude * waveform`. The phase
+/// is accumulated in double precision, so very slow modulations (e.g. 0.01Hz) stay accurate
+/// over long renders.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_lfo`](crate::context::BaseAudioContext::create_lfo)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode, LfoType};
+///
+/// let context = AudioContext::default();
+///
+/// let osc = context.create_oscillator();
+/// let tremolo = context.create_gain();
+/// osc.connect(&tremolo);
+/// tremolo.connect(&context.destination());
+///
+/// // modulate the gain between 0.25 and 0.75, twice per second
+/// tremolo.gain().set_value(0.);
+/// let lfo = context.create_lfo();
+/// lfo.set_type(LfoType::Triangle);
+/// lfo.frequency().set_value(2.);
+/// lfo.amplitude().set_value(0.25);
+/// lfo.offset().set_value(0.5);
+/// lfo.connect(tremolo.gain());
+///
+/// osc.start();
+/// lfo.start();
+/// ```
+pub struct LfoNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Shape of the modulation signal
+    type_: Arc<AtomicU32>,
+    /// Number of cycles per second
+    frequency: AudioParam,
+    /// Peak deviation from the offset
+    amplitude: AudioParam,
+    /// Center value
+    offset: AudioParam,
+    /// starts and stops the modulation
+    scheduler: Scheduler,
+}
+
+impl AudioNode for LfoNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioScheduledSourceNode for LfoNode {
+    fn try_start_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_start_at(when)
+    }
+
+    fn try_stop_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_stop_at(when)
+    }
+}
+
+impl LfoNode {
+    /// Returns a `LfoNode`
+    ///
+    /// # Arguments:
+    ///
+    /// * `context` - The `AudioContext`
+    /// * `options` - The LfoOptions
+    pub fn new<C: BaseAudioContext>(context: &C, options: LfoOptions) -> Self {
+        context.register(move |registration| {
+            let LfoOptions {
+                type_,
+                frequency,
+                amplitude,
+                offset,
+                seed,
+            } = options;
+
+            let nyquist = context.sample_rate() / 2.;
+            let frequency_opts = AudioParamDescriptor {
+                min_value: -nyquist,
+                max_value: nyquist,
+                default_value: 1.,
+                automation_rate: AutomationRate::A,
+            };
+            let (f_param, f_proc) = context.create_audio_param(frequency_opts, &registration);
+            f_param.set_value(frequency);
+
+            let amplitude_opts = AudioParamDescriptor {
+                min_value: f32::MIN,
+                max_value: f32::MAX,
+                default_value: 1.,
+                automation_rate: AutomationRate::A,
+            };
+            let (a_param, a_proc) = context.create_audio_param(amplitude_opts, &registration);
+            a_param.set_value(amplitude);
+
+            let offset_opts = AudioParamDescriptor {
+                min_value: f32::MIN,
+                max_value: f32::MAX,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+            let (o_param, o_proc) = context.create_audio_param(offset_opts, &registration);
+            o_param.set_value(offset);
+
+            let type_ = Arc::new(AtomicU32::new(type_ as u32));
+            let scheduler = Scheduler::new();
+
+            let seed = seed.unwrap_or_else(|| context.base().random_seed(registration.id()));
+
+            let renderer = LfoRenderer {
+                type_: type_.clone(),
+                frequency: f_proc,
+                amplitude: a_proc,
+                offset: o_proc,
+                scheduler: scheduler.clone(),
+                rng: Rng::new(seed),
+                phase: 0.,
+                held: 0.,
+                started: false,
+                ended_triggered: false,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: ChannelConfig::default(),
+                type_,
+                frequency: f_param,
+                amplitude: a_param,
+                offset: o_param,
+                scheduler,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// Returns the shape of the modulation signal
+    #[must_use]
+    pub fn type_(&self) -> LfoType {
+        self.type_.load(Ordering::SeqCst).into()
+    }
+
+    /// Set the shape of the modulation signal
+    ///
+    /// The phase is kept, so the modulation continues smoothly in time.
+    pub fn set_type(&self, type_: LfoType) {
+        self.type_.store(type_ as u32, Ordering::SeqCst);
+    }
+
+    /// A-rate [`AudioParam`] that defines the number of cycles per second
+    #[must_use]
+    pub fn frequency(&self) -> &AudioParam {
+        &self.frequency
+    }
+
+    /// A-rate [`AudioParam`] that defines the peak deviation of the signal from its offset
+    #[must_use]
+    pub fn amplitude(&self) -> &AudioParam {
+        &self.amplitude
+    }
+
+    /// A-rate [`AudioParam`] that defines the center value of the signal
+    #[must_use]
+    pub fn offset(&self) -> &AudioParam {
+        &self.offset
+    }
+}
+
+struct LfoRenderer {
+    /// Shape of the modulation signal
+    type_: Arc<AtomicU32>,
+    /// Number of cycles per second
+    frequency: AudioParamId,
+    /// Peak deviation from the offset
+    amplitude: AudioParamId,
+    /// Center value
+    offset: AudioParamId,
+    /// starts and stops the modulation
+    scheduler: Scheduler,
+    /// random values of the sample and hold shape
+    rng: Rng,
+    /// position in the current cycle, in the [0, 1) range
+    phase: f64,
+    /// current value of the sample and hold shape
+    held: f32,
+    /// defines if the first sample was rendered
+    started: bool,
+    /// defines if the `ended` events was already dispatched
+    ended_triggered: bool,
+}
+
+impl LfoRenderer {
+    #[inline]
+    fn waveform(&self, type_: LfoType) -> f32 {
+        let phase = self.phase;
+        let value = match type_ {
+            LfoType::Sine => (2. * PI * phase).sin(),
+            LfoType::Triangle => {
+                if phase < 0.25 {
+                    4. * phase
+                } else if phase < 0.75 {
+                    2. - 4. * phase
+                } else {
+                    4. * phase - 4.
+                }
+            }
+            LfoType::Square => {
+                if phase < 0.5 {
+                    1.
+                } else {
+                    -1.
+                }
+            }
+            LfoType::Sawtooth => {
+                if phase < 0.5 {
+                    2. * phase
+                } else {
+                    2. * phase - 2.
+                }
+            }
+            LfoType::SampleAndHold => return self.held,
+        };
+
+        value as f32
+    }
+}
+
+impl AudioProcessor for LfoRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+
+        let dt = 1. / scope.sample_rate as f64;
+        let next_block_time = scope.current_time + dt * scope.render_quantum_size as f64;
+
+        let start_time = self.scheduler.get_start_at();
+        let stop_time = self.scheduler.get_stop_at();
+
+        if start_time >= next_block_time {
+            output.make_silent();
+            return true;
+        }
+
+        output.ensure_mono();
+
+        let type_: LfoType = self.type_.load(Ordering::SeqCst).into();
+        let frequency = params.get(&self.frequency);
+        let amplitude = params.get(&self.amplitude);
+        let offset = params.get(&self.offset);
+        let mut current_time = scope.current_time;
+
+        output
+            .channel_data_mut(0)
+            .iter_mut()
+            .zip(frequency.iter().cycle())
+            .zip(amplitude.iter().cycle().zip(offset.iter().cycle()))
+            .for_each(|((o, &frequency), (&amplitude, &offset))| {
+                if current_time < start_time || current_time >= stop_time {
+                    *o = 0.;
+                } else {
+                    if !self.started {
+                        self.held = self.rng.next_bipolar();
+                        self.started = true;
+                    }
+
+                    *o = amplitude.mul_add(self.waveform(type_), offset);
+
+                    // a new cycle starts when the phase wraps around, in either direction
+                    let phase = self.phase + f64::from(frequency) * dt;
+                    self.phase = phase.rem_euclid(1.);
+                    if !(0. ..1.).contains(&phase) {
+                        self.held = self.rng.next_bipolar();
+                    }
+                }
+
+                current_time += dt;
+            });
+
+        // tail_time false when output has ended this quantum
+        let still_running = stop_time >= next_block_time;
+
+        if !still_running && !self.ended_triggered {
+            scope.send_ended_event();
+            self.ended_triggered = true;
+        }
+
+        still_running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    use super::*;
+
+    /// Expected waveform for a given phase
+    type Shape = fn(f64) -> f64;
+
+    /// Render the LFO modulating the gain of a constant signal
+    fn render_modulated_gain(options: LfoOptions, length: usize) -> Vec<f32> {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, length, sample_rate);
+
+        let src = context.create_constant_source();
+        let gain = context.create_gain();
+        gain.gain().set_value(0.);
+        src.connect(&gain);
+        gain.connect(&context.destination());
+        src.start();
+
+        let lfo = LfoNode::new(&context, options);
+        lfo.connect(gain.gain());
+        lfo.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_waveforms() {
+        // 100 cycles per second is 480 samples per cycle
+        let period = 480;
+        let shapes: [(LfoType, Shape); 4] = [
+            (LfoType::Sine, |p| (2. * PI * p).sin()),
+            (LfoType::Triangle, |p| {
+                if p < 0.25 {
+                    4. * p
+                } else if p < 0.75 {
+                    2. - 4. * p
+                } else {
+                    4. * p - 4.
+                }
+            }),
+            (LfoType::Square, |p| if p < 0.5 { 1. } else { -1. }),
+            (LfoType::Sawtooth, |p| {
+                if p < 0.5 {
+                    2. * p
+                } else {
+                    2. * p - 2.
+                }
+            }),
+        ];
+
+        for (type_, shape) in shapes {
+            let options = LfoOptions {
+                type_,
+                frequency: 100.,
+                amplitude: 0.5,
+                offset: 0.25,
+                ..LfoOptions::default()
+            };
+            let result = render_modulated_gain(options, 2 * period);
+
+            let expected: Vec<f32> = (0..2 * period)
+                .map(|i| {
+                    let phase = (i % period) as f64 / period as f64;
+                    (0.25 + 0.5 * shape(phase)) as f32
+                })
+                .collect();
+
+            // the accumulated phase may land on either side of a jump, skip these samples
+            result
+                .iter()
+                .zip(&expected)
+                .enumerate()
+                .filter(|(i, _)| i % (period / 2) != 0)
+                .for_each(|(_, (&r, &e))| assert_float_eq!(r, e, abs <= 1e-5));
+        }
+    }
+
+    #[test]
+    fn test_sample_and_hold() {
+        let period = 480;
+        let options = LfoOptions {
+            type_: LfoType::SampleAndHold,
+            frequency: 100.,
+            seed: Some(42),
+            ..LfoOptions::default()
+        };
+        let result = render_modulated_gain(options.clone(), 4 * period);
+
+        // value is held for a whole cycle, and changes every cycle
+        let cycles: Vec<&[f32]> = result.chunks(period).collect();
+        // (the accumulated phase may wrap one sample early or late)
+        for cycle in &cycles {
+            assert!(cycle[1..period - 1].iter().all(|&v| v == cycle[1]));
+            assert!((-1. ..1.).contains(&cycle[1]));
+        }
+        for pair in cycles.windows(2) {
+            assert!(pair[0][1] != pair[1][1]);
+        }
+
+        // reproducible with the same seed
+        let again = render_modulated_gain(options.clone(), 4 * period);
+        assert_float_eq!(result[..], again[..], abs_all <= 0.);
+
+        let other = LfoOptions {
+            seed: Some(43),
+            ..options
+        };
+        let other = render_modulated_gain(other, 4 * period);
+        assert!(result != other);
+    }
+
+    #[test]
+    fn test_very_low_frequency() {
+        let sample_rate = 48000;
+        let frequency = 0.01;
+        let options = LfoOptions {
+            frequency,
+            ..LfoOptions::default()
+        };
+        let result = render_modulated_gain(options, sample_rate * 2);
+
+        let expected: Vec<f32> = (0..sample_rate * 2)
+            .map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                (2. * PI * f64::from(frequency) * t).sin() as f32
+            })
+            .collect();
+
+        // the signal keeps rising at the expected rate, without precision loss
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-6);
+        assert!(result.windows(2).all(|w| w[1] > w[0]));
+    }
+
+    #[test]
+    fn test_start_stop() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 256, sample_rate);
+
+        let lfo = context.create_lfo();
+        lfo.set_type(LfoType::Square);
+        lfo.connect(&context.destination());
+        lfo.start_at(9.5 / sample_rate as f64);
+        lfo.stop_at(199.5 / sample_rate as f64);
+
+        let result = context.start_rendering_sync();
+        let channel = result.get_channel_data(0);
+
+        assert_float_eq!(channel[..10], [0.; 10][..], abs_all <= 0.);
+        assert_float_eq!(channel[10..200], [1.; 190][..], abs_all <= 0.);
+        assert_float_eq!(channel[200..], [0.; 56][..], abs_all <= 0.);
+    }
+}
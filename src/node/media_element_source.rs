@@ -1,6 +1,5 @@
 use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::media::{MediaElement, Resampler};
-use crate::RENDER_QUANTUM_SIZE;
 
 use super::{AudioNode, ChannelConfig, MediaStreamRenderer};
 
@@ -85,7 +84,8 @@ impl MediaElementAudioSourceNode {
                 .take_stream()
                 .expect("stream already taken");
 
-            let resampler = Resampler::new(context.sample_rate(), RENDER_QUANTUM_SIZE, stream);
+            let resampler =
+                Resampler::new(context.sample_rate(), context.render_quantum_size(), stream);
 
             let render = MediaStreamRenderer::new(resampler);
 
@@ -1,6 +1,5 @@
 use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::media::{MediaStream, Resampler};
-use crate::RENDER_QUANTUM_SIZE;
 
 use super::{AudioNode, ChannelConfig, MediaStreamRenderer};
 
@@ -54,7 +53,7 @@ impl MediaStreamAudioSourceNode {
 
             let resampler = Resampler::new(
                 context.sample_rate(),
-                RENDER_QUANTUM_SIZE,
+                context.render_quantum_size(),
                 options.media_stream,
             );
 
@@ -1,9 +1,10 @@
 //! The AudioNode interface and concrete types
+use std::error::Error;
 use std::f32::consts::PI;
 use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use crate::context::{AudioContextRegistration, ConcreteBaseAudioContext};
+use crate::context::{AudioContextRegistration, BaseAudioContext, ConcreteBaseAudioContext};
 use crate::media::MediaStream;
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 
@@ -13,38 +14,85 @@ mod analyser;
 pub use analyser::*;
 mod audio_buffer_source;
 pub use audio_buffer_source::*;
+mod audio_worklet;
+pub(crate) use audio_worklet::WorkletDefinition;
+pub use audio_worklet::{
+    AudioWorkletNode, AudioWorkletNodeOptions, Worklet, WorkletParamDescriptor, WorkletParamValues,
+};
+mod balance;
+pub use balance::*;
 mod biquad_filter;
 pub use biquad_filter::*;
+mod bit_crusher;
+pub use bit_crusher::*;
 mod channel_merger;
 pub use channel_merger::*;
 mod channel_splitter;
 pub use channel_splitter::*;
+mod comb_filter;
+pub use comb_filter::*;
 mod constant_source;
 pub use constant_source::*;
 mod convolver;
 pub use convolver::*;
+mod crossover;
+pub use crossover::*;
 mod delay;
 pub use delay::*;
 mod destination;
 pub use destination::*;
 mod dynamics_compressor;
 pub use dynamics_compressor::*;
+mod fft_eq;
+pub use fft_eq::*;
+mod file_player;
+pub use file_player::*;
+mod filtered_noise;
+pub use filtered_noise::*;
+mod frequency_shifter;
+pub use frequency_shifter::*;
 mod gain;
 pub use gain::*;
+mod gate;
+pub use gate::*;
+mod haas;
+pub use haas::*;
 mod iir_filter;
 pub use iir_filter::*;
+mod lfo;
+pub use lfo::*;
 mod media_element_source;
 pub use media_element_source::*;
 mod media_stream_destination;
 pub use media_stream_destination::*;
 mod media_stream_source;
 pub use media_stream_source::*;
+mod noise;
+pub use noise::*;
 mod oscillator;
 pub use oscillator::*;
 mod panner;
 pub use panner::*;
+mod phase_vocoder;
+mod pitch_shift;
+pub use pitch_shift::*;
+mod quadrature_oscillator;
+pub use quadrature_oscillator::*;
+mod reverb;
+pub use reverb::*;
+mod ring_modulator;
+pub use ring_modulator::*;
 mod stereo_panner;
 pub use stereo_panner::*;
+mod stereo_width;
+pub use stereo_width::*;
+mod stft;
+mod super_saw;
+pub use super_saw::*;
+#[cfg(test)]
+mod test_util;
+mod time_stretch;
+pub use time_stretch::*;
 mod waveshaper;
 use crate::events::Callback;
 pub use waveshaper::*;
@@ -65,8 +113,64 @@ lazy_static! {
     };
 }
 
+// Converting a value 𝑣 in decibels to linear gain unit means returning 10𝑣/20.
+pub(crate) fn db_to_lin(val: f32) -> f32 {
+    (10.0_f32).powf(val / 20.)
+}
+
+// Converting a value 𝑣 in linear gain unit to decibel means executing the following steps:
+// If 𝑣 is equal to zero, return -1000.
+// Else, return 20log10𝑣.
+pub(crate) fn lin_to_db(val: f32) -> f32 {
+    if val == 0. {
+        -1000.
+    } else {
+        20. * val.log10() // 20 * log10(val);
+    }
+}
+
+/// Number of silent input samples since the latest sound, to report the tail time of a node
+#[derive(Debug, Default)]
+pub(crate) struct TailTimeCounter {
+    silent_samples: usize,
+}
+
+impl TailTimeCounter {
+    /// Counter of a node that has no sound to play out yet
+    pub(crate) fn silent(tail_samples: usize) -> Self {
+        Self {
+            silent_samples: tail_samples,
+        }
+    }
+
+    /// Count the input of the render quantum, returns `true` once the latest sound has played
+    /// out, i.e. the input has been silent for at least `tail_samples` samples
+    pub(crate) fn tail_ended(
+        &mut self,
+        input: &AudioRenderQuantum,
+        tail_samples: usize,
+        render_quantum_size: usize,
+    ) -> bool {
+        if input.is_silent() {
+            if self.silent_samples >= tail_samples {
+                return true;
+            }
+            self.silent_samples += render_quantum_size;
+        } else {
+            self.silent_samples = 0;
+        }
+
+        false
+    }
+}
+
 /// How channels must be matched between the node's inputs and outputs.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
 pub enum ChannelCountMode {
     /// `computedNumberOfChannels` is the maximum of the number of channels of all connections to an
     /// input. In this mode channelCount is ignored.
@@ -93,6 +197,11 @@ impl From<u32> for ChannelCountMode {
 
 /// The meaning of the channels, defining how audio up-mixing and down-mixing will happen.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum ChannelInterpretation {
     Speakers,
     Discrete,
@@ -112,6 +221,11 @@ impl From<u32> for ChannelInterpretation {
 
 /// Options that can be used in constructing all AudioNodes.
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct ChannelConfigOptions {
     /// Desired number of channels for the [`AudioNode::channel_count`] attribute.
     pub count: usize,
@@ -170,7 +284,7 @@ impl ChannelConfig {
     pub(crate) fn count_mode(&self) -> ChannelCountMode {
         self.count_mode.load(Ordering::SeqCst).into()
     }
-    fn set_count_mode(&self, v: ChannelCountMode) {
+    pub(crate) fn set_count_mode(&self, v: ChannelCountMode) {
         self.count_mode.store(v as u32, Ordering::SeqCst)
     }
 
@@ -204,6 +318,16 @@ impl From<ChannelConfigOptions> for ChannelConfig {
     }
 }
 
+impl From<&ChannelConfig> for ChannelConfigOptions {
+    fn from(config: &ChannelConfig) -> Self {
+        Self {
+            count: config.count(),
+            count_mode: config.count_mode(),
+            interpretation: config.interpretation(),
+        }
+    }
+}
+
 /// This interface represents audio sources, the audio destination, and intermediate processing
 /// modules.
 ///
@@ -247,15 +371,7 @@ pub trait AudioNode {
         output: usize,
         input: usize,
     ) -> &'a dyn AudioNode {
-        if self.context() != dest.context() {
-            panic!("InvalidAccessError: Attempting to connect nodes from different contexts");
-        }
-        if self.number_of_outputs() <= output {
-            panic!("IndexSizeError: output port {} is out of bounds", output);
-        }
-        if dest.number_of_inputs() <= input {
-            panic!("IndexSizeError: input port {} is out of bounds", input);
-        }
+        assert_valid_connection(self, dest, output, input);
 
         self.context().connect(
             self.registration().id(),
@@ -266,6 +382,61 @@ pub trait AudioNode {
         dest
     }
 
+    /// Connect the output of this AudioNode to the input of another node, rejecting the
+    /// connection if it would create a cycle that does not contain a [`DelayNode`].
+    ///
+    /// The render thread mutes the nodes of such a cycle, as mandated by the specification. Use
+    /// this method instead of [`connect`](Self::connect) to catch these at connect time. A cycle
+    /// closed through a node whose handles have been dropped is detected as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection would create a cycle without a [`DelayNode`], the
+    /// connection is not made in that case.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when
+    /// - the AudioContext of the source and destination does not match
+    fn try_connect<'a>(
+        &self,
+        dest: &'a dyn AudioNode,
+    ) -> Result<&'a dyn AudioNode, Box<dyn Error + Send + Sync>> {
+        self.try_connect_at(dest, 0, 0)
+    }
+
+    /// Connect a specific output of this AudioNode to a specific input of another node,
+    /// rejecting the connection if it would create a cycle that does not contain a
+    /// [`DelayNode`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection would create a cycle without a [`DelayNode`], the
+    /// connection is not made in that case.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic when
+    /// - the AudioContext of the source and destination does not match
+    /// - if the input port is out of bounds for the destination node
+    /// - if the output port is out of bounds for the source node
+    fn try_connect_at<'a>(
+        &self,
+        dest: &'a dyn AudioNode,
+        output: usize,
+        input: usize,
+    ) -> Result<&'a dyn AudioNode, Box<dyn Error + Send + Sync>> {
+        assert_valid_connection(self, dest, output, input);
+
+        self.context().try_connect(
+            self.registration().id(),
+            dest.registration().id(),
+            output,
+            input,
+        )?;
+        Ok(dest)
+    }
+
     /// Disconnects all outputs of the AudioNode that go to a specific destination AudioNode.
     fn disconnect_from<'a>(&self, dest: &'a dyn AudioNode) -> &'a dyn AudioNode {
         if self.context() != dest.context() {
@@ -283,6 +454,56 @@ pub trait AudioNode {
         self.context().disconnect(self.registration().id());
     }
 
+    /// Bypass the processing of this AudioNode, or enable it again.
+    ///
+    /// A bypassed node passes its first input unchanged to its first output, after the up/down-
+    /// mixing dictated by its channel configuration. Any other outputs, and all outputs of source
+    /// nodes (which have no inputs), are silent.
+    ///
+    /// The toggle takes effect at the next render quantum. If the node has already been
+    /// rendered, its first output crossfades between the processed and the passed through
+    /// signal over that quantum to avoid clicks.
+    ///
+    /// The processor is not run while bypassed: its internal state is frozen and its latest
+    /// reported tail time is kept, so a bypassed node is not released while its processor
+    /// still had a tail. In particular, a bypassed source node does not advance and does not
+    /// emit its `ended` event until it is enabled again.
+    fn set_bypass(&self, bypassed: bool) {
+        self.context()
+            .set_bypass(self.registration().id(), bypassed);
+    }
+
+    /// Mute the outputs of this AudioNode, or unmute them again.
+    ///
+    /// The processor keeps running while muted, only its outputs are silenced. The toggle takes
+    /// effect at the next render quantum, and fades over that quantum if the node has already
+    /// been rendered. A muted node stays silent when it is soloed.
+    ///
+    /// A node on which this method or [`set_soloed`](AudioNode::set_soloed) has been called
+    /// becomes a mixer channel, and is silenced when other nodes are soloed.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    fn set_muted(&self, muted: bool) {
+        self.context().set_muted(self.registration().id(), muted);
+    }
+
+    /// Solo this AudioNode, or unsolo it again.
+    ///
+    /// While any node is soloed, all mixer channels that are not soloed are silenced. Solos are
+    /// additive: all soloed nodes stay audible. Mixer channels are the nodes on which
+    /// [`set_muted`](AudioNode::set_muted) or this method has been called, so the solo does not
+    /// silence e.g. the destination or the busses the channels feed into. Use
+    /// [`BaseAudioContext::clear_solo`](crate::context::BaseAudioContext::clear_solo) to unsolo
+    /// all nodes at once.
+    ///
+    /// As for muting, the toggle takes effect at the next render quantum and fades over that
+    /// quantum if the node has already been rendered.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    fn set_soloed(&self, soloed: bool) {
+        self.context().set_soloed(self.registration().id(), soloed);
+    }
+
     /// The number of inputs feeding into the AudioNode. For source nodes, this will be 0.
     fn number_of_inputs(&self) -> usize;
 
@@ -322,6 +543,32 @@ pub trait AudioNode {
     }
 }
 
+/// Check the connection from `output` of `source` to `input` of `dest` can be made
+///
+/// # Panics
+///
+/// This function will panic when
+/// - the AudioContext of the source and destination does not match
+/// - if the input port is out of bounds for the destination node
+/// - if the output port is out of bounds for the source node
+#[track_caller]
+pub(crate) fn assert_valid_connection<N: AudioNode + ?Sized>(
+    source: &N,
+    dest: &dyn AudioNode,
+    output: usize,
+    input: usize,
+) {
+    if source.context() != dest.context() {
+        panic!("InvalidAccessError: Attempting to connect nodes from different contexts");
+    }
+    if source.number_of_outputs() <= output {
+        panic!("IndexSizeError: output port {} is out of bounds", output);
+    }
+    if dest.number_of_inputs() <= input {
+        panic!("IndexSizeError: input port {} is out of bounds", input);
+    }
+}
+
 /// Interface of source nodes, controlling start and stop times.
 /// The node will emit silence before it is started, and after it has ended.
 pub trait AudioScheduledSourceNode: AudioNode {
@@ -330,28 +577,76 @@ pub trait AudioScheduledSourceNode: AudioNode {
     /// # Panics
     ///
     /// Panics if the source was already started
-    fn start(&self);
+    fn start(&self) {
+        self.start_at(self.context().current_time());
+    }
 
     /// Schedule playback start at given timestamp
     ///
     /// # Panics
     ///
-    /// Panics if the source was already started
-    fn start_at(&self, when: f64);
+    /// Panics if the source was already started, or if `when` is negative
+    fn start_at(&self, when: f64) {
+        self.try_start_at(when).unwrap_or_else(|e| panic!("{}", e));
+    }
 
     /// Stop immediately
     ///
     /// # Panics
     ///
-    /// Panics if the source was already stopped
-    fn stop(&self);
+    /// Panics if the source was not started yet
+    fn stop(&self) {
+        self.stop_at(self.context().current_time());
+    }
 
     /// Schedule playback stop at given timestamp
     ///
+    /// When called multiple times, only the last stop time is applied.
+    ///
     /// # Panics
     ///
-    /// Panics if the source was already stopped
-    fn stop_at(&self, when: f64);
+    /// Panics if the source was not started yet, or if `when` is negative
+    fn stop_at(&self, when: f64) {
+        self.try_stop_at(when).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    /// Play immediately, failing instead of panicking on invalid calls
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidStateError` if the source was already started
+    fn try_start(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.try_start_at(self.context().current_time())
+    }
+
+    /// Schedule playback start at given timestamp, failing instead of panicking on invalid calls
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, and the source is not scheduled, when
+    /// - the source was already started (`InvalidStateError`)
+    /// - `when` is negative or not finite (`RangeError`)
+    fn try_start_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Stop immediately, failing instead of panicking on invalid calls
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidStateError` if the source was not started yet
+    fn try_stop(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.try_stop_at(self.context().current_time())
+    }
+
+    /// Schedule playback stop at given timestamp, failing instead of panicking on invalid calls
+    ///
+    /// When called multiple times, only the last stop time is applied.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, and the stop time is not changed, when
+    /// - the source was not started yet (`InvalidStateError`)
+    /// - `when` is negative or not finite (`RangeError`)
+    fn try_stop_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>>;
 
     /// Register callback to run when the source node has stopped playing
     ///
@@ -363,8 +658,8 @@ pub trait AudioScheduledSourceNode: AudioNode {
     /// not possible to remove an event handler.
     fn onended<F: FnOnce() + Send + 'static>(&self, callback: F) {
         self.context().register_event_handler(
-            crate::events::Event::Ended(self.registration().id()),
-            Callback::Once(Box::new(callback)),
+            crate::events::EventType::Ended(self.registration().id()),
+            Callback::Once(Box::new(move |_| callback())),
         );
     }
 }
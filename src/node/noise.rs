@@ -0,0 +1,416 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, BaseAudioContext};
+use crate::control::Scheduler;
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions};
+
+/// Options for constructing a [`NoiseSourceNode`]
+#[derive(Clone, Debug, Default)]
+pub struct NoiseSourceOptions {
+    /// The color of the noise
+    pub type_: NoiseType,
+    /// Seed of the random number generator, use `None` to draw it from the random seed of the
+    /// context, or for a random seed if the context is not seeded
+    ///
+    /// Rendering with the same seed yields identical output, which is useful for testing.
+    pub seed: Option<u64>,
+    /// channel config options
+    pub channel_config: ChannelConfigOptions,
+}
+
+/// Color of the noise rendered by a `NoiseSourceNode`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum NoiseType {
+    /// Uniformly distributed in the [-1, 1] range, flat spectrum
+    #[default]
+    White,
+    /// Spectrum falls 3dB per octave
+    ///
+    /// The pink filter coefficients are designed for a sample rate of 44.1kHz and are used
+    /// unchanged at other sample rates. As the -3dB per octave slope is scale invariant, the
+    /// filter remains accurate up to the Nyquist frequency, but its lower bound (about 9Hz at
+    /// 44.1kHz) scales with the sample rate, e.g. the spectrum flattens below about 20Hz at
+    /// 96kHz.
+    Pink,
+    /// Spectrum falls 6dB per octave (also known as red noise)
+    Brown,
+}
+
+impl From<u32> for NoiseType {
+    fn from(i: u32) -> Self {
+        match i {
+            0 => NoiseType::White,
+            1 => NoiseType::Pink,
+            2 => NoiseType::Brown,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// `NoiseSourceNode` represents an audio source generating white, pink or brown noise.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_noise_source`](crate::context::BaseAudioContext::create_noise_source)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode, NoiseType};
+///
+/// let context = AudioContext::default();
+///
+/// let noise = context.create_noise_source();
+/// noise.set_type(NoiseType::Pink);
+/// noise.connect(&context.destination());
+/// noise.start();
+/// ```
+pub struct NoiseSourceNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Color of the noise
+    type_: Arc<AtomicU32>,
+    /// starts and stops noise audio streams
+    scheduler: Scheduler,
+}
+
+impl AudioNode for NoiseSourceNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioScheduledSourceNode for NoiseSourceNode {
+    fn try_start_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_start_at(when)
+    }
+
+    fn try_stop_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_stop_at(when)
+    }
+}
+
+impl NoiseSourceNode {
+    /// Returns a `NoiseSourceNode`
+    ///
+    /// # Arguments:
+    ///
+    /// * `context` - The `AudioContext`
+    /// * `options` - The NoiseSourceOptions
+    pub fn new<C: BaseAudioContext>(context: &C, options: NoiseSourceOptions) -> Self {
+        context.register(move |registration| {
+            let NoiseSourceOptions {
+                type_,
+                seed,
+                channel_config,
+            } = options;
+
+            let type_ = Arc::new(AtomicU32::new(type_ as u32));
+            let scheduler = Scheduler::new();
+
+            let seed = seed.unwrap_or_else(|| context.base().random_seed(registration.id()));
+
+            let renderer = NoiseSourceRenderer {
+                type_: type_.clone(),
+                scheduler: scheduler.clone(),
+                rng: Rng::new(seed),
+                pink: [0.; 7],
+                brown: 0.,
+                ended_triggered: false,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                type_,
+                scheduler,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// Returns the noise type
+    #[must_use]
+    pub fn type_(&self) -> NoiseType {
+        self.type_.load(Ordering::SeqCst).into()
+    }
+
+    /// Set the noise type
+    pub fn set_type(&self, type_: NoiseType) {
+        self.type_.store(type_ as u32, Ordering::SeqCst);
+    }
+}
+
+/// Small and fast pseudo random number generator (xorshift64*), fully determined by its seed
+#[derive(Clone, Debug)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // scramble the seed (splitmix64) so similar seeds yield unrelated sequences, the state
+        // of a xorshift generator must never be zero
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        Self { state: z.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniformly distributed value in the [-1, 1) range
+    // 24 bits of randomness are exactly representable by a f32
+    #[allow(clippy::cast_precision_loss)]
+    pub fn next_bipolar(&mut self) -> f32 {
+        let bits = self.next_u64() >> 40;
+        (bits as f32 / (1 << 24) as f32).mul_add(2., -1.)
+    }
+}
+
+struct NoiseSourceRenderer {
+    /// Color of the noise
+    type_: Arc<AtomicU32>,
+    /// starts and stops noise audio streams
+    scheduler: Scheduler,
+    /// white noise generator
+    rng: Rng,
+    /// pink noise filter state
+    pink: [f32; 7],
+    /// brown noise integrator state
+    brown: f32,
+    /// defines if the `ended` events was already dispatched
+    ended_triggered: bool,
+}
+
+impl NoiseSourceRenderer {
+    #[inline]
+    fn generate_pink(&mut self, white: f32) -> f32 {
+        // Paul Kellet's refined method, accurate to within +/-0.05dB above 9.2Hz (at 44.1kHz)
+        // The coefficients are not recomputed for other sample rates (see `NoiseType::Pink`)
+        let b = &mut self.pink;
+        b[0] = 0.99886 * b[0] + white * 0.055_517_9;
+        b[1] = 0.99332 * b[1] + white * 0.075_075_9;
+        b[2] = 0.96900 * b[2] + white * 0.153_852;
+        b[3] = 0.86650 * b[3] + white * 0.310_485_6;
+        b[4] = 0.55000 * b[4] + white * 0.532_952_2;
+        b[5] = -0.7616 * b[5] - white * 0.016_898;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115_926;
+
+        // roughly compensate for the gain of the filter
+        pink * 0.11
+    }
+
+    #[inline]
+    fn generate_brown(&mut self, white: f32) -> f32 {
+        // leaky integrator, the leak prevents the signal from drifting away
+        self.brown = (self.brown + 0.02 * white) / 1.02;
+
+        // roughly compensate for the gain of the integrator
+        self.brown * 3.5
+    }
+}
+
+impl AudioProcessor for NoiseSourceRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+
+        let dt = 1. / scope.sample_rate as f64;
+        let next_block_time = scope.current_time + dt * scope.render_quantum_size as f64;
+
+        let start_time = self.scheduler.get_start_at();
+        let stop_time = self.scheduler.get_stop_at();
+
+        if start_time >= next_block_time {
+            output.make_silent();
+            return true;
+        }
+
+        output.ensure_mono();
+
+        let type_: NoiseType = self.type_.load(Ordering::Relaxed).into();
+        let mut current_time = scope.current_time;
+
+        let channel = output.channel_data_mut(0);
+        channel.iter_mut().for_each(|o| {
+            if current_time < start_time || current_time >= stop_time {
+                *o = 0.;
+            } else {
+                let white = self.rng.next_bipolar();
+                *o = match type_ {
+                    NoiseType::White => white,
+                    NoiseType::Pink => self.generate_pink(white),
+                    NoiseType::Brown => self.generate_brown(white),
+                };
+            }
+
+            current_time += dt;
+        });
+
+        // tail_time false when output has ended this quantum
+        let still_running = stop_time >= next_block_time;
+
+        if !still_running && !self.ended_triggered {
+            scope.send_ended_event();
+            self.ended_triggered = true;
+        }
+
+        still_running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use easyfft::prelude::*;
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    use super::*;
+
+    fn render_noise(type_: NoiseType, seed: u64, length: usize) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, length, 44_100.);
+
+        let options = NoiseSourceOptions {
+            type_,
+            seed: Some(seed),
+            ..NoiseSourceOptions::default()
+        };
+        let noise = NoiseSourceNode::new(&context, options);
+        noise.connect(&context.destination());
+        noise.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    // average power spectral density in the octave bands starting at 250, 500, 1000 and 2000Hz
+    fn octave_band_densities(signal: &[f32]) -> Vec<f32> {
+        const FFT_SIZE: usize = 4096;
+        let bin_width = 44_100. / FFT_SIZE as f32;
+
+        let mut power = vec![0.; FFT_SIZE / 2 + 1];
+        signal.chunks_exact(FFT_SIZE).for_each(|chunk| {
+            let spectrum = chunk.real_fft();
+            power
+                .iter_mut()
+                .zip(spectrum.iter())
+                .for_each(|(p, c)| *p += c.norm_sqr());
+        });
+
+        [250., 500., 1000., 2000.]
+            .iter()
+            .map(|&low| {
+                let from = (low / bin_width).ceil() as usize;
+                let to = (2. * low / bin_width).ceil() as usize;
+                power[from..to].iter().sum::<f32>() / (to - from) as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reproducible_with_seed() {
+        for type_ in [NoiseType::White, NoiseType::Pink, NoiseType::Brown] {
+            let a = render_noise(type_, 42, 1024);
+            let b = render_noise(type_, 42, 1024);
+            assert_float_eq!(&a[..], &b[..], abs_all <= 0.);
+
+            let c = render_noise(type_, 43, 1024);
+            assert!(a != c);
+        }
+    }
+
+    #[test]
+    fn test_white_noise_range() {
+        let signal = render_noise(NoiseType::White, 0, 44_100);
+
+        assert!(signal.iter().all(|v| (-1. ..=1.).contains(v)));
+
+        let mean = signal.iter().sum::<f32>() / signal.len() as f32;
+        assert_float_eq!(mean, 0., abs <= 0.02);
+
+        // variance of uniform distribution in [-1, 1] is 1/3
+        let variance = signal.iter().map(|v| v * v).sum::<f32>() / signal.len() as f32;
+        assert_float_eq!(variance, 1. / 3., abs <= 0.02);
+    }
+
+    #[test]
+    fn test_spectral_slope() {
+        // slope (in dB per octave) of the power spectral density
+        let slope = |type_| {
+            let densities = octave_band_densities(&render_noise(type_, 1, 1 << 17));
+            let slopes: Vec<_> = densities
+                .windows(2)
+                .map(|w| 10. * (w[1] / w[0]).log10())
+                .collect();
+            slopes.iter().sum::<f32>() / slopes.len() as f32
+        };
+
+        assert_float_eq!(slope(NoiseType::White), 0., abs <= 1.);
+        assert_float_eq!(slope(NoiseType::Pink), -3., abs <= 1.);
+        assert_float_eq!(slope(NoiseType::Brown), -6., abs <= 1.);
+    }
+
+    #[test]
+    fn test_start_stop() {
+        let sample_rate = 44_100.;
+        let context = OfflineAudioContext::new(1, 128 * 3, sample_rate);
+
+        let noise = context.create_noise_source();
+        noise.connect(&context.destination());
+        noise.start_at(128. / sample_rate as f64);
+        noise.stop_at(256. / sample_rate as f64);
+
+        let buffer = context.start_rendering_sync();
+        let channel = buffer.get_channel_data(0);
+
+        assert_float_eq!(channel[0..128], [0.; 128][..], abs_all <= 0.);
+        assert!(channel[128..256].iter().any(|v| *v != 0.));
+        assert_float_eq!(channel[256..384], [0.; 128][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_set_type() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let noise = context.create_noise_source();
+        assert_eq!(noise.type_(), NoiseType::White);
+
+        noise.set_type(NoiseType::Brown);
+        assert_eq!(noise.type_(), NoiseType::Brown);
+    }
+}
@@ -1,20 +1,50 @@
-use crossbeam_channel::{self, Receiver, Sender};
+use std::error::Error;
+use std::f64::consts::PI;
 use std::fmt::Debug;
-use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
 
-use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::context::{
+    AudioContextRegistration, AudioParamId, BaseAudioContext, PatchNodeType, Patchable,
+};
 use crate::control::Scheduler;
 use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
 use crate::periodic_wave::PeriodicWave;
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
-use crate::RENDER_QUANTUM_SIZE;
+use crate::triple_buffer::{triple_buffer, TripleBufferReader, TripleBufferWriter};
+use crate::AtomicF32;
+
+#[cfg(feature = "simd")]
+use wide::{f32x4, f64x4};
 
 use super::{
     AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions, SINETABLE,
     TABLE_LENGTH_USIZE,
 };
 
+/// Number of samples rendered at once by the SIMD sine path
+#[cfg(feature = "simd")]
+const SINE_LANES: usize = 4;
+
+/// Gain of the BLIT types, compensating the overshoot of the band-limited discontinuities so the
+/// output stays within [-1, 1]
+///
+/// The overshoot (Gibbs phenomenon) is about 18% of the half jump, and up to 24% depending on the
+/// alignment of the discontinuities with the samples.
+const BLIT_NORMALIZATION: f64 = 0.8;
+
+/// Fraction of the state of the BLIT integrator that leaks away every period
+const BLIT_LEAK_PER_PERIOD: f64 = 0.01;
+
+/// Highest frequency rendered when the alias guard is enabled, relative to the Nyquist frequency
+const ALIAS_GUARD_RATIO: f32 = 0.99;
+
+/// Lower bound of the phase increment of the BLIT types, i.e. a frequency of 1 Hz at 48 kHz
+///
+/// This bounds the number of terms of the impulse train, and the time it takes for the integrator
+/// to settle.
+const MIN_BLIT_PHASE_INCR: f64 = 1. / 48_000.;
+
 /// Options for constructing an [`OscillatorNode`]
 // dictionary OscillatorOptions : AudioNodeOptions {
 //   OscillatorType type = "sine";
@@ -23,8 +53,14 @@ use super::{
 //   PeriodicWave periodicWave;
 // };
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct OscillatorOptions {
     /// The shape of the periodic waveform
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub type_: OscillatorType,
     /// The frequency of the fundamental frequency.
     pub frequency: f32,
@@ -32,6 +68,11 @@ pub struct OscillatorOptions {
     pub detune: f32,
     /// Optionnal custom waveform, if specified (set `type` to "custom")
     pub periodic_wave: Option<PeriodicWave>,
+    /// Phase of the first rendered sample, as a fraction of the period, wrapped to `[0, 1)`
+    ///
+    /// A value of `0.25` starts a sine a quarter period ahead, i.e. as a cosine. This option is
+    /// not part of the Web Audio API specification.
+    pub initial_phase: f32,
     /// channel config options
     pub channel_config: ChannelConfigOptions,
 }
@@ -43,13 +84,22 @@ impl Default for OscillatorOptions {
             frequency: 440.,
             detune: 0.,
             periodic_wave: None,
+            initial_phase: 0.,
             channel_config: ChannelConfigOptions::default(),
         }
     }
 }
 
 /// Type of the waveform rendered by an `OscillatorNode`
+///
+/// With the `serde` feature, the variants are (de)serialized by their name in the Web Audio API
+/// (`"sine"`, `"square"`, ...).
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum OscillatorType {
     /// Sine wave
     Sine,
@@ -61,6 +111,19 @@ pub enum OscillatorType {
     Triangle,
     /// type used when periodic_wave is specified
     Custom,
+    /// Sawtooth wave, integrated from a band-limited impulse train
+    ///
+    /// The BLIT (band-limited impulse train) oscillators contain all harmonics up to the Nyquist
+    /// frequency, and none above, at the cost of a few sine evaluations per sample. After a start
+    /// or type change, they settle from the naive waveform to the band-limited one within about a
+    /// hundred periods.
+    ///
+    /// This type is not part of the Web Audio API specification.
+    BlitSawtooth,
+    /// Square wave, integrated from a band-limited bipolar impulse train
+    ///
+    /// This type is not part of the Web Audio API specification.
+    BlitSquare,
 }
 
 impl Default for OscillatorType {
@@ -77,6 +140,8 @@ impl From<u32> for OscillatorType {
             2 => OscillatorType::Sawtooth,
             3 => OscillatorType::Triangle,
             4 => OscillatorType::Custom,
+            5 => OscillatorType::BlitSawtooth,
+            6 => OscillatorType::BlitSquare,
             _ => unreachable!(),
         }
     }
@@ -111,6 +176,33 @@ impl From<u32> for OscillatorType {
 /// - `cargo run --release --example many_oscillators_with_env`
 /// - `cargo run --release --example amplitude_modulation`
 ///
+/// # Hard sync
+///
+/// The single input of the node is a `sync` input: the phase of the oscillator resets to its
+/// initial phase whenever the input signal crosses zero upwards, e.g. every period of another
+/// oscillator connected to it. The reset happens one sample after the crossing, at its
+/// interpolated position between the samples, and the jump of the waveform is band-limited
+/// with a polyBLEP. Only the first channel of the input is used. This input is not part of the
+/// Web Audio API specification.
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode, OscillatorType};
+///
+/// let context = AudioContext::default();
+///
+/// let master = context.create_oscillator();
+/// master.frequency().set_value(110.);
+///
+/// let slave = context.create_oscillator();
+/// slave.set_type(OscillatorType::Sawtooth);
+/// slave.frequency().set_value(280.);
+///
+/// master.connect(&slave);
+/// slave.connect(&context.destination());
+/// master.start();
+/// slave.start();
+/// ```
 pub struct OscillatorNode {
     /// Represents the node instance and its associated audio context
     registration: AudioContextRegistration,
@@ -124,8 +216,14 @@ pub struct OscillatorNode {
     type_: Arc<AtomicU32>,
     /// starts and stops Oscillator audio streams
     scheduler: Scheduler,
-    /// channel between control and renderer parts (sender part)
-    sender: Sender<PeriodicWave>,
+    /// lock-free channel between control and renderer parts (writing side)
+    periodic_wave: Mutex<TripleBufferWriter<PeriodicWave>>,
+    /// last wave given to `set_periodic_wave`, saved in patches
+    custom_wave: Arc<Mutex<Option<PeriodicWave>>>,
+    /// clamp the computed frequency below the Nyquist frequency
+    alias_guard: Arc<AtomicBool>,
+    /// phase of the next sample to render, updated by the renderer every render quantum
+    current_phase: Arc<AtomicF32>,
 }
 
 impl AudioNode for OscillatorNode {
@@ -137,9 +235,11 @@ impl AudioNode for OscillatorNode {
         &self.channel_config
     }
 
-    /// `OscillatorNode` is a source node. A source node is by definition with no input
+    /// `OscillatorNode` is a source node, with a single `sync` input for hard sync
+    ///
+    /// The Web Audio API specification defines no input for this node.
     fn number_of_inputs(&self) -> usize {
-        0
+        1
     }
 
     /// `OscillatorNode` is a mono source node.
@@ -149,22 +249,12 @@ impl AudioNode for OscillatorNode {
 }
 
 impl AudioScheduledSourceNode for OscillatorNode {
-    fn start(&self) {
-        let when = self.registration.context().current_time();
-        self.start_at(when);
-    }
-
-    fn start_at(&self, when: f64) {
-        self.scheduler.start_at(when);
+    fn try_start_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_start_at(when)
     }
 
-    fn stop(&self) {
-        let when = self.registration.context().current_time();
-        self.stop_at(when);
-    }
-
-    fn stop_at(&self, when: f64) {
-        self.scheduler.stop_at(when);
+    fn try_stop_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_stop_at(when)
     }
 }
 
@@ -176,7 +266,9 @@ impl OscillatorNode {
     /// * `context` - The `AudioContext`
     /// * `options` - The OscillatorOptions
     pub fn new<C: BaseAudioContext>(context: &C, options: OscillatorOptions) -> Self {
-        context.register(move |registration| {
+        let patch_options = options.clone();
+
+        let node = context.register(move |registration| {
             let sample_rate = context.sample_rate();
             let nyquist = sample_rate / 2.;
 
@@ -186,6 +278,7 @@ impl OscillatorNode {
                 detune,
                 channel_config,
                 periodic_wave,
+                initial_phase,
             } = options;
 
             // frequency audio parameter
@@ -209,20 +302,30 @@ impl OscillatorNode {
             det_param.set_value(detune);
 
             let type_ = Arc::new(AtomicU32::new(type_ as u32));
+            let alias_guard = Arc::new(AtomicBool::new(false));
+            let initial_phase = f64::from(initial_phase).rem_euclid(1.);
+            let current_phase = Arc::new(AtomicF32::new(initial_phase as f32));
 
             let scheduler = Scheduler::new();
-            let (sender, receiver) = crossbeam_channel::bounded(1);
+            let (writer, reader) = triple_buffer();
 
             let renderer = OscillatorRenderer {
                 type_: type_.clone(),
                 frequency: f_proc,
                 detune: det_proc,
                 scheduler: scheduler.clone(),
-                receiver,
-                phase: 0.,
+                phase: initial_phase,
+                initial_phase,
                 started: false,
-                periodic_wave: None,
+                periodic_wave: reader,
                 ended_triggered: false,
+                blit_integrator: None,
+                previous_type: type_.load(Ordering::SeqCst).into(),
+                alias_guard: alias_guard.clone(),
+                clamping: false,
+                current_phase: current_phase.clone(),
+                previous_sync: 0.,
+                sync_residual: 0.,
             };
 
             let node = Self {
@@ -232,7 +335,10 @@ impl OscillatorNode {
                 detune: det_param,
                 type_,
                 scheduler,
-                sender,
+                periodic_wave: Mutex::new(writer),
+                custom_wave: Arc::new(Mutex::new(None)),
+                alias_guard,
+                current_phase,
             };
 
             // if periodic wave has been given, init it
@@ -241,7 +347,32 @@ impl OscillatorNode {
             }
 
             (node, Box::new(renderer))
-        })
+        });
+
+        context
+            .base()
+            .record_patch_node(&node, node.patch_snapshot(patch_options));
+
+        node
+    }
+
+    /// Current options of the node, given the options it was created with
+    fn patch_snapshot(
+        &self,
+        options: OscillatorOptions,
+    ) -> impl Fn() -> PatchNodeType + Send + Sync + 'static {
+        let channel_config = self.channel_config.clone();
+        let type_ = self.type_.clone();
+        let custom_wave = self.custom_wave.clone();
+
+        move || {
+            PatchNodeType::Oscillator(OscillatorOptions {
+                type_: type_.load(Ordering::SeqCst).into(),
+                periodic_wave: custom_wave.lock().unwrap().clone(),
+                channel_config: (&channel_config).into(),
+                ..options.clone()
+            })
+        }
     }
 
     /// A-rate [`AudioParam`] that defines the fondamental frequency of the
@@ -274,7 +405,7 @@ impl OscillatorNode {
     ///
     /// # Arguments
     ///
-    /// * `type_` - oscillator type (sine, square, triangle, sawtooth)
+    /// * `type_` - oscillator type (sine, square, triangle, sawtooth, or one of the BLIT types)
     ///
     /// # Panics
     ///
@@ -298,13 +429,112 @@ impl OscillatorNode {
     ///
     /// Calling this sets the oscillator type to `custom`, once set to `custom`
     /// the oscillator cannot be reverted back to a standard waveform.
+    ///
+    /// This never blocks on the render thread, which picks up the latest wave at the next
+    /// render quantum. When called repeatedly in between, only the last wave is rendered.
     pub fn set_periodic_wave(&self, periodic_wave: PeriodicWave) {
+        *self.custom_wave.lock().unwrap() = Some(periodic_wave.clone());
+        self.periodic_wave.lock().unwrap().write(periodic_wave);
+
+        // set the type after the wave is written, so the renderer never sees a custom type
+        // without a wave
         self.type_
             .store(OscillatorType::Custom as u32, Ordering::SeqCst);
+    }
+
+    /// Returns whether the computed frequency is clamped below the Nyquist frequency
+    #[must_use]
+    pub fn alias_guard(&self) -> bool {
+        self.alias_guard.load(Ordering::SeqCst)
+    }
+
+    /// Clamp the computed frequency, i.e. after applying the detune, to just below the Nyquist
+    /// frequency, instead of letting it alias back to lower frequencies
+    ///
+    /// The guard is disabled by default. When enabled, a debug message is logged each time the
+    /// frequency starts being clamped. Only the fundamental frequency is guarded, the harmonics
+    /// of the non-sine types may still alias.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    pub fn set_alias_guard(&self, enabled: bool) {
+        self.alias_guard.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Returns the phase of the oscillator, as a fraction of the period in the `[0, 1)` range
+    ///
+    /// The phase is updated by the render thread at the end of every render quantum, it is the
+    /// phase of the first sample of the next render quantum. A phase of 0 is the start of the
+    /// waveform, e.g. the upward zero crossing of a sine, independently of the size of the
+    /// wavetables. Before the oscillator starts, this is the `initial_phase` of the
+    /// [`OscillatorOptions`].
+    ///
+    /// This method is not part of the Web Audio API specification.
+    #[must_use]
+    pub fn current_phase(&self) -> f32 {
+        self.current_phase.load()
+    }
+}
+
+impl Patchable for OscillatorNode {
+    fn patch_params(&self) -> Vec<&AudioParam> {
+        vec![&self.frequency, &self.detune]
+    }
+
+    fn patch_scheduler(&self) -> Option<&Scheduler> {
+        Some(&self.scheduler)
+    }
+}
+
+// computes the `polyBLEP` corrections to apply to aliasing signal
+// `polyBLEP` stands for `polyBandLimitedstEP`
+// This basically soften the sharp edges in square and sawtooth signals
+// to avoid infinite frequencies impulses (jumps from -1 to 1 or inverse).
+// cf. http://www.martin-finke.de/blog/articles/audio-plugins-018-polyblep-oscillator/
+//
+// @note: do not apply in tests so we can avoid relying on snapshots
+#[inline]
+pub(super) fn poly_blep(mut t: f64, dt: f64, is_test: bool) -> f64 {
+    if is_test {
+        0.
+    } else if t < dt {
+        t /= dt;
+        t + t - t * t - 1.0
+    } else if t > 1.0 - dt {
+        t = (t - 1.0) / dt;
+        t.mul_add(t, t) + t + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Leaky integrator of the band-limited impulse trains of the BLIT types
+///
+/// A plain running sum boosts the harmonics close to the Nyquist frequency by up to 57% compared
+/// to an ideal integrator, and the trapezoidal rule attenuates them. Weighting the two by 2/3 and
+/// 1/3 gives a flat response up to the second order, within 5% of the ideal one up to the
+/// Nyquist frequency.
+struct BlitIntegrator {
+    value: f64,
+    previous_input: f64,
+}
+
+impl BlitIntegrator {
+    fn new(value: f64, previous_input: f64) -> Self {
+        Self {
+            value,
+            previous_input,
+        }
+    }
 
-        self.sender
-            .send(periodic_wave)
-            .expect("Sending periodic wave to the node renderer failed");
+    #[inline]
+    fn process(&mut self, input: f64, phase_incr: f64) -> f64 {
+        // leak a fixed fraction every period, so errors decay and the output cannot drift away
+        let leak = 1. - BLIT_LEAK_PER_PERIOD * phase_incr.abs().min(1.);
+        let increment = (5. * input + self.previous_input) / 6.;
+        self.value = leak.mul_add(self.value, increment);
+        self.previous_input = input;
+
+        self.value
     }
 }
 
@@ -318,39 +548,59 @@ struct OscillatorRenderer {
     detune: AudioParamId,
     /// starts and stops oscillator audio streams
     scheduler: Scheduler,
-    /// channel between control and renderer parts (receiver part)
-    receiver: Receiver<PeriodicWave>,
     /// current phase of the oscillator
     phase: f64,
+    /// phase of the first sample, in `[0, 1)`
+    initial_phase: f64,
     /// defines if the oscillator has started
     started: bool,
-    /// wavetable placeholder for custom oscillators
-    periodic_wave: Option<PeriodicWave>,
+    /// wavetable for custom oscillators (reading side)
+    periodic_wave: TripleBufferReader<PeriodicWave>,
     /// defines if the `ended` events was already dispatched
     ended_triggered: bool,
+    /// integrator of the BLIT types, `None` until the first BLIT sample
+    blit_integrator: Option<BlitIntegrator>,
+    /// type rendered in the previous render quantum
+    previous_type: OscillatorType,
+    /// clamp the computed frequency below the Nyquist frequency
+    alias_guard: Arc<AtomicBool>,
+    /// defines if the computed frequency is currently clamped by the alias guard
+    clamping: bool,
+    /// phase of the next sample to render, read by the control thread
+    current_phase: Arc<AtomicF32>,
+    /// last sample of the `sync` input in the previous render quantum
+    previous_sync: f32,
+    /// band-limiting correction of a hard sync reset, pending for the next sample
+    sync_residual: f64,
 }
 
 impl AudioProcessor for OscillatorRenderer {
     fn process(
         &mut self,
-        _inputs: &[AudioRenderQuantum],
+        inputs: &[AudioRenderQuantum],
         outputs: &mut [AudioRenderQuantum],
         params: AudioParamValues,
         scope: &RenderScope,
     ) -> bool {
+        // the phase resets are driven by the first channel of the sync input
+        let sync = if inputs[0].is_silent() {
+            None
+        } else {
+            Some(&inputs[0].channel_data(0)[..])
+        };
+        let previous_sync = std::mem::replace(
+            &mut self.previous_sync,
+            sync.map_or(0., |sync| sync[sync.len() - 1]),
+        );
+
         // single output node
         let output = &mut outputs[0];
         // 1 channel output
-        output.set_number_of_channels(1);
-
-        // check if any message was send from the control thread
-        if let Ok(periodic_wave) = self.receiver.try_recv() {
-            self.periodic_wave = Some(periodic_wave);
-        }
+        output.ensure_mono();
 
         let sample_rate = scope.sample_rate as f64;
         let dt = 1. / sample_rate;
-        let num_frames = RENDER_QUANTUM_SIZE;
+        let num_frames = scope.render_quantum_size;
         let next_block_time = scope.current_time + dt * num_frames as f64;
 
         let mut start_time = self.scheduler.get_start_at();
@@ -373,10 +623,23 @@ impl AudioProcessor for OscillatorRenderer {
         }
 
         let type_ = self.type_.load(Ordering::SeqCst).into();
+        // restart the integration from the naive waveform when switching to a BLIT type
+        if type_ != self.previous_type {
+            self.blit_integrator = None;
+            self.previous_type = type_;
+        }
+        // pick up the latest periodic wave, it is written before the type is set to custom
+        self.periodic_wave.update();
         let channel_data = output.channel_data_mut(0);
         let frequency_values = params.get(&self.frequency);
         let detune_values = params.get(&self.detune);
 
+        let max_frequency = if self.alias_guard.load(Ordering::SeqCst) {
+            ALIAS_GUARD_RATIO * scope.sample_rate / 2.
+        } else {
+            f32::INFINITY
+        };
+
         let mut current_time = scope.current_time;
 
         // Prevent scheduling in the past
@@ -388,11 +651,31 @@ impl AudioProcessor for OscillatorRenderer {
             start_time = current_time;
         }
 
+        // fast track: the oscillator is running during the whole block at a constant frequency
+        #[cfg(feature = "simd")]
+        if type_ == OscillatorType::Sine
+            && sync.is_none()
+            && self.sync_residual == 0.
+            && self.started
+            && stop_time >= next_block_time
+            && frequency_values.len() == 1
+            && detune_values.len() == 1
+        {
+            let computed_frequency =
+                self.computed_frequency(frequency_values[0], detune_values[0], max_frequency);
+            let phase_incr = computed_frequency as f64 / sample_rate;
+            self.generate_sine_block(&mut channel_data[..], phase_incr);
+            self.publish_phase();
+
+            return true;
+        }
+
         channel_data
             .iter_mut()
             .zip(frequency_values.iter().cycle())
             .zip(detune_values.iter().cycle())
-            .for_each(|((o, &frequency), &detune)| {
+            .enumerate()
+            .for_each(|(i, ((o, &frequency), &detune))| {
                 if current_time < start_time || current_time >= stop_time {
                     *o = 0.;
                     current_time += dt;
@@ -401,7 +684,7 @@ impl AudioProcessor for OscillatorRenderer {
                 }
 
                 // @todo: we could avoid recompute that if both param lengths are 1
-                let computed_frequency = frequency * (detune / 1200.).exp2();
+                let computed_frequency = self.computed_frequency(frequency, detune, max_frequency);
 
                 // first sample to render
                 if !self.started {
@@ -410,7 +693,7 @@ impl AudioProcessor for OscillatorRenderer {
                     if current_time > start_time {
                         let phase_incr = computed_frequency as f64 / sample_rate;
                         let ratio = (current_time - start_time) / dt;
-                        self.phase = Self::unroll_phase(phase_incr * ratio);
+                        self.phase = Self::unroll_phase(self.initial_phase + phase_incr * ratio);
                     }
 
                     self.started = true;
@@ -428,18 +711,115 @@ impl AudioProcessor for OscillatorRenderer {
                     OscillatorType::Square => self.generate_square(phase_incr),
                     OscillatorType::Triangle => self.generate_triangle(),
                     OscillatorType::Custom => self.generate_custom(),
+                    OscillatorType::BlitSawtooth => self.generate_blit_sawtooth(phase_incr),
+                    OscillatorType::BlitSquare => self.generate_blit_square(phase_incr),
                 };
 
                 current_time += dt;
 
+                // the sync input is read one sample late, so the reset falls between this
+                // sample and the next one and both can be band-limited
+                if let Some(sync) = sync {
+                    let previous = if i == 0 { previous_sync } else { sync[i - 1] };
+                    let current = sync[i];
+
+                    if previous <= 0. && current > 0. {
+                        let fraction = f64::from(previous / (previous - current));
+                        *o = self.hard_sync(type_, fraction, phase_incr, *o);
+
+                        return;
+                    }
+                }
+
+                if self.sync_residual != 0. {
+                    *o += std::mem::take(&mut self.sync_residual) as f32;
+                }
+
                 self.phase = Self::unroll_phase(self.phase + phase_incr);
             });
 
+        self.publish_phase();
+
         true
     }
 }
 
 impl OscillatorRenderer {
+    /// Reset the phase at `fraction` of the sample period after the current sample, and return
+    /// the current sample with the band-limiting correction of the jump
+    ///
+    /// The correction is the polyBLEP residual of a step of the size of the jump, split between
+    /// the current sample and the next one. The BLIT integrator jumps along with the waveform.
+    fn hard_sync(
+        &mut self,
+        type_: OscillatorType,
+        fraction: f64,
+        phase_incr: f64,
+        sample: f32,
+    ) -> f32 {
+        let before = self.waveform_at(type_, (self.phase + fraction * phase_incr).rem_euclid(1.));
+        let after = self.waveform_at(type_, self.initial_phase);
+        let jump = after - before;
+
+        let gain = match type_ {
+            OscillatorType::BlitSawtooth | OscillatorType::BlitSquare => {
+                if let Some(integrator) = self.blit_integrator.as_mut() {
+                    integrator.value += jump;
+                }
+                BLIT_NORMALIZATION
+            }
+            _ => 1.,
+        };
+
+        let residual = std::mem::take(&mut self.sync_residual);
+        self.sync_residual = -gain * jump * fraction * fraction / 2.;
+        self.phase = (self.initial_phase + (1. - fraction) * phase_incr).rem_euclid(1.);
+
+        let correction = gain * jump * (1. - fraction) * (1. - fraction) / 2.;
+        sample + (residual + correction) as f32
+    }
+
+    /// Naive value of the waveform at the given phase, without band-limiting
+    fn waveform_at(&mut self, type_: OscillatorType, phase: f64) -> f64 {
+        let current_phase = std::mem::replace(&mut self.phase, phase);
+        // a null phase increment disables the polyBLEP
+        let value = match type_ {
+            OscillatorType::Sine => self.generate_sine(),
+            OscillatorType::Sawtooth | OscillatorType::BlitSawtooth => self.generate_sawtooth(0.),
+            OscillatorType::Square | OscillatorType::BlitSquare => self.generate_square(0.),
+            OscillatorType::Triangle => self.generate_triangle(),
+            OscillatorType::Custom => self.generate_custom(),
+        };
+        self.phase = current_phase;
+
+        f64::from(value)
+    }
+
+    /// Share the current phase with the control thread
+    fn publish_phase(&self) {
+        // rounding to single precision may reach the end of the period
+        self.current_phase.store((self.phase as f32).rem_euclid(1.));
+    }
+
+    /// Final frequency, after applying the detune and the alias guard
+    #[inline]
+    fn computed_frequency(&mut self, frequency: f32, detune: f32, max_frequency: f32) -> f32 {
+        let computed_frequency = frequency * (detune / 1200.).exp2();
+        let clamped = computed_frequency.clamp(-max_frequency, max_frequency);
+
+        let clamping = clamped != computed_frequency;
+        if clamping && !self.clamping {
+            log::debug!(
+                "Oscillator frequency {}Hz clamped to {}Hz to prevent aliasing",
+                computed_frequency,
+                clamped
+            );
+        }
+        self.clamping = clamping;
+
+        clamped
+    }
+
     #[inline]
     fn generate_sine(&mut self) -> f32 {
         let position = self.phase * TABLE_LENGTH_USIZE as f64;
@@ -451,9 +831,71 @@ impl OscillatorRenderer {
             next_index = 0;
         }
 
-        // linear interpolation into lookup table
+        // linear interpolation into lookup table, without fused multiply-add so the
+        // rounding is the same as in the `wide` lanes of `generate_sine_block`
         let k = (position - floored) as f32;
-        SINETABLE[prev_index].mul_add(1. - k, SINETABLE[next_index] * k)
+        SINETABLE[prev_index] * (1. - k) + SINETABLE[next_index] * k
+    }
+
+    /// Render a sine at constant frequency, `SINE_LANES` samples at a time
+    ///
+    /// The phases of a chunk are accumulated exactly as in the per-sample path. The table
+    /// positions and the interpolation are computed in `wide` lanes, only the table lookups
+    /// remain scalar. Both paths round identically, so the output matches
+    /// [`generate_sine`](Self::generate_sine) exactly.
+    ///
+    /// Measured on x86_64 (release build, SSE2 only), rendering 10 000 quanta of a 1234.567 Hz
+    /// sine in an `OfflineAudioContext`: about 24 ms with the per-sample path against 12 ms
+    /// with this one.
+    #[cfg(feature = "simd")]
+    fn generate_sine_block(&mut self, output: &mut [f32], phase_incr: f64) {
+        // dereference the lazy static once
+        let table = SINETABLE.as_slice();
+        let table_length = f64x4::splat(TABLE_LENGTH_USIZE as f64);
+        let mut phase = self.phase;
+        let mut chunks = output.chunks_exact_mut(SINE_LANES);
+
+        for chunk in &mut chunks {
+            let mut phases = [0.; SINE_LANES];
+            phases.iter_mut().for_each(|p| {
+                *p = phase;
+                phase = Self::unroll_phase(phase + phase_incr);
+            });
+
+            let positions = f64x4::new(phases) * table_length;
+            let floored = positions.floor();
+            let indices = floored.to_array();
+            let fractions = (positions - floored).to_array();
+
+            // gather the two table values surrounding each position
+            let mut prev_values = [0.; SINE_LANES];
+            let mut next_values = [0.; SINE_LANES];
+            let mut k = [0.; SINE_LANES];
+            for i in 0..SINE_LANES {
+                let prev_index = indices[i] as usize;
+                let mut next_index = prev_index + 1;
+                if next_index == TABLE_LENGTH_USIZE {
+                    next_index = 0;
+                }
+
+                prev_values[i] = table[prev_index];
+                next_values[i] = table[next_index];
+                k[i] = fractions[i] as f32;
+            }
+
+            // linear interpolation
+            let k = f32x4::new(k);
+            let values = f32x4::new(prev_values) * (f32x4::ONE - k) + f32x4::new(next_values) * k;
+            chunk.copy_from_slice(&values.to_array());
+        }
+
+        self.phase = phase;
+
+        // remaining samples, if the block size is not a multiple of the number of lanes
+        chunks.into_remainder().iter_mut().for_each(|o| {
+            *o = self.generate_sine();
+            self.phase = Self::unroll_phase(self.phase + phase_incr);
+        });
     }
 
     #[inline]
@@ -461,7 +903,7 @@ impl OscillatorRenderer {
         // offset phase to start at 0. (not -1.)
         let phase = Self::unroll_phase(self.phase + 0.5);
         let mut sample = 2.0 * phase - 1.0;
-        sample -= Self::poly_blep(phase, phase_incr, cfg!(test));
+        sample -= poly_blep(phase, phase_incr, cfg!(test));
 
         sample as f32
     }
@@ -469,10 +911,10 @@ impl OscillatorRenderer {
     #[inline]
     fn generate_square(&mut self, phase_incr: f64) -> f32 {
         let mut sample = if self.phase < 0.5 { 1.0 } else { -1.0 };
-        sample += Self::poly_blep(self.phase, phase_incr, cfg!(test));
+        sample += poly_blep(self.phase, phase_incr, cfg!(test));
 
         let shift_phase = Self::unroll_phase(self.phase + 0.5);
-        sample -= Self::poly_blep(shift_phase, phase_incr, cfg!(test));
+        sample -= poly_blep(shift_phase, phase_incr, cfg!(test));
 
         sample as f32
     }
@@ -492,7 +934,7 @@ impl OscillatorRenderer {
 
     #[inline]
     fn generate_custom(&mut self) -> f32 {
-        let periodic_wave = self.periodic_wave.as_ref().unwrap().as_slice();
+        let periodic_wave = self.periodic_wave.get().unwrap().as_slice();
         let position = self.phase * TABLE_LENGTH_USIZE as f64;
         let floored = position.floor();
 
@@ -507,26 +949,74 @@ impl OscillatorRenderer {
         periodic_wave[prev_index].mul_add(1. - k, periodic_wave[next_index] * k)
     }
 
-    // computes the `polyBLEP` corrections to apply to aliasing signal
-    // `polyBLEP` stands for `polyBandLimitedstEP`
-    // This basically soften the sharp edges in square and sawtooth signals
-    // to avoid infinite frequencies impulses (jumps from -1 to 1 or inverse).
-    // cf. http://www.martin-finke.de/blog/articles/audio-plugins-018-polyblep-oscillator/
-    //
-    // @note: do not apply in tests so we can avoid relying on snapshots
     #[inline]
-    fn poly_blep(mut t: f64, dt: f64, is_test: bool) -> f64 {
-        if is_test {
-            0.
-        } else if t < dt {
-            t /= dt;
-            t + t - t * t - 1.0
-        } else if t > 1.0 - dt {
-            t = (t - 1.0) / dt;
-            t.mul_add(t, t) + t + 1.0
-        } else {
-            0.0
+    fn generate_blit_sawtooth(&mut self, phase_incr: f64) -> f32 {
+        // integrate the impulse train minus its mean, which gives a rise of 2 per period and a
+        // drop of 2 at phase 0.5, where the impulses are located
+        let input = |phase: f64| 2. * (phase_incr - Self::blit(phase, phase_incr));
+        let phase = Self::unroll_phase(self.phase + 0.5);
+
+        // start from the naive waveform at the previous sample, before a possible impulse
+        let integrator = self.blit_integrator.get_or_insert_with(|| {
+            let previous = (phase - phase_incr).rem_euclid(1.);
+            BlitIntegrator::new(2. * previous - 1., input(previous))
+        });
+        let value = integrator.process(input(phase), phase_incr);
+
+        // the output only exceeds the range while settling, e.g. during a fast sweep
+        (value * BLIT_NORMALIZATION).clamp(-1., 1.) as f32
+    }
+
+    #[inline]
+    fn generate_blit_square(&mut self, phase_incr: f64) -> f32 {
+        // integrate alternating impulses, rising at phase 0 and falling at phase 0.5
+        let input = |phase: f64| {
+            let shift_phase = Self::unroll_phase(phase + 0.5);
+            2. * (Self::blit(phase, phase_incr) - Self::blit(shift_phase, phase_incr))
+        };
+        let phase = self.phase;
+
+        // start from the naive waveform at the previous sample, before a possible impulse
+        let integrator = self.blit_integrator.get_or_insert_with(|| {
+            let previous = (phase - phase_incr).rem_euclid(1.);
+            let value = if previous < 0.5 { 1. } else { -1. };
+            BlitIntegrator::new(value, input(previous))
+        });
+        let value = integrator.process(input(phase), phase_incr);
+
+        // the output only exceeds the range while settling, e.g. during a fast sweep
+        (value * BLIT_NORMALIZATION).clamp(-1., 1.) as f32
+    }
+
+    // computes the band-limited impulse train (BLIT) at the given phase, i.e. a sum of cosines
+    // of all the harmonics below the Nyquist frequency, with a mean of `phase_incr` per sample
+    // so every impulse integrates to 1
+    // cf. Stilson & Smith, "Alias-Free Digital Synthesis of Classic Analog Waveforms", 1996
+    #[inline]
+    fn blit(phase: f64, phase_incr: f64) -> f64 {
+        let phase_incr = phase_incr.abs().max(MIN_BLIT_PHASE_INCR);
+        // highest harmonic strictly below the Nyquist frequency
+        let top = (0.5 / phase_incr).ceil() - 1.;
+        if top < 1. {
+            return phase_incr;
         }
+
+        // closed form of the DC term and the lower harmonics, i.e. the Dirichlet kernel
+        let terms = 2. * top - 1.;
+        let denominator = (PI * phase).sin();
+        let lower = if denominator.abs() < 1e-9 {
+            // limit at the location of the impulse
+            terms
+        } else {
+            (PI * terms * phase).sin() / denominator
+        };
+
+        // The top harmonic fades out as it approaches the Nyquist frequency, where the next
+        // harmonic takes over, so the integrated harmonics do not jump during a sweep.
+        let gain = (0.5 - top * phase_incr) / phase_incr;
+        let top = 2. * gain * (2. * PI * top * phase).cos();
+
+        phase_incr * (lower + top)
     }
 
     #[inline]
@@ -546,9 +1036,15 @@ mod tests {
 
     use crate::context::{BaseAudioContext, OfflineAudioContext};
     use crate::node::{AudioNode, AudioScheduledSourceNode};
+    use crate::param::AutomationRate;
     use crate::periodic_wave::{PeriodicWave, PeriodicWaveOptions};
+    use crate::RENDER_QUANTUM_SIZE;
+
+    use easyfft::prelude::*;
 
-    use super::{OscillatorNode, OscillatorOptions, OscillatorRenderer, OscillatorType};
+    #[cfg(feature = "simd")]
+    use super::OscillatorRenderer;
+    use super::{poly_blep, OscillatorNode, OscillatorOptions, OscillatorType};
 
     #[test]
     fn assert_osc_default_build_with_factory_func() {
@@ -651,6 +1147,41 @@ mod tests {
     // - PolyBlep is not applied on `square` and `triangle` for tests, so we can
     //   compare according to a crude waveforms
 
+    #[test]
+    fn sine_tracks_frequency_changes() {
+        let sample_rate = 48_000.;
+        let segment = 24_000;
+        let frequencies = [100., 440., 1_000., 3_000., 55.];
+        let context = OfflineAudioContext::new(1, segment * frequencies.len(), sample_rate as f32);
+
+        let osc = context.create_oscillator();
+        frequencies.iter().enumerate().for_each(|(i, &f)| {
+            osc.frequency()
+                .set_value_at_time(f, (i * segment) as f64 / sample_rate);
+        });
+        osc.connect(&context.destination());
+        osc.start();
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        for (i, &frequency) in frequencies.iter().enumerate() {
+            let data = &output[i * segment..(i + 1) * segment];
+
+            // interpolated times of the rising zero crossings
+            let crossings: Vec<f64> = data
+                .windows(2)
+                .enumerate()
+                .filter(|(_, w)| w[0] < 0. && w[1] >= 0.)
+                .map(|(n, w)| n as f64 + (w[0] / (w[0] - w[1])) as f64)
+                .collect();
+
+            let periods = (crossings.len() - 1) as f64;
+            let measured = periods * sample_rate / (crossings[crossings.len() - 1] - crossings[0]);
+            assert_float_eq!(measured, frequency as f64, r2nd <= 1e-4);
+        }
+    }
+
     #[test]
     fn sine_raw() {
         // 1, 10, 100, 1_000, 10_000 Hz
@@ -687,6 +1218,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn initial_phase() {
+        // a period of exactly 100 samples
+        let sample_rate = 48_000.;
+        let frequency = 480.;
+
+        let types = [
+            OscillatorType::Sine,
+            OscillatorType::Square,
+            OscillatorType::Sawtooth,
+            OscillatorType::Triangle,
+        ];
+
+        for type_ in types.iter().copied() {
+            let render = |initial_phase| {
+                let context = OfflineAudioContext::new(1, 2 * RENDER_QUANTUM_SIZE, sample_rate);
+                let options = OscillatorOptions {
+                    type_,
+                    frequency,
+                    initial_phase,
+                    ..OscillatorOptions::default()
+                };
+                let osc = OscillatorNode::new(&context, options);
+                osc.connect(&context.destination());
+                osc.start();
+                context.start_rendering_sync().get_channel_data(0).to_vec()
+            };
+
+            let reference = render(0.);
+            let shifted = render(0.25);
+
+            // a quarter period shift, i.e. 25 samples
+            assert_float_eq!(
+                shifted[..200],
+                reference[25..225],
+                abs_all <= 1e-4,
+                "{:?}",
+                type_
+            );
+
+            // the phase wraps around the period
+            assert_float_eq!(render(1.25)[..], shifted[..], abs_all <= 1e-4);
+            assert_float_eq!(render(-0.75)[..], shifted[..], abs_all <= 1e-4);
+        }
+    }
+
     #[test]
     fn sine_raw_exact_phase() {
         // 1, 10, 100, 1_000, 10_000 Hz
@@ -941,6 +1518,98 @@ mod tests {
         }
     }
 
+    #[test]
+    fn set_periodic_wave_does_not_block() {
+        let sample_rate = 44_100;
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate as f32);
+
+        let osc = context.create_oscillator();
+        osc.connect(&context.destination());
+        osc.frequency().set_value(1000.);
+        osc.start_at(0.);
+
+        // the render thread does not pick up any wave before rendering starts
+        let amplitude = |i: usize| (i + 1) as f32 / 2000.;
+        for i in 0..1000 {
+            let options = PeriodicWaveOptions {
+                real: Some(vec![0., 0.]),
+                imag: Some(vec![0., amplitude(i)]),
+                disable_normalization: true,
+            };
+            osc.set_periodic_wave(context.create_periodic_wave(options));
+        }
+
+        let output = context.start_rendering_sync();
+        let result = output.get_channel_data(0);
+
+        // the last wave is applied, with an amplitude of 0.5
+        let expected: Vec<f32> = (0..RENDER_QUANTUM_SIZE)
+            .map(|i| 0.5 * (2. * PI * 1000. * i as f64 / sample_rate as f64).sin() as f32)
+            .collect();
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
+    }
+
+    #[test]
+    #[cfg(feature = "simd")]
+    fn simd_sine_matches_scalar() {
+        use crate::control::Scheduler;
+        use crate::param::{AudioParamDescriptor, AutomationRate};
+        use std::sync::atomic::{AtomicBool, AtomicU32};
+        use std::sync::Arc;
+
+        let context = OfflineAudioContext::new(1, 0, 44_100.);
+        let registration = context.mock_registration();
+        let opts = AudioParamDescriptor {
+            min_value: f32::MIN,
+            max_value: f32::MAX,
+            default_value: 0.,
+            automation_rate: AutomationRate::A,
+        };
+        let (_, frequency) = context.create_audio_param(opts.clone(), &registration);
+        let (_, detune) = context.create_audio_param(opts, &registration);
+        let (_, reader) = crate::triple_buffer::triple_buffer();
+
+        let mut renderer = OscillatorRenderer {
+            type_: Arc::new(AtomicU32::new(OscillatorType::Sine as u32)),
+            frequency,
+            detune,
+            scheduler: Scheduler::new(),
+            phase: 0.,
+            initial_phase: 0.,
+            alias_guard: Arc::new(AtomicBool::new(false)),
+            clamping: false,
+            current_phase: Arc::new(crate::AtomicF32::new(0.)),
+            started: true,
+            periodic_wave: reader,
+            ended_triggered: false,
+            blit_integrator: None,
+            previous_type: OscillatorType::Sine,
+            previous_sync: 0.,
+            sync_residual: 0.,
+        };
+
+        // frequencies with an irrational phase increment, several blocks to cover wrapping
+        for frequency in [1., 440., 1234.567, 15_000.] {
+            let phase_incr = frequency / 44_100.;
+            renderer.phase = 0.3;
+
+            let mut scalar = [0.; RENDER_QUANTUM_SIZE * 4];
+            scalar.iter_mut().for_each(|o| {
+                *o = renderer.generate_sine();
+                renderer.phase = OscillatorRenderer::unroll_phase(renderer.phase + phase_incr);
+            });
+            let scalar_phase = renderer.phase;
+
+            renderer.phase = 0.3;
+            let mut simd = [0.; RENDER_QUANTUM_SIZE * 4];
+            simd.chunks_mut(RENDER_QUANTUM_SIZE)
+                .for_each(|block| renderer.generate_sine_block(block, phase_incr));
+
+            assert_float_eq!(simd[..], scalar[..], abs_all <= 0.);
+            assert_float_eq!(renderer.phase, scalar_phase, abs <= 0.);
+        }
+    }
+
     #[test]
     fn polyblep_isolated() {
         // @note: Only first branch of the polyblep seems to be used here.
@@ -956,8 +1625,8 @@ mod tests {
             for (index, s) in signal.iter_mut().enumerate() {
                 let phase = index as f64 / len;
 
-                *s += OscillatorRenderer::poly_blep(phase, dt, false);
-                *s -= OscillatorRenderer::poly_blep((phase + 0.5) % 1., dt, false);
+                *s += poly_blep(phase, dt, false);
+                *s -= poly_blep((phase + 0.5) % 1., dt, false);
             }
 
             let expected = [0., 1., 1., 1., 0., -1., -1., -1.];
@@ -973,7 +1642,7 @@ mod tests {
 
             for (index, s) in signal.iter_mut().enumerate() {
                 let phase = index as f64 / len;
-                *s -= OscillatorRenderer::poly_blep((phase + 0.5) % 1., dt, false);
+                *s -= poly_blep((phase + 0.5) % 1., dt, false);
             }
 
             let expected = [0., 0.25, 0.75, 1., 0., -0.75, -0.5, -0.25];
@@ -981,6 +1650,204 @@ mod tests {
         }
     }
 
+    // energy outside of the harmonics of the fundamental, relative to the total energy
+    fn inharmonic_ratio(signal: &[f32], fundamental: usize) -> f32 {
+        // one second of signal, so the bins are 1 Hz wide
+        let spectrum = signal.real_fft();
+        let power: Vec<f32> = spectrum.iter().map(|c| c.norm_sqr()).collect();
+        let total: f32 = power[1..].iter().sum();
+        let harmonic: f32 = power.iter().step_by(fundamental).skip(1).sum();
+        (total - harmonic) / total
+    }
+
+    #[test]
+    fn alias_guard() {
+        let sample_rate = 48_000;
+
+        // peak frequency and spectrum of a one second render, with 1 Hz wide bins
+        let render = |guard: bool| {
+            let context = OfflineAudioContext::new(1, sample_rate, sample_rate as f32);
+            let osc = context.create_oscillator();
+            assert!(!osc.alias_guard());
+            osc.set_alias_guard(guard);
+            assert_eq!(osc.alias_guard(), guard);
+
+            // 40kHz after detune, within the range of the frequency param
+            osc.frequency().set_value(20_000.);
+            osc.detune().set_value(1200.);
+            osc.connect(&context.destination());
+            osc.start();
+
+            let output = context.start_rendering_sync();
+            let power: Vec<f32> = output
+                .get_channel_data(0)
+                .real_fft()
+                .iter()
+                .map(|c| c.norm_sqr())
+                .collect();
+            let peak = power
+                .iter()
+                .enumerate()
+                .fold((0, 0.), |max, (i, &p)| if p > max.1 { (i, p) } else { max })
+                .0;
+            (peak, power)
+        };
+
+        // by default, the frequency aliases back to 48kHz - 40kHz
+        let (peak, _) = render(false);
+        assert_eq!(peak, 8_000);
+
+        // with the guard, the frequency is clamped to just below 24kHz
+        let (peak, power) = render(true);
+        let max_frequency = super::ALIAS_GUARD_RATIO * sample_rate as f32 / 2.;
+        assert_float_eq!(peak as f32, max_frequency, abs <= 1.);
+
+        let total: f32 = power.iter().sum();
+        let aliased: f32 = power[7_990..8_010].iter().sum();
+        assert!(aliased / total < 1e-6);
+    }
+
+    #[test]
+    fn frequency_param_range() {
+        let sample_rate = 44_100.;
+        let context = OfflineAudioContext::new(1, 0, sample_rate);
+        let osc = context.create_oscillator();
+
+        let frequency = osc.frequency();
+        assert_float_eq!(frequency.min_value(), -sample_rate / 2., abs <= 0.);
+        assert_float_eq!(frequency.max_value(), sample_rate / 2., abs <= 0.);
+        assert_float_eq!(frequency.default_value(), 440., abs <= 0.);
+
+        // the range does not depend on the automation rate
+        frequency.set_automation_rate(AutomationRate::K);
+        assert_float_eq!(frequency.min_value(), -sample_rate / 2., abs <= 0.);
+        assert_float_eq!(frequency.max_value(), sample_rate / 2., abs <= 0.);
+    }
+
+    #[test]
+    fn current_phase() {
+        let sample_rate = 48_000.;
+        let frequency = 100.;
+        let mut context = OfflineAudioContext::new(1, 128 * 8, sample_rate);
+
+        let options = OscillatorOptions {
+            frequency,
+            initial_phase: 0.25,
+            ..OscillatorOptions::default()
+        };
+        let osc = std::sync::Arc::new(OscillatorNode::new(&context, options));
+        osc.connect(&context.destination());
+        assert_float_eq!(osc.current_phase(), 0.25, abs <= 0.);
+
+        // poll the phase in between render quanta, the oscillator starts in the second one
+        let phases = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        for i in 1..8 {
+            let osc = std::sync::Arc::clone(&osc);
+            let phases = std::sync::Arc::clone(&phases);
+            context.suspend_at(128 * i, move |_| {
+                phases.lock().unwrap().push(osc.current_phase());
+            });
+        }
+        osc.start_at(128. / f64::from(sample_rate));
+
+        context.start_rendering_sync();
+
+        let phases = phases.lock().unwrap();
+        // not started yet
+        assert_float_eq!(phases[0], 0.25, abs <= 0.);
+        // then advances 128 * 100 / 48000 of a period every render quantum
+        phases.iter().skip(1).enumerate().for_each(|(i, &phase)| {
+            let expected = (0.25 + (i + 1) as f32 * 128. * frequency / sample_rate) % 1.;
+            assert_float_eq!(phase, expected, abs <= 1e-5);
+        });
+    }
+
+    #[test]
+    fn blit_spectrum() {
+        let sample_rate = 48_000;
+        // the harmonics above 24000 Hz alias in between the harmonics below
+        let frequency = 3_100;
+
+        let mut blit_ratios = vec![];
+        for type_ in [OscillatorType::BlitSawtooth, OscillatorType::BlitSquare] {
+            let context = OfflineAudioContext::new(1, 2 * sample_rate, sample_rate as f32);
+            let osc = context.create_oscillator();
+            osc.set_type(type_);
+            osc.frequency().set_value(frequency as f32);
+            osc.connect(&context.destination());
+            osc.start();
+
+            let output = context.start_rendering_sync();
+            // skip the settling of the integrator
+            let signal = &output.get_channel_data(0)[sample_rate..];
+            blit_ratios.push(inharmonic_ratio(signal, frequency));
+
+            // the amplitude of the fundamental is close to the one of the ideal waveform
+            let spectrum = signal.real_fft();
+            let fundamental = 2. * spectrum[frequency].norm() / sample_rate as f32;
+            let expected = match type_ {
+                OscillatorType::BlitSawtooth => 2. / std::f32::consts::PI,
+                _ => 4. / std::f32::consts::PI,
+            };
+            assert_float_eq!(
+                fundamental,
+                expected * super::BLIT_NORMALIZATION as f32,
+                r2nd <= 0.02
+            );
+        }
+
+        // the polyBLEP sawtooth, which is not applied in the renderer during tests
+        let dt = frequency as f64 / sample_rate as f64;
+        let blep: Vec<f32> = (0..sample_rate)
+            .map(|n| {
+                let phase = (n as f64 * dt + 0.5) % 1.;
+                (2. * phase - 1. - poly_blep(phase, dt, false)) as f32
+            })
+            .collect();
+        let blep_ratio = inharmonic_ratio(&blep, frequency);
+        // the naive sawtooth
+        let naive: Vec<f32> = (0..sample_rate)
+            .map(|n| (2. * ((n as f64 * dt + 0.5) % 1.) - 1.) as f32)
+            .collect();
+        let naive_ratio = inharmonic_ratio(&naive, frequency);
+
+        // both the BLEP and BLIT oscillators are band-limited, the BLIT ones cleaner
+        assert!(blep_ratio < naive_ratio / 10.);
+        blit_ratios.iter().for_each(|&ratio| {
+            assert!(ratio < blep_ratio / 10., "{} {}", ratio, blep_ratio);
+        });
+    }
+
+    #[test]
+    fn blit_sweep_is_stable() {
+        let sample_rate = 48_000.;
+        let duration = 4.;
+
+        for type_ in [OscillatorType::BlitSawtooth, OscillatorType::BlitSquare] {
+            let length = (duration * sample_rate) as usize;
+            let context = OfflineAudioContext::new(1, length, sample_rate as f32);
+            let osc = context.create_oscillator();
+            osc.set_type(type_);
+            osc.frequency().set_value(20.);
+            osc.frequency()
+                .exponential_ramp_to_value_at_time(20_000., duration / 2.)
+                .exponential_ramp_to_value_at_time(100., duration);
+            osc.connect(&context.destination());
+            osc.start();
+
+            let output = context.start_rendering_sync();
+            let output = output.get_channel_data(0);
+
+            // the amplitude stays within range without clipping
+            assert!(output.iter().all(|v| v.is_finite() && v.abs() < 1.));
+
+            // no DC offset builds up, the last 10 periods at 100 Hz average out
+            let tail = &output[length - 4_800..];
+            let mean = tail.iter().sum::<f32>() / tail.len() as f32;
+            assert!(mean.abs() < 0.05, "{}", mean);
+        }
+    }
+
     #[test]
     fn osc_sub_quantum_start() {
         let freq = 1.25;
@@ -1114,14 +1981,17 @@ mod tests {
         let freq = 8910.1;
         let sample_rate = 44_100;
 
-        let context = OfflineAudioContext::new(1, sample_rate, sample_rate as f32);
+        let mut context =
+            OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE + sample_rate, sample_rate as f32);
         let osc = context.create_oscillator();
         osc.connect(&context.destination());
         osc.frequency().set_value(freq);
-        osc.start_at(-1.);
+
+        // start at time 0 while rendering the second block
+        context.suspend_at(RENDER_QUANTUM_SIZE, move |_| osc.start_at(0.));
 
         let output = context.start_rendering_sync();
-        let result = output.get_channel_data(0);
+        let result = &output.get_channel_data(0)[RENDER_QUANTUM_SIZE..];
 
         let mut expected = Vec::<f32>::with_capacity(sample_rate);
         let mut phase: f64 = 0.;
@@ -1135,4 +2005,221 @@ mod tests {
 
         assert_float_eq!(result[..], expected[..], abs_all <= 1e-5);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+        let wave_options = PeriodicWaveOptions {
+            real: Some(vec![0., 0.5, 0.25]),
+            imag: Some(vec![0., 0.5, 0.125]),
+            disable_normalization: false,
+        };
+        let json = serde_json::to_string(&wave_options).unwrap();
+        let wave_options: PeriodicWaveOptions = serde_json::from_str(&json).unwrap();
+        assert_eq!(wave_options.real, Some(vec![0., 0.5, 0.25]));
+        assert_eq!(wave_options.imag, Some(vec![0., 0.5, 0.125]));
+
+        let options = OscillatorOptions {
+            type_: OscillatorType::Custom,
+            frequency: 220.,
+            detune: 12.,
+            periodic_wave: Some(PeriodicWave::new(&context, wave_options)),
+            ..OscillatorOptions::default()
+        };
+
+        let json = serde_json::to_value(&options).unwrap();
+        assert_eq!(json["type"], "custom");
+        assert_eq!(json["channel_config"]["count_mode"], "max");
+        assert_eq!(json["channel_config"]["interpretation"], "speakers");
+
+        let restored: OscillatorOptions = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.type_, OscillatorType::Custom);
+        assert_float_eq!(restored.frequency, 220., abs <= 0.);
+        assert_float_eq!(restored.detune, 12., abs <= 0.);
+        assert_float_eq!(
+            restored.periodic_wave.as_ref().unwrap().as_slice(),
+            options.periodic_wave.as_ref().unwrap().as_slice(),
+            abs_all <= 0.
+        );
+
+        // both configs build identical nodes
+        let render = |options| {
+            let context = OfflineAudioContext::new(1, 2 * RENDER_QUANTUM_SIZE, 44_100.);
+            let osc = OscillatorNode::new(&context, options);
+            osc.connect(&context.destination());
+            osc.start();
+            context.start_rendering_sync().get_channel_data(0).to_vec()
+        };
+        assert_float_eq!(render(options)[..], render(restored)[..], abs_all <= 0.);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_names() {
+        let names: Vec<String> = [
+            OscillatorType::Sine,
+            OscillatorType::Square,
+            OscillatorType::Sawtooth,
+            OscillatorType::Triangle,
+            OscillatorType::Custom,
+            OscillatorType::BlitSawtooth,
+            OscillatorType::BlitSquare,
+        ]
+        .iter()
+        .map(|type_| serde_json::to_string(type_).unwrap())
+        .collect();
+        assert_eq!(
+            names,
+            [
+                "\"sine\"",
+                "\"square\"",
+                "\"sawtooth\"",
+                "\"triangle\"",
+                "\"custom\"",
+                "\"blitsawtooth\"",
+                "\"blitsquare\""
+            ]
+        );
+
+        // missing fields take their default value
+        let options: OscillatorOptions = serde_json::from_str(r#"{"type": "square"}"#).unwrap();
+        assert_eq!(options.type_, OscillatorType::Square);
+        assert_float_eq!(options.frequency, 440., abs <= 0.);
+        assert!(options.periodic_wave.is_none());
+
+        // the wavetable length is validated
+        let invalid = serde_json::from_str::<OscillatorOptions>(r#"{"periodic_wave": [0, 1]}"#);
+        assert!(invalid.is_err());
+    }
+
+    // renders a slave oscillator at 250 Hz, synced to a master sine at 100 Hz if given
+    fn render_hard_sync(type_: OscillatorType, master_frequency: Option<f32>) -> Vec<f32> {
+        let sample_rate = 48_000.;
+        let context = OfflineAudioContext::new(1, 48_000, sample_rate);
+
+        let slave = context.create_oscillator();
+        slave.set_type(type_);
+        slave.frequency().set_value(250.);
+        slave.connect(&context.destination());
+        slave.start();
+
+        if let Some(frequency) = master_frequency {
+            let master = context.create_oscillator();
+            master.frequency().set_value(frequency);
+            master.connect(&slave);
+            master.start();
+        }
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    // magnitude of the component of the signal at the given frequency, for a one second signal
+    fn magnitude_at(signal: &[f32], frequency: f64) -> f64 {
+        let (re, im) = signal
+            .iter()
+            .enumerate()
+            .fold((0., 0.), |(re, im), (i, &s)| {
+                let angle = 2. * PI * frequency * i as f64 / signal.len() as f64;
+                (re + s as f64 * angle.cos(), im - s as f64 * angle.sin())
+            });
+
+        2. * re.hypot(im) / signal.len() as f64
+    }
+
+    #[test]
+    fn test_hard_sync() {
+        // one period of the master
+        let period = 480;
+
+        for type_ in [
+            OscillatorType::Sine,
+            OscillatorType::Sawtooth,
+            OscillatorType::Square,
+            OscillatorType::Triangle,
+        ] {
+            let free = render_hard_sync(type_, None);
+            let synced = render_hard_sync(type_, Some(100.));
+
+            // the output period locks to the master's
+            assert_float_eq!(
+                synced[period..synced.len() - period],
+                synced[2 * period..],
+                abs_all <= 1e-3
+            );
+            assert!(magnitude_at(&synced, 100.) > 0.05);
+            assert!(magnitude_at(&free, 100.) < 1e-3);
+
+            // the slave still runs at its own frequency within the period, with a new timbre. The
+            // master starts at a zero crossing, which resets the slave on the second sample.
+            assert_float_eq!(synced[2..200], free[1..199], abs_all <= 1e-3);
+            let difference = synced
+                .iter()
+                .zip(free.iter())
+                .map(|(a, b)| (a - b).abs())
+                .fold(0., f32::max);
+            assert!(difference > 0.5);
+        }
+    }
+
+    #[test]
+    fn test_hard_sync_is_band_limited() {
+        // the slave is a quarter period ahead when reset, i.e. close to its maximum
+        let synced = render_hard_sync(OscillatorType::Sine, Some(77.));
+
+        // the drop to zero is spread over two samples
+        let max_step = synced
+            .windows(2)
+            .map(|w| (w[1] - w[0]).abs())
+            .fold(0., f32::max);
+        let max_drop = synced
+            .windows(3)
+            .map(|w| (w[2] - w[0]).abs())
+            .fold(0., f32::max);
+        assert!(max_drop > 0.9);
+        assert!(max_step < 0.8);
+    }
+
+    #[test]
+    fn test_hard_sync_follows_frequency_modulation() {
+        let sample_rate = 48_000.;
+        let context = OfflineAudioContext::new(1, 48_000, sample_rate);
+
+        let slave = context.create_oscillator();
+        slave.set_type(OscillatorType::Sawtooth);
+        slave.frequency().set_value(1000.);
+        slave.connect(&context.destination());
+        slave.start();
+
+        // the master sweeps from 100 Hz to 200 Hz
+        let master = context.create_oscillator();
+        master.frequency().set_value(100.);
+        master.frequency().linear_ramp_to_value_at_time(200., 1.);
+        master.connect(&slave);
+        master.start();
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        // the slave restarts from zero on every upward zero crossing of the master
+        let mut phase = 0.;
+        let mut resets = vec![];
+        for i in 0..48_000 {
+            let frequency = 100. + 100. * i as f64 / 48_000.;
+            let next = phase + frequency / 48_000.;
+            if next >= 1. {
+                // reset one sample after the crossing
+                resets.push(i + 2);
+            }
+            phase = next % 1.;
+        }
+
+        assert!(resets.len() > 140);
+        resets.iter().filter(|&&i| i < 47_999).for_each(|&i| {
+            // rising from zero after the reset, by about 2 * 1000 / 48000 per sample, within a
+            // sample of the exact position
+            assert!(output[i - 1..=i + 1].iter().any(|v| (0. ..0.1).contains(v)));
+        });
+    }
 }
@@ -1,11 +1,12 @@
 use std::f32::consts::PI;
-use std::sync::atomic::{AtomicU8, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
 
 use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
 use crate::param::{AudioParam, AudioParamDescriptor};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
-use crate::{AtomicF64, RENDER_QUANTUM_SIZE};
+use crate::triple_buffer::{triple_buffer, TripleBufferReader, TripleBufferWriter};
+use crate::AtomicF64;
 
 use super::{
     AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
@@ -119,18 +120,18 @@ struct HrtfState {
 }
 
 impl HrtfState {
-    fn new(hrir_sphere: HrirSphere) -> Self {
+    fn new(hrir_sphere: HrirSphere, render_quantum_size: usize) -> Self {
         let len = hrir_sphere.len();
 
         let interpolation_steps = 1;
-        let samples_per_step = RENDER_QUANTUM_SIZE / interpolation_steps;
+        let samples_per_step = render_quantum_size / interpolation_steps;
 
         let processor = HrtfProcessor::new(hrir_sphere, interpolation_steps, samples_per_step);
 
         Self {
             len,
             processor,
-            output_interleaved: vec![(0., 0.); RENDER_QUANTUM_SIZE],
+            output_interleaved: vec![(0., 0.); render_quantum_size],
             prev_sample_vector: Vec3::new(0., 0., 1.),
             prev_left_samples: vec![],  // will resize accordingly
             prev_right_samples: vec![], // will resize accordingly
@@ -177,6 +178,102 @@ impl HrtfState {
     }
 }
 
+/// Default speed of sound for the doppler effect, in units per second
+pub const DEFAULT_SPEED_OF_SOUND: f64 = 343.3;
+
+/// Longest propagation delay of the doppler effect, in seconds
+const MAX_DOPPLER_DELAY: f64 = 1.;
+
+/// Internal state of the doppler effect
+///
+/// The pitch shift is applied with a delay line, the distance between the write and read
+/// positions is the propagation delay of the sound. It starts at the delay corresponding to
+/// the distance between source and listener, and shrinks (grows) when the pitch is raised
+/// (lowered).
+struct DopplerState {
+    buffer: Vec<f32>,
+    write_index: usize,
+    /// propagation delay, in samples
+    delay: f64,
+    max_delay: f64,
+    /// source and listener positions of the previous render quantum
+    prev_positions: Option<([f32; 3], [f32; 3])>,
+}
+
+impl DopplerState {
+    fn new(sample_rate: f32) -> Self {
+        let max_delay = (MAX_DOPPLER_DELAY * sample_rate as f64).ceil();
+        // room for the max delay and the linear interpolation
+        let len = max_delay as usize + 2;
+
+        Self {
+            buffer: vec![0.; len],
+            write_index: 0,
+            delay: 0.,
+            max_delay,
+            prev_positions: None,
+        }
+    }
+
+    /// Apply the pitch shift in place, for the positions at the start of the render quantum
+    fn process(
+        &mut self,
+        signal: &mut [f32],
+        source_position: [f32; 3],
+        listener_position: [f32; 3],
+        speed_of_sound: f32,
+        sample_rate: f32,
+    ) {
+        let rate = match self.prev_positions {
+            None => {
+                let distance = crate::spatial::distance(source_position, listener_position);
+                let delay = distance as f64 / speed_of_sound as f64 * sample_rate as f64;
+                self.delay = delay.clamp(0., self.max_delay);
+                1.
+            }
+            Some((prev_source_position, prev_listener_position)) => {
+                // velocities from the displacement during the previous render quantum
+                let scale = sample_rate / signal.len() as f32;
+                let velocity = |current: [f32; 3], prev: [f32; 3]| {
+                    [0, 1, 2].map(|i| (current[i] - prev[i]) * scale)
+                };
+
+                crate::spatial::doppler_rate(
+                    source_position,
+                    listener_position,
+                    velocity(source_position, prev_source_position),
+                    velocity(listener_position, prev_listener_position),
+                    speed_of_sound,
+                )
+            }
+        };
+        self.prev_positions = Some((source_position, listener_position));
+
+        let len = self.buffer.len();
+        let delay_incr = 1. - rate as f64;
+
+        signal.iter_mut().for_each(|s| {
+            self.buffer[self.write_index] = *s;
+
+            // linear interpolation between the two samples around the read position
+            let read_position = self.write_index as f64 + len as f64 - self.delay;
+            let read_floor = read_position.floor();
+            let k = (read_position - read_floor) as f32;
+            let prev = self.buffer[read_floor as usize % len];
+            let next = self.buffer[(read_floor as usize + 1) % len];
+            *s = (1. - k) * prev + k * next;
+
+            self.delay = (self.delay + delay_incr).clamp(0., self.max_delay);
+            self.write_index = (self.write_index + 1) % len;
+        });
+    }
+
+    /// Number of samples still in the delay line when the input becomes silent
+    fn tail_time_samples(&self) -> usize {
+        self.delay.ceil() as usize + 1
+    }
+}
+
 /// Node that positions / spatializes an incoming audio stream in three-dimensional space.
 ///
 /// - MDN documentation: <https://developer.mozilla.org/en-US/docs/Web/API/PannerNode>
@@ -243,6 +340,10 @@ pub struct PannerNode {
     panning_model: AtomicU8,
     /// HRTF message bus to the renderer
     sender: Sender<Option<HrtfState>>,
+    speed_of_sound: Arc<AtomicF64>,
+    doppler_enabled: AtomicBool,
+    /// doppler delay line passed to the renderer, the latest value wins so toggling never blocks
+    doppler_writer: Mutex<TripleBufferWriter<Option<DopplerState>>>,
 }
 
 impl AudioNode for PannerNode {
@@ -319,6 +420,10 @@ impl PannerNode {
             // simply block the control thread when used concurrently
             let (sender, receiver) = crossbeam_channel::bounded(1);
 
+            // doppler attributes, the delay line is sent to the renderer when enabled
+            let speed_of_sound = Arc::new(AtomicF64::new(DEFAULT_SPEED_OF_SOUND));
+            let (doppler_writer, doppler_reader) = triple_buffer();
+
             let render = PannerRenderer {
                 position_x: render_px,
                 position_y: render_py,
@@ -335,6 +440,8 @@ impl PannerNode {
                 cone_outer_gain: cone_outer_gain.clone(),
                 hrtf_state: None,
                 receiver,
+                speed_of_sound: speed_of_sound.clone(),
+                doppler_state: doppler_reader,
                 tail_time_counter: 0,
             };
 
@@ -361,6 +468,9 @@ impl PannerNode {
                 cone_outer_gain,
                 sender,
                 panning_model: AtomicU8::new(0),
+                speed_of_sound,
+                doppler_enabled: AtomicBool::new(false),
+                doppler_writer: Mutex::new(doppler_writer),
             };
 
             node.set_panning_model(options.panning_model);
@@ -472,13 +582,63 @@ impl PannerNode {
                 let resource = include_bytes!("../../resources/IRC_1003_C.bin");
                 let sample_rate = self.context().sample_rate() as u32;
                 let hrir_sphere = HrirSphere::new(&resource[..], sample_rate).unwrap();
-                Some(HrtfState::new(hrir_sphere))
+                Some(HrtfState::new(
+                    hrir_sphere,
+                    self.context().render_quantum_size(),
+                ))
             }
         };
 
         let _ = self.sender.send(hrtf_option); // can fail when render thread shut down
         self.panning_model.store(value as u8, Ordering::SeqCst);
     }
+
+    /// Returns true if the doppler effect is enabled, false by default
+    ///
+    /// The doppler effect was removed from the Web Audio API specification.
+    pub fn doppler_enabled(&self) -> bool {
+        self.doppler_enabled.load(Ordering::SeqCst)
+    }
+
+    /// Enable or disable the doppler effect
+    ///
+    /// The pitch of the sound is shifted according to the velocities of the panner and the
+    /// listener, derived from the change of their positions at each render quantum. The
+    /// output is delayed by the propagation time of the sound between panner and listener, at
+    /// most one second.
+    ///
+    /// The doppler effect was removed from the Web Audio API specification.
+    pub fn set_doppler_enabled(&self, value: bool) {
+        // hold the lock so concurrent calls write the values in the order of the flag changes
+        let mut doppler_writer = self.doppler_writer.lock().unwrap();
+        if self.doppler_enabled.swap(value, Ordering::SeqCst) == value {
+            return;
+        }
+
+        let doppler_option = value.then(|| DopplerState::new(self.context().sample_rate()));
+        doppler_writer.write(doppler_option);
+    }
+
+    /// Speed of sound for the doppler effect, in units per second
+    ///
+    /// Defaults to [`DEFAULT_SPEED_OF_SOUND`], the speed of sound in air in meters per second.
+    pub fn speed_of_sound(&self) -> f64 {
+        self.speed_of_sound.load()
+    }
+
+    /// Set the speed of sound for the doppler effect, in units per second
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not strictly positive
+    pub fn set_speed_of_sound(&self, value: f64) {
+        assert!(
+            value > 0.,
+            "RangeError - speed of sound should be strictly positive, received {:?}",
+            value
+        );
+        self.speed_of_sound.store(value);
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -505,6 +665,8 @@ struct PannerRenderer {
     cone_outer_gain: Arc<AtomicF64>,
     receiver: Receiver<Option<HrtfState>>,
     hrtf_state: Option<HrtfState>,
+    speed_of_sound: Arc<AtomicF64>,
+    doppler_state: TripleBufferReader<Option<DopplerState>>,
     tail_time_counter: usize,
 }
 
@@ -514,7 +676,7 @@ impl AudioProcessor for PannerRenderer {
         inputs: &[AudioRenderQuantum],
         outputs: &mut [AudioRenderQuantum],
         params: AudioParamValues,
-        _scope: &RenderScope,
+        scope: &RenderScope,
     ) -> bool {
         // single input/output node
         let input = &inputs[0];
@@ -526,23 +688,30 @@ impl AudioProcessor for PannerRenderer {
         // only handle mono for now (todo issue #44)
         output.mix(1, ChannelInterpretation::Speakers);
 
+        // handle changes in doppler effect mandated from control thread
+        self.doppler_state.update();
+
         // early exit for silence
         if input.is_silent() {
             // HRTF panner has tail time equal to the max length of the impulse response buffers
-            // (12 ms)
-            let tail_time = match &self.hrtf_state {
-                None => false,
-                Some(hrtf_state) => hrtf_state.tail_time_samples() > self.tail_time_counter,
-            };
-            if !tail_time {
+            // (12 ms), the doppler effect the propagation delay
+            let hrtf_tail_time = self
+                .hrtf_state
+                .as_ref()
+                .map_or(0, HrtfState::tail_time_samples);
+            let doppler_tail_time = self
+                .doppler_state
+                .get()
+                .and_then(Option::as_ref)
+                .map_or(0, DopplerState::tail_time_samples);
+            if hrtf_tail_time.max(doppler_tail_time) <= self.tail_time_counter {
                 return false;
             }
-            self.tail_time_counter += RENDER_QUANTUM_SIZE;
+            self.tail_time_counter += scope.render_quantum_size;
+        } else {
+            self.tail_time_counter = 0;
         }
 
-        // convert mono to identical stereo
-        output.mix(2, ChannelInterpretation::Speakers);
-
         // handle changes in panning_model_type mandated from control thread
         if let Ok(hrtf_state) = self.receiver.try_recv() {
             self.hrtf_state = hrtf_state;
@@ -562,6 +731,30 @@ impl AudioProcessor for PannerRenderer {
         let [listener_position_x, listener_position_y, listener_position_z, listener_forward_x, listener_forward_y, listener_forward_z, listener_up_x, listener_up_y, listener_up_z] =
             params.listener_params();
 
+        // doppler effect on the mono signal, before panning
+        if let Some(doppler_state) = self.doppler_state.get_mut().and_then(Option::as_mut) {
+            // the silent input is still fed into the delay line to play out the tail
+            let output = output.channel_data_mut(0);
+            doppler_state.process(
+                &mut output[..],
+                [
+                    source_position_x[0],
+                    source_position_y[0],
+                    source_position_z[0],
+                ],
+                [
+                    listener_position_x[0],
+                    listener_position_y[0],
+                    listener_position_z[0],
+                ],
+                self.speed_of_sound.load() as f32,
+                scope.sample_rate,
+            );
+        }
+
+        // convert mono to identical stereo
+        output.mix(2, ChannelInterpretation::Speakers);
+
         // build up the a-rate iterator for spatial variables
         let mut a_rate_params = source_position_x
             .iter()
@@ -638,7 +831,7 @@ impl AudioProcessor for PannerRenderer {
             }
 
             let output_interleaved = hrtf_state.process(
-                output.channel_data(0).as_slice(),
+                &output.channel_data(0)[..],
                 new_distance_gain,
                 projected_source,
             );
@@ -712,8 +905,8 @@ impl AudioProcessor for PannerRenderer {
         // put the hrtf_state back into self (borrow reasons)
         self.hrtf_state = hrtf_state;
 
-        // tail time only for HRTF panning
-        self.hrtf_state.is_some()
+        // tail time only for HRTF panning and the doppler effect
+        self.hrtf_state.is_some() || matches!(self.doppler_state.get(), Some(Some(_)))
     }
 }
 
@@ -783,7 +976,7 @@ mod tests {
 
     use crate::context::{BaseAudioContext, OfflineAudioContext};
     use crate::node::{AudioBufferSourceNode, AudioBufferSourceOptions, AudioScheduledSourceNode};
-    use crate::AudioBuffer;
+    use crate::{AudioBuffer, RENDER_QUANTUM_SIZE};
 
     use super::*;
 
@@ -839,6 +1032,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_listener_orientation() {
+        let sample_rate = 44100.;
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, sample_rate);
+
+        let input = AudioBuffer::from(vec![vec![1.; RENDER_QUANTUM_SIZE]], sample_rate);
+        let src = AudioBufferSourceNode::new(&context, AudioBufferSourceOptions::default());
+        src.set_buffer(input);
+        src.start();
+
+        let options = PannerOptions {
+            panning_model: PanningModelType::EqualPower,
+            ..PannerOptions::default()
+        };
+        let panner = PannerNode::new(&context, options);
+        panner.position_z().set_value(-1.); // sound comes from the front
+
+        // the listener turns to the right, the sound now comes from its left side
+        let listener = context.listener();
+        listener.forward_x().set_value(1.);
+        listener.forward_z().set_value(0.);
+
+        src.connect(&panner);
+        panner.connect(&context.destination());
+
+        let output = context.start_rendering_sync();
+        let original = vec![1.; RENDER_QUANTUM_SIZE];
+        let zero = vec![0.; RENDER_QUANTUM_SIZE];
+
+        assert_float_eq!(
+            output.get_channel_data(0)[..],
+            &original[..],
+            abs_all <= 1E-6
+        );
+        assert_float_eq!(output.get_channel_data(1)[..], &zero[..], abs_all <= 1E-6);
+    }
+
     #[test]
     fn test_hrtf() {
         let sample_rate = 44100.;
@@ -884,4 +1114,137 @@ mod tests {
         let right = output.channel_data(1).as_slice();
         assert!(right[128..256].iter().any(|v| *v >= 1E-6));
     }
+
+    /// Frequency of a signal, from its rising zero crossings
+    fn zero_crossing_frequency(signal: &[f32], sample_rate: f32) -> f32 {
+        let crossings: Vec<f32> = signal
+            .windows(2)
+            .enumerate()
+            .filter(|(_, w)| w[0] < 0. && w[1] >= 0.)
+            .map(|(i, w)| i as f32 + w[0] / (w[0] - w[1]))
+            .collect();
+
+        let periods = (crossings.len() - 1) as f32;
+        periods * sample_rate / (crossings[crossings.len() - 1] - crossings[0])
+    }
+
+    #[test]
+    fn test_doppler() {
+        let sample_rate = 48000.;
+
+        let render = |doppler_enabled: bool| {
+            let context = OfflineAudioContext::new(2, sample_rate as usize, sample_rate);
+
+            let osc = context.create_oscillator();
+            osc.frequency().set_value(1000.);
+            osc.start();
+
+            let panner = context.create_panner();
+            assert!(!panner.doppler_enabled());
+            panner.set_doppler_enabled(doppler_enabled);
+            assert_eq!(panner.doppler_enabled(), doppler_enabled);
+
+            // the source passes in front of the listener at 100 units per second
+            panner.position_x().set_value(10.);
+            panner.position_z().set_value_at_time(-50., 0.);
+            panner.position_z().linear_ramp_to_value_at_time(50., 1.);
+
+            osc.connect(&panner);
+            panner.connect(&context.destination());
+
+            context.start_rendering_sync()
+        };
+
+        let to_index = |t: f32| (t * sample_rate) as usize;
+
+        // pitch rises while approaching, and falls while moving away
+        let output = render(true);
+        let right = output.get_channel_data(1);
+        let approaching =
+            zero_crossing_frequency(&right[to_index(0.3)..to_index(0.45)], sample_rate);
+        let receding = zero_crossing_frequency(&right[to_index(0.7)..to_index(0.95)], sample_rate);
+        assert!(approaching > 1200., "{}", approaching);
+        assert!(receding < 850., "{}", receding);
+
+        // the sound is delayed by the propagation time from 51 units away (0.149 seconds),
+        // shortened by the approach of the source
+        assert!(right[..to_index(0.1)].iter().all(|&v| v == 0.));
+        assert!(right[to_index(0.149)..to_index(0.15)]
+            .iter()
+            .any(|&v| v != 0.));
+
+        // no doppler effect by default
+        let output = render(false);
+        let right = output.get_channel_data(1);
+        let approaching =
+            zero_crossing_frequency(&right[to_index(0.3)..to_index(0.45)], sample_rate);
+        let receding = zero_crossing_frequency(&right[to_index(0.7)..to_index(0.95)], sample_rate);
+        assert_float_eq!(approaching, 1000., rmax <= 1e-3);
+        assert_float_eq!(receding, 1000., rmax <= 1e-3);
+    }
+
+    #[test]
+    fn test_doppler_toggle_before_rendering() {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 48000.);
+
+        let src = context.create_constant_source();
+        src.start();
+
+        // toggling does not block the control thread while the renderer is not running
+        let panner = context.create_panner();
+        panner.set_doppler_enabled(true);
+        panner.set_doppler_enabled(false);
+        panner.set_doppler_enabled(true);
+        panner.set_doppler_enabled(false);
+        assert!(!panner.doppler_enabled());
+
+        // with the doppler effect, the propagation delay would be longer than the render
+        panner.position_z().set_value(-10.);
+
+        src.connect(&panner);
+        panner.connect(&context.destination());
+
+        // the latest setting applies, the signal is not delayed
+        let output = context.start_rendering_sync();
+        assert!(output.get_channel_data(0).iter().all(|&v| v != 0.));
+    }
+
+    #[test]
+    fn test_doppler_tail_time() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE * 10, sample_rate);
+
+        let input = AudioBuffer::from(vec![vec![1.; RENDER_QUANTUM_SIZE]], sample_rate);
+        let src = AudioBufferSourceNode::new(&context, AudioBufferSourceOptions::default());
+        src.set_buffer(input);
+        src.start();
+
+        // propagation delay of 4 render quanta
+        let panner = context.create_panner();
+        panner.set_speed_of_sound(sample_rate as f64 / (4 * RENDER_QUANTUM_SIZE) as f64);
+        panner.position_z().set_value(-1.);
+        panner.set_doppler_enabled(true);
+
+        src.connect(&panner);
+        panner.connect(&context.destination());
+
+        let output = context.start_rendering_sync();
+        let left = output.get_channel_data(0);
+        let start = 4 * RENDER_QUANTUM_SIZE;
+        assert!(left[..start - 1].iter().all(|&v| v == 0.));
+        assert!(left[start + 1..start + RENDER_QUANTUM_SIZE - 1]
+            .iter()
+            .all(|&v| v > 0.5));
+        assert!(left[start + RENDER_QUANTUM_SIZE + 1..]
+            .iter()
+            .all(|&v| v == 0.));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_speed_of_sound() {
+        let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 48000.);
+        let panner = context.create_panner();
+        panner.set_speed_of_sound(0.);
+    }
 }
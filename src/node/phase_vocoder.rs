@@ -0,0 +1,256 @@
+//! Phase vocoder shared by the [`PitchShiftNode`](super::PitchShiftNode) and the
+//! [`TimeStretchNode`](super::TimeStretchNode)
+
+use std::f32::consts::PI;
+
+use easyfft::num_complex::Complex;
+use easyfft::prelude::*;
+
+use super::stft::{hann_window, BINS, FFT_SIZE, HOP_SIZE, WINDOW_OVERLAP_GAIN};
+
+/// Phase advance of a bin center frequency over a hop, per bin
+const BIN_ADVANCE: f32 = 2. * PI * HOP_SIZE as f32 / FFT_SIZE as f32;
+
+/// Short-time Fourier transform based pitch and time modification of a single channel
+///
+/// Every call to [`Self::process`] analyses a frame of input and overlap-adds the resynthesized
+/// frame to the output, which then holds [`HOP_SIZE`] samples ready to be played. The frames
+/// are Hann windowed at the analysis and at the synthesis, so unmodified frames overlap-add back
+/// to the input.
+///
+/// The frequency of each bin is estimated from the phase difference between two analysis frames
+/// `HOP_SIZE` samples apart. Each peak of the spectrum is moved to its transposed frequency
+/// together with its region of influence, the bins closer to it than to any other peak. The
+/// phases of the peaks are advanced according to their frequency, and the bins of each region
+/// keep their phase relative to the peak ("identity phase locking", Laroche & Dolson 1999).
+/// This preserves the shape of the partials, reducing the phasiness and the smearing of
+/// transients of the plain phase vocoder.
+pub(super) struct PhaseVocoder {
+    window: Vec<f32>,
+    /// Windowed analysis frame, then synthesized frame
+    frame: Vec<f32>,
+    spectrum: DynRealDft<f32>,
+    /// Phases of the previous analysis frame, `HOP_SIZE` samples earlier
+    previous_phases: Vec<f32>,
+    magnitudes: Vec<f32>,
+    phases: Vec<f32>,
+    /// Estimated frequency of each bin, in bins
+    frequencies: Vec<f32>,
+    /// Peaks of the analysis spectrum
+    peaks: Vec<usize>,
+    /// Synthesis phase of each peak, at its shifted bin
+    peak_phases: Vec<f32>,
+    /// Analysis bin and index of the peak of its region moved to each synthesis bin,
+    /// `usize::MAX` if none
+    sources: Vec<(usize, usize)>,
+    /// Magnitudes of the synthesis frame
+    shifted_magnitudes: Vec<f32>,
+    /// Phases of the synthesis frame, and of the previous one until it is synthesized
+    synthesis_phases: Vec<f32>,
+    /// Synthesis spectrum, copied into `spectrum` for the inverse FFT
+    bins: Vec<Complex<f32>>,
+    /// Overlap-add of the synthesized frames, the first `HOP_SIZE` samples are complete
+    output: Vec<f32>,
+    /// Whether `synthesis_phases` hold the phases of a previous frame
+    running: bool,
+}
+
+impl PhaseVocoder {
+    pub fn new() -> Self {
+        Self {
+            window: hann_window(),
+            frame: vec![0.; FFT_SIZE],
+            spectrum: DynRealDft::default(FFT_SIZE),
+            previous_phases: vec![0.; BINS],
+            magnitudes: vec![0.; BINS],
+            phases: vec![0.; BINS],
+            frequencies: vec![0.; BINS],
+            peaks: Vec::with_capacity(BINS),
+            peak_phases: Vec::with_capacity(BINS),
+            sources: vec![(usize::MAX, usize::MAX); BINS],
+            shifted_magnitudes: vec![0.; BINS],
+            synthesis_phases: vec![0.; BINS],
+            bins: vec![Complex::default(); BINS],
+            output: vec![0.; FFT_SIZE],
+            running: false,
+        }
+    }
+
+    /// The `HOP_SIZE` samples completed by the latest call to [`Self::process`]
+    pub fn output(&self) -> &[f32] {
+        &self.output[..HOP_SIZE]
+    }
+
+    /// Process the latest `FFT_SIZE` samples of `input`, which holds `FFT_SIZE + HOP_SIZE`
+    /// samples
+    ///
+    /// The frequencies are multiplied by `pitch_ratio`. `time_ratio` is the ratio between the
+    /// hop of the synthesis and the hop of the analysis frames since the previous call, when both
+    /// ratios are 1 the input is passed through.
+    pub fn process(&mut self, input: &[f32], pitch_ratio: f32, time_ratio: f64) {
+        debug_assert_eq!(input.len(), FFT_SIZE + HOP_SIZE);
+
+        self.analyse(&input[..FFT_SIZE]);
+        self.previous_phases
+            .iter_mut()
+            .zip(self.spectrum.iter())
+            .for_each(|(p, c)| *p = c.arg());
+
+        self.analyse(&input[HOP_SIZE..]);
+        self.spectrum
+            .iter()
+            .zip(self.previous_phases.iter())
+            .zip(self.magnitudes.iter_mut())
+            .zip(self.phases.iter_mut())
+            .zip(self.frequencies.iter_mut())
+            .enumerate()
+            .for_each(|(k, ((((c, previous), magnitude), phase), frequency))| {
+                *magnitude = c.norm();
+                *phase = c.arg();
+                // deviation from the advance of the bin center frequency, in [-PI, PI)
+                let deviation =
+                    (*phase - previous - k as f32 * BIN_ADVANCE + PI).rem_euclid(2. * PI) - PI;
+                *frequency = k as f32 + deviation / BIN_ADVANCE;
+            });
+
+        let passthrough = pitch_ratio == 1. && time_ratio == 1.;
+        self.shift(pitch_ratio);
+        self.lock_phases(pitch_ratio, passthrough);
+        self.synthesize();
+    }
+
+    /// Windowed FFT of `input` into `self.spectrum`
+    fn analyse(&mut self, input: &[f32]) {
+        self.frame
+            .iter_mut()
+            .zip(input.iter().zip(self.window.iter()))
+            .for_each(|(f, (i, w))| *f = i * w);
+        self.frame[..].real_fft_using(&mut self.spectrum);
+    }
+
+    /// Move the region of each peak of the analysis spectrum to its shifted frequency
+    fn shift(&mut self, pitch_ratio: f32) {
+        let Self {
+            magnitudes,
+            peaks,
+            sources,
+            shifted_magnitudes,
+            ..
+        } = self;
+
+        peaks.clear();
+        (0..BINS).for_each(|k| {
+            let magnitude = magnitudes[k];
+            let is_peak = magnitude > 0.
+                && (k.saturating_sub(2)..k).all(|i| magnitudes[i] < magnitude)
+                && (k + 1..(k + 3).min(BINS)).all(|i| magnitudes[i] <= magnitude);
+            if is_peak {
+                peaks.push(k);
+            }
+        });
+
+        shifted_magnitudes.fill(0.);
+        sources.fill((usize::MAX, usize::MAX));
+        peaks.iter().enumerate().for_each(|(index, &p)| {
+            // the region extends halfway to the neighbouring peaks
+            let start = if index == 0 {
+                0
+            } else {
+                (peaks[index - 1] + p) / 2 + 1
+            };
+            let end = peaks
+                .get(index + 1)
+                .map_or(BINS, |&next| (p + next) / 2 + 1);
+            let offset = (p as f32 * pitch_ratio).round() as isize - p as isize;
+
+            (start..end).for_each(|k| {
+                let j = k as isize + offset;
+                if j < 0 || j >= BINS as isize {
+                    return;
+                }
+                let j = j as usize;
+                shifted_magnitudes[j] += magnitudes[k];
+                // the phase of the strongest contribution is kept
+                if sources[j].0 == usize::MAX || magnitudes[sources[j].0] < magnitudes[k] {
+                    sources[j] = (k, index);
+                }
+            });
+        });
+    }
+
+    /// Compute the synthesis phases, locked to the phases of the shifted peaks
+    fn lock_phases(&mut self, pitch_ratio: f32, passthrough: bool) {
+        let Self {
+            phases,
+            frequencies,
+            peaks,
+            peak_phases,
+            sources,
+            synthesis_phases,
+            running,
+            ..
+        } = self;
+
+        if passthrough || !*running || peaks.is_empty() {
+            // start from the analysis phases, an unmodified frame is reconstructed exactly
+            sources
+                .iter()
+                .zip(synthesis_phases.iter_mut())
+                .for_each(|(&(k, _), phase)| {
+                    *phase = phases.get(k).copied().unwrap_or(0.);
+                });
+            *running = !peaks.is_empty();
+            return;
+        }
+
+        // advance the phase of each peak according to its frequency, from the previous phase
+        // at its shifted bin
+        peak_phases.clear();
+        peak_phases.extend(peaks.iter().map(|&p| {
+            let j = ((p as f32 * pitch_ratio).round() as usize).min(BINS - 1);
+            let frequency = frequencies[p] * pitch_ratio;
+            (synthesis_phases[j] + BIN_ADVANCE * frequency).rem_euclid(2. * PI)
+        }));
+
+        sources
+            .iter()
+            .zip(synthesis_phases.iter_mut())
+            .for_each(|(&(k, index), phase)| {
+                *phase = match peaks.get(index) {
+                    Some(&p) => peak_phases[index] + phases[k] - phases[p],
+                    None => 0.,
+                };
+            });
+    }
+
+    /// Inverse FFT of the synthesis spectrum, overlap-added to the output
+    fn synthesize(&mut self) {
+        let Self {
+            window,
+            frame,
+            spectrum,
+            shifted_magnitudes,
+            synthesis_phases,
+            bins,
+            output,
+            ..
+        } = self;
+
+        bins.iter_mut()
+            .zip(shifted_magnitudes.iter().zip(synthesis_phases.iter()))
+            .for_each(|(c, (&magnitude, &phase))| *c = Complex::from_polar(magnitude, phase));
+        // the DC and Nyquist bins of a real signal are real
+        bins[0].im = 0.;
+        bins[BINS - 1].im = 0.;
+        spectrum.copy_from_slice(bins);
+        spectrum.real_ifft_using(frame);
+
+        output.copy_within(HOP_SIZE.., 0);
+        output[FFT_SIZE - HOP_SIZE..].fill(0.);
+        let normalization = 1. / (FFT_SIZE as f32 * WINDOW_OVERLAP_GAIN);
+        output
+            .iter_mut()
+            .zip(frame.iter().zip(window.iter()))
+            .for_each(|(o, (f, w))| *o += f * w * normalization);
+    }
+}
@@ -0,0 +1,353 @@
+//! The pitch shift control and renderer parts
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::phase_vocoder::PhaseVocoder;
+use super::stft::{FFT_SIZE, HOP_SIZE};
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions, TailTimeCounter};
+
+/// Options for constructing a [`PitchShiftNode`]
+#[derive(Clone, Debug)]
+pub struct PitchShiftOptions {
+    /// initial value for the pitch ratio parameter
+    pub pitch_ratio: f32,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for PitchShiftOptions {
+    fn default() -> Self {
+        Self {
+            pitch_ratio: 1.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `PitchShiftNode` transposes an audio stream without changing its tempo
+///
+/// The input is analysed with a phase vocoder: a short-time Fourier transform of 2048 samples
+/// frames, 512 samples apart. The partials found in each frame are moved to their transposed
+/// frequency, with the phases of the bins around each partial locked to it to limit the
+/// smearing of transients. Each channel is processed independently.
+///
+/// The output is delayed by 2048 samples (the frame length) relative to the input, including
+/// when the `pitch_ratio` is 1, in which case the input is passed through unmodified.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_pitch_shift`](crate::context::BaseAudioContext::create_pitch_shift)
+/// - see also: [`TimeStretchNode`](crate::node::TimeStretchNode), to change the tempo of a
+///   buffer without changing its pitch
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// // an octave up
+/// let shift = context.create_pitch_shift();
+/// shift.pitch_ratio().set_value(2.);
+/// shift.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&shift);
+/// osc.start();
+/// ```
+pub struct PitchShiftNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Ratio of the output to the input frequencies
+    pitch_ratio: AudioParam,
+}
+
+impl AudioNode for PitchShiftNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl PitchShiftNode {
+    /// returns a `PitchShiftNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - pitch shift options
+    pub fn new<C: BaseAudioContext>(context: &C, options: PitchShiftOptions) -> Self {
+        context.register(move |registration| {
+            let pitch_ratio_options = AudioParamDescriptor {
+                min_value: 0.25,
+                max_value: 4.,
+                default_value: 1.,
+                automation_rate: AutomationRate::K,
+            };
+            let (mut pitch_ratio_param, pitch_ratio_proc) =
+                context.create_audio_param(pitch_ratio_options, &registration);
+            pitch_ratio_param.set_automation_rate_constrained(true);
+            pitch_ratio_param.set_value(options.pitch_ratio);
+
+            let renderer = PitchShiftRenderer {
+                pitch_ratio: pitch_ratio_proc,
+                channels: vec![],
+                position: 0,
+                tail_time: TailTimeCounter::default(),
+            };
+
+            let node = Self {
+                registration,
+                channel_config: options.channel_config.into(),
+                pitch_ratio: pitch_ratio_param,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// K-rate [`AudioParam`] of the ratio of the output to the input frequencies, e.g. `2` for
+    /// an octave up or `0.5` for an octave down
+    ///
+    /// The value is clamped to the range `[0.25, 4]` and picked up every 512 samples.
+    #[must_use]
+    pub fn pitch_ratio(&self) -> &AudioParam {
+        &self.pitch_ratio
+    }
+}
+
+/// Analysis history and phase vocoder of a channel
+struct ChannelState {
+    vocoder: PhaseVocoder,
+    /// Latest `FFT_SIZE + HOP_SIZE` input samples, the last `HOP_SIZE` being filled
+    input: Vec<f32>,
+}
+
+impl ChannelState {
+    fn new() -> Self {
+        Self {
+            vocoder: PhaseVocoder::new(),
+            input: vec![0.; FFT_SIZE + HOP_SIZE],
+        }
+    }
+}
+
+/// `PitchShiftRenderer` represents the rendering part of `PitchShiftNode`
+struct PitchShiftRenderer {
+    /// Ratio of the output to the input frequencies
+    pitch_ratio: AudioParamId,
+    /// State of each channel, allocated when the channel count grows
+    channels: Vec<ChannelState>,
+    /// Number of samples of the current hop that were processed
+    position: usize,
+    /// Number of silent input samples since the latest sound, to report the tail time
+    tail_time: TailTimeCounter,
+}
+
+impl AudioProcessor for PitchShiftRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        // the latest sound has left the analysis frames and the output
+        if self
+            .tail_time
+            .tail_ended(input, FFT_SIZE + HOP_SIZE, scope.render_quantum_size)
+        {
+            output.make_silent();
+            return false;
+        }
+
+        let number_of_channels = input.number_of_channels().max(self.channels.len());
+        if self.channels.len() < number_of_channels {
+            self.channels
+                .resize_with(number_of_channels, ChannelState::new);
+        }
+        output.set_number_of_channels(number_of_channels);
+
+        // the render quantum may span several hops, or only part of one
+        let mut offset = 0;
+        while offset < scope.render_quantum_size {
+            let position = self.position;
+            let len = (HOP_SIZE - position).min(scope.render_quantum_size - offset);
+
+            self.channels
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, channel)| {
+                    let history = &mut channel.input[FFT_SIZE + position..][..len];
+                    match input.channels().get(i) {
+                        // a silent input may have fewer channels
+                        Some(data) => history.copy_from_slice(&data[offset..][..len]),
+                        None => history.fill(0.),
+                    }
+
+                    let ready = &channel.vocoder.output()[position..][..len];
+                    output.channel_data_mut(i)[offset..][..len].copy_from_slice(ready);
+                });
+
+            self.position += len;
+            offset += len;
+            if self.position == HOP_SIZE {
+                let pitch_ratio = params.get(&self.pitch_ratio)[0];
+                self.channels.iter_mut().for_each(|channel| {
+                    channel.vocoder.process(&channel.input, pitch_ratio, 1.);
+                    channel.input.copy_within(HOP_SIZE.., 0);
+                });
+                self.position = 0;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::{AudioNode, AudioScheduledSourceNode};
+
+    use super::super::test_util::peak_frequency;
+    use super::*;
+
+    /// Render a 440 Hz sine playing for one second, through a pitch shift
+    fn render(pitch_ratio: f32) -> Vec<f32> {
+        let sample_rate = 48_000;
+        let context = OfflineAudioContext::new(1, 2 * sample_rate, sample_rate as f32);
+
+        let shift = context.create_pitch_shift();
+        shift.pitch_ratio().set_value(pitch_ratio);
+        shift.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(440.);
+        osc.connect(&shift);
+        osc.start();
+        osc.stop_at(1.);
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_octave_up() {
+        let output = render(2.);
+
+        // the fundamental is doubled
+        assert_eq!(peak_frequency(&output[FFT_SIZE..], 48_000), 880);
+
+        // the duration is preserved, up to the latency and the hop over which the frame holding
+        // the end of the tone is smeared
+        let energy = |range: std::ops::Range<usize>| -> f32 {
+            output[range.clone()].iter().map(|v| v * v).sum::<f32>() / range.len() as f32
+        };
+        assert!(energy(FFT_SIZE..48_000) > 0.4);
+        assert!(energy(48_000 + FFT_SIZE + HOP_SIZE..48_000 + 2 * FFT_SIZE) < 1e-5);
+    }
+
+    #[test]
+    fn test_octave_down() {
+        let output = render(0.5);
+        assert_eq!(peak_frequency(&output[FFT_SIZE..], 48_000), 220);
+    }
+
+    #[test]
+    fn test_passthrough() {
+        let output = render(1.);
+
+        // the input delayed by the frame length
+        let expected = render_sine();
+        assert_float_eq!(
+            output[FFT_SIZE..48_000],
+            expected[..48_000 - FFT_SIZE],
+            abs_all <= 1e-4
+        );
+    }
+
+    fn render_sine() -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, 48_000, 48_000.);
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(440.);
+        osc.connect(&context.destination());
+        osc.start();
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_render_quantum_sizes() {
+        let render = |render_quantum_size| {
+            let context = OfflineAudioContext::new_with_render_quantum_size(
+                1,
+                8192,
+                48_000.,
+                render_quantum_size,
+            );
+            let shift = context.create_pitch_shift();
+            shift.pitch_ratio().set_value(1.5);
+            shift.connect(&context.destination());
+
+            let osc = context.create_oscillator();
+            osc.connect(&shift);
+            osc.start();
+            context.start_rendering_sync().get_channel_data(0).to_vec()
+        };
+
+        // the hops span several render quanta, or several hops fit in a render quantum
+        let expected = render(crate::RENDER_QUANTUM_SIZE);
+        assert!(expected.iter().any(|&v| v.abs() > 0.1));
+        for render_quantum_size in [64, 256, 4096] {
+            assert_float_eq!(
+                render(render_quantum_size)[..],
+                expected[..],
+                abs_all <= 1e-5
+            );
+        }
+    }
+
+    #[test]
+    fn test_tail_time() {
+        let context = OfflineAudioContext::new(1, 48_000, 48_000.);
+        let shift = context.create_pitch_shift();
+        shift.pitch_ratio().set_value(1.5);
+        shift.connect(&context.destination());
+
+        // a single click, heard after the latency
+        let src = context.create_constant_source();
+        src.connect(&shift);
+        src.start();
+        src.stop_at(128. / 48_000.);
+        drop(shift);
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+        assert!(channel[..HOP_SIZE].iter().all(|&v| v == 0.));
+        assert!(channel[FFT_SIZE - HOP_SIZE..FFT_SIZE + HOP_SIZE]
+            .iter()
+            .any(|&v| v.abs() > 0.01));
+        assert!(channel[2 * FFT_SIZE + HOP_SIZE..].iter().all(|&v| v == 0.));
+    }
+}
@@ -0,0 +1,343 @@
+use std::error::Error;
+use std::f64::consts::PI;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::control::Scheduler;
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions};
+
+/// Options for constructing a [`QuadratureOscillatorNode`]
+#[derive(Clone, Debug)]
+pub struct QuadratureOscillatorOptions {
+    /// The frequency of the fundamental frequency.
+    pub frequency: f32,
+    /// A detuning value (in cents) which will offset the frequency by the given amount.
+    pub detune: f32,
+    /// channel config options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for QuadratureOscillatorOptions {
+    fn default() -> Self {
+        Self {
+            frequency: 440.,
+            detune: 0.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `QuadratureOscillatorNode` is a sine oscillator with two outputs 90° apart, e.g. for single
+/// sideband modulation or smooth panning
+///
+/// Output 0 renders a sine and output 1 a cosine of the same phase, i.e. output 1 leads output 0
+/// by a quarter period. Both are computed from a single phase accumulator, so they stay exactly
+/// in quadrature when the frequency is modulated.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_quadrature_oscillator`](crate::context::BaseAudioContext::create_quadrature_oscillator)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// // sine on the left, cosine on the right
+/// let merger = context.create_channel_merger(2);
+/// merger.connect(&context.destination());
+///
+/// let osc = context.create_quadrature_oscillator();
+/// osc.frequency().set_value(200.);
+/// osc.connect_at(&merger, 0, 0);
+/// osc.connect_at(&merger, 1, 1);
+/// osc.start();
+/// ```
+pub struct QuadratureOscillatorNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// The frequency of the fundamental frequency.
+    frequency: AudioParam,
+    /// A detuning value (in cents) which will offset the frequency by the given amount.
+    detune: AudioParam,
+    /// starts and stops the oscillator
+    scheduler: Scheduler,
+}
+
+impl AudioNode for QuadratureOscillatorNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    /// Output 0 is the sine, output 1 the cosine
+    fn number_of_outputs(&self) -> usize {
+        2
+    }
+}
+
+impl AudioScheduledSourceNode for QuadratureOscillatorNode {
+    fn try_start_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_start_at(when)
+    }
+
+    fn try_stop_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_stop_at(when)
+    }
+}
+
+impl QuadratureOscillatorNode {
+    /// Returns a `QuadratureOscillatorNode`
+    ///
+    /// # Arguments:
+    ///
+    /// * `context` - The `AudioContext`
+    /// * `options` - The QuadratureOscillatorOptions
+    pub fn new<C: BaseAudioContext>(context: &C, options: QuadratureOscillatorOptions) -> Self {
+        context.register(move |registration| {
+            let QuadratureOscillatorOptions {
+                frequency,
+                detune,
+                channel_config,
+            } = options;
+
+            let nyquist = context.sample_rate() / 2.;
+            let freq_param_opts = AudioParamDescriptor {
+                min_value: -nyquist,
+                max_value: nyquist,
+                default_value: 440.,
+                automation_rate: AutomationRate::A,
+            };
+            let (f_param, f_proc) = context.create_audio_param(freq_param_opts, &registration);
+            f_param.set_value(frequency);
+
+            let det_param_opts = AudioParamDescriptor {
+                min_value: -153_600.,
+                max_value: 153_600.,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+            let (det_param, det_proc) = context.create_audio_param(det_param_opts, &registration);
+            det_param.set_value(detune);
+
+            let scheduler = Scheduler::new();
+
+            let renderer = QuadratureOscillatorRenderer {
+                frequency: f_proc,
+                detune: det_proc,
+                scheduler: scheduler.clone(),
+                phase: 0.,
+                ended_triggered: false,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                frequency: f_param,
+                detune: det_param,
+                scheduler,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// A-rate [`AudioParam`] that defines the fondamental frequency of the
+    /// oscillator, expressed in Hz
+    ///
+    /// The final frequency is calculated as follow: frequency * 2^(detune/1200)
+    #[must_use]
+    pub fn frequency(&self) -> &AudioParam {
+        &self.frequency
+    }
+
+    /// A-rate [`AudioParam`] that defines a transposition according to the
+    /// frequency, expressed in cents.
+    ///
+    /// The final frequency is calculated as follow: frequency * 2^(detune/1200)
+    #[must_use]
+    pub fn detune(&self) -> &AudioParam {
+        &self.detune
+    }
+}
+
+struct QuadratureOscillatorRenderer {
+    /// The frequency of the fundamental frequency.
+    frequency: AudioParamId,
+    /// A detuning value (in cents) which will offset the frequency by the given amount.
+    detune: AudioParamId,
+    /// starts and stops the oscillator
+    scheduler: Scheduler,
+    /// position in the current cycle, in the [0, 1) range, shared by both outputs
+    phase: f64,
+    /// defines if the `ended` events was already dispatched
+    ended_triggered: bool,
+}
+
+impl AudioProcessor for QuadratureOscillatorRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        let dt = 1. / scope.sample_rate as f64;
+        let next_block_time = scope.current_time + dt * scope.render_quantum_size as f64;
+
+        let start_time = self.scheduler.get_start_at();
+        let stop_time = self.scheduler.get_stop_at();
+
+        if start_time >= next_block_time {
+            outputs.iter_mut().for_each(AudioRenderQuantum::make_silent);
+            return true;
+        }
+
+        let (sine, cosine) = outputs.split_at_mut(1);
+        let sine = &mut sine[0];
+        let cosine = &mut cosine[0];
+        sine.ensure_mono();
+        cosine.ensure_mono();
+
+        let frequency = params.get(&self.frequency);
+        let detune = params.get(&self.detune);
+        let mut current_time = scope.current_time;
+
+        sine.channel_data_mut(0)
+            .iter_mut()
+            .zip(cosine.channel_data_mut(0).iter_mut())
+            .zip(frequency.iter().cycle().zip(detune.iter().cycle()))
+            .for_each(|((s, c), (&frequency, &detune))| {
+                if current_time < start_time || current_time >= stop_time {
+                    *s = 0.;
+                    *c = 0.;
+                } else {
+                    let (sin, cos) = (2. * PI * self.phase).sin_cos();
+                    *s = sin as f32;
+                    *c = cos as f32;
+
+                    let computed_frequency = frequency * (detune / 1200.).exp2();
+                    self.phase = (self.phase + f64::from(computed_frequency) * dt).rem_euclid(1.);
+                }
+
+                current_time += dt;
+            });
+
+        // tail_time false when output has ended this quantum
+        let still_running = stop_time >= next_block_time;
+
+        if !still_running && !self.ended_triggered {
+            scope.send_ended_event();
+            self.ended_triggered = true;
+        }
+
+        still_running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+
+    use super::*;
+
+    /// Render the sine and cosine outputs on two channels
+    fn render(sample_rate: f32, length: usize, sweep: bool) -> (Vec<f32>, Vec<f32>) {
+        let context = OfflineAudioContext::new(2, length, sample_rate);
+        let merger = context.create_channel_merger(2);
+        merger.connect(&context.destination());
+
+        let osc = context.create_quadrature_oscillator();
+        osc.connect_at(&merger, 0, 0);
+        osc.connect_at(&merger, 1, 1);
+        osc.frequency().set_value(100.);
+        if sweep {
+            let duration = length as f64 / f64::from(sample_rate);
+            osc.frequency()
+                .exponential_ramp_to_value_at_time(2_000., duration);
+        }
+        osc.start();
+
+        let output = context.start_rendering_sync();
+        (
+            output.get_channel_data(0).to_vec(),
+            output.get_channel_data(1).to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_quarter_period_shift() {
+        // 100Hz at 48kHz is a period of exactly 480 samples
+        let (sine, cosine) = render(48_000., 960, false);
+
+        let expected: Vec<f32> = (0..960)
+            .map(|i| (2. * PI * i as f64 / 480.).sin() as f32)
+            .collect();
+        assert_float_eq!(sine[..], expected[..], abs_all <= 1e-5);
+
+        // the cosine leads the sine by 120 samples
+        assert_float_eq!(cosine[..840], sine[120..], abs_all <= 1e-5);
+    }
+
+    #[test]
+    fn test_quadrature_under_frequency_sweep() {
+        let sample_rate = 48_000.;
+        let (sine, cosine) = render(sample_rate, 48_000, true);
+
+        // the instantaneous frequency follows the sweep
+        let frequency_at = |i: usize| {
+            let phase = |i: usize| f64::from(sine[i]).atan2(f64::from(cosine[i]));
+            let delta = (phase(i + 1) - phase(i)).rem_euclid(2. * PI);
+            delta / (2. * PI) * f64::from(sample_rate)
+        };
+        assert_float_eq!(frequency_at(0), 100., abs <= 0.1);
+        assert!(frequency_at(24_000) > 400.);
+        assert_float_eq!(frequency_at(47_998), 2_000., abs <= 1.);
+
+        sine.iter().zip(&cosine).for_each(|(&s, &c)| {
+            // exactly 90° apart, i.e. the pair stays on the unit circle
+            assert_float_eq!(s * s + c * c, 1., abs <= 1e-5);
+        });
+
+        // the cosine leads the sine: its derivative is the sine and the sine's is minus the
+        // cosine, so the cross product of successive points is always positive
+        sine.windows(2)
+            .zip(cosine.windows(2))
+            .for_each(|(s, c)| assert!(c[0] * s[1] - s[0] * c[1] > 0.));
+    }
+
+    #[test]
+    fn test_start_stop() {
+        let sample_rate = 48_000.;
+        let context = OfflineAudioContext::new(1, 384, sample_rate);
+
+        let osc = context.create_quadrature_oscillator();
+        osc.connect_at(&context.destination(), 1, 0);
+        osc.start_at(128. / f64::from(sample_rate));
+        osc.stop_at(256. / f64::from(sample_rate));
+
+        let output = context.start_rendering_sync();
+        let channel = output.get_channel_data(0);
+
+        assert_float_eq!(channel[..128], [0.; 128][..], abs_all <= 0.);
+        // the cosine starts at 1
+        assert_float_eq!(channel[128], 1., abs <= 0.);
+        assert_float_eq!(channel[256..], [0.; 128][..], abs_all <= 0.);
+    }
+}
@@ -0,0 +1,563 @@
+//! The algorithmic reverb control and renderer parts
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::flush_denormal_f32;
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::{
+    AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
+};
+
+// Tuning of the Freeverb algorithm by Jezar at Dreampoint, the delay lengths are given in
+// samples at 44.1kHz and scaled to the sample rate of the context.
+
+/// Delay lengths of the parallel comb filters
+const COMB_TUNING: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+/// Delay lengths of the serial all-pass filters
+const ALLPASS_TUNING: [usize; 4] = [556, 441, 341, 225];
+/// Additional delay of the filters of the right channel, decorrelates the channels
+const STEREO_SPREAD: usize = 23;
+/// Sample rate of the tuning
+const TUNING_SAMPLE_RATE: f32 = 44_100.;
+
+/// Gain of the input of the comb filters, keeps their sum in range
+const FIXED_GAIN: f32 = 0.015;
+/// Feedback of the comb filters for a room size of zero
+const OFFSET_ROOM: f32 = 0.7;
+/// Additional feedback of the comb filters for a room size of one
+const SCALE_ROOM: f32 = 0.28;
+/// Largest coefficient of the lowpass filters in the comb filters
+const SCALE_DAMPING: f32 = 0.4;
+/// Makeup gain of the reverberated signal
+const SCALE_WET: f32 = 3.;
+/// Feedback of the all-pass filters
+const ALLPASS_FEEDBACK: f32 = 0.5;
+
+/// The tail is over when the output stayed below this level for the length of the filters
+const TAIL_THRESHOLD: f32 = 1e-7;
+
+/// Options for constructing a [`ReverbNode`]
+#[derive(Clone, Debug)]
+pub struct ReverbOptions {
+    /// initial value for the room size parameter
+    pub room_size: f32,
+    /// initial value for the damping parameter
+    pub damping: f32,
+    /// initial value for the wet parameter
+    pub wet: f32,
+    /// initial value for the dry parameter
+    pub dry: f32,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for ReverbOptions {
+    fn default() -> Self {
+        Self {
+            room_size: 0.5,
+            damping: 0.5,
+            wet: 1. / 3.,
+            dry: 1.,
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                count_mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        }
+    }
+}
+
+/// Feedback comb filter with a one pole lowpass filter in the loop
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    lowpass: f32,
+}
+
+impl Comb {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![0.; length],
+            index: 0,
+            lowpass: 0.,
+        }
+    }
+
+    #[inline(always)]
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.lowpass = flush_denormal_f32(output.mul_add(1. - damping, self.lowpass * damping));
+        self.buffer[self.index] = self.lowpass.mul_add(feedback, input);
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(0.);
+        self.lowpass = 0.;
+    }
+}
+
+/// Schroeder all-pass filter, diffuses the echoes of the comb filters
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![0.; length],
+            index: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = flush_denormal_f32(delayed.mul_add(ALLPASS_FEEDBACK, input));
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(0.);
+    }
+}
+
+/// Comb and all-pass filters of a single output channel
+struct ReverbChannel {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+}
+
+impl ReverbChannel {
+    fn new(sample_rate: f32, spread: usize) -> Self {
+        let scale = |length: usize| {
+            ((length + spread) as f32 * sample_rate / TUNING_SAMPLE_RATE).round() as usize
+        };
+
+        Self {
+            combs: COMB_TUNING.iter().map(|&l| Comb::new(scale(l))).collect(),
+            allpasses: ALLPASS_TUNING
+                .iter()
+                .map(|&l| Allpass::new(scale(l)))
+                .collect(),
+        }
+    }
+
+    #[inline(always)]
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let combs = self
+            .combs
+            .iter_mut()
+            .map(|comb| comb.process(input, feedback, damping))
+            .sum();
+
+        self.allpasses
+            .iter_mut()
+            .fold(combs, |signal, allpass| allpass.process(signal))
+    }
+
+    /// Number of samples for an input to go through the longest path of the filters
+    fn length(&self) -> usize {
+        let comb = self.combs.iter().map(|c| c.buffer.len()).max().unwrap_or(0);
+        let allpass: usize = self.allpasses.iter().map(|a| a.buffer.len()).sum();
+        comb + allpass
+    }
+
+    fn reset(&mut self) {
+        self.combs.iter_mut().for_each(Comb::reset);
+        self.allpasses.iter_mut().for_each(Allpass::reset);
+    }
+}
+
+/// `ReverbNode` adds an algorithmic reverberation to its input
+///
+/// This is a lightweight alternative to the [`ConvolverNode`](super::ConvolverNode) which does
+/// not require an impulse response, following the Freeverb design: the input is fed through
+/// eight parallel feedback comb filters followed by four serial all-pass filters. The filters
+/// of the right channel are slightly longer than the ones of the left channel, so the two
+/// channels of the reverberation are decorrelated.
+///
+/// - `room_size` sets the feedback of the comb filters, larger rooms have longer tails
+/// - `damping` sets the attenuation of the high frequencies in the comb filters
+/// - `wet` is the gain of the reverberated signal
+/// - `dry` is the gain of the unprocessed input
+///
+/// The input is down-mixed to mono before the reverberation, the output is always stereo.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_reverb`](crate::context::BaseAudioContext::create_reverb)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// let reverb = context.create_reverb();
+/// reverb.room_size().set_value(0.8);
+/// reverb.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&reverb);
+/// osc.start();
+/// osc.stop_at(context.current_time() + 0.1);
+/// ```
+pub struct ReverbNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Size of the simulated room, in the `[0, 1]` range
+    room_size: AudioParam,
+    /// Attenuation of the high frequencies, in the `[0, 1]` range
+    damping: AudioParam,
+    /// Gain of the reverberated signal
+    wet: AudioParam,
+    /// Gain of the unprocessed input
+    dry: AudioParam,
+}
+
+impl AudioNode for ReverbNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl ReverbNode {
+    /// returns a `ReverbNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - reverb options
+    pub fn new<C: BaseAudioContext>(context: &C, options: ReverbOptions) -> Self {
+        context.register(move |registration| {
+            let unit_options = |default_value| AudioParamDescriptor {
+                min_value: 0.,
+                max_value: 1.,
+                default_value,
+                automation_rate: AutomationRate::K,
+            };
+
+            let (room_size_param, room_size_proc) =
+                context.create_audio_param(unit_options(0.5), &registration);
+            room_size_param.set_value(options.room_size);
+
+            let (damping_param, damping_proc) =
+                context.create_audio_param(unit_options(0.5), &registration);
+            damping_param.set_value(options.damping);
+
+            let gain_options = |default_value| AudioParamDescriptor {
+                min_value: f32::MIN,
+                max_value: f32::MAX,
+                default_value,
+                automation_rate: AutomationRate::A,
+            };
+
+            let (wet_param, wet_proc) =
+                context.create_audio_param(gain_options(1. / 3.), &registration);
+            wet_param.set_value(options.wet);
+
+            let (dry_param, dry_proc) = context.create_audio_param(gain_options(1.), &registration);
+            dry_param.set_value(options.dry);
+
+            let sample_rate = context.sample_rate();
+            let left = ReverbChannel::new(sample_rate, 0);
+            let right = ReverbChannel::new(sample_rate, STEREO_SPREAD);
+            let tail_length = left.length().max(right.length());
+
+            let renderer = ReverbRenderer {
+                room_size: room_size_proc,
+                damping: damping_proc,
+                wet: wet_proc,
+                dry: dry_proc,
+                channels: [left, right],
+                tail_length,
+                silent_samples: tail_length,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: options.channel_config.into(),
+                room_size: room_size_param,
+                damping: damping_param,
+                wet: wet_param,
+                dry: dry_param,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// Returns the room size audio parameter, in the `[0, 1]` range
+    ///
+    /// Larger rooms have longer reverberation tails.
+    #[must_use]
+    pub fn room_size(&self) -> &AudioParam {
+        &self.room_size
+    }
+
+    /// Returns the damping audio parameter, in the `[0, 1]` range
+    ///
+    /// Higher values attenuate the high frequencies of the tail faster.
+    #[must_use]
+    pub fn damping(&self) -> &AudioParam {
+        &self.damping
+    }
+
+    /// Returns the wet audio parameter, the gain of the reverberated signal
+    #[must_use]
+    pub fn wet(&self) -> &AudioParam {
+        &self.wet
+    }
+
+    /// Returns the dry audio parameter, the gain of the unprocessed input
+    #[must_use]
+    pub fn dry(&self) -> &AudioParam {
+        &self.dry
+    }
+}
+
+/// `ReverbRenderer` represents the rendering part of `ReverbNode`
+struct ReverbRenderer {
+    room_size: AudioParamId,
+    damping: AudioParamId,
+    wet: AudioParamId,
+    dry: AudioParamId,
+    /// Filters of the left and right channel
+    channels: [ReverbChannel; 2],
+    /// Number of samples for an input to go through the filters
+    tail_length: usize,
+    /// Number of samples in a row with a silent input and an inaudible output
+    silent_samples: usize,
+}
+
+impl AudioProcessor for ReverbRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        // the filters only hold silence, nothing left to process
+        if input.is_silent() && self.silent_samples >= self.tail_length {
+            output.make_silent();
+            return false;
+        }
+
+        let mut mono = input.clone();
+        mono.mix(1, ChannelInterpretation::Speakers);
+        let mut dry = input.clone();
+        dry.mix(2, ChannelInterpretation::Speakers);
+
+        // k-rate params
+        let room_size = params.get(&self.room_size)[0];
+        let feedback = room_size.mul_add(SCALE_ROOM, OFFSET_ROOM);
+        let damping = params.get(&self.damping)[0] * SCALE_DAMPING;
+        // a-rate params
+        let wet = params.get(&self.wet);
+        let dry_gain = params.get(&self.dry);
+
+        output.set_number_of_channels(2);
+        let [left, right] = output.stereo_mut();
+        let [reverb_left, reverb_right] = &mut self.channels;
+        let mut peak: f32 = 0.;
+
+        left.iter_mut()
+            .zip(right.iter_mut())
+            .zip(mono.channel_data(0).iter())
+            .zip(dry.channel_data(0).iter().zip(dry.channel_data(1).iter()))
+            .zip(wet.iter().cycle().zip(dry_gain.iter().cycle()))
+            .for_each(|((((l, r), &x), (&dry_l, &dry_r)), (&wet, &dry_gain))| {
+                let x = x * FIXED_GAIN;
+                let wet_l = reverb_left.process(x, feedback, damping);
+                let wet_r = reverb_right.process(x, feedback, damping);
+                peak = peak.max(wet_l.abs()).max(wet_r.abs());
+
+                let wet = wet * SCALE_WET;
+                *l = wet_l.mul_add(wet, dry_l * dry_gain);
+                *r = wet_r.mul_add(wet, dry_r * dry_gain);
+            });
+
+        if input.is_silent() && peak < TAIL_THRESHOLD {
+            self.silent_samples += left.len();
+            // clear the inaudible remainder of the tail
+            if self.silent_samples >= self.tail_length {
+                self.channels.iter_mut().for_each(ReverbChannel::reset);
+            }
+        } else {
+            self.silent_samples = 0;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+    use crate::AudioBuffer;
+
+    use super::*;
+
+    // render the reverberation of an impulse, without the dry signal
+    fn render_impulse(room_size: f32, length: usize) -> (Vec<f32>, Vec<f32>) {
+        let sample_rate = 44_100.;
+        let context = OfflineAudioContext::new(2, length, sample_rate);
+
+        let reverb = ReverbNode::new(
+            &context,
+            ReverbOptions {
+                room_size,
+                dry: 0.,
+                ..ReverbOptions::default()
+            },
+        );
+        reverb.connect(&context.destination());
+
+        let mut impulse = vec![0.; 128];
+        impulse[0] = 1.;
+        let src = context.create_buffer_source();
+        src.set_buffer(AudioBuffer::from(vec![impulse], sample_rate));
+        src.connect(&reverb);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        (
+            output.get_channel_data(0).to_vec(),
+            output.get_channel_data(1).to_vec(),
+        )
+    }
+
+    // index of the last sample above -60dB relative to the peak
+    fn tail_length(signal: &[f32]) -> usize {
+        let peak = signal.iter().fold(0., |m: f32, v| m.max(v.abs()));
+        signal.iter().rposition(|v| v.abs() > peak * 1e-3).unwrap()
+    }
+
+    fn energy(signal: &[f32]) -> f32 {
+        signal.iter().map(|v| v * v).sum()
+    }
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(2, 1, 44_100.);
+        let node = context.create_reverb();
+        assert_float_eq!(node.room_size().value(), 0.5, abs <= 0.);
+        assert_float_eq!(node.damping().value(), 0.5, abs <= 0.);
+        assert_float_eq!(node.wet().value(), 1. / 3., abs <= 0.);
+        assert_float_eq!(node.dry().value(), 1., abs <= 0.);
+    }
+
+    #[test]
+    fn test_decaying_tail() {
+        let (left, _) = render_impulse(0.5, 44_100);
+
+        // nothing before the shortest comb filter
+        assert!(left[..1000].iter().all(|&v| v == 0.));
+
+        // dense, noise-like tail
+        let window = &left[4410..8820];
+        let non_zero = window.iter().filter(|v| v.abs() > 1e-6).count();
+        assert!(non_zero > window.len() * 9 / 10);
+        let zero_crossings = window.windows(2).filter(|w| w[0] * w[1] < 0.).count();
+        assert!(zero_crossings > 100);
+
+        // which decays over time
+        let energies: Vec<f32> = left[4410..].chunks(4410).map(energy).collect();
+        assert!(energies.windows(2).all(|w| w[1] < w[0]), "{:?}", energies);
+    }
+
+    #[test]
+    fn test_room_size() {
+        let small = tail_length(&render_impulse(0.2, 4 * 44_100).0);
+        let medium = tail_length(&render_impulse(0.5, 4 * 44_100).0);
+        let large = tail_length(&render_impulse(0.8, 4 * 44_100).0);
+        assert!(small < medium, "{} {}", small, medium);
+        assert!(medium < large, "{} {}", medium, large);
+    }
+
+    #[test]
+    fn test_stable() {
+        // the tail decays even for the largest room
+        let (left, right) = render_impulse(1., 20 * 44_100);
+        let start = energy(&left[..44_100]) + energy(&right[..44_100]);
+        let end = energy(&left[19 * 44_100..]) + energy(&right[19 * 44_100..]);
+        assert!(left
+            .iter()
+            .chain(&right)
+            .all(|v| v.is_finite() && v.abs() < 1.));
+        assert!(end < start * 1e-3);
+    }
+
+    #[test]
+    fn test_stereo_decorrelation() {
+        let (left, right) = render_impulse(0.5, 44_100);
+        let left = &left[4410..];
+        let right = &right[4410..];
+
+        let correlation: f32 = left.iter().zip(right).map(|(l, r)| l * r).sum();
+        let normalized = correlation / (energy(left) * energy(right)).sqrt();
+        assert!(normalized.abs() < 0.5, "{}", normalized);
+        assert!(energy(right) > 0.5 * energy(left));
+    }
+
+    #[test]
+    fn test_dry() {
+        let sample_rate = 44_100.;
+        let context = OfflineAudioContext::new(2, 128, sample_rate);
+
+        let reverb = ReverbNode::new(
+            &context,
+            ReverbOptions {
+                wet: 0.,
+                dry: 0.5,
+                ..ReverbOptions::default()
+            },
+        );
+        reverb.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&reverb);
+        src.start();
+
+        // mono input is up-mixed to both channels
+        let output = context.start_rendering_sync();
+        assert_float_eq!(
+            output.get_channel_data(0)[..],
+            [0.5; 128][..],
+            abs_all <= 0.
+        );
+        assert_float_eq!(
+            output.get_channel_data(1)[..],
+            [0.5; 128][..],
+            abs_all <= 0.
+        );
+    }
+}
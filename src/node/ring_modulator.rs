@@ -0,0 +1,330 @@
+//! The ring modulator control and renderer parts
+use std::f64::consts::PI;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::{AudioNode, ChannelConfig, ChannelConfigOptions};
+
+/// Options for constructing a [`RingModulatorNode`]
+#[derive(Clone, Debug)]
+pub struct RingModulatorOptions {
+    /// initial value for the frequency of the internal carrier, in Hz
+    pub frequency: f32,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for RingModulatorOptions {
+    fn default() -> Self {
+        Self {
+            frequency: 440.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `RingModulatorNode` multiplies its input by a carrier signal
+///
+/// The product of two sines at the frequencies `f1` and `f2` is the sum of two sines at the
+/// difference `|f1 - f2|` and at the sum `f1 + f2` of the frequencies (the sidebands), the
+/// original frequencies being removed. This yields metallic or robotic inharmonic timbres.
+///
+/// The carrier is either:
+/// - an internal sine at the a-rate `frequency` param, when nothing is connected to the second
+///   input
+/// - the signal connected to the second input, with [`connect_at`](AudioNode::connect_at) and
+///   an input index of `1`, e.g. another oscillator or any audio stream
+///
+/// The multiplication is applied on each sample. A mono carrier modulates all channels of the
+/// input.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_ring_modulator`](crate::context::BaseAudioContext::create_ring_modulator)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// let ring_mod = context.create_ring_modulator();
+/// ring_mod.frequency().set_value(100.);
+/// ring_mod.connect(&context.destination());
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&ring_mod);
+/// osc.start();
+/// ```
+pub struct RingModulatorNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Frequency of the internal carrier
+    frequency: AudioParam,
+}
+
+impl AudioNode for RingModulatorNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        2
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl RingModulatorNode {
+    /// returns a `RingModulatorNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - ring modulator options
+    pub fn new<C: BaseAudioContext>(context: &C, options: RingModulatorOptions) -> Self {
+        context.register(move |registration| {
+            let nyquist = context.sample_rate() / 2.;
+            let frequency_options = AudioParamDescriptor {
+                min_value: -nyquist,
+                max_value: nyquist,
+                default_value: 440.,
+                automation_rate: AutomationRate::A,
+            };
+            let (frequency_param, frequency_proc) =
+                context.create_audio_param(frequency_options, &registration);
+            frequency_param.set_value(options.frequency);
+
+            let renderer = RingModulatorRenderer {
+                frequency: frequency_proc,
+                phase: 0.,
+                carrier: vec![0.; context.render_quantum_size()],
+            };
+
+            let node = Self {
+                registration,
+                channel_config: options.channel_config.into(),
+                frequency: frequency_param,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// A-rate [`AudioParam`] of the frequency of the internal carrier, in Hz
+    ///
+    /// The param has no effect while a carrier is connected to the second input.
+    #[must_use]
+    pub fn frequency(&self) -> &AudioParam {
+        &self.frequency
+    }
+}
+
+/// `RingModulatorRenderer` represents the rendering part of `RingModulatorNode`
+struct RingModulatorRenderer {
+    /// Frequency of the internal carrier
+    frequency: AudioParamId,
+    /// Position of the internal carrier in the current cycle, in the [0, 1) range
+    phase: f64,
+    /// Samples of the internal carrier for the render quantum
+    carrier: Vec<f32>,
+}
+
+impl AudioProcessor for RingModulatorRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+        let dt = 1. / scope.sample_rate as f64;
+
+        if scope.is_input_connected(1) {
+            // external carrier
+            let carrier = &inputs[1];
+            if input.is_silent() || carrier.is_silent() {
+                output.make_silent();
+                return false;
+            }
+
+            *output = input.clone();
+            let carrier_channels = carrier.channels();
+            output
+                .channels_mut()
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, channel)| {
+                    // a mono carrier modulates all channels
+                    let modulator = &carrier_channels[i.min(carrier_channels.len() - 1)];
+                    channel
+                        .iter_mut()
+                        .zip(modulator.iter())
+                        .for_each(|(o, m)| *o *= m);
+                });
+
+            return false;
+        }
+
+        // internal carrier, the phase runs even when the input is silent
+        let frequency = params.get(&self.frequency);
+        let mut phase = self.phase;
+
+        if input.is_silent() {
+            // only the phase advance is needed
+            frequency
+                .iter()
+                .cycle()
+                .take(scope.render_quantum_size)
+                .for_each(|&f| phase = (phase + f64::from(f) * dt).rem_euclid(1.));
+            self.phase = phase;
+            output.make_silent();
+            return false;
+        }
+
+        let carrier = &mut self.carrier;
+        carrier
+            .iter_mut()
+            .zip(frequency.iter().cycle())
+            .for_each(|(c, &f)| {
+                *c = (2. * PI * phase).sin() as f32;
+                phase = (phase + f64::from(f) * dt).rem_euclid(1.);
+            });
+        self.phase = phase;
+
+        *output = input.clone();
+        output.channels_mut().iter_mut().for_each(|channel| {
+            channel
+                .iter_mut()
+                .zip(carrier.iter())
+                .for_each(|(o, c)| *o *= c);
+        });
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::*;
+
+    const SAMPLE_RATE: usize = 48_000;
+
+    /// Frequencies of the two highest bins of the spectrum of one second of `signal`
+    fn sidebands(signal: &[f32]) -> (usize, usize) {
+        let spectrum = crate::analysis::power_spectrum(&signal[..SAMPLE_RATE]);
+        let mut bins: Vec<usize> = (0..spectrum.len()).collect();
+        bins.sort_by(|&a, &b| spectrum[b].total_cmp(&spectrum[a]));
+
+        // skip the neighbours of the highest peak, in the main lobe of the window
+        let first = bins[0];
+        let second = *bins.iter().find(|&&k| k.abs_diff(first) > 5).unwrap();
+        (first.min(second), first.max(second))
+    }
+
+    /// Render one second of a 440 Hz sine through a ring modulator, with an internal carrier
+    /// or an external 100 Hz sine carrier
+    fn render(external: bool) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, SAMPLE_RATE, SAMPLE_RATE as f32);
+
+        let ring_mod = context.create_ring_modulator();
+        ring_mod.connect(&context.destination());
+
+        let osc = context.create_oscillator();
+        osc.frequency().set_value(440.);
+        osc.connect(&ring_mod);
+        osc.start();
+
+        if external {
+            // the internal carrier is not used
+            ring_mod.frequency().set_value(1000.);
+
+            let carrier = context.create_oscillator();
+            carrier.frequency().set_value(100.);
+            carrier.connect_at(&ring_mod, 0, 1);
+            carrier.start();
+        } else {
+            ring_mod.frequency().set_value(100.);
+        }
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(1, 1, 44_100.);
+        let ring_mod = context.create_ring_modulator();
+        assert_float_eq!(ring_mod.frequency().value(), 440., abs <= 0.);
+        assert_eq!(ring_mod.number_of_inputs(), 2);
+    }
+
+    #[test]
+    fn test_internal_carrier() {
+        let output = render(false);
+        assert_eq!(sidebands(&output), (340, 540));
+
+        // the original frequencies are removed
+        let spectrum = crate::analysis::power_spectrum(&output);
+        assert!(spectrum[440] < spectrum[340] * 1e-6);
+        assert!(spectrum[100] < spectrum[340] * 1e-6);
+    }
+
+    #[test]
+    fn test_external_carrier() {
+        let output = render(true);
+        assert_eq!(sidebands(&output), (340, 540));
+    }
+
+    #[test]
+    fn test_per_sample_multiplication() {
+        let length = 256;
+        let context = OfflineAudioContext::new(1, length, SAMPLE_RATE as f32);
+
+        let ring_mod = context.create_ring_modulator();
+        ring_mod.connect(&context.destination());
+
+        let ramp: Vec<f32> = (0..length).map(|i| i as f32 / length as f32).collect();
+        let mut buffer = context.create_buffer(1, length, SAMPLE_RATE as f32);
+        buffer.copy_to_channel(&ramp, 0);
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&ring_mod);
+        src.start();
+
+        // an a-rate carrier ramping from 1 to -1
+        let carrier = context.create_constant_source();
+        carrier.offset().set_value_at_time(1., 0.);
+        carrier
+            .offset()
+            .linear_ramp_to_value_at_time(-1., length as f64 / SAMPLE_RATE as f64);
+        carrier.connect_at(&ring_mod, 0, 1);
+        carrier.start();
+
+        let output = context.start_rendering_sync();
+        let expected: Vec<f32> = ramp
+            .iter()
+            .enumerate()
+            .map(|(i, r)| r * (1. - 2. * i as f32 / length as f32))
+            .collect();
+        assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 1e-6);
+    }
+}
@@ -1,5 +1,7 @@
 //! The stereo panner control and renderer parts
-use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::context::{
+    AudioContextRegistration, AudioParamId, BaseAudioContext, PatchNodeType, Patchable,
+};
 use crate::param::{AudioParam, AudioParamDescriptor};
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 
@@ -13,6 +15,11 @@ use super::{
 //   float pan = 0;
 // };
 #[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct StereoPannerOptions {
     /// initial value for the pan parameter
     pub pan: f32,
@@ -170,7 +177,9 @@ impl StereoPannerNode {
     /// * `options.channel_config.mode` is `ChannelCountMode::Max`
     ///
     pub fn new<C: BaseAudioContext>(context: &C, options: StereoPannerOptions) -> Self {
-        context.register(move |registration| {
+        let patch_options = options.clone();
+
+        let node = context.register(move |registration| {
             assert_valid_channel_count_mode(options.channel_config.count_mode);
             assert_valid_channel_count(options.channel_config.count);
 
@@ -193,7 +202,17 @@ impl StereoPannerNode {
             };
 
             (node, Box::new(renderer))
-        })
+        });
+
+        let channel_config = node.channel_config.clone();
+        context.base().record_patch_node(&node, move || {
+            PatchNodeType::StereoPanner(StereoPannerOptions {
+                channel_config: (&channel_config).into(),
+                ..patch_options.clone()
+            })
+        });
+
+        node
     }
 
     /// Returns the pan audio paramter
@@ -203,6 +222,12 @@ impl StereoPannerNode {
     }
 }
 
+impl Patchable for StereoPannerNode {
+    fn patch_params(&self) -> Vec<&AudioParam> {
+        vec![&self.pan]
+    }
+}
+
 /// `StereoPannerRenderer` represents the rendering part of `StereoPannerNode`
 struct StereoPannerRenderer {
     /// Position of the input in the output’s stereo image.
@@ -0,0 +1,353 @@
+//! The stereo width control and renderer parts
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::{
+    AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
+};
+
+/// Options for constructing a [`StereoWidthNode`]
+#[derive(Clone, Debug)]
+pub struct StereoWidthOptions {
+    /// initial value for the width parameter
+    pub width: f32,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for StereoWidthOptions {
+    fn default() -> Self {
+        Self {
+            width: 1.,
+            channel_config: ChannelConfigOptions {
+                count: 2,
+                count_mode: ChannelCountMode::ClampedMax,
+                interpretation: ChannelInterpretation::Speakers,
+            },
+        }
+    }
+}
+
+/// Assert that the channel count is valid for the StereoWidthNode
+///
+/// # Panics
+///
+/// This function panics if given count is greater than 2
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count(count: usize) {
+    if count > 2 {
+        panic!("NotSupportedError: StereoWidthNode channel count cannot be greater than two");
+    }
+}
+
+/// Assert that the channel count mode is valid for the StereoWidthNode
+///
+/// # Panics
+///
+/// This function panics if given count mode is [`ChannelCountMode::Max`]
+///
+#[track_caller]
+#[inline(always)]
+fn assert_valid_channel_count_mode(mode: ChannelCountMode) {
+    if mode == ChannelCountMode::Max {
+        panic!("NotSupportedError: StereoWidthNode channel count mode cannot be set to max");
+    }
+}
+
+/// `StereoWidthNode` widens or narrows the stereo image of an audio stream
+///
+/// The input is decoded into a mid (`(L + R) / 2`) and a side (`(L - R) / 2`) signal, the side
+/// signal is scaled by the `width` param and both are encoded back to left and right:
+/// - a width of `0` collapses the stereo image to the center (mono)
+/// - a width of `1` leaves the signal unchanged
+/// - a width larger than `1` makes the stereo image wider
+///
+/// A mono input has no side signal and passes through unchanged.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_stereo_width`](crate::context::BaseAudioContext::create_stereo_width)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// let width = context.create_stereo_width();
+/// width.width().set_value(1.5);
+/// width.connect(&context.destination());
+///
+/// let panner = context.create_stereo_panner();
+/// panner.pan().set_value(-0.5);
+/// panner.connect(&width);
+///
+/// let osc = context.create_oscillator();
+/// osc.connect(&panner);
+/// osc.start();
+/// ```
+pub struct StereoWidthNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Gain applied to the side signal
+    width: AudioParam,
+}
+
+impl AudioNode for StereoWidthNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        1
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+
+    fn set_channel_count_mode(&self, mode: ChannelCountMode) {
+        assert_valid_channel_count_mode(mode);
+        self.channel_config.set_count_mode(mode);
+    }
+
+    fn set_channel_count(&self, count: usize) {
+        assert_valid_channel_count(count);
+        self.channel_config.set_count(count);
+    }
+}
+
+impl StereoWidthNode {
+    /// returns a `StereoWidthNode` instance
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - audio context in which the audio node will live.
+    /// * `options` - stereo width options
+    ///
+    /// # Panics
+    ///
+    /// Will panic if:
+    ///
+    /// * `options.channel_config.count` is greater than 2
+    /// * `options.channel_config.mode` is `ChannelCountMode::Max`
+    ///
+    pub fn new<C: BaseAudioContext>(context: &C, options: StereoWidthOptions) -> Self {
+        context.register(move |registration| {
+            assert_valid_channel_count_mode(options.channel_config.count_mode);
+            assert_valid_channel_count(options.channel_config.count);
+
+            let width_options = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: f32::MAX,
+                default_value: 1.,
+                automation_rate: AutomationRate::A,
+            };
+            let (width_param, width_proc) =
+                context.create_audio_param(width_options, &registration);
+
+            width_param.set_value(options.width);
+
+            let renderer = StereoWidthRenderer { width: width_proc };
+
+            let node = Self {
+                registration,
+                channel_config: options.channel_config.into(),
+                width: width_param,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// Returns the width audio parameter, the gain applied to the side signal
+    #[must_use]
+    pub fn width(&self) -> &AudioParam {
+        &self.width
+    }
+}
+
+/// `StereoWidthRenderer` represents the rendering part of `StereoWidthNode`
+struct StereoWidthRenderer {
+    /// Gain applied to the side signal
+    width: AudioParamId,
+}
+
+impl AudioProcessor for StereoWidthRenderer {
+    fn process(
+        &mut self,
+        inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        _scope: &RenderScope,
+    ) -> bool {
+        // single input/output node
+        let input = &inputs[0];
+        let output = &mut outputs[0];
+
+        if input.is_silent() {
+            output.make_silent();
+            return false;
+        }
+
+        // nothing to widen without a side signal
+        if input.number_of_channels() == 1 {
+            *output = input.clone();
+            return false;
+        }
+
+        output.set_number_of_channels(2);
+
+        // a-rate param
+        let width_values = params.get(&self.width);
+        let [left, right] = output.stereo_mut();
+
+        left.iter_mut()
+            .zip(right.iter_mut())
+            .zip(width_values.iter().cycle())
+            .zip(input.channel_data(0).iter())
+            .zip(input.channel_data(1).iter())
+            .for_each(|((((l, r), &width), &input_left), &input_right)| {
+                let mid = (input_left + input_right) * 0.5;
+                let side = (input_left - input_right) * 0.5 * width;
+
+                *l = mid + side;
+                *r = mid - side;
+            });
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::*;
+
+    // render a signal hard panned to the left through a StereoWidthNode
+    fn render_hard_left(width: f32) -> (Vec<f32>, Vec<f32>) {
+        let sample_rate = 8000.;
+        let context = OfflineAudioContext::new(2, 128, sample_rate);
+
+        let left: Vec<f32> = (0..128).map(|i| (i as f32 * 0.1).sin()).collect();
+        let mut buffer = context.create_buffer(2, 128, sample_rate);
+        buffer.copy_to_channel(&left, 0);
+
+        let node = StereoWidthNode::new(
+            &context,
+            StereoWidthOptions {
+                width,
+                ..StereoWidthOptions::default()
+            },
+        );
+        node.connect(&context.destination());
+
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&node);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        (
+            output.get_channel_data(0).to_vec(),
+            output.get_channel_data(1).to_vec(),
+        )
+    }
+
+    fn side_energy(left: &[f32], right: &[f32]) -> f32 {
+        left.iter()
+            .zip(right)
+            .map(|(l, r)| ((l - r) * 0.5).powi(2))
+            .sum()
+    }
+
+    #[test]
+    fn test_constructor() {
+        let context = OfflineAudioContext::new(2, 1, 44_100.);
+        let node = context.create_stereo_width();
+        assert_float_eq!(node.width().value(), 1., abs <= 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_channel_count() {
+        let context = OfflineAudioContext::new(2, 1, 44_100.);
+        let node = context.create_stereo_width();
+        node.set_channel_count(3);
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let (left, right) = render_hard_left(1.);
+        let expected: Vec<f32> = (0..128).map(|i| (i as f32 * 0.1).sin()).collect();
+
+        assert_float_eq!(left[..], expected[..], abs_all <= 1e-7);
+        assert_float_eq!(right[..], [0.; 128][..], abs_all <= 1e-7);
+    }
+
+    #[test]
+    fn test_collapse_to_center() {
+        let (left, right) = render_hard_left(0.);
+
+        assert_float_eq!(left[..], right[..], abs_all <= 0.);
+        assert_float_eq!(side_energy(&left, &right), 0., abs <= 0.);
+        // the mid signal is preserved
+        assert!(left.iter().any(|v| v.abs() > 0.4));
+    }
+
+    #[test]
+    fn test_wider() {
+        let (left, right) = render_hard_left(1.);
+        let reference = side_energy(&left, &right);
+
+        let (left, right) = render_hard_left(2.);
+        // side gain of 2 yields 4 times the energy
+        assert_float_eq!(side_energy(&left, &right), 4. * reference, rmax <= 1e-5);
+
+        // the right channel now holds the inverted side signal
+        let expected: Vec<f32> = (0..128).map(|i| -0.5 * (i as f32 * 0.1).sin()).collect();
+        assert_float_eq!(right[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_mono_passthrough() {
+        let sample_rate = 8000.;
+        let context = OfflineAudioContext::new(1, 128, sample_rate);
+
+        let node = StereoWidthNode::new(
+            &context,
+            StereoWidthOptions {
+                width: 0.,
+                ..StereoWidthOptions::default()
+            },
+        );
+        node.connect(&context.destination());
+
+        let signal: Vec<f32> = (0..128).map(|i| (i as f32 * 0.1).sin()).collect();
+        let mut buffer = context.create_buffer(1, 128, sample_rate);
+        buffer.copy_to_channel(&signal, 0);
+
+        let src = context.create_buffer_source();
+        src.set_buffer(buffer);
+        src.connect(&node);
+        src.start();
+
+        let output = context.start_rendering_sync();
+        assert_float_eq!(output.get_channel_data(0)[..], signal[..], abs_all <= 0.);
+    }
+}
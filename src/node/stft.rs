@@ -0,0 +1,19 @@
+//! Short-time Fourier transform parameters shared by the spectral processing nodes
+
+use std::f32::consts::PI;
+
+/// Length of the analysis and synthesis frames
+pub(super) const FFT_SIZE: usize = 2048;
+/// Interval between consecutive frames, i.e. an overlap of 75%
+pub(super) const HOP_SIZE: usize = FFT_SIZE / 4;
+/// Number of frequency bins, from DC to the Nyquist frequency
+pub(super) const BINS: usize = FFT_SIZE / 2 + 1;
+/// Sum of the squared (periodic) Hann windows overlapping at any sample
+pub(super) const WINDOW_OVERLAP_GAIN: f32 = 1.5;
+
+/// Periodic Hann window of `FFT_SIZE` samples, applied at the analysis and at the synthesis
+pub(super) fn hann_window() -> Vec<f32> {
+    (0..FFT_SIZE)
+        .map(|i| 0.5 - 0.5 * (2. * PI * i as f32 / FFT_SIZE as f32).cos())
+        .collect()
+}
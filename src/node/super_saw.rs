@@ -0,0 +1,513 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::control::Scheduler;
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::oscillator::poly_blep;
+use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions};
+
+/// Maximum number of voices of a [`SuperSawNode`]
+pub const MAX_SUPER_SAW_VOICES: usize = 16;
+
+#[track_caller]
+fn assert_valid_voices(voices: usize) {
+    assert!(
+        (1..=MAX_SUPER_SAW_VOICES).contains(&voices),
+        "NotSupportedError - number of voices ({:?}) should be between 1 and {}",
+        voices,
+        MAX_SUPER_SAW_VOICES
+    );
+}
+
+/// Options for constructing a [`SuperSawNode`]
+#[derive(Clone, Debug)]
+pub struct SuperSawOptions {
+    /// The frequency of the center voice, in Hz
+    pub frequency: f32,
+    /// A detuning value (in cents) applied to all voices
+    pub detune: f32,
+    /// Number of sawtooth voices, between 1 and [`MAX_SUPER_SAW_VOICES`]
+    pub voices: usize,
+    /// Detune of the outermost voices relative to the center, in cents
+    pub detune_spread: f32,
+    /// Level of the detuned voices relative to the center voice, between 0 and 1
+    pub mix: f32,
+    /// channel config options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for SuperSawOptions {
+    fn default() -> Self {
+        Self {
+            frequency: 440.,
+            detune: 0.,
+            voices: 7,
+            detune_spread: 25.,
+            mix: 0.5,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `SuperSawNode` is a stack of detuned sawtooth oscillators rendered as a single source
+///
+/// The voices are spread symmetrically around the frequency: the outermost voices are detuned
+/// by minus and plus `detune_spread` cents and the others are evenly spaced in between. With an
+/// odd number of voices, the center voice plays the frequency itself and `mix` sets the level
+/// of the detuned voices relative to it, from 0 (center voice only) to 1 (detuned voices only).
+/// With an even number of voices there is no center voice, all voices are equally loud and
+/// `mix` has no effect.
+///
+/// All voices start in phase, the output is normalized by the total level of the voices so it
+/// stays within `[-1, 1]` whatever the number of voices. The sawtooths are band-limited with
+/// the same polyBLEP correction as the [`OscillatorNode`](super::OscillatorNode).
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_super_saw`](crate::context::BaseAudioContext::create_super_saw)
+///
+/// # Usage
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+///
+/// let saw = context.create_super_saw();
+/// saw.frequency().set_value(220.);
+/// saw.detune_spread().set_value(40.);
+/// saw.connect(&context.destination());
+/// saw.start();
+/// ```
+pub struct SuperSawNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// The frequency of the center voice
+    frequency: AudioParam,
+    /// A detuning value (in cents) applied to all voices
+    detune: AudioParam,
+    /// Detune of the outermost voices, in cents
+    detune_spread: AudioParam,
+    /// Level of the detuned voices relative to the center voice
+    mix: AudioParam,
+    /// Number of voices
+    voices: Arc<AtomicUsize>,
+    /// starts and stops the oscillator
+    scheduler: Scheduler,
+}
+
+impl AudioNode for SuperSawNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioScheduledSourceNode for SuperSawNode {
+    fn try_start_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_start_at(when)
+    }
+
+    fn try_stop_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_stop_at(when)
+    }
+}
+
+impl SuperSawNode {
+    /// Returns a `SuperSawNode`
+    ///
+    /// # Arguments:
+    ///
+    /// * `context` - The `AudioContext`
+    /// * `options` - The SuperSawOptions
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `options.voices` is zero or greater than [`MAX_SUPER_SAW_VOICES`]
+    pub fn new<C: BaseAudioContext>(context: &C, options: SuperSawOptions) -> Self {
+        let SuperSawOptions {
+            frequency,
+            detune,
+            voices,
+            detune_spread,
+            mix,
+            channel_config,
+        } = options;
+
+        assert_valid_voices(voices);
+
+        context.register(move |registration| {
+            let nyquist = context.sample_rate() / 2.;
+            let freq_param_opts = AudioParamDescriptor {
+                min_value: -nyquist,
+                max_value: nyquist,
+                default_value: 440.,
+                automation_rate: AutomationRate::A,
+            };
+            let (f_param, f_proc) = context.create_audio_param(freq_param_opts, &registration);
+            f_param.set_value(frequency);
+
+            let det_param_opts = AudioParamDescriptor {
+                min_value: -153_600.,
+                max_value: 153_600.,
+                default_value: 0.,
+                automation_rate: AutomationRate::A,
+            };
+            let (det_param, det_proc) = context.create_audio_param(det_param_opts, &registration);
+            det_param.set_value(detune);
+
+            let spread_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: 1200.,
+                default_value: 25.,
+                automation_rate: AutomationRate::K,
+            };
+            let (spread_param, spread_proc) =
+                context.create_audio_param(spread_param_opts, &registration);
+            spread_param.set_value(detune_spread);
+
+            let mix_param_opts = AudioParamDescriptor {
+                min_value: 0.,
+                max_value: 1.,
+                default_value: 0.5,
+                automation_rate: AutomationRate::K,
+            };
+            let (mix_param, mix_proc) = context.create_audio_param(mix_param_opts, &registration);
+            mix_param.set_value(mix);
+
+            let voices = Arc::new(AtomicUsize::new(voices));
+            let scheduler = Scheduler::new();
+
+            let renderer = SuperSawRenderer {
+                frequency: f_proc,
+                detune: det_proc,
+                detune_spread: spread_proc,
+                mix: mix_proc,
+                voices: voices.clone(),
+                scheduler: scheduler.clone(),
+                phases: [0.; MAX_SUPER_SAW_VOICES],
+                ended_triggered: false,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                frequency: f_param,
+                detune: det_param,
+                detune_spread: spread_param,
+                mix: mix_param,
+                voices,
+                scheduler,
+            };
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// A-rate [`AudioParam`] that defines the frequency of the center voice, expressed in Hz
+    ///
+    /// The final frequency is calculated as follow: frequency * 2^(detune/1200)
+    #[must_use]
+    pub fn frequency(&self) -> &AudioParam {
+        &self.frequency
+    }
+
+    /// A-rate [`AudioParam`] that defines a transposition of all voices, expressed in cents.
+    #[must_use]
+    pub fn detune(&self) -> &AudioParam {
+        &self.detune
+    }
+
+    /// K-rate [`AudioParam`] that defines the detune of the outermost voices, expressed in cents
+    #[must_use]
+    pub fn detune_spread(&self) -> &AudioParam {
+        &self.detune_spread
+    }
+
+    /// K-rate [`AudioParam`] that defines the level of the detuned voices relative to the center
+    /// voice, between 0 and 1
+    #[must_use]
+    pub fn mix(&self) -> &AudioParam {
+        &self.mix
+    }
+
+    /// Number of sawtooth voices
+    #[must_use]
+    pub fn voices(&self) -> usize {
+        self.voices.load(Ordering::SeqCst)
+    }
+
+    /// Set the number of sawtooth voices
+    ///
+    /// Added voices start at the beginning of their cycle at the next render quantum.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `voices` is zero or greater than [`MAX_SUPER_SAW_VOICES`]
+    pub fn set_voices(&self, voices: usize) {
+        assert_valid_voices(voices);
+        self.voices.store(voices, Ordering::SeqCst);
+    }
+}
+
+struct SuperSawRenderer {
+    /// The frequency of the center voice
+    frequency: AudioParamId,
+    /// A detuning value (in cents) applied to all voices
+    detune: AudioParamId,
+    /// Detune of the outermost voices, in cents
+    detune_spread: AudioParamId,
+    /// Level of the detuned voices relative to the center voice
+    mix: AudioParamId,
+    /// Number of voices
+    voices: Arc<AtomicUsize>,
+    /// starts and stops the oscillator
+    scheduler: Scheduler,
+    /// position in the current cycle of each voice, in the [0, 1) range
+    phases: [f64; MAX_SUPER_SAW_VOICES],
+    /// defines if the `ended` events was already dispatched
+    ended_triggered: bool,
+}
+
+impl AudioProcessor for SuperSawRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        // single output node
+        let output = &mut outputs[0];
+
+        let dt = 1. / scope.sample_rate as f64;
+        let next_block_time = scope.current_time + dt * scope.render_quantum_size as f64;
+
+        let start_time = self.scheduler.get_start_at();
+        let stop_time = self.scheduler.get_stop_at();
+
+        if start_time >= next_block_time {
+            output.make_silent();
+            return true;
+        }
+
+        output.ensure_mono();
+
+        // frequency ratio and level of each voice, constant over the render quantum
+        let voices = self.voices.load(Ordering::Relaxed);
+        let spread = f64::from(params.get(&self.detune_spread)[0]);
+        let mix = f64::from(params.get(&self.mix)[0]);
+        let mut ratios = [1.; MAX_SUPER_SAW_VOICES];
+        let mut levels = [1.; MAX_SUPER_SAW_VOICES];
+        if voices > 1 {
+            let center = (voices - 1) as f64 / 2.;
+            for i in 0..voices {
+                let position = (i as f64 - center) / center;
+                ratios[i] = (position * spread / 1200.).exp2();
+                if voices % 2 == 1 {
+                    levels[i] = if position == 0. { 1. - mix } else { mix };
+                }
+            }
+        }
+        let total_level: f64 = levels[..voices].iter().sum();
+        let gain = if total_level > 0. {
+            1. / total_level
+        } else {
+            0.
+        };
+
+        let frequency = params.get(&self.frequency);
+        let detune = params.get(&self.detune);
+        let mut current_time = scope.current_time;
+
+        output
+            .channel_data_mut(0)
+            .iter_mut()
+            .zip(frequency.iter().cycle().zip(detune.iter().cycle()))
+            .for_each(|(o, (&frequency, &detune))| {
+                if current_time < start_time || current_time >= stop_time {
+                    *o = 0.;
+                } else {
+                    let base_incr = f64::from(frequency * (detune / 1200.).exp2()) * dt;
+                    let mut sample = 0.;
+
+                    for i in 0..voices {
+                        let phase_incr = base_incr * ratios[i];
+                        // offset phase to start at 0. (not -1.), like the `OscillatorNode`
+                        let phase = (self.phases[i] + 0.5).rem_euclid(1.);
+                        let saw = 2. * phase - 1. - poly_blep(phase, phase_incr.abs(), cfg!(test));
+                        sample += levels[i] * saw;
+                        self.phases[i] = (self.phases[i] + phase_incr).rem_euclid(1.);
+                    }
+
+                    *o = (sample * gain) as f32;
+                }
+
+                current_time += dt;
+            });
+
+        // voices that are not rendered restart from the beginning of their cycle
+        self.phases[voices..]
+            .iter_mut()
+            .for_each(|phase| *phase = 0.);
+
+        // tail_time false when output has ended this quantum
+        let still_running = stop_time >= next_block_time;
+
+        if !still_running && !self.ended_triggered {
+            scope.send_ended_event();
+            self.ended_triggered = true;
+        }
+
+        still_running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::analysis::power_spectrum;
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+
+    use super::*;
+
+    fn render(options: SuperSawOptions, length: usize) -> Vec<f32> {
+        let context = OfflineAudioContext::new(1, length, 48_000.);
+        let saw = SuperSawNode::new(&context, options);
+        saw.connect(&context.destination());
+        saw.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    #[test]
+    fn test_detuned_partials() {
+        let sample_rate = 48_000;
+        let options = SuperSawOptions {
+            frequency: 1000.,
+            voices: 7,
+            detune_spread: 100.,
+            ..SuperSawOptions::default()
+        };
+        let signal = render(options, sample_rate);
+        let spectrum = power_spectrum(&signal);
+
+        // 1Hz per bin, the voices are a third of a semitone apart
+        let partials: Vec<usize> = (-3..=3)
+            .map(|i| (1000. * (f64::from(i) * 100. / 3. / 1200.).exp2()).round() as usize)
+            .collect();
+        let peak = spectrum[partials[0]];
+
+        // a local maximum for each voice, symmetric around the fundamental
+        partials.iter().for_each(|&bin| {
+            assert!(spectrum[bin] > peak / 4., "{}", bin);
+            assert!(spectrum[bin] > 10. * spectrum[bin - 8]);
+            assert!(spectrum[bin] > 10. * spectrum[bin + 8]);
+        });
+        // no energy in between the partials
+        assert!(spectrum[1100..1800].iter().all(|&v| v < peak / 1e3));
+
+        let maxima = (900..1100)
+            .filter(|&i| {
+                spectrum[i] > peak / 10.
+                    && spectrum[i] > spectrum[i - 1]
+                    && spectrum[i] > spectrum[i + 1]
+            })
+            .count();
+        assert_eq!(maxima, 7);
+    }
+
+    #[test]
+    fn test_single_voice() {
+        let options = SuperSawOptions {
+            frequency: 375.,
+            voices: 1,
+            ..SuperSawOptions::default()
+        };
+        let signal = render(options, 256);
+
+        // a plain sawtooth starting at zero, with a period of 128 samples
+        let expected: Vec<f32> = (0..256)
+            .map(|i| {
+                let phase = (i % 128) as f32 / 128.;
+                2. * ((phase + 0.5) % 1.) - 1.
+            })
+            .collect();
+        assert_float_eq!(signal[..], expected[..], abs_all <= 1e-5);
+    }
+
+    #[test]
+    fn test_normalized() {
+        let options = SuperSawOptions {
+            frequency: 100.,
+            voices: MAX_SUPER_SAW_VOICES,
+            detune_spread: 1.,
+            ..SuperSawOptions::default()
+        };
+        let signal = render(options, 4800);
+
+        // the voices are almost in phase, the peaks add up but do not clip
+        let max = signal.iter().fold(0_f32, |max, v| max.max(v.abs()));
+        assert!(max <= 1.);
+        assert!(max > 0.9);
+    }
+
+    #[test]
+    fn test_mix() {
+        let options = SuperSawOptions {
+            frequency: 375.,
+            voices: 3,
+            detune_spread: 1200.,
+            mix: 0.,
+            ..SuperSawOptions::default()
+        };
+        // the center voice only
+        let center = render(options.clone(), 256);
+        assert_float_eq!(center[..], render_single(375.)[..], abs_all <= 1e-5);
+
+        // the octaves below and above only
+        let sides = render(SuperSawOptions { mix: 1., ..options }, 256);
+        let expected: Vec<f32> = render_single(187.5)
+            .iter()
+            .zip(render_single(750.))
+            .map(|(a, b)| (a + b) / 2.)
+            .collect();
+        assert_float_eq!(sides[..], expected[..], abs_all <= 1e-5);
+    }
+
+    fn render_single(frequency: f32) -> Vec<f32> {
+        let options = SuperSawOptions {
+            frequency,
+            voices: 1,
+            ..SuperSawOptions::default()
+        };
+        render(options, 256)
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_voices() {
+        let context = OfflineAudioContext::new(1, 128, 48_000.);
+        let saw = context.create_super_saw();
+        saw.set_voices(0);
+    }
+}
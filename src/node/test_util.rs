@@ -0,0 +1,11 @@
+//! Helpers shared by the tests of the spectral processing nodes
+
+/// Frequency of the highest bin of the spectrum of one second of `signal`
+pub(super) fn peak_frequency(signal: &[f32], sample_rate: usize) -> usize {
+    let spectrum = crate::analysis::power_spectrum(&signal[..sample_rate]);
+    spectrum
+        .iter()
+        .enumerate()
+        .fold((0, 0.), |max, (i, &p)| if p > max.1 { (i, p) } else { max })
+        .0
+}
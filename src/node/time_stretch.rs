@@ -0,0 +1,483 @@
+use std::error::Error;
+
+use crossbeam_channel::{Receiver, Sender};
+use once_cell::sync::OnceCell;
+
+use crate::buffer::AudioBuffer;
+use crate::context::{AudioContextRegistration, AudioParamId, BaseAudioContext};
+use crate::control::Scheduler;
+use crate::param::{AudioParam, AudioParamDescriptor, AutomationRate};
+use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
+
+use super::phase_vocoder::PhaseVocoder;
+use super::stft::{FFT_SIZE, HOP_SIZE};
+use super::{AudioNode, AudioScheduledSourceNode, ChannelConfig, ChannelConfigOptions};
+
+/// Options for constructing a [`TimeStretchNode`]
+#[derive(Clone, Debug)]
+pub struct TimeStretchOptions {
+    /// The buffer to play
+    pub buffer: Option<AudioBuffer>,
+    /// initial value for the stretch ratio parameter
+    pub stretch_ratio: f32,
+    /// audio node options
+    pub channel_config: ChannelConfigOptions,
+}
+
+impl Default for TimeStretchOptions {
+    fn default() -> Self {
+        Self {
+            buffer: None,
+            stretch_ratio: 1.,
+            channel_config: ChannelConfigOptions::default(),
+        }
+    }
+}
+
+/// `TimeStretchNode` plays an [`AudioBuffer`] at a different tempo, without changing its pitch
+///
+/// The buffer is resynthesized with the phase vocoder of the
+/// [`PitchShiftNode`](crate::node::PitchShiftNode): frames of 2048 samples are read from the
+/// buffer at the stretched pace, and overlap-added 512 samples apart. With a `stretch_ratio` of
+/// 1 the buffer is played unmodified.
+///
+/// The buffer is played at the sample rate of the context: when the rates differ, the vocoder
+/// also compensates the change of pitch. The `ended` event is dispatched once the last frame
+/// reading the buffer has been played, up to 2048 samples after the stretched buffer.
+///
+/// This node is not part of the Web Audio API specification.
+///
+/// - see also: [`BaseAudioContext::create_time_stretch`](crate::context::BaseAudioContext::create_time_stretch)
+///
+/// # Usage
+///
+/// ```no_run
+/// use std::fs::File;
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+///
+/// let context = AudioContext::default();
+/// let file = File::open("samples/sample.wav").unwrap();
+/// let buffer = context.decode_audio_data_sync(file).unwrap();
+///
+/// // play twice as slow, at the same pitch
+/// let stretch = context.create_time_stretch();
+/// stretch.set_buffer(buffer);
+/// stretch.stretch_ratio().set_value(2.);
+/// stretch.connect(&context.destination());
+/// stretch.start();
+/// ```
+pub struct TimeStretchNode {
+    /// Represents the node instance and its associated audio context
+    registration: AudioContextRegistration,
+    /// Infos about audio node channel configuration
+    channel_config: ChannelConfig,
+    /// Ratio of the played duration to the buffer duration
+    stretch_ratio: AudioParam,
+    /// starts and stops the playback
+    scheduler: Scheduler,
+    /// The buffer to play, set only once
+    buffer: OnceCell<AudioBuffer>,
+    /// Sends the buffer and the vocoder state to the renderer
+    sender: Sender<StretchedBuffer>,
+}
+
+impl AudioNode for TimeStretchNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl AudioScheduledSourceNode for TimeStretchNode {
+    fn try_start_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_start_at(when)
+    }
+
+    fn try_stop_at(&self, when: f64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.scheduler.try_stop_at(when)
+    }
+}
+
+impl TimeStretchNode {
+    /// Returns a `TimeStretchNode`
+    ///
+    /// # Arguments:
+    ///
+    /// * `context` - The `AudioContext`
+    /// * `options` - The TimeStretchOptions
+    pub fn new<C: BaseAudioContext>(context: &C, options: TimeStretchOptions) -> Self {
+        context.register(move |registration| {
+            let TimeStretchOptions {
+                buffer,
+                stretch_ratio,
+                channel_config,
+            } = options;
+
+            let stretch_ratio_options = AudioParamDescriptor {
+                min_value: 0.25,
+                max_value: 4.,
+                default_value: 1.,
+                automation_rate: AutomationRate::K,
+            };
+            let (mut stretch_ratio_param, stretch_ratio_proc) =
+                context.create_audio_param(stretch_ratio_options, &registration);
+            stretch_ratio_param.set_automation_rate_constrained(true);
+            stretch_ratio_param.set_value(stretch_ratio);
+
+            // the buffer can only be set once
+            let (sender, receiver) = crossbeam_channel::bounded(1);
+            let scheduler = Scheduler::new();
+
+            let renderer = TimeStretchRenderer {
+                stretch_ratio: stretch_ratio_proc,
+                scheduler: scheduler.clone(),
+                receiver,
+                buffer: None,
+                started: false,
+                position: 0.,
+                ready: HOP_SIZE,
+                frames_past_end: 0,
+                ended_triggered: false,
+            };
+
+            let node = Self {
+                registration,
+                channel_config: channel_config.into(),
+                stretch_ratio: stretch_ratio_param,
+                scheduler,
+                buffer: OnceCell::new(),
+                sender,
+            };
+
+            if let Some(buffer) = buffer {
+                node.set_buffer(buffer);
+            }
+
+            (node, Box::new(renderer))
+        })
+    }
+
+    /// K-rate [`AudioParam`] of the ratio of the played duration to the buffer duration, e.g.
+    /// `2` to play twice as slow or `0.5` twice as fast
+    ///
+    /// The value is clamped to the range `[0.25, 4]` and picked up every 512 samples.
+    #[must_use]
+    pub fn stretch_ratio(&self) -> &AudioParam {
+        &self.stretch_ratio
+    }
+
+    /// Current buffer value (nullable)
+    pub fn buffer(&self) -> Option<&AudioBuffer> {
+        self.buffer.get()
+    }
+
+    /// Provide the [`AudioBuffer`] to play
+    ///
+    /// # Panics
+    ///
+    /// Panics if a buffer has already been given to the node (through `new` or through
+    /// `set_buffer`)
+    pub fn set_buffer(&self, buffer: AudioBuffer) {
+        // allocate the vocoders here rather than in the render thread
+        let stretched = StretchedBuffer {
+            channels: (0..buffer.number_of_channels())
+                .map(|_| ChannelState::new())
+                .collect(),
+            buffer: buffer.clone(),
+        };
+
+        if self.buffer.set(buffer).is_err() {
+            panic!("InvalidStateError - cannot assign buffer twice");
+        }
+
+        self.sender
+            .send(stretched)
+            .expect("Sending the buffer to the renderer failed");
+    }
+}
+
+/// Analysis frames and phase vocoder of a channel
+struct ChannelState {
+    vocoder: PhaseVocoder,
+    /// `FFT_SIZE + HOP_SIZE` samples read from the buffer
+    input: Vec<f32>,
+}
+
+impl ChannelState {
+    fn new() -> Self {
+        Self {
+            vocoder: PhaseVocoder::new(),
+            input: vec![0.; FFT_SIZE + HOP_SIZE],
+        }
+    }
+}
+
+/// The buffer to play with the state of each of its channels
+struct StretchedBuffer {
+    buffer: AudioBuffer,
+    channels: Vec<ChannelState>,
+}
+
+impl StretchedBuffer {
+    /// Synthesize the frame read at `position`, `speed` is the number of buffer frames read per
+    /// output frame
+    fn synthesize(&mut self, position: f64, speed: f64, pitch_ratio: f32) {
+        let Self { buffer, channels } = self;
+        let length = buffer.length() as i64;
+        // the frequencies are estimated from the frame a hop before
+        let start = position.round() as i64 - HOP_SIZE as i64;
+
+        channels.iter_mut().enumerate().for_each(|(c, channel)| {
+            let data = buffer.get_channel_data(c);
+            channel.input.iter_mut().enumerate().for_each(|(i, v)| {
+                let index = start + i as i64;
+                *v = if index >= 0 && index < length {
+                    data[index as usize]
+                } else {
+                    0.
+                };
+            });
+            channel
+                .vocoder
+                .process(&channel.input, pitch_ratio, 1. / speed);
+        });
+    }
+}
+
+struct TimeStretchRenderer {
+    /// Ratio of the played duration to the buffer duration
+    stretch_ratio: AudioParamId,
+    /// starts and stops the playback
+    scheduler: Scheduler,
+    /// Receives the buffer from the control thread
+    receiver: Receiver<StretchedBuffer>,
+    buffer: Option<StretchedBuffer>,
+    /// defines if the playback has started
+    started: bool,
+    /// Position in the buffer of the next frame to synthesize
+    position: f64,
+    /// Number of samples of the latest synthesized hop that were played
+    ready: usize,
+    /// Number of frames synthesized after the end of the buffer
+    frames_past_end: usize,
+    /// defines if the `ended` events was already dispatched
+    ended_triggered: bool,
+}
+
+impl AudioProcessor for TimeStretchRenderer {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        let output = &mut outputs[0];
+
+        if let Ok(buffer) = self.receiver.try_recv() {
+            self.buffer = Some(buffer);
+        }
+
+        let sample_rate = f64::from(scope.sample_rate);
+        let dt = 1. / sample_rate;
+        let next_block_time = scope.current_time + dt * scope.render_quantum_size as f64;
+
+        let start_time = self.scheduler.get_start_at();
+        let stop_time = self.scheduler.get_stop_at();
+
+        if self.ended_triggered {
+            output.make_silent();
+            return false;
+        }
+
+        // wait for the start time and for the buffer
+        let buffer = match &mut self.buffer {
+            Some(buffer) if start_time < next_block_time => buffer,
+            _ => {
+                output.make_silent();
+                return true;
+            }
+        };
+
+        let buffer_rate = f64::from(buffer.buffer.sample_rate());
+        let stretch_ratio = f64::from(params.get(&self.stretch_ratio)[0]);
+        let speed = buffer_rate / sample_rate / stretch_ratio;
+        // compensate the transposition of playing the buffer at the context rate
+        let pitch_ratio = (buffer_rate / sample_rate) as f32;
+        let length = buffer.buffer.length() as f64;
+
+        if !self.started {
+            // prime the overlap-add with the frames preceding the start of the buffer
+            let overlap = FFT_SIZE / HOP_SIZE - 1;
+            self.position = -((overlap * HOP_SIZE) as f64) * speed;
+            for _ in 0..overlap {
+                buffer.synthesize(self.position, speed, pitch_ratio);
+                self.position += HOP_SIZE as f64 * speed;
+            }
+            self.started = true;
+        }
+
+        output.set_number_of_channels(buffer.channels.len());
+        let mut current_time = scope.current_time;
+        let mut finished = false;
+
+        for i in 0..scope.render_quantum_size {
+            if current_time < start_time || current_time >= stop_time || finished {
+                output
+                    .channels_mut()
+                    .iter_mut()
+                    .for_each(|channel| channel[i] = 0.);
+                finished |= current_time >= stop_time;
+                current_time += dt;
+                continue;
+            }
+
+            if self.ready == HOP_SIZE {
+                // the last frame reading the buffer has been played entirely
+                if self.frames_past_end == FFT_SIZE / HOP_SIZE - 1 {
+                    finished = true;
+                    continue;
+                }
+
+                buffer.synthesize(self.position, speed, pitch_ratio);
+                if self.position >= length {
+                    self.frames_past_end += 1;
+                }
+                self.position += HOP_SIZE as f64 * speed;
+                self.ready = 0;
+            }
+
+            let ready = self.ready;
+            output
+                .channels_mut()
+                .iter_mut()
+                .zip(buffer.channels.iter())
+                .for_each(|(o, channel)| o[i] = channel.vocoder.output()[ready]);
+            self.ready += 1;
+            current_time += dt;
+        }
+
+        if finished {
+            scope.send_ended_event();
+            self.ended_triggered = true;
+            return false;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
+
+    use super::super::test_util::peak_frequency;
+    use super::*;
+
+    /// Play a one second 440 Hz sine buffer with the given stretch ratio
+    fn render(stretch_ratio: f32, buffer_rate: f32) -> Vec<f32> {
+        let sample_rate = 48_000;
+        let context = OfflineAudioContext::new(1, 3 * sample_rate, sample_rate as f32);
+
+        let samples: Vec<f32> = (0..buffer_rate as usize)
+            .map(|i| {
+                (2. * std::f64::consts::PI * 440. * i as f64 / buffer_rate as f64).sin() as f32
+            })
+            .collect();
+        let buffer = AudioBuffer::from(vec![samples], buffer_rate);
+
+        let stretch = context.create_time_stretch();
+        stretch.set_buffer(buffer);
+        stretch.stretch_ratio().set_value(stretch_ratio);
+        stretch.connect(&context.destination());
+        stretch.start();
+
+        context.start_rendering_sync().get_channel_data(0).to_vec()
+    }
+
+    /// Number of samples until the output is silent
+    fn duration(signal: &[f32]) -> usize {
+        signal.iter().rposition(|v| v.abs() > 1e-3).unwrap() + 1
+    }
+
+    #[test]
+    fn test_stretch() {
+        let output = render(2., 48_000.);
+
+        // twice as long, at the same pitch
+        let duration = duration(&output);
+        assert!(duration > 96_000 && duration < 96_000 + FFT_SIZE);
+        assert_eq!(peak_frequency(&output[FFT_SIZE..], 48_000), 440);
+    }
+
+    #[test]
+    fn test_compress() {
+        let output = render(0.5, 48_000.);
+
+        let duration = duration(&output);
+        assert!(duration > 24_000 && duration < 24_000 + FFT_SIZE);
+        let spectrum = crate::analysis::power_spectrum(&output[..16_384]);
+        let peak = spectrum
+            .iter()
+            .enumerate()
+            .fold((0, 0.), |max, (i, &p)| if p > max.1 { (i, p) } else { max })
+            .0;
+        // bins of 48000 / 16384 Hz
+        assert_float_eq!(peak as f32 * 48_000. / 16_384., 440., abs <= 3.);
+    }
+
+    #[test]
+    fn test_passthrough() {
+        let output = render(1., 48_000.);
+
+        let expected: Vec<f32> = (0..48_000)
+            .map(|i| (2. * std::f64::consts::PI * 440. * i as f64 / 48_000.).sin() as f32)
+            .collect();
+        assert_float_eq!(output[..48_000], expected[..], abs_all <= 1e-4);
+        assert!(output[48_000..].iter().all(|v| v.abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_buffer_sample_rate() {
+        // the duration and pitch of the buffer are preserved
+        let output = render(1., 44_100.);
+        let duration = duration(&output);
+        assert!(duration > 48_000 && duration < 48_000 + FFT_SIZE);
+        assert_eq!(peak_frequency(&output[FFT_SIZE..], 48_000), 440);
+    }
+
+    #[test]
+    fn test_ended() {
+        let sample_rate = 48_000.;
+        let context = OfflineAudioContext::new(1, 48_000, sample_rate);
+        let buffer = AudioBuffer::from(vec![vec![0.5; 4_800]], sample_rate);
+
+        let stretch = context.create_time_stretch();
+        stretch.set_buffer(buffer);
+        stretch.stretch_ratio().set_value(2.);
+        stretch.connect(&context.destination());
+        stretch.start();
+
+        let ended = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let ended_clone = std::sync::Arc::clone(&ended);
+        stretch.onended(move || ended_clone.store(true, std::sync::atomic::Ordering::SeqCst));
+
+        let _ = context.start_rendering_sync();
+        assert!(ended.load(std::sync::atomic::Ordering::SeqCst));
+    }
+}
@@ -180,6 +180,7 @@ impl WaveShaperNode {
 
             let config = RendererConfig {
                 sample_rate,
+                render_quantum_size: context.render_quantum_size(),
                 oversample: oversample.clone(),
                 receiver,
             };
@@ -245,11 +246,105 @@ impl WaveShaperNode {
     }
 }
 
+/// Generators for common distortion curves, to be used with [`WaveShaperNode::set_curve`]
+///
+/// Each generator returns `len` values sampling the transfer function over the `[-1, 1]` input
+/// range, the first value maps an input of `-1` and the last value an input of `1`.
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::Curve;
+///
+/// let context = AudioContext::default();
+/// let shaper = context.create_wave_shaper();
+/// shaper.set_curve(Curve::tanh(5., 1024));
+/// ```
+pub struct Curve;
+
+impl Curve {
+    /// Soft clipping curve, following a normalized hyperbolic tangent
+    ///
+    /// The curve is scaled so that an input of `1` is mapped to `1`. A `drive` of zero yields the
+    /// identity curve, higher values saturate the signal more.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `drive` is negative or not finite, or if `len` is less than 2
+    #[must_use]
+    pub fn tanh(drive: f32, len: usize) -> Vec<f32> {
+        assert!(
+            drive.is_finite() && drive >= 0.,
+            "RangeError - drive must be a positive finite number, got {:?}",
+            drive
+        );
+
+        if drive == 0. {
+            return Self::generate(len, |x| x);
+        }
+
+        let norm = drive.tanh();
+        Self::generate(len, |x| (drive * x).tanh() / norm)
+    }
+
+    /// Hard clipping curve, limiting the signal to `[-threshold, threshold]`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threshold` is not strictly positive or not finite, or if `len` is less than 2
+    #[must_use]
+    pub fn hard_clip(threshold: f32, len: usize) -> Vec<f32> {
+        assert!(
+            threshold.is_finite() && threshold > 0.,
+            "RangeError - threshold must be a strictly positive finite number, got {:?}",
+            threshold
+        );
+
+        Self::generate(len, |x| x.clamp(-threshold, threshold))
+    }
+
+    /// Bit crushing curve, quantizing the signal to the resolution of `bits` bits
+    ///
+    /// As for a signed integer of `bits` bits, the signal is mapped to `2^bits` levels evenly
+    /// spaced in `[-1, 1)`, i.e. the largest level is `1 - 2^(1 - bits)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is not in the range `[1, 24]`, or if `len` is less than 2
+    #[must_use]
+    pub fn bit_crush(bits: u32, len: usize) -> Vec<f32> {
+        assert!(
+            (1..=24).contains(&bits),
+            "RangeError - bits must be in the range [1, 24], got {:?}",
+            bits
+        );
+
+        let steps = (1_u32 << (bits - 1)) as f32;
+        Self::generate(len, |x| {
+            (x * steps).floor().clamp(-steps, steps - 1.) / steps
+        })
+    }
+
+    fn generate(len: usize, transfer: impl Fn(f32) -> f32) -> Vec<f32> {
+        assert!(
+            len >= 2,
+            "RangeError - curve length must be at least 2, got {:?}",
+            len
+        );
+
+        let last = (len - 1) as f32;
+        (0..len)
+            .map(|i| transfer(2. * i as f32 / last - 1.))
+            .collect()
+    }
+}
+
 /// Helper struct which regroups all parameters
 /// required to build `WaveShaperRenderer`
 struct RendererConfig {
     /// Sample rate (equals to audio context sample rate)
     sample_rate: usize,
+    /// Render quantum size (equals to audio context render quantum size)
+    render_quantum_size: usize,
     /// oversample factor
     oversample: Arc<AtomicU32>,
     /// Channel between node and renderer (receiver part)
@@ -260,6 +355,8 @@ struct RendererConfig {
 struct WaveShaperRenderer {
     /// Sample rate (equals to audio context sample rate)
     sample_rate: usize,
+    /// Render quantum size (equals to audio context render quantum size)
+    render_quantum_size: usize,
     /// oversample factor
     oversample: Arc<AtomicU32>,
     /// Number of channels used to build the up/down sampler X2
@@ -321,7 +418,7 @@ impl AudioProcessor for WaveShaperRenderer {
                         self.upsampler_x2 = FftFixedInOut::<f32>::new(
                             self.sample_rate,
                             self.sample_rate * 2,
-                            256,
+                            self.render_quantum_size * 2,
                             self.channels_x2,
                         )
                         .unwrap();
@@ -329,7 +426,7 @@ impl AudioProcessor for WaveShaperRenderer {
                         self.downsampler_x2 = FftFixedInOut::<f32>::new(
                             self.sample_rate * 2,
                             self.sample_rate,
-                            128,
+                            self.render_quantum_size,
                             self.channels_x2,
                         )
                         .unwrap();
@@ -359,7 +456,7 @@ impl AudioProcessor for WaveShaperRenderer {
                         self.upsampler_x4 = FftFixedInOut::<f32>::new(
                             self.sample_rate,
                             self.sample_rate * 4,
-                            512,
+                            self.render_quantum_size * 4,
                             self.channels_x4,
                         )
                         .unwrap();
@@ -367,7 +464,7 @@ impl AudioProcessor for WaveShaperRenderer {
                         self.downsampler_x4 = FftFixedInOut::<f32>::new(
                             self.sample_rate * 4,
                             self.sample_rate,
-                            128,
+                            self.render_quantum_size,
                             self.channels_x4,
                         )
                         .unwrap();
@@ -401,6 +498,7 @@ impl WaveShaperRenderer {
     fn new(config: RendererConfig) -> Self {
         let RendererConfig {
             sample_rate,
+            render_quantum_size,
             oversample,
             receiver,
         } = config;
@@ -408,20 +506,41 @@ impl WaveShaperRenderer {
         let channels_x2 = 1;
         let channels_x4 = 1;
 
-        let upsampler_x2 =
-            FftFixedInOut::<f32>::new(sample_rate, sample_rate * 2, 256, channels_x2).unwrap();
+        let upsampler_x2 = FftFixedInOut::<f32>::new(
+            sample_rate,
+            sample_rate * 2,
+            render_quantum_size * 2,
+            channels_x2,
+        )
+        .unwrap();
 
-        let downsampler_x2 =
-            FftFixedInOut::<f32>::new(sample_rate * 2, sample_rate, 128, channels_x2).unwrap();
+        let downsampler_x2 = FftFixedInOut::<f32>::new(
+            sample_rate * 2,
+            sample_rate,
+            render_quantum_size,
+            channels_x2,
+        )
+        .unwrap();
 
-        let upsampler_x4 =
-            FftFixedInOut::<f32>::new(sample_rate, sample_rate * 4, 512, channels_x4).unwrap();
+        let upsampler_x4 = FftFixedInOut::<f32>::new(
+            sample_rate,
+            sample_rate * 4,
+            render_quantum_size * 4,
+            channels_x4,
+        )
+        .unwrap();
 
-        let downsampler_x4 =
-            FftFixedInOut::<f32>::new(sample_rate * 4, sample_rate, 128, channels_x4).unwrap();
+        let downsampler_x4 = FftFixedInOut::<f32>::new(
+            sample_rate * 4,
+            sample_rate,
+            render_quantum_size,
+            channels_x4,
+        )
+        .unwrap();
 
         Self {
             sample_rate,
+            render_quantum_size,
             oversample,
             channels_x2,
             channels_x4,
@@ -625,4 +744,85 @@ mod tests {
 
         assert_float_eq!(channel[..], expected[..], abs_all <= 0.);
     }
+
+    #[test]
+    fn test_curve_tanh() {
+        // zero drive yields the identity
+        let curve = Curve::tanh(0., 5);
+        assert_float_eq!(curve[..], [-1., -0.5, 0., 0.5, 1.][..], abs_all <= 0.);
+
+        let curve = Curve::tanh(10., 101);
+        assert_eq!(curve.len(), 101);
+        // odd symmetric and normalized
+        assert_float_eq!(curve[50], 0., abs <= 0.);
+        assert_float_eq!(curve[0], -1., abs <= 1e-6);
+        assert_float_eq!(curve[100], 1., abs <= 1e-6);
+        for i in 0..101 {
+            assert_float_eq!(curve[i], -curve[100 - i], abs <= 1e-6);
+        }
+        // saturates: input 0.5 is already close to the maximum
+        assert!(curve[75] > 0.99);
+        // monotonic
+        assert!(curve.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_curve_hard_clip() {
+        let curve = Curve::hard_clip(0.5, 9);
+        let expected = [-0.5, -0.5, -0.5, -0.25, 0., 0.25, 0.5, 0.5, 0.5];
+        assert_float_eq!(curve[..], expected[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_curve_bit_crush() {
+        let curve = Curve::bit_crush(2, 9);
+        let expected = [-1., -1., -0.5, -0.5, 0., 0., 0.5, 0.5, 0.5];
+        assert_float_eq!(curve[..], expected[..], abs_all <= 0.);
+
+        // number of distinct levels is bounded by the bit depth
+        for bits in 1..=8 {
+            let mut levels = Curve::bit_crush(bits, 10_000);
+            levels.dedup();
+            assert_eq!(levels.len(), 1 << bits);
+        }
+    }
+
+    #[test]
+    fn test_curve_minimal_length() {
+        assert_float_eq!(Curve::tanh(3., 2)[..], [-1., 1.][..], abs_all <= 1e-6);
+        assert_float_eq!(Curve::hard_clip(0.5, 2)[..], [-0.5, 0.5][..], abs_all <= 0.);
+        assert_float_eq!(Curve::bit_crush(1, 2)[..], [-1., 0.][..], abs_all <= 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_curve_hard_clip_nan_threshold() {
+        let _ = Curve::hard_clip(f32::NAN, 10);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_curve_too_short() {
+        let _ = Curve::tanh(1., 1);
+    }
+
+    #[test]
+    fn test_curve_shapes_signal() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+
+        let shaper = context.create_wave_shaper();
+        shaper.set_curve(Curve::hard_clip(0.5, 3));
+        shaper.connect(&context.destination());
+
+        let src = context.create_constant_source();
+        src.connect(&shaper);
+        src.start();
+
+        let result = context.start_rendering_sync();
+        assert_float_eq!(
+            result.get_channel_data(0)[..],
+            [0.5; 128][..],
+            abs_all <= 0.
+        );
+    }
 }
@@ -3,15 +3,14 @@ use std::slice::{Iter, IterMut};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use crate::context::AudioContextRegistration;
+use crate::context::{AudioContextRegistration, BaseAudioContext};
 use crate::node::{
     AudioNode, ChannelConfig, ChannelConfigOptions, ChannelCountMode, ChannelInterpretation,
 };
 use crate::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
-use crate::{AtomicF32, RENDER_QUANTUM_SIZE};
+use crate::{AtomicF32, AtomicF64};
 
 use crossbeam_channel::{Receiver, Sender};
-use lazy_static::lazy_static;
 
 /// For SetTargetAtTime event, that theoreticaly cannot end, if the diff between
 /// the current value and the target is below this threshold, the value is set
@@ -78,7 +77,7 @@ pub struct AudioParamDescriptor {
     pub max_value: f32,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone)]
 enum AudioParamEventType {
     SetValue,
     SetValueAtTime,
@@ -90,17 +89,129 @@ enum AudioParamEventType {
     SetValueCurveAtTime,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct AudioParamEvent {
     event_type: AudioParamEventType,
     value: f32,
     time: f64,
     time_constant: Option<f64>, // populated by `SetTargetAtTime` events
     cancel_time: Option<f64>,   // populated by `CancelAndHoldAtTime` events
-    duration: Option<f64>,      // populated by `SetValueCurveAtTime` events
+    duration: Option<f64>,      // populated by `SetValueCurveAtTime` and ramped `SetValue` events
     values: Option<Box<[f32]>>, // populated by `SetValueCurveAtTime` events
 }
 
+impl AudioParamEvent {
+    /// The public description of this event, to be replayed with [`AutomationEvent::apply`]
+    pub(crate) fn to_automation_event(&self) -> AutomationEvent {
+        match self.event_type {
+            AudioParamEventType::SetValue => AutomationEvent::SetValue { value: self.value },
+            AudioParamEventType::SetValueAtTime => AutomationEvent::SetValueAtTime {
+                value: self.value,
+                start_time: self.time,
+            },
+            AudioParamEventType::LinearRampToValueAtTime => AutomationEvent::LinearRamp {
+                value: self.value,
+                end_time: self.time,
+            },
+            AudioParamEventType::ExponentialRampToValueAtTime => AutomationEvent::ExponentialRamp {
+                value: self.value,
+                end_time: self.time,
+            },
+            AudioParamEventType::SetTargetAtTime => AutomationEvent::SetTargetAtTime {
+                value: self.value,
+                start_time: self.time,
+                time_constant: self.time_constant.unwrap(),
+            },
+            AudioParamEventType::CancelScheduledValues => AutomationEvent::CancelScheduledValues {
+                cancel_time: self.time,
+            },
+            AudioParamEventType::CancelAndHoldAtTime => AutomationEvent::CancelAndHoldAtTime {
+                cancel_time: self.time,
+            },
+            AudioParamEventType::SetValueCurveAtTime => AutomationEvent::SetValueCurveAtTime {
+                values: self.values.as_deref().unwrap().to_vec(),
+                start_time: self.time,
+                duration: self.duration.unwrap(),
+            },
+        }
+    }
+}
+
+/// Automation call made on an [`AudioParam`], as recorded in a
+/// [`Patch`](crate::context::Patch)
+///
+/// Each variant corresponds to the `AudioParam` method of the same name.
+///
+/// This type is not part of the Web Audio API specification.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "kebab-case")
+)]
+pub enum AutomationEvent {
+    /// See [`AudioParam::set_value`]
+    SetValue { value: f32 },
+    /// See [`AudioParam::set_value_at_time`]
+    SetValueAtTime { value: f32, start_time: f64 },
+    /// See [`AudioParam::linear_ramp_to_value_at_time`]
+    LinearRamp { value: f32, end_time: f64 },
+    /// See [`AudioParam::exponential_ramp_to_value_at_time`]
+    ExponentialRamp { value: f32, end_time: f64 },
+    /// See [`AudioParam::set_target_at_time`]
+    SetTargetAtTime {
+        value: f32,
+        start_time: f64,
+        time_constant: f64,
+    },
+    /// See [`AudioParam::cancel_scheduled_values`]
+    CancelScheduledValues { cancel_time: f64 },
+    /// See [`AudioParam::cancel_and_hold_at_time`]
+    CancelAndHoldAtTime { cancel_time: f64 },
+    /// See [`AudioParam::set_value_curve_at_time`]
+    SetValueCurveAtTime {
+        values: Vec<f32>,
+        start_time: f64,
+        duration: f64,
+    },
+}
+
+impl AutomationEvent {
+    /// Make the corresponding automation call on the `param`
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as the corresponding `AudioParam` method
+    pub fn apply(&self, param: &AudioParam) {
+        match *self {
+            Self::SetValue { value } => param.set_value(value),
+            Self::SetValueAtTime { value, start_time } => {
+                param.set_value_at_time(value, start_time)
+            }
+            Self::LinearRamp { value, end_time } => {
+                param.linear_ramp_to_value_at_time(value, end_time)
+            }
+            Self::ExponentialRamp { value, end_time } => {
+                param.exponential_ramp_to_value_at_time(value, end_time)
+            }
+            Self::SetTargetAtTime {
+                value,
+                start_time,
+                time_constant,
+            } => param.set_target_at_time(value, start_time, time_constant),
+            Self::CancelScheduledValues { cancel_time } => {
+                param.cancel_scheduled_values(cancel_time)
+            }
+            Self::CancelAndHoldAtTime { cancel_time } => param.cancel_and_hold_at_time(cancel_time),
+            Self::SetValueCurveAtTime {
+                ref values,
+                start_time,
+                duration,
+            } => param.set_value_curve_at_time(values, start_time, duration),
+        };
+    }
+}
+
 // Event queue that contains `AudioParamEvent`s, most of the time, events must be
 // ordered (using stable sort), some operation may break this ordering (e.g. `push`)
 // in which cases `sort` must be called explicitely.
@@ -110,7 +221,7 @@ pub(crate) struct AudioParamEvent {
 // occurs during the insertion of events)
 // After this point, the queue should be considered sorted and no operations that
 // breaks the ordering should be done.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct AudioParamEventTimeline {
     inner: Vec<AudioParamEvent>,
     dirty: bool,
@@ -145,8 +256,8 @@ impl AudioParamEventTimeline {
         self.inner.retain(func);
     }
 
-    // Only used to handle special cases in `ExponentialRampToValueAtTime`:
-    // as the replaced item has the same time, order is preserved.
+    // Only used to handle special cases in `ExponentialRampToValueAtTime` and
+    // `SetTargetAtTime`: as the replaced item has the same time, order is preserved.
     // If the method turned out to be used elsewhere, this could maybe
     // become wrong, be careful here.
     fn replace_peek(&mut self, item: AudioParamEvent) {
@@ -200,7 +311,9 @@ pub struct AudioParam {
     min_value: f32,     // readonly
     max_value: f32,     // readonly
     current_value: Arc<AtomicF32>,
+    value_ramp_time: Arc<AtomicF64>,
     sender: Sender<AudioParamEvent>,
+    channel_config: ChannelConfig,
 }
 
 // helper struct to attach / detach to context (for borrow reasons)
@@ -212,17 +325,18 @@ pub(crate) struct AudioParamRaw {
     min_value: f32,
     max_value: f32,
     current_value: Arc<AtomicF32>,
+    value_ramp_time: Arc<AtomicF64>,
     sender: Sender<AudioParamEvent>,
+    channel_config: ChannelConfig,
 }
 
-lazy_static! {
-    static ref AUDIO_PARAM_CHANNEL_CONFIG: ChannelConfig = ChannelConfigOptions {
-        count: 1,
-        count_mode: ChannelCountMode::Explicit,
-        interpretation: ChannelInterpretation::Discrete,
-    }
-    .into();
-}
+// Inputs connected to an AudioParam are summed and down-mixed to mono
+// <https://webaudio.github.io/web-audio-api/#computation-of-value>
+const AUDIO_PARAM_CHANNEL_CONFIG: ChannelConfigOptions = ChannelConfigOptions {
+    count: 1,
+    count_mode: ChannelCountMode::Explicit,
+    interpretation: ChannelInterpretation::Speakers,
+};
 
 impl AudioNode for AudioParam {
     fn registration(&self) -> &AudioContextRegistration {
@@ -230,7 +344,7 @@ impl AudioNode for AudioParam {
     }
 
     fn channel_config(&self) -> &ChannelConfig {
-        &AUDIO_PARAM_CHANNEL_CONFIG
+        &self.channel_config
     }
 
     fn number_of_inputs(&self) -> usize {
@@ -280,14 +394,32 @@ impl AudioParam {
         self.automation_rate_constrained = value;
     }
 
+    /// Keep the channels of the signals connected to the param instead of down-mixing them to
+    /// mono, the intrinsic value is added to each channel
+    ///
+    /// This is not part of the specification, the renderer owning the param must read the
+    /// multi-channel values with `AudioParamValues::get_channels`.
+    pub(crate) fn set_multi_channel_input(&self) {
+        self.channel_config.set_count_mode(ChannelCountMode::Max);
+    }
+
+    /// Initial value of the `AudioParam`, before any value is set or scheduled
     pub fn default_value(&self) -> f32 {
         self.default_value
     }
 
+    /// Lower bound of the nominal range of the `AudioParam`
+    ///
+    /// The computed value, i.e. the intrinsic value summed with the connected inputs, is
+    /// clamped to the nominal range, whatever the automation rate.
     pub fn min_value(&self) -> f32 {
         self.min_value
     }
 
+    /// Upper bound of the nominal range of the `AudioParam`
+    ///
+    /// The computed value, i.e. the intrinsic value summed with the connected inputs, is
+    /// clamped to the nominal range, whatever the automation rate.
     pub fn max_value(&self) -> f32 {
         self.max_value
     }
@@ -311,6 +443,9 @@ impl AudioParam {
     ///
     /// Is equivalent to calling the `set_value_at_time` method with the current
     /// AudioContext's currentTime
+    ///
+    /// If a ramp time is set with [`set_value_ramp_time`](Self::set_value_ramp_time), the value
+    /// is reached through a linear ramp instead.
     //
     // @note: Setting this attribute has the effect of assigning the requested value
     // to the [[current value]] slot, and calling the setValueAtTime() method
@@ -325,13 +460,18 @@ impl AudioParam {
 
         // this event is meant to update param intrisic value before any calculation
         // is done, will behave as SetValueAtTime with `time == block_timestamp`
+        let ramp_time = self.value_ramp_time.load();
         let event = AudioParamEvent {
             event_type: AudioParamEventType::SetValue,
             value,
             time: 0.,
             time_constant: None,
             cancel_time: None,
-            duration: None,
+            duration: if ramp_time > 0. {
+                Some(ramp_time)
+            } else {
+                None
+            },
             values: None,
         };
 
@@ -340,6 +480,39 @@ impl AudioParam {
         self
     }
 
+    /// Duration in seconds of the linear ramp applied by [`set_value`](Self::set_value)
+    pub fn value_ramp_time(&self) -> f64 {
+        self.value_ramp_time.load()
+    }
+
+    /// Make [`set_value`](Self::set_value) ramp linearly from the current value to the new value
+    /// during `ramp_time` seconds, instead of jumping to it
+    ///
+    /// A ramp of a few milliseconds removes the clicks, or the zipper noise, caused by abrupt
+    /// changes of the value, e.g. when the value follows a slider of a user interface. Calling
+    /// `set_value` during the ramp starts a new ramp from the current value. A ramp time of
+    /// zero, the default, restores the instantaneous behavior.
+    ///
+    /// Explicit automation events take precedence: `set_value` is instantaneous if the param
+    /// has automation events pending. Likewise, scheduling an automation event during the ramp
+    /// ends it, the param jumps to the value given to `set_value` and the automation applies
+    /// from there.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `ramp_time` is negative or not finite
+    pub fn set_value_ramp_time(&self, ramp_time: f64) {
+        assert!(
+            ramp_time.is_finite() && ramp_time >= 0.,
+            "RangeError - ramp time ({:?}) should be a non-negative finite number",
+            ramp_time
+        );
+
+        self.value_ramp_time.store(ramp_time);
+    }
+
     /// Schedules a parameter value change at the given time.
     ///
     /// # Panics
@@ -546,7 +719,9 @@ impl AudioParam {
             min_value: self.min_value,
             max_value: self.max_value,
             current_value: self.current_value,
+            value_ramp_time: self.value_ramp_time,
             sender: self.sender,
+            channel_config: self.channel_config,
         }
     }
 
@@ -563,11 +738,66 @@ impl AudioParam {
             min_value: parts.min_value,
             max_value: parts.max_value,
             current_value: parts.current_value,
+            value_ramp_time: parts.value_ramp_time,
             sender: parts.sender,
+            channel_config: parts.channel_config,
+        }
+    }
+
+    /// Compute the values the param would take from `start_time`, at the given sample rate,
+    /// without running the render thread
+    ///
+    /// Returns the values at `start_time + n / sample_rate` for `n` in `0..count`, e.g. to draw
+    /// the automation curve in a user interface. The automation events scheduled so far are
+    /// replayed with the evaluator of the render thread, render quantum by render quantum from
+    /// the current time of the context. Elapsed events are not kept: before the current time,
+    /// the values are the one the param holds at the current time. When rendering at the same
+    /// sample rate, the values match the rendered ones exactly, unless audio nodes are
+    /// connected to the param or its automation rate is changed in the meantime. Between the
+    /// samples of the render thread, i.e. if `start_time` is not a multiple of the sample
+    /// period, the values are linearly interpolated.
+    ///
+    /// This method is not part of the Web Audio API specification.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if:
+    /// - `start_time` is negative
+    /// - `sample_rate` is not strictly positive
+    pub fn compute_values(&self, start_time: f64, count: usize, sample_rate: f32) -> Vec<f32> {
+        assert_non_negative(start_time);
+        assert!(
+            sample_rate > 0.,
+            "RangeError - sample rate ({:?}) should be strictly positive",
+            sample_rate
+        );
+
+        let position = start_time * f64::from(sample_rate);
+        let first_frame = position.round();
+        if (position - first_frame).abs() <= 1e-6 {
+            return self.render_values(first_frame as usize, count, sample_rate);
         }
+
+        let first_frame = position.floor();
+        let k = (position - first_frame) as f32;
+        let values = self.render_values(first_frame as usize, count + 1, sample_rate);
+
+        values
+            .windows(2)
+            .map(|w| (w[1] - w[0]).mul_add(k, w[0]))
+            .collect()
+    }
+
+    /// Values of the param rendered from `first_frame`, as by the render thread
+    fn render_values(&self, first_frame: usize, count: usize, sample_rate: f32) -> Vec<f32> {
+        self.context().with_automation_history(self, |automation| {
+            automation.values(first_frame, count, sample_rate)
+        })
     }
 
     fn send_event(&self, event: AudioParamEvent) {
+        self.context().record_automation_event(self, &event);
+
         if cfg!(test) {
             // bypass audiocontext enveloping of control messages for simpler testing
             self.sender.send(event).unwrap();
@@ -577,6 +807,210 @@ impl AudioParam {
     }
 }
 
+/// Automation of an [`AudioParam`] kept on the control thread
+///
+/// The events are stored with the frame they were sent at, recording an event does not
+/// evaluate anything. Only when the automation is read, by `save_patch` or `compute_values`,
+/// the events are evaluated as by the render thread up to the current frame of the context.
+/// Elapsed events are dropped then, only the state they left the param in is kept.
+pub(crate) struct AutomationHistory {
+    /// Replica of the render thread evaluator, at the start of `frame`
+    processor: AudioParamProcessor,
+    sender: Sender<AudioParamEvent>,
+    /// Sample rate of the context
+    sample_rate: f64,
+    /// Render quantum size of the context
+    render_quantum_size: usize,
+    /// First frame not evaluated yet by `processor`
+    frame: u64,
+    /// Whether `processor` received events, i.e. whether elapsed events were dropped
+    evaluated: bool,
+    /// Events not passed to `processor` yet, with the frame they were sent at
+    pending: Vec<(u64, AudioParamEvent)>,
+}
+
+impl AutomationHistory {
+    pub(crate) fn new(param: &AudioParam) -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let processor = AudioParamProcessor {
+            intrisic_value: param.default_value,
+            current_value: Arc::new(AtomicF32::new(param.default_value)),
+            receiver,
+            is_a_rate: param.is_a_rate.clone(),
+            default_value: param.default_value,
+            min_value: param.min_value,
+            max_value: param.max_value,
+            event_timeline: AudioParamEventTimeline::new(),
+            last_event: None,
+            set_value_ramp_target: None,
+            buffer: Vec::new(),
+        };
+
+        Self {
+            processor,
+            sender,
+            sample_rate: f64::from(param.context().sample_rate()),
+            render_quantum_size: param.context().render_quantum_size(),
+            frame: 0,
+            evaluated: false,
+            pending: vec![],
+        }
+    }
+
+    /// Record an event sent at `frame`
+    pub(crate) fn record(&mut self, event: &AudioParamEvent, frame: u64) {
+        // only the last of consecutive `set_value` calls in a render quantum has an effect
+        if let Some((sent, last)) = self.pending.last() {
+            if *sent == frame
+                && last.event_type == AudioParamEventType::SetValue
+                && event.event_type == AudioParamEventType::SetValue
+            {
+                self.pending.pop();
+            }
+        }
+        self.pending.push((frame, event.clone()));
+    }
+
+    /// Evaluate the automation up to `frame`, render quantum by render quantum
+    pub(crate) fn advance(&mut self, frame: u64) {
+        let quantum = self.render_quantum_size as u64;
+
+        while self.frame < frame {
+            // the render thread picks up the events at the first render quantum after they
+            // were sent
+            let sent = self
+                .pending
+                .iter()
+                .take_while(|(sent, _)| *sent <= self.frame)
+                .count();
+            let sender = &self.sender;
+            self.pending.drain(..sent).for_each(|(_, event)| {
+                sender.send(event).unwrap();
+            });
+            self.evaluated |= sent > 0;
+
+            // the value does not change until the next event, skip to it
+            if sent == 0 && self.processor.event_timeline.is_empty() {
+                let next = self.pending.first().map_or(frame, |(sent, _)| *sent);
+                let next = next.min(frame);
+                self.frame += (next - self.frame).div_ceil(quantum) * quantum;
+                continue;
+            }
+
+            self.processor.compute_intrisic_values(
+                self.frame as f64 / self.sample_rate,
+                1. / self.sample_rate,
+                self.render_quantum_size,
+            );
+            self.frame += quantum;
+        }
+    }
+
+    /// The automation calls that restore the param, elapsed events are replaced by the value
+    /// they left the param at
+    pub(crate) fn automation_events(&self) -> Vec<AutomationEvent> {
+        let mut events = vec![];
+
+        if self.evaluated {
+            events.push(AutomationEvent::SetValue {
+                value: self.processor.intrisic_value,
+            });
+
+            let timeline = &self.processor.event_timeline.inner;
+            events.extend(timeline.iter().map(AudioParamEvent::to_automation_event));
+
+            // ramps and curves shortened by `cancel_and_hold_at_time`
+            let mut cancel_times: Vec<_> = timeline
+                .iter()
+                .filter_map(|event| event.cancel_time)
+                .collect();
+            cancel_times.dedup();
+            events.extend(
+                cancel_times
+                    .into_iter()
+                    .map(|cancel_time| AutomationEvent::CancelAndHoldAtTime { cancel_time }),
+            );
+        }
+
+        events.extend(
+            self.pending
+                .iter()
+                .map(|(_, event)| event.to_automation_event()),
+        );
+
+        events
+    }
+
+    /// Values of the param from `first_frame` at `sample_rate`, replayed from the evaluated
+    /// state as by the render thread, see [`AudioParam::compute_values`]
+    pub(crate) fn values(&self, first_frame: usize, count: usize, sample_rate: f32) -> Vec<f32> {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let evaluated = &self.processor;
+        let mut processor = AudioParamProcessor {
+            intrisic_value: evaluated.intrisic_value,
+            current_value: Arc::new(AtomicF32::new(evaluated.intrisic_value)),
+            receiver,
+            is_a_rate: evaluated.is_a_rate.clone(),
+            default_value: evaluated.default_value,
+            min_value: evaluated.min_value,
+            max_value: evaluated.max_value,
+            event_timeline: evaluated.event_timeline.clone(),
+            last_event: evaluated.last_event.clone(),
+            set_value_ramp_target: evaluated.set_value_ramp_target,
+            buffer: Vec::with_capacity(self.render_quantum_size),
+        };
+
+        // clamped as in `mix_to_output`
+        let clamp = |value: f32| {
+            if value.is_nan() {
+                evaluated.default_value
+            } else {
+                value.clamp(evaluated.min_value, evaluated.max_value)
+            }
+        };
+
+        let sample_rate = f64::from(sample_rate);
+        let mut block_frame = (self.frame as f64 / self.sample_rate * sample_rate).round() as usize;
+
+        // the elapsed events are not kept, hold the value they left the param at
+        let held = clamp(evaluated.intrisic_value);
+        let mut values = vec![held; block_frame.saturating_sub(first_frame).min(count)];
+        let mut events = self.pending.iter().peekable();
+
+        while values.len() < count {
+            let block_time = block_frame as f64 / sample_rate;
+
+            // the render thread picks up the events at the first render quantum after they
+            // were sent
+            while let Some((_, event)) =
+                events.next_if(|(sent, _)| *sent as f64 / self.sample_rate <= block_time)
+            {
+                sender.send(event.clone()).unwrap();
+            }
+
+            let buffer = processor.compute_intrisic_values(
+                block_time,
+                1. / sample_rate,
+                self.render_quantum_size,
+            );
+
+            if block_frame + self.render_quantum_size > first_frame {
+                let start = first_frame.saturating_sub(block_frame);
+                let remaining = count - values.len();
+                values.extend(
+                    (start..self.render_quantum_size)
+                        .take(remaining)
+                        .map(|i| clamp(buffer[if buffer.len() == 1 { 0 } else { i }])),
+                );
+            }
+
+            block_frame += self.render_quantum_size;
+        }
+
+        values
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct AudioParamProcessor {
     intrisic_value: f32,
@@ -588,6 +1022,8 @@ pub(crate) struct AudioParamProcessor {
     max_value: f32,
     event_timeline: AudioParamEventTimeline,
     last_event: Option<AudioParamEvent>,
+    /// Value given to a ramped `set_value`, while its ramp is in the timeline
+    set_value_ramp_target: Option<f32>,
     buffer: Vec<f32>,
 }
 
@@ -604,7 +1040,7 @@ impl AudioProcessor for AudioParamProcessor {
         let input = &inputs[0]; // single input mode
         let output = &mut outputs[0];
 
-        self.compute_intrisic_values(scope.current_time, period, RENDER_QUANTUM_SIZE);
+        self.compute_intrisic_values(scope.current_time, period, scope.render_quantum_size);
         self.mix_to_output(input, output);
 
         true // has intrinsic value
@@ -616,7 +1052,7 @@ impl AudioParamProcessor {
     // for the tests should be done here
     fn compute_intrisic_values(&mut self, block_time: f64, dt: f64, count: usize) -> &[f32] {
         if !self.receiver.is_empty() {
-            self.handle_incoming_events();
+            self.handle_incoming_events(block_time);
         }
 
         self.compute_buffer(block_time, dt, count);
@@ -626,7 +1062,7 @@ impl AudioParamProcessor {
 
     fn mix_to_output(&mut self, input: &AudioRenderQuantum, output: &mut AudioRenderQuantum) {
         #[cfg(test)]
-        assert!(self.buffer.len() == 1 || self.buffer.len() == RENDER_QUANTUM_SIZE);
+        assert!(self.buffer.len() == 1 || self.buffer.len() == output.channel_data(0).len());
 
         if self.buffer.len() == 1 && input.is_silent() {
             let mut value = self.buffer[0];
@@ -636,6 +1072,8 @@ impl AudioParamProcessor {
             }
 
             output.set_single_valued(true);
+            // drop the channels of a previously connected multi-channel input
+            output.set_number_of_channels(1);
 
             let output_channel = output.channel_data_mut(0);
             output_channel[0] = value.clamp(self.min_value, self.max_value);
@@ -648,19 +1086,21 @@ impl AudioParamProcessor {
             *output = input.clone();
             output.set_single_valued(false);
 
-            output
-                .channel_data_mut(0)
-                .iter_mut()
-                .zip(self.buffer.iter().cycle())
-                .for_each(|(o, p)| {
-                    *o += p;
-
-                    if o.is_nan() {
-                        *o = self.default_value;
-                    }
+            // the input is mono, unless the owner of the param accepts multi-channel inputs
+            output.channels_mut().iter_mut().for_each(|channel| {
+                channel
+                    .iter_mut()
+                    .zip(self.buffer.iter().cycle())
+                    .for_each(|(o, p)| {
+                        *o += p;
 
-                    *o = o.clamp(self.min_value, self.max_value)
-                });
+                        if o.is_nan() {
+                            *o = self.default_value;
+                        }
+
+                        *o = o.clamp(self.min_value, self.max_value)
+                    });
+            });
         }
     }
 
@@ -688,8 +1128,9 @@ impl AudioParamProcessor {
         ratio: f32, // end_value / start_value
         time: f64,
     ) -> f32 {
+        // computed in double precision, as the segments filled with `extend_geometric`
         let phase = (time - start_time) / duration;
-        start_value * ratio.powf(phase as f32)
+        (start_value as f64 * (ratio as f64).powf(phase)) as f32
     }
 
     // 𝑣(𝑡) = 𝑉1 + (𝑉0 − 𝑉1) * 𝑒^−((𝑡−𝑇0) / 𝜏)
@@ -726,7 +1167,7 @@ impl AudioParamProcessor {
         }
     }
 
-    fn handle_incoming_events(&mut self) {
+    fn handle_incoming_events(&mut self, block_time: f64) {
         // cf. https://www.w3.org/TR/webaudio/#computation-of-value
         // 1. paramIntrinsicValue will be calculated at each time, which is either the
         // value set directly to the value attribute, or, if there are any automation
@@ -735,7 +1176,65 @@ impl AudioParamProcessor {
         // then the paramIntrinsicValue value will remain unchanged and stay at its
         // previous value until either the value attribute is directly set, or
         // automation events are added for the time range.
-        for event in self.receiver.try_iter() {
+        for mut event in self.receiver.try_iter() {
+            // ramped `set_value` calls, see `AudioParam::set_value_ramp_time`
+            let ramp_time = match event.event_type {
+                AudioParamEventType::SetValue => event.duration.take(),
+                _ => None,
+            };
+
+            // a pending ramp of `set_value` is the only content of the timeline, it is replaced
+            // by another ramp starting from the current value, any other event ends it at the
+            // value given to `set_value`
+            if let Some(target) = self.set_value_ramp_target.take() {
+                if !self.event_timeline.is_empty() {
+                    self.event_timeline.retain(|_| false);
+
+                    if ramp_time.is_none() {
+                        self.intrisic_value = target;
+                        self.last_event = Some(AudioParamEvent {
+                            event_type: AudioParamEventType::SetValueAtTime,
+                            value: target,
+                            time: block_time,
+                            time_constant: None,
+                            cancel_time: None,
+                            duration: None,
+                            values: None,
+                        });
+                    }
+                }
+            }
+
+            // ramp linearly from the current value, unless the param is being automated
+            if let Some(ramp_time) = ramp_time {
+                if self.event_timeline.is_empty() {
+                    let start_event = AudioParamEvent {
+                        event_type: AudioParamEventType::SetValue,
+                        value: self.intrisic_value,
+                        // replaced by the block timestamp during event processing
+                        time: 0.,
+                        time_constant: None,
+                        cancel_time: None,
+                        duration: None,
+                        values: None,
+                    };
+                    let ramp_event = AudioParamEvent {
+                        event_type: AudioParamEventType::LinearRampToValueAtTime,
+                        value: event.value,
+                        time: block_time + ramp_time,
+                        time_constant: None,
+                        cancel_time: None,
+                        duration: None,
+                        values: None,
+                    };
+
+                    self.event_timeline.push(start_event);
+                    self.event_timeline.push(ramp_event);
+                    self.set_value_ramp_target = Some(event.value);
+                    continue;
+                }
+            }
+
             // handle CancelScheduledValues events
             // cf. https://www.w3.org/TR/webaudio/#dom-audioparam-cancelscheduledvalues
             if event.event_type == AudioParamEventType::CancelScheduledValues {
@@ -1218,22 +1717,19 @@ impl AudioParamProcessor {
                                     let end_index_clipped = end_index.min(count);
 
                                     if end_index_clipped > start_index {
-                                        let mut time = (start_index as f64).mul_add(dt, block_time);
-
-                                        for _ in start_index..end_index_clipped {
-                                            let value = self.compute_exponential_ramp_sample(
-                                                start_time,
-                                                duration,
-                                                start_value,
-                                                ratio,
-                                                time,
-                                            );
-
-                                            self.buffer.push(value);
-                                            self.intrisic_value = value;
-
-                                            time += dt;
-                                        }
+                                        let time = (start_index as f64).mul_add(dt, block_time);
+                                        let phase = (time - start_time) / duration;
+                                        let value = start_value as f64 * (ratio as f64).powf(phase);
+                                        // successive samples differ by a constant factor
+                                        let factor = (ratio as f64).powf(dt / duration);
+
+                                        self.intrisic_value = extend_geometric(
+                                            &mut self.buffer,
+                                            0.,
+                                            value,
+                                            factor,
+                                            end_index_clipped - start_index,
+                                        );
                                     }
                                 }
 
@@ -1285,6 +1781,33 @@ impl AudioParamProcessor {
                         // inserted in the timeline. This could be done at k-rate.
                         // Note that Chrome has such strategy, cf. `HasSetTargetConverged`
                         AudioParamEventType::SetTargetAtTime => {
+                            // [spec] If the preceding event of a ramp is a SetTarget
+                            // event that has not started, 𝑇0 is the start time of
+                            // the event, and 𝑉0 is the value just before the
+                            // SetTarget event starts.
+                            // i.e. the SetTarget behaves as a SetValue of the
+                            // current value at its start time
+                            let next_is_ramp = self.event_timeline.next().is_some_and(|next| {
+                                next.event_type == AudioParamEventType::LinearRampToValueAtTime
+                                    || next.event_type
+                                        == AudioParamEventType::ExponentialRampToValueAtTime
+                            });
+
+                            if next_is_ramp && event.time > block_time {
+                                let event = AudioParamEvent {
+                                    event_type: AudioParamEventType::SetValueAtTime,
+                                    time: event.time,
+                                    value: self.intrisic_value,
+                                    time_constant: None,
+                                    cancel_time: None,
+                                    duration: None,
+                                    values: None,
+                                };
+
+                                self.event_timeline.replace_peek(event);
+                                continue;
+                            }
+
                             let mut end_time = next_block_time;
                             let mut ended = false;
 
@@ -1303,6 +1826,7 @@ impl AudioParamProcessor {
                                         // is the value just before the SetTarget event
                                         // starts. In this case, the LinearRampToValue
                                         // event effectively replaces the SetTarget event.
+                                        // (not started case is handled above)
                                         // If the SetTarget event has already started,
                                         // 𝑇0 is the current context time, and 𝑉0 is
                                         // the current SetTarget automation value at time 𝑇0.
@@ -1347,30 +1871,37 @@ impl AudioParamProcessor {
                                     ((end_time - block_time).max(0.) / dt).ceil() as usize;
                                 let end_index_clipped = end_index.min(count);
 
-                                if end_index_clipped > start_index {
-                                    let mut time = (start_index as f64).mul_add(dt, block_time);
+                                let mut index = start_index;
+                                let mut time = (start_index as f64).mul_add(dt, block_time);
 
-                                    for _ in start_index..end_index_clipped {
-                                        // check if we have reached start_time
-                                        let value = if time - start_time < 0. {
-                                            self.intrisic_value
-                                        } else {
-                                            self.compute_set_target_sample(
-                                                start_time,
-                                                time_constant,
-                                                end_value,
-                                                diff,
-                                                time,
-                                            )
-                                        };
+                                // hold the current value until start_time is reached
+                                while index < end_index_clipped && time < start_time {
+                                    self.buffer.push(self.intrisic_value);
+                                    index += 1;
+                                    time += dt;
+                                }
 
-                                        self.buffer.push(value);
-                                        self.intrisic_value = value;
-                                        time += dt;
-                                    }
+                                if end_index_clipped > index {
+                                    let exponent = -(time - start_time) / time_constant;
+                                    let value = diff as f64 * exponent.exp();
+                                    // the distance to the target decays by a constant factor
+                                    let factor = (-dt / time_constant).exp();
+
+                                    self.intrisic_value = extend_geometric(
+                                        &mut self.buffer,
+                                        end_value,
+                                        value,
+                                        factor,
+                                        end_index_clipped - index,
+                                    );
                                 }
                             }
 
+                            // the event only starts after this block, hold the current value
+                            if !ended && start_time > next_block_time {
+                                break;
+                            }
+
                             if !ended {
                                 // compute value for `next_block_time` so that `param.value()`
                                 // stays coherent (see. comment in `AudioParam`)
@@ -1534,6 +2065,7 @@ pub(crate) fn audio_param_pair(
     registration: AudioContextRegistration,
 ) -> (AudioParam, AudioParamProcessor) {
     let (sender, receiver) = crossbeam_channel::unbounded();
+    let render_quantum_size = registration.context().render_quantum_size();
     let current_value = Arc::new(AtomicF32::new(opts.default_value));
     let is_a_rate = Arc::new(AtomicBool::new(opts.automation_rate == AutomationRate::A));
 
@@ -1545,7 +2077,9 @@ pub(crate) fn audio_param_pair(
         min_value: opts.min_value,
         max_value: opts.max_value,
         current_value: current_value.clone(),
+        value_ramp_time: Arc::new(AtomicF64::new(0.)),
         sender,
+        channel_config: AUDIO_PARAM_CHANNEL_CONFIG.into(),
     };
 
     let render = AudioParamProcessor {
@@ -1558,18 +2092,46 @@ pub(crate) fn audio_param_pair(
         max_value: opts.max_value,
         event_timeline: AudioParamEventTimeline::new(),
         last_event: None,
-        buffer: Vec::with_capacity(RENDER_QUANTUM_SIZE),
+        set_value_ramp_target: None,
+        buffer: Vec::with_capacity(render_quantum_size),
     };
 
     (param, render)
 }
 
+/// Push `count` values `offset + value * factor^n` to `buffer`, returns the last value pushed
+///
+/// This evaluates exponential curves with a single multiplication per sample, instead of a call
+/// to `powf` or `exp`. The sequence is computed in double precision so the accumulated rounding
+/// error stays far below the precision of the `f32` output.
+#[inline(always)]
+fn extend_geometric(
+    buffer: &mut Vec<f32>,
+    offset: f32,
+    mut value: f64,
+    factor: f64,
+    count: usize,
+) -> f32 {
+    let offset = offset as f64;
+    let mut last = 0.;
+
+    buffer.extend((0..count).map(|_| {
+        last = (offset + value) as f32;
+        value *= factor;
+        last
+    }));
+
+    last
+}
+
 #[cfg(test)]
 mod tests {
     use float_eq::assert_float_eq;
 
     use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::AudioScheduledSourceNode;
     use crate::render::Alloc;
+    use crate::RENDER_QUANTUM_SIZE;
 
     use super::*;
 
@@ -1682,6 +2244,164 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_set_value_ramp_time() {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+        assert_float_eq!(param.value_ramp_time(), 0., abs <= 0.);
+
+        param.set_value_ramp_time(5.);
+        assert_float_eq!(param.value_ramp_time(), 5., abs <= 0.);
+        param.set_value(10.);
+
+        let vs = render.compute_intrisic_values(0., 1., 10);
+        assert_float_eq!(
+            vs,
+            &[0., 2., 4., 6., 8., 10., 10., 10., 10., 10.][..],
+            abs_all <= 0.
+        );
+        let vs = render.compute_intrisic_values(10., 1., 10);
+        assert_float_eq!(vs, &[10.; 1][..], abs_all <= 0.);
+
+        // zero restores the instantaneous behavior
+        param.set_value_ramp_time(0.);
+        param.set_value(-10.);
+        let vs = render.compute_intrisic_values(20., 1., 10);
+        assert_float_eq!(vs, &[-10.; 10][..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_set_value_ramp_time_restart() {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+        param.set_value_ramp_time(10.);
+
+        param.set_value(10.);
+        let vs = render.compute_intrisic_values(0., 1., 5);
+        assert_float_eq!(vs, &[0., 1., 2., 3., 4.][..], abs_all <= 0.);
+
+        // a new ramp starts from the current value
+        param.set_value(0.);
+        let vs = render.compute_intrisic_values(5., 1., 5);
+        assert_float_eq!(vs, &[5., 4.5, 4., 3.5, 3.][..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_set_value_ramp_time_automation() {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+
+        // automation events scheduled during the ramp end it at the target value
+        {
+            let (param, mut render) = audio_param_pair(opts.clone(), context.mock_registration());
+            param.set_value_ramp_time(10.);
+
+            param.set_value(10.);
+            let vs = render.compute_intrisic_values(0., 1., 5);
+            assert_float_eq!(vs, &[0., 1., 2., 3., 4.][..], abs_all <= 0.);
+
+            param.linear_ramp_to_value_at_time(0., 15.);
+            let vs = render.compute_intrisic_values(5., 1., 5);
+            assert_float_eq!(vs, &[10., 9., 8., 7., 6.][..], abs_all <= 1e-6);
+        }
+
+        // set_value is instantaneous while automation events are pending
+        {
+            let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+            param.set_value_ramp_time(10.);
+
+            param.set_value_at_time(5., 20.);
+            param.set_value(10.);
+            let vs = render.compute_intrisic_values(0., 1., 5);
+            assert_float_eq!(vs, &[10.; 5][..], abs_all <= 0.);
+            let vs = render.compute_intrisic_values(20., 1., 5);
+            assert_float_eq!(vs, &[5.; 5][..], abs_all <= 0.);
+        }
+    }
+
+    #[test]
+    fn test_set_value_ramp_time_rendering() {
+        let sample_rate = 48000.;
+        let ramp_frames = 240; // 5ms
+
+        // jump from 0 to 1 at frame 1280
+        let render = |ramp_time: f64| {
+            let mut context = OfflineAudioContext::new(1, 2048, sample_rate);
+            let src = context.create_constant_source();
+            src.offset().set_value(0.);
+            src.offset().set_value_ramp_time(ramp_time);
+            src.connect(&context.destination());
+            src.start();
+
+            context.suspend_at(1280, move |_| {
+                src.offset().set_value(1.);
+            });
+
+            context.start_rendering_sync().get_channel_data(0).to_vec()
+        };
+
+        let instant = render(0.);
+        assert_float_eq!(instant[..1280], [0.; 1280][..], abs_all <= 0.);
+        assert_float_eq!(instant[1280..], [1.; 768][..], abs_all <= 0.);
+
+        let ramped = render(ramp_frames as f64 / sample_rate as f64);
+        assert_float_eq!(ramped[..1280], [0.; 1280][..], abs_all <= 0.);
+        let expected: Vec<f32> = (0..ramp_frames)
+            .map(|i| i as f32 / ramp_frames as f32)
+            .collect();
+        assert_float_eq!(
+            ramped[1280..1280 + ramp_frames],
+            expected[..],
+            abs_all <= 1e-5
+        );
+        assert_float_eq!(ramped[1280 + ramp_frames..], [1.; 528][..], abs_all <= 0.);
+
+        // the largest step between consecutive samples is the slope of the ramp
+        let max_step = |output: &[f32]| {
+            output
+                .windows(2)
+                .map(|w| (w[1] - w[0]).abs())
+                .fold(0., f32::max)
+        };
+        assert_float_eq!(max_step(&instant), 1., abs <= 0.);
+        assert!(max_step(&ramped) <= 1. / ramp_frames as f32 + 1e-5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_set_value_ramp_time_invalid() {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (param, _render) = audio_param_pair(opts, context.mock_registration());
+        param.set_value_ramp_time(-1.);
+    }
+
     #[test]
     fn test_steps_a_rate() {
         let context = OfflineAudioContext::new(1, 0, 48000.);
@@ -1766,6 +2486,141 @@ mod tests {
         assert_float_eq!(vs, &[3.; 1][..], abs_all <= 0.);
     }
 
+    #[test]
+    fn test_automation_history_drops_elapsed_events() {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+        let src = context.create_constant_source();
+        let mut history = AutomationHistory::new(src.offset());
+
+        // a user interface ramping the param at each render quantum
+        for i in 0..10_000_u64 {
+            let event = AudioParamEvent {
+                event_type: AudioParamEventType::LinearRampToValueAtTime,
+                value: i as f32,
+                time: (i * 128 + 64) as f64 / 48000.,
+                time_constant: None,
+                cancel_time: None,
+                duration: None,
+                values: None,
+            };
+            history.record(&event, i * 128);
+        }
+        assert_eq!(history.pending.len(), 10_000);
+
+        // the elapsed events are dropped once the automation is read, the last ramp is still in
+        // progress
+        history.advance(9_999 * 128);
+        assert_eq!(history.pending.len(), 1);
+        assert!(history.processor.event_timeline.is_empty());
+        assert_float_eq!(history.processor.intrisic_value, 9998., abs <= 0.);
+        assert_eq!(history.automation_events().len(), 2);
+    }
+
+    #[test]
+    fn test_automation_history_record_after_long_render() {
+        let sample_rate = 48000.;
+        let context = OfflineAudioContext::new(1, 0, sample_rate);
+        let src = context.create_constant_source();
+        let mut history = AutomationHistory::new(src.offset());
+
+        // a ramp over the whole render, then a `set_value` call after 20 minutes
+        let ramp = AudioParamEvent {
+            event_type: AudioParamEventType::LinearRampToValueAtTime,
+            value: 1.,
+            time: 3600.,
+            time_constant: None,
+            cancel_time: None,
+            duration: None,
+            values: None,
+        };
+        history.record(&ramp, 0);
+        let set_value = AudioParamEvent {
+            event_type: AudioParamEventType::SetValue,
+            value: 0.5,
+            time: 0.,
+            time_constant: None,
+            cancel_time: None,
+            duration: None,
+            values: None,
+        };
+        history.record(&set_value, 20 * 60 * sample_rate as u64);
+
+        // recording does not evaluate the automation, whatever the time elapsed
+        assert_eq!(history.frame, 0);
+        assert_eq!(history.pending.len(), 2);
+    }
+
+    #[test]
+    fn test_compute_values() {
+        let sample_rate = 48_000.;
+        let length = 2_000;
+        let context = OfflineAudioContext::new(1, length, sample_rate);
+
+        let src = context.create_constant_source();
+        src.offset().set_value_at_time(0., 0.);
+        src.offset().linear_ramp_to_value_at_time(1., 0.0101);
+        src.offset().exponential_ramp_to_value_at_time(0.1, 0.02);
+        src.offset().set_target_at_time(2., 0.025, 0.003);
+        src.offset()
+            .set_value_curve_at_time(&[0., 1., 0.5], 0.035, 0.003);
+        src.connect(&context.destination());
+        src.start();
+
+        let values = src.offset().compute_values(0., length, sample_rate);
+        let partial = src.offset().compute_values(0.01, 300, sample_rate);
+        let between = src
+            .offset()
+            .compute_values(0.5 / sample_rate as f64, 10, sample_rate);
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        assert_float_eq!(values[..], output[..], abs_all <= 0.);
+        assert_float_eq!(partial[..], output[480..780], abs_all <= 0.);
+
+        // halfway between the samples of the ramp
+        let expected: Vec<f32> = (0..10).map(|i| (i as f32 + 0.5) / 484.8).collect();
+        assert_float_eq!(between[..], expected[..], abs_all <= 1e-6);
+    }
+
+    #[test]
+    fn test_compute_values_after_elapsed_events() {
+        let sample_rate = 48_000.;
+        let mut context = OfflineAudioContext::new(1, 2048, sample_rate);
+
+        let src = context.create_constant_source();
+        src.offset().set_value_at_time(0., 0.);
+        src.offset().linear_ramp_to_value_at_time(1., 0.01);
+        src.offset().set_target_at_time(0.2, 0.015, 0.005);
+        src.connect(&context.destination());
+        src.start();
+
+        let values = Arc::new(std::sync::Mutex::new(vec![]));
+        let computed = values.clone();
+        context.suspend_at(512, move |context| {
+            // replayed from the current frame, in the middle of the ramp
+            let start_time = context.current_time();
+            *computed.lock().unwrap() = src.offset().compute_values(start_time, 1536, sample_rate);
+
+            // the elapsed events hold the value of the current frame
+            let past = src.offset().compute_values(0., 4, sample_rate);
+            let held = src.offset().compute_values(start_time, 1, sample_rate);
+            assert_float_eq!(past[..], [held[0]; 4][..], abs_all <= 0.);
+        });
+
+        let output = context.start_rendering_sync();
+        let values = values.lock().unwrap();
+        assert_float_eq!(values[..], output.get_channel_data(0)[512..], abs_all <= 0.);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_compute_values_invalid_sample_rate() {
+        let context = OfflineAudioContext::new(1, 0, 48_000.);
+        let src = context.create_constant_source();
+        src.offset().compute_values(0., 128, 0.);
+    }
+
     #[test]
     fn test_linear_ramp_arate() {
         let context = OfflineAudioContext::new(1, 0, 48000.);
@@ -2012,7 +2867,7 @@ mod tests {
         let end: f32 = 1.;
 
         for t in 0..10 {
-            let value = start * (end / start).powf(t as f32 / 10.);
+            let value = (start as f64 * ((end / start) as f64).powf(t as f64 / 10.)) as f32;
             res.push(value);
         }
 
@@ -2046,7 +2901,7 @@ mod tests {
         // set_value is implicit here as this is the first value of the computed ramp
         // exponential ramp (v(t) = v1*(v2/v1)^((t-t1)/(t2-t1)))
         for t in 0..10 {
-            let value = start * (end / start).powf(t as f32 / 10.);
+            let value = (start as f64 * ((end / start) as f64).powf(t as f64 / 10.)) as f32;
             res.push(value);
         }
         // fill remaining with target value
@@ -2150,7 +3005,7 @@ mod tests {
         // set_value is implicit here as this is the first value of the computed ramp
         // exponential ramp (v(t) = v1*(v2/v1)^((t-t1)/(t2-t1)))
         for t in 0..10 {
-            let value = start * (end / start).powf(t as f32 / 10.);
+            let value = (start as f64 * ((end / start) as f64).powf(t as f64 / 10.)) as f32;
             res.push(value);
         }
         // fill remaining with target value
@@ -2251,6 +3106,122 @@ mod tests {
         assert_float_eq!(vs, &res[10..], abs_all <= 1e-7);
     }
 
+    #[test]
+    fn test_dense_automation_segments() {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+        const PERIOD: usize = 37; // several events per render quantum
+        const EVENTS: usize = 13;
+
+        // chained exponential ramps
+        {
+            let opts = AudioParamDescriptor {
+                automation_rate: AutomationRate::A,
+                default_value: 0.,
+                min_value: -10.,
+                max_value: 10.,
+            };
+            let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+            let value = |k: usize| [0.5, 2.][k % 2];
+            param.set_value_at_time(value(0), 0.);
+            for k in 1..=EVENTS {
+                param.exponential_ramp_to_value_at_time(value(k), (k * PERIOD) as f64);
+            }
+
+            // naive evaluation of the automation at each sample
+            let expected: Vec<f32> = (0..4 * RENDER_QUANTUM_SIZE)
+                .map(|t| {
+                    let k = t / PERIOD;
+                    if k >= EVENTS {
+                        return value(EVENTS);
+                    }
+                    let phase = (t - k * PERIOD) as f64 / PERIOD as f64;
+                    let ratio = value(k + 1) as f64 / value(k) as f64;
+                    (value(k) as f64 * ratio.powf(phase)) as f32
+                })
+                .collect();
+
+            for (block, expected) in expected.chunks(RENDER_QUANTUM_SIZE).enumerate() {
+                let block_time = (block * RENDER_QUANTUM_SIZE) as f64;
+                let vs = render.compute_intrisic_values(block_time, 1., RENDER_QUANTUM_SIZE);
+                assert_float_eq!(vs, expected, rmax_all <= 1e-6);
+            }
+        }
+
+        // chained set target
+        {
+            let opts = AudioParamDescriptor {
+                automation_rate: AutomationRate::A,
+                default_value: 0.,
+                min_value: -10.,
+                max_value: 10.,
+            };
+            let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+            let target = |k: usize| [1_f32, -1.][k % 2];
+            let time_constant = 10.;
+            for k in 0..EVENTS {
+                param.set_target_at_time(target(k), (k * PERIOD) as f64, time_constant);
+            }
+
+            // naive evaluation of the automation at each sample
+            let value = |k: usize, start_value: f64, elapsed: f64| {
+                let target = target(k) as f64;
+                target + (start_value - target) * (-elapsed / time_constant).exp()
+            };
+            let mut start_value = 0.;
+            let expected: Vec<f32> = (0..4 * RENDER_QUANTUM_SIZE)
+                .map(|t| {
+                    let k = (t / PERIOD).min(EVENTS - 1);
+                    let current = value(k, start_value, (t - k * PERIOD) as f64);
+                    // the next event starts from the value reached at its start time
+                    if t + 1 == (k + 1) * PERIOD && k + 1 < EVENTS {
+                        start_value = value(k, start_value, PERIOD as f64);
+                    }
+                    current as f32
+                })
+                .collect();
+
+            for (block, expected) in expected.chunks(RENDER_QUANTUM_SIZE).enumerate() {
+                let block_time = (block * RENDER_QUANTUM_SIZE) as f64;
+                let vs = render.compute_intrisic_values(block_time, 1., RENDER_QUANTUM_SIZE);
+                assert_float_eq!(vs, expected, abs_all <= 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn test_segment_fill_matches_per_sample_evaluation() {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: -10.,
+            max_value: 10.,
+        };
+        let (_param, render) = audio_param_pair(opts, context.mock_registration());
+
+        let (start_value, ratio, duration) = (0.001_f32, 1000_f32, 1000.);
+
+        let per_sample: Vec<f32> = (0..RENDER_QUANTUM_SIZE)
+            .map(|t| {
+                render.compute_exponential_ramp_sample(0., duration, start_value, ratio, t as f64)
+            })
+            .collect();
+
+        let mut segmented = Vec::with_capacity(RENDER_QUANTUM_SIZE);
+        let factor = (ratio as f64).powf(1. / duration);
+        extend_geometric(
+            &mut segmented,
+            0.,
+            start_value as f64,
+            factor,
+            RENDER_QUANTUM_SIZE,
+        );
+
+        assert_float_eq!(per_sample[..], segmented[..], rmax_all <= 1e-6);
+    }
+
     #[test]
     fn test_set_target_at_time_a_rate() {
         let context = OfflineAudioContext::new(1, 0, 48000.);
@@ -2329,7 +3300,8 @@ mod tests {
 
             let mut res = Vec::<f32>::with_capacity(10);
             for t in 0..10 {
-                let val = v1 + (v0 - v1) * (-1. * ((t as f64 - t0) / time_constant)).exp() as f32;
+                let val = (v1 as f64 + (v0 - v1) as f64 * (-(t as f64 - t0) / time_constant).exp())
+                    as f32;
                 res.push(val);
             }
             // start_time is 1.
@@ -2486,6 +3458,32 @@ mod tests {
         assert_float_eq!(vs[5], 1., abs <= 0.);
     }
 
+    #[test]
+    fn test_set_target_at_time_waits_for_start_time_multiple_blocks() {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: 0.,
+            min_value: 0.,
+            max_value: 2.,
+        };
+        let (param, mut render) = audio_param_pair(opts, context.mock_registration());
+
+        param.set_value_at_time(1., 0.);
+        param.set_target_at_time(0., 15., 1.);
+
+        // the value is held in the blocks before the start time
+        let vs = render.compute_intrisic_values(0., 1., 10);
+        assert_float_eq!(vs, &[1.; 10][..], abs_all <= 0.);
+
+        let vs = render.compute_intrisic_values(10., 1., 10);
+        let mut res = [1.; 10];
+        for (t, v) in res.iter_mut().enumerate().skip(5) {
+            *v = (5. - t as f64).exp() as f32;
+        }
+        assert_float_eq!(vs, &res[..], abs_all <= 0.);
+    }
+
     #[test]
     fn test_set_target_at_time_a_rate_followed_by_ramp() {
         let context = OfflineAudioContext::new(1, 0, 48000.);
@@ -2510,7 +3508,8 @@ mod tests {
 
             for t in 0..11 {
                 // we compute the 10th elements as it will be the start value of the ramp
-                let val = v1 + (v0 - v1) * (-1. * ((t as f64 - t0) / time_constant)).exp() as f32;
+                let val = (v1 as f64 + (v0 - v1) as f64 * (-(t as f64 - t0) / time_constant).exp())
+                    as f32;
                 res.push(val);
             }
 
@@ -2876,7 +3875,7 @@ mod tests {
             let end: f32 = 1.;
 
             for t in 0..6 {
-                let value = start * (end / start).powf(t as f32 / 10.);
+                let value = (start as f64 * ((end / start) as f64).powf(t as f64 / 10.)) as f32;
                 res.push(value);
             }
 
@@ -2909,11 +3908,11 @@ mod tests {
             let end: f32 = 1.;
 
             for t in 0..5 {
-                let value = start * (end / start).powf(t as f32 / 10.);
+                let value = (start as f64 * ((end / start) as f64).powf(t as f64 / 10.)) as f32;
                 res.push(value);
             }
 
-            let hold_value = start * (end / start).powf(4.5 / 10.);
+            let hold_value = (start as f64 * ((end / start) as f64).powf(4.5 / 10.)) as f32;
             res.resize(10, hold_value);
 
             let vs = render.compute_intrisic_values(0., 1., 10);
@@ -3323,4 +4322,478 @@ mod tests {
 
         assert_float_eq!(output.channel_data(0)[..], &expected[..], abs_all <= 0.);
     }
+
+    // Canonical evaluation of a list of automation events, following the
+    // "Computation of value" rules of the spec rather than the incremental
+    // strategy of `compute_buffer`. It is used as a reference to check that
+    // the rendered values follow the spec's piecewise behavior.
+    //
+    // All events are supposed to be scheduled before rendering starts, i.e.
+    // at time 0, which is also the start time of a ramp with no preceding event.
+    // cf. https://www.w3.org/TR/webaudio/#computation-of-value
+    //
+    // It is deliberately kept test-only: it allocates and sorts the whole event
+    // list on each call, which is not acceptable on the render thread, and it
+    // cannot handle events scheduled while rendering (the start time of a ramp
+    // then depends on when it was received), nor cancellations. The renderer
+    // must stay incremental, so there is no non-test caller for it.
+    fn evaluate_event_list(intrisic_value: f32, events: &[AudioParamEvent], time: f64) -> f32 {
+        // stable sort: events at the same time are applied in insertion order
+        let mut sorted: Vec<&AudioParamEvent> = events.iter().collect();
+        sorted.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+
+        // time and value at which the previous event ended
+        let mut prev_time = 0.;
+        let mut prev_value = intrisic_value;
+
+        for (index, event) in sorted.iter().enumerate() {
+            let next = sorted.get(index + 1);
+
+            match event.event_type {
+                AudioParamEventType::SetValueAtTime => {
+                    if time < event.time {
+                        return prev_value;
+                    }
+                }
+                // 𝑣(𝑡) = 𝑉0 + (𝑉1−𝑉0) * ((𝑡−𝑇0) / (𝑇1−𝑇0))
+                AudioParamEventType::LinearRampToValueAtTime => {
+                    if time < event.time {
+                        let phase = (time - prev_time) / (event.time - prev_time);
+                        return prev_value + (event.value - prev_value) * phase as f32;
+                    }
+                }
+                // 𝑣(𝑡) = 𝑉0 * (𝑉1/𝑉0)^((𝑡−𝑇0) / (𝑇1−𝑇0))
+                AudioParamEventType::ExponentialRampToValueAtTime => {
+                    if time < event.time {
+                        // If 𝑉0 and 𝑉1 have opposite signs or if 𝑉0 is zero,
+                        // then 𝑣(𝑡)=𝑉0 for 𝑇0≤𝑡<𝑇1.
+                        if prev_value == 0. || prev_value * event.value < 0. {
+                            return prev_value;
+                        }
+
+                        let phase = (time - prev_time) / (event.time - prev_time);
+                        let ratio = (event.value / prev_value) as f64;
+                        return (prev_value as f64 * ratio.powf(phase)) as f32;
+                    }
+                }
+                // 𝑣(𝑡) = 𝑉1 + (𝑉0 − 𝑉1) * 𝑒^−((𝑡−𝑇0) / 𝜏)
+                AudioParamEventType::SetTargetAtTime => {
+                    if time < event.time {
+                        return prev_value;
+                    }
+
+                    let time_constant = event.time_constant.unwrap();
+                    let start_value = prev_value;
+                    let target = |t: f64| {
+                        let exponent = -(t - event.time) / time_constant;
+                        event.value + (start_value - event.value) * exponent.exp() as f32
+                    };
+
+                    let end_time = match next {
+                        // A ramp following a SetTarget that has not started when
+                        // the ramp is scheduled starts from the SetTarget start
+                        // time and from the value just before it, i.e. the ramp
+                        // replaces the SetTarget.
+                        Some(next)
+                            if next.event_type == AudioParamEventType::LinearRampToValueAtTime
+                                || next.event_type
+                                    == AudioParamEventType::ExponentialRampToValueAtTime =>
+                        {
+                            event.time
+                        }
+                        Some(next) => next.time,
+                        None => f64::INFINITY,
+                    };
+
+                    if time < end_time {
+                        return target(time);
+                    }
+
+                    prev_time = end_time;
+                    prev_value = target(end_time);
+                    continue;
+                }
+                // 𝑣(𝑡) interpolates linearly between 𝑉[𝑘] and 𝑉[𝑘+1]
+                AudioParamEventType::SetValueCurveAtTime => {
+                    if time < event.time {
+                        return prev_value;
+                    }
+
+                    let values = event.values.as_ref().unwrap();
+                    let duration = event.duration.unwrap();
+                    let end_time = event.time + duration;
+
+                    if time < end_time {
+                        let position = (values.len() - 1) as f64 * (time - event.time) / duration;
+                        let k = position as usize;
+                        let phase = (position - position.floor()) as f32;
+                        return values[k] + (values[k + 1] - values[k]) * phase;
+                    }
+
+                    prev_time = end_time;
+                    prev_value = values[values.len() - 1];
+                    continue;
+                }
+                _ => panic!(
+                    "AudioParamEvent {:?} is not an automation event",
+                    event.event_type
+                ),
+            }
+
+            prev_time = event.time;
+            prev_value = event.value;
+        }
+
+        prev_value
+    }
+
+    // Apply the same automation to a renderer and to the reference evaluator,
+    // check that the rendered values (in blocks of 10 frames, with a frame
+    // duration of 1 second) match the reference, and that the reference
+    // matches the expected values
+    fn assert_event_list_conformance<F>(intrisic_value: f32, automation: F, expected: &[f32])
+    where
+        F: Fn(&AudioParam),
+    {
+        let context = OfflineAudioContext::new(1, 0, 48000.);
+
+        let opts = AudioParamDescriptor {
+            automation_rate: AutomationRate::A,
+            default_value: intrisic_value,
+            min_value: -100.,
+            max_value: 100.,
+        };
+        let (param, mut render) = audio_param_pair(opts.clone(), context.mock_registration());
+        automation(&param);
+
+        // capture the events sent by the control side for the evaluator
+        let (capture, capture_render) = audio_param_pair(opts, context.mock_registration());
+        automation(&capture);
+        let events: Vec<AudioParamEvent> = capture_render.receiver.try_iter().collect();
+
+        let reference: Vec<f32> = (0..expected.len())
+            .map(|frame| evaluate_event_list(intrisic_value, &events, frame as f64))
+            .collect();
+
+        let mut rendered: Vec<f32> = Vec::with_capacity(expected.len());
+        for block_time in (0..expected.len()).step_by(10) {
+            let vs = render.compute_intrisic_values(block_time as f64, 1., 10);
+            // constant blocks are returned as a single value
+            rendered.extend(vs.iter().cycle().take(10));
+        }
+
+        assert_float_eq!(reference[..], expected[..], abs_all <= 1e-5);
+        assert_float_eq!(rendered[..], reference[..], abs_all <= 1e-5);
+    }
+
+    #[test]
+    fn test_event_list_set_value_at_time() {
+        let mut expected = [0.; 30];
+        expected[5..].fill(1.);
+        expected[12..].fill(2.);
+
+        assert_event_list_conformance(
+            0.,
+            |param| {
+                param.set_value_at_time(1., 5.);
+                param.set_value_at_time(2., 12.);
+            },
+            &expected,
+        );
+    }
+
+    #[test]
+    fn test_event_list_out_of_order() {
+        let mut expected = [0.; 30];
+        expected[5..].fill(1.);
+        expected[12..].fill(2.);
+        expected[25..].fill(3.);
+
+        // scheduled in reverse order
+        assert_event_list_conformance(
+            0.,
+            |param| {
+                param.set_value_at_time(3., 25.);
+                param.set_value_at_time(2., 12.);
+                param.set_value_at_time(1., 5.);
+            },
+            &expected,
+        );
+
+        // ramp scheduled before the event it starts from
+        let expected: Vec<f32> = (0..30)
+            .map(|i| match i {
+                0..=9 => 0.,
+                10..=19 => 10. + (i - 10) as f32,
+                _ => 20.,
+            })
+            .collect();
+
+        assert_event_list_conformance(
+            0.,
+            |param| {
+                param.linear_ramp_to_value_at_time(20., 20.);
+                param.set_value_at_time(10., 10.);
+            },
+            &expected,
+        );
+    }
+
+    #[test]
+    fn test_event_list_same_time() {
+        // the last scheduled event wins
+        let mut expected = [0.; 20];
+        expected[5..].fill(2.);
+
+        assert_event_list_conformance(
+            0.,
+            |param| {
+                param.set_value_at_time(1., 5.);
+                param.set_value_at_time(2., 5.);
+            },
+            &expected,
+        );
+
+        // a ramp ending at the time of a set value starts from the previous event
+        // and is overridden by the set value
+        let expected: Vec<f32> = (0..30)
+            .map(|i| match i {
+                0..=9 => i as f32,
+                _ => 5.,
+            })
+            .collect();
+
+        assert_event_list_conformance(
+            0.,
+            |param| {
+                param.set_value_at_time(0., 0.);
+                param.linear_ramp_to_value_at_time(10., 10.);
+                param.set_value_at_time(5., 10.);
+            },
+            &expected,
+        );
+
+        // a set value followed by a ramp at the same time, the ramp has a zero
+        // duration and directly jumps to its end value
+        let mut expected = [0.; 20];
+        expected[10..].fill(8.);
+
+        assert_event_list_conformance(
+            0.,
+            |param| {
+                param.set_value_at_time(4., 10.);
+                param.linear_ramp_to_value_at_time(8., 10.);
+            },
+            &expected,
+        );
+    }
+
+    #[test]
+    fn test_event_list_ramp_without_preceding_event() {
+        // the linear ramp starts at time 0 from the intrinsic value
+        let expected: Vec<f32> = (0..30)
+            .map(|i| if i < 20 { 2. + i as f32 * 0.5 } else { 12. })
+            .collect();
+
+        assert_event_list_conformance(
+            2.,
+            |param| {
+                param.linear_ramp_to_value_at_time(12., 20.);
+            },
+            &expected,
+        );
+
+        // same for the exponential ramp
+        let expected: Vec<f32> = (0..30)
+            .map(|i| {
+                if i < 20 {
+                    1. * 16_f32.powf(i as f32 / 20.)
+                } else {
+                    16.
+                }
+            })
+            .collect();
+
+        assert_event_list_conformance(
+            1.,
+            |param| {
+                param.exponential_ramp_to_value_at_time(16., 20.);
+            },
+            &expected,
+        );
+    }
+
+    #[test]
+    fn test_event_list_successive_ramps() {
+        // each ramp starts from the value and time of the previous event
+        let expected: Vec<f32> = (0..40)
+            .map(|i| match i {
+                0..=4 => 0.,
+                5..=14 => (i - 5) as f32,
+                15..=24 => 10. - (i - 15) as f32 * 0.5,
+                25..=34 => 5. * 2_f32.powf((i - 25) as f32 / 10.),
+                _ => 10.,
+            })
+            .collect();
+
+        assert_event_list_conformance(
+            0.,
+            |param| {
+                param.set_value_at_time(0., 5.);
+                param.linear_ramp_to_value_at_time(10., 15.);
+                param.linear_ramp_to_value_at_time(5., 25.);
+                param.exponential_ramp_to_value_at_time(10., 35.);
+            },
+            &expected,
+        );
+    }
+
+    #[test]
+    fn test_event_list_ramp_after_set_value() {
+        // the ramp starts at the set value time, not at time 0
+        let expected: Vec<f32> = (0..30)
+            .map(|i| match i {
+                0..=9 => 1.,
+                10..=19 => 3. + (i - 10) as f32 * 0.2,
+                _ => 5.,
+            })
+            .collect();
+
+        assert_event_list_conformance(
+            1.,
+            |param| {
+                param.set_value_at_time(3., 10.);
+                param.linear_ramp_to_value_at_time(5., 20.);
+            },
+            &expected,
+        );
+    }
+
+    #[test]
+    fn test_event_list_exponential_ramp_from_zero() {
+        // the start value is held until the end of the ramp
+        let mut expected = [0.; 30];
+        expected[15..].fill(4.);
+
+        assert_event_list_conformance(
+            0.,
+            |param| {
+                param.set_value_at_time(0., 0.);
+                param.exponential_ramp_to_value_at_time(4., 15.);
+            },
+            &expected,
+        );
+
+        // same with opposite signs
+        let mut expected = [-1.; 30];
+        expected[15..].fill(4.);
+
+        assert_event_list_conformance(
+            -1.,
+            |param| {
+                param.exponential_ramp_to_value_at_time(4., 15.);
+            },
+            &expected,
+        );
+    }
+
+    #[test]
+    fn test_event_list_set_target() {
+        // set target ends at the time of the next set value
+        let expected: Vec<f32> = (0..30)
+            .map(|i| match i {
+                0..=4 => 0.,
+                5..=14 => 1. - (-(i - 5) as f32 / 4.).exp(),
+                _ => 2.,
+            })
+            .collect();
+
+        assert_event_list_conformance(
+            0.,
+            |param| {
+                param.set_target_at_time(1., 5., 4.);
+                param.set_value_at_time(2., 15.);
+            },
+            &expected,
+        );
+
+        // a ramp following a set target replaces it
+        let expected: Vec<f32> = (0..30)
+            .map(|i| if i < 20 { i as f32 * 0.5 } else { 10. })
+            .collect();
+
+        assert_event_list_conformance(
+            0.,
+            |param| {
+                param.set_target_at_time(1., 0., 4.);
+                param.linear_ramp_to_value_at_time(10., 20.);
+            },
+            &expected,
+        );
+
+        // the ramp starts at the set target start time if it has not started yet
+        let expected: Vec<f32> = (0..30)
+            .map(|i| match i {
+                0..=4 => 0.,
+                5..=19 => (i - 5) as f32 * 10. / 15.,
+                _ => 10.,
+            })
+            .collect();
+
+        assert_event_list_conformance(
+            0.,
+            |param| {
+                param.set_target_at_time(1., 5., 4.);
+                param.linear_ramp_to_value_at_time(10., 20.);
+            },
+            &expected,
+        );
+
+        // same, starting after the first render block
+        let expected: Vec<f32> = (0..40)
+            .map(|i| match i {
+                0..=14 => 2.,
+                15..=24 => 2. + (i - 15) as f32 * 0.8,
+                _ => 10.,
+            })
+            .collect();
+
+        assert_event_list_conformance(
+            2.,
+            |param| {
+                param.set_target_at_time(1., 15., 4.);
+                param.linear_ramp_to_value_at_time(10., 25.);
+            },
+            &expected,
+        );
+    }
+
+    #[test]
+    fn test_event_list_set_value_curve() {
+        // curve followed by a ramp starting from its last value
+        let expected: Vec<f32> = (0..40)
+            .map(|i| match i {
+                0..=4 => 0.,
+                5..=14 => {
+                    let position = (i - 5) as f32 * 2. / 10.;
+                    if position < 1. {
+                        position * 4.
+                    } else {
+                        4. - (position - 1.) * 2.
+                    }
+                }
+                15..=24 => 2. + (i - 15) as f32 * 0.2,
+                _ => 4.,
+            })
+            .collect();
+
+        assert_event_list_conformance(
+            0.,
+            |param| {
+                param.set_value_curve_at_time(&[0., 4., 2.], 5., 10.);
+                param.linear_ramp_to_value_at_time(4., 25.);
+            },
+            &expected,
+        );
+    }
 }
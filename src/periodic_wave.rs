@@ -8,6 +8,11 @@ use crate::node::TABLE_LENGTH_USIZE;
 
 /// Options for constructing a [`PeriodicWave`]
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct PeriodicWaveOptions {
     /// The real parameter represents an array of cosine terms of Fourrier series.
     ///
@@ -68,6 +73,11 @@ pub struct PeriodicWaveOptions {
 ///
 /// - `cargo run --release --example oscillators`
 ///
+/// # Serialization
+///
+/// With the `serde` feature, a `PeriodicWave` is (de)serialized as the samples of its wavetable,
+/// since the Fourier coefficients it was built from are not retained.
+///
 // Basically a wrapper around Arc<Vec<f32>>, so `PeriodicWave`s are cheap to clone
 #[derive(Debug, Clone)]
 pub struct PeriodicWave {
@@ -202,12 +212,38 @@ impl PeriodicWave {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for PeriodicWave {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.wavetable.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PeriodicWave {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wavetable = Vec::<f32>::deserialize(deserializer)?;
+
+        // the oscillator renderer relies on the table length
+        if wavetable.len() != TABLE_LENGTH_USIZE {
+            return Err(serde::de::Error::invalid_length(
+                wavetable.len(),
+                &format!("a wavetable of {} samples", TABLE_LENGTH_USIZE).as_str(),
+            ));
+        }
+
+        Ok(Self {
+            wavetable: Arc::new(wavetable),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use float_eq::assert_float_eq;
     use std::f32::consts::PI;
 
-    use crate::context::AudioContext;
+    use crate::context::{AudioContext, OfflineAudioContext};
     use crate::node::{TABLE_LENGTH_F32, TABLE_LENGTH_USIZE};
 
     use super::{PeriodicWave, PeriodicWaveOptions};
@@ -305,6 +341,40 @@ mod tests {
         assert_float_eq!(result[..], expected[..], abs_all <= 1e-6);
     }
 
+    #[test]
+    fn wavetable_generate_phases() {
+        // harmonics with phase angles in all four quadrants, including the non-negative and
+        // negative ranges of atan2
+        let harmonics: [(f32, f32); 5] =
+            [(1., 0.), (0.5, 0.75), (0.25, 2.5), (0.2, -0.5), (0.1, -2.)];
+
+        let mut reals = vec![0.];
+        let mut imags = vec![0.];
+        harmonics.iter().for_each(|(amplitude, phase)| {
+            reals.push(amplitude * phase.sin());
+            imags.push(amplitude * phase.cos());
+        });
+
+        let result = PeriodicWave::generate_wavetable(&reals, &imags, false, TABLE_LENGTH_USIZE);
+
+        // analytic Fourier synthesis: sum of A_k * sin(k * theta + phi_k)
+        let expected: Vec<f32> = (0..TABLE_LENGTH_USIZE)
+            .map(|i| {
+                let theta = 2. * std::f64::consts::PI * i as f64 / TABLE_LENGTH_USIZE as f64;
+                harmonics
+                    .iter()
+                    .enumerate()
+                    .map(|(k, (amplitude, phase))| {
+                        let k = (k + 1) as f64;
+                        *amplitude as f64 * (k * theta + *phase as f64).sin()
+                    })
+                    .sum::<f64>() as f32
+            })
+            .collect();
+
+        assert_float_eq!(result[..], expected[..], abs_all <= 1e-4);
+    }
+
     #[test]
     fn normalize() {
         {
@@ -322,6 +392,32 @@ mod tests {
 
             assert_float_eq!(signal[..], expected[..], abs_all <= 0.);
         }
+
+        {
+            // nothing to normalize, no division by zero
+            let mut signal = [0., 0.];
+            PeriodicWave::normalize(&mut signal);
+            assert_float_eq!(signal[..], [0., 0.][..], abs_all <= 0.);
+        }
+    }
+
+    #[test]
+    fn normalize_negative_peak() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+
+        // -cos(x) - 0.5 * cos(2x) ranges from -1.5 to 0.75
+        let options = PeriodicWaveOptions {
+            real: Some(vec![0., -1., -0.5]),
+            imag: Some(vec![0., 0., 0.]),
+            disable_normalization: false,
+        };
+        let periodic_wave = PeriodicWave::new(&context, options);
+
+        let wavetable = periodic_wave.as_slice();
+        let min = wavetable.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = wavetable.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        assert_float_eq!(min, -1., abs <= 1e-6);
+        assert_float_eq!(max, 0.5, abs <= 1e-6);
     }
 
     #[test]
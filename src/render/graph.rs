@@ -1,13 +1,16 @@
 //! The audio graph topology and render algorithm
+use std::any::Any;
 use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
 
-use crate::context::AudioNodeId;
+use crate::context::{AudioNodeId, DESTINATION_NODE_ID, LISTENER_NODE_ID};
 use rustc_hash::FxHashMap;
 use smallvec::{smallvec, SmallVec};
 
-use super::{Alloc, AudioParamValues, AudioProcessor, AudioRenderQuantum};
-use crate::node::ChannelConfig;
-use crate::render::RenderScope;
+use super::{Alloc, AllocStats, AudioParamValues, AudioProcessor, AudioRenderQuantum};
+use crate::node::{ChannelConfig, ChannelCountMode, ChannelInterpretation};
+use crate::render::{input_mask, RenderScope};
 
 /// Connection between two audio nodes
 struct OutgoingEdge {
@@ -35,15 +38,165 @@ pub struct Node {
     free_when_finished: bool,
     /// Indicates if the node has any incoming connections (for lifecycle management)
     has_inputs_connected: bool,
+    /// Bitmask of the inputs with incoming connections, bit `n` is set for input `n`
+    inputs_connected: u64,
     /// Indicates if the node can act as a cycle breaker (only DelayNode for now)
     cycle_breaker: bool,
+    /// Indicates if the processor is skipped and the input passed through
+    bypassed: bool,
+    /// Indicates if the bypass has been toggled, the next quantum crossfades to the new state
+    bypass_toggled: bool,
+    /// Indicates if the node has been rendered at least once
+    rendered: bool,
+    /// Latest tail time reported by the processor, kept while the node is bypassed
+    tail_time: bool,
+    /// Indicates if the processor has panicked, it is not run anymore and outputs silence
+    failed: bool,
+    /// Indicates if the outputs are muted
+    muted: bool,
+    /// Indicates if the node is soloed
+    soloed: bool,
+    /// Indicates if the node takes part in the mute/solo system, i.e. it has been muted or
+    /// soloed at least once
+    mixer_channel: bool,
+    /// Indicates if the outputs were audible in the latest render quantum, with regard to
+    /// mute and solo
+    audible: bool,
+    /// Indicates if non-finite output values of this node have been replaced
+    sanitized: bool,
 }
 
 impl Node {
-    /// Render an audio quantum
-    fn process(&mut self, params: AudioParamValues, scope: &RenderScope) -> bool {
-        self.processor
-            .process(&self.inputs[..], &mut self.outputs[..], params, scope)
+    /// Render an audio quantum, a panic of the processor is caught and returned as an error
+    fn process(&mut self, params: AudioParamValues, scope: &RenderScope) -> Result<bool, String> {
+        let Self {
+            processor,
+            inputs,
+            outputs,
+            ..
+        } = self;
+
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            processor.process(&inputs[..], &mut outputs[..], params, scope)
+        }))
+        .map_err(|payload| panic_message(&*payload))
+    }
+
+    /// Silence all outputs
+    fn silence(&mut self) {
+        self.outputs
+            .iter_mut()
+            .for_each(AudioRenderQuantum::make_silent);
+    }
+
+    /// Crossfade the first output between the processed signal and the first input over the
+    /// render quantum, towards the current bypass state
+    fn crossfade_bypass(&mut self) {
+        let Self {
+            inputs,
+            outputs,
+            channel_config,
+            bypassed,
+            ..
+        } = self;
+
+        let output = match outputs.first_mut() {
+            Some(output) => output,
+            None => return,
+        };
+
+        let mut dry = match inputs.first() {
+            Some(input) => input.clone(),
+            None => {
+                let mut silence = output.clone();
+                silence.make_silent();
+                silence
+            }
+        };
+        dry.mix(output.number_of_channels(), channel_config.interpretation());
+
+        output
+            .channels_mut()
+            .iter_mut()
+            .zip(dry.channels())
+            .for_each(|(wet, dry)| {
+                let len = wet.len() as f32;
+                wet.iter_mut()
+                    .zip(dry.iter())
+                    .enumerate()
+                    .for_each(|(i, (w, d))| {
+                        let ratio = (i as f32 + 0.5) / len;
+                        let dry_gain = if *bypassed { ratio } else { 1. - ratio };
+                        *w += (d - *w) * dry_gain;
+                    });
+            });
+    }
+
+    /// Silence or fade the outputs according to the mute and solo state
+    ///
+    /// A change of audibility on a node that has been rendered before fades over the render
+    /// quantum to avoid clicks.
+    fn apply_mute(&mut self, solo_active: bool, was_rendered: bool) {
+        let audible = !self.muted && (!solo_active || self.soloed || !self.mixer_channel);
+
+        if audible != self.audible && was_rendered {
+            self.outputs.iter_mut().for_each(|output| {
+                output.channels_mut().iter_mut().for_each(|channel| {
+                    let len = channel.len() as f32;
+                    channel.iter_mut().enumerate().for_each(|(i, v)| {
+                        let ratio = (i as f32 + 0.5) / len;
+                        *v *= if audible { ratio } else { 1. - ratio };
+                    });
+                });
+            });
+        } else if !audible {
+            self.silence();
+        }
+
+        self.audible = audible;
+    }
+
+    /// Replace NaN and infinite output values by zero
+    ///
+    /// Returns `true` if any value was replaced.
+    fn sanitize(&mut self) -> bool {
+        let mut sanitized = false;
+
+        self.outputs
+            .iter_mut()
+            .filter(|output| {
+                // only take the mutable borrow, which may copy shared buffers, when needed
+                output
+                    .channels()
+                    .iter()
+                    .any(|channel| !channel.is_silent() && channel.iter().any(|v| !v.is_finite()))
+            })
+            .for_each(|output| {
+                output.channels_mut().iter_mut().for_each(|channel| {
+                    channel
+                        .iter_mut()
+                        .filter(|v| !v.is_finite())
+                        .for_each(|v| *v = 0.);
+                });
+                sanitized = true;
+            });
+
+        sanitized
+    }
+
+    /// Pass the first input through to the first output, silence the other outputs
+    fn bypass(&mut self) {
+        let Self {
+            inputs, outputs, ..
+        } = self;
+
+        outputs
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, output)| match inputs.first() {
+                Some(input) if i == 0 => *output = input.clone(),
+                _ => output.make_silent(),
+            });
     }
 
     /// Determine if this node is done playing and can be removed from the audio graph
@@ -71,6 +224,17 @@ impl Node {
     }
 }
 
+/// Extract the message of a panic payload
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown error".to_string()
+    }
+}
+
 /// The audio graph
 pub(crate) struct Graph {
     /// Processing Nodes
@@ -88,10 +252,28 @@ pub(crate) struct Graph {
     in_cycle: Vec<AudioNodeId>,
     /// Topological sorting helper
     cycle_breakers: Vec<AudioNodeId>,
+    /// Topological sorting helper, node ids in descending order
+    node_ids: Vec<AudioNodeId>,
+    /// Indicates if any node feeding other nodes reported a tail in the last render quantum
+    active: bool,
+    /// Number of soloed nodes, all other mixer channels are silenced when non-zero
+    soloed_count: usize,
+    /// Indicates if non-finite output values are replaced by zero
+    sanitize_output: bool,
 }
 
+/// Default number of buffers in the render quantum buffer pool
+pub(crate) const DEFAULT_POOL_CAPACITY: usize = 64;
+
 impl Graph {
+    #[cfg(test)]
     pub fn new() -> Self {
+        Self::with_pool_capacity(DEFAULT_POOL_CAPACITY, crate::RENDER_QUANTUM_SIZE)
+    }
+
+    /// Create a new graph with the given number of pre-allocated render quantum buffers, of
+    /// `render_quantum_size` samples
+    pub fn with_pool_capacity(capacity: usize, render_quantum_size: usize) -> Self {
         Graph {
             nodes: FxHashMap::default(),
             ordered: vec![],
@@ -99,10 +281,58 @@ impl Graph {
             marked_temp: vec![],
             in_cycle: vec![],
             cycle_breakers: vec![],
-            alloc: Alloc::with_capacity(64),
+            node_ids: vec![],
+            alloc: Alloc::with_quantum_size(capacity, render_quantum_size),
+            active: false,
+            soloed_count: 0,
+            sanitize_output: false,
         }
     }
 
+    /// Number of samples rendered at once
+    pub fn render_quantum_size(&self) -> usize {
+        self.alloc.render_quantum_size()
+    }
+
+    /// Channel interpretation of the destination node, used to map its output to the hardware
+    pub fn destination_interpretation(&self) -> ChannelInterpretation {
+        self.nodes
+            .get(&DESTINATION_NODE_ID)
+            .unwrap()
+            .borrow()
+            .channel_config
+            .interpretation()
+    }
+
+    /// Check if the last rendered quantum had nodes which may still produce output
+    ///
+    /// AudioParams, the listener and nodes without outgoing audio connections are not taken
+    /// into account: they always report a tail but cannot be heard by themselves.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Number of nodes in the graph, including the destination, listener and `AudioParam`s
+    #[cfg(any(test, feature = "tracing"))]
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Replace NaN and infinite values by zero at the output of every node
+    pub fn set_sanitize_output(&mut self, sanitize_output: bool) {
+        self.sanitize_output = sanitize_output;
+    }
+
+    /// Usage counters of the render quantum buffer pool
+    pub fn alloc_stats(&self) -> Arc<AllocStats> {
+        self.alloc.stats()
+    }
+
+    /// Number of render quantum buffers that were allocated because the pool was exhausted
+    pub fn pool_allocations(&self) -> u64 {
+        self.alloc.allocations()
+    }
+
     pub fn add_node(
         &mut self,
         index: AudioNodeId,
@@ -128,7 +358,18 @@ impl Graph {
                 outgoing_edges: smallvec![],
                 free_when_finished: false,
                 has_inputs_connected: false,
+                inputs_connected: 0,
                 cycle_breaker: false,
+                bypassed: false,
+                bypass_toggled: false,
+                rendered: false,
+                tail_time: false,
+                failed: false,
+                muted: false,
+                soloed: false,
+                mixer_channel: false,
+                audible: true,
+                sanitized: false,
             }),
         );
     }
@@ -189,6 +430,50 @@ impl Graph {
         self.nodes.get_mut(&index).unwrap().get_mut().cycle_breaker = true;
     }
 
+    pub fn set_bypass(&mut self, index: AudioNodeId, bypassed: bool) {
+        // the node may have been dropped already
+        if let Some(node) = self.nodes.get_mut(&index) {
+            let node = node.get_mut();
+            if node.bypassed != bypassed {
+                node.bypassed = bypassed;
+                // toggling back and forth before the next quantum is a no-op
+                node.bypass_toggled = !node.bypass_toggled;
+            }
+        }
+    }
+
+    pub fn set_muted(&mut self, index: AudioNodeId, muted: bool) {
+        // the node may have been dropped already
+        if let Some(node) = self.nodes.get_mut(&index) {
+            let node = node.get_mut();
+            node.muted = muted;
+            node.mixer_channel = true;
+        }
+    }
+
+    pub fn set_soloed(&mut self, index: AudioNodeId, soloed: bool) {
+        // the node may have been dropped already
+        if let Some(node) = self.nodes.get_mut(&index) {
+            let node = node.get_mut();
+            if node.soloed != soloed {
+                node.soloed = soloed;
+                if soloed {
+                    self.soloed_count += 1;
+                } else {
+                    self.soloed_count -= 1;
+                }
+            }
+            node.mixer_channel = true;
+        }
+    }
+
+    pub fn clear_solo(&mut self) {
+        self.nodes
+            .values_mut()
+            .for_each(|node| node.get_mut().soloed = false);
+        self.soloed_count = 0;
+    }
+
     /// Helper function for `order_nodes` - traverse node and outgoing edges
     ///
     /// The return value indicates `cycle_breaker_applied`:
@@ -281,6 +566,9 @@ impl Graph {
     /// - Perform a topological sort of the graph
     /// - Break cycles when possible (if there is a DelayNode present)
     /// - Mute nodes that are still in a cycle
+    /// - Be deterministic: the ordering only depends on the node ids and connections, not on the
+    ///   iteration order of the node map. Nodes rendering into the same input are summed in a
+    ///   stable order, so renders are bit-reproducible
     /// - For performance: no new allocations (reuse Vecs)
     fn order_nodes(&mut self) {
         // For borrowck reasons, we need the `visit` call to be &self.
@@ -290,6 +578,13 @@ impl Graph {
         let mut marked_temp = std::mem::take(&mut self.marked_temp);
         let mut in_cycle = std::mem::take(&mut self.in_cycle);
         let mut cycle_breakers = std::mem::take(&mut self.cycle_breakers);
+        let mut node_ids = std::mem::take(&mut self.node_ids);
+
+        // Visit the nodes in descending id order, the ordering is reversed at the end so sibling
+        // nodes render in ascending id order
+        node_ids.clear();
+        node_ids.extend(self.nodes.keys());
+        node_ids.sort_unstable_by(|a, b| b.cmp(a));
 
         // When a cycle breaker is applied, the graph topology changes and we need to run the
         // ordering again
@@ -307,7 +602,7 @@ impl Graph {
             // since the audio graph could contain legs detached from the destination and those should
             // still be rendered.
             let mut cycle_breaker_applied = false;
-            for &node_id in self.nodes.keys() {
+            for &node_id in &node_ids {
                 cycle_breaker_applied = self.visit(
                     node_id,
                     &mut marked,
@@ -349,6 +644,7 @@ impl Graph {
         self.marked_temp = marked_temp;
         self.in_cycle = in_cycle;
         self.cycle_breakers = cycle_breakers;
+        self.node_ids = node_ids;
     }
 
     /// Render a single audio quantum by traversing the node list
@@ -361,6 +657,14 @@ impl Graph {
         // keep track of end-of-lifecyle nodes
         let mut nodes_dropped = false;
 
+        // keep track of nodes that may still produce output
+        let mut active = false;
+
+        // when any node is soloed, the other mixer channels are silenced
+        let solo_active = self.soloed_count > 0;
+
+        let sanitize_output = self.sanitize_output;
+
         // for borrow-checker reasons, move mutable borrow of nodes out of self
         let nodes = &mut self.nodes;
 
@@ -373,14 +677,70 @@ impl Graph {
             // the case if the node has no inputs connected or the channel count has just changed
             let interpretation = node.channel_config.interpretation();
             let count = node.channel_config.count();
-            node.inputs
-                .iter_mut()
-                .for_each(|i| i.mix(count, interpretation));
+            let count_mode = node.channel_config.count_mode();
+            node.inputs.iter_mut().for_each(|i| {
+                // inputs with more channels are kept as is in max mode
+                let count = match count_mode {
+                    ChannelCountMode::Max => i.number_of_channels().max(count),
+                    _ => count,
+                };
+                i.mix(count, interpretation)
+            });
 
             // let the current node process
             let params = AudioParamValues::from(&*nodes);
             scope.node_id.set(*index);
-            let tail_time = node.process(params, scope);
+            scope.inputs_connected.set(node.inputs_connected);
+            // crossfade over a single quantum when the bypass is toggled on a running node
+            let was_rendered = node.rendered;
+            let crossfade = node.bypass_toggled && was_rendered;
+            node.bypass_toggled = false;
+            node.rendered = true;
+
+            let tail_time = if node.failed {
+                node.silence();
+                false
+            } else if node.bypassed && !crossfade {
+                node.bypass();
+                // the processor does not run, keep its latest tail time so a bypassed node is
+                // not dropped while its processor still has a tail
+                node.tail_time
+            } else {
+                match node.process(params, scope) {
+                    Ok(tail_time) => {
+                        if crossfade {
+                            node.crossfade_bypass();
+                        }
+                        node.tail_time = tail_time;
+                        tail_time
+                    }
+                    Err(message) => {
+                        // do not take the render thread down, mute the node from now on
+                        scope.report_processor_error(message);
+                        node.failed = true;
+                        node.silence();
+                        false
+                    }
+                }
+            };
+
+            node.apply_mute(solo_active, was_rendered);
+
+            // stop NaN and infinite values from spreading to the other nodes, only report the
+            // first occurrence of each node
+            if sanitize_output && node.sanitize() && !node.sanitized {
+                node.sanitized = true;
+                scope.report_output_sanitized();
+            }
+
+            // audio params are connected to the 'hidden' usize::MAX input
+            active |= tail_time
+                && *index != DESTINATION_NODE_ID
+                && *index != LISTENER_NODE_ID
+                && node
+                    .outgoing_edges
+                    .iter()
+                    .any(|edge| edge.other_index != usize::MAX);
 
             // iterate all outgoing edges, lookup these nodes and add to their input
             node.outgoing_edges
@@ -390,6 +750,7 @@ impl Graph {
                 .for_each(|edge| {
                     let mut output_node = nodes.get(&edge.other_id).unwrap().borrow_mut();
                     output_node.has_inputs_connected = true;
+                    output_node.inputs_connected |= input_mask(edge.other_index);
                     let signal = &node.outputs[edge.self_index];
                     let channel_config = &output_node.channel_config.clone();
 
@@ -407,6 +768,7 @@ impl Graph {
 
                 // Reset input state
                 node.has_inputs_connected = false;
+                node.inputs_connected = 0;
             }
 
             drop(node); // release borrow of self.nodes
@@ -415,6 +777,7 @@ impl Graph {
             if can_free {
                 // Node is dropped, remove it from the node list
                 nodes.remove(index);
+                scope.report_node_dropped(*index);
 
                 // And remove it from the ordering after we have processed all nodes
                 nodes_dropped = true;
@@ -422,18 +785,26 @@ impl Graph {
                 // Nodes are only dropped when they do not have incoming connections.
                 // But they may have AudioParams feeding into them, these can de dropped too.
                 nodes.retain(|id, n| {
-                    id.0 < 2 // never drop Listener and Destination node
+                    // never drop Listener and Destination node
+                    let retain = *id == DESTINATION_NODE_ID
+                        || *id == LISTENER_NODE_ID
                         || !n
                             .borrow()
                             .outgoing_edges
                             .iter()
-                            .any(|e| e.other_id == *index)
+                            .any(|e| e.other_id == *index);
+                    if !retain {
+                        scope.report_node_dropped(*id);
+                    }
+                    retain
                 });
             }
         });
 
         // If there were any nodes decomissioned, remove from graph order
         if nodes_dropped {
+            self.soloed_count = nodes.values().filter(|n| n.borrow().soloed).count();
+
             let mut i = 0;
             while i < self.ordered.len() {
                 if !nodes.contains_key(&self.ordered[i]) {
@@ -444,6 +815,8 @@ impl Graph {
             }
         }
 
+        self.active = active;
+
         // Return the output buffer of destination node
         self.nodes
             .get_mut(&AudioNodeId(0))
@@ -457,6 +830,7 @@ impl Graph {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::events::Event;
 
     #[derive(Debug, Clone)]
     struct TestNode {}
@@ -572,6 +946,43 @@ mod tests {
         assert!(pos2 < pos0); // node 1 depends on node 0
     }
 
+    #[test]
+    fn test_deterministic_order() {
+        let node = Box::new(TestNode {});
+
+        // same graph, nodes added in a different order and with a different map capacity
+        let mut graph1 = Graph::new();
+        graph1.add_node(AudioNodeId(0), node.clone(), 1, 1, config());
+        for id in [3, 1, 4, 2] {
+            graph1.add_node(AudioNodeId(id), node.clone(), 1, 1, config());
+            graph1.add_edge((AudioNodeId(id), 0), (AudioNodeId(0), 0));
+        }
+
+        let mut graph2 = Graph::new();
+        graph2.nodes.reserve(100);
+        for id in [2, 4, 1, 3] {
+            graph2.add_node(AudioNodeId(id), node.clone(), 1, 1, config());
+        }
+        graph2.add_node(AudioNodeId(0), node.clone(), 1, 1, config());
+        for id in [1, 2, 3, 4] {
+            graph2.add_edge((AudioNodeId(id), 0), (AudioNodeId(0), 0));
+        }
+
+        graph1.order_nodes();
+        graph2.order_nodes();
+
+        // sources are summed into the root node in ascending id order
+        let expected: Vec<_> = [1, 2, 3, 4, 0].iter().copied().map(AudioNodeId).collect();
+        assert_eq!(graph1.ordered, expected);
+        assert_eq!(graph2.ordered, expected);
+
+        // removing a node does not change the order of the others
+        graph1.nodes.remove(&AudioNodeId(3));
+        graph1.order_nodes();
+        let expected: Vec<_> = [1, 2, 4, 0].iter().copied().map(AudioNodeId).collect();
+        assert_eq!(graph1.ordered, expected);
+    }
+
     #[test]
     fn test_cycle() {
         let mut graph = Graph::new();
@@ -606,4 +1017,257 @@ mod tests {
         // a-cyclic part should be present
         assert!(pos3.unwrap() < pos0.unwrap());
     }
+
+    #[derive(Debug, Clone)]
+    struct ConstantNode {}
+
+    impl AudioProcessor for ConstantNode {
+        fn process(
+            &mut self,
+            _inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            outputs[0].channels_mut()[0].fill(1.);
+            true
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct NanNode {}
+
+    impl AudioProcessor for NanNode {
+        fn process(
+            &mut self,
+            _inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            let channel = &mut outputs[0].channels_mut()[0];
+            channel.fill(1.);
+            channel[0] = f32::NAN;
+            channel[1] = f32::INFINITY;
+            channel[2] = f32::NEG_INFINITY;
+            true
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct HalfNode {}
+
+    impl AudioProcessor for HalfNode {
+        fn process(
+            &mut self,
+            inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            outputs[0] = inputs[0].clone();
+            outputs[0]
+                .channels_mut()
+                .iter_mut()
+                .for_each(|c| c.iter_mut().for_each(|v| *v *= 0.5));
+            false
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct PassthroughNode {}
+
+    impl AudioProcessor for PassthroughNode {
+        fn process(
+            &mut self,
+            inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            outputs[0] = inputs[0].clone();
+            false
+        }
+    }
+
+    /// Source playing for a number of render quanta
+    #[derive(Debug, Clone)]
+    struct FiniteNode {
+        remaining: usize,
+    }
+
+    impl AudioProcessor for FiniteNode {
+        fn process(
+            &mut self,
+            _inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            if self.remaining == 0 {
+                outputs[0].make_silent();
+                return false;
+            }
+            self.remaining -= 1;
+            outputs[0].channels_mut()[0].fill(1.);
+            true
+        }
+    }
+
+    #[test]
+    fn test_free_when_finished() {
+        let mut graph = Graph::new();
+
+        graph.add_node(AudioNodeId(0), Box::new(PassthroughNode {}), 1, 1, config());
+        graph.add_node(AudioNodeId(2), Box::new(HalfNode {}), 1, 1, config());
+        graph.add_node(
+            AudioNodeId(3),
+            Box::new(FiniteNode { remaining: 2 }),
+            0,
+            1,
+            config(),
+        );
+        // finished source whose handle is still alive
+        graph.add_node(
+            AudioNodeId(4),
+            Box::new(FiniteNode { remaining: 0 }),
+            0,
+            1,
+            config(),
+        );
+
+        graph.add_edge((AudioNodeId(3), 0), (AudioNodeId(2), 0));
+        graph.add_edge((AudioNodeId(2), 0), (AudioNodeId(0), 0));
+        graph.add_edge((AudioNodeId(4), 0), (AudioNodeId(0), 0));
+
+        // the control thread drops the handles of the source and of the node it feeds
+        graph.mark_free_when_finished(AudioNodeId(2));
+        graph.mark_free_when_finished(AudioNodeId(3));
+
+        let scope = RenderScope {
+            current_frame: 0,
+            current_time: 0.,
+            sample_rate: 48000.,
+            render_quantum_size: crate::RENDER_QUANTUM_SIZE,
+            event_sender: None,
+            node_id: std::cell::Cell::new(AudioNodeId(0)),
+            inputs_connected: std::cell::Cell::new(0),
+        };
+
+        // the source is still playing
+        for _ in 0..2 {
+            let output = graph.render(&scope);
+            assert!(output.channels()[0].iter().all(|&v| v == 0.5));
+            assert_eq!(graph.node_count(), 4);
+        }
+
+        // the source has finished and is removed, the node it fed was still connected
+        graph.render(&scope);
+        assert_eq!(graph.node_count(), 3);
+
+        // the node it fed has no input and no tail left
+        graph.render(&scope);
+        assert_eq!(graph.node_count(), 2);
+
+        // the finished source with a live handle can still be restarted or reconnected
+        graph.render(&scope);
+        assert_eq!(graph.node_count(), 2);
+    }
+
+    #[test]
+    fn test_bypass_crossfade() {
+        let mut graph = Graph::new();
+
+        graph.add_node(AudioNodeId(0), Box::new(PassthroughNode {}), 1, 1, config());
+        graph.add_node(AudioNodeId(2), Box::new(HalfNode {}), 1, 1, config());
+        graph.add_node(AudioNodeId(3), Box::new(ConstantNode {}), 0, 1, config());
+
+        graph.add_edge((AudioNodeId(3), 0), (AudioNodeId(2), 0));
+        graph.add_edge((AudioNodeId(2), 0), (AudioNodeId(0), 0));
+
+        let scope = RenderScope {
+            current_frame: 0,
+            current_time: 0.,
+            sample_rate: 48000.,
+            render_quantum_size: crate::RENDER_QUANTUM_SIZE,
+            event_sender: None,
+            node_id: std::cell::Cell::new(AudioNodeId(0)),
+            inputs_connected: std::cell::Cell::new(0),
+        };
+
+        let output = graph.render(&scope);
+        assert!(output.channels()[0].iter().all(|&v| v == 0.5));
+
+        // fade in the bypass over a single quantum
+        graph.set_bypass(AudioNodeId(2), true);
+        let output = graph.render(&scope);
+        let channel = &output.channels()[0];
+        assert!(channel[0] > 0.5 && channel[0] < 0.51);
+        assert!(channel[127] > 0.99 && channel[127] < 1.);
+        assert!(channel.windows(2).all(|w| w[0] < w[1]));
+
+        let output = graph.render(&scope);
+        assert!(output.channels()[0].iter().all(|&v| v == 1.));
+
+        // toggling twice before the next quantum does not fade
+        graph.set_bypass(AudioNodeId(2), false);
+        graph.set_bypass(AudioNodeId(2), true);
+        let output = graph.render(&scope);
+        assert!(output.channels()[0].iter().all(|&v| v == 1.));
+
+        // fade out the bypass
+        graph.set_bypass(AudioNodeId(2), false);
+        let output = graph.render(&scope);
+        let channel = &output.channels()[0];
+        assert!(channel[0] < 1. && channel[0] > 0.99);
+        assert!(channel[127] < 0.51 && channel[127] > 0.5);
+        assert!(channel.windows(2).all(|w| w[0] > w[1]));
+
+        let output = graph.render(&scope);
+        assert!(output.channels()[0].iter().all(|&v| v == 0.5));
+    }
+
+    #[test]
+    fn test_sanitize_output() {
+        let render = |sanitize_output| {
+            let mut graph = Graph::new();
+            graph.set_sanitize_output(sanitize_output);
+
+            graph.add_node(AudioNodeId(0), Box::new(PassthroughNode {}), 1, 1, config());
+            graph.add_node(AudioNodeId(2), Box::new(HalfNode {}), 1, 1, config());
+            graph.add_node(AudioNodeId(3), Box::new(NanNode {}), 0, 1, config());
+
+            graph.add_edge((AudioNodeId(3), 0), (AudioNodeId(2), 0));
+            graph.add_edge((AudioNodeId(2), 0), (AudioNodeId(0), 0));
+
+            let (sender, receiver) = crossbeam_channel::unbounded();
+            let scope = RenderScope {
+                current_frame: 0,
+                current_time: 0.,
+                sample_rate: 48000.,
+                render_quantum_size: crate::RENDER_QUANTUM_SIZE,
+                event_sender: Some(sender),
+                node_id: std::cell::Cell::new(AudioNodeId(0)),
+                inputs_connected: std::cell::Cell::new(0),
+            };
+
+            let output = graph.render(&scope);
+            // the sanitized node is only reported once
+            graph.render(&scope);
+            let events: Vec<_> = receiver.try_iter().collect();
+
+            (output.channels()[0].to_vec(), events)
+        };
+
+        let (output, events) = render(false);
+        assert!(output[0].is_nan());
+        assert_eq!(output[1], f32::INFINITY);
+        assert_eq!(output[2], f32::NEG_INFINITY);
+        assert!(events.is_empty());
+
+        let (output, events) = render(true);
+        assert_eq!(&output[..3], &[0.; 3]);
+        assert!(output[3..].iter().all(|&v| v == 0.5));
+        assert_eq!(events, vec![Event::OutputSanitized(AudioNodeId(3))]);
+    }
 }
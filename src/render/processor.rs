@@ -1,15 +1,18 @@
 //! Audio processing code that runs on the audio rendering thread
-use crate::context::{AudioNodeId, AudioParamId};
-use crate::events::Event;
-use crate::RENDER_QUANTUM_SIZE;
-
 use super::{graph::Node, AudioRenderQuantum};
+use crate::context::{AudioNodeId, AudioParamId};
+use crate::events::{Event, ProcessorErrorEvent};
 
 use crossbeam_channel::Sender;
 use rustc_hash::FxHashMap;
 use std::cell::{Cell, RefCell};
 use std::ops::Deref;
 
+/// Bit of the given input index in a bitmask of inputs, the inputs past the 64th share the last bit
+pub(crate) fn input_mask(index: usize) -> u64 {
+    1 << index.min(63)
+}
+
 #[non_exhaustive] // we may want to add user-provided blobs to this later
 /// The execution context of all AudioProcessors in a given AudioContext
 ///
@@ -20,17 +23,48 @@ pub struct RenderScope {
     pub current_frame: u64,
     pub current_time: f64,
     pub sample_rate: f32,
+    /// Number of sample frames of the render quantum, see
+    /// [`BaseAudioContext::render_quantum_size`](crate::context::BaseAudioContext::render_quantum_size)
+    pub render_quantum_size: usize,
 
     pub(crate) node_id: Cell<AudioNodeId>,
     pub(crate) event_sender: Option<Sender<Event>>,
+    /// Bitmask of the inputs of the current node with incoming connections
+    pub(crate) inputs_connected: Cell<u64>,
 }
 
 impl RenderScope {
+    /// Indicates if the input at the given index of the current node has incoming connections
+    ///
+    /// A connected input may still carry silence, e.g. when its source has not started yet.
+    pub(crate) fn is_input_connected(&self, index: usize) -> bool {
+        self.inputs_connected.get() & input_mask(index) != 0
+    }
+
     pub(crate) fn send_ended_event(&self) {
         if let Some(sender) = self.event_sender.as_ref() {
             let _ = sender.try_send(Event::Ended(self.node_id.get()));
         }
     }
+
+    pub(crate) fn report_output_sanitized(&self) {
+        if let Some(sender) = self.event_sender.as_ref() {
+            let _ = sender.try_send(Event::OutputSanitized(self.node_id.get()));
+        }
+    }
+
+    pub(crate) fn report_node_dropped(&self, id: AudioNodeId) {
+        if let Some(sender) = self.event_sender.as_ref() {
+            let _ = sender.try_send(Event::NodeDropped(id));
+        }
+    }
+
+    pub(crate) fn report_processor_error(&self, message: String) {
+        if let Some(sender) = self.event_sender.as_ref() {
+            let event = ProcessorErrorEvent::new(message, self.current_time);
+            let _ = sender.try_send(Event::ProcessorError(event));
+        }
+    }
 }
 
 /// Interface for audio processing code that runs on the audio rendering thread.
@@ -60,6 +94,14 @@ pub trait AudioProcessor: Send {
     /// - return `true` for some time when the node still outputs after the inputs are disconnected
     /// (e.g. DelayNode)
     /// - return `true` as long as this node is a source of output (e.g. OscillatorNode)
+    ///
+    /// # Errors
+    ///
+    /// A panic in this function does not take down the render thread: the node is muted for the
+    /// rest of its lifetime, the other nodes keep playing and the error is reported to
+    /// [`AudioContext::onerror`](crate::context::AudioContext::onerror) or
+    /// [`OfflineAudioContext::onerror`](crate::context::OfflineAudioContext::onerror). This
+    /// relies on unwinding, it does not apply when compiling with `panic = "abort"`.
     fn process(
         &mut self,
         inputs: &[AudioRenderQuantum],
@@ -79,13 +121,23 @@ impl Deref for DerefAudioRenderQuantumChannel<'_> {
         let len = if buffer.single_valued() {
             1
         } else {
-            RENDER_QUANTUM_SIZE
+            buffer.channel_data(0).len()
         };
 
         &buffer.channel_data(0)[..len]
     }
 }
 
+struct DerefAudioRenderQuantum<'a>(std::cell::Ref<'a, Node>);
+
+impl Deref for DerefAudioRenderQuantum<'_> {
+    type Target = AudioRenderQuantum;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.get_buffer()
+    }
+}
+
 /// Accessor for current [`crate::param::AudioParam`] values
 ///
 /// Provided to implementations of [`AudioProcessor`] in the render thread
@@ -102,12 +154,22 @@ impl<'a> AudioParamValues<'a> {
     ///
     /// For k-rate params or if the (a-rate) parameter is constant for this block, it will
     /// provide a slice of length 1. In other cases, i.e. a-rate param with scheduled
-    /// automations it will provide a slice of length [`crate::RENDER_QUANTUM_SIZE`]
+    /// automations it will provide a slice of the length of the render quantum
     #[allow(clippy::missing_panics_doc)]
     pub fn get(&self, index: &AudioParamId) -> impl Deref<Target = [f32]> + '_ {
         DerefAudioRenderQuantumChannel(self.nodes.get(&index.into()).unwrap().borrow())
     }
 
+    /// Get all the channels of the computed values of a param accepting multi-channel inputs
+    ///
+    /// The values are single valued, and mono, when no input is connected to the param.
+    pub(crate) fn get_channels(
+        &self,
+        index: &AudioParamId,
+    ) -> impl Deref<Target = AudioRenderQuantum> + '_ {
+        DerefAudioRenderQuantum(self.nodes.get(&index.into()).unwrap().borrow())
+    }
+
     pub(crate) fn listener_params(&self) -> [impl Deref<Target = [f32]> + '_; 9] {
         crate::context::LISTENER_AUDIO_PARAM_IDS.map(|p| self.get(&p))
     }
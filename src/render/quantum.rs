@@ -2,11 +2,13 @@
 use arrayvec::ArrayVec;
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 use crate::node::{ChannelConfig, ChannelCountMode, ChannelInterpretation};
 
 use crate::assert_valid_number_of_channels;
-use crate::{MAX_CHANNELS, RENDER_QUANTUM_SIZE};
+use crate::MAX_CHANNELS;
 
 // object pool for `AudioRenderQuantumChannel`s, only allocate if the pool is empty
 pub(crate) struct Alloc {
@@ -15,18 +17,95 @@ pub(crate) struct Alloc {
 
 #[derive(Debug)]
 struct AllocInner {
-    pool: RefCell<Vec<Rc<[f32; RENDER_QUANTUM_SIZE]>>>,
-    zeroes: Rc<[f32; RENDER_QUANTUM_SIZE]>,
+    pool: RefCell<Vec<Rc<[f32]>>>,
+    zeroes: Rc<[f32]>,
+    stats: Arc<AllocStats>,
+    /// Number of samples of each buffer
+    render_quantum_size: usize,
+}
+
+/// Usage counters of the object pool, shared with the control thread
+#[derive(Debug)]
+pub(crate) struct AllocStats {
+    capacity: usize,
+    allocations: AtomicU64,
+    reuses: AtomicU64,
+}
+
+impl AllocStats {
+    pub fn metrics(&self) -> RenderPoolMetrics {
+        RenderPoolMetrics {
+            capacity: self.capacity,
+            allocations: self.allocations.load(Ordering::Relaxed),
+            reuses: self.reuses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Usage metrics of the pool of render quantum buffers
+///
+/// This is mostly useful for debugging purposes, to size the
+/// [`render_pool_capacity`](crate::context::AudioContextOptions::render_pool_capacity)
+/// of large audio graphs.
+#[derive(Copy, Clone, Debug)]
+pub struct RenderPoolMetrics {
+    capacity: usize,
+    allocations: u64,
+    reuses: u64,
+}
+
+impl RenderPoolMetrics {
+    /// Number of buffers the pool was created with
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of buffers that were allocated on demand because the pool was empty
+    pub fn allocations(&self) -> u64 {
+        self.allocations
+    }
+
+    /// Number of buffer requests that were served from the pool
+    pub fn reuses(&self) -> u64 {
+        self.reuses
+    }
+
+    /// Ratio of buffer requests served from the pool, in the range [0, 1]
+    ///
+    /// Returns 1 when no buffers have been requested yet.
+    // we don't care about the precision loss for such large numbers
+    #[allow(clippy::cast_precision_loss)]
+    pub fn reuse_rate(&self) -> f64 {
+        let total = self.allocations + self.reuses;
+        if total == 0 {
+            1.
+        } else {
+            self.reuses as f64 / total as f64
+        }
+    }
 }
 
 impl Alloc {
+    #[cfg(test)]
     pub fn with_capacity(n: usize) -> Self {
-        let pool: Vec<_> = (0..n).map(|_| Rc::new([0.; RENDER_QUANTUM_SIZE])).collect();
-        let zeroes = Rc::new([0.; RENDER_QUANTUM_SIZE]);
+        Self::with_quantum_size(n, crate::RENDER_QUANTUM_SIZE)
+    }
+
+    /// Create a pool of `n` buffers of `render_quantum_size` samples
+    pub fn with_quantum_size(n: usize, render_quantum_size: usize) -> Self {
+        let pool: Vec<_> = (0..n).map(|_| zeroed(render_quantum_size)).collect();
+        let zeroes = zeroed(render_quantum_size);
+        let stats = AllocStats {
+            capacity: n,
+            allocations: AtomicU64::new(0),
+            reuses: AtomicU64::new(0),
+        };
 
         let inner = AllocInner {
             pool: RefCell::new(pool),
             zeroes,
+            stats: Arc::new(stats),
+            render_quantum_size,
         };
 
         Self {
@@ -34,6 +113,16 @@ impl Alloc {
         }
     }
 
+    /// Usage counters of this pool, can be read from any thread
+    pub fn stats(&self) -> Arc<AllocStats> {
+        Arc::clone(&self.inner.stats)
+    }
+
+    /// Number of buffers that were allocated on demand because the pool was empty
+    pub fn allocations(&self) -> u64 {
+        self.inner.stats.allocations.load(Ordering::Relaxed)
+    }
+
     #[cfg(test)]
     pub fn allocate(&self) -> AudioRenderQuantumChannel {
         AudioRenderQuantumChannel {
@@ -53,20 +142,33 @@ impl Alloc {
     pub fn pool_size(&self) -> usize {
         self.inner.pool.borrow().len()
     }
+
+    /// Number of samples of the buffers
+    pub fn render_quantum_size(&self) -> usize {
+        self.inner.render_quantum_size
+    }
+}
+
+/// A new buffer of `len` zeroes
+fn zeroed(len: usize) -> Rc<[f32]> {
+    vec![0.; len].into()
 }
 
 impl AllocInner {
-    fn allocate(&self) -> Rc<[f32; RENDER_QUANTUM_SIZE]> {
+    fn allocate(&self) -> Rc<[f32]> {
         if let Some(rc) = self.pool.borrow_mut().pop() {
             // re-use from pool
+            self.stats.reuses.fetch_add(1, Ordering::Relaxed);
             rc
         } else {
-            // allocate
-            Rc::new([0.; RENDER_QUANTUM_SIZE])
+            // allocate, the buffer will be added to the pool when it is dropped
+            // (no logging here, the render thread reports the allocations to the control thread)
+            self.stats.allocations.fetch_add(1, Ordering::Relaxed);
+            zeroed(self.render_quantum_size)
         }
     }
 
-    fn push(&self, data: Rc<[f32; RENDER_QUANTUM_SIZE]>) {
+    fn push(&self, data: Rc<[f32]>) {
         self.pool
             .borrow_mut() // infallible when single threaded
             .push(data);
@@ -75,9 +177,12 @@ impl AllocInner {
 
 /// Render thread channel buffer
 ///
-/// Basically wraps a `Rc<[f32; RENDER_QUANTUM_SIZE]>`, which means it derefs to a (mutable) slice
+/// Basically wraps a `Rc<[f32]>` of one render quantum, which means it derefs to a (mutable) slice
 /// of `[f32]` sample values. Plus it has copy-on-write semantics, so it is cheap to clone.
 ///
+/// The length of the slice is the render quantum size of the context, [`RENDER_QUANTUM_SIZE`]
+/// unless configured otherwise.
+///
 /// # Usage
 ///
 /// Audio buffers are managed with a dedicated allocator per render thread, hence there are no
@@ -93,19 +198,22 @@ impl AllocInner {
 /// ```
 #[derive(Clone, Debug)]
 pub struct AudioRenderQuantumChannel {
-    data: Rc<[f32; RENDER_QUANTUM_SIZE]>,
+    data: Rc<[f32]>,
     alloc: Rc<AllocInner>,
 }
 
 impl AudioRenderQuantumChannel {
-    fn make_mut(&mut self) -> &mut [f32; RENDER_QUANTUM_SIZE] {
-        if Rc::strong_count(&self.data) != 1 {
+    fn make_mut(&mut self) -> &mut [f32] {
+        if Rc::get_mut(&mut self.data).is_none() {
             let mut new = self.alloc.allocate();
-            Rc::make_mut(&mut new).copy_from_slice(self.data.deref());
+            // buffers from the pool are never shared
+            Rc::get_mut(&mut new)
+                .unwrap()
+                .copy_from_slice(self.data.deref());
             self.data = new;
         }
 
-        Rc::make_mut(&mut self.data)
+        Rc::get_mut(&mut self.data).unwrap()
     }
 
     /// `O(1)` check if this buffer is equal to the 'silence buffer'
@@ -124,6 +232,47 @@ impl AudioRenderQuantumChannel {
         }
     }
 
+    /// Sample-wise add the values of `other`, e.g. a signal rendered by the processor itself
+    ///
+    /// `other` should contain a render quantum of samples, only the overlapping part is added
+    /// in release builds.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if `other` does not contain a render quantum of samples
+    pub fn add_from(&mut self, other: &[f32]) {
+        debug_assert_eq!(
+            other.len(),
+            self.len(),
+            "add_from requires a slice of render quantum size"
+        );
+        self.iter_mut().zip(other).for_each(|(a, b)| *a += b);
+    }
+
+    /// Set every sample to the value returned by `f` for its index
+    ///
+    /// ```
+    /// # use web_audio_api::render::AudioRenderQuantumChannel;
+    /// fn ramp(channel: &mut AudioRenderQuantumChannel) {
+    ///     channel.fill_with(|i| i as f32 / 128.);
+    /// }
+    /// ```
+    pub fn fill_with<F: FnMut(usize) -> f32>(&mut self, mut f: F) {
+        self.iter_mut().enumerate().for_each(|(i, s)| *s = f(i));
+    }
+
+    /// Replace every sample by the value returned by `f` for it
+    ///
+    /// ```
+    /// # use web_audio_api::render::AudioRenderQuantumChannel;
+    /// fn apply_gain(channel: &mut AudioRenderQuantumChannel, gain: f32) {
+    ///     channel.apply(|s| s * gain);
+    /// }
+    /// ```
+    pub fn apply<F: FnMut(f32) -> f32>(&mut self, mut f: F) {
+        self.iter_mut().for_each(|s| *s = f(*s));
+    }
+
     pub(crate) fn silence(&self) -> Self {
         Self {
             data: self.alloc.zeroes.clone(),
@@ -135,7 +284,7 @@ impl AudioRenderQuantumChannel {
 use std::ops::{Deref, DerefMut};
 
 impl Deref for AudioRenderQuantumChannel {
-    type Target = [f32; RENDER_QUANTUM_SIZE];
+    type Target = [f32];
 
     fn deref(&self) -> &Self::Target {
         &self.data
@@ -165,7 +314,7 @@ impl std::ops::Drop for AudioRenderQuantumChannel {
 
 /// Render thread audio buffer, consisting of multiple channel buffers
 ///
-/// Internal fixed length audio asset of `RENDER_QUANTUM_SIZE` sample frames for
+/// Internal fixed length audio asset of one render quantum of sample frames for
 /// block rendering, basically a matrix of `channels * AudioRenderQuantumChannel`
 /// cf. <https://webaudio.github.io/web-audio-api/#render-quantum>
 ///
@@ -520,8 +669,13 @@ impl AudioRenderQuantum {
     }
 
     /// Convert to a single channel buffer, dropping excess channels
-    pub(crate) fn force_mono(&mut self) {
-        self.channels.truncate(1);
+    ///
+    /// This is a no-op when the buffer is already mono, so single output sources can call it at
+    /// each render quantum. It never allocates, the excess channels are returned to the pool.
+    pub fn ensure_mono(&mut self) {
+        if self.channels.len() > 1 {
+            self.channels.truncate(1);
+        }
     }
 
     /// Modify every channel in the same way
@@ -568,6 +722,8 @@ mod tests {
 
     use super::*;
 
+    use crate::RENDER_QUANTUM_SIZE;
+
     #[test]
     fn test_pool() {
         // Create pool of size 2
@@ -647,6 +803,33 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_pool_stats() {
+        let alloc = Alloc::with_capacity(1);
+        let stats = alloc.stats();
+
+        let metrics = stats.metrics();
+        assert_eq!(metrics.capacity(), 1);
+        assert_eq!(metrics.allocations(), 0);
+        assert_eq!(metrics.reuses(), 0);
+        assert_float_eq!(metrics.reuse_rate(), 1., abs <= 0.);
+
+        {
+            let _a = alloc.allocate(); // from pool
+            let _b = alloc.allocate(); // on demand
+        }
+        let _c = alloc.allocate(); // from pool (extended)
+
+        let metrics = stats.metrics();
+        assert_eq!(metrics.allocations(), 1);
+        assert_eq!(metrics.reuses(), 2);
+        assert_float_eq!(metrics.reuse_rate(), 2. / 3., abs <= 1e-12);
+
+        // silence does not take a buffer from the pool
+        let _d = alloc.silence();
+        assert_eq!(stats.metrics().reuses(), 2);
+    }
+
     #[test]
     fn test_silence() {
         let alloc = Alloc::with_capacity(1);
@@ -718,6 +901,42 @@ mod tests {
         assert_eq!(buffer.number_of_channels(), 2);
     }
 
+    #[test]
+    fn test_ensure_mono() {
+        let alloc = Alloc::with_capacity(2);
+
+        let mut left = alloc.allocate();
+        left.iter_mut().for_each(|v| *v = 1.);
+        let mut right = alloc.allocate();
+        right.iter_mut().for_each(|v| *v = 2.);
+
+        let mut buffer = AudioRenderQuantum::from(left);
+        buffer.channels.push(right);
+        assert_eq!(alloc.pool_size(), 0);
+
+        alloc_counter::deny_alloc(|| {
+            // stereo to mono keeps the first channel, the other one returns to the pool
+            buffer.ensure_mono();
+            assert_eq!(buffer.number_of_channels(), 1);
+            assert_float_eq!(
+                &buffer.channel_data(0)[..],
+                &[1.; RENDER_QUANTUM_SIZE][..],
+                abs_all <= 0.
+            );
+            assert_eq!(alloc.pool_size(), 1);
+
+            // no-op when already mono
+            buffer.ensure_mono();
+            assert_eq!(buffer.number_of_channels(), 1);
+            assert_float_eq!(
+                &buffer.channel_data(0)[..],
+                &[1.; RENDER_QUANTUM_SIZE][..],
+                abs_all <= 0.
+            );
+            assert_eq!(alloc.pool_size(), 1);
+        });
+    }
+
     #[test]
     fn test_audiobuffer_mix_discrete() {
         let alloc = Alloc::with_capacity(1);
@@ -1479,4 +1698,62 @@ mod tests {
         );
         assert!(!buffer.is_silent());
     }
+
+    #[test]
+    fn test_channel_helpers() {
+        let alloc = Alloc::with_capacity(3);
+
+        // generate a ramp and a square wave
+        let mut ramp = alloc.allocate();
+        ramp.fill_with(|i| i as f32);
+        let mut square = alloc.allocate();
+        square.fill_with(|i| if i % 2 == 0 { 1. } else { -1. });
+
+        let mut expected_ramp = [0.; RENDER_QUANTUM_SIZE];
+        let mut expected_square = [0.; RENDER_QUANTUM_SIZE];
+        for i in 0..RENDER_QUANTUM_SIZE {
+            expected_ramp[i] = i as f32;
+            expected_square[i] = if i % 2 == 0 { 1. } else { -1. };
+        }
+        assert_float_eq!(&ramp[..], &expected_ramp[..], abs_all <= 0.);
+        assert_float_eq!(&square[..], &expected_square[..], abs_all <= 0.);
+
+        // mix the square wave at half gain into the ramp
+        square.apply(|s| s * 0.5);
+        ramp.add_from(&square[..]);
+
+        let mut expected = [0.; RENDER_QUANTUM_SIZE];
+        for i in 0..RENDER_QUANTUM_SIZE {
+            expected[i] = expected_ramp[i] + expected_square[i] * 0.5;
+        }
+        assert_float_eq!(&ramp[..], &expected[..], abs_all <= 0.);
+    }
+
+    #[test]
+    fn test_channel_helpers_copy_on_write() {
+        let alloc = Alloc::with_capacity(2);
+        let mut a = alloc.allocate();
+        a.fill_with(|_| 1.);
+
+        // the clone is not affected
+        let b = a.clone();
+        a.apply(|s| s * 2.);
+        assert_float_eq!(&a[..], &[2.; RENDER_QUANTUM_SIZE][..], abs_all <= 0.);
+        assert_float_eq!(&b[..], &[1.; RENDER_QUANTUM_SIZE][..], abs_all <= 0.);
+
+        // adding to silence
+        let mut silence = alloc.silence();
+        silence.add_from(&b[..]);
+        assert_float_eq!(&silence[..], &[1.; RENDER_QUANTUM_SIZE][..], abs_all <= 0.);
+        assert!(alloc.silence().is_silent());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_add_from_length_mismatch() {
+        let alloc = Alloc::with_capacity(1);
+        let mut a = alloc.allocate();
+        a.add_from(&[1.; 10]);
+    }
 }
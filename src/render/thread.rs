@@ -12,7 +12,6 @@ use crate::buffer::{AudioBuffer, AudioBufferOptions};
 use crate::context::AudioNodeId;
 use crate::events::Event;
 use crate::message::ControlMessage;
-use crate::node::ChannelInterpretation;
 use crate::render::RenderScope;
 use crate::{AudioRenderCapacityLoad, RENDER_QUANTUM_SIZE};
 
@@ -24,10 +23,16 @@ pub(crate) struct RenderThread {
     sample_rate: f32,
     number_of_channels: usize,
     frames_played: Arc<AtomicU64>,
+    /// Number of render calls that took longer than the duration of the rendered audio
+    xruns: Arc<AtomicU64>,
     receiver: Option<Receiver<ControlMessage>>,
     buffer_offset: Option<(usize, AudioRenderQuantum)>,
     load_value_sender: Option<Sender<AudioRenderCapacityLoad>>,
     event_sender: Option<Sender<Event>>,
+    /// Number of on-demand allocations of the buffer pool already reported
+    pool_allocations: u64,
+    /// Number of frames of a render quantum, set by the graph at startup
+    render_quantum_size: usize,
 }
 
 // SAFETY:
@@ -47,6 +52,7 @@ impl RenderThread {
         number_of_channels: usize,
         receiver: Receiver<ControlMessage>,
         frames_played: Arc<AtomicU64>,
+        xruns: Arc<AtomicU64>,
         load_value_sender: Option<Sender<AudioRenderCapacityLoad>>,
         event_sender: Option<Sender<Event>>,
     ) -> Self {
@@ -55,10 +61,13 @@ impl RenderThread {
             sample_rate,
             number_of_channels,
             frames_played,
+            xruns,
             receiver: Some(receiver),
             buffer_offset: None,
             load_value_sender,
             event_sender,
+            pool_allocations: 0,
+            render_quantum_size: RENDER_QUANTUM_SIZE,
         }
     }
 
@@ -113,12 +122,26 @@ impl RenderThread {
                 MarkCycleBreaker { id } => {
                     self.graph.as_mut().unwrap().mark_cycle_breaker(id);
                 }
+                SetBypass { id, bypassed } => {
+                    self.graph.as_mut().unwrap().set_bypass(id, bypassed);
+                }
+                SetMuted { id, muted } => {
+                    self.graph.as_mut().unwrap().set_muted(id, muted);
+                }
+                SetSoloed { id, soloed } => {
+                    self.graph.as_mut().unwrap().set_soloed(id, soloed);
+                }
+                ClearSolo => {
+                    self.graph.as_mut().unwrap().clear_solo();
+                }
                 Shutdown { sender } => {
                     let _ = sender.send(self.graph.take().unwrap());
                     self.receiver = None;
                     return; // no further handling of ctrl msgs
                 }
                 Startup { graph } => {
+                    self.pool_allocations = graph.pool_allocations();
+                    self.render_quantum_size = graph.render_quantum_size();
                     self.graph = Some(graph);
                 }
             }
@@ -126,56 +149,123 @@ impl RenderThread {
     }
 
     // render method of the OfflineAudioContext
-    pub fn render_audiobuffer(mut self, length: usize) -> AudioBuffer {
+    //
+    // `suspend` is called with the current frame before each render quantum, so the control
+    // thread can alter the audio graph from that frame onwards
+    pub fn render_audiobuffer<F: FnMut(usize)>(
+        mut self,
+        length: usize,
+        mut suspend: F,
+    ) -> AudioBuffer {
+        // pick up the graph, and its render quantum size
+        self.handle_control_messages();
+        let render_quantum_size = self.render_quantum_size;
+
         // assert input was properly sized
-        debug_assert_eq!(length % RENDER_QUANTUM_SIZE, 0);
+        debug_assert_eq!(length % render_quantum_size, 0);
 
-        let options = AudioBufferOptions {
-            number_of_channels: self.number_of_channels,
-            length: 0,
-            sample_rate: self.sample_rate,
-        };
+        let mut buf = self.empty_audiobuffer();
 
-        let mut buf = AudioBuffer::new(options);
+        for quantum in 0..length / render_quantum_size {
+            suspend(quantum * render_quantum_size);
+            let rendered = self.render_offline_quantum();
+            buf.extend_alloc(&rendered);
+        }
 
-        for _ in 0..length / RENDER_QUANTUM_SIZE {
-            // handle addition/removal of nodes/edges
-            self.handle_control_messages();
+        buf
+    }
 
-            // update time
-            let current_frame = self
-                .frames_played
-                .fetch_add(RENDER_QUANTUM_SIZE as u64, Ordering::SeqCst);
-            let current_time = current_frame as f64 / self.sample_rate as f64;
+    // render method of the OfflineAudioContext, stopping when the graph has no active nodes left
+    pub fn render_audiobuffer_until_inactive<F: FnMut(usize)>(
+        mut self,
+        max_length: usize,
+        mut suspend: F,
+    ) -> AudioBuffer {
+        // pick up the graph, and its render quantum size
+        self.handle_control_messages();
+        let render_quantum_size = self.render_quantum_size;
 
-            let scope = RenderScope {
-                current_frame,
-                current_time,
-                sample_rate: self.sample_rate,
-                event_sender: self.event_sender.clone(),
-                node_id: Cell::new(AudioNodeId(0)), // placeholder value
-            };
+        // assert input was properly sized
+        debug_assert_eq!(max_length % render_quantum_size, 0);
 
-            // render audio graph
-            let rendered = self.graph.as_mut().unwrap().render(&scope);
+        let mut buf = self.empty_audiobuffer();
 
+        for quantum in 0..max_length / render_quantum_size {
+            suspend(quantum * render_quantum_size);
+            let rendered = self.render_offline_quantum();
             buf.extend_alloc(&rendered);
+
+            if !self.graph.as_ref().unwrap().is_active() {
+                break;
+            }
         }
 
         buf
     }
 
+    fn empty_audiobuffer(&self) -> AudioBuffer {
+        let options = AudioBufferOptions {
+            number_of_channels: self.number_of_channels,
+            length: 0,
+            sample_rate: self.sample_rate,
+        };
+
+        AudioBuffer::new(options)
+    }
+
+    fn render_offline_quantum(&mut self) -> AudioRenderQuantum {
+        // handle addition/removal of nodes/edges
+        self.handle_control_messages();
+
+        // update time
+        let current_frame = self
+            .frames_played
+            .fetch_add(self.render_quantum_size as u64, Ordering::SeqCst);
+        let current_time = current_frame as f64 / self.sample_rate as f64;
+
+        let scope = RenderScope {
+            current_frame,
+            current_time,
+            sample_rate: self.sample_rate,
+            render_quantum_size: self.render_quantum_size,
+            event_sender: self.event_sender.clone(),
+            node_id: Cell::new(AudioNodeId(0)), // placeholder value
+            inputs_connected: Cell::new(0),
+        };
+
+        // render audio graph
+        self.graph.as_mut().unwrap().render(&scope)
+    }
+
     pub fn render<S: crate::Sample>(&mut self, buffer: &mut [S]) {
+        // Only static span metadata and atomic counters are used on the render thread, the
+        // cost of recording the spans is up to the subscriber.
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!(
+            "render",
+            frame = self.frames_played.load(Ordering::Relaxed),
+            frames = buffer.len() / self.number_of_channels,
+            xruns = self.xruns.load(Ordering::Relaxed),
+        )
+        .entered();
+
         // collect timing information
         let render_start = Instant::now();
 
         // perform actual rendering
         self.render_inner(buffer);
 
+        // the callback is late when it took longer than the audio it rendered
+        let available =
+            buffer.len() as f64 / (self.number_of_channels as f64 * self.sample_rate as f64);
+        if render_start.elapsed().as_secs_f64() > available {
+            self.xruns.fetch_add(1, Ordering::Relaxed);
+        }
+
         // calculate load value and ship to control thread
         if let Some(load_value_sender) = &self.load_value_sender {
             let duration = render_start.elapsed().as_micros() as f64 / 1E6;
-            let max_duration = RENDER_QUANTUM_SIZE as f64 / self.sample_rate as f64;
+            let max_duration = self.render_quantum_size as f64 / self.sample_rate as f64;
             let load_value = duration / max_duration;
             let render_timestamp =
                 self.frames_played.load(Ordering::SeqCst) as f64 / self.sample_rate as f64;
@@ -189,9 +279,9 @@ impl RenderThread {
 
     fn render_inner<S: crate::Sample>(&mut self, mut buffer: &mut [S]) {
         // There may be audio frames left over from the previous render call,
-        // if the cpal buffer size did not align with our internal RENDER_QUANTUM_SIZE
+        // if the cpal buffer size did not align with our internal render quantum size
         if let Some((offset, prev_rendered)) = self.buffer_offset.take() {
-            let leftover_len = (RENDER_QUANTUM_SIZE - offset) * self.number_of_channels;
+            let leftover_len = (self.render_quantum_size - offset) * self.number_of_channels;
             // split the leftover frames slice, to fit in `buffer`
             let (first, next) = buffer.split_at_mut(leftover_len.min(buffer.len()));
 
@@ -227,31 +317,53 @@ impl RenderThread {
             return;
         }
 
-        // The audio graph is rendered in chunks of RENDER_QUANTUM_SIZE frames.  But some audio backends
-        // may not be able to emit chunks of this size.
-        let chunk_size = RENDER_QUANTUM_SIZE * self.number_of_channels;
+        // The audio graph is rendered in chunks of render quantum size frames.  But some audio
+        // backends may not be able to emit chunks of this size.
+        let render_quantum_size = self.render_quantum_size;
+        let chunk_size = render_quantum_size * self.number_of_channels;
 
         for data in buffer.chunks_mut(chunk_size) {
             // update time
             let current_frame = self
                 .frames_played
-                .fetch_add(RENDER_QUANTUM_SIZE as u64, Ordering::SeqCst);
+                .fetch_add(render_quantum_size as u64, Ordering::SeqCst);
             let current_time = current_frame as f64 / self.sample_rate as f64;
 
             let scope = RenderScope {
                 current_frame,
                 current_time,
                 sample_rate: self.sample_rate,
+                render_quantum_size,
                 event_sender: self.event_sender.clone(),
                 node_id: Cell::new(AudioNodeId(0)), // placeholder value
+                inputs_connected: Cell::new(0),
             };
 
             // render audio graph
-            let mut rendered = self.graph.as_mut().unwrap().render(&scope);
+            let graph = self.graph.as_mut().unwrap();
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!(
+                "render_quantum",
+                frame = current_frame,
+                nodes = graph.node_count()
+            )
+            .entered();
+            let mut rendered = graph.render(&scope);
+
+            // report on-demand allocations of the buffer pool, the warning is logged by the
+            // control thread as formatting and I/O are not real-time safe
+            let pool_allocations = graph.pool_allocations();
+            if pool_allocations > self.pool_allocations {
+                self.pool_allocations = pool_allocations;
+                if let Some(sender) = &self.event_sender {
+                    let _ = sender.send(Event::RenderPoolExhausted(pool_allocations));
+                }
+            }
 
-            // online AudioContext allows channel count to be less than no of hardware channels
+            // online AudioContext allows channel count to be less than no of hardware channels,
+            // up-mix following the channel interpretation of the destination
             if rendered.number_of_channels() != self.number_of_channels {
-                rendered.mix(self.number_of_channels, ChannelInterpretation::Discrete);
+                rendered.mix(self.number_of_channels, graph.destination_interpretation());
             }
 
             // copy rendered audio into output slice
@@ -265,9 +377,9 @@ impl RenderThread {
             }
 
             if data.len() != chunk_size {
-                // this is the last chunk, and it contained less than RENDER_QUANTUM_SIZE samples
+                // this is the last chunk, and it contained less than a render quantum of samples
                 let channel_offset = data.len() / self.number_of_channels;
-                debug_assert!(channel_offset < RENDER_QUANTUM_SIZE);
+                debug_assert!(channel_offset < render_quantum_size);
                 self.buffer_offset = Some((channel_offset, rendered));
             }
 
@@ -279,6 +391,142 @@ impl RenderThread {
 
 impl Drop for RenderThread {
     fn drop(&mut self) {
+        // The audio backend was torn down before the pending shutdown message was handled (e.g.
+        // because the audio device was lost). Drain the control messages so that the changes
+        // queued before the shutdown message are applied and the audio graph is handed over to
+        // be revived.
+        if self.graph.is_some() {
+            self.handle_control_messages();
+        }
+
         log::info!("Audio render thread has been dropped");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{ChannelConfigOptions, ChannelCountMode, ChannelInterpretation};
+    use crate::render::{AudioParamValues, AudioProcessor};
+
+    struct ConstantProcessor;
+
+    impl AudioProcessor for ConstantProcessor {
+        fn process(
+            &mut self,
+            _inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            outputs[0].channels_mut()[0].fill(1.);
+            true
+        }
+    }
+
+    struct PassthroughProcessor;
+
+    impl AudioProcessor for PassthroughProcessor {
+        fn process(
+            &mut self,
+            inputs: &[AudioRenderQuantum],
+            outputs: &mut [AudioRenderQuantum],
+            _params: AudioParamValues,
+            _scope: &RenderScope,
+        ) -> bool {
+            outputs[0] = inputs[0].clone();
+            false
+        }
+    }
+
+    // render a mono destination to a stereo output
+    fn render_mono_destination(interpretation: ChannelInterpretation) -> Vec<f32> {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let mut render_thread = RenderThread::new(
+            48000.,
+            2,
+            receiver,
+            Arc::new(AtomicU64::new(0)),
+            Arc::new(AtomicU64::new(0)),
+            None,
+            None,
+        );
+
+        let config = ChannelConfigOptions {
+            count: 1,
+            count_mode: ChannelCountMode::Explicit,
+            interpretation,
+        };
+        let mut graph = Graph::new();
+        graph.add_node(
+            AudioNodeId(0),
+            Box::new(PassthroughProcessor),
+            1,
+            1,
+            config.clone().into(),
+        );
+        graph.add_node(
+            AudioNodeId(2),
+            Box::new(ConstantProcessor),
+            0,
+            1,
+            config.into(),
+        );
+        graph.add_edge((AudioNodeId(2), 0), (AudioNodeId(0), 0));
+
+        sender.send(ControlMessage::Startup { graph }).unwrap();
+        render_thread.handle_control_messages();
+
+        let mut buffer = vec![0.; 2 * RENDER_QUANTUM_SIZE];
+        render_thread.render(&mut buffer);
+        buffer
+    }
+
+    #[test]
+    fn test_destination_up_mix() {
+        // speakers interpretation copies the mono channel to left and right
+        let output = render_mono_destination(ChannelInterpretation::Speakers);
+        assert!(output.iter().all(|&v| v == 1.));
+
+        // discrete interpretation leaves the right channel silent
+        let output = render_mono_destination(ChannelInterpretation::Discrete);
+        assert!(output.iter().step_by(2).all(|&v| v == 1.));
+        assert!(output.iter().skip(1).step_by(2).all(|&v| v == 0.));
+    }
+
+    #[test]
+    fn test_drop_hands_over_graph() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let mut render_thread = RenderThread::new(
+            48000.,
+            2,
+            receiver,
+            Arc::new(AtomicU64::new(0)),
+            Arc::new(AtomicU64::new(0)),
+            None,
+            None,
+        );
+
+        sender
+            .send(ControlMessage::Startup {
+                graph: Graph::new(),
+            })
+            .unwrap();
+        render_thread.handle_control_messages();
+
+        // the shutdown message is not the first pending message
+        let (graph_send, graph_recv) = crossbeam_channel::bounded(1);
+        sender
+            .send(ControlMessage::Startup {
+                graph: Graph::new(),
+            })
+            .unwrap();
+        sender
+            .send(ControlMessage::Shutdown { sender: graph_send })
+            .unwrap();
+
+        drop(render_thread);
+
+        assert!(graph_recv.try_recv().is_ok());
+    }
+}
@@ -0,0 +1,270 @@
+//! Windowed-sinc resampling of in-memory audio data
+
+use rubato::{InterpolationParameters, InterpolationType, Resampler, SincFixedIn, WindowFunction};
+
+/// Quality of the sample rate conversion of decoded audio data
+///
+/// See [`BaseAudioContext::decode_audio_data_sync_with_quality`](crate::context::BaseAudioContext::decode_audio_data_sync_with_quality)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResampleQuality {
+    /// Linear interpolation, fast but attenuates high frequencies and lets aliasing through
+    Low,
+    /// Windowed-sinc interpolation with a short kernel, the default
+    #[default]
+    Medium,
+    /// Windowed-sinc interpolation with a long kernel, transparent for most material
+    High,
+}
+
+/// Windowed-sinc resampler for arbitrary ratios, backed by [`rubato::SincFixedIn`]
+///
+/// When downsampling, the cutoff frequency is lowered to the target Nyquist frequency to prevent
+/// aliasing.
+pub(crate) struct SincResampler {
+    /// Distance between two output samples, in input samples
+    step: f64,
+    /// Quality of the conversion, other than `ResampleQuality::Low`
+    quality: ResampleQuality,
+}
+
+impl SincResampler {
+    /// Create a resampler converting from `source_sample_rate` to `target_sample_rate`
+    ///
+    /// Returns `None` for `ResampleQuality::Low`, which does not use a sinc kernel
+    pub fn new(
+        source_sample_rate: f32,
+        target_sample_rate: f32,
+        quality: ResampleQuality,
+    ) -> Option<Self> {
+        if quality == ResampleQuality::Low {
+            return None;
+        }
+
+        Some(Self {
+            step: source_sample_rate as f64 / target_sample_rate as f64,
+            quality,
+        })
+    }
+
+    /// Parameters of the sinc kernel for the quality of the resampler
+    fn parameters(&self) -> InterpolationParameters {
+        match self.quality {
+            ResampleQuality::Low => unreachable!(),
+            ResampleQuality::Medium => InterpolationParameters {
+                sinc_len: 32,
+                f_cutoff: 0.9,
+                oversampling_factor: 128,
+                interpolation: InterpolationType::Linear,
+                window: WindowFunction::Blackman2,
+            },
+            ResampleQuality::High => InterpolationParameters {
+                sinc_len: 256,
+                f_cutoff: 0.95,
+                oversampling_factor: 256,
+                interpolation: InterpolationType::Cubic,
+                window: WindowFunction::BlackmanHarris2,
+            },
+        }
+    }
+
+    /// Number of output samples for the given number of input samples
+    pub fn output_length(&self, input_length: usize) -> usize {
+        (input_length as f64 / self.step).ceil() as usize
+    }
+
+    /// Resample a full channel of audio data, the samples outside `input` are considered silent
+    pub fn process(&self, input: &[f32]) -> Vec<f32> {
+        let output_length = self.output_length(input.len());
+        let parameters = self.parameters();
+        let sinc_len = parameters.sinc_len;
+        let step = self.step;
+        let ratio = step.recip();
+
+        // rubato computes the output sample `n` at the input position `(n + 1) * step - 1`.
+        // Delay the input, or skip the first output samples, so that the output sample `n` is
+        // computed within half a sample of the input position `n * step`. A residual latency
+        // remains, unless the ratio is a whole number.
+        let (input_delay, output_skip) = if step >= 1. {
+            ((step - 1.).round() as usize, 0)
+        } else {
+            (0, (ratio - 1.).round() as usize)
+        };
+
+        // Process the whole channel as a single chunk. The input is padded with silence so
+        // that the chunk also yields the samples near the end of the input.
+        let padding = 2 * sinc_len + 2 * step.ceil() as usize;
+        let mut chunk = vec![0.; input_delay];
+        chunk.reserve(input.len() + padding);
+        chunk.extend_from_slice(input);
+        chunk.resize(input_delay + input.len() + padding, 0.);
+
+        let mut resampler = SincFixedIn::<f32>::new(ratio, 1., parameters, chunk.len(), 1).unwrap();
+
+        let mut output = resampler.process(&[chunk], None).unwrap().remove(0);
+
+        output.drain(..output_skip.min(output.len()));
+        output.resize(output_length, 0.);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    // phase computed in f64 to keep the reference signal free of rounding noise
+    fn sine(frequency: f64, sample_rate: f64, length: usize) -> Vec<f32> {
+        (0..length)
+            .map(|i| (2. * PI * frequency * i as f64 / sample_rate).sin() as f32)
+            .collect()
+    }
+
+    fn rms(signal: &[f32]) -> f32 {
+        (signal.iter().map(|v| v * v).sum::<f32>() / signal.len() as f32).sqrt()
+    }
+
+    // rms error between `output[range]` and a sine, delayed by the latency (within half a
+    // sample at `source_sample_rate`) that matches the output best
+    fn sine_error(
+        output: &[f32],
+        frequency: f64,
+        sample_rate: f64,
+        source_sample_rate: f64,
+        range: std::ops::Range<usize>,
+    ) -> f32 {
+        let error = |latency: f64| {
+            let error: Vec<f32> = range
+                .clone()
+                .map(|n| {
+                    let time = n as f64 / sample_rate - latency / source_sample_rate;
+                    output[n] - (2. * PI * frequency * time).sin() as f32
+                })
+                .collect();
+            rms(&error)
+        };
+
+        // coarse search in steps of 1/100 sample, then refine in steps of 1/2000 sample
+        let coarse = (-50..=50)
+            .map(|i| i as f64 / 100.)
+            .min_by(|a, b| error(*a).total_cmp(&error(*b)))
+            .unwrap();
+        (-20..=20)
+            .map(|i| error(coarse + i as f64 / 2000.))
+            .fold(f32::MAX, f32::min)
+    }
+
+    #[test]
+    fn test_low_quality_has_no_kernel() {
+        assert!(SincResampler::new(48000., 44100., ResampleQuality::Low).is_none());
+    }
+
+    #[test]
+    fn test_output_length() {
+        [
+            (48000., 44100., 48000, 44100),
+            (44100., 48000., 44100, 48000),
+            (44100., 96000., 1000, 2177),
+            (96000., 8000., 1001, 84),
+            (44100., 44100., 1000, 1000),
+        ]
+        .iter()
+        .for_each(|&(source, target, input_length, output_length)| {
+            let resampler = SincResampler::new(source, target, ResampleQuality::High).unwrap();
+            let output = resampler.process(&vec![0.; input_length]);
+            assert_eq!(output.len(), output_length);
+            // within one sample of the exact length
+            let exact = input_length as f64 * target as f64 / source as f64;
+            assert!((output.len() as f64 - exact).abs() < 1.);
+        });
+    }
+
+    #[test]
+    fn test_resample_sine() {
+        [ResampleQuality::Medium, ResampleQuality::High]
+            .iter()
+            .for_each(|&quality| {
+                let resampler = SincResampler::new(48000., 44100., quality).unwrap();
+                let input = sine(1000., 48000., 48000);
+                let output = resampler.process(&input);
+
+                // skip the edges, where the kernel extends outside the input
+                let error = sine_error(&output, 1000., 44100., 48000., 500..43600);
+                let tolerance = match quality {
+                    ResampleQuality::High => 1e-4,
+                    _ => 1e-2,
+                };
+                assert!(error < tolerance, "{:?} {}", quality, error);
+            });
+    }
+
+    #[test]
+    fn test_arbitrary_ratio() {
+        // ratio that is not a simple fraction
+        let resampler = SincResampler::new(48000., 44_123.4, ResampleQuality::High).unwrap();
+        let input = sine(440., 48000., 4800);
+        let output = resampler.process(&input);
+
+        let error = sine_error(&output, 440., 44_123.4, 48000., 200..output.len() - 200);
+        assert!(error < 1e-4);
+    }
+
+    #[test]
+    fn test_alignment() {
+        // the output is aligned with the input within half a sample
+        [
+            (48000., 44100.),
+            (44100., 48000.),
+            (48000., 96000.),
+            (96000., 48000.),
+            (48000., 8000.),
+        ]
+        .iter()
+        .for_each(|&(source, target)| {
+            let resampler = SincResampler::new(source, target, ResampleQuality::High).unwrap();
+            let mut input = vec![0.; 4800];
+            input[2400] = 1.;
+            let output = resampler.process(&input);
+
+            let peak = output
+                .iter()
+                .enumerate()
+                .fold(
+                    (0, 0.),
+                    |(i, max), (j, &v)| if v > max { (j, v) } else { (i, max) },
+                )
+                .0;
+            let expected = 2400. * target as f64 / source as f64;
+            assert!(
+                (peak as f64 - expected).abs() <= 1.,
+                "{} {}",
+                source,
+                target
+            );
+        });
+    }
+
+    #[test]
+    fn test_aliasing() {
+        // 23kHz is above the Nyquist frequency of the target rate, it would alias at 21.1kHz
+        let resampler = SincResampler::new(48000., 44100., ResampleQuality::High).unwrap();
+        let input = sine(23000., 48000., 48000);
+        let output = resampler.process(&input);
+
+        let level = rms(&output[500..43600]) / rms(&input);
+        assert!(level < 1e-3); // -60dB
+
+        // linear interpolation lets the aliased signal through
+        let linear: Vec<f32> = (0..44100)
+            .map(|i| {
+                let position = i as f64 * 48000. / 44100.;
+                let index = position as usize;
+                let k = (position - index as f64) as f32;
+                let next = input.get(index + 1).copied().unwrap_or(0.);
+                input[index] * (1. - k) + next * k
+            })
+            .collect();
+        assert!(rms(&linear) / rms(&input) > 0.1);
+    }
+}
@@ -24,6 +24,13 @@ pub(crate) const PARAM_OPTS: AudioParamDescriptor = AudioParamDescriptor {
 ///
 /// All [`PannerNode`](crate::node::PannerNode) objects spatialize in relation to the [BaseAudioContext's](crate::context::BaseAudioContext) listener.
 ///
+/// The orientation is defined by a forward vector (the direction the listener is facing) and an
+/// up vector (the direction of the top of the head). The vectors do not need to be normalized
+/// nor orthogonal: the up vector is orthonormalized against the forward vector. A zero vector
+/// falls back to its default value, i.e. a forward vector of `(0, 0, -1)` and an up vector of
+/// `(0, 1, 0)`. If the forward and up vectors are parallel the orientation is undefined and all
+/// sources are rendered in front of the listener.
+///
 /// # Usage
 ///
 /// For example usage, check the [`PannerNode`](crate::node::PannerNode) docs.
@@ -40,30 +47,39 @@ pub struct AudioListener {
 }
 
 impl AudioListener {
+    /// X coordinate of the position of the listener
     pub fn position_x(&self) -> &AudioParam {
         &self.position_x
     }
+    /// Y coordinate of the position of the listener
     pub fn position_y(&self) -> &AudioParam {
         &self.position_y
     }
+    /// Z coordinate of the position of the listener
     pub fn position_z(&self) -> &AudioParam {
         &self.position_z
     }
+    /// X component of the direction the listener is facing
     pub fn forward_x(&self) -> &AudioParam {
         &self.forward_x
     }
+    /// Y component of the direction the listener is facing
     pub fn forward_y(&self) -> &AudioParam {
         &self.forward_y
     }
+    /// Z component of the direction the listener is facing
     pub fn forward_z(&self) -> &AudioParam {
         &self.forward_z
     }
+    /// X component of the direction of the top of the head of the listener
     pub fn up_x(&self) -> &AudioParam {
         &self.up_x
     }
+    /// Y component of the direction of the top of the head of the listener
     pub fn up_y(&self) -> &AudioParam {
         &self.up_y
     }
+    /// Z component of the direction of the top of the head of the listener
     pub fn up_z(&self) -> &AudioParam {
         &self.up_z
     }
@@ -198,6 +214,18 @@ use vecmath::{
     vec3_cross, vec3_dot, vec3_len, vec3_normalized, vec3_scale, vec3_square_len, vec3_sub, Vector3,
 };
 
+/// Default direction the listener is facing
+const DEFAULT_LISTENER_FORWARD: Vector3<f32> = [0., 0., -1.];
+/// Default direction of the top of the head of the listener
+const DEFAULT_LISTENER_UP: Vector3<f32> = [0., 1., 0.];
+
+/// Angle in degrees between two unit vectors
+///
+/// The dot product is clamped as rounding errors could push it out of the domain of `acos`
+fn unit_vectors_angle(a: Vector3<f32>, b: Vector3<f32>) -> f32 {
+    180. * vec3_dot(a, b).clamp(-1., 1.).acos() / PI
+}
+
 /// Direction to source position measured from listener in 3D
 pub fn azimuth_and_elevation(
     source_position: Vector3<f32>,
@@ -215,6 +243,18 @@ pub fn azimuth_and_elevation(
     // Calculate the source-listener vector.
     let source_listener = vec3_normalized(relative_pos);
 
+    // Zero vectors have no direction, use the defaults instead
+    let listener_forward = if vec3_square_len(listener_forward) == 0. {
+        DEFAULT_LISTENER_FORWARD
+    } else {
+        listener_forward
+    };
+    let listener_up = if vec3_square_len(listener_up) == 0. {
+        DEFAULT_LISTENER_UP
+    } else {
+        listener_up
+    };
+
     // Align axes.
     let listener_right = vec3_cross(listener_forward, listener_up);
 
@@ -230,7 +270,7 @@ pub fn azimuth_and_elevation(
     let up = vec3_cross(listener_right_norm, listener_forward_norm);
 
     // Determine elevation first
-    let mut elevation = 90. - 180. * vec3_dot(source_listener, up).acos() / PI;
+    let mut elevation = 90. - unit_vectors_angle(source_listener, up);
     if elevation > 90. {
         elevation = 180. - elevation;
     } else if elevation < -90. {
@@ -247,7 +287,7 @@ pub fn azimuth_and_elevation(
     }
     let projected_source = vec3_normalized(projected_source);
 
-    let mut azimuth = 180. * vec3_dot(projected_source, listener_right_norm).acos() / PI;
+    let mut azimuth = unit_vectors_angle(projected_source, listener_right_norm);
 
     // Source in front or behind the listener.
     let front_back = vec3_dot(projected_source, listener_forward_norm);
@@ -271,6 +311,40 @@ pub fn distance(source_position: Vector3<f32>, listener_position: Vector3<f32>)
     vec3_len(vec3_sub(source_position, listener_position))
 }
 
+/// Bounds of the doppler pitch ratio, reached at supersonic speeds
+pub(crate) const MAX_DOPPLER_RATE: f32 = 8.;
+
+/// Pitch ratio of the doppler effect for the given positions and velocities
+///
+/// Only the components of the velocities along the line between source and listener count.
+/// A source moving towards the listener, or a listener moving towards the source, raises the
+/// pitch. Speeds close to or above the speed of sound are clamped, the ratio stays in the
+/// `[1 / MAX_DOPPLER_RATE, MAX_DOPPLER_RATE]` range.
+pub fn doppler_rate(
+    source_position: Vector3<f32>,
+    listener_position: Vector3<f32>,
+    source_velocity: Vector3<f32>,
+    listener_velocity: Vector3<f32>,
+    speed_of_sound: f32,
+) -> f32 {
+    let source_listener = vec3_sub(listener_position, source_position);
+    // Handle degenerate case if source and listener are at the same point.
+    if vec3_square_len(source_listener) <= f32::MIN_POSITIVE {
+        return 1.;
+    }
+    let source_listener = vec3_normalized(source_listener);
+
+    // the denominator is at least `speed_of_sound / MAX_DOPPLER_RATE`
+    let max_source_speed = speed_of_sound * (1. - 1. / MAX_DOPPLER_RATE);
+    let source_speed =
+        vec3_dot(source_listener, source_velocity).clamp(-speed_of_sound, max_source_speed);
+    let listener_speed =
+        vec3_dot(source_listener, listener_velocity).clamp(-speed_of_sound, speed_of_sound);
+
+    let rate = (speed_of_sound - listener_speed) / (speed_of_sound - source_speed);
+    rate.clamp(1. / MAX_DOPPLER_RATE, MAX_DOPPLER_RATE)
+}
+
 /// Angle between two vectors in 3D
 pub fn angle(
     source_position: Vector3<f32>,
@@ -353,6 +427,79 @@ mod tests {
         assert_float_eq!(elevation, 90., abs <= 0.001);
     }
 
+    #[test]
+    fn azimuth_elevation_rotated_listener() {
+        // source in front of the default listener
+        let pos = [0., 0., -10.];
+        let (azimuth, _) = azimuth_and_elevation(pos, LP, LF, LU);
+        assert_float_eq!(azimuth, 0., abs <= 0.001);
+
+        // listener turned 90 degrees to the right, the source is now on its left side
+        let (azimuth, elevation) = azimuth_and_elevation(pos, LP, [1., 0., 0.], LU);
+        assert_float_eq!(azimuth, -90., abs <= 0.001);
+        assert_float_eq!(elevation, 0., abs <= 0.001);
+
+        // listener turned 90 degrees to the left, the source is now on its right side
+        let (azimuth, elevation) = azimuth_and_elevation(pos, LP, [-1., 0., 0.], LU);
+        assert_float_eq!(azimuth, 90., abs <= 0.001);
+        assert_float_eq!(elevation, 0., abs <= 0.001);
+
+        // listener lying on its right side, the source above its head is to the left
+        let pos = [0., 10., 0.];
+        let (azimuth, elevation) = azimuth_and_elevation(pos, LP, LF, [1., 0., 0.]);
+        assert_float_eq!(azimuth, -90., abs <= 0.001);
+        assert_float_eq!(elevation, 0., abs <= 0.001);
+    }
+
+    #[test]
+    fn azimuth_elevation_non_orthonormal_orientation() {
+        let positions = [[10., 0., -10.], [-3., 4., 5.], [0., -10., 0.], [1., 2., 3.]];
+
+        for pos in positions {
+            let expected = azimuth_and_elevation(pos, LP, LF, LU);
+
+            // scaled vectors and an up vector tilted towards the forward direction
+            let (azimuth, elevation) = azimuth_and_elevation(pos, LP, [0., 0., -5.], [0., 3., -2.]);
+            assert_float_eq!(azimuth, expected.0, abs <= 0.001);
+            assert_float_eq!(elevation, expected.1, abs <= 0.001);
+        }
+    }
+
+    #[test]
+    fn azimuth_elevation_zero_orientation() {
+        let pos = [10., 0., -10.];
+        let expected = azimuth_and_elevation(pos, LP, LF, LU);
+
+        let result = azimuth_and_elevation(pos, LP, [0., 0., 0.], LU);
+        assert_float_eq!(result.0, expected.0, abs <= 0.);
+        assert_float_eq!(result.1, expected.1, abs <= 0.);
+
+        let result = azimuth_and_elevation(pos, LP, LF, [0., 0., 0.]);
+        assert_float_eq!(result.0, expected.0, abs <= 0.);
+        assert_float_eq!(result.1, expected.1, abs <= 0.);
+
+        // parallel vectors do not define an orientation
+        let result = azimuth_and_elevation(pos, LP, [0., 2., 0.], LU);
+        assert_float_eq!(result.0, 0., abs <= 0.);
+        assert_float_eq!(result.1, 0., abs <= 0.);
+    }
+
+    #[test]
+    fn azimuth_elevation_rounding() {
+        // the dot products of nearly parallel unit vectors may exceed 1 due to rounding
+        let forward = [0.3, 0.1, -0.7];
+        let up = [0.2, 0.9, 0.1];
+        let right = vec3_normalized(vec3_cross(forward, up));
+
+        for scale in [1., 1e-3, 1e3, 7.3] {
+            let (azimuth, elevation) =
+                azimuth_and_elevation(vec3_scale(right, scale), LP, forward, up);
+            assert!(!azimuth.is_nan() && !elevation.is_nan());
+            assert_float_eq!(azimuth, 90., abs <= 0.01);
+            assert_float_eq!(elevation, 0., abs <= 0.01);
+        }
+    }
+
     #[test]
     fn angle_equal_pos() {
         let pos = [0., 0., 0.];
@@ -388,4 +535,62 @@ mod tests {
 
         assert_float_eq!(angle, 90., abs <= 0.);
     }
+
+    #[test]
+    fn doppler_rate_moving_source() {
+        let c = 340.;
+        let pos = [0., 0., -10.];
+
+        // static
+        let rate = doppler_rate(pos, LP, [0.; 3], [0.; 3], c);
+        assert_float_eq!(rate, 1., abs <= 0.);
+
+        // towards the listener
+        let rate = doppler_rate(pos, LP, [0., 0., 34.], [0.; 3], c);
+        assert_float_eq!(rate, 340. / 306., abs <= 1e-6);
+
+        // away from the listener
+        let rate = doppler_rate(pos, LP, [0., 0., -34.], [0.; 3], c);
+        assert_float_eq!(rate, 340. / 374., abs <= 1e-6);
+
+        // perpendicular to the listener direction
+        let rate = doppler_rate(pos, LP, [34., 0., 0.], [0.; 3], c);
+        assert_float_eq!(rate, 1., abs <= 1e-6);
+    }
+
+    #[test]
+    fn doppler_rate_moving_listener() {
+        let c = 340.;
+        let pos = [0., 0., -10.];
+
+        // towards the source
+        let rate = doppler_rate(pos, LP, [0.; 3], [0., 0., -34.], c);
+        assert_float_eq!(rate, 374. / 340., abs <= 1e-6);
+
+        // away from the source
+        let rate = doppler_rate(pos, LP, [0.; 3], [0., 0., 34.], c);
+        assert_float_eq!(rate, 306. / 340., abs <= 1e-6);
+
+        // moving along with the source
+        let rate = doppler_rate(pos, LP, [0., 0., 34.], [0., 0., 34.], c);
+        assert_float_eq!(rate, 306. / 306., abs <= 1e-6);
+    }
+
+    #[test]
+    fn doppler_rate_supersonic() {
+        let c = 340.;
+        let pos = [0., 0., -10.];
+
+        // at and above the speed of sound
+        for speed in [340., 680., f32::MAX] {
+            let rate = doppler_rate(pos, LP, [0., 0., speed], [0.; 3], c);
+            assert_float_eq!(rate, MAX_DOPPLER_RATE, abs <= 1e-6);
+            let rate = doppler_rate(pos, LP, [0., 0., -speed], [0., 0., speed], c);
+            assert_float_eq!(rate, 1. / MAX_DOPPLER_RATE, abs <= 1e-6);
+        }
+
+        // source and listener at the same point
+        let rate = doppler_rate(LP, LP, [0., 0., 34.], [0.; 3], c);
+        assert_float_eq!(rate, 1., abs <= 0.);
+    }
 }
@@ -0,0 +1,171 @@
+//! Lock-free single value channel from the control thread to the render thread
+
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Flag set on the shared index when the back slot holds a value the reader has not seen yet
+const DIRTY: usize = 0b100;
+
+/// Three slots, each side owns one, the third one (the back slot) is exchanged between them
+struct Shared<T> {
+    slots: [UnsafeCell<Option<T>>; 3],
+    /// Index of the back slot, with the `DIRTY` flag
+    back: AtomicUsize,
+}
+
+// SAFETY:
+// A slot is only accessed by the side owning its index, the ownership is transferred by the
+// atomic swap of the back index.
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+/// Create a triple buffer, returning the writing and reading side
+///
+/// Neither side ever blocks or allocates. The writer always overwrites the pending value
+/// (latest-wins), so the reader sees the last value written but may skip intermediate ones.
+pub(crate) fn triple_buffer<T>() -> (TripleBufferWriter<T>, TripleBufferReader<T>) {
+    let shared = Arc::new(Shared {
+        slots: [
+            UnsafeCell::new(None),
+            UnsafeCell::new(None),
+            UnsafeCell::new(None),
+        ],
+        back: AtomicUsize::new(1),
+    });
+
+    let writer = TripleBufferWriter {
+        shared: shared.clone(),
+        index: 0,
+    };
+    let reader = TripleBufferReader { shared, index: 2 };
+
+    (writer, reader)
+}
+
+/// Writing side of the triple buffer, lives in the control thread
+pub(crate) struct TripleBufferWriter<T> {
+    shared: Arc<Shared<T>>,
+    index: usize,
+}
+
+impl<T> TripleBufferWriter<T> {
+    /// Publish a new value for the reader
+    ///
+    /// The value previously held by the slot of the writer (i.e. a value that has been replaced
+    /// on the reading side, or that has never been read) is dropped here.
+    pub fn write(&mut self, value: T) {
+        // SAFETY: the writer owns the slot at `self.index`
+        unsafe { *self.shared.slots[self.index].get() = Some(value) };
+
+        let previous = self.shared.back.swap(self.index | DIRTY, Ordering::AcqRel);
+        self.index = previous & !DIRTY;
+    }
+}
+
+/// Reading side of the triple buffer, lives in the render thread
+pub(crate) struct TripleBufferReader<T> {
+    shared: Arc<Shared<T>>,
+    index: usize,
+}
+
+impl<T> TripleBufferReader<T> {
+    /// Acquire the latest value written, returns `true` if a new value is available
+    ///
+    /// The value replaced on the reading side is handed back to the writer, which drops it.
+    pub fn update(&mut self) -> bool {
+        // only the reader clears the flag, so it cannot be cleared between load and swap
+        if self.shared.back.load(Ordering::Relaxed) & DIRTY == 0 {
+            return false;
+        }
+
+        let previous = self.shared.back.swap(self.index, Ordering::AcqRel);
+        self.index = previous & !DIRTY;
+
+        true
+    }
+
+    /// The value acquired by the last [`update`](Self::update), if any
+    pub fn get(&self) -> Option<&T> {
+        // SAFETY: the reader owns the slot at `self.index`
+        unsafe { (*self.shared.slots[self.index].get()).as_ref() }
+    }
+
+    /// Mutable access to the value acquired by the last [`update`](Self::update), if any
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        // SAFETY: the reader owns the slot at `self.index`
+        unsafe { (*self.shared.slots[self.index].get()).as_mut() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_wins() {
+        let (mut writer, mut reader) = triple_buffer();
+        assert!(!reader.update());
+        assert_eq!(reader.get(), None);
+
+        writer.write(1);
+        writer.write(2);
+        writer.write(3);
+        assert!(reader.update());
+        assert_eq!(reader.get(), Some(&3));
+
+        // the value is kept until a new one is written
+        assert!(!reader.update());
+        assert_eq!(reader.get(), Some(&3));
+
+        writer.write(4);
+        assert!(reader.update());
+        assert_eq!(reader.get(), Some(&4));
+
+        // the reader may alter its value, a new value replaces it
+        *reader.get_mut().unwrap() = 5;
+        assert_eq!(reader.get(), Some(&5));
+        writer.write(6);
+        assert!(reader.update());
+        assert_eq!(reader.get_mut(), Some(&mut 6));
+    }
+
+    #[test]
+    fn test_values_are_dropped_by_writer() {
+        let (mut writer, mut reader) = triple_buffer();
+        let value = Arc::new(());
+
+        for _ in 0..10 {
+            writer.write(value.clone());
+            reader.update();
+        }
+
+        // one copy held by the reader, at most two in the other slots
+        assert!(Arc::strong_count(&value) <= 4);
+        drop(writer);
+        drop(reader);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn test_concurrent_updates() {
+        let (mut writer, mut reader) = triple_buffer();
+        const COUNT: usize = 100_000;
+
+        let handle = std::thread::spawn(move || {
+            (0..COUNT).for_each(|i| writer.write(i));
+        });
+
+        // values are seen in order, without tearing
+        let mut last = 0;
+        while last != COUNT - 1 {
+            if reader.update() {
+                let value = *reader.get().unwrap();
+                assert!(value >= last);
+                last = value;
+            }
+        }
+
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,768 @@
+//! Utilities for musical applications, filter and signal analysis
+//!
+//! ```
+//! use web_audio_api::util::{freq_to_midi, midi_to_freq, DEFAULT_A4};
+//!
+//! assert_eq!(midi_to_freq(69., DEFAULT_A4), 440.);
+//! assert_eq!(freq_to_midi(880., DEFAULT_A4), 81.);
+//! ```
+
+use crate::analysis::power_spectrum;
+use crate::node::{BiquadFilterNode, IIRFilterNode};
+use crate::AudioBuffer;
+
+/// Standard frequency in Hz of the A4 reference note (MIDI note 69)
+pub const DEFAULT_A4: f32 = 440.;
+
+/// MIDI note number of the A4 reference note
+const A4_NOTE: f32 = 69.;
+
+/// Frequency in Hz of a MIDI note, in twelve-tone equal temperament tuned to `a4`
+///
+/// Fractional note numbers are allowed for microtuning, e.g. `60.5` is a quarter tone above C4.
+/// Note numbers outside the MIDI range `[0, 127]` are extrapolated.
+#[must_use]
+pub fn midi_to_freq(note: f32, a4: f32) -> f32 {
+    a4 * ((note - A4_NOTE) / 12.).exp2()
+}
+
+/// Fractional MIDI note number of a frequency in Hz, in twelve-tone equal temperament tuned to
+/// `a4`
+///
+/// This is the inverse of [`midi_to_freq`]. Round the result to get the nearest MIDI note, the
+/// remainder is the deviation in semitones.
+#[must_use]
+pub fn freq_to_midi(freq: f32, a4: f32) -> f32 {
+    A4_NOTE + 12. * (freq / a4).log2()
+}
+
+/// Audio nodes that can compute their frequency response, see [`plot_frequency_response`]
+pub trait FrequencyResponse {
+    /// Returns the magnitude and phase response for the specified frequencies
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if arguments' lengths don't match
+    fn get_frequency_response(
+        &self,
+        frequency_hz: &[f32],
+        mag_response: &mut [f32],
+        phase_response: &mut [f32],
+    );
+}
+
+impl FrequencyResponse for BiquadFilterNode {
+    fn get_frequency_response(
+        &self,
+        frequency_hz: &[f32],
+        mag_response: &mut [f32],
+        phase_response: &mut [f32],
+    ) {
+        BiquadFilterNode::get_frequency_response(self, frequency_hz, mag_response, phase_response);
+    }
+}
+
+impl FrequencyResponse for IIRFilterNode {
+    fn get_frequency_response(
+        &self,
+        frequency_hz: &[f32],
+        mag_response: &mut [f32],
+        phase_response: &mut [f32],
+    ) {
+        IIRFilterNode::get_frequency_response(self, frequency_hz, mag_response, phase_response);
+    }
+}
+
+/// Frequency response of `node` at `points` log-spaced frequencies from `f_min` to `f_max`
+///
+/// Returns a `(frequency in Hz, magnitude in dB, phase in radians)` tuple per frequency, ready
+/// to be plotted on a log scale. A magnitude of zero maps to `-inf` dB. As for
+/// `get_frequency_response`, frequencies above the Nyquist frequency yield `NaN` values.
+///
+/// ```no_run
+/// use web_audio_api::context::{AudioContext, BaseAudioContext};
+/// use web_audio_api::util::plot_frequency_response;
+///
+/// let context = AudioContext::default();
+/// let filter = context.create_biquad_filter();
+///
+/// for (frequency, magnitude, _phase) in plot_frequency_response(&filter, 20., 20_000., 32) {
+///     println!("{:>8.1} Hz {:>6.1} dB", frequency, magnitude);
+/// }
+/// ```
+///
+/// # Panics
+///
+/// Will panic if `f_min` is not strictly positive, if `f_max` is smaller than `f_min`, or if
+/// `points` is zero
+#[must_use]
+pub fn plot_frequency_response<N: FrequencyResponse>(
+    node: &N,
+    f_min: f32,
+    f_max: f32,
+    points: usize,
+) -> Vec<(f32, f32, f32)> {
+    assert!(
+        f_min > 0. && f_min.is_finite(),
+        "RangeError - f_min ({:?}) should be strictly positive, as frequencies are log-spaced",
+        f_min
+    );
+    assert!(
+        f_max >= f_min && f_max.is_finite(),
+        "RangeError - f_max ({:?}) should be greater than or equal to f_min ({:?})",
+        f_max,
+        f_min
+    );
+    assert!(points > 0, "RangeError - points should be at least 1");
+
+    // equal ratio between consecutive frequencies, computed in f64 to hit f_max exactly
+    let ratio = (f_max as f64 / f_min as f64).ln() / (points - 1).max(1) as f64;
+    let frequency_hz: Vec<f32> = (0..points)
+        .map(|i| (f_min as f64 * (ratio * i as f64).exp()) as f32)
+        .collect();
+
+    let mut mag_response = vec![0.; points];
+    let mut phase_response = vec![0.; points];
+    node.get_frequency_response(&frequency_hz, &mut mag_response, &mut phase_response);
+
+    frequency_hz
+        .into_iter()
+        .zip(mag_response)
+        .zip(phase_response)
+        .map(|((frequency, mag), phase)| (frequency, 20. * mag.log10(), phase))
+        .collect()
+}
+
+/// Number of bins at each side of a partial attributed to it: the main lobe of the Blackman window
+/// (3 bins at each side) and its first side lobes
+const PARTIAL_HALF_WIDTH: usize = 6;
+
+/// Energy of a periodic signal split by spectral content
+struct HarmonicEnergy {
+    fundamental: f64,
+    /// Harmonics above the fundamental, up to the Nyquist frequency
+    harmonics: f64,
+    /// Everything that is neither DC nor a partial, e.g. aliased partials
+    non_harmonic: f64,
+    total: f64,
+}
+
+/// Split the spectrum of `samples` in the bins around DC, the fundamental and its harmonics, and
+/// the remaining bins
+///
+/// The partials do not need to land on a bin: all bins within the main lobe of the window around
+/// the exact frequency of a partial are attributed to it.
+#[track_caller]
+fn harmonic_energy(samples: &[f32], fundamental: f32, sample_rate: f32) -> HarmonicEnergy {
+    assert!(
+        fundamental > 0. && fundamental < sample_rate / 2.,
+        "RangeError - fundamental ({:?}) should be in the range (0, {:?})",
+        fundamental,
+        sample_rate / 2.
+    );
+
+    // the partials must be far enough apart to be resolved
+    let bin_width = sample_rate as f64 / samples.len() as f64;
+    let partial_distance = fundamental as f64 / bin_width;
+    assert!(
+        partial_distance >= (2 * PARTIAL_HALF_WIDTH + 2) as f64,
+        "RangeError - {} samples are not enough to resolve a fundamental of {:?} Hz",
+        samples.len(),
+        fundamental
+    );
+
+    let spectrum = power_spectrum(samples);
+
+    let mut energy = HarmonicEnergy {
+        fundamental: 0.,
+        harmonics: 0.,
+        non_harmonic: 0.,
+        total: 0.,
+    };
+
+    spectrum.iter().enumerate().for_each(|(bin, &power)| {
+        let power = power as f64;
+        energy.total += power;
+
+        // nearest partial, DC being partial 0
+        let partial = (bin as f64 / partial_distance).round();
+        let distance = (bin as f64 - partial * partial_distance).abs();
+
+        if distance > PARTIAL_HALF_WIDTH as f64 + 0.5 {
+            energy.non_harmonic += power;
+        } else if partial == 1. {
+            energy.fundamental += power;
+        } else if partial > 1. {
+            energy.harmonics += power;
+        }
+    });
+
+    energy
+}
+
+/// Total harmonic distortion of a periodic signal with the given `fundamental` frequency
+///
+/// Returns the ratio of the RMS amplitude of the harmonics (up to the Nyquist frequency) to the
+/// RMS amplitude of the fundamental, e.g. `0.01` for 1% distortion. A pure sine yields `0.`, an
+/// ideal sawtooth `sqrt(pi^2 / 6 - 1)` (about `0.8`) when all its harmonics are below the Nyquist
+/// frequency.
+///
+/// A Blackman window is applied to limit spectral leakage, and the fundamental does not need to
+/// land on a bin of the FFT. The samples should contain at least about ten periods of the
+/// fundamental.
+///
+/// # Panics
+///
+/// Will panic if `fundamental` is not in the range `(0, sample_rate / 2)`, or if the signal is too
+/// short to resolve its harmonics
+#[must_use]
+pub fn measure_thd(samples: &[f32], fundamental: f32, sample_rate: f32) -> f32 {
+    let energy = harmonic_energy(samples, fundamental, sample_rate);
+    (energy.harmonics / energy.fundamental).sqrt() as f32
+}
+
+/// Relative level of the non-harmonic content of a periodic signal with the given `fundamental`
+/// frequency
+///
+/// Returns the ratio of the RMS amplitude of the spectrum outside DC, the fundamental and its
+/// harmonics to the RMS amplitude of the full signal. For an oscillator or waveshaper this
+/// measures aliasing: partials above the Nyquist frequency fold back in between the harmonics.
+/// Convert to dB with `20. * ratio.log10()`.
+///
+/// See [`measure_thd`] for the analysis method.
+///
+/// # Panics
+///
+/// Will panic if `fundamental` is not in the range `(0, sample_rate / 2)`, or if the signal is too
+/// short to resolve its harmonics
+#[must_use]
+pub fn measure_aliasing(samples: &[f32], fundamental: f32, sample_rate: f32) -> f32 {
+    let energy = harmonic_energy(samples, fundamental, sample_rate);
+    (energy.non_harmonic / energy.total).sqrt() as f32
+}
+
+/// Offset of the loudness scale, which compensates the gain of the K-weighting at 1 kHz
+const LUFS_OFFSET: f64 = -0.691;
+/// Blocks below this loudness (in LUFS) are ignored
+const ABSOLUTE_GATE: f64 = -70.;
+/// Blocks below the loudness of the blocks above the absolute gate by this amount (in LU) are
+/// ignored
+const RELATIVE_GATE: f64 = -10.;
+/// Duration of the gating blocks in seconds, consecutive blocks overlap by 75%
+const GATING_BLOCK: f32 = 0.4;
+
+/// Integrated loudness of a signal, see [`measure_lufs`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Loudness {
+    /// Loudness in LUFS, `-inf` for silence
+    pub lufs: f32,
+    /// Whether the gating was applied, i.e. the signal lasts at least 400 ms
+    pub gated: bool,
+}
+
+/// Coefficients `[b0, b1, b2, a1, a2]`, normalized by `a0`, of the two stages of the
+/// K-weighting filter: a high shelf modelling the head and a high pass (the RLB weighting)
+///
+/// The coefficients are derived from the analog prototypes of the filters, so they match the
+/// reference coefficients at 48 kHz and hold at other sample rates.
+fn k_weighting(sample_rate: f64) -> [[f64; 5]; 2] {
+    use std::f64::consts::PI;
+
+    let k = (PI * 1_681.974_450_955_533 / sample_rate).tan();
+    let q = 0.707_175_236_955_419_6;
+    let vh = 10_f64.powf(3.999_843_853_973_347 / 20.);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+    let a0 = 1. + k / q + k * k;
+    let shelf = [
+        (vh + vb * k / q + k * k) / a0,
+        2. * (k * k - vh) / a0,
+        (vh - vb * k / q + k * k) / a0,
+        2. * (k * k - 1.) / a0,
+        (1. - k / q + k * k) / a0,
+    ];
+
+    let k = (PI * 38.135_470_876_024_44 / sample_rate).tan();
+    let q = 0.500_327_037_323_877_3;
+    let a0 = 1. + k / q + k * k;
+    let high_pass = [
+        1.,
+        -2.,
+        1.,
+        2. * (k * k - 1.) / a0,
+        (1. - k / q + k * k) / a0,
+    ];
+
+    [shelf, high_pass]
+}
+
+/// Cumulative energy of the K-weighted `samples`, starting with `0.`, so that the energy of the
+/// samples in `start..end` is `energy[end] - energy[start]`
+fn cumulative_k_weighted_energy(samples: &[f32], sample_rate: f32) -> Vec<f64> {
+    let stages = k_weighting(f64::from(sample_rate));
+    // transposed direct form II
+    let mut state = [[0.; 2]; 2];
+
+    let mut energy = Vec::with_capacity(samples.len() + 1);
+    energy.push(0.);
+    samples.iter().fold(0., |sum, &x| {
+        let y = stages.iter().zip(state.iter_mut()).fold(
+            f64::from(x),
+            |x, ([b0, b1, b2, a1, a2], s)| {
+                let y = b0 * x + s[0];
+                s[0] = b1 * x - a1 * y + s[1];
+                s[1] = b2 * x - a2 * y;
+                y
+            },
+        );
+        let sum = sum + y * y;
+        energy.push(sum);
+        sum
+    });
+
+    energy
+}
+
+/// Integrated loudness of the `buffer`, in LUFS, as specified by ITU-R BS.1770
+///
+/// The channels are K-weighted and their power is measured over blocks of 400 ms overlapping by
+/// 75%. The blocks quieter than -70 LUFS, then the blocks 10 LU below the loudness of the
+/// remaining ones, are discarded. Use the result to normalize a rendering to a target loudness,
+/// e.g. with a gain of `10_f32.powf((-23. - loudness.lufs) / 20.)`.
+///
+/// The surround channels of quad (4 channels) and 5.1 (6 channels) buffers are weighted by
+/// 1.41 and the LFE channel is ignored, all channels count equally for other layouts.
+///
+/// A buffer shorter than a block cannot be gated, its loudness is then measured over the whole
+/// buffer and [`Loudness::gated`] is `false`. Silent buffers, and buffers that are only quieter
+/// than the absolute gate, have a loudness of `-inf`.
+///
+/// ```
+/// use web_audio_api::AudioBuffer;
+/// use web_audio_api::util::measure_lufs;
+///
+/// // a full scale 1 kHz sine is -3 LUFS
+/// let samples: Vec<f32> = (0..48_000)
+///     .map(|i| (2. * std::f32::consts::PI * 1000. * i as f32 / 48_000.).sin())
+///     .collect();
+/// let buffer = AudioBuffer::from(vec![samples], 48_000.);
+///
+/// let loudness = measure_lufs(&buffer);
+/// assert!(loudness.gated);
+/// assert!((loudness.lufs + 3.01).abs() < 0.05);
+/// ```
+#[must_use]
+pub fn measure_lufs(buffer: &AudioBuffer) -> Loudness {
+    let sample_rate = buffer.sample_rate();
+    let length = buffer.length();
+
+    let weights: &[f64] = match buffer.number_of_channels() {
+        4 => &[1., 1., 1.41, 1.41],
+        6 => &[1., 1., 1., 0., 1.41, 1.41],
+        _ => &[],
+    };
+    let energies: Vec<Vec<f64>> = (0..buffer.number_of_channels())
+        .map(|c| cumulative_k_weighted_energy(buffer.get_channel_data(c), sample_rate))
+        .collect();
+
+    // weighted sum of the mean square of the channels over `start..end`
+    let power = |start: usize, end: usize| {
+        let energy: f64 = energies
+            .iter()
+            .enumerate()
+            .map(|(c, energy)| weights.get(c).unwrap_or(&1.) * (energy[end] - energy[start]))
+            .sum();
+        energy / (end - start) as f64
+    };
+    let loudness = |power: f64| LUFS_OFFSET + 10. * power.log10();
+
+    let block_length = (GATING_BLOCK * sample_rate).round() as usize;
+    if length < block_length {
+        let lufs = if length == 0 {
+            f64::NEG_INFINITY
+        } else {
+            loudness(power(0, length))
+        };
+
+        return Loudness {
+            lufs: lufs as f32,
+            gated: false,
+        };
+    }
+
+    let step = block_length / 4;
+    let blocks: Vec<f64> = (0..=(length - block_length) / step)
+        .map(|i| power(i * step, i * step + block_length))
+        .collect();
+    // mean power of the blocks above the threshold, `NaN` if there are none
+    let gated_power = |threshold: f64| {
+        let (sum, count) = blocks
+            .iter()
+            .filter(|&&power| loudness(power) > threshold)
+            .fold((0., 0), |(sum, count), power| (sum + power, count + 1));
+        sum / count as f64
+    };
+
+    let absolute = gated_power(ABSOLUTE_GATE);
+    let lufs = if absolute.is_nan() {
+        f64::NEG_INFINITY
+    } else {
+        let threshold = (loudness(absolute) + RELATIVE_GATE).max(ABSOLUTE_GATE);
+        loudness(gated_power(threshold))
+    };
+
+    Loudness {
+        lufs: lufs as f32,
+        gated: true,
+    }
+}
+
+/// Mono compatibility of a stereo buffer, i.e. how much of its level survives a downmix to mono
+///
+/// Returns the ratio of the RMS level of the mono downmix `(L + R) / 2` to the average RMS
+/// level of the two channels. The score is `1` when the channels are identical, about `0.71`
+/// (`1 / sqrt(2)`) when they are uncorrelated, e.g. for a wide stereo reverb, and close to `0`
+/// when they are out of phase, as the channels cancel each other in mono. A score well below
+/// `0.71` reveals phase cancellation.
+///
+/// Mono and silent buffers have a score of `1`. Only the first two channels, i.e. the front
+/// left and right channels, of buffers with more channels are considered.
+///
+/// ```
+/// use web_audio_api::AudioBuffer;
+/// use web_audio_api::util::mono_compatibility;
+///
+/// let left: Vec<f32> = (0..48_000)
+///     .map(|i| (2. * std::f32::consts::PI * 1000. * i as f32 / 48_000.).sin())
+///     .collect();
+/// let right: Vec<f32> = left.iter().map(|v| -v).collect();
+///
+/// // polarity inversion of one of the channels
+/// let buffer = AudioBuffer::from(vec![left, right], 48_000.);
+/// assert!(mono_compatibility(&buffer) < 1e-6);
+/// ```
+#[must_use]
+pub fn mono_compatibility(buffer: &AudioBuffer) -> f32 {
+    if buffer.number_of_channels() < 2 {
+        return 1.;
+    }
+
+    let left = buffer.get_channel_data(0);
+    let right = buffer.get_channel_data(1);
+    let rms = |samples: &[f64]| {
+        (samples.iter().map(|v| v * v).sum::<f64>() / samples.len() as f64).sqrt()
+    };
+
+    let left: Vec<f64> = left.iter().map(|&v| f64::from(v)).collect();
+    let right: Vec<f64> = right.iter().map(|&v| f64::from(v)).collect();
+    let mono: Vec<f64> = left.iter().zip(&right).map(|(l, r)| (l + r) / 2.).collect();
+    let average = (rms(&left) + rms(&right)) / 2.;
+
+    // silent or empty buffer
+    if average == 0. || average.is_nan() {
+        return 1.;
+    }
+
+    (rms(&mono) / average) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use float_eq::assert_float_eq;
+
+    use crate::context::{BaseAudioContext, OfflineAudioContext};
+    use crate::node::BiquadFilterType;
+
+    use super::*;
+
+    #[test]
+    fn test_midi_to_freq() {
+        assert_float_eq!(midi_to_freq(69., DEFAULT_A4), 440., abs <= 0.);
+        assert_float_eq!(midi_to_freq(57., DEFAULT_A4), 220., abs <= 0.);
+        assert_float_eq!(midi_to_freq(81., DEFAULT_A4), 880., abs <= 0.);
+        assert_float_eq!(midi_to_freq(60., DEFAULT_A4), 261.62558, abs <= 1e-3);
+        // out of MIDI range
+        assert_float_eq!(midi_to_freq(129., DEFAULT_A4), 14080., abs <= 1e-2);
+        assert_float_eq!(midi_to_freq(-3., DEFAULT_A4), 6.875, abs <= 1e-5);
+    }
+
+    #[test]
+    fn test_tuning_reference() {
+        assert_float_eq!(midi_to_freq(69., 432.), 432., abs <= 0.);
+        assert_float_eq!(midi_to_freq(57., 415.), 207.5, abs <= 0.);
+        assert_float_eq!(freq_to_midi(432., 432.), 69., abs <= 0.);
+    }
+
+    #[test]
+    fn test_microtuning() {
+        // a quarter tone is half a semitone
+        let quarter_tone = midi_to_freq(69.5, DEFAULT_A4);
+        assert_float_eq!(quarter_tone, 440. * 2_f32.powf(1. / 24.), rmax <= 1e-6);
+        assert_float_eq!(freq_to_midi(quarter_tone, DEFAULT_A4), 69.5, abs <= 1e-5);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for a4 in [415., 440., 442.] {
+            for note in 0..=127 {
+                let note = note as f32;
+                let freq = midi_to_freq(note, a4);
+                assert_float_eq!(freq_to_midi(freq, a4), note, abs <= 1e-4);
+            }
+
+            for freq in [20., 100., 1000., 10_000., 20_000.] {
+                let note = freq_to_midi(freq, a4);
+                assert_float_eq!(midi_to_freq(note, a4), freq, rmax <= 1e-5);
+            }
+        }
+    }
+
+    fn sine(frequency: f64, sample_rate: f64, length: usize) -> Vec<f32> {
+        (0..length)
+            .map(|i| (2. * std::f64::consts::PI * frequency * i as f64 / sample_rate).sin() as f32)
+            .collect()
+    }
+
+    #[test]
+    fn test_thd_sine() {
+        // fundamental between two bins
+        let samples = sine(1234.5, 44_100., 8192);
+        assert!(measure_thd(&samples, 1234.5, 44_100.) < 1e-3);
+        assert!(measure_aliasing(&samples, 1234.5, 44_100.) < 1e-3);
+    }
+
+    #[test]
+    fn test_thd_harmonics() {
+        // fundamental and 2nd and 3rd harmonics at 10% and 5%
+        let fundamental = sine(1000., 44_100., 8192);
+        let second = sine(2000., 44_100., 8192);
+        let third = sine(3000., 44_100., 8192);
+        let samples: Vec<f32> = (0..8192)
+            .map(|i| fundamental[i] + 0.1 * second[i] + 0.05 * third[i])
+            .collect();
+
+        let expected = (0.1_f32.powi(2) + 0.05_f32.powi(2)).sqrt();
+        assert_float_eq!(
+            measure_thd(&samples, 1000., 44_100.),
+            expected,
+            rmax <= 0.01
+        );
+        assert!(measure_aliasing(&samples, 1000., 44_100.) < 1e-3);
+
+        // a partial in between the harmonics is not distortion, but aliasing
+        let spurious = sine(1500., 44_100., 8192);
+        let samples: Vec<f32> = (0..8192)
+            .map(|i| fundamental[i] + 0.1 * spurious[i])
+            .collect();
+        assert!(measure_thd(&samples, 1000., 44_100.) < 1e-3);
+        let expected = 0.1 / (1. + 0.1_f32.powi(2)).sqrt();
+        assert_float_eq!(
+            measure_aliasing(&samples, 1000., 44_100.),
+            expected,
+            rmax <= 0.01
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_thd_too_short() {
+        let samples = sine(100., 44_100., 1024);
+        let _ = measure_thd(&samples, 100., 44_100.);
+    }
+
+    #[test]
+    fn test_log_spacing() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let filter = context.create_biquad_filter();
+
+        let response = plot_frequency_response(&filter, 10., 10_000., 4);
+        let frequencies: Vec<f32> = response.iter().map(|(f, _, _)| *f).collect();
+        assert_float_eq!(
+            frequencies[..],
+            [10., 100., 1000., 10_000.][..],
+            rmax_all <= 1e-6
+        );
+
+        let response = plot_frequency_response(&filter, 440., 440., 1);
+        assert_eq!(response.len(), 1);
+        assert_float_eq!(response[0].0, 440., abs <= 0.);
+    }
+
+    #[test]
+    fn test_lowpass_cutoff() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let filter = context.create_biquad_filter();
+        filter.set_type(BiquadFilterType::Lowpass);
+        filter.frequency().set_value(1000.);
+        // Butterworth response: the gain at the cutoff frequency is 1 / sqrt(2), i.e. -3dB
+        filter.q().set_value(-3.0103);
+
+        let response = plot_frequency_response(&filter, 20., 20_000., 1000);
+
+        // flat pass band, steep stop band
+        assert_float_eq!(response[0].1, 0., abs <= 0.01);
+        assert!(response[999].1 < -40.);
+
+        let cutoff = response
+            .iter()
+            .find(|(_, magnitude, _)| *magnitude < -3.)
+            .unwrap()
+            .0;
+        assert_float_eq!(cutoff, 1000., rmax <= 0.01);
+    }
+
+    #[test]
+    fn test_zero_magnitude() {
+        struct Mute;
+
+        impl FrequencyResponse for Mute {
+            fn get_frequency_response(
+                &self,
+                _frequency_hz: &[f32],
+                mag_response: &mut [f32],
+                phase_response: &mut [f32],
+            ) {
+                mag_response.iter_mut().for_each(|m| *m = 0.);
+                phase_response.iter_mut().for_each(|p| *p = 0.);
+            }
+        }
+
+        let response = plot_frequency_response(&Mute, 20., 20_000., 8);
+        assert!(response
+            .iter()
+            .all(|(_, magnitude, _)| *magnitude == f32::NEG_INFINITY));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_f_min() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let filter = context.create_biquad_filter();
+        let _ = plot_frequency_response(&filter, 0., 20_000., 8);
+    }
+
+    /// Stereo 1 kHz sine at the given level in dBFS on both channels, cf. EBU Tech 3341
+    fn calibrated_sine(level: f64, sample_rate: f64, length: usize) -> AudioBuffer {
+        let amplitude = 10_f64.powf(level / 20.) as f32;
+        let channel: Vec<f32> = sine(1000., sample_rate, length)
+            .iter()
+            .map(|v| v * amplitude)
+            .collect();
+        AudioBuffer::from(vec![channel.clone(), channel], sample_rate as f32)
+    }
+
+    #[test]
+    fn test_lufs_calibration() {
+        for &sample_rate in &[44_100., 48_000., 96_000.] {
+            for &level in &[-23., -33.] {
+                let buffer = calibrated_sine(level, sample_rate, 5 * sample_rate as usize);
+                let loudness = measure_lufs(&buffer);
+                assert!(loudness.gated);
+                assert_float_eq!(loudness.lufs, level as f32, abs <= 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lufs_gating() {
+        let sample_rate = 48_000.;
+        // long enough for the few blocks straddling a transition to be negligible
+        let loud = calibrated_sine(-23., sample_rate, 48_000 * 10);
+        let quiet = calibrated_sine(-40., sample_rate, 48_000 * 10);
+        let concat = |parts: &[&AudioBuffer]| {
+            let channel: Vec<f32> = parts
+                .iter()
+                .flat_map(|b| b.get_channel_data(0).to_vec())
+                .collect();
+            AudioBuffer::from(vec![channel.clone(), channel], sample_rate as f32)
+        };
+
+        // silence is below the absolute gate
+        let silence = AudioBuffer::from(vec![vec![0.; 48_000 * 10]; 2], sample_rate as f32);
+        let loudness = measure_lufs(&concat(&[&loud, &silence]));
+        assert_float_eq!(loudness.lufs, -23., abs <= 0.1);
+
+        // content 17 LU below the rest is below the relative gate
+        let loudness = measure_lufs(&concat(&[&loud, &quiet]));
+        assert_float_eq!(loudness.lufs, -23., abs <= 0.1);
+    }
+
+    #[test]
+    fn test_lufs_short_buffer() {
+        // 300 ms, shorter than a gating block
+        let loudness = measure_lufs(&calibrated_sine(-23., 48_000., 14_400));
+        assert!(!loudness.gated);
+        assert_float_eq!(loudness.lufs, -23., abs <= 0.1);
+    }
+
+    #[test]
+    fn test_lufs_silence() {
+        let silence = AudioBuffer::from(vec![vec![0.; 48_000]; 2], 48_000.);
+        let loudness = measure_lufs(&silence);
+        assert!(loudness.gated);
+        assert_eq!(loudness.lufs, f32::NEG_INFINITY);
+
+        let short = AudioBuffer::from(vec![vec![0.; 128]], 48_000.);
+        let loudness = measure_lufs(&short);
+        assert!(!loudness.gated);
+        assert_eq!(loudness.lufs, f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_lufs_surround_weights() {
+        // the same signal on a surround channel is 1.5 dB louder (10 * log10(1.41))
+        let sample_rate = 48_000.;
+        let signal = calibrated_sine(-23., sample_rate, 48_000)
+            .get_channel_data(0)
+            .to_vec();
+        let silence = vec![0.; 48_000];
+
+        let mut front = vec![silence.clone(); 6];
+        front[0] = signal.clone();
+        let front = measure_lufs(&AudioBuffer::from(front, sample_rate as f32));
+
+        let mut surround = vec![silence.clone(); 6];
+        surround[4] = signal.clone();
+        let surround = measure_lufs(&AudioBuffer::from(surround, sample_rate as f32));
+        assert_float_eq!(surround.lufs - front.lufs, 1.49, abs <= 0.01);
+
+        // the LFE channel is ignored
+        let mut lfe = vec![silence; 6];
+        lfe[3] = signal;
+        let lfe = measure_lufs(&AudioBuffer::from(lfe, sample_rate as f32));
+        assert_eq!(lfe.lufs, f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_mono_compatibility() {
+        let sample_rate = 48_000.;
+        let left = sine(1000., sample_rate, 48_000);
+        let stereo = |right: Vec<f32>| AudioBuffer::from(vec![left.clone(), right], 48_000.);
+
+        // in phase, also at different levels
+        let score = mono_compatibility(&stereo(left.clone()));
+        assert_float_eq!(score, 1., abs <= 1e-6);
+        let score = mono_compatibility(&stereo(left.iter().map(|v| v * 0.5).collect()));
+        assert_float_eq!(score, 1., abs <= 1e-6);
+
+        // uncorrelated, the frequencies are orthogonal over one second
+        let score = mono_compatibility(&stereo(sine(1500., sample_rate, 48_000)));
+        assert_float_eq!(score, std::f32::consts::FRAC_1_SQRT_2, abs <= 1e-3);
+
+        // anti-phase
+        let score = mono_compatibility(&stereo(left.iter().map(|v| -v).collect()));
+        assert_float_eq!(score, 0., abs <= 1e-6);
+
+        // a quarter period of delay between the channels
+        let score = mono_compatibility(&stereo(sine(1000., sample_rate, 48_012)[12..].to_vec()));
+        assert_float_eq!(score, std::f32::consts::FRAC_1_SQRT_2, abs <= 1e-3);
+    }
+
+    #[test]
+    fn test_mono_compatibility_mono_and_silence() {
+        let mono = AudioBuffer::from(vec![sine(1000., 48_000., 480)], 48_000.);
+        assert_float_eq!(mono_compatibility(&mono), 1., abs <= 0.);
+
+        let silence = AudioBuffer::from(vec![vec![0.; 480]; 2], 48_000.);
+        assert_float_eq!(mono_compatibility(&silence), 1., abs <= 0.);
+    }
+}
@@ -0,0 +1,371 @@
+//! Polyphonic voice allocation
+
+use crate::context::BaseAudioContext;
+use crate::envelope::AdsrEnvelope;
+use crate::node::{
+    AudioNode, AudioScheduledSourceNode, GainNode, GainOptions, OscillatorNode, OscillatorOptions,
+    OscillatorType,
+};
+use crate::util::{midi_to_freq, DEFAULT_A4};
+
+/// Duration in seconds of the fade out applied to a voice before it is stolen
+const STEAL_FADE_DURATION: f64 = 0.005;
+
+/// Options for constructing a [`VoiceManager`]
+#[derive(Clone, Debug)]
+pub struct VoiceManagerOptions {
+    /// Maximum number of simultaneously sounding notes
+    pub voices: usize,
+    /// The shape of the waveform of each voice
+    pub type_: OscillatorType,
+    /// Amplitude envelope applied to each note
+    pub envelope: AdsrEnvelope,
+}
+
+impl Default for VoiceManagerOptions {
+    fn default() -> Self {
+        Self {
+            voices: 8,
+            type_: OscillatorType::default(),
+            envelope: AdsrEnvelope::default(),
+        }
+    }
+}
+
+struct Voice {
+    oscillator: OscillatorNode,
+    amp: GainNode,
+    /// MIDI note held by this voice, `None` when idle or releasing
+    note: Option<u8>,
+    /// Order in which the voices were triggered or released
+    age: u64,
+}
+
+/// Polyphonic synthesizer allocating a fixed number of voices to MIDI notes
+///
+/// Each voice is an [`OscillatorNode`] running through a [`GainNode`] shaped by an
+/// [`AdsrEnvelope`]. A note-on is assigned to the voice that has been free for the longest time.
+/// When all voices are sounding, the oldest note is stolen: it is faded out in a few milliseconds
+/// before the new note starts, to prevent clicks.
+///
+/// ```no_run
+/// use web_audio_api::context::{BaseAudioContext, AudioContext};
+/// use web_audio_api::node::AudioNode;
+/// use web_audio_api::{AdsrEnvelope, VoiceManager, VoiceManagerOptions};
+///
+/// let context = AudioContext::default();
+///
+/// let options = VoiceManagerOptions {
+///     voices: 4,
+///     envelope: AdsrEnvelope {
+///         attack: 0.01,
+///         decay: 0.1,
+///         sustain: 0.7,
+///         release: 0.5,
+///     },
+///     ..VoiceManagerOptions::default()
+/// };
+/// let mut synth = VoiceManager::new(&context, options);
+/// synth.output().connect(&context.destination());
+///
+/// let now = context.current_time();
+/// synth.note_on(60, 100, now);
+/// synth.note_on(64, 100, now);
+/// synth.note_off(60, now + 1.);
+/// synth.note_off(64, now + 1.);
+/// ```
+pub struct VoiceManager {
+    voices: Vec<Voice>,
+    output: GainNode,
+    envelope: AdsrEnvelope,
+    /// Incremented on each voice allocation or release
+    clock: u64,
+}
+
+impl VoiceManager {
+    /// Creates the voices and their common output node
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the number of voices is zero
+    pub fn new<C: BaseAudioContext>(context: &C, options: VoiceManagerOptions) -> Self {
+        let VoiceManagerOptions {
+            voices,
+            type_,
+            envelope,
+        } = options;
+
+        assert!(
+            voices > 0,
+            "RangeError - the number of voices should be at least 1"
+        );
+
+        let output = GainNode::new(context, GainOptions::default());
+
+        let voices = (0..voices)
+            .map(|_| {
+                let osc_options = OscillatorOptions {
+                    type_,
+                    ..OscillatorOptions::default()
+                };
+                let oscillator = OscillatorNode::new(context, osc_options);
+
+                let amp_options = GainOptions {
+                    gain: 0.,
+                    ..GainOptions::default()
+                };
+                let amp = GainNode::new(context, amp_options);
+
+                oscillator.connect(&amp);
+                amp.connect(&output);
+                oscillator.start();
+
+                Voice {
+                    oscillator,
+                    amp,
+                    note: None,
+                    age: 0,
+                }
+            })
+            .collect();
+
+        Self {
+            voices,
+            output,
+            envelope,
+            clock: 0,
+        }
+    }
+
+    /// Node summing all voices, connect it to hear the synthesizer
+    #[must_use]
+    pub fn output(&self) -> &GainNode {
+        &self.output
+    }
+
+    /// The MIDI note held by each voice, `None` for a voice that is idle or releasing
+    #[must_use]
+    pub fn notes(&self) -> Vec<Option<u8>> {
+        self.voices.iter().map(|voice| voice.note).collect()
+    }
+
+    /// Start playing `note` with the given `velocity` at time `when`
+    ///
+    /// If `note` is already sounding its voice is retriggered. A `velocity` of zero is handled
+    /// as a note-off, as is customary in MIDI.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `note` or `velocity` is larger than 127, or if `when` is negative
+    pub fn note_on(&mut self, note: u8, velocity: u8, when: f64) {
+        assert_valid_midi_value("note", note);
+        assert_valid_midi_value("velocity", velocity);
+
+        if velocity == 0 {
+            self.note_off(note, when);
+            return;
+        }
+
+        let level = f32::from(velocity) / 127.;
+        let index = self.allocate(note);
+        let voice = &mut self.voices[index];
+
+        // fade out the note currently held by the voice before it is replaced
+        let start_time = match voice.note {
+            Some(current) if current != note => {
+                let amp = voice.amp.gain();
+                amp.cancel_and_hold_at_time(when);
+                amp.set_target_at_time(0., when, STEAL_FADE_DURATION / 5.);
+                when + STEAL_FADE_DURATION
+            }
+            _ => when,
+        };
+
+        voice
+            .oscillator
+            .frequency()
+            .set_value_at_time(midi_to_freq(f32::from(note), DEFAULT_A4), start_time);
+        self.envelope
+            .trigger_scaled(voice.amp.gain(), start_time, level);
+
+        voice.note = Some(note);
+        voice.age = self.clock;
+        self.clock += 1;
+    }
+
+    /// Release `note` at time `when`, a note that is not sounding is ignored
+    ///
+    /// # Panics
+    ///
+    /// Will panic if `note` is larger than 127, or if `when` is negative
+    pub fn note_off(&mut self, note: u8, when: f64) {
+        assert_valid_midi_value("note", note);
+
+        if let Some(voice) = self.voices.iter_mut().find(|v| v.note == Some(note)) {
+            self.envelope.release(voice.amp.gain(), when);
+
+            voice.note = None;
+            voice.age = self.clock;
+            self.clock += 1;
+        }
+    }
+
+    /// Pick the voice for `note`: the voice already holding this note, else the voice free for
+    /// the longest time, else the voice holding the oldest note.
+    fn allocate(&self, note: u8) -> usize {
+        if let Some(index) = self.voices.iter().position(|v| v.note == Some(note)) {
+            return index;
+        }
+
+        let oldest = |free: bool| {
+            self.voices
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| v.note.is_none() == free)
+                .min_by_key(|(_, v)| v.age)
+                .map(|(index, _)| index)
+        };
+
+        oldest(true).or_else(|| oldest(false)).unwrap()
+    }
+}
+
+#[track_caller]
+fn assert_valid_midi_value(name: &str, value: u8) {
+    assert!(
+        value <= 127,
+        "RangeError - MIDI {} ({:?}) should be in the range [0, 127]",
+        name,
+        value
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::OfflineAudioContext;
+
+    use super::*;
+
+    fn voice_manager(context: &OfflineAudioContext, voices: usize) -> VoiceManager {
+        let options = VoiceManagerOptions {
+            voices,
+            envelope: AdsrEnvelope {
+                attack: 0.01,
+                decay: 0.01,
+                sustain: 0.5,
+                release: 0.05,
+            },
+            ..VoiceManagerOptions::default()
+        };
+
+        VoiceManager::new(context, options)
+    }
+
+    #[test]
+    fn test_voice_stealing() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let mut synth = voice_manager(&context, 2);
+
+        synth.note_on(60, 100, 0.);
+        synth.note_on(64, 100, 0.);
+        assert_eq!(synth.notes(), vec![Some(60), Some(64)]);
+
+        // the oldest note is stolen
+        synth.note_on(67, 100, 0.1);
+        assert_eq!(synth.notes(), vec![Some(67), Some(64)]);
+        synth.note_on(72, 100, 0.2);
+        assert_eq!(synth.notes(), vec![Some(67), Some(72)]);
+
+        // the stolen notes are not sounding anymore
+        synth.note_off(60, 0.3);
+        synth.note_off(64, 0.3);
+        assert_eq!(synth.notes(), vec![Some(67), Some(72)]);
+    }
+
+    #[test]
+    fn test_free_voice_allocation() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let mut synth = voice_manager(&context, 3);
+
+        synth.note_on(60, 100, 0.);
+        synth.note_on(64, 100, 0.);
+        synth.note_on(67, 100, 0.);
+        synth.note_off(64, 0.1);
+        synth.note_off(60, 0.2);
+        assert_eq!(synth.notes(), vec![None, None, Some(67)]);
+
+        // the voice released first is reused first, free voices are preferred over stealing
+        synth.note_on(72, 100, 0.3);
+        assert_eq!(synth.notes(), vec![None, Some(72), Some(67)]);
+        synth.note_on(76, 100, 0.3);
+        assert_eq!(synth.notes(), vec![Some(76), Some(72), Some(67)]);
+    }
+
+    #[test]
+    fn test_retrigger_note() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let mut synth = voice_manager(&context, 2);
+
+        synth.note_on(60, 100, 0.);
+        synth.note_on(64, 100, 0.);
+        synth.note_on(60, 50, 0.1);
+        assert_eq!(synth.notes(), vec![Some(60), Some(64)]);
+
+        // the retriggered note is now the most recent one
+        synth.note_on(67, 100, 0.2);
+        assert_eq!(synth.notes(), vec![Some(60), Some(67)]);
+    }
+
+    #[test]
+    fn test_zero_velocity_is_note_off() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let mut synth = voice_manager(&context, 2);
+
+        synth.note_on(60, 100, 0.);
+        synth.note_on(60, 0, 0.1);
+        assert_eq!(synth.notes(), vec![None, None]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_note() {
+        let context = OfflineAudioContext::new(1, 128, 44_100.);
+        let mut synth = voice_manager(&context, 2);
+        synth.note_on(128, 100, 0.);
+    }
+
+    #[test]
+    fn test_render_voice_stealing() {
+        let sample_rate = 44_100.;
+        let context = OfflineAudioContext::new(1, 44_100 / 5, sample_rate);
+
+        let mut synth = voice_manager(&context, 1);
+        synth.output().connect(&context.destination());
+
+        synth.note_on(45, 127, 0.); // 110 Hz
+        synth.note_on(57, 127, 0.1); // 220 Hz, steals the voice
+
+        let output = context.start_rendering_sync();
+        let output = output.get_channel_data(0);
+
+        // the first note is sounding when it is stolen
+        let steal_frame = (0.1 * sample_rate) as usize;
+        let peak = output[steal_frame - 500..steal_frame]
+            .iter()
+            .fold(0_f32, |max, v| max.max(v.abs()));
+        assert!(peak > 0.4);
+
+        // and is faded out before the new note starts
+        let fade_end = ((0.1 + STEAL_FADE_DURATION) * sample_rate as f64) as usize;
+        assert!(output[fade_end - 10..fade_end]
+            .iter()
+            .all(|v| v.abs() < 0.01));
+
+        // no clicks: the sample differences stay close to the steepest slope of a unit sine
+        // at 220 Hz, whereas an abrupt change of level would cause a jump of up to 0.5
+        let max_step = 2. * 2. * std::f32::consts::PI * 220. / sample_rate;
+        output
+            .windows(2)
+            .for_each(|w| assert!((w[1] - w[0]).abs() < max_step));
+    }
+}
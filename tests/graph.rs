@@ -1,5 +1,8 @@
-use web_audio_api::context::{AudioContextRegistration, BaseAudioContext, OfflineAudioContext};
-use web_audio_api::node::{AudioNode, ChannelConfig};
+use web_audio_api::context::{
+    AudioContext, AudioContextOptions, AudioContextRegistration, AudioContextState,
+    BaseAudioContext, OfflineAudioContext,
+};
+use web_audio_api::node::{AudioNode, AudioScheduledSourceNode, ChannelConfig};
 use web_audio_api::render::{AudioParamValues, AudioProcessor, AudioRenderQuantum, RenderScope};
 use web_audio_api::RENDER_QUANTUM_SIZE;
 
@@ -123,6 +126,144 @@ impl AudioProcessor for DebugProcessor {
     }
 }
 
+/// Outputs ones until its processor panics at the given time
+struct FailingNode {
+    registration: AudioContextRegistration,
+    channel_config: ChannelConfig,
+}
+
+impl AudioNode for FailingNode {
+    fn registration(&self) -> &AudioContextRegistration {
+        &self.registration
+    }
+
+    fn channel_config(&self) -> &ChannelConfig {
+        &self.channel_config
+    }
+
+    fn number_of_inputs(&self) -> usize {
+        0
+    }
+
+    fn number_of_outputs(&self) -> usize {
+        1
+    }
+}
+
+impl FailingNode {
+    fn new<C: BaseAudioContext>(context: &C, fail_at: f64) -> Self {
+        context.register(move |registration| {
+            let node = FailingNode {
+                registration,
+                channel_config: ChannelConfig::default(),
+            };
+
+            (node, Box::new(FailingProcessor { fail_at }))
+        })
+    }
+}
+
+struct FailingProcessor {
+    fail_at: f64,
+}
+
+impl AudioProcessor for FailingProcessor {
+    fn process(
+        &mut self,
+        _inputs: &[AudioRenderQuantum],
+        outputs: &mut [AudioRenderQuantum],
+        _params: AudioParamValues,
+        scope: &RenderScope,
+    ) -> bool {
+        if scope.current_time >= self.fail_at {
+            panic!("degenerate state");
+        }
+
+        outputs[0].ensure_mono();
+        outputs[0]
+            .channel_data_mut(0)
+            .iter_mut()
+            .for_each(|o| *o = 1.);
+        true
+    }
+}
+
+#[test]
+fn test_processor_panic_mutes_node() {
+    let sample_rate = 44_100.;
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 3, sample_rate);
+
+    let failing = FailingNode::new(&context, RENDER_QUANTUM_SIZE as f64 / sample_rate as f64);
+    failing.connect(&context.destination());
+
+    let src = context.create_constant_source();
+    src.connect(&context.destination());
+    src.start();
+
+    // rendering completes, the failing node is silent after the first render quantum
+    let output = context.start_rendering_sync();
+    let output = output.get_channel_data(0);
+    assert!(output[..RENDER_QUANTUM_SIZE].iter().all(|&v| v == 2.));
+    assert!(output[RENDER_QUANTUM_SIZE..].iter().all(|&v| v == 1.));
+}
+
+#[test]
+fn test_processor_panic_reports_error() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        ..AudioContextOptions::default()
+    };
+    let context = AudioContext::new(options);
+
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    context.onerror(move |error| {
+        let _ = sender.send(error);
+    });
+
+    let failing = FailingNode::new(&context, 0.);
+    failing.connect(&context.destination());
+
+    let error = receiver
+        .recv_timeout(std::time::Duration::from_secs(1))
+        .unwrap();
+    assert_eq!(error.message(), "degenerate state");
+    assert!(error.current_time() >= 0.);
+
+    // reported once, the node is not run anymore
+    assert!(receiver
+        .recv_timeout(std::time::Duration::from_millis(50))
+        .is_err());
+    assert_eq!(context.state(), AudioContextState::Running);
+}
+
+#[test]
+fn test_processor_panic_reports_error_offline() {
+    let sample_rate = 44_100.;
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 3, sample_rate);
+
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    context.onerror(move |error| {
+        let _ = sender.send(error);
+    });
+
+    let failing = FailingNode::new(&context, RENDER_QUANTUM_SIZE as f64 / sample_rate as f64);
+    failing.connect(&context.destination());
+
+    let _ = context.start_rendering_sync();
+
+    let error = receiver
+        .recv_timeout(std::time::Duration::from_secs(1))
+        .unwrap();
+    assert_eq!(error.message(), "degenerate state");
+    assert_eq!(
+        error.current_time(),
+        RENDER_QUANTUM_SIZE as f64 / sample_rate as f64
+    );
+    assert!(receiver
+        .recv_timeout(std::time::Duration::from_millis(50))
+        .is_err());
+}
+
 fn pos(v: Label, list: &[Label]) -> usize {
     list.iter().position(|&x| x == v).unwrap()
 }
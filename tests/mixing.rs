@@ -104,3 +104,94 @@ fn test_stereo_to_discrete_mono() {
     assert_eq!(output.number_of_channels(), 1);
     assert_float_eq!(output.get_channel_data(0), ONES, abs_all <= 0.);
 }
+
+fn render_param_inputs(
+    intrinsic: Option<f32>,
+    connect: impl FnOnce(&OfflineAudioContext, &dyn AudioNode),
+) -> AudioBuffer {
+    let context = OfflineAudioContext::new(1, 128, 44_100.);
+    {
+        let carrier = context.create_constant_source();
+        carrier.offset().set_value(intrinsic.unwrap_or(0.));
+        carrier.start();
+
+        match intrinsic {
+            // the inputs modulate the offset of the carrier
+            Some(_) => {
+                connect(&context, carrier.offset());
+                carrier.connect(&context.destination());
+            }
+            // reference rendering, the inputs are routed to the destination
+            None => connect(&context, &context.destination()),
+        }
+    }
+
+    context.start_rendering_sync()
+}
+
+#[test]
+fn test_audio_param_sums_inputs() {
+    let connect = |context: &OfflineAudioContext, dest: &dyn AudioNode| {
+        let sine = context.create_oscillator();
+        sine.frequency().set_value(440.);
+        sine.connect(dest);
+        sine.start();
+
+        let square = context.create_oscillator();
+        square.set_type(web_audio_api::node::OscillatorType::Square);
+        square.frequency().set_value(1000.);
+        square.connect(dest);
+        square.start();
+    };
+
+    let reference = render_param_inputs(None, connect);
+    let output = render_param_inputs(Some(0.5), connect);
+
+    let expected: Vec<f32> = reference
+        .get_channel_data(0)
+        .iter()
+        .map(|v| v + 0.5)
+        .collect();
+    assert_float_eq!(output.get_channel_data(0), &expected[..], abs_all <= 1e-6);
+}
+
+#[test]
+fn test_audio_param_down_mixes_inputs() {
+    let output = render_param_inputs(Some(1.), |context, dest| {
+        let merger = context.create_channel_merger(2);
+
+        let left = context.create_constant_source();
+        left.offset().set_value(0.5);
+        left.connect_at(&merger, 0, 0);
+        left.start();
+
+        let right = context.create_constant_source();
+        right.offset().set_value(0.25);
+        right.connect_at(&merger, 0, 1);
+        right.start();
+
+        merger.connect(dest);
+    });
+
+    // speakers down-mix: 0.5 * (L + R)
+    assert_float_eq!(output.get_channel_data(0), &[1.375; 128][..], abs_all <= 0.);
+}
+
+#[test]
+fn test_audio_param_disconnect_single_input() {
+    let output = render_param_inputs(Some(1.), |context, dest| {
+        let first = context.create_constant_source();
+        first.offset().set_value(0.5);
+        first.connect(dest);
+        first.start();
+
+        let second = context.create_constant_source();
+        second.offset().set_value(0.25);
+        second.connect(dest);
+        second.start();
+
+        first.disconnect_from(dest);
+    });
+
+    assert_float_eq!(output.get_channel_data(0), &[1.25; 128][..], abs_all <= 0.);
+}
@@ -4,6 +4,8 @@ use web_audio_api::context::OfflineAudioContext;
 use web_audio_api::node::{
     AudioNode, AudioScheduledSourceNode, OscillatorNode, OscillatorOptions, OscillatorType,
 };
+use web_audio_api::util::{measure_aliasing, measure_thd};
+use web_audio_api::PeriodicWaveOptions;
 use web_audio_api::RENDER_QUANTUM_SIZE;
 
 #[test]
@@ -242,6 +244,206 @@ fn test_cycle_breaker() {
     );
 }
 
+#[test]
+fn test_try_connect_rejects_cycle() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 48000.);
+
+    {
+        let gain1 = context.create_gain();
+        gain1.connect(&context.destination());
+
+        let gain2 = context.create_gain();
+        gain1.try_connect(&gain2).unwrap();
+
+        // gain1 -> gain2 -> gain1 without delay is rejected, as is a self connection
+        assert!(gain2.try_connect(&gain1).is_err());
+        assert!(gain1.try_connect(&gain1).is_err());
+        // also when closed through an AudioParam
+        assert!(gain2.try_connect(gain1.gain()).is_err());
+
+        // the connections are still valid once the cycle is broken
+        gain1.disconnect_from(&gain2);
+        gain2.try_connect(&gain1).unwrap();
+
+        let source = context.create_constant_source();
+        source.connect(&gain2);
+        source.start();
+    }
+
+    // the rejected connections were not made, the graph is not muted
+    let output = context.start_rendering_sync();
+    assert_float_eq!(
+        output.get_channel_data(0),
+        &[1.; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+}
+
+#[test]
+fn test_try_connect_rejects_cycle_through_dropped_node() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 48000.);
+
+    let gain1 = context.create_gain();
+    let gain2 = context.create_gain();
+    gain2.connect(&context.destination());
+
+    {
+        let mid = context.create_gain();
+        gain1.connect(&mid);
+        mid.connect(&gain2);
+    }
+
+    // the dropped node is still part of the graph, gain1 -> mid -> gain2 -> gain1 is rejected
+    assert!(gain2.try_connect(&gain1).is_err());
+}
+
+#[test]
+fn test_try_connect_delay_feedback_loop() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE * 4, 48000.);
+
+    {
+        let delay = context.create_delay(1.);
+        delay.connect(&context.destination());
+
+        let feedback = context.create_gain();
+        feedback.gain().set_value(0.5);
+        delay.try_connect(&feedback).unwrap();
+        // the loop is closed through the delay, which is legal
+        feedback.try_connect(&delay).unwrap();
+
+        // single impulse
+        let mut buffer = context.create_buffer(1, 1, 48000.);
+        buffer.copy_to_channel(&[1.], 0);
+        let source = context.create_buffer_source();
+        source.set_buffer(buffer);
+        source.connect(&delay);
+        source.start();
+    }
+
+    // the delay in the loop is clamped to one render quantum
+    let output = context.start_rendering_sync();
+    let output = output.get_channel_data(0);
+
+    let mut expected = vec![0.; RENDER_QUANTUM_SIZE * 4];
+    expected[RENDER_QUANTUM_SIZE] = 1.;
+    expected[RENDER_QUANTUM_SIZE * 2] = 0.5;
+    expected[RENDER_QUANTUM_SIZE * 3] = 0.25;
+    assert_float_eq!(output, &expected[..], abs_all <= 0.);
+}
+
+#[test]
+fn test_render_until_silent_delay_tail() {
+    let sample_rate = 8000.;
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate);
+
+    {
+        let delay = context.create_delay(1.);
+        delay.delay_time().set_value(0.5);
+        delay.connect(&context.destination());
+
+        // single impulse, the source ends long before the delayed output
+        let mut buffer = context.create_buffer(1, 1, sample_rate);
+        buffer.copy_to_channel(&[1.], 0);
+        let source = context.create_buffer_source();
+        source.set_buffer(buffer);
+        source.connect(&delay);
+        source.start();
+    }
+
+    let output = context.render_until_silent(10 * 8000);
+    let output = output.get_channel_data(0);
+
+    // rendering continues past the length of the context, up to the delayed impulse
+    assert_eq!(output.len(), 4001);
+    assert_float_eq!(output[4000], 1., abs <= 0.);
+    assert_float_eq!(output[..4000], &[0.; 4000][..], abs_all <= 0.);
+}
+
+#[test]
+fn test_render_until_silent_feedback_tail() {
+    let sample_rate = 8000.;
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, sample_rate);
+
+    {
+        let delay = context.create_delay(1.);
+        delay.delay_time().set_value(0.125);
+        delay.connect(&context.destination());
+
+        let feedback = context.create_gain();
+        feedback.gain().set_value(0.5);
+        delay.connect(&feedback);
+        feedback.connect(&delay);
+
+        let mut buffer = context.create_buffer(1, 1, sample_rate);
+        buffer.copy_to_channel(&[1.], 0);
+        let source = context.create_buffer_source();
+        source.set_buffer(buffer);
+        source.connect(&delay);
+        source.start();
+    }
+
+    let output = context.render_until_silent(10 * 8000);
+    let output = output.get_channel_data(0);
+
+    // echoes every 1000 frames, halving each time: 0.5^19 is the last one above -120 dBFS
+    assert_eq!(output.len(), 20 * 1000 + 1);
+    (1..=20).for_each(|k| {
+        assert_float_eq!(output[k * 1000], 0.5_f32.powi(k as i32 - 1), abs <= 1e-7);
+    });
+}
+
+#[test]
+fn test_render_until_silent_max_frames() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 8000.);
+
+    // a running oscillator never stops by itself
+    let osc = context.create_oscillator();
+    osc.connect(&context.destination());
+    osc.start();
+
+    let output = context.render_until_silent(1000);
+    assert_eq!(output.length(), 1000);
+}
+
+#[test]
+fn test_render_until_silent_stopped_source() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 8000.);
+
+    let src = context.create_constant_source();
+    src.connect(&context.destination());
+    src.start_at(0.);
+    src.stop_at(0.1);
+
+    let output = context.render_until_silent(8000);
+    let output = output.get_channel_data(0);
+    assert_eq!(output.len(), 800);
+    assert_float_eq!(output, &[1.; 800][..], abs_all <= 0.);
+}
+
+#[test]
+fn test_render_until_silent_ignores_length() {
+    // the context length does not extend the rendering
+    let context = OfflineAudioContext::new(1, 4000, 8000.);
+
+    let src = context.create_constant_source();
+    src.connect(&context.destination());
+    src.start_at(0.);
+    src.stop_at(0.1);
+
+    // shorter than the length
+    let output = context.render_until_silent(8000);
+    assert_eq!(output.length(), 800);
+}
+
+#[test]
+fn test_render_until_silent_empty_graph() {
+    let context = OfflineAudioContext::new(2, RENDER_QUANTUM_SIZE, 8000.);
+    let output = context.render_until_silent(8000);
+
+    assert_eq!(output.number_of_channels(), 2);
+    assert_eq!(output.length(), 0);
+}
+
 #[test]
 fn test_spatial() {
     // setup stereo
@@ -287,3 +489,75 @@ fn test_spatial() {
         abs_all <= 0.001
     );
 }
+
+#[test]
+fn test_degenerate_periodic_wave() {
+    let context = OfflineAudioContext::new(1, RENDER_QUANTUM_SIZE, 44_100.);
+
+    // all coefficients zero: nothing to normalize, renders silence without failing
+    let options = PeriodicWaveOptions {
+        real: Some(vec![0.; 4]),
+        imag: Some(vec![0.; 4]),
+        disable_normalization: false,
+    };
+    let osc = context.create_oscillator();
+    osc.set_periodic_wave(context.create_periodic_wave(options));
+    osc.connect(&context.destination());
+    osc.start();
+
+    let output = context.start_rendering_sync();
+    assert_float_eq!(
+        output.get_channel_data(0),
+        &[0.; RENDER_QUANTUM_SIZE][..],
+        abs_all <= 0.
+    );
+}
+
+#[test]
+fn test_sawtooth_quality() {
+    let sample_rate = 44_100.;
+    let frequency = 1234.5;
+    let length = 8192;
+
+    let context = OfflineAudioContext::new(1, length, sample_rate);
+    let osc = OscillatorNode::new(
+        &context,
+        OscillatorOptions {
+            type_: OscillatorType::Sawtooth,
+            frequency,
+            ..OscillatorOptions::default()
+        },
+    );
+    osc.connect(&context.destination());
+    osc.start();
+    let band_limited = context.start_rendering_sync().get_channel_data(0).to_vec();
+
+    // naive sawtooth, with the same phase
+    let naive: Vec<f32> = (0..length)
+        .map(|i| {
+            let phase = (i as f64 * frequency as f64 / sample_rate as f64 + 0.5).fract();
+            (2. * phase - 1.) as f32
+        })
+        .collect();
+
+    // harmonics below the Nyquist frequency of an ideal sawtooth decay as 1 / k
+    let harmonics = (sample_rate / 2. / frequency) as usize;
+    let ideal_thd = (2..=harmonics)
+        .map(|k| 1. / (k * k) as f32)
+        .sum::<f32>()
+        .sqrt();
+
+    let thd_band_limited = measure_thd(&band_limited, frequency, sample_rate);
+    let thd_naive = measure_thd(&naive, frequency, sample_rate);
+    let aliasing_band_limited = measure_aliasing(&band_limited, frequency, sample_rate);
+    let aliasing_naive = measure_aliasing(&naive, frequency, sample_rate);
+
+    // both have the harmonic content of a sawtooth, the polyBLEP correction slightly attenuates
+    // the highest harmonics
+    assert_float_eq!(thd_naive, ideal_thd, rmax <= 0.01);
+    assert_float_eq!(thd_band_limited, ideal_thd, rmax <= 0.1);
+    assert!(thd_band_limited < thd_naive);
+
+    // but the naive one has much more aliasing, more than 12dB here
+    assert!(aliasing_band_limited < aliasing_naive / 4.);
+}
@@ -6,8 +6,11 @@
 use web_audio_api::context::{
     AudioContext, AudioContextOptions, AudioContextState, BaseAudioContext,
 };
+use web_audio_api::node::{AudioNode, AudioScheduledSourceNode};
+use web_audio_api::RENDER_QUANTUM_SIZE;
 
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 fn require_send_sync_static<T: Send + Sync + 'static>(_: T) {}
 
@@ -47,3 +50,152 @@ fn test_none_sink_id() {
 
     assert!(sink_stable.load(Ordering::SeqCst));
 }
+
+#[test]
+fn test_destination_max_channel_count() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        ..AudioContextOptions::default()
+    };
+    let context = AudioContext::new(options);
+
+    let max_channel_count = context.destination().max_channel_count();
+    assert!(max_channel_count > 0);
+    assert_eq!(context.destination().channel_count(), max_channel_count);
+
+    // any count up to the max is allowed, and is shared by all destination handles
+    context.destination().set_channel_count(1);
+    assert_eq!(context.destination().channel_count(), 1);
+    context.destination().set_channel_count(max_channel_count);
+    assert_eq!(context.destination().channel_count(), max_channel_count);
+
+    // requesting more than the max is rejected
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        context
+            .destination()
+            .set_channel_count(max_channel_count + 1)
+    }));
+    assert!(result.is_err());
+    assert_eq!(context.destination().channel_count(), max_channel_count);
+}
+
+#[test]
+fn test_onstatechange() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        ..AudioContextOptions::default()
+    };
+    let context = AudioContext::new(options);
+
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    context.onstatechange(move |state| {
+        let _ = sender.send((state, std::thread::current().id()));
+    });
+
+    context.suspend_sync();
+    context.suspend_sync(); // no duplicate event
+    context.resume_sync();
+    context.close_sync();
+
+    let timeout = std::time::Duration::from_secs(1);
+    let mut states = vec![];
+    for _ in 0..3 {
+        let (state, thread_id) = receiver.recv_timeout(timeout).unwrap();
+        // callback runs on the event thread
+        assert_ne!(thread_id, std::thread::current().id());
+        states.push(state);
+    }
+
+    assert_eq!(
+        states,
+        vec![
+            AudioContextState::Suspended,
+            AudioContextState::Running,
+            AudioContextState::Closed
+        ]
+    );
+    assert!(receiver
+        .recv_timeout(std::time::Duration::from_millis(50))
+        .is_err());
+}
+
+#[test]
+fn test_render_pool_capacity() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        render_pool_capacity: Some(2),
+        ..AudioContextOptions::default()
+    };
+    let context = AudioContext::new(options);
+    assert_eq!(context.render_pool_metrics().capacity(), 2);
+
+    // a graph requiring many more buffers than available in the pool
+    let gain = context.create_gain();
+    gain.connect(&context.destination());
+    let oscillators: Vec<_> = (0..32)
+        .map(|_| {
+            let osc = context.create_oscillator();
+            osc.connect(&gain);
+            osc.start();
+            osc
+        })
+        .collect();
+
+    // The pool is exhausted, so buffers are allocated on demand. In the steady state the
+    // extended pool is reused and the reuse rate increases. Poll the metrics rather than
+    // relying on the render thread timing.
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let metrics = context.render_pool_metrics();
+        if metrics.allocations() > 0 && metrics.reuse_rate() > 0.5 {
+            break;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "unexpected metrics {:?}",
+            metrics
+        );
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    drop(oscillators);
+    context.close_sync();
+}
+
+#[test]
+fn test_current_frame() {
+    let options = AudioContextOptions {
+        sink_id: "none".into(),
+        ..AudioContextOptions::default()
+    };
+    let context = AudioContext::new(options);
+    let sample_rate = context.sample_rate() as f64;
+
+    // time advances monotonically, in whole render quanta
+    let mut previous = context.current_frame();
+    let start = std::time::Instant::now();
+    while start.elapsed() < std::time::Duration::from_millis(100) {
+        let frame = context.current_frame();
+        assert!(frame >= previous);
+        assert_eq!(frame % RENDER_QUANTUM_SIZE as u64, 0);
+        previous = frame;
+    }
+    assert!(previous > 0);
+
+    // current_time is derived from the frame counter
+    context.suspend_sync();
+    let frame = context.current_frame();
+    assert_eq!(context.current_time(), frame as f64 / sample_rate);
+
+    // suspending freezes the frame counter
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    assert_eq!(context.current_frame(), frame);
+    assert_eq!(context.current_time(), frame as f64 / sample_rate);
+
+    // resuming continues from the same frame
+    context.resume_sync();
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    let resumed = context.current_frame();
+    assert!(resumed > frame);
+    assert_eq!(resumed % RENDER_QUANTUM_SIZE as u64, 0);
+}